@@ -0,0 +1,1952 @@
+//! Exception queries: the inbox's group listing and pages, a group's detail
+//! and breakdowns, release summaries and crash-free sessions, regressions, and
+//! the activity snoozes and similar-group suggestions are checked against.
+//! Sampled occurrences count for every occurrence they stand for (see
+//! [`occurrence_weight`]).
+
+use std::collections::{HashMap, HashSet};
+
+use analytics_api::{
+    CountRow, CrashFreeSessions, ExceptionBreakdowns, ExceptionGroup, ExceptionGroupDetail,
+    ExceptionSort, ExceptionStatus, ExceptionVariant, Release, ReleaseIssue, ReleaseSummary,
+    SimilarGroup, TREND_BUCKETS, source_label, summary_line,
+};
+use chrono::Utc;
+use polars::prelude::*;
+
+use super::filter::CompiledFilter;
+use super::{
+    ADVICE, BREAKDOWN_LIMIT, combined, count_by, scalar_i64, source_filter, traces_of_occurrences,
+    trend_bucket,
+};
+use crate::errors::{Result, ResultExt};
+use crate::store::Store;
+
+/// How many of the groups a release introduced its summary lists.
+const NEW_ISSUE_LIMIT: usize = 50;
+
+/// The environments exceptions reported over `[from, to)`, with their
+/// occurrences, most frequent first — the choices of the inbox's environment
+/// selector.
+pub fn exception_environments(
+    store: &Store,
+    parquet_dir: &str,
+    from_ms: i64,
+    to_ms: i64,
+) -> Result<Vec<CountRow>> {
+    let df = combined(store, parquet_dir, from_ms, to_ms)?
+        .filter(
+            col("kind")
+                .eq(lit("exception"))
+                .and(col("environment").is_not_null()),
+        )
+        .select([col("environment"), occurrence_weight().alias("occurrences")])
+        .collect()
+        .or_system_err(ADVICE)?;
+    count_by(&df, "environment")
+}
+
+/// One page of an exception group listing: the `limit` groups after `after`,
+/// a `(sort value, source, group)` position, in `sort` order.
+pub struct GroupPage<'a> {
+    pub sort: ExceptionSort,
+    pub after: Option<(i64, &'a str, &'a str)>,
+    pub limit: usize,
+}
+
+/// The listing column a group order sorts by.
+fn sort_column(sort: ExceptionSort) -> &'static str {
+    match sort {
+        ExceptionSort::LastSeen => "last_seen",
+        ExceptionSort::FirstSeen => "first_seen",
+        ExceptionSort::Count => "count",
+        ExceptionSort::Sessions => "sessions",
+    }
+}
+
+/// The exception occurrences in `[from_ms, to_ms)` matching `filter`,
+/// narrowed to the `(source, group)` pairs in `keys` when given.
+fn exception_rows(
+    store: &Store,
+    parquet_dir: &str,
+    from_ms: i64,
+    to_ms: i64,
+    filter: Option<&CompiledFilter>,
+    keys: Option<&[(String, String)]>,
+) -> Result<LazyFrame> {
+    let mut lf = combined(store, parquet_dir, from_ms, to_ms)?
+        .filter(col("kind").eq(lit("exception")))
+        .filter(col("exc_group").is_not_null());
+    if let Some(filter) = filter {
+        lf = lf.filter(filter.predicate.clone());
+    }
+    if let Some(keys) = keys {
+        // The pairs are distinct, so the inner join only drops rows.
+        let keys = df![
+            "source" => keys.iter().map(|(source, _)| source.as_str()).collect::<Vec<_>>(),
+            "exc_group" => keys.iter().map(|(_, group)| group.as_str()).collect::<Vec<_>>(),
+        ]
+        .or_system_err(ADVICE)?;
+        lf = lf.join(
+            keys.lazy(),
+            [col("source"), col("exc_group")],
+            [col("source"), col("exc_group")],
+            JoinArgs::new(JoinType::Inner),
+        );
+    }
+    Ok(lf)
+}
+
+/// The `(source, group, last seen)` of every exception group matching the
+/// compiled filter, among `keys` when given: what an inbox needs to count and
+/// narrow the groups by their triage before loading a page of them.
+pub fn exception_group_keys(
+    store: &Store,
+    parquet_dir: &str,
+    from_ms: i64,
+    to_ms: i64,
+    filter: Option<&CompiledFilter>,
+    keys: Option<&[(String, String)]>,
+) -> Result<Vec<(String, String, i64)>> {
+    let df = exception_rows(store, parquet_dir, from_ms, to_ms, filter, keys)?
+        .group_by([col("source"), col("exc_group")])
+        .agg([col("received_ms")
+            .max()
+            .cast(DataType::Int64)
+            .alias("last_seen")])
+        .collect()
+        .or_system_err(ADVICE)?;
+    let source = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let group = df
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let last = df
+        .column("last_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    Ok((0..df.height())
+        .filter_map(|i| {
+            Some((
+                source.get(i)?.to_string(),
+                group.get(i)?.to_string(),
+                last.get(i).unwrap_or(0),
+            ))
+        })
+        .collect())
+}
+
+/// Exception groups matching the compiled filter, grouped by
+/// `(fingerprint, source)` with a [`TREND_BUCKETS`]-bucket occurrence trend
+/// each. A fingerprint is computed from the error alone, so the same
+/// `exc_group` legitimately occurs on multiple sources/projects; keeping the
+/// source in the key keeps those occurrences separate. The caller folds
+/// per-source rows up to per-project rows (summing trends element-wise) for
+/// the global Exceptions inbox. `keys`, when given, narrows the rows to those
+/// `(source, group)` pairs (a search's matches, or the groups in one status).
+///
+/// Without a `page` every group is returned, the most recently seen first.
+/// With one, the groups are ordered by its sort value, descending, then by
+/// `(source, group)`, and only those after its position are read back.
+pub fn exception_groups_by_source(
+    store: &Store,
+    parquet_dir: &str,
+    from_ms: i64,
+    to_ms: i64,
+    filter: Option<&CompiledFilter>,
+    keys: Option<&[(String, String)]>,
+    page: Option<&GroupPage<'_>>,
+) -> Result<Vec<(ExceptionGroup, String)>> {
+    // Oldest first, so `first()` is each group's earliest occurrence.
+    let mut grouped = exception_rows(store, parquet_dir, from_ms, to_ms, filter, keys)?
+        .sort(["received_ms"], SortMultipleOptions::default())
+        .group_by([col("exc_group"), col("source")])
+        .agg([
+            occurrence_weight().sum().alias("count"),
+            col("sid")
+                .drop_nulls()
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias("sessions"),
+            col("bid")
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias("page_views"),
+            col("received_ms")
+                .min()
+                .cast(DataType::Int64)
+                .alias("first_seen"),
+            col("received_ms")
+                .max()
+                .cast(DataType::Int64)
+                .alias("last_seen"),
+            col("exc_type").first().alias("exc_type"),
+            col("exc_message").first().alias("sample_message"),
+            col("app_version").first().alias("first_release"),
+            col("received_ms").alias("times"),
+            occurrence_weight().alias("weights"),
+        ]);
+    grouped = match page {
+        None => grouped.sort(
+            ["last_seen"],
+            SortMultipleOptions::default().with_order_descending(true),
+        ),
+        Some(page) => {
+            let column = sort_column(page.sort);
+            if let Some((value, source, group)) = page.after {
+                let later = col("source").gt(lit(source)).or(col("source")
+                    .eq(lit(source))
+                    .and(col("exc_group").gt(lit(group))));
+                grouped = grouped.filter(
+                    col(column)
+                        .lt(lit(value))
+                        .or(col(column).eq(lit(value)).and(later)),
+                );
+            }
+            grouped
+                .sort(
+                    [column, "source", "exc_group"],
+                    SortMultipleOptions::default()
+                        .with_order_descending_multi([true, false, false]),
+                )
+                .limit(IdxSize::try_from(page.limit).unwrap_or(IdxSize::MAX))
+        }
+    };
+    let df = grouped.collect().or_system_err(ADVICE)?;
+
+    let group_id = df
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let count = df
+        .column("count")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let sessions = df
+        .column("sessions")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let page_views = df
+        .column("page_views")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let first = df
+        .column("first_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let last = df
+        .column("last_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let exc_type = df
+        .column("exc_type")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let message = df
+        .column("sample_message")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let first_release = df
+        .column("first_release")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let source = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let times = df
+        .column("times")
+        .or_system_err(ADVICE)?
+        .list()
+        .or_system_err(ADVICE)?;
+    let weights = df
+        .column("weights")
+        .or_system_err(ADVICE)?
+        .list()
+        .or_system_err(ADVICE)?;
+
+    Ok((0..df.height())
+        .filter_map(|i| {
+            group_id.get(i).map(|gid| {
+                (
+                    ExceptionGroup {
+                        group_id: gid.to_string(),
+                        exc_type: exc_type.get(i).unwrap_or("").to_string(),
+                        sample_message: summary_line(message.get(i).unwrap_or("")).to_string(),
+                        count: count.get(i).unwrap_or(0),
+                        sessions: sessions.get(i).unwrap_or(0),
+                        page_views: page_views.get(i).unwrap_or(0),
+                        first_seen_ms: first.get(i).unwrap_or(0),
+                        last_seen_ms: last.get(i).unwrap_or(0),
+                        status: ExceptionStatus::Unresolved,
+                        resolved: false,
+                        muted: false,
+                        snooze: None,
+                        assignee: None,
+                        comments: 0,
+                        first_release: first_release.get(i).map(str::to_string),
+                        regressed_in: None,
+                        trend: weighted_trend_of(
+                            list_i64(times, i).into_iter().zip(list_i64(weights, i)),
+                            from_ms,
+                            to_ms,
+                        ),
+                    },
+                    source.get(i).unwrap_or("").to_string(),
+                )
+            })
+        })
+        .collect())
+}
+
+/// The most recent occurrence of each of `groups` on `source`, across all
+/// time. Groups without occurrences are absent.
+pub fn exception_last_seen(
+    store: &Store,
+    parquet_dir: &str,
+    source: &str,
+    groups: &[String],
+) -> Result<HashMap<String, i64>> {
+    let mut wanted = lit(false);
+    for group in groups {
+        wanted = wanted.or(col("exc_group").eq(lit(group.clone())));
+    }
+    let to = Utc::now().timestamp_millis() + 1;
+    let df = combined(store, parquet_dir, 0, to)?
+        .filter(col("kind").eq(lit("exception")))
+        .filter(col("source").eq(lit(source.to_string())))
+        .filter(wanted)
+        .group_by([col("exc_group")])
+        .agg([col("received_ms")
+            .max()
+            .cast(DataType::Int64)
+            .alias("last_seen")])
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let group = df
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let last = df
+        .column("last_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    Ok((0..df.height())
+        .filter_map(|i| Some((group.get(i)?.to_string(), last.get(i)?)))
+        .collect())
+}
+
+/// How many occurrences each of `groups`, a `(source, group)` with the instant
+/// to count from, had after that instant and before `to_ms`, and in how many
+/// sessions, every group's in one query. Groups without any are absent.
+pub fn exception_activity_since(
+    store: &Store,
+    parquet_dir: &str,
+    groups: &HashMap<(String, String), i64>,
+    to_ms: i64,
+) -> Result<HashMap<(String, String), (i64, i64)>> {
+    let Some(from_ms) = groups.values().map(|since| since + 1).min() else {
+        return Ok(HashMap::new());
+    };
+    let since = df![
+        "source" => groups.keys().map(|(source, _)| source.as_str()).collect::<Vec<_>>(),
+        "exc_group" => groups.keys().map(|(_, group)| group.as_str()).collect::<Vec<_>>(),
+        "since" => groups.values().copied().collect::<Vec<_>>(),
+    ]
+    .or_system_err(ADVICE)?;
+    let df = combined(store, parquet_dir, from_ms, to_ms)?
+        .filter(col("kind").eq(lit("exception")))
+        .join(
+            since.lazy(),
+            [col("source"), col("exc_group")],
+            [col("source"), col("exc_group")],
+            JoinArgs::new(JoinType::Inner),
+        )
+        .filter(col("received_ms").gt(col("since")))
+        .group_by([col("source"), col("exc_group")])
+        .agg([
+            occurrence_weight().sum().alias("count"),
+            col("sid")
+                .drop_nulls()
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias("sessions"),
+        ])
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let source = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let group = df
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let count = df
+        .column("count")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let sessions = df
+        .column("sessions")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    Ok((0..df.height())
+        .filter_map(|i| {
+            let key = (source.get(i)?.to_string(), group.get(i)?.to_string());
+            Some((
+                key,
+                (count.get(i).unwrap_or(0), sessions.get(i).unwrap_or(0)),
+            ))
+        })
+        .collect())
+}
+
+/// Fill in how often each of `similar` (groups of `source`, best match first)
+/// occurred over `[from, to)` and when it was last seen, dropping those not
+/// seen then, and keep the first `limit`.
+pub fn similar_group_activity(
+    store: &Store,
+    parquet_dir: &str,
+    source: &str,
+    similar: Vec<SimilarGroup>,
+    from_ms: i64,
+    to_ms: i64,
+    limit: usize,
+) -> Result<Vec<SimilarGroup>> {
+    if similar.is_empty() {
+        return Ok(similar);
+    }
+    let groups: Vec<String> = similar.iter().map(|g| g.group_id.clone()).collect();
+    let df = combined(store, parquet_dir, from_ms, to_ms)?
+        .filter(col("kind").eq(lit("exception")))
+        .filter(col("source").eq(lit(source.to_string())))
+        .filter(col("exc_group").is_in(
+            lit(Series::new("groups".into(), groups)).implode(false),
+            false,
+        ))
+        .group_by([col("exc_group")])
+        .agg([
+            occurrence_weight().sum().alias("count"),
+            col("received_ms")
+                .max()
+                .cast(DataType::Int64)
+                .alias("last_seen"),
+        ])
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let group_id = df
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let count = df
+        .column("count")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let last = df
+        .column("last_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let activity: HashMap<&str, (i64, i64)> = (0..df.height())
+        .filter_map(|i| {
+            Some((
+                group_id.get(i)?,
+                (count.get(i).unwrap_or(0), last.get(i).unwrap_or(0)),
+            ))
+        })
+        .collect();
+    let mut seen: Vec<SimilarGroup> = similar
+        .into_iter()
+        .filter_map(|group| {
+            let (count, last_seen_ms) = *activity.get(group.group_id.as_str())?;
+            Some(SimilarGroup {
+                count,
+                last_seen_ms,
+                ..group
+            })
+        })
+        .collect();
+    seen.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.last_seen_ms.cmp(&a.last_seen_ms))
+    });
+    seen.truncate(limit);
+    Ok(seen)
+}
+
+/// The release each of `resolved` — `(source, group)` pairs resolved at the
+/// given instant and seen again since — regressed in, for those that recurred
+/// on a newer release than they were last seen on (see [`regressed_in`]).
+/// Versions are ordered by their releases' deploy times on the group's source.
+/// Every source is covered by one query.
+pub fn exception_regressions(
+    store: &Store,
+    parquet_dir: &str,
+    resolved: &HashMap<(String, String), i64>,
+) -> Result<HashMap<(String, String), String>> {
+    if resolved.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let sources: HashSet<&str> = resolved.keys().map(|(source, _)| source.as_str()).collect();
+    let releases: Vec<Release> = store
+        .list_releases()?
+        .into_iter()
+        .filter(|r| sources.contains(r.source.as_str()))
+        .collect();
+    let deployed: HashMap<&str, HashMap<&str, i64>> =
+        releases.iter().fold(HashMap::new(), |mut deployed, r| {
+            deployed
+                .entry(r.source.as_str())
+                .or_default()
+                .insert(r.version.as_str(), r.deployed_at.timestamp_millis());
+            deployed
+        });
+
+    let groups: HashSet<&str> = resolved.keys().map(|(_, group)| group.as_str()).collect();
+    let sources = Series::new("sources".into(), sources.into_iter().collect::<Vec<_>>());
+    let groups = Series::new("groups".into(), groups.into_iter().collect::<Vec<_>>());
+    let to = Utc::now().timestamp_millis() + 1;
+    let df = combined(store, parquet_dir, 0, to)?
+        .filter(col("kind").eq(lit("exception")))
+        .filter(col("app_version").is_not_null())
+        .filter(col("source").is_in(lit(sources).implode(false), false))
+        .filter(col("exc_group").is_in(lit(groups).implode(false), false))
+        .select([
+            col("source"),
+            col("exc_group"),
+            col("received_ms")
+                .cast(DataType::Int64)
+                .alias("received_ms"),
+            col("app_version"),
+        ])
+        .sort(["received_ms"], SortMultipleOptions::default())
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let source = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let group = df
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let received = df
+        .column("received_ms")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let version = df
+        .column("app_version")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let mut occurrences: HashMap<(&str, &str), Vec<(i64, &str)>> = HashMap::new();
+    for i in 0..df.height() {
+        if let (Some(source), Some(group), Some(at), Some(version)) =
+            (source.get(i), group.get(i), received.get(i), version.get(i))
+        {
+            occurrences
+                .entry((source, group))
+                .or_default()
+                .push((at, version));
+        }
+    }
+
+    let none = HashMap::new();
+    Ok(occurrences
+        .into_iter()
+        .filter_map(|((source, group), occurrences)| {
+            let key = (source.to_string(), group.to_string());
+            let resolved_ms = *resolved.get(&key)?;
+            let deployed = deployed.get(source).unwrap_or(&none);
+            let release = regressed_in(&occurrences, resolved_ms, deployed)?;
+            Some((key, release))
+        })
+        .collect())
+}
+
+/// The release a group resolved at `resolved_ms` regressed in: the first
+/// version reported after the resolution whose release was deployed later than
+/// that of the last version reported before it — or, when there is none, later
+/// than the resolution itself. A group recurring on the release it was
+/// resolved in (the fix didn't ship yet) hasn't regressed. `occurrences` are
+/// `(received_ms, version)`, oldest first.
+fn regressed_in(
+    occurrences: &[(i64, &str)],
+    resolved_ms: i64,
+    deployed: &HashMap<&str, i64>,
+) -> Option<String> {
+    let baseline = occurrences
+        .iter()
+        .rev()
+        .find(|(at, _)| *at <= resolved_ms)
+        .and_then(|(_, version)| deployed.get(version).copied())
+        .unwrap_or(resolved_ms);
+    occurrences
+        .iter()
+        .filter(|(at, _)| *at > resolved_ms)
+        .find(|(_, version)| deployed.get(version).is_some_and(|at| *at > baseline))
+        .map(|(_, version)| version.to_string())
+}
+
+/// Every release of `source`, newest deploy first, with the exception
+/// occurrences and distinct groups it reported, its crash-free sessions, and
+/// the groups whose earliest occurrence it reported, across all time.
+pub fn release_summaries(
+    store: &Store,
+    parquet_dir: &str,
+    source: &str,
+) -> Result<Vec<ReleaseSummary>> {
+    let mut releases: Vec<Release> = store
+        .list_releases()?
+        .into_iter()
+        .filter(|r| r.source == source)
+        .collect();
+    if releases.is_empty() {
+        return Ok(Vec::new());
+    }
+    releases.sort_by_key(|r| std::cmp::Reverse(r.deployed_at));
+
+    let to = Utc::now().timestamp_millis() + 1;
+    let base =
+        combined(store, parquet_dir, 0, to)?.filter(col("source").eq(lit(source.to_string())));
+    let occurrences = base
+        .clone()
+        .filter(col("kind").eq(lit("exception")))
+        .filter(col("exc_group").is_not_null())
+        .collect()
+        .or_system_err(ADVICE)?;
+    let crash_free: HashMap<String, (i64, i64)> = crash_free_rows(
+        session_outcomes(base)
+            .filter(col("app_version").is_not_null())
+            .group_by([col("source"), col("app_version")]),
+    )?
+    .into_iter()
+    .filter_map(|row| Some((row.app_version?, (row.sessions, row.crashed_sessions))))
+    .collect();
+
+    let per_release = occurrences
+        .clone()
+        .lazy()
+        .filter(col("app_version").is_not_null())
+        .group_by([col("app_version")])
+        .agg([
+            occurrence_weight().sum().alias("exceptions"),
+            col("exc_group")
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias("groups"),
+        ])
+        .collect()
+        .or_system_err(ADVICE)?;
+    let version = per_release
+        .column("app_version")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let exceptions = per_release
+        .column("exceptions")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let groups = per_release
+        .column("groups")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let mut counts: HashMap<String, (i64, i64)> = HashMap::new();
+    for i in 0..per_release.height() {
+        if let Some(version) = version.get(i) {
+            counts.insert(
+                version.to_string(),
+                (exceptions.get(i).unwrap_or(0), groups.get(i).unwrap_or(0)),
+            );
+        }
+    }
+
+    // Oldest first, so `first()` is each group's earliest occurrence.
+    let per_group = occurrences
+        .lazy()
+        .sort(["received_ms"], SortMultipleOptions::default())
+        .group_by([col("exc_group")])
+        .agg([
+            occurrence_weight().sum().alias("count"),
+            col("received_ms")
+                .first()
+                .cast(DataType::Int64)
+                .alias("first_seen"),
+            col("exc_type").first().alias("exc_type"),
+            col("exc_message").first().alias("sample_message"),
+            col("app_version").first().alias("first_release"),
+        ])
+        .filter(col("first_release").is_not_null())
+        .sort(
+            ["count"],
+            SortMultipleOptions::default().with_order_descending(true),
+        )
+        .collect()
+        .or_system_err(ADVICE)?;
+    let group_id = per_group
+        .column("exc_group")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let count = per_group
+        .column("count")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let first_seen = per_group
+        .column("first_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let exc_type = per_group
+        .column("exc_type")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let message = per_group
+        .column("sample_message")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let first_release = per_group
+        .column("first_release")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let mut introduced: HashMap<&str, Vec<ReleaseIssue>> = HashMap::new();
+    for i in 0..per_group.height() {
+        let (Some(group_id), Some(release)) = (group_id.get(i), first_release.get(i)) else {
+            continue;
+        };
+        let issues = introduced.entry(release).or_default();
+        if issues.len() < NEW_ISSUE_LIMIT {
+            issues.push(ReleaseIssue {
+                group_id: group_id.to_string(),
+                exc_type: exc_type.get(i).unwrap_or("").to_string(),
+                sample_message: summary_line(message.get(i).unwrap_or("")).to_string(),
+                count: count.get(i).unwrap_or(0),
+                first_seen_ms: first_seen.get(i).unwrap_or(0),
+            });
+        }
+    }
+
+    Ok(releases
+        .into_iter()
+        .map(|release| {
+            let (exceptions, groups) = counts.get(&release.version).copied().unwrap_or((0, 0));
+            let (sessions, crashed_sessions) =
+                crash_free.get(&release.version).copied().unwrap_or((0, 0));
+            let new_issues = introduced
+                .remove(release.version.as_str())
+                .unwrap_or_default();
+            ReleaseSummary {
+                release,
+                exceptions,
+                groups,
+                sessions,
+                crashed_sessions,
+                crash_free_rate: crash_free_rate(sessions, crashed_sessions),
+                new_issues,
+            }
+        })
+        .collect())
+}
+
+/// Crash-free sessions of each of `sources` within `[from_ms, to_ms)`: one
+/// row per source across every release, then one per release it reported,
+/// each source's releases most sessions first. A session counts once its
+/// page loads are in range and crashed once it also reported an unhandled
+/// exception; it belongs to the release its first versioned page load
+/// reported.
+pub fn crash_free_sessions(
+    store: &Store,
+    parquet_dir: &str,
+    sources: &[String],
+    from_ms: i64,
+    to_ms: i64,
+) -> Result<Vec<CrashFreeSessions>> {
+    let outcomes = session_outcomes(
+        combined(store, parquet_dir, from_ms, to_ms)?.filter(source_filter(sources)),
+    );
+    let totals = crash_free_rows(outcomes.clone().group_by([col("source")]))?;
+    let mut per_version = crash_free_rows(
+        outcomes
+            .with_column(col("app_version").fill_null(lit("")))
+            .group_by([col("source"), col("app_version")]),
+    )?;
+    per_version.sort_by(|a, b| b.sessions.cmp(&a.sessions));
+
+    let mut rows = Vec::with_capacity(totals.len() + per_version.len());
+    for total in totals {
+        let source = total.source.clone();
+        rows.push(total);
+        rows.extend(
+            per_version
+                .iter()
+                .filter(|row| row.source == source)
+                .cloned(),
+        );
+    }
+    Ok(rows)
+}
+
+/// One row per session with page loads: its `source`, the release its first
+/// versioned page load reported (`app_version`, null when none did), and
+/// whether it reported an unhandled exception (`crashed`).
+fn session_outcomes(base: LazyFrame) -> LazyFrame {
+    let unhandled = col("kind")
+        .eq(lit("exception"))
+        .and(col("exc_handled").eq(lit(false)));
+    base.filter(col("sid").is_not_null())
+        .filter(col("kind").eq(lit("page_load")).or(unhandled.clone()))
+        // Oldest first, so `first()` is the session's earliest release.
+        .sort(["received_ms"], SortMultipleOptions::default())
+        .group_by([col("source"), col("sid")])
+        .agg([
+            col("kind")
+                .eq(lit("page_load"))
+                .sum()
+                .cast(DataType::Int64)
+                .alias("page_loads"),
+            col("app_version")
+                .filter(col("kind").eq(lit("page_load")))
+                .drop_nulls()
+                .first()
+                .alias("app_version"),
+            unhandled.sum().gt(lit(0)).alias("crashed"),
+        ])
+        .filter(col("page_loads").gt(lit(0)))
+}
+
+/// Collects [`session_outcomes`] grouped by `source` (and optionally
+/// `app_version`) into crash-free rows.
+fn crash_free_rows(grouped: LazyGroupBy) -> Result<Vec<CrashFreeSessions>> {
+    let df = grouped
+        .agg([
+            len().cast(DataType::Int64).alias("sessions"),
+            col("crashed")
+                .sum()
+                .cast(DataType::Int64)
+                .alias("crashed_sessions"),
+        ])
+        .sort(["source"], SortMultipleOptions::default())
+        .collect()
+        .or_system_err(ADVICE)?;
+    let source = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let app_version = match df.column("app_version") {
+        Ok(column) => Some(column.str().or_system_err(ADVICE)?),
+        Err(_) => None,
+    };
+    let sessions = df
+        .column("sessions")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let crashed = df
+        .column("crashed_sessions")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+
+    Ok((0..df.height())
+        .filter_map(|i| {
+            let sessions = sessions.get(i).unwrap_or(0);
+            let crashed_sessions = crashed.get(i).unwrap_or(0);
+            source.get(i).map(|source| CrashFreeSessions {
+                source: source.to_string(),
+                app_version: app_version
+                    .and_then(|versions| versions.get(i))
+                    .map(str::to_string),
+                sessions,
+                crashed_sessions,
+                crash_free_rate: crash_free_rate(sessions, crashed_sessions).unwrap_or(1.0),
+            })
+        })
+        .collect())
+}
+
+/// The share of `sessions` that did not crash; `None` without sessions.
+fn crash_free_rate(sessions: i64, crashed_sessions: i64) -> Option<f64> {
+    (sessions > 0).then(|| 1.0 - crashed_sessions as f64 / sessions as f64)
+}
+
+/// A single exception group in forensic detail: the aggregate (with trend),
+/// how its occurrences distribute across key dimensions, and its **distinct
+/// variants** — occurrences collapsed by (message, stack, handledness) so an
+/// operator scrubs through genuinely different examples rather than paging
+/// hundreds of identical ones. Derived from one scan filtered to the group;
+/// looked up by id directly (no top-N cap), so a linked or bookmarked group
+/// opens regardless of how many fingerprints a project has. Returns `None` if
+/// the group has no occurrences in `[from_ms, to_ms)`.
+pub fn exception_detail(
+    store: &Store,
+    parquet_dir: &str,
+    sources: &[String],
+    group_id: &str,
+    from_ms: i64,
+    to_ms: i64,
+    limit: usize,
+) -> Result<Option<ExceptionGroupDetail>> {
+    let df = combined(store, parquet_dir, from_ms, to_ms)?
+        .filter(source_filter(sources))
+        .filter(col("kind").eq(lit("exception")))
+        .filter(col("exc_group").eq(lit(group_id.to_string())))
+        .select([
+            col("exc_type"),
+            col("exc_message"),
+            col("exc_stack"),
+            col("exc_handled"),
+            col("received_ms")
+                .cast(DataType::Int64)
+                .alias("received_ms"),
+            col("ua_browser"),
+            col("ua_os"),
+            col("ua_device"),
+            col("app_version"),
+            col("exc_level"),
+            col("environment"),
+            col("source"),
+            col("metadata_json"),
+            col("breadcrumbs_json"),
+            col("sid"),
+            col("bid"),
+            occurrence_weight().alias("occurrences"),
+            col("value"),
+            col("currency"),
+        ])
+        .sort(
+            ["received_ms"],
+            SortMultipleOptions::default().with_order_descending(true),
+        )
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let height = df.height();
+    if height == 0 {
+        return Ok(None);
+    }
+    let reach = df
+        .clone()
+        .lazy()
+        .select([
+            col("occurrences").sum().alias("count"),
+            col("sid")
+                .drop_nulls()
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias("sessions"),
+            col("bid")
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias("page_views"),
+        ])
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let exc_type = df
+        .column("exc_type")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let message = df
+        .column("exc_message")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let received = df
+        .column("received_ms")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let app_version = df
+        .column("app_version")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let weight = df
+        .column("occurrences")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+
+    // Rows are newest-first: index 0 is the most recent occurrence, the last
+    // index the oldest. The aggregate spans every row.
+    let group = ExceptionGroup {
+        group_id: group_id.to_string(),
+        exc_type: exc_type.get(0).unwrap_or("").to_string(),
+        sample_message: summary_line(message.get(0).unwrap_or("")).to_string(),
+        count: scalar_i64(&reach, "count"),
+        sessions: scalar_i64(&reach, "sessions"),
+        page_views: scalar_i64(&reach, "page_views"),
+        first_seen_ms: received.get(height - 1).unwrap_or(0),
+        last_seen_ms: received.get(0).unwrap_or(0),
+        status: ExceptionStatus::Unresolved,
+        resolved: false,
+        muted: false,
+        snooze: None,
+        assignee: None,
+        comments: 0,
+        first_release: app_version.get(height - 1).map(str::to_string),
+        regressed_in: None,
+        trend: weighted_trend_of(
+            (0..height).filter_map(|i| Some((received.get(i)?, weight.get(i).unwrap_or(1)))),
+            from_ms,
+            to_ms,
+        ),
+    };
+
+    let breakdowns = ExceptionBreakdowns {
+        app_versions: app_version_rows(&df)?,
+        browsers: count_by(&df, "ua_browser")?,
+        operating_systems: count_by(&df, "ua_os")?,
+        devices: count_by(&df, "ua_device")?,
+        levels: count_by(&df, "exc_level")?,
+        environments: count_by(&df, "environment")?,
+    };
+    let variants = variants_of(&df, limit)?;
+
+    let traces = traces_of_occurrences(store, parquet_dir, &df, from_ms, to_ms)?;
+
+    Ok(Some(ExceptionGroupDetail {
+        group,
+        breakdowns,
+        variants,
+        traces,
+        // Suggested by the detail handler, which scores the source's groups.
+        similar: Vec::new(),
+        // Filled in from the group's triage by the detail handler.
+        comments: Vec::new(),
+    }))
+}
+
+/// Occurrence counts per reported release. When the frame spans several
+/// sources, rows are keyed as `app @ version` (the app being the source's
+/// label) — a release number is only meaningful within its application. A
+/// frame scoped to a single source (the per-source detail view) keys rows by
+/// the bare version number, since the application is given. Occurrences with
+/// no reported version aggregate under the empty sentinel, whichever source
+/// they came from.
+fn app_version_rows(occurrences: &DataFrame) -> Result<Vec<CountRow>> {
+    let df = occurrences
+        .clone()
+        .lazy()
+        .with_columns([
+            col("source").fill_null(lit("")).alias("app"),
+            col("app_version").fill_null(lit("")).alias("version"),
+        ])
+        .group_by([col("app"), col("version")])
+        .agg([col("occurrences").sum().alias("count")])
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let apps = df
+        .column("app")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let versions = df
+        .column("version")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let counts = df
+        .column("count")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+
+    // Qualify versions with their application only when the frame genuinely
+    // mixes applications; labels are compared (not URIs) since distinct
+    // sources can share one (http vs https).
+    let mut labels: Vec<&str> = (0..df.height())
+        .filter_map(|i| apps.get(i))
+        .filter(|app| !app.is_empty())
+        .map(source_label)
+        .collect();
+    labels.sort_unstable();
+    labels.dedup();
+    let qualify = labels.len() > 1;
+
+    // Fold by the display key rather than trusting the group-by to have
+    // finished the job (see the label-sharing note above).
+    let mut totals: HashMap<String, i64> = HashMap::new();
+    for i in 0..df.height() {
+        let (Some(app), Some(version)) = (apps.get(i), versions.get(i)) else {
+            continue;
+        };
+        let key = match (app.is_empty(), version.is_empty()) {
+            (_, true) => String::new(),
+            (true, false) => version.to_string(),
+            (false, false) if qualify => format!("{} @ {version}", source_label(app)),
+            (false, false) => version.to_string(),
+        };
+        *totals.entry(key).or_insert(0) += counts.get(i).unwrap_or(0);
+    }
+    let mut rows: Vec<CountRow> = totals
+        .into_iter()
+        .map(|(key, count)| CountRow { key, count })
+        .collect();
+    rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    rows.truncate(BREAKDOWN_LIMIT as usize);
+    Ok(rows)
+}
+
+/// Collapse a group's occurrences (already sorted newest-first) into distinct
+/// variants keyed by (message, stack, handledness): one representative each,
+/// counted, most frequent first. The representative context (client, source,
+/// version, reporter metadata, breadcrumbs) comes from the variant's latest
+/// occurrence.
+fn variants_of(occurrences: &DataFrame, limit: usize) -> Result<Vec<ExceptionVariant>> {
+    let df = occurrences
+        .clone()
+        .lazy()
+        .group_by([col("exc_message"), col("exc_stack"), col("exc_handled")])
+        .agg([
+            col("occurrences").sum().alias("count"),
+            col("received_ms").min().alias("first_seen"),
+            col("received_ms").max().alias("last_seen"),
+            // The frame is newest-first, so `first()` is the latest context.
+            col("ua_browser").first().alias("ua_browser"),
+            col("ua_os").first().alias("ua_os"),
+            col("source").first().alias("source"),
+            col("app_version").first().alias("app_version"),
+            // Metadata is optional per report; surface the latest occurrence
+            // that actually carried some.
+            col("metadata_json")
+                .drop_nulls()
+                .first()
+                .alias("metadata_json"),
+            // Likewise the session link and the breadcrumbs.
+            col("sid").drop_nulls().first().alias("sid"),
+            col("breadcrumbs_json")
+                .drop_nulls()
+                .first()
+                .alias("breadcrumbs_json"),
+        ])
+        .sort(
+            ["count"],
+            SortMultipleOptions::default().with_order_descending(true),
+        )
+        .limit(limit as u32)
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let message = df
+        .column("exc_message")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let stack = df
+        .column("exc_stack")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let handled = df
+        .column("exc_handled")
+        .or_system_err(ADVICE)?
+        .bool()
+        .or_system_err(ADVICE)?;
+    let count = df
+        .column("count")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let first = df
+        .column("first_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let last = df
+        .column("last_seen")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let browser = df
+        .column("ua_browser")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let os = df
+        .column("ua_os")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let source = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let version = df
+        .column("app_version")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let metadata = df
+        .column("metadata_json")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let sid = df
+        .column("sid")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let breadcrumbs = df
+        .column("breadcrumbs_json")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+
+    Ok((0..df.height())
+        .map(|i| ExceptionVariant {
+            message: message.get(i).unwrap_or("").to_string(),
+            stack: stack.get(i).map(str::to_string),
+            handled: handled.get(i).unwrap_or(false),
+            count: count.get(i).unwrap_or(0),
+            first_seen_ms: first.get(i).unwrap_or(0),
+            last_seen_ms: last.get(i).unwrap_or(0),
+            ua_browser: browser.get(i).map(str::to_string),
+            ua_os: os.get(i).map(str::to_string),
+            source: source.get(i).map(str::to_string),
+            app_version: version.get(i).map(str::to_string),
+            metadata: metadata.get(i).map(str::to_string),
+            session_id: sid.get(i).map(str::to_string),
+            breadcrumbs: breadcrumbs
+                .get(i)
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default(),
+            // Parsed by the detail handler, once the stack is symbolicated.
+            frames: Vec::new(),
+        })
+        .collect())
+}
+
+// ----------------------------------------------------------------- internals
+
+/// [`trend_of`](super::trend_of) for `(timestamp, occurrences)` pairs, e.g.
+/// sampled exception occurrences standing for several each.
+fn weighted_trend_of(
+    occurrences: impl Iterator<Item = (i64, i64)>,
+    from_ms: i64,
+    to_ms: i64,
+) -> Vec<i64> {
+    let mut buckets = vec![0i64; TREND_BUCKETS];
+    for (t, weight) in occurrences {
+        buckets[trend_bucket(t, from_ms, to_ms)] += weight;
+    }
+    buckets
+}
+
+/// The `i64` values of row `i` of a list column.
+fn list_i64(column: &ListChunked, i: usize) -> Vec<i64> {
+    let Some(series) = column.get_as_series(i) else {
+        return Vec::new();
+    };
+    let Ok(values) = series.i64() else {
+        return Vec::new();
+    };
+    (0..values.len()).filter_map(|j| values.get(j)).collect()
+}
+
+/// How many occurrences each row stands for: the inverse of its `sample_rate`
+/// for an exception sampled during a storm, otherwise one.
+pub(super) fn occurrence_weight() -> Expr {
+    (lit(1.0) / col("sample_rate") + lit(0.5))
+        .cast(DataType::Int64)
+        .fill_null(lit(1i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::analytics::{dashboard, filter};
+    use crate::store::{EventKind, GroupRedirect, StoredEvent};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn temp_redb() -> std::path::PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+        std::env::temp_dir().join(format!(
+            "analytics-exceptions-{}-{}.redb",
+            std::process::id(),
+            n
+        ))
+    }
+
+    fn load(source: &str, received_ms: i64, unique: bool, duration: Option<i64>) -> StoredEvent {
+        StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            bid: "b".into(),
+            kind: if duration.is_some() {
+                EventKind::PageUnload
+            } else {
+                EventKind::PageLoad
+            },
+            source: source.into(),
+            pathname: Some("/home".into()),
+            is_unique_user: unique,
+            is_unique_page: unique,
+            ua_browser: Some("Chrome".into()),
+            ua_version: Some("120.0".into()),
+            duration_ms: duration,
+            ..Default::default()
+        }
+    }
+
+    fn source_q(source: &str) -> String {
+        format!(r#"source == "{source}""#)
+    }
+
+    fn typed(source: &str, received_ms: i64, kind: EventKind) -> StoredEvent {
+        StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            source: source.into(),
+            kind,
+            is_unique_user: false,
+            ..Default::default()
+        }
+    }
+
+    fn exc(group: &str, received_ms: i64) -> StoredEvent {
+        exc_on("https://a.com", group, received_ms)
+    }
+
+    fn exc_on(source: &str, group: &str, received_ms: i64) -> StoredEvent {
+        StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            kind: EventKind::Exception,
+            source: source.into(),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some("boom".into()),
+            exc_group: Some(group.into()),
+            exc_handled: Some(false),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exception_groups_keep_sources_separate_and_carry_trends() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        // Same fingerprint on two different sources (e.g. a shared-library error).
+        store
+            .append_events(&[
+                exc_on("https://a.com", "g1", 1_000),
+                exc_on("https://b.com", "g1", 2_000),
+                exc_on("https://a.com", "g1", 3_000),
+            ])
+            .unwrap();
+
+        let rows =
+            exception_groups_by_source(&store, "/none", 0, 10_000, None, None, None).unwrap();
+        // One row per (fingerprint, source) — not collapsed across sources.
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|(g, _)| g.group_id == "g1"));
+        let a = rows.iter().find(|(_, s)| s == "https://a.com").unwrap();
+        assert_eq!(a.0.count, 2);
+        assert_eq!(a.0.trend.len(), TREND_BUCKETS);
+        assert_eq!(a.0.trend.iter().sum::<i64>(), 2);
+        let b = rows.iter().find(|(_, s)| s == "https://b.com").unwrap();
+        assert_eq!(b.0.count, 1);
+        assert_eq!(b.0.trend.iter().sum::<i64>(), 1);
+
+        // A search's matches narrow the rows to their `(source, group)` pairs.
+        let keys = [
+            ("https://b.com".to_string(), "g1".to_string()),
+            ("https://a.com".to_string(), "g2".to_string()),
+        ];
+        let narrowed =
+            exception_groups_by_source(&store, "/none", 0, 10_000, None, Some(&keys), None)
+                .unwrap();
+        assert_eq!(narrowed.len(), 1);
+        assert_eq!(narrowed[0].1, "https://b.com");
+        let mut listed =
+            exception_group_keys(&store, "/none", 0, 10_000, None, Some(&keys)).unwrap();
+        listed.sort();
+        assert_eq!(listed, vec![("https://b.com".into(), "g1".into(), 2_000)]);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn merged_groups_read_as_their_target_until_applied() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        store
+            .append_events(&[
+                exc_on("https://a.com", "g1", 1_000),
+                exc_on("https://a.com", "g2", 2_000),
+                exc_on("https://b.com", "g1", 3_000),
+                typed("https://a.com", 4_000, EventKind::PageLoad),
+            ])
+            .unwrap();
+        let merge = GroupRedirect {
+            source: "https://a.com".into(),
+            group_id: "g1".into(),
+            target: "g2".into(),
+            merged_at: Utc::now(),
+            merged_by: None,
+            applied: false,
+        };
+        store.merge_exception_groups(None, &[merge], None).unwrap();
+
+        // Only the merged source's group moves, and rows without a group are
+        // kept by the join.
+        let counts = |store: &Store| {
+            let mut rows: Vec<(String, String, i64)> =
+                exception_groups_by_source(store, "/none", 0, 10_000, None, None, None)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(group, source)| (source, group.group_id, group.count))
+                    .collect();
+            rows.sort();
+            rows
+        };
+        assert_eq!(
+            counts(&store),
+            vec![
+                ("https://a.com".into(), "g2".into(), 2),
+                ("https://b.com".into(), "g1".into(), 1),
+            ]
+        );
+        let all = combined(&store, "/none", 0, 10_000)
+            .unwrap()
+            .collect()
+            .unwrap();
+        assert_eq!(all.height(), 4);
+
+        // Once a pass has stored the target, queries stop mapping the group
+        // (this store was never rewritten, so the old group shows through).
+        store
+            .mark_group_edits_applied(&|_| true, Utc::now())
+            .unwrap();
+        assert_eq!(counts(&store).len(), 3);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn sampled_occurrences_count_for_every_occurrence_they_stand_for() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let sampled = StoredEvent {
+            sample_rate: Some(0.25),
+            ..exc("g1", 2_000)
+        };
+        store.append_events(&[exc("g1", 1_000), sampled]).unwrap();
+
+        let rows =
+            exception_groups_by_source(&store, "/none", 0, 10_000, None, None, None).unwrap();
+        assert_eq!(rows[0].0.count, 5);
+        assert_eq!(rows[0].0.trend.iter().sum::<i64>(), 5);
+        let sources = ["https://a.com".to_string()];
+        let detail = exception_detail(&store, "/none", &sources, "g1", 0, 10_000, 10)
+            .unwrap()
+            .expect("g1 resolves");
+        assert_eq!(detail.group.count, 5);
+        assert_eq!(detail.variants[0].count, 5);
+        assert_eq!(detail.breakdowns.browsers[0].count, 5);
+        let dash = dashboard(&store, "/none", None, 0, 10_000, 86_400_000).unwrap();
+        assert_eq!(dash.timeseries[0].exceptions, 5);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn exceptions_break_down_by_level_and_environment() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let at = |ms, level: &str, environment: Option<&str>| StoredEvent {
+            exc_level: Some(level.into()),
+            environment: environment.map(Into::into),
+            ..exc("g1", ms)
+        };
+        store
+            .append_events(&[
+                at(1_000, "error", Some("production")),
+                at(2_000, "error", Some("production")),
+                at(3_000, "warning", Some("staging")),
+                at(4_000, "fatal", None),
+            ])
+            .unwrap();
+
+        let sources = ["https://a.com".to_string()];
+        let detail = exception_detail(&store, "/none", &sources, "g1", 0, 10_000, 10)
+            .unwrap()
+            .expect("g1 resolves");
+        assert_eq!(detail.breakdowns.levels[0].key, "error");
+        assert_eq!(detail.breakdowns.levels[0].count, 2);
+        assert_eq!(detail.breakdowns.environments.len(), 3);
+
+        // The selector only offers environments that were reported.
+        let environments = exception_environments(&store, "/none", 0, 10_000).unwrap();
+        let keys: Vec<&str> = environments.iter().map(|row| row.key.as_str()).collect();
+        assert_eq!(keys, vec!["production", "staging"]);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn exception_group_lookup_ignores_the_listing_page() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let events: Vec<_> = (1..=15).map(|i| exc(&format!("g{i}"), i * 100)).collect();
+        store.append_events(&events).unwrap();
+        let sources = ["https://a.com".to_string()];
+
+        // g1 is the oldest, so it falls outside the first page of the listing...
+        let listing_filter = filter::compile_query(
+            &source_q("https://a.com"),
+            filter::FieldSet::Exceptions,
+            &store,
+        )
+        .unwrap()
+        .unwrap();
+        let page = GroupPage {
+            sort: ExceptionSort::LastSeen,
+            after: None,
+            limit: 10,
+        };
+        let listed = exception_groups_by_source(
+            &store,
+            "/none",
+            0,
+            10_000_000,
+            Some(&listing_filter),
+            None,
+            Some(&page),
+        )
+        .unwrap();
+        assert_eq!(listed.len(), 10);
+        assert_eq!(listed[0].0.group_id, "g15");
+        assert!(!listed.iter().any(|(g, _)| g.group_id == "g1"));
+
+        // ...but a direct lookup still resolves it (group + variants in one scan).
+        let g1 = exception_detail(&store, "/none", &sources, "g1", 0, 10_000_000, 10).unwrap();
+        let detail = g1.expect("g1 resolves");
+        assert_eq!(detail.group.group_id, "g1");
+        assert_eq!(detail.group.count, 1);
+        assert_eq!(detail.group.trend.iter().sum::<i64>(), 1);
+        assert_eq!(detail.variants.len(), 1);
+        // An unknown group resolves to None.
+        assert!(
+            exception_detail(&store, "/none", &sources, "nope", 0, 10_000_000, 10)
+                .unwrap()
+                .is_none()
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn exception_group_pages_resume_after_their_position_through_ties() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let mut events = Vec::new();
+        for (group, count) in [("a", 5), ("b", 9), ("c", 5), ("d", 1)] {
+            events.extend((0..count).map(|i| exc(group, 1_000 + i)));
+        }
+        store.append_events(&events).unwrap();
+        let page = |after: Option<(i64, &str, &str)>| {
+            let page = GroupPage {
+                sort: ExceptionSort::Count,
+                after,
+                limit: 2,
+            };
+            exception_groups_by_source(&store, "/none", 0, 10_000, None, None, Some(&page))
+                .unwrap()
+                .into_iter()
+                .map(|(group, _)| (group.group_id, group.count))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(page(None), vec![("b".into(), 9), ("a".into(), 5)]);
+        assert_eq!(
+            page(Some((5, "https://a.com", "a"))),
+            vec![("c".into(), 5), ("d".into(), 1)]
+        );
+        assert!(page(Some((1, "https://a.com", "d"))).is_empty());
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn exception_detail_collapses_variants_and_attributes_releases() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        // One group, three occurrences: two share a message/stack (one variant
+        // of count 2), the third differs. Different app versions throughout,
+        // and the latest occurrence of the repeated variant carries metadata.
+        let mut a1 = exc("g1", 1_000);
+        a1.exc_message = Some("boom at start".into());
+        a1.exc_stack = Some("at start (app.js)".into());
+        a1.app_version = Some("1.0.0".into());
+        let mut a2 = exc("g1", 2_000);
+        a2.exc_message = Some("boom at start".into());
+        a2.exc_stack = Some("at start (app.js)".into());
+        a2.app_version = Some("1.1.0".into());
+        a2.metadata_json = Some(r#"{"feature_flag":"checkout-v2"}"#.into());
+        a2.sid = Some("sess-1".into());
+        a2.bid = "page-2".into();
+        let mut b = exc("g1", 3_000);
+        b.exc_message = Some("boom at shutdown".into());
+        b.exc_stack = Some("at shutdown (app.js)".into());
+        b.app_version = Some("1.1.0".into());
+        store.append_events(&[a1, a2, b]).unwrap();
+
+        let sources = ["https://a.com".to_string()];
+        let detail = exception_detail(&store, "/none", &sources, "g1", 0, 10_000, 10)
+            .unwrap()
+            .expect("g1 resolves");
+
+        // Two distinct variants; the repeated one carries its count and the
+        // context (source-as-app, version, metadata) of its latest occurrence.
+        assert_eq!(detail.variants.len(), 2);
+        let repeated = detail
+            .variants
+            .iter()
+            .find(|v| v.message == "boom at start")
+            .unwrap();
+        assert_eq!(repeated.count, 2);
+        assert_eq!(repeated.source.as_deref(), Some("https://a.com"));
+        assert_eq!(repeated.app_version.as_deref(), Some("1.1.0"));
+        assert_eq!(
+            repeated.metadata.as_deref(),
+            Some(r#"{"feature_flag":"checkout-v2"}"#)
+        );
+        // The exemplar links to the session of its latest session-linked
+        // occurrence.
+        assert_eq!(repeated.session_id.as_deref(), Some("sess-1"));
+
+        // The group's sessions surface as trace summaries for the picker.
+        assert_eq!(detail.traces.len(), 1);
+        assert_eq!(detail.traces[0].session_id, "sess-1");
+        assert_eq!(detail.traces[0].exceptions, 1);
+
+        // Distributions cover app releases (1.1.0 twice, 1.0.0 once). All the
+        // occurrences share one source, so versions are keyed bare — the
+        // application is given by the (source-scoped) view.
+        let versions = &detail.breakdowns.app_versions;
+        assert_eq!(
+            versions.first().map(|r| (r.key.as_str(), r.count)),
+            Some(("1.1.0", 2))
+        );
+        assert!(versions.iter().any(|r| r.key == "1.0.0" && r.count == 1));
+        assert_eq!(detail.group.first_release.as_deref(), Some("1.0.0"));
+        // Only a2 belongs to a session; it was on a page view of its own.
+        assert_eq!((detail.group.sessions, detail.group.page_views), (1, 2));
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    fn release(version: &str, deployed_ms: i64) -> Release {
+        let deployed_at = Utc.timestamp_millis_opt(deployed_ms).unwrap();
+        Release {
+            source: "https://a.com".into(),
+            version: version.into(),
+            deployed_at,
+            notes: None,
+            created_at: deployed_at,
+            auto: false,
+        }
+    }
+
+    fn exc_in(group: &str, received_ms: i64, version: &str) -> StoredEvent {
+        StoredEvent {
+            app_version: Some(version.into()),
+            ..exc(group, received_ms)
+        }
+    }
+
+    #[test]
+    fn releases_summarize_counts_and_introduced_groups() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        store.put_release(&release("1.0.0", 500)).unwrap();
+        store.put_release(&release("1.1.0", 2_500)).unwrap();
+        store
+            .append_events(&[
+                exc_in("g1", 1_000, "1.0.0"),
+                load("https://a.com", 1_500, true, None),
+                exc_in("g1", 3_000, "1.1.0"),
+                exc_in("g2", 4_000, "1.1.0"),
+                exc_in("g2", 5_000, "1.1.0"),
+            ])
+            .unwrap();
+
+        let summaries = release_summaries(&store, "/none", "https://a.com").unwrap();
+        let versions: Vec<&str> = summaries
+            .iter()
+            .map(|s| s.release.version.as_str())
+            .collect();
+        assert_eq!(versions, ["1.1.0", "1.0.0"]);
+        assert_eq!((summaries[0].exceptions, summaries[0].groups), (3, 2));
+        let introduced: Vec<&str> = summaries[0]
+            .new_issues
+            .iter()
+            .map(|i| i.group_id.as_str())
+            .collect();
+        assert_eq!(introduced, ["g2"]);
+        assert_eq!(summaries[1].new_issues[0].group_id, "g1");
+        assert_eq!(summaries[1].new_issues[0].count, 2);
+
+        // Only deploys inside the window mark the dashboard's series.
+        let dash = dashboard(&store, "/none", None, 2_000, 10_000, 1_000).unwrap();
+        let marked: Vec<&str> = dash.releases.iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(marked, ["1.1.0"]);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn crash_free_sessions_count_unhandled_exceptions_per_release() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        store.put_release(&release("1.0.0", 500)).unwrap();
+        let visit = |sid: &str, received_ms: i64, version: Option<&str>| StoredEvent {
+            sid: Some(sid.into()),
+            app_version: version.map(str::to_string),
+            ..load("https://a.com", received_ms, true, None)
+        };
+        let crash = |sid: &str, received_ms: i64, handled: bool| StoredEvent {
+            sid: Some(sid.into()),
+            exc_handled: Some(handled),
+            ..exc("g1", received_ms)
+        };
+        store
+            .append_events(&[
+                visit("s1", 1_000, Some("1.0.0")),
+                crash("s1", 1_100, false),
+                visit("s2", 1_200, Some("1.0.0")),
+                visit("s3", 1_300, Some("1.1.0")),
+                crash("s3", 1_400, true),
+                visit("s4", 1_500, None),
+                // No page load in range: not a session to rate.
+                crash("s5", 1_600, false),
+            ])
+            .unwrap();
+
+        let sources = ["https://a.com".to_string()];
+        let rows = crash_free_sessions(&store, "/none", &sources, 0, 10_000).unwrap();
+        let row = |version: Option<&str>| {
+            rows.iter()
+                .find(|r| r.app_version.as_deref() == version)
+                .map(|r| (r.sessions, r.crashed_sessions, r.crash_free_rate))
+        };
+        assert_eq!(rows[0].app_version, None);
+        assert_eq!(row(None), Some((4, 1, 0.75)));
+        assert_eq!(row(Some("1.0.0")), Some((2, 1, 0.5)));
+        assert_eq!(row(Some("1.1.0")), Some((1, 0, 1.0)));
+        assert_eq!(row(Some("")), Some((1, 0, 1.0)));
+
+        let summaries = release_summaries(&store, "/none", "https://a.com").unwrap();
+        assert_eq!(
+            (summaries[0].sessions, summaries[0].crash_free_rate),
+            (2, Some(0.5))
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn regressions_need_a_newer_release_than_the_last_one_seen() {
+        let deployed = HashMap::from([("1.0.0", 0), ("1.1.0", 2_000), ("1.2.0", 5_000)]);
+        let seen = [(1_000, "1.0.0"), (2_500, "1.1.0")];
+
+        // Resolved at 3s after last failing on 1.1.0: recurring on 1.1.0 (the
+        // fix hadn't shipped) is not a regression, 1.2.0 is.
+        let recurred = [seen.as_slice(), &[(4_000, "1.1.0"), (6_000, "1.2.0")]].concat();
+        assert_eq!(
+            regressed_in(&recurred, 3_000, &deployed).as_deref(),
+            Some("1.2.0")
+        );
+        assert_eq!(regressed_in(&recurred[..3], 3_000, &deployed), None);
+
+        // Without a versioned occurrence before the resolution, a release
+        // deployed after it is newer.
+        assert_eq!(
+            regressed_in(&[(6_000, "1.2.0")], 3_000, &deployed).as_deref(),
+            Some("1.2.0")
+        );
+        assert_eq!(regressed_in(&[(6_000, "9.9.9")], 3_000, &deployed), None);
+    }
+
+    #[test]
+    fn regressions_of_every_source_come_from_one_query() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let release = |source: &str, version: &str, deployed_ms: i64| Release {
+            source: source.into(),
+            version: version.into(),
+            deployed_at: Utc.timestamp_millis_opt(deployed_ms).unwrap(),
+            notes: None,
+            created_at: Utc::now(),
+            auto: false,
+        };
+        // The same version deploys at different times on the two sources.
+        for release in [
+            release("https://a.com", "1.0.0", 0),
+            release("https://a.com", "2.0.0", 5_000),
+            release("https://b.com", "1.0.0", 0),
+            release("https://b.com", "2.0.0", 1_000),
+        ] {
+            store.put_release(&release).unwrap();
+        }
+        let on = |source: &str, received_ms: i64, version: &str| StoredEvent {
+            app_version: Some(version.into()),
+            ..exc_on(source, "g1", received_ms)
+        };
+        store
+            .append_events(&[
+                on("https://a.com", 1_000, "1.0.0"),
+                on("https://a.com", 6_000, "2.0.0"),
+                on("https://b.com", 2_000, "2.0.0"),
+                on("https://b.com", 6_000, "2.0.0"),
+            ])
+            .unwrap();
+
+        let key = |source: &str| (source.to_string(), "g1".to_string());
+        let resolved =
+            HashMap::from([(key("https://a.com"), 3_000), (key("https://b.com"), 3_000)]);
+        let regressions = exception_regressions(&store, "/none", &resolved).unwrap();
+        // b.com still ran the release it was resolved on, so it didn't regress.
+        assert_eq!(
+            regressions,
+            HashMap::from([(key("https://a.com"), "2.0.0".to_string())])
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn similar_groups_keep_only_those_seen_in_range() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        store
+            .append_events(&[
+                exc_on("https://a.com", "near", 1_000),
+                exc_on("https://a.com", "near", 2_000),
+                exc_on("https://a.com", "closest", 9_000),
+                exc_on("https://b.com", "far", 2_000),
+            ])
+            .unwrap();
+        let suggested = |group_id: &str, score: f64| SimilarGroup {
+            group_id: group_id.into(),
+            exc_type: "TypeError".into(),
+            sample_message: "x".into(),
+            count: 0,
+            last_seen_ms: 0,
+            score,
+        };
+
+        let similar = similar_group_activity(
+            &store,
+            "/none",
+            "https://a.com",
+            vec![
+                suggested("closest", 0.9),
+                suggested("near", 0.7),
+                suggested("far", 0.6),
+            ],
+            0,
+            5_000,
+            5,
+        )
+        .unwrap();
+        // `closest` wasn't seen in the range, nor `far` on this source.
+        assert_eq!(similar.len(), 1);
+        assert_eq!(
+            (
+                similar[0].group_id.as_str(),
+                similar[0].count,
+                similar[0].last_seen_ms
+            ),
+            ("near", 2, 2_000)
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn exception_versions_qualify_only_across_sources() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let mut a = exc_on("https://a.com", "g1", 1_000);
+        a.app_version = Some("1.0.0".into());
+        let mut b = exc_on("https://b.com", "g1", 2_000);
+        b.app_version = Some("1.0.0".into());
+        store.append_events(&[a, b]).unwrap();
+
+        // Across two sources the bare number would be ambiguous, so rows stay
+        // qualified as `app @ version`.
+        let sources = ["https://a.com".to_string(), "https://b.com".to_string()];
+        let detail = exception_detail(&store, "/none", &sources, "g1", 0, 10_000, 10)
+            .unwrap()
+            .expect("g1 resolves");
+        let keys: Vec<&str> = detail
+            .breakdowns
+            .app_versions
+            .iter()
+            .map(|r| r.key.as_str())
+            .collect();
+        assert!(keys.contains(&"a.com @ 1.0.0"));
+        assert!(keys.contains(&"b.com @ 1.0.0"));
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+}
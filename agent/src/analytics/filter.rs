@@ -31,7 +31,7 @@ pub enum FieldSet {
 
 impl FieldSet {
     /// `property name → events column`, or `None` for unknown properties.
-    fn column(self, property: &str) -> Option<Field> {
+    pub(crate) fn column(self, property: &str) -> Option<Field> {
         let string = |column: &'static str| {
             Some(Field {
                 column,
@@ -53,6 +53,7 @@ impl FieldSet {
             (FieldSet::Dashboard, "utm_source") => string("utm_source"),
             (FieldSet::Dashboard, "utm_medium") => string("utm_medium"),
             (FieldSet::Dashboard, "utm_campaign") => string("utm_campaign"),
+            (FieldSet::Dashboard, "channel") => string("channel"),
             // The name of a custom/pixel event; page views carry none, so an
             // event filter naturally scopes the view to those events.
             (FieldSet::Dashboard, "event") => string("event_name"),
@@ -62,6 +63,8 @@ impl FieldSet {
             (FieldSet::Exceptions, "app_version") => string("app_version"),
             (FieldSet::Exceptions, "type") => string("exc_type"),
             (FieldSet::Exceptions, "message") => string("exc_message"),
+            (FieldSet::Exceptions, "level") => string("exc_level"),
+            (FieldSet::Exceptions, "environment") => string("environment"),
             (FieldSet::Exceptions, "handled") => Some(Field {
                 column: "exc_handled",
                 boolean: true,
@@ -71,24 +74,24 @@ impl FieldSet {
     }
 
     /// The property names this field set accepts (for error messages).
-    fn known(self) -> &'static str {
+    pub(crate) fn known(self) -> &'static str {
         match self {
             FieldSet::Dashboard => {
                 "project, source, path, referrer, country, language, browser, version, os, \
-                 device, utm_source, utm_medium, utm_campaign, event"
+                 device, utm_source, utm_medium, utm_campaign, channel, event"
             }
             FieldSet::Exceptions => {
                 "project, source, browser, version, os, device, app, app_version, type, \
-                 message, handled"
+                 message, handled, level, environment"
             }
         }
     }
 }
 
 #[derive(Clone, Copy)]
-struct Field {
-    column: &'static str,
-    boolean: bool,
+pub(crate) struct Field {
+    pub(crate) column: &'static str,
+    pub(crate) boolean: bool,
 }
 
 /// A query compiled to a polars predicate, plus the properties it referenced
@@ -461,25 +464,36 @@ impl<'a> ExprVisitor<'a, Fold> for Compiler<'_> {
     }
 }
 
+/// Every canonical URI form a `source` value could be stored as: sources are
+/// stored as `https://…`/`app://…`/`pixel://…` but read as bare hostnames
+/// throughout the UI, so a scheme-less name stands for each scheme. Values
+/// that already carry a scheme name only themselves. Lowercased, as hostnames
+/// are at ingest.
+pub(crate) fn source_forms(value: &str) -> Vec<String> {
+    let value = value.to_lowercase();
+    if value.contains("://") {
+        vec![value]
+    } else {
+        ["https://", "app://", "pixel://", ""]
+            .iter()
+            .map(|scheme| format!("{scheme}{value}"))
+            .collect()
+    }
+}
+
+/// Whether the stored `source` URI is one of the [forms](source_forms) `value`
+/// names, i.e. whether a query's `source == value` selects it.
+pub(crate) fn source_matches(source: &str, value: &str) -> bool {
+    source_forms(value).contains(&source.to_lowercase())
+}
+
 /// Membership over the `source` column, tolerant of scheme-less names: each
-/// value expands to every canonical URI form it could be stored as (sources
-/// are stored as `https://…`/`app://…`/`pixel://…` but read as bare hostnames
-/// throughout the UI). Values that already carry a scheme match as-is, always
-/// case-insensitively (hostnames are lowercased at ingest).
+/// value expands to its [canonical forms](source_forms), matched
+/// case-insensitively.
 fn source_in(values: &[String]) -> Expr {
     let forms: Vec<String> = values
         .iter()
-        .flat_map(|value| {
-            let value = value.to_lowercase();
-            if value.contains("://") {
-                vec![value]
-            } else {
-                ["https://", "app://", "pixel://", ""]
-                    .iter()
-                    .map(|scheme| format!("{scheme}{value}"))
-                    .collect()
-            }
-        })
+        .flat_map(|value| source_forms(value))
         .collect();
     col("source").str().to_lowercase().is_in(
         lit(Series::new("sources".into(), forms)).implode(false),
@@ -603,6 +617,14 @@ mod tests {
         assert!(compile_query(r#"app_version == "1.2.3""#, FieldSet::Dashboard, &store).is_err());
         assert!(compile_query(r#"path == "/x""#, FieldSet::Exceptions, &store).is_err());
         assert!(compile_query("handled == false", FieldSet::Exceptions, &store).is_ok());
+        assert!(
+            compile_query(
+                r#"level in ["error", "fatal"] && environment == "production""#,
+                FieldSet::Exceptions,
+                &store
+            )
+            .is_ok()
+        );
         drop(store);
         let _ = std::fs::remove_file(&path);
     }
@@ -616,6 +638,18 @@ mod tests {
             r#"source in ["docs.example.com", "https://shop.example.com"]"#
         ));
         assert!(compiles(r#"source in_cs ["docs.example.com"]"#));
+
+        assert!(source_matches(
+            "https://docs.example.com",
+            "Docs.Example.com"
+        ));
+        assert!(source_matches("app://com.example.ios", "com.example.ios"));
+        assert!(source_matches("pixel://shop", "pixel://shop"));
+        assert!(!source_matches(
+            "https://docs.example.com",
+            "app://docs.example.com"
+        ));
+        assert!(!source_matches("https://docs.example.com", "example.com"));
     }
 
     #[test]
@@ -0,0 +1,385 @@
+//! User flows: where sessions were before an anchor page or event and where
+//! they went next. Each session's page views and custom events are walked
+//! oldest first (ties in arrival order, as on its trace) from its first event
+//! matching the anchor, a few steps either way. Repeats of a step in a row (a
+//! reload, an event fired twice) count once.
+//!
+//! Only the busiest steps at each position keep their own node; the rest of
+//! that position merges into one "other" node, so the diagram stays legible.
+
+use std::collections::HashMap;
+
+use analytics_api::{Flow, FlowLink, FlowNode, FlowNodeKind};
+use polars::prelude::*;
+
+use super::filter::CompiledFilter;
+use super::{ADVICE, combined};
+use crate::errors::{Result, ResultExt};
+use crate::store::Store;
+
+/// The most steps a flow follows either side of its anchor.
+pub const MAX_FLOW_DEPTH: usize = 5;
+/// The steps followed either side of the anchor unless asked otherwise.
+pub const DEFAULT_FLOW_DEPTH: usize = 3;
+/// The most distinct steps kept at one position.
+pub const MAX_FLOW_LIMIT: usize = 10;
+/// The distinct steps kept at one position unless asked otherwise.
+pub const DEFAULT_FLOW_LIMIT: usize = 5;
+
+/// A page view (by pathname) or custom event (by name) in a session.
+type Step = (FlowNodeKind, String);
+
+/// What a flow explores from, and how far.
+pub struct FlowSpec {
+    pub anchor: CompiledFilter,
+    /// The anchor node's label.
+    pub label: String,
+    /// Steps to follow either side of the anchor.
+    pub depth: usize,
+    /// Distinct steps to keep at each position.
+    pub limit: usize,
+}
+
+/// The flow through the events matching `spec`'s anchor within
+/// `[from_ms, to_ms)`, considering only page views and custom events that
+/// match `filter`.
+pub fn flow(
+    store: &Store,
+    parquet_dir: &str,
+    filter: Option<&CompiledFilter>,
+    spec: &FlowSpec,
+    from_ms: i64,
+    to_ms: i64,
+) -> Result<Flow> {
+    let depth = spec.depth.clamp(1, MAX_FLOW_DEPTH);
+    let limit = spec.limit.clamp(1, MAX_FLOW_LIMIT);
+    let mut lf = combined(store, parquet_dir, from_ms, to_ms)?.filter(
+        col("sid").is_not_null().and(
+            col("kind")
+                .eq(lit("page_load"))
+                .or(col("kind").eq(lit("custom"))),
+        ),
+    );
+    if let Some(filter) = filter {
+        lf = lf.filter(filter.predicate.clone());
+    }
+
+    let df = lf
+        .select([
+            col("sid"),
+            col("received_ms")
+                .cast(DataType::Int64)
+                .alias("received_ms"),
+            col("seq"),
+            col("kind"),
+            col("pathname"),
+            col("event_name"),
+            spec.anchor
+                .predicate
+                .clone()
+                .fill_null(lit(false))
+                .alias("anchor"),
+        ])
+        // Each session's events together, in the order its trace shows them.
+        .sort(
+            ["sid", "received_ms", "seq"],
+            SortMultipleOptions::default(),
+        )
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let column = |name: &str| -> Result<StringChunked> {
+        Ok(df
+            .column(name)
+            .or_system_err(ADVICE)?
+            .str()
+            .or_system_err(ADVICE)?
+            .clone())
+    };
+    let (sid, kind, pathname, event_name) = (
+        column("sid")?,
+        column("kind")?,
+        column("pathname")?,
+        column("event_name")?,
+    );
+    let anchored = df
+        .column("anchor")
+        .or_system_err(ADVICE)?
+        .bool()
+        .or_system_err(ADVICE)?;
+
+    let mut paths = Vec::new();
+    let mut start = 0;
+    while start < df.height() {
+        let session = sid.get(start);
+        let mut end = start + 1;
+        while end < df.height() && sid.get(end) == session {
+            end += 1;
+        }
+
+        let mut steps: Vec<(Step, bool)> = Vec::with_capacity(end - start);
+        for i in start..end {
+            let step = if kind.get(i) == Some("page_load") {
+                (FlowNodeKind::Page, pathname.get(i).unwrap_or_default())
+            } else {
+                (FlowNodeKind::Event, event_name.get(i).unwrap_or_default())
+            };
+            let is_anchor = anchored.get(i).unwrap_or(false);
+            match steps.last_mut() {
+                Some(((k, label), a)) if *k == step.0 && *label == step.1 => *a |= is_anchor,
+                _ => steps.push(((step.0, step.1.to_string()), is_anchor)),
+            }
+        }
+        if let Some(at) = steps.iter().position(|(_, a)| *a) {
+            let before = steps[at.saturating_sub(depth)..at].iter().rev();
+            let after = steps[at + 1..].iter().take(depth);
+            paths.push((
+                before.map(|(step, _)| step.clone()).collect(),
+                after.map(|(step, _)| step.clone()).collect(),
+            ));
+        }
+        start = end;
+    }
+
+    Ok(build(&paths, limit, &spec.label))
+}
+
+/// Assemble the Sankey nodes and links from each session's steps before the
+/// anchor (nearest first) and after it.
+fn build(paths: &[(Vec<Step>, Vec<Step>)], limit: usize, anchor_label: &str) -> Flow {
+    // The busiest steps at each position keep their own node.
+    let mut counts: HashMap<(i32, &Step), i64> = HashMap::new();
+    for path in paths {
+        for (position, step) in positioned(path) {
+            if let Some(step) = step {
+                *counts.entry((position, step)).or_default() += 1;
+            }
+        }
+    }
+    let mut ranked: Vec<(&(i32, &Step), &i64)> = counts.iter().collect();
+    ranked.sort_by(|(a, x), (b, y)| {
+        a.0.cmp(&b.0)
+            .then_with(|| y.cmp(x))
+            .then_with(|| a.1.1.cmp(&b.1.1))
+    });
+    let mut kept: HashMap<(i32, &Step), usize> = HashMap::new();
+    let mut nodes = vec![FlowNode {
+        position: 0,
+        kind: FlowNodeKind::Anchor,
+        label: anchor_label.to_string(),
+        sessions: paths.len() as i64,
+    }];
+    let mut per_position: HashMap<i32, usize> = HashMap::new();
+    for (&(position, step), &sessions) in ranked {
+        let seen = per_position.entry(position).or_default();
+        if *seen < limit {
+            *seen += 1;
+            kept.insert((position, step), nodes.len());
+            nodes.push(FlowNode {
+                position,
+                kind: step.0,
+                label: step.1.clone(),
+                sessions,
+            });
+        }
+    }
+
+    // Everything else merges into one node per position, and every session's
+    // consecutive steps become links.
+    let mut others: HashMap<i32, usize> = HashMap::new();
+    let mut links: HashMap<(usize, usize), i64> = HashMap::new();
+    for path in paths {
+        let mut previous: Option<usize> = None;
+        for (position, step) in positioned(path) {
+            let node = match step {
+                None => 0,
+                Some(step) => match kept.get(&(position, step)) {
+                    Some(&node) => node,
+                    None => *others.entry(position).or_insert_with(|| {
+                        nodes.push(FlowNode {
+                            position,
+                            kind: FlowNodeKind::Other,
+                            label: String::new(),
+                            sessions: 0,
+                        });
+                        nodes.len() - 1
+                    }),
+                },
+            };
+            if nodes[node].kind == FlowNodeKind::Other {
+                nodes[node].sessions += 1;
+            }
+            if let Some(previous) = previous {
+                *links.entry((previous, node)).or_default() += 1;
+            }
+            previous = Some(node);
+        }
+    }
+
+    // Order the nodes by position, busiest first and "other" last, remapping
+    // the links' indices.
+    let mut order: Vec<usize> = (0..nodes.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&nodes[a], &nodes[b]);
+        a.position
+            .cmp(&b.position)
+            .then_with(|| (a.kind == FlowNodeKind::Other).cmp(&(b.kind == FlowNodeKind::Other)))
+            .then_with(|| b.sessions.cmp(&a.sessions))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    let mut index = vec![0; nodes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        index[old] = new;
+    }
+    let mut links: Vec<FlowLink> = links
+        .into_iter()
+        .map(|((source, target), sessions)| FlowLink {
+            source: index[source],
+            target: index[target],
+            sessions,
+        })
+        .collect();
+    links.sort_by_key(|l| (l.source, std::cmp::Reverse(l.sessions), l.target));
+
+    Flow {
+        sessions: paths.len() as i64,
+        nodes: order.into_iter().map(|i| nodes[i].clone()).collect(),
+        links,
+    }
+}
+
+/// A session's steps by position, `None` standing for the anchor at 0.
+fn positioned((before, after): &(Vec<Step>, Vec<Step>)) -> Vec<(i32, Option<&Step>)> {
+    let mut steps: Vec<(i32, Option<&Step>)> = before
+        .iter()
+        .enumerate()
+        .map(|(i, step)| (-(i as i32) - 1, Some(step)))
+        .collect();
+    steps.reverse();
+    steps.push((0, None));
+    steps.extend(
+        after
+            .iter()
+            .enumerate()
+            .map(|(i, step)| (i as i32 + 1, Some(step))),
+    );
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::filter::{FieldSet, compile_query};
+    use crate::store::{EventKind, StoredEvent};
+
+    fn page(path: &str) -> Step {
+        (FlowNodeKind::Page, path.to_string())
+    }
+
+    #[test]
+    fn merges_steps_beyond_the_limit() {
+        let paths = vec![
+            (vec![page("/")], vec![page("/signup")]),
+            (vec![page("/")], vec![page("/signup")]),
+            (vec![page("/blog")], vec![page("/docs")]),
+            (vec![], vec![page("/about")]),
+        ];
+        let flow = build(&paths, 1, "pricing");
+
+        let nodes: Vec<(i32, FlowNodeKind, &str, i64)> = flow
+            .nodes
+            .iter()
+            .map(|n| (n.position, n.kind, n.label.as_str(), n.sessions))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (-1, FlowNodeKind::Page, "/", 2),
+                (-1, FlowNodeKind::Other, "", 1),
+                (0, FlowNodeKind::Anchor, "pricing", 4),
+                (1, FlowNodeKind::Page, "/signup", 2),
+                (1, FlowNodeKind::Other, "", 2),
+            ]
+        );
+        let links: Vec<(usize, usize, i64)> = flow
+            .links
+            .iter()
+            .map(|l| (l.source, l.target, l.sessions))
+            .collect();
+        assert_eq!(links, vec![(0, 2, 2), (1, 2, 1), (2, 3, 2), (2, 4, 2)]);
+    }
+
+    #[test]
+    fn follows_sessions_either_side_of_the_anchor() {
+        let redb = std::env::temp_dir().join(format!(
+            "analytics-flow-{}-{}.redb",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let store = Store::open(&redb).unwrap();
+        let view = |sid: &str, received_ms: i64, path: &str| StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            bid: "b".into(),
+            sid: Some(sid.into()),
+            kind: EventKind::PageLoad,
+            source: "https://a.com".into(),
+            pathname: Some(path.into()),
+            ..Default::default()
+        };
+        store
+            .append_events(&[
+                view("s1", 1_000, "/"),
+                view("s1", 2_000, "/pricing"),
+                // A reload counts once.
+                view("s1", 2_500, "/pricing"),
+                StoredEvent {
+                    created_ms: 3_000,
+                    received_ms: 3_000,
+                    sid: Some("s1".into()),
+                    kind: EventKind::Custom,
+                    source: "https://a.com".into(),
+                    event_name: Some("signup".into()),
+                    ..Default::default()
+                },
+                view("s2", 1_000, "/pricing"),
+                view("s2", 2_000, "/docs"),
+                // Never reaches the anchor.
+                view("s3", 1_000, "/"),
+            ])
+            .unwrap();
+
+        let spec = FlowSpec {
+            anchor: compile_query(r#"path == "/pricing""#, FieldSet::Dashboard, &store)
+                .unwrap()
+                .unwrap(),
+            label: "/pricing".into(),
+            depth: 3,
+            limit: 5,
+        };
+        let flow = flow(&store, "/none", None, &spec, 0, 10_000).unwrap();
+
+        assert_eq!(flow.sessions, 2);
+        let nodes: Vec<(i32, FlowNodeKind, &str, i64)> = flow
+            .nodes
+            .iter()
+            .map(|n| (n.position, n.kind, n.label.as_str(), n.sessions))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (-1, FlowNodeKind::Page, "/", 1),
+                (0, FlowNodeKind::Anchor, "/pricing", 2),
+                (1, FlowNodeKind::Page, "/docs", 1),
+                (1, FlowNodeKind::Event, "signup", 1),
+            ]
+        );
+        assert_eq!(flow.links.len(), 3);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+}
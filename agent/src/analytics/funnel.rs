@@ -0,0 +1,360 @@
+//! Funnels: the share of sessions that pass through an ordered series of
+//! steps. Each step is a compiled filter over the dashboard vocabulary, so a
+//! step is any page view or custom event the dashboard could filter to.
+//!
+//! A session's events are walked oldest first (ties in arrival order, as on
+//! its trace). It reaches the next step on the first event matching it, if
+//! that comes soon enough after the previous step; a repeat of the step last
+//! reached restarts that clock, so a visitor who returns to `/pricing` before
+//! signing up is measured from the return.
+
+use std::collections::HashMap;
+
+use analytics_api::{Funnel, FunnelBreakdownRow, FunnelStep};
+use polars::prelude::*;
+
+use super::filter::CompiledFilter;
+use super::{ADVICE, BREAKDOWN_LIMIT, combined};
+use crate::errors::{Result, ResultExt};
+use crate::store::Store;
+
+/// The most steps a funnel may have (each is a bit of a session's event mask).
+pub const MAX_FUNNEL_STEPS: usize = 10;
+
+/// One compiled step of a funnel.
+pub struct FunnelStepSpec {
+    pub label: String,
+    pub filter: CompiledFilter,
+    /// The longest the step may follow the previous one, in milliseconds.
+    pub within_ms: Option<i64>,
+}
+
+/// Count the sessions reaching each of `steps` in order within
+/// `[from_ms, to_ms)`, considering only page views and custom events that
+/// match `filter`. `breakdown` is the events column to split sessions by.
+pub fn funnel(
+    store: &Store,
+    parquet_dir: &str,
+    filter: Option<&CompiledFilter>,
+    steps: &[FunnelStepSpec],
+    breakdown: Option<&str>,
+    from_ms: i64,
+    to_ms: i64,
+) -> Result<Funnel> {
+    let steps = &steps[..steps.len().min(MAX_FUNNEL_STEPS)];
+    let mut lf = combined(store, parquet_dir, from_ms, to_ms)?.filter(
+        col("sid").is_not_null().and(
+            col("kind")
+                .eq(lit("page_load"))
+                .or(col("kind").eq(lit("custom"))),
+        ),
+    );
+    if let Some(filter) = filter {
+        lf = lf.filter(filter.predicate.clone());
+    }
+
+    let mut columns = vec![
+        col("sid"),
+        col("received_ms")
+            .cast(DataType::Int64)
+            .alias("received_ms"),
+        col("seq"),
+    ];
+    let mut relevant = lit(false);
+    for (i, step) in steps.iter().enumerate() {
+        let matched = step.filter.predicate.clone().fill_null(lit(false));
+        relevant = relevant.or(matched.clone());
+        columns.push(matched.alias(format!("step_{i}")));
+    }
+    if let Some(column) = breakdown {
+        relevant = relevant.or(col(column).is_not_null());
+        columns.push(col(column).alias("breakdown"));
+    }
+
+    let df = lf
+        .filter(relevant)
+        .select(columns)
+        // Each session's events together, in the order its trace shows them.
+        .sort(
+            ["sid", "received_ms", "seq"],
+            SortMultipleOptions::default(),
+        )
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let sid = df
+        .column("sid")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let received = df
+        .column("received_ms")
+        .or_system_err(ADVICE)?
+        .i64()
+        .or_system_err(ADVICE)?;
+    let flags = (0..steps.len())
+        .map(|i| {
+            Ok(df
+                .column(&format!("step_{i}"))
+                .or_system_err(ADVICE)?
+                .bool()
+                .or_system_err(ADVICE)?
+                .clone())
+        })
+        .collect::<Result<Vec<BooleanChunked>>>()?;
+    let keys = match breakdown {
+        Some(_) => Some(
+            df.column("breakdown")
+                .or_system_err(ADVICE)?
+                .str()
+                .or_system_err(ADVICE)?
+                .clone(),
+        ),
+        None => None,
+    };
+
+    let within_ms: Vec<Option<i64>> = steps.iter().map(|s| s.within_ms).collect();
+    let mut reached = vec![0i64; steps.len()];
+    let mut gaps: Vec<Vec<i64>> = vec![Vec::new(); steps.len()];
+    let mut by_key: HashMap<String, Vec<i64>> = HashMap::new();
+
+    let mut start = 0;
+    while start < df.height() {
+        let session = sid.get(start);
+        let mut end = start + 1;
+        while end < df.height() && sid.get(end) == session {
+            end += 1;
+        }
+
+        let events: Vec<(i64, u32)> = (start..end)
+            .map(|i| {
+                let mask = flags
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, f)| f.get(i).unwrap_or(false))
+                    .fold(0u32, |mask, (step, _)| mask | (1 << step));
+                (received.get(i).unwrap_or(0), mask)
+            })
+            .collect();
+        let progress = progress(&events, &within_ms);
+        if !progress.is_empty() {
+            for (step, gap) in progress.iter().enumerate() {
+                reached[step] += 1;
+                if step > 0 {
+                    gaps[step].push(*gap);
+                }
+            }
+            if let Some(keys) = &keys {
+                let key = (start..end)
+                    .find_map(|i| keys.get(i))
+                    .unwrap_or("")
+                    .to_string();
+                let counts = by_key.entry(key).or_insert_with(|| vec![0; steps.len()]);
+                for count in counts.iter_mut().take(progress.len()) {
+                    *count += 1;
+                }
+            }
+        }
+        start = end;
+    }
+
+    let entered = reached.first().copied().unwrap_or(0);
+    let funnel_steps = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let previous = if i == 0 { entered } else { reached[i - 1] };
+            FunnelStep {
+                label: step.label.clone(),
+                sessions: reached[i],
+                conversion: fraction(reached[i], entered),
+                step_conversion: if i == 0 {
+                    1.0
+                } else {
+                    fraction(reached[i], previous)
+                },
+                dropped: previous - reached[i],
+                median_ms: median(&mut gaps[i]),
+            }
+        })
+        .collect();
+
+    let mut rows: Vec<FunnelBreakdownRow> = by_key
+        .into_iter()
+        .map(|(key, sessions)| FunnelBreakdownRow { key, sessions })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.sessions
+            .first()
+            .cmp(&a.sessions.first())
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    rows.truncate(BREAKDOWN_LIMIT as usize);
+
+    Ok(Funnel {
+        steps: funnel_steps,
+        breakdown: rows,
+    })
+}
+
+/// How far one session got: for each step reached, the time since the
+/// previous step (zero for the first). `events` are `(received_ms, mask)`
+/// oldest first, bit `i` of the mask set when the event matches step `i`.
+fn progress(events: &[(i64, u32)], within_ms: &[Option<i64>]) -> Vec<i64> {
+    let mut gaps: Vec<i64> = Vec::new();
+    // When the step last reached last occurred.
+    let mut anchor = 0i64;
+    for &(at, mask) in events {
+        let next = gaps.len();
+        if next < within_ms.len() && mask & (1 << next) != 0 {
+            let gap = if next == 0 { 0 } else { at - anchor };
+            if next == 0 || within_ms[next].is_none_or(|limit| gap <= limit) {
+                gaps.push(gap);
+                anchor = at;
+                continue;
+            }
+        }
+        if next > 0 && mask & (1 << (next - 1)) != 0 {
+            anchor = at;
+        }
+    }
+    gaps
+}
+
+fn fraction(part: i64, whole: i64) -> f64 {
+    if whole > 0 {
+        part as f64 / whole as f64
+    } else {
+        0.0
+    }
+}
+
+fn median(values: &mut [i64]) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[values.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::filter::{FieldSet, compile_query};
+    use crate::store::{EventKind, StoredEvent};
+
+    fn temp_redb() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "analytics-funnel-{}-{}.redb",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ))
+    }
+
+    fn view(sid: &str, received_ms: i64, path: &str, country: &str) -> StoredEvent {
+        StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            bid: "b".into(),
+            sid: Some(sid.into()),
+            kind: EventKind::PageLoad,
+            source: "https://a.com".into(),
+            pathname: Some(path.into()),
+            country: Some(country.into()),
+            ..Default::default()
+        }
+    }
+
+    fn custom(sid: &str, received_ms: i64, name: &str) -> StoredEvent {
+        StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            sid: Some(sid.into()),
+            kind: EventKind::Custom,
+            source: "https://a.com".into(),
+            event_name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    fn step(store: &Store, q: &str, within_ms: Option<i64>) -> FunnelStepSpec {
+        FunnelStepSpec {
+            label: q.to_string(),
+            filter: compile_query(q, FieldSet::Dashboard, store)
+                .unwrap()
+                .unwrap(),
+            within_ms,
+        }
+    }
+
+    #[test]
+    fn repeating_the_last_step_restarts_the_clock() {
+        let within = [None, Some(100)];
+        // Too slow from the first visit, but in time from the second.
+        assert_eq!(
+            progress(&[(0, 1), (500, 1), (550, 2)], &within),
+            vec![0, 50]
+        );
+        assert_eq!(progress(&[(0, 1), (500, 2)], &within), vec![0]);
+        // Steps only count in order.
+        assert_eq!(progress(&[(0, 2), (10, 1)], &within), vec![0]);
+    }
+
+    #[test]
+    fn counts_sessions_through_ordered_steps() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        store
+            .append_events(&[
+                // Converts.
+                view("s1", 1_000, "/pricing", "DE"),
+                view("s1", 2_000, "/signup", "DE"),
+                custom("s1", 3_000, "signup_completed"),
+                // Signs up before seeing pricing: only enters.
+                view("s2", 1_000, "/signup", "US"),
+                view("s2", 2_000, "/pricing", "US"),
+                // Takes too long over the signup form.
+                view("s3", 1_000, "/pricing", "DE"),
+                view("s3", 2_000, "/signup", "DE"),
+                custom("s3", 90_000, "signup_completed"),
+                // Never enters.
+                view("s4", 1_000, "/home", "FR"),
+            ])
+            .unwrap();
+
+        let steps = [
+            step(&store, r#"path == "/pricing""#, None),
+            step(&store, r#"path == "/signup""#, None),
+            step(&store, r#"event == "signup_completed""#, Some(60_000)),
+        ];
+        let funnel = funnel(&store, "/none", None, &steps, Some("country"), 0, 100_000).unwrap();
+
+        let sessions: Vec<i64> = funnel.steps.iter().map(|s| s.sessions).collect();
+        assert_eq!(sessions, vec![3, 2, 1]);
+        assert_eq!(funnel.steps[1].dropped, 1);
+        assert!((funnel.steps[2].conversion - 1.0 / 3.0).abs() < 1e-9);
+        assert!((funnel.steps[2].step_conversion - 0.5).abs() < 1e-9);
+        assert_eq!(funnel.steps[1].median_ms, Some(1_000));
+        assert_eq!(funnel.steps[0].median_ms, None);
+
+        assert_eq!(
+            funnel.breakdown,
+            vec![
+                FunnelBreakdownRow {
+                    key: "DE".into(),
+                    sessions: vec![2, 2, 1],
+                },
+                FunnelBreakdownRow {
+                    key: "US".into(),
+                    sessions: vec![1, 0, 0],
+                },
+            ]
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+}
@@ -0,0 +1,450 @@
+//! Goals: per-project conversions. A session converts on a goal when it views
+//! a matching page or reports a matching event; the dashboard reports each
+//! goal's converting sessions over the slice and under every page-view
+//! breakdown row, out of the sessions on the goal's project's sources.
+
+use std::collections::HashMap;
+
+use analytics_api::{BreakdownRow, Breakdowns, Goal, GoalConversions, GoalSummary, GoalTarget};
+use polars::prelude::*;
+use regex::Regex;
+
+use super::{ADVICE, is_event, project_source_uris, source_filter};
+use crate::errors::{Result, ResultExt};
+use crate::ingest;
+use crate::store::Store;
+
+/// The most goals a project may define.
+pub const MAX_GOALS: usize = 20;
+
+/// A goal compiled to a predicate over events.
+pub struct CompiledGoal {
+    predicate: Expr,
+}
+
+impl CompiledGoal {
+    /// Compile `goal`. `Err` carries a message suitable for a 400 response.
+    pub fn new(goal: &Goal) -> std::result::Result<Self, String> {
+        let name = goal.name.trim();
+        let predicate = match &goal.target {
+            GoalTarget::Path { kind, pattern } => {
+                let pattern = pattern.trim();
+                if pattern.is_empty() {
+                    return Err(format!("The goal \"{name}\" needs a path pattern."));
+                }
+                let source = ingest::pattern_regex(*kind, pattern);
+                Regex::new(&source).map_err(|err| {
+                    format!("The pattern `{pattern}` of the goal \"{name}\" is not valid: {err}")
+                })?;
+                col("kind")
+                    .eq(lit("page_load"))
+                    .and(col("pathname").str().contains(lit(source), true))
+            }
+            GoalTarget::Event {
+                name: event,
+                metadata,
+            } => {
+                if event.trim().is_empty() {
+                    return Err(format!("The goal \"{name}\" needs an event name."));
+                }
+                let mut predicate = is_event().and(col("event_name").eq(lit(event.clone())));
+                for (key, value) in metadata {
+                    predicate = predicate.and(
+                        col("metadata_json")
+                            .str()
+                            .contains(lit(metadata_entry(key, value)), true),
+                    );
+                }
+                predicate
+            }
+        };
+        Ok(Self {
+            predicate: predicate.fill_null(lit(false)),
+        })
+    }
+}
+
+/// Check a project's goals: named, uniquely, and each compiling.
+pub fn validate_goals(goals: &[Goal]) -> std::result::Result<(), String> {
+    if goals.len() > MAX_GOALS {
+        return Err(format!("A project may have at most {MAX_GOALS} goals."));
+    }
+    let mut names: Vec<String> = Vec::with_capacity(goals.len());
+    for goal in goals {
+        let name = goal.name.trim();
+        if name.is_empty() {
+            return Err("Every goal needs a name.".into());
+        }
+        if names.contains(&name.to_lowercase()) {
+            return Err(format!("There is more than one goal named \"{name}\"."));
+        }
+        names.push(name.to_lowercase());
+        CompiledGoal::new(goal)?;
+    }
+    Ok(())
+}
+
+/// A regex matching one `key: value` entry of an event's stored metadata.
+/// Metadata is stored as a compact JSON object of strings, in which quotes
+/// inside keys and values are escaped, so an entry appears verbatim between
+/// the object's delimiters.
+fn metadata_entry(key: &str, value: &str) -> String {
+    let json = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    format!(
+        "[{{,]{}:{}[,}}]",
+        regex::escape(&json(key)),
+        regex::escape(&json(value))
+    )
+}
+
+/// The conversions of every goal of the projects with sessions in `current`,
+/// attaching each goal's conversions to the page-view breakdown rows
+/// (index-aligned with the returned summaries).
+pub(super) fn attach_goals(
+    store: &Store,
+    breakdowns: &mut Breakdowns,
+    current: LazyFrame,
+) -> Result<Vec<GoalSummary>> {
+    let projects: Vec<_> = store
+        .list_projects()?
+        .into_iter()
+        .filter(|p| !p.goals.is_empty())
+        .collect();
+    if projects.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // `(project index, goal, compiled)` for every goal that compiles (all of
+    // them, unless a stored goal predates a validation rule).
+    let mut goals = Vec::new();
+    let mut in_project = Vec::with_capacity(projects.len());
+    for (p, project) in projects.iter().enumerate() {
+        in_project.push(source_filter(&project_source_uris(store, &project.id)?));
+        for goal in &project.goals {
+            if let Ok(compiled) = CompiledGoal::new(goal) {
+                goals.push((p, goal, compiled));
+            }
+        }
+    }
+
+    // One row per session: which projects it belongs to, which goals it met.
+    let sessions = current.filter(col("sid").is_not_null());
+    let mut aggs: Vec<Expr> = in_project
+        .iter()
+        .enumerate()
+        .map(|(p, expr)| expr.clone().sum().gt(lit(0)).alias(format!("p{p}")))
+        .collect();
+    for (i, (p, _, compiled)) in goals.iter().enumerate() {
+        aggs.push(
+            in_project[*p]
+                .clone()
+                .and(compiled.predicate.clone())
+                .sum()
+                .gt(lit(0))
+                .alias(format!("g{i}")),
+        );
+    }
+    let per_session = sessions
+        .clone()
+        .group_by([col("sid")])
+        .agg(aggs)
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let sid = per_session
+        .column("sid")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let flagged = |name: String| -> Result<Vec<String>> {
+        let flags = per_session
+            .column(&name)
+            .or_system_err(ADVICE)?
+            .bool()
+            .or_system_err(ADVICE)?;
+        Ok((0..per_session.height())
+            .filter(|&i| flags.get(i).unwrap_or(false))
+            .filter_map(|i| sid.get(i).map(str::to_string))
+            .collect())
+    };
+    let project_sessions = (0..projects.len())
+        .map(|p| Ok(flagged(format!("p{p}"))?.len() as i64))
+        .collect::<Result<Vec<i64>>>()?;
+
+    // Goals of projects with no sessions in view are left out.
+    let mut summaries = Vec::new();
+    let mut converted = Vec::new();
+    for (i, (p, goal, _)) in goals.iter().enumerate() {
+        if project_sessions[*p] == 0 {
+            continue;
+        }
+        let sids = flagged(format!("g{i}"))?;
+        summaries.push(GoalSummary {
+            project_id: projects[*p].id.clone(),
+            project_name: projects[*p].name.clone(),
+            goal: goal.name.clone(),
+            conversions: GoalConversions::new(sids.len() as i64, project_sessions[*p]),
+        });
+        converted.push((*p, sids));
+    }
+    if summaries.is_empty() {
+        return Ok(summaries);
+    }
+
+    let mut flags: Vec<Expr> = in_project
+        .iter()
+        .enumerate()
+        .map(|(p, expr)| expr.clone().alias(format!("p{p}")))
+        .collect();
+    for (i, (_, sids)) in converted.iter().enumerate() {
+        flags.push(
+            col("sid")
+                .is_in(
+                    lit(Series::new("sids".into(), sids.clone())).implode(false),
+                    false,
+                )
+                .alias(format!("c{i}")),
+        );
+    }
+    let pageloads = sessions
+        .filter(col("kind").eq(lit("page_load")))
+        .with_columns(flags)
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let panels: [(&mut Vec<BreakdownRow>, &str); 12] = [
+        (&mut breakdowns.pages, "pathname"),
+        (&mut breakdowns.referrers, "referrer_host"),
+        (&mut breakdowns.countries, "country"),
+        (&mut breakdowns.languages, "language"),
+        (&mut breakdowns.browsers, "ua_browser"),
+        (&mut breakdowns.operating_systems, "ua_os"),
+        (&mut breakdowns.devices, "ua_device"),
+        (&mut breakdowns.utm_sources, "utm_source"),
+        (&mut breakdowns.utm_mediums, "utm_medium"),
+        (&mut breakdowns.utm_campaigns, "utm_campaign"),
+        (&mut breakdowns.channels, "channel"),
+        (&mut breakdowns.sources, "source"),
+    ];
+    for (rows, column) in panels {
+        let by_key = conversions_by(pageloads.clone().lazy(), column, &converted)?;
+        for row in rows.iter_mut() {
+            row.goals = by_key
+                .get(&row.key)
+                .cloned()
+                .unwrap_or_else(|| vec![GoalConversions::default(); converted.len()]);
+        }
+    }
+    Ok(summaries)
+}
+
+/// Per value of `column`, each goal's converting sessions out of the sessions
+/// of its project with page views under that value.
+fn conversions_by(
+    pageloads: LazyFrame,
+    column: &str,
+    converted: &[(usize, Vec<String>)],
+) -> Result<HashMap<String, Vec<GoalConversions>>> {
+    let mut aggs = Vec::new();
+    for (i, (p, _)) in converted.iter().enumerate() {
+        aggs.push(
+            col("sid")
+                .filter(col(format!("p{p}")))
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias(format!("s{i}")),
+        );
+        aggs.push(
+            col("sid")
+                .filter(col(format!("c{i}")))
+                .n_unique()
+                .cast(DataType::Int64)
+                .alias(format!("c{i}")),
+        );
+    }
+    let df = pageloads
+        .with_columns([col(column).fill_null(lit("")).alias("key")])
+        .group_by([col("key")])
+        .agg(aggs)
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let keys = df
+        .column("key")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let counts = (0..converted.len())
+        .map(|i| {
+            let column = |name: String| -> Result<Int64Chunked> {
+                Ok(df
+                    .column(&name)
+                    .or_system_err(ADVICE)?
+                    .i64()
+                    .or_system_err(ADVICE)?
+                    .clone())
+            };
+            Ok((column(format!("c{i}"))?, column(format!("s{i}"))?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((0..df.height())
+        .filter_map(|row| {
+            let key = keys.get(row)?.to_string();
+            let goals = counts
+                .iter()
+                .map(|(conversions, sessions)| {
+                    GoalConversions::new(
+                        conversions.get(row).unwrap_or(0),
+                        sessions.get(row).unwrap_or(0),
+                    )
+                })
+                .collect();
+            Some((key, goals))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use analytics_api::{PathPatternKind, Project, Source, SourceKind};
+    use chrono::Utc;
+
+    use crate::store::{EventKind, StoredEvent};
+
+    #[test]
+    fn metadata_entries_match_whole_values_only() {
+        let entry = Regex::new(&metadata_entry("plan", "pro")).unwrap();
+        assert!(entry.is_match(r#"{"plan":"pro"}"#));
+        assert!(entry.is_match(r#"{"a":"b","plan":"pro","z":"y"}"#));
+        assert!(!entry.is_match(r#"{"plan":"professional"}"#));
+        assert!(!entry.is_match(r#"{"note":"x\",\"plan\":\"pro"}"#));
+    }
+
+    #[test]
+    fn goals_must_be_named_uniquely_and_compile() {
+        let path = |name: &str, pattern: &str| Goal {
+            name: name.into(),
+            target: GoalTarget::Path {
+                kind: PathPatternKind::Regex,
+                pattern: pattern.into(),
+            },
+        };
+        assert!(validate_goals(&[path("Thanks", "/thank-you")]).is_ok());
+        assert!(validate_goals(&[path(" ", "/thank-you")]).is_err());
+        assert!(validate_goals(&[path("Thanks", "/a"), path("thanks", "/b")]).is_err());
+        assert!(validate_goals(&[path("Thanks", "(")]).is_err());
+    }
+
+    fn view(sid: &str, received_ms: i64, source: &str, path: &str, referrer: &str) -> StoredEvent {
+        StoredEvent {
+            created_ms: received_ms,
+            received_ms,
+            bid: "b".into(),
+            sid: Some(sid.into()),
+            kind: EventKind::PageLoad,
+            source: source.into(),
+            pathname: Some(path.into()),
+            referrer_host: Some(referrer.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dashboard_reports_goal_conversions_overall_and_per_row() {
+        let redb = std::env::temp_dir().join(format!(
+            "analytics-goals-{}-{}.redb",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let store = Store::open(&redb).unwrap();
+        let now = Utc::now();
+        store
+            .put_project(&Project {
+                id: "p1".into(),
+                name: "Site".into(),
+                slug: "site".into(),
+                created_at: now,
+                path_rules: Vec::new(),
+                keep_original_path: false,
+                fingerprint_rules: Vec::new(),
+                exception_sampling: Default::default(),
+                inbound_filters: Default::default(),
+                goals: vec![
+                    Goal {
+                        name: "Thanks".into(),
+                        target: GoalTarget::Path {
+                            kind: PathPatternKind::Glob,
+                            pattern: "/thanks".into(),
+                        },
+                    },
+                    Goal {
+                        name: "Pro signup".into(),
+                        target: GoalTarget::Event {
+                            name: "signup".into(),
+                            metadata: [("plan".to_string(), "pro".to_string())].into(),
+                        },
+                    },
+                ],
+            })
+            .unwrap();
+        store
+            .put_source(&Source {
+                uri: "https://a.com".into(),
+                project_id: Some("p1".into()),
+                kind: SourceKind::Website,
+                display_name: None,
+                created_at: now,
+                first_seen: Some(now),
+                last_seen: Some(now),
+                aliases: Vec::new(),
+            })
+            .unwrap();
+        store
+            .append_events(&[
+                view("s1", 1_000, "https://a.com", "/", "google.com"),
+                view("s1", 2_000, "https://a.com", "/thanks", "google.com"),
+                StoredEvent {
+                    created_ms: 3_000,
+                    received_ms: 3_000,
+                    sid: Some("s1".into()),
+                    kind: EventKind::Custom,
+                    source: "https://a.com".into(),
+                    event_name: Some("signup".into()),
+                    metadata_json: Some(r#"{"plan":"pro"}"#.into()),
+                    ..Default::default()
+                },
+                view("s2", 1_000, "https://a.com", "/", "bing.com"),
+                // Another project's session converts on nothing.
+                view("s3", 1_000, "https://b.com", "/thanks", "google.com"),
+            ])
+            .unwrap();
+
+        let dash = super::super::dashboard(&store, "/none", None, 0, 10_000, 86_400_000).unwrap();
+        let overall: Vec<(&str, i64, i64)> = dash
+            .goals
+            .iter()
+            .map(|g| {
+                (
+                    g.goal.as_str(),
+                    g.conversions.conversions,
+                    g.conversions.sessions,
+                )
+            })
+            .collect();
+        assert_eq!(overall, vec![("Thanks", 1, 2), ("Pro signup", 1, 2)]);
+
+        let referrers = &dash.breakdowns.referrers;
+        let google = referrers.iter().find(|r| r.key == "google.com").unwrap();
+        let bing = referrers.iter().find(|r| r.key == "bing.com").unwrap();
+        assert_eq!(google.goals[0], GoalConversions::new(1, 1));
+        assert_eq!(bing.goals[0], GoalConversions::new(0, 1));
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+}
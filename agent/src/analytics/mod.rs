@@ -3,24 +3,29 @@
 //! expression, and bounded to a half-open `[from, to)` time range. Queries are
 //! CPU-bound and synchronous, so handlers run them via `web::block`.
 
+pub mod exceptions;
 pub mod filter;
+pub mod flow;
+pub mod funnel;
+pub mod goals;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use analytics_api::{
-    BreakdownRow, Breakdowns, CountRow, Dashboard, EventBreakdowns, EventDetail, EventVariant,
-    ExceptionBreakdowns, ExceptionGroup, ExceptionGroupDetail, ExceptionStatus, ExceptionVariant,
-    MetricSummary, SessionTrace, TREND_BUCKETS, TimeSeriesPoint, TraceEvent, TraceEventKind,
-    TraceSummary, VersionRow, pixel_source, source_label, summary_line,
+    BreakdownRow, Breakdowns, CountRow, Dashboard, EventBreakdowns, EventDetail,
+    EventValueBreakdowns, EventVariant, MetricSummary, ReleaseMarker, SCROLL_MARKS, ScrollDepth,
+    SessionTrace, TREND_BUCKETS, TimeSeriesPoint, TraceEvent, TraceEventKind, TraceSummary,
+    ValueRow, ValueSummary, VersionRow, pixel_source,
 };
 use chrono::{Datelike, TimeZone, Utc};
 use polars::prelude::*;
 use tracing_batteries::prelude::warn;
 
 use crate::errors::{Result, ResultExt};
-use crate::store::Store;
+use crate::store::{GroupRedirect, Store};
 
+use exceptions::occurrence_weight;
 use filter::CompiledFilter;
 
 const ADVICE: &[&str] = &["This is an internal analytics error; please report it with the logs."];
@@ -92,30 +97,41 @@ pub fn dashboard(
     let per_source = source_rollup(current.clone(), unique_flag)?;
     let (projects, sources, unassigned) = project_rollup(store, per_source)?;
     let traces = recent_traces(current.clone(), TRACE_SAMPLE)?;
+    let releases = release_markers(store, current.clone(), from_ms, to_ms)?;
+    let valued = current.clone().filter(col("value").is_not_null());
+
+    let headline = summary(current.clone(), unique_flag)?;
+    let mut breakdowns = Breakdowns {
+        pages: breakdown(pageloads.clone(), "pathname", "is_unique_page")?,
+        referrers: breakdown(pageloads.clone(), "referrer_host", unique_flag)?,
+        countries: breakdown(pageloads.clone(), "country", unique_flag)?,
+        languages: breakdown(pageloads.clone(), "language", unique_flag)?,
+        browsers: breakdown(pageloads.clone(), "ua_browser", unique_flag)?,
+        versions: version_breakdown(pageloads.clone(), unique_flag)?,
+        operating_systems: breakdown(pageloads.clone(), "ua_os", unique_flag)?,
+        devices: breakdown(pageloads.clone(), "ua_device", unique_flag)?,
+        utm_sources: breakdown(pageloads.clone(), "utm_source", unique_flag)?,
+        utm_mediums: breakdown(pageloads.clone(), "utm_medium", unique_flag)?,
+        utm_campaigns: breakdown(pageloads.clone(), "utm_campaign", unique_flag)?,
+        channels: breakdown(pageloads, "channel", unique_flag)?,
+        event_names,
+        projects,
+        sources,
+    };
+    attach_values(&mut breakdowns, valued, headline.visitors)?;
+    attach_scroll_depth(&mut breakdowns.pages, current.clone())?;
+    let goals = goals::attach_goals(store, &mut breakdowns, current.clone())?;
 
     Ok(Dashboard {
-        summary: summary(current.clone(), unique_flag)?,
+        summary: headline,
         previous_summary: summary(previous, unique_flag)?,
         timeseries: timeseries(current, from_ms, to_ms, bucket_ms, unique_flag)?,
         previous_timeseries,
-        breakdowns: Breakdowns {
-            pages: breakdown(pageloads.clone(), "pathname", "is_unique_page")?,
-            referrers: breakdown(pageloads.clone(), "referrer_host", unique_flag)?,
-            countries: breakdown(pageloads.clone(), "country", unique_flag)?,
-            languages: breakdown(pageloads.clone(), "language", unique_flag)?,
-            browsers: breakdown(pageloads.clone(), "ua_browser", unique_flag)?,
-            versions: version_breakdown(pageloads.clone(), unique_flag)?,
-            operating_systems: breakdown(pageloads.clone(), "ua_os", unique_flag)?,
-            devices: breakdown(pageloads.clone(), "ua_device", unique_flag)?,
-            utm_sources: breakdown(pageloads.clone(), "utm_source", unique_flag)?,
-            utm_mediums: breakdown(pageloads.clone(), "utm_medium", unique_flag)?,
-            utm_campaigns: breakdown(pageloads, "utm_campaign", unique_flag)?,
-            event_names,
-            projects,
-            sources,
-        },
+        breakdowns,
         unassigned,
         traces,
+        releases,
+        goals,
     })
 }
 
@@ -154,214 +170,52 @@ pub fn project_source_uris(store: &Store, project_id: &str) -> Result<Vec<String
     Ok(uris)
 }
 
-/// Exception groups matching the compiled filter, grouped by
-/// `(fingerprint, source)` with a [`TREND_BUCKETS`]-bucket occurrence trend
-/// each. A fingerprint is computed from the error alone, so the same
-/// `exc_group` legitimately occurs on multiple sources/projects; keeping the
-/// source in the key keeps those occurrences separate. The caller folds
-/// per-source rows up to per-project rows (summing trends element-wise) for
-/// the global Exceptions inbox.
-pub fn exception_groups_by_source(
+/// The most-viewed pathnames of `sources` over `[from, to)` with their page
+/// views, as reported where the original was kept — the sample a path rule is
+/// previewed against before it is saved.
+pub fn recent_pathnames(
     store: &Store,
     parquet_dir: &str,
+    sources: &[String],
     from_ms: i64,
     to_ms: i64,
-    filter: Option<&CompiledFilter>,
-) -> Result<Vec<(ExceptionGroup, String)>> {
-    let mut lf = combined(store, parquet_dir, from_ms, to_ms)?
-        .filter(col("kind").eq(lit("exception")))
-        .filter(col("exc_group").is_not_null());
-    if let Some(filter) = filter {
-        lf = lf.filter(filter.predicate.clone());
-    }
-    let df = lf
-        .group_by([col("exc_group"), col("source")])
-        .agg([
-            len().cast(DataType::Int64).alias("count"),
-            col("received_ms")
-                .min()
-                .cast(DataType::Int64)
-                .alias("first_seen"),
-            col("received_ms")
-                .max()
-                .cast(DataType::Int64)
-                .alias("last_seen"),
-            col("exc_type").first().alias("exc_type"),
-            col("exc_message").first().alias("sample_message"),
-            col("received_ms").alias("times"),
-        ])
+    limit: u32,
+) -> Result<Vec<(String, i64)>> {
+    let df = combined(store, parquet_dir, from_ms, to_ms)?
+        .filter(
+            source_filter(sources)
+                .and(col("kind").eq(lit("page_load")))
+                .and(col("pathname").is_not_null()),
+        )
+        .select([col("original_pathname")
+            .cast(DataType::String)
+            .fill_null(col("pathname"))
+            .alias("path")])
+        .group_by([col("path")])
+        .agg([len().cast(DataType::Int64).alias("count")])
         .sort(
-            ["last_seen"],
+            ["count"],
             SortMultipleOptions::default().with_order_descending(true),
         )
-        .limit(500)
+        .limit(limit)
         .collect()
         .or_system_err(ADVICE)?;
 
-    let group_id = df
-        .column("exc_group")
+    let paths = df
+        .column("path")
         .or_system_err(ADVICE)?
         .str()
         .or_system_err(ADVICE)?;
-    let count = df
+    let counts = df
         .column("count")
         .or_system_err(ADVICE)?
         .i64()
         .or_system_err(ADVICE)?;
-    let first = df
-        .column("first_seen")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
-    let last = df
-        .column("last_seen")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
-    let exc_type = df
-        .column("exc_type")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let message = df
-        .column("sample_message")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let source = df
-        .column("source")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let times = df
-        .column("times")
-        .or_system_err(ADVICE)?
-        .list()
-        .or_system_err(ADVICE)?;
-
     Ok((0..df.height())
-        .filter_map(|i| {
-            group_id.get(i).map(|gid| {
-                (
-                    ExceptionGroup {
-                        group_id: gid.to_string(),
-                        exc_type: exc_type.get(i).unwrap_or("").to_string(),
-                        sample_message: summary_line(message.get(i).unwrap_or("")).to_string(),
-                        count: count.get(i).unwrap_or(0),
-                        first_seen_ms: first.get(i).unwrap_or(0),
-                        last_seen_ms: last.get(i).unwrap_or(0),
-                        status: ExceptionStatus::Unresolved,
-                        resolved: false,
-                        muted: false,
-                        note: None,
-                        trend: trend_of(list_i64(times, i).into_iter(), from_ms, to_ms),
-                    },
-                    source.get(i).unwrap_or("").to_string(),
-                )
-            })
-        })
+        .filter_map(|i| Some((paths.get(i)?.to_string(), counts.get(i).unwrap_or(0))))
         .collect())
 }
 
-/// A single exception group in forensic detail: the aggregate (with trend),
-/// how its occurrences distribute across key dimensions, and its **distinct
-/// variants** — occurrences collapsed by (message, stack, handledness) so an
-/// operator scrubs through genuinely different examples rather than paging
-/// hundreds of identical ones. Derived from one scan filtered to the group;
-/// looked up by id directly (no top-N cap), so a linked or bookmarked group
-/// opens regardless of how many fingerprints a project has. Returns `None` if
-/// the group has no occurrences in `[from_ms, to_ms)`.
-pub fn exception_detail(
-    store: &Store,
-    parquet_dir: &str,
-    sources: &[String],
-    group_id: &str,
-    from_ms: i64,
-    to_ms: i64,
-    limit: usize,
-) -> Result<Option<ExceptionGroupDetail>> {
-    let df = combined(store, parquet_dir, from_ms, to_ms)?
-        .filter(source_filter(sources))
-        .filter(col("kind").eq(lit("exception")))
-        .filter(col("exc_group").eq(lit(group_id.to_string())))
-        .select([
-            col("exc_type"),
-            col("exc_message"),
-            col("exc_stack"),
-            col("exc_handled"),
-            col("received_ms")
-                .cast(DataType::Int64)
-                .alias("received_ms"),
-            col("ua_browser"),
-            col("ua_os"),
-            col("ua_device"),
-            col("app_version"),
-            col("source"),
-            col("metadata_json"),
-            col("sid"),
-        ])
-        .sort(
-            ["received_ms"],
-            SortMultipleOptions::default().with_order_descending(true),
-        )
-        .collect()
-        .or_system_err(ADVICE)?;
-
-    let height = df.height();
-    if height == 0 {
-        return Ok(None);
-    }
-
-    let exc_type = df
-        .column("exc_type")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let message = df
-        .column("exc_message")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let received = df
-        .column("received_ms")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
-
-    // Rows are newest-first: index 0 is the most recent occurrence, the last
-    // index the oldest. The aggregate spans every row.
-    let group = ExceptionGroup {
-        group_id: group_id.to_string(),
-        exc_type: exc_type.get(0).unwrap_or("").to_string(),
-        sample_message: summary_line(message.get(0).unwrap_or("")).to_string(),
-        count: height as i64,
-        first_seen_ms: received.get(height - 1).unwrap_or(0),
-        last_seen_ms: received.get(0).unwrap_or(0),
-        status: ExceptionStatus::Unresolved,
-        resolved: false,
-        muted: false,
-        note: None,
-        trend: trend_of((0..height).filter_map(|i| received.get(i)), from_ms, to_ms),
-    };
-
-    let breakdowns = ExceptionBreakdowns {
-        app_versions: app_version_rows(&df)?,
-        browsers: count_by(&df, "ua_browser")?,
-        operating_systems: count_by(&df, "ua_os")?,
-        devices: count_by(&df, "ua_device")?,
-    };
-    let variants = variants_of(&df, limit)?;
-
-    let traces = traces_of_occurrences(store, parquet_dir, &df, from_ms, to_ms)?;
-
-    Ok(Some(ExceptionGroupDetail {
-        group,
-        breakdowns,
-        variants,
-        traces,
-    }))
-}
-
 /// One named custom/pixel event in forensic detail: the aggregate (with
 /// trend), how its occurrences distribute across key dimensions, its
 /// **distinct metadata variants** (one representative per unique reporter
@@ -398,6 +252,7 @@ pub fn event_detail(
             col("ua_device"),
             col("metadata_json"),
             col("sid"),
+            occurrence_weight().alias("occurrences"),
         ])
         .sort(
             ["received_ms"],
@@ -429,6 +284,43 @@ pub fn event_detail(
     let variants = event_variants(&df, limit)?;
     let traces = traces_of_occurrences(store, parquet_dir, &df, from_ms, to_ms)?;
 
+    // Value aggregates, over only the occurrences that reported one.
+    let valued = df.clone().lazy().filter(col("value").is_not_null());
+    let totals = valued
+        .clone()
+        .select(value_aggs())
+        .collect()
+        .or_system_err(ADVICE)?;
+    let value = ValueColumns::of(&totals)?.get(0);
+    let value_trend = if value.is_some() {
+        let values = df
+            .column("value")
+            .or_system_err(ADVICE)?
+            .f64()
+            .or_system_err(ADVICE)?;
+        value_trend_of(
+            (0..height).filter_map(|i| Some((received.get(i)?, values.get(i)?))),
+            from_ms,
+            to_ms,
+        )
+    } else {
+        Vec::new()
+    };
+    let value_rows_by = |column: &str| -> Result<Vec<ValueRow>> {
+        let mut rows = value_rows(valued.clone(), column)?;
+        rows.truncate(BREAKDOWN_LIMIT as usize);
+        Ok(rows)
+    };
+    let value_breakdowns = EventValueBreakdowns {
+        sources: value_rows_by("source")?,
+        pages: value_rows_by("pathname")?,
+        browsers: value_rows_by("ua_browser")?,
+        operating_systems: value_rows_by("ua_os")?,
+        devices: value_rows_by("ua_device")?,
+        countries: value_rows_by("country")?,
+        languages: value_rows_by("language")?,
+    };
+
     Ok(Some(EventDetail {
         name: name.to_string(),
         count: height as i64,
@@ -436,6 +328,9 @@ pub fn event_detail(
         last_seen_ms: received.get(0).unwrap_or(0),
         trend: trend_of((0..height).filter_map(|i| received.get(i)), from_ms, to_ms),
         breakdowns,
+        value,
+        value_trend,
+        value_breakdowns,
         variants,
         traces,
     }))
@@ -575,7 +470,7 @@ fn count_by(occurrences: &DataFrame, column: &str) -> Result<Vec<CountRow>> {
         .lazy()
         .with_columns([col(column).fill_null(lit("")).alias("key")])
         .group_by([col("key")])
-        .agg([len().cast(DataType::Int64).alias("count")])
+        .agg([col("occurrences").sum().alias("count")])
         .sort(
             ["count"],
             SortMultipleOptions::default().with_order_descending(true),
@@ -604,192 +499,185 @@ fn count_by(occurrences: &DataFrame, column: &str) -> Result<Vec<CountRow>> {
         .collect())
 }
 
-/// Occurrence counts per reported release. When the frame spans several
-/// sources, rows are keyed as `app @ version` (the app being the source's
-/// label) — a release number is only meaningful within its application. A
-/// frame scoped to a single source (the per-source detail view) keys rows by
-/// the bare version number, since the application is given. Occurrences with
-/// no reported version aggregate under the empty sentinel, whichever source
-/// they came from.
-fn app_version_rows(occurrences: &DataFrame) -> Result<Vec<CountRow>> {
-    let df = occurrences
-        .clone()
-        .lazy()
-        .with_columns([
-            col("source").fill_null(lit("")).alias("app"),
-            col("app_version").fill_null(lit("")).alias("version"),
-        ])
-        .group_by([col("app"), col("version")])
-        .agg([len().cast(DataType::Int64).alias("count")])
-        .collect()
-        .or_system_err(ADVICE)?;
+/// The aggregations behind a [`ValueSummary`] (read back by [`ValueColumns`]):
+/// the sum and count of reported values, the distinct sessions that reported
+/// them, and the currencies qualifying them.
+fn value_aggs() -> [Expr; 5] {
+    let currency = col("currency")
+        .filter(col("value").is_not_null())
+        .drop_nulls();
+    [
+        col("value")
+            .sum()
+            .cast(DataType::Float64)
+            .alias("value_total"),
+        col("value")
+            .count()
+            .cast(DataType::Int64)
+            .alias("value_count"),
+        col("sid")
+            .filter(col("value").is_not_null())
+            .drop_nulls()
+            .n_unique()
+            .cast(DataType::Int64)
+            .alias("value_sessions"),
+        currency
+            .clone()
+            .n_unique()
+            .cast(DataType::Int64)
+            .alias("value_currencies"),
+        currency
+            .first()
+            .cast(DataType::String)
+            .alias("value_currency"),
+    ]
+}
 
-    let apps = df
-        .column("app")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let versions = df
-        .column("version")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let counts = df
-        .column("count")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
+/// Typed access to the [`value_aggs`] columns of an aggregated frame.
+struct ValueColumns<'a> {
+    total: &'a Float64Chunked,
+    count: &'a Int64Chunked,
+    sessions: &'a Int64Chunked,
+    currencies: &'a Int64Chunked,
+    currency: &'a StringChunked,
+}
 
-    // Qualify versions with their application only when the frame genuinely
-    // mixes applications; labels are compared (not URIs) since distinct
-    // sources can share one (http vs https).
-    let mut labels: Vec<&str> = (0..df.height())
-        .filter_map(|i| apps.get(i))
-        .filter(|app| !app.is_empty())
-        .map(source_label)
-        .collect();
-    labels.sort_unstable();
-    labels.dedup();
-    let qualify = labels.len() > 1;
+impl<'a> ValueColumns<'a> {
+    fn of(df: &'a DataFrame) -> Result<Self> {
+        Ok(Self {
+            total: df
+                .column("value_total")
+                .or_system_err(ADVICE)?
+                .f64()
+                .or_system_err(ADVICE)?,
+            count: df
+                .column("value_count")
+                .or_system_err(ADVICE)?
+                .i64()
+                .or_system_err(ADVICE)?,
+            sessions: df
+                .column("value_sessions")
+                .or_system_err(ADVICE)?
+                .i64()
+                .or_system_err(ADVICE)?,
+            currencies: df
+                .column("value_currencies")
+                .or_system_err(ADVICE)?
+                .i64()
+                .or_system_err(ADVICE)?,
+            currency: df
+                .column("value_currency")
+                .or_system_err(ADVICE)?
+                .str()
+                .or_system_err(ADVICE)?,
+        })
+    }
 
-    // Fold by the display key rather than trusting the group-by to have
-    // finished the job (see the label-sharing note above).
-    let mut totals: HashMap<String, i64> = HashMap::new();
-    for i in 0..df.height() {
-        let (Some(app), Some(version)) = (apps.get(i), versions.get(i)) else {
-            continue;
-        };
-        let key = match (app.is_empty(), version.is_empty()) {
-            (_, true) => String::new(),
-            (true, false) => version.to_string(),
-            (false, false) if qualify => format!("{} @ {version}", source_label(app)),
-            (false, false) => version.to_string(),
-        };
-        *totals.entry(key).or_insert(0) += counts.get(i).unwrap_or(0);
+    /// Row `i` as a [`ValueSummary`] — `None` when no event in it reported a
+    /// value — with `per_visitor` measured against the sessions that did.
+    fn get(&self, i: usize) -> Option<ValueSummary> {
+        let count = self.count.get(i).unwrap_or(0);
+        if count == 0 {
+            return None;
+        }
+        let total = self.total.get(i).unwrap_or(0.0);
+        let sessions = self.sessions.get(i).unwrap_or(0);
+        Some(ValueSummary {
+            total,
+            count,
+            average: total / count as f64,
+            per_visitor: (sessions > 0).then(|| total / sessions as f64),
+            // A sum across currencies is meaningless as money; report it bare.
+            currency: (self.currencies.get(i) == Some(1))
+                .then(|| self.currency.get(i).map(str::to_string))
+                .flatten(),
+        })
     }
-    let mut rows: Vec<CountRow> = totals
-        .into_iter()
-        .map(|(key, count)| CountRow { key, count })
-        .collect();
-    rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
-    rows.truncate(BREAKDOWN_LIMIT as usize);
-    Ok(rows)
 }
 
-/// Collapse a group's occurrences (already sorted newest-first) into distinct
-/// variants keyed by (message, stack, handledness): one representative each,
-/// counted, most frequent first. The representative context (client, source,
-/// version, reporter metadata) comes from the variant's latest occurrence.
-fn variants_of(occurrences: &DataFrame, limit: usize) -> Result<Vec<ExceptionVariant>> {
-    let df = occurrences
-        .clone()
-        .lazy()
-        .group_by([col("exc_message"), col("exc_stack"), col("exc_handled")])
-        .agg([
-            len().cast(DataType::Int64).alias("count"),
-            col("received_ms").min().alias("first_seen"),
-            col("received_ms").max().alias("last_seen"),
-            // The frame is newest-first, so `first()` is the latest context.
-            col("ua_browser").first().alias("ua_browser"),
-            col("ua_os").first().alias("ua_os"),
-            col("source").first().alias("source"),
-            col("app_version").first().alias("app_version"),
-            // Metadata is optional per report; surface the latest occurrence
-            // that actually carried some.
-            col("metadata_json")
-                .drop_nulls()
-                .first()
-                .alias("metadata_json"),
-            // Likewise the session link: the latest occurrence that has one.
-            col("sid").drop_nulls().first().alias("sid"),
-        ])
+/// Re-measure `value.per_visitor` against `visitors` (the dashboard's
+/// daily-unique visitor counts rather than the reporting sessions).
+fn per_visitor(mut value: ValueSummary, visitors: i64) -> ValueSummary {
+    value.per_visitor = (visitors > 0).then(|| value.total / visitors as f64);
+    value
+}
+
+/// Value aggregates per value of `column` over the valued events (nulls under
+/// the empty-string sentinel), largest total first.
+fn value_rows(valued: LazyFrame, column: &str) -> Result<Vec<ValueRow>> {
+    let df = valued
+        .with_columns([col(column).fill_null(lit("")).alias("key")])
+        .group_by([col("key")])
+        .agg(value_aggs())
         .sort(
-            ["count"],
+            ["value_total"],
             SortMultipleOptions::default().with_order_descending(true),
         )
-        .limit(limit as u32)
         .collect()
         .or_system_err(ADVICE)?;
 
-    let message = df
-        .column("exc_message")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let stack = df
-        .column("exc_stack")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let handled = df
-        .column("exc_handled")
-        .or_system_err(ADVICE)?
-        .bool()
-        .or_system_err(ADVICE)?;
-    let count = df
-        .column("count")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
-    let first = df
-        .column("first_seen")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
-    let last = df
-        .column("last_seen")
-        .or_system_err(ADVICE)?
-        .i64()
-        .or_system_err(ADVICE)?;
-    let browser = df
-        .column("ua_browser")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let os = df
-        .column("ua_os")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let source = df
-        .column("source")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let version = df
-        .column("app_version")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let metadata = df
-        .column("metadata_json")
-        .or_system_err(ADVICE)?
-        .str()
-        .or_system_err(ADVICE)?;
-    let sid = df
-        .column("sid")
+    let keys = df
+        .column("key")
         .or_system_err(ADVICE)?
         .str()
         .or_system_err(ADVICE)?;
-
+    let values = ValueColumns::of(&df)?;
     Ok((0..df.height())
-        .map(|i| ExceptionVariant {
-            message: message.get(i).unwrap_or("").to_string(),
-            stack: stack.get(i).map(str::to_string),
-            handled: handled.get(i).unwrap_or(false),
-            count: count.get(i).unwrap_or(0),
-            first_seen_ms: first.get(i).unwrap_or(0),
-            last_seen_ms: last.get(i).unwrap_or(0),
-            ua_browser: browser.get(i).map(str::to_string),
-            ua_os: os.get(i).map(str::to_string),
-            source: source.get(i).map(str::to_string),
-            app_version: version.get(i).map(str::to_string),
-            metadata: metadata.get(i).map(str::to_string),
-            session_id: sid.get(i).map(str::to_string),
+        .filter_map(|i| {
+            Some(ValueRow {
+                key: keys.get(i)?.to_string(),
+                value: values.get(i)?,
+            })
         })
         .collect())
 }
 
+/// Annotate the dashboard breakdowns with the values custom events reported
+/// under each key. Values ride on custom events rather than page loads, so
+/// they aggregate separately and join onto the panel rows by key; event-name
+/// rows carry no visitors of their own, so theirs are measured against the
+/// whole slice's `visitors`.
+fn attach_values(breakdowns: &mut Breakdowns, valued: LazyFrame, visitors: i64) -> Result<()> {
+    // One collect serves every panel; the valued frame is typically tiny.
+    let valued = valued.collect().or_system_err(ADVICE)?;
+    if valued.height() == 0 {
+        return Ok(());
+    }
+
+    let panels: [(&mut Vec<BreakdownRow>, &str); 13] = [
+        (&mut breakdowns.pages, "pathname"),
+        (&mut breakdowns.referrers, "referrer_host"),
+        (&mut breakdowns.countries, "country"),
+        (&mut breakdowns.languages, "language"),
+        (&mut breakdowns.browsers, "ua_browser"),
+        (&mut breakdowns.operating_systems, "ua_os"),
+        (&mut breakdowns.devices, "ua_device"),
+        (&mut breakdowns.utm_sources, "utm_source"),
+        (&mut breakdowns.utm_mediums, "utm_medium"),
+        (&mut breakdowns.utm_campaigns, "utm_campaign"),
+        (&mut breakdowns.channels, "channel"),
+        (&mut breakdowns.event_names, "event_name"),
+        (&mut breakdowns.sources, "source"),
+    ];
+    for (rows, column) in panels {
+        let mut values: HashMap<String, ValueSummary> = value_rows(valued.clone().lazy(), column)?
+            .into_iter()
+            .map(|row| (row.key, row.value))
+            .collect();
+        for row in rows.iter_mut() {
+            let against = if column == "event_name" {
+                visitors
+            } else {
+                row.visitors
+            };
+            row.value = values
+                .remove(&row.key)
+                .map(|value| per_visitor(value, against));
+        }
+    }
+    Ok(())
+}
+
 /// The most recent sessions in the (already filtered) event frame, one summary
 /// row each, newest first. Events are grouped by the tracker's per-visit
 /// session id; events without one (pixel hits, pre-session trackers) never
@@ -819,7 +707,7 @@ fn recent_traces(base: LazyFrame, limit: u32) -> Result<Vec<TraceSummary>> {
             col("ua_browser").drop_nulls().first().alias("ua_browser"),
             col("ua_version").drop_nulls().first().alias("ua_version"),
             col("ua_device").drop_nulls().first().alias("ua_device"),
-            // Exception reports carry the app's claimed release.
+            // Versioned page loads and exception reports carry the app's release.
             col("app_version").drop_nulls().first().alias("app_version"),
             col("kind")
                 .eq(lit("page_load"))
@@ -827,10 +715,9 @@ fn recent_traces(base: LazyFrame, limit: u32) -> Result<Vec<TraceSummary>> {
                 .cast(DataType::Int64)
                 .alias("pageviews"),
             is_event().sum().cast(DataType::Int64).alias("events"),
-            col("kind")
-                .eq(lit("exception"))
+            occurrence_weight()
+                .filter(col("kind").eq(lit("exception")))
                 .sum()
-                .cast(DataType::Int64)
                 .alias("exceptions"),
         ])
         .sort(
@@ -1100,25 +987,28 @@ pub fn session_trace(
 /// Occurrence timestamps bucketed into [`TREND_BUCKETS`] equal slices of
 /// `[from, to)`, oldest first.
 fn trend_of(times: impl Iterator<Item = i64>, from_ms: i64, to_ms: i64) -> Vec<i64> {
-    let span = (to_ms - from_ms).max(1) as i128;
     let mut buckets = vec![0i64; TREND_BUCKETS];
     for t in times {
-        let idx = ((t - from_ms) as i128 * TREND_BUCKETS as i128 / span)
-            .clamp(0, TREND_BUCKETS as i128 - 1) as usize;
-        buckets[idx] += 1;
+        buckets[trend_bucket(t, from_ms, to_ms)] += 1;
     }
     buckets
 }
 
-/// The `i64` values of row `i` of a list column.
-fn list_i64(column: &ListChunked, i: usize) -> Vec<i64> {
-    let Some(series) = column.get_as_series(i) else {
-        return Vec::new();
-    };
-    let Ok(values) = series.i64() else {
-        return Vec::new();
-    };
-    (0..values.len()).filter_map(|j| values.get(j)).collect()
+/// Reported `(timestamp, value)` pairs summed on the same bucket grid as
+/// [`trend_of`].
+fn value_trend_of(values: impl Iterator<Item = (i64, f64)>, from_ms: i64, to_ms: i64) -> Vec<f64> {
+    let mut buckets = vec![0f64; TREND_BUCKETS];
+    for (t, value) in values {
+        buckets[trend_bucket(t, from_ms, to_ms)] += value;
+    }
+    buckets
+}
+
+/// The index of the [`TREND_BUCKETS`] slice of `[from, to)` that `t` falls in.
+fn trend_bucket(t: i64, from_ms: i64, to_ms: i64) -> usize {
+    let span = (to_ms - from_ms).max(1) as i128;
+    ((t - from_ms) as i128 * TREND_BUCKETS as i128 / span).clamp(0, TREND_BUCKETS as i128 - 1)
+        as usize
 }
 
 /// The time-filtered (half-open `[from, to)`) union of the cold Parquet
@@ -1134,7 +1024,7 @@ fn combined(store: &Store, parquet_dir: &str, from_ms: i64, to_ms: i64) -> Resul
             Err(err) => warn!("skipping unreadable parquet partition {path}: {err}"),
         }
     }
-    frames.push(store.hot_dataframe()?.lazy());
+    frames.push(store.hot_dataframe_between(from_ms, to_ms)?.lazy());
 
     let combined = if frames.len() == 1 {
         frames.pop().expect("one frame")
@@ -1158,13 +1048,89 @@ fn combined(store: &Store, parquet_dir: &str, from_ms: i64, to_ms: i64) -> Resul
     // unique per-event `seq`, so two *distinct* events can never be all-columns-equal;
     // a full-row unique therefore collapses exactly the crash duplicates and nothing
     // else.
-    Ok(combined
+    let combined = combined
         .filter(
             col("received_ms")
                 .gt_eq(lit(from_ms))
                 .and(col("received_ms").lt(lit(to_ms))),
         )
-        .unique(None, UniqueKeepStrategy::Any))
+        .unique(None, UniqueKeepStrategy::Any);
+
+    // Manually merged exception groups read as their target until re-grouping
+    // stores the target outright.
+    let redirects: Vec<GroupRedirect> = store
+        .list_group_redirects()?
+        .into_iter()
+        .filter(|redirect| !redirect.applied)
+        .collect();
+    if redirects.is_empty() {
+        return Ok(combined);
+    }
+    redirected_groups(combined, &redirects)
+}
+
+/// Deploys within `[from_ms, to_ms)` of the sources with events in `current`,
+/// oldest first.
+fn release_markers(
+    store: &Store,
+    current: LazyFrame,
+    from_ms: i64,
+    to_ms: i64,
+) -> Result<Vec<ReleaseMarker>> {
+    let mut markers: Vec<ReleaseMarker> = store
+        .list_releases()?
+        .into_iter()
+        .map(|release| ReleaseMarker {
+            deployed_ms: release.deployed_at.timestamp_millis(),
+            source: release.source,
+            version: release.version,
+        })
+        .filter(|marker| marker.deployed_ms >= from_ms && marker.deployed_ms < to_ms)
+        .collect();
+    if markers.is_empty() {
+        return Ok(markers);
+    }
+
+    let df = current
+        .select([col("source")])
+        .unique(None, UniqueKeepStrategy::Any)
+        .collect()
+        .or_system_err(ADVICE)?;
+    let sources = df
+        .column("source")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let charted: HashSet<&str> = sources.into_iter().flatten().collect();
+    markers.retain(|marker| charted.contains(marker.source.as_str()));
+    markers.sort_by_key(|marker| marker.deployed_ms);
+    Ok(markers)
+}
+
+/// `events` with every merged group replaced by the group it was merged into,
+/// on the source it was merged on: one join against the redirects rather than
+/// a condition per redirect.
+fn redirected_groups(events: LazyFrame, redirects: &[GroupRedirect]) -> Result<LazyFrame> {
+    let redirects = df![
+        "source" => redirects.iter().map(|r| r.source.as_str()).collect::<Vec<_>>(),
+        "exc_group" => redirects.iter().map(|r| r.group_id.as_str()).collect::<Vec<_>>(),
+        "merged_into" => redirects.iter().map(|r| r.target.as_str()).collect::<Vec<_>>(),
+    ]
+    .or_system_err(ADVICE)?;
+    Ok(events
+        .join(
+            redirects.lazy(),
+            [col("source"), col("exc_group")],
+            [col("source"), col("exc_group")],
+            JoinArgs::new(JoinType::Left),
+        )
+        .with_column(
+            when(col("merged_into").is_null())
+                .then(col("exc_group"))
+                .otherwise(col("merged_into"))
+                .alias("exc_group"),
+        )
+        .drop(cols(["merged_into"])))
 }
 
 /// An OR-chain matching any of the given source URIs (empty set matches nothing).
@@ -1184,50 +1150,85 @@ fn is_event() -> Expr {
 }
 
 fn summary(base: LazyFrame, unique_flag: &str) -> Result<MetricSummary> {
-    let df = base
-        .select([
-            col("kind")
-                .eq(lit("page_load"))
-                .sum()
-                .cast(DataType::Int64)
-                .alias("pageviews"),
-            col("kind")
-                .eq(lit("page_load"))
-                .and(col(unique_flag))
-                .sum()
-                .cast(DataType::Int64)
-                .alias("visitors"),
-            is_event().sum().cast(DataType::Int64).alias("events"),
-            col("duration_ms")
-                .is_not_null()
-                .sum()
-                .cast(DataType::Int64)
-                .alias("samples"),
-            col("duration_ms")
-                .gt_eq(lit(BOUNCE_MIN_MS))
-                .and(col("duration_ms").lt_eq(lit(BOUNCE_MAX_MS)))
-                .sum()
-                .cast(DataType::Int64)
-                .alias("bounces"),
-            col("duration_ms").median().alias("median_ms"),
-        ])
+    let values = base
+        .clone()
+        .select(value_aggs())
         .collect()
         .or_system_err(ADVICE)?;
+    let mut aggs = vec![
+        col("kind")
+            .eq(lit("page_load"))
+            .sum()
+            .cast(DataType::Int64)
+            .alias("pageviews"),
+        col("kind")
+            .eq(lit("page_load"))
+            .and(col(unique_flag))
+            .sum()
+            .cast(DataType::Int64)
+            .alias("visitors"),
+        is_event().sum().cast(DataType::Int64).alias("events"),
+        col("duration_ms")
+            .is_not_null()
+            .sum()
+            .cast(DataType::Int64)
+            .alias("samples"),
+        col("duration_ms")
+            .gt_eq(lit(BOUNCE_MIN_MS))
+            .and(col("duration_ms").lt_eq(lit(BOUNCE_MAX_MS)))
+            .sum()
+            .cast(DataType::Int64)
+            .alias("bounces"),
+        col("duration_ms").median().alias("median_ms"),
+        col("engaged_ms")
+            .cast(DataType::Float64)
+            .median()
+            .alias("median_engaged_ms"),
+        col("scroll_depth")
+            .is_not_null()
+            .sum()
+            .cast(DataType::Int64)
+            .alias("scroll_samples"),
+        col("scroll_depth")
+            .cast(DataType::Float64)
+            .median()
+            .alias("scroll_median"),
+    ];
+    aggs.extend(SCROLL_MARKS.iter().map(|mark| {
+        col("scroll_depth")
+            .gt_eq(lit(*mark))
+            .sum()
+            .cast(DataType::Int64)
+            .alias(format!("scroll_{mark}"))
+    }));
+    let df = base.select(aggs).collect().or_system_err(ADVICE)?;
 
     let samples = scalar_i64(&df, "samples");
     let bounces = scalar_i64(&df, "bounces");
-    let median = df
-        .column("median_ms")
-        .ok()
-        .and_then(|c| c.f64().ok())
-        .and_then(|a| a.get(0));
+    let median = scalar_f64(&df, "median_ms");
+    let scroll_samples = scalar_i64(&df, "scroll_samples");
+    let scroll_depth = (scroll_samples > 0).then(|| ScrollDepth {
+        samples: scroll_samples,
+        median: scalar_f64(&df, "scroll_median").map_or(0, |m| m.round() as i64),
+        reached: SCROLL_MARKS
+            .iter()
+            .map(|mark| scalar_i64(&df, &format!("scroll_{mark}")))
+            .collect(),
+    });
+
+    let visitors = scalar_i64(&df, "visitors");
 
     Ok(MetricSummary {
-        visitors: scalar_i64(&df, "visitors"),
+        visitors,
         pageviews: scalar_i64(&df, "pageviews"),
         events: scalar_i64(&df, "events"),
         bounce_rate: (samples >= MIN_BOUNCE_SAMPLES).then(|| bounces as f64 / samples as f64),
         median_duration_ms: median.map(|m| m.round() as i64),
+        median_engaged_ms: scalar_f64(&df, "median_engaged_ms").map(|m| m.round() as i64),
+        scroll_depth,
+        value: ValueColumns::of(&values)?
+            .get(0)
+            .map(|value| per_visitor(value, visitors)),
     })
 }
 
@@ -1265,7 +1266,10 @@ fn timeseries(
                 .cast(DataType::Int64)
                 .alias("visitors"),
             is_event().sum().cast(DataType::Int64).alias("events"),
-            is_exception.sum().cast(DataType::Int64).alias("exceptions"),
+            occurrence_weight()
+                .filter(is_exception)
+                .sum()
+                .alias("exceptions"),
         ])
         .collect()
         .or_system_err(ADVICE)?;
@@ -1392,11 +1396,52 @@ fn breakdown(pageloads: LazyFrame, column: &str, unique_flag: &str) -> Result<Ve
                 pageviews: pageviews.get(i).unwrap_or(0),
                 visitors: visitors.get(i).unwrap_or(0),
                 events: 0,
+                value: None,
+                scroll_depth: None,
+                goals: Vec::new(),
             })
         })
         .collect())
 }
 
+/// Annotate the pages breakdown with each page's median scroll depth. Depth
+/// rides on the unload beacon, which carries the same pathname as its load.
+fn attach_scroll_depth(pages: &mut [BreakdownRow], base: LazyFrame) -> Result<()> {
+    let df = base
+        .filter(
+            col("kind")
+                .eq(lit("page_unload"))
+                .and(col("scroll_depth").is_not_null()),
+        )
+        .with_columns([col("pathname").fill_null(lit("")).alias("key")])
+        .group_by([col("key")])
+        .agg([col("scroll_depth")
+            .cast(DataType::Float64)
+            .median()
+            .alias("depth")])
+        .collect()
+        .or_system_err(ADVICE)?;
+
+    let keys = df
+        .column("key")
+        .or_system_err(ADVICE)?
+        .str()
+        .or_system_err(ADVICE)?;
+    let depth = df
+        .column("depth")
+        .or_system_err(ADVICE)?
+        .f64()
+        .or_system_err(ADVICE)?;
+    let depths: HashMap<&str, f64> = (0..df.height())
+        .filter_map(|i| Some((keys.get(i)?, depth.get(i)?)))
+        .collect();
+
+    for row in pages {
+        row.scroll_depth = depths.get(row.key.as_str()).map(|d| d.round() as i64);
+    }
+    Ok(())
+}
+
 /// The client-versions breakdown over the page-load frame, keyed by the
 /// (application, version) pair — a version number is only meaningful within
 /// its application, so "120.0" from Chrome and "120.0" from Edge stay separate
@@ -1493,6 +1538,9 @@ fn event_name_breakdown(events: LazyFrame) -> Result<Vec<BreakdownRow>> {
                 visitors: 0,
                 pageviews: 0,
                 events: events.get(i).unwrap_or(0),
+                value: None,
+                scroll_depth: None,
+                goals: Vec::new(),
             })
         })
         .collect())
@@ -1549,6 +1597,9 @@ fn source_rollup(base: LazyFrame, unique_flag: &str) -> Result<Vec<BreakdownRow>
                 pageviews: pageviews.get(i).unwrap_or(0),
                 visitors: visitors.get(i).unwrap_or(0),
                 events: events.get(i).unwrap_or(0),
+                value: None,
+                scroll_depth: None,
+                goals: Vec::new(),
             })
         })
         .collect();
@@ -1586,6 +1637,9 @@ fn project_rollup(
                         visitors: 0,
                         pageviews: 0,
                         events: 0,
+                        value: None,
+                        scroll_depth: None,
+                        goals: Vec::new(),
                     });
                 entry.visitors += row.visitors;
                 entry.pageviews += row.pageviews;
@@ -1606,6 +1660,9 @@ fn project_rollup(
                 visitors: 0,
                 pageviews: 0,
                 events: 0,
+                value: None,
+                scroll_depth: None,
+                goals: Vec::new(),
             })
         })
         .collect();
@@ -1626,6 +1683,13 @@ fn scalar_i64(df: &DataFrame, name: &str) -> i64 {
         .unwrap_or(0)
 }
 
+fn scalar_f64(df: &DataFrame, name: &str) -> Option<f64> {
+    df.column(name)
+        .ok()
+        .and_then(|c| c.f64().ok())
+        .and_then(|a| a.get(0))
+}
+
 /// Parquet partition files whose `YYYY/MM/DD` directory overlaps `[from_ms, to_ms]`.
 /// Partitions are date-partitioned, so pruning whole day directories keeps a wide
 /// query range from scanning the entire archive.
@@ -2100,6 +2164,12 @@ mod tests {
                 name: "Apps".into(),
                 slug: "apps".into(),
                 created_at: Utc::now(),
+                path_rules: Vec::new(),
+                keep_original_path: false,
+                fingerprint_rules: Vec::new(),
+                exception_sampling: Default::default(),
+                inbound_filters: Default::default(),
+                goals: Vec::new(),
             })
             .unwrap();
         store
@@ -2111,6 +2181,7 @@ mod tests {
                 created_at: Utc::now(),
                 first_seen: None,
                 last_seen: None,
+                aliases: Vec::new(),
             })
             .unwrap();
         store
@@ -2177,35 +2248,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn exception_groups_keep_sources_separate_and_carry_trends() {
-        let redb = temp_redb();
-        let store = Store::open(&redb).unwrap();
-        // Same fingerprint on two different sources (e.g. a shared-library error).
-        store
-            .append_events(&[
-                exc_on("https://a.com", "g1", 1_000),
-                exc_on("https://b.com", "g1", 2_000),
-                exc_on("https://a.com", "g1", 3_000),
-            ])
-            .unwrap();
-
-        let rows = exception_groups_by_source(&store, "/none", 0, 10_000, None).unwrap();
-        // One row per (fingerprint, source) — not collapsed across sources.
-        assert_eq!(rows.len(), 2);
-        assert!(rows.iter().all(|(g, _)| g.group_id == "g1"));
-        let a = rows.iter().find(|(_, s)| s == "https://a.com").unwrap();
-        assert_eq!(a.0.count, 2);
-        assert_eq!(a.0.trend.len(), TREND_BUCKETS);
-        assert_eq!(a.0.trend.iter().sum::<i64>(), 2);
-        let b = rows.iter().find(|(_, s)| s == "https://b.com").unwrap();
-        assert_eq!(b.0.count, 1);
-        assert_eq!(b.0.trend.iter().sum::<i64>(), 1);
-
-        drop(store);
-        let _ = std::fs::remove_file(&redb);
-    }
-
     #[test]
     fn union_deduplicates_a_crash_duplicated_window() {
         let redb = temp_redb();
@@ -2250,114 +2292,6 @@ mod tests {
         let _ = std::fs::remove_dir_all(&parquet_dir);
     }
 
-    #[test]
-    fn exception_group_lookup_ignores_the_recency_cap() {
-        let redb = temp_redb();
-        let store = Store::open(&redb).unwrap();
-        let events: Vec<_> = (1..=505)
-            .map(|i| exc(&format!("g{i}"), i * 1_000))
-            .collect();
-        store.append_events(&events).unwrap();
-        let sources = ["https://a.com".to_string()];
-
-        // g1 is the oldest, so it falls outside the top-500-by-recency listing...
-        let listing_filter = filter::compile_query(
-            &source_q("https://a.com"),
-            filter::FieldSet::Exceptions,
-            &store,
-        )
-        .unwrap()
-        .unwrap();
-        let listed =
-            exception_groups_by_source(&store, "/none", 0, 10_000_000, Some(&listing_filter))
-                .unwrap();
-        assert_eq!(listed.len(), 500);
-        assert!(!listed.iter().any(|(g, _)| g.group_id == "g1"));
-
-        // ...but a direct lookup still resolves it (group + variants in one scan).
-        let g1 = exception_detail(&store, "/none", &sources, "g1", 0, 10_000_000, 10).unwrap();
-        let detail = g1.expect("g1 resolves");
-        assert_eq!(detail.group.group_id, "g1");
-        assert_eq!(detail.group.count, 1);
-        assert_eq!(detail.group.trend.iter().sum::<i64>(), 1);
-        assert_eq!(detail.variants.len(), 1);
-        // An unknown group resolves to None.
-        assert!(
-            exception_detail(&store, "/none", &sources, "nope", 0, 10_000_000, 10)
-                .unwrap()
-                .is_none()
-        );
-
-        drop(store);
-        let _ = std::fs::remove_file(&redb);
-    }
-
-    #[test]
-    fn exception_detail_collapses_variants_and_attributes_releases() {
-        let redb = temp_redb();
-        let store = Store::open(&redb).unwrap();
-        // One group, three occurrences: two share a message/stack (one variant
-        // of count 2), the third differs. Different app versions throughout,
-        // and the latest occurrence of the repeated variant carries metadata.
-        let mut a1 = exc("g1", 1_000);
-        a1.exc_message = Some("boom at start".into());
-        a1.exc_stack = Some("at start (app.js)".into());
-        a1.app_version = Some("1.0.0".into());
-        let mut a2 = exc("g1", 2_000);
-        a2.exc_message = Some("boom at start".into());
-        a2.exc_stack = Some("at start (app.js)".into());
-        a2.app_version = Some("1.1.0".into());
-        a2.metadata_json = Some(r#"{"feature_flag":"checkout-v2"}"#.into());
-        a2.sid = Some("sess-1".into());
-        let mut b = exc("g1", 3_000);
-        b.exc_message = Some("boom at shutdown".into());
-        b.exc_stack = Some("at shutdown (app.js)".into());
-        b.app_version = Some("1.1.0".into());
-        store.append_events(&[a1, a2, b]).unwrap();
-
-        let sources = ["https://a.com".to_string()];
-        let detail = exception_detail(&store, "/none", &sources, "g1", 0, 10_000, 10)
-            .unwrap()
-            .expect("g1 resolves");
-
-        // Two distinct variants; the repeated one carries its count and the
-        // context (source-as-app, version, metadata) of its latest occurrence.
-        assert_eq!(detail.variants.len(), 2);
-        let repeated = detail
-            .variants
-            .iter()
-            .find(|v| v.message == "boom at start")
-            .unwrap();
-        assert_eq!(repeated.count, 2);
-        assert_eq!(repeated.source.as_deref(), Some("https://a.com"));
-        assert_eq!(repeated.app_version.as_deref(), Some("1.1.0"));
-        assert_eq!(
-            repeated.metadata.as_deref(),
-            Some(r#"{"feature_flag":"checkout-v2"}"#)
-        );
-        // The exemplar links to the session of its latest session-linked
-        // occurrence.
-        assert_eq!(repeated.session_id.as_deref(), Some("sess-1"));
-
-        // The group's sessions surface as trace summaries for the picker.
-        assert_eq!(detail.traces.len(), 1);
-        assert_eq!(detail.traces[0].session_id, "sess-1");
-        assert_eq!(detail.traces[0].exceptions, 1);
-
-        // Distributions cover app releases (1.1.0 twice, 1.0.0 once). All the
-        // occurrences share one source, so versions are keyed bare — the
-        // application is given by the (source-scoped) view.
-        let versions = &detail.breakdowns.app_versions;
-        assert_eq!(
-            versions.first().map(|r| (r.key.as_str(), r.count)),
-            Some(("1.1.0", 2))
-        );
-        assert!(versions.iter().any(|r| r.key == "1.0.0" && r.count == 1));
-
-        drop(store);
-        let _ = std::fs::remove_file(&redb);
-    }
-
     /// A custom event belonging to a session.
     fn custom_in(source: &str, sid: &str, received_ms: i64, name: &str) -> StoredEvent {
         StoredEvent {
@@ -2481,34 +2415,143 @@ mod tests {
     }
 
     #[test]
-    fn exception_versions_qualify_only_across_sources() {
+    fn numeric_values_aggregate_per_event_and_dimension() {
         let redb = temp_redb();
         let store = Store::open(&redb).unwrap();
-        let mut a = exc_on("https://a.com", "g1", 1_000);
-        a.app_version = Some("1.0.0".into());
-        let mut b = exc_on("https://b.com", "g1", 2_000);
-        b.app_version = Some("1.0.0".into());
-        store.append_events(&[a, b]).unwrap();
-
-        // Across two sources the bare number would be ambiguous, so rows stay
-        // qualified as `app @ version`.
-        let sources = ["https://a.com".to_string(), "https://b.com".to_string()];
-        let detail = exception_detail(&store, "/none", &sources, "g1", 0, 10_000, 10)
-            .unwrap()
-            .expect("g1 resolves");
-        let keys: Vec<&str> = detail
+        let valued = |sid: &str, at: i64, value: f64, currency: &str| {
+            let mut e = custom_in("https://a.com", sid, at, "purchase");
+            e.pathname = Some("/home".into());
+            e.value = Some(value);
+            e.currency = Some(currency.into());
+            e
+        };
+        store
+            .append_events(&[
+                load("https://a.com", 1_000, true, None),
+                load("https://a.com", 1_500, true, None),
+                valued("s1", 2_000, 30.0, "EUR"),
+                valued("s1", 2_500, 10.0, "EUR"),
+                valued("s2", 3_000, 20.0, "EUR"),
+                // Unvalued occurrences count but don't dilute the average.
+                custom_in("https://a.com", "s3", 3_500, "purchase"),
+            ])
+            .unwrap();
+
+        let dash = dashboard(&store, "/none", None, 0, 10_000, 86_400_000).unwrap();
+        let value = dash.summary.value.expect("valued events");
+        assert_eq!(value.total, 60.0);
+        assert_eq!(value.count, 3);
+        assert_eq!(value.average, 20.0);
+        assert_eq!(value.per_visitor, Some(30.0));
+        assert_eq!(value.currency.as_deref(), Some("EUR"));
+
+        let purchase = dash
             .breakdowns
-            .app_versions
+            .event_names
             .iter()
-            .map(|r| r.key.as_str())
-            .collect();
-        assert!(keys.contains(&"a.com @ 1.0.0"));
-        assert!(keys.contains(&"b.com @ 1.0.0"));
+            .find(|r| r.key == "purchase")
+            .unwrap();
+        assert_eq!(purchase.value.as_ref().map(|v| v.total), Some(60.0));
+        let home = dash
+            .breakdowns
+            .pages
+            .iter()
+            .find(|r| r.key == "/home")
+            .unwrap();
+        assert_eq!(home.value.as_ref().and_then(|v| v.per_visitor), Some(30.0));
+
+        let detail = event_detail(&store, "/none", "purchase", 0, 10_000, None, 10)
+            .unwrap()
+            .expect("purchase resolves");
+        assert_eq!(detail.count, 4);
+        let value = detail.value.expect("valued occurrences");
+        assert_eq!((value.total, value.count), (60.0, 3));
+        // Measured against the two sessions that reported a value.
+        assert_eq!(value.per_visitor, Some(30.0));
+        assert_eq!(detail.value_trend.iter().sum::<f64>(), 60.0);
+        assert_eq!(detail.value_breakdowns.pages[0].key, "/home");
+
+        // A second currency makes the sum a bare number.
+        store
+            .append_events(&[valued("s4", 4_000, 5.0, "USD")])
+            .unwrap();
+        let dash = dashboard(&store, "/none", None, 0, 10_000, 86_400_000).unwrap();
+        assert_eq!(dash.summary.value.unwrap().currency, None);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[test]
+    fn summarizes_engaged_time_and_scroll_depth() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let unload = |at: i64, engaged: i64, depth: i64| {
+            let mut e = load("https://a.com", at, false, Some(60_000));
+            e.engaged_ms = Some(engaged);
+            e.scroll_depth = Some(depth);
+            e
+        };
+        store
+            .append_events(&[
+                load("https://a.com", 1_000, true, None),
+                unload(2_000, 4_000, 30),
+                unload(3_000, 6_000, 80),
+                unload(4_000, 8_000, 100),
+                // Trackers predating engagement still report a duration.
+                load("https://a.com", 5_000, false, Some(1_000)),
+            ])
+            .unwrap();
+
+        let dash = dashboard(&store, "/none", None, 0, 10_000, 86_400_000).unwrap();
+        assert_eq!(dash.summary.median_engaged_ms, Some(6_000));
+        let scroll = dash.summary.scroll_depth.expect("scroll measured");
+        assert_eq!(scroll.samples, 3);
+        assert_eq!(scroll.median, 80);
+        assert_eq!(scroll.reached, vec![3, 2, 2, 1]);
+
+        let home = &dash.breakdowns.pages[0];
+        assert_eq!(home.key, "/home");
+        assert_eq!(home.scroll_depth, Some(80));
 
         drop(store);
         let _ = std::fs::remove_file(&redb);
     }
 
+    #[test]
+    fn recent_pathnames_prefer_the_reported_path() {
+        let redb = temp_redb();
+        let store = Store::open(&redb).unwrap();
+        let at = |path: &str, original: Option<&str>, received_ms: i64| {
+            let mut e = load("https://a.com", received_ms, true, None);
+            e.pathname = Some(path.into());
+            e.original_pathname = original.map(str::to_string);
+            e
+        };
+        store
+            .append_events(&[
+                at("/orders/:id", Some("/orders/1"), 1_000),
+                at("/orders/:id", Some("/orders/1"), 2_000),
+                at("/pricing", None, 3_000),
+                load("https://b.com", 4_000, true, None),
+            ])
+            .unwrap();
+
+        let paths = recent_pathnames(
+            &store,
+            "/none",
+            &["https://a.com".to_string()],
+            0,
+            10_000,
+            10,
+        )
+        .unwrap();
+        assert_eq!(
+            paths,
+            vec![("/orders/1".to_string(), 2), ("/pricing".to_string(), 1)]
+        );
+    }
+
     #[test]
     fn dashboard_samples_recent_session_traces() {
         let redb = temp_redb();
@@ -16,6 +16,7 @@ pub struct Config {
     pub web: WebConfig,
     pub storage: StorageConfig,
     pub privacy: PrivacyConfig,
+    pub ingest: IngestConfig,
     pub ratelimit: RateLimitConfig,
     pub telemetry: TelemetryConfig,
 }
@@ -97,6 +98,8 @@ pub struct StorageConfig {
     pub redb_path: String,
     /// Directory holding the rolled-up Parquet partitions (the cold archive).
     pub parquet_dir: String,
+    /// Directory holding the source maps uploaded to symbolicate exceptions.
+    pub sourcemap_dir: String,
     /// How long events stay in redb before being compacted to Parquet.
     #[serde(with = "humantime_serde")]
     pub hot_window: Duration,
@@ -119,6 +122,7 @@ impl Default for StorageConfig {
         Self {
             redb_path: "analytics.redb".to_string(),
             parquet_dir: "parquet-store".to_string(),
+            sourcemap_dir: "sourcemaps".to_string(),
             hot_window: Duration::from_secs(48 * 60 * 60),
             rollup_interval: Duration::from_secs(60 * 60),
             retention: Duration::from_secs(365 * 24 * 60 * 60),
@@ -147,6 +151,51 @@ impl Default for PrivacyConfig {
     }
 }
 
+/// Ingest-time policy applied to every enriched event before it is stored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct IngestConfig {
+    /// [filt-rs](https://github.com/SierraSoftworks/filters) expressions over the
+    /// same fields the dashboard and exception queries accept. An event matching
+    /// any of them is dropped (and counted against the first rule it matched)
+    /// instead of being stored; a rule only applies to the events that have
+    /// every field it names.
+    pub exclude: Vec<String>,
+    pub referrers: ReferrerConfig,
+    /// Acquisition channel rules, evaluated in order; the first match names a
+    /// hit's channel. Empty keeps the built-in rules.
+    pub channels: Vec<ChannelRule>,
+}
+
+/// One acquisition channel and the filter expression (over `referrer`,
+/// `referrer_group` and the UTM fields) that selects it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelRule {
+    pub channel: String,
+    pub when: String,
+}
+
+/// Referrer classification: which hosts count as search, social, email, AI and
+/// so on, and what happens to referrer spam.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReferrerConfig {
+    /// A rules file replacing the bundled `assets/referrers.yaml`.
+    pub rules: Option<String>,
+    /// What to do with events from a blocklisted spam referrer.
+    pub spam: SpamAction,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpamAction {
+    /// Discard the event.
+    #[default]
+    Drop,
+    /// Keep the event, recording its referrer group as `Spam`.
+    Flag,
+}
+
 /// Per-IP rate limiting. IPs are used only as transient in-memory keys and are
 /// never logged or stored.
 #[derive(Debug, Clone, Deserialize)]
@@ -234,7 +283,7 @@ impl Config {
 
     /// Parse a YAML document, interpolating `${{ env.VAR }}` placeholders inside
     /// string *values* (so placeholders in comments are ignored) and validating the
-    /// ACL expression.
+    /// ACL, ingest exclusion expressions, referrer rules and channel rules.
     pub fn from_yaml_str(raw: &str) -> Result<Self> {
         if raw.trim().is_empty() {
             return Ok(Config::default());
@@ -252,6 +301,9 @@ impl Config {
 
         // Fail fast on an invalid ACL rather than at the first request.
         config.web.admin.acl_filter()?;
+        crate::ingest::Exclusions::new(&config.ingest.exclude)?;
+        crate::ingest::ReferrerRules::load(&config.ingest.referrers)?;
+        crate::ingest::Channels::new(&config.ingest.channels)?;
         Ok(config)
     }
 }
@@ -386,6 +438,15 @@ mod tests {
         assert!(err.to_string().contains("acl"));
     }
 
+    #[test]
+    fn invalid_exclusion_rule_is_rejected() {
+        let err = Config::from_yaml_str(
+            "ingest:\n  exclude:\n    - 'path == \"/health\"'\n    - '&& ||'\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("ingest.exclude"));
+    }
+
     #[test]
     fn example_config_loads() {
         let raw = include_str!("../../config.example.yaml");
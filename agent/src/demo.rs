@@ -12,7 +12,9 @@
 
 use std::collections::BTreeMap;
 
-use analytics_api::{Pixel, Project, Source, SourceKind};
+use analytics_api::{
+    Breadcrumb, BreadcrumbKind, ExceptionLevel, Pixel, Project, Source, SourceKind,
+};
 use chrono::{Duration, Utc};
 use ulid::Ulid;
 
@@ -169,6 +171,7 @@ struct Exc {
     message: &'static str,
     stack: &'static str,
     handled: bool,
+    level: ExceptionLevel,
 }
 
 const WEB_EXCEPTIONS: &[(Exc, u32)] = &[
@@ -179,6 +182,7 @@ const WEB_EXCEPTIONS: &[(Exc, u32)] = &[
             message: "Cannot read properties of undefined (reading 'id')",
             stack: "at renderProfile (app.js:842:19)\nat onLoad (app.js:120:7)\nat dispatch (runtime.js:55:3)",
             handled: false,
+            level: ExceptionLevel::Error,
         },
         40,
     ),
@@ -189,6 +193,7 @@ const WEB_EXCEPTIONS: &[(Exc, u32)] = &[
             message: "Failed to fetch /api/v1/dashboard (503)",
             stack: "at fetchJson (api.js:31:11)\nat loadDashboard (dashboard.js:88:22)",
             handled: true,
+            level: ExceptionLevel::Warning,
         },
         28,
     ),
@@ -199,6 +204,7 @@ const WEB_EXCEPTIONS: &[(Exc, u32)] = &[
             message: "analytics is not defined",
             stack: "at track (tracker.js:14:5)\nat HTMLDocument.<anonymous> (index.html:60:9)",
             handled: false,
+            level: ExceptionLevel::Error,
         },
         16,
     ),
@@ -209,6 +215,7 @@ const WEB_EXCEPTIONS: &[(Exc, u32)] = &[
             message: "Maximum call stack size exceeded",
             stack: "at walk (tree.js:210:14)\nat walk (tree.js:214:9)\nat walk (tree.js:214:9)",
             handled: false,
+            level: ExceptionLevel::Fatal,
         },
         8,
     ),
@@ -222,6 +229,7 @@ const APP_EXCEPTIONS: &[(Exc, u32)] = &[
             message: "called `Result::unwrap()` on an `Err` value: ConnectionRefused",
             stack: "at bender::db::connect (db.rs:88)\nat bender::main (main.rs:24)",
             handled: false,
+            level: ExceptionLevel::Fatal,
         },
         30,
     ),
@@ -232,13 +240,23 @@ const APP_EXCEPTIONS: &[(Exc, u32)] = &[
             message: "No such file or directory (os error 2)",
             stack: "at bender::config::load (config.rs:41)\nat bender::run (main.rs:57)",
             handled: true,
+            level: ExceptionLevel::Error,
         },
         18,
     ),
 ];
 
+/// The deployment environments exceptions report: mostly production, a few
+/// from a staging deploy.
+const ENVIRONMENTS: &[(&str, u32)] = &[("production", 9), ("staging", 1)];
+
 /// The reported releases for the application source (drives the version breakdown).
-const APP_VERSIONS: &[(&str, u32)] = &[("1.4.2", 40), ("1.4.1", 25), ("1.3.0", 15), ("1.5.0-rc1", 8)];
+const APP_VERSIONS: &[(&str, u32)] = &[
+    ("1.4.2", 40),
+    ("1.4.1", 25),
+    ("1.3.0", 15),
+    ("1.5.0-rc1", 8),
+];
 
 // --------------------------------------------------------------------- sites
 
@@ -496,6 +514,12 @@ fn register_entities(store: &Store, now: chrono::DateTime<Utc>) -> Result<()> {
             name: (*name).to_string(),
             slug: (*slug).to_string(),
             created_at: created,
+            path_rules: Vec::new(),
+            keep_original_path: false,
+            fingerprint_rules: Vec::new(),
+            exception_sampling: Default::default(),
+            inbound_filters: Default::default(),
+            goals: Vec::new(),
         })?;
     }
 
@@ -508,6 +532,7 @@ fn register_entities(store: &Store, now: chrono::DateTime<Utc>) -> Result<()> {
             created_at: created,
             first_seen: Some(first_seen),
             last_seen: Some(now),
+            aliases: Vec::new(),
         })?;
     }
 
@@ -621,6 +646,8 @@ fn simulate_visit(rng: &mut Rng, site: &Site, start_ms: i64, batch: &mut Vec<Sto
                 ua_os: Some(os.to_string()),
                 ua_device: Some(device.to_string()),
                 duration_ms: Some(dwell),
+                engaged_ms: (!site.is_app).then(|| engaged_ms(rng, dwell)),
+                scroll_depth: (!site.is_app).then(|| scroll_depth(rng, dwell)),
                 app_version: app_version.clone(),
                 ..Default::default()
             });
@@ -629,6 +656,7 @@ fn simulate_visit(rng: &mut Rng, site: &Site, start_ms: i64, batch: &mut Vec<Sto
         // A custom event sometimes fires mid-visit.
         if !site.events.is_empty() && rng.chance(0.18) {
             let name = *rng.weighted(site.events);
+            let (value, currency) = event_value(rng, name);
             batch.push(StoredEvent {
                 created_ms: t + rng.between(200, dwell.max(300)),
                 received_ms: t + rng.between(200, dwell.max(300)),
@@ -645,6 +673,8 @@ fn simulate_visit(rng: &mut Rng, site: &Site, start_ms: i64, batch: &mut Vec<Sto
                 ua_device: Some(device.to_string()),
                 event_name: Some(name.to_string()),
                 metadata_json: Some(event_metadata(rng, name)),
+                value,
+                currency,
                 app_version: app_version.clone(),
                 ..Default::default()
             });
@@ -677,11 +707,60 @@ fn simulate_visit(rng: &mut Rng, site: &Site, start_ms: i64, batch: &mut Vec<Sto
             exc_stack: Some(exc.stack.to_string()),
             exc_group: Some(exc.group.to_string()),
             exc_handled: Some(exc.handled),
+            exc_level: Some(exc.level.as_str().to_string()),
+            environment: Some((*rng.weighted(ENVIRONMENTS)).to_string()),
+            breadcrumbs_json: (!site.is_app)
+                .then(|| demo_breadcrumbs(rng, site.uri, exc))
+                .flatten(),
             ..Default::default()
         });
     }
 }
 
+/// A plausible trail of steps leading up to a web exception.
+fn demo_breadcrumbs(rng: &mut Rng, site_uri: &str, exc: &Exc) -> Option<String> {
+    let crumb = |age_ms: i64, kind, message: String| Breadcrumb {
+        age_ms: age_ms as u64,
+        kind,
+        message,
+        level: None,
+        status: None,
+    };
+    let page = *rng.pick(&["/", "/pricing", "/docs", "/dashboard"]);
+    let mut crumbs = vec![
+        crumb(
+            rng.between(8_000, 40_000),
+            BreadcrumbKind::Navigation,
+            format!("{site_uri}{page}"),
+        ),
+        crumb(
+            rng.between(2_000, 8_000),
+            BreadcrumbKind::Click,
+            r#"button.btn.btn-primary "Continue""#.to_string(),
+        ),
+    ];
+    if exc.exc_type == "NetworkError" {
+        crumbs.push(Breadcrumb {
+            status: Some(503),
+            ..crumb(
+                rng.between(200, 1_500),
+                BreadcrumbKind::Fetch,
+                "GET /api/v1/dashboard".to_string(),
+            )
+        });
+    } else {
+        crumbs.push(Breadcrumb {
+            level: Some("warn".to_string()),
+            ..crumb(
+                rng.between(200, 1_500),
+                BreadcrumbKind::Console,
+                "render took 1.8s".to_string(),
+            )
+        });
+    }
+    serde_json::to_string(&crumbs).ok()
+}
+
 /// A single newsletter-open pixel hit.
 fn pixel_hit(rng: &mut Rng, t: i64) -> StoredEvent {
     let ua = rng.weighted(UA_PROFILES);
@@ -727,6 +806,23 @@ fn dwell_ms(rng: &mut Rng) -> i64 {
     }
 }
 
+/// Visible time on page: most of a short dwell, less of a long one (tabs get
+/// backgrounded).
+fn engaged_ms(rng: &mut Rng, dwell: i64) -> i64 {
+    let share = if dwell < 60_000 { 0.8 } else { 0.45 };
+    ((dwell as f64) * (share + rng.unit() * (1.0 - share))).round() as i64
+}
+
+/// Maximum scroll depth: bounces rarely leave the fold, engaged readers often
+/// reach the end.
+fn scroll_depth(rng: &mut Rng, dwell: i64) -> i64 {
+    if dwell < 5_000 {
+        rng.between(10, 45)
+    } else {
+        rng.between(35, 100)
+    }
+}
+
 /// A small, varied metadata payload for a custom event.
 fn event_metadata(rng: &mut Rng, name: &str) -> String {
     let mut meta = BTreeMap::new();
@@ -756,6 +852,19 @@ fn event_metadata(rng: &mut Rng, name: &str) -> String {
     serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string())
 }
 
+/// A numeric value for the custom events that carry one: signups report
+/// their first invoice, report exports their row count.
+fn event_value(rng: &mut Rng, name: &str) -> (Option<f64>, Option<String>) {
+    match name {
+        "signup" => (
+            Some(*rng.pick(&[0.0, 12.0, 12.0, 49.0])),
+            Some("USD".to_string()),
+        ),
+        "report_export" => (Some(rng.between(10, 5_000) as f64), None),
+        _ => (None, None),
+    }
+}
+
 /// Persist and clear the current batch, returning how many events were written.
 fn flush(store: &Store, batch: &mut Vec<StoredEvent>) -> Result<usize> {
     if batch.is_empty() {
@@ -0,0 +1,215 @@
+//! Hostname aliases: attribute traffic reported under alternate hostnames
+//! (`example.co.uk`, `app-preview-123.example.dev`, `127.0.0.1`) to one
+//! canonical source instead of auto-registering each as its own.
+//!
+//! Aliases live on the [`Source`](analytics_api::Source) they point at and are
+//! compiled into a lookup table that the source handlers
+//! [`reload`](HostAliases::reload) after every change.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use analytics_api::website_source;
+use regex::Regex;
+use tracing_batteries::prelude::warn;
+
+use crate::errors::Result;
+use crate::store::Store;
+
+/// A validated alias pattern: an exact hostname, or one containing `*`
+/// wildcards that each match one or more characters within a single label.
+pub enum HostPattern {
+    Exact(String),
+    Wildcard(Regex),
+}
+
+impl HostPattern {
+    /// Parse an alias as entered by an administrator. A pasted scheme, trailing
+    /// slash or `www.` prefix is tolerated, mirroring how reporting hostnames are
+    /// normalized. `Err` carries a message suitable for a 400 response.
+    pub fn new(alias: &str) -> std::result::Result<Self, String> {
+        let host = normalize_alias(alias);
+        if host.is_empty() {
+            return Err("An alias needs a hostname.".into());
+        }
+        if !host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '*' | ':' | '[' | ']'))
+        {
+            return Err(format!("`{alias}` is not a hostname or hostname pattern."));
+        }
+        if !host.contains('*') {
+            return Ok(HostPattern::Exact(host));
+        }
+        let pattern = host
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join("[^.]+");
+        Regex::new(&format!("^{pattern}$"))
+            .map(HostPattern::Wildcard)
+            .map_err(|err| format!("`{alias}` is not a valid pattern: {err}"))
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Exact(exact) => exact == host,
+            HostPattern::Wildcard(regex) => regex.is_match(host),
+        }
+    }
+}
+
+/// The canonical form an alias is stored and compared in.
+pub fn normalize_alias(alias: &str) -> String {
+    let host = alias.trim().to_lowercase();
+    let host = host
+        .strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(&host);
+    let host = host.trim_end_matches('/').trim_end_matches('.');
+    host.strip_prefix("www.").unwrap_or(host).to_string()
+}
+
+#[derive(Default)]
+struct AliasTable {
+    exact: HashMap<String, String>,
+    wildcards: Vec<(Regex, String)>,
+    /// Hostnames whose own source is assigned to a project. Wildcards never
+    /// capture them — a deliberately configured source only moves when it is
+    /// named as an exact alias.
+    assigned: HashSet<String>,
+}
+
+/// Every source's compiled aliases, resolving a reporting hostname to the
+/// canonical source URI it should be attributed to.
+#[derive(Default)]
+pub struct HostAliases {
+    table: RwLock<AliasTable>,
+}
+
+impl HostAliases {
+    pub fn load(store: &Store) -> Result<Self> {
+        let aliases = Self::default();
+        aliases.reload(store)?;
+        Ok(aliases)
+    }
+
+    /// Rebuild the table from the stored sources.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let mut table = AliasTable::default();
+        let mut sources = store.list_sources()?;
+        // Deterministic wildcard precedence when patterns overlap.
+        sources.sort_by(|a, b| a.uri.cmp(&b.uri));
+        for source in sources {
+            if source.project_id.is_some()
+                && let Some(host) = source.uri.strip_prefix("https://")
+            {
+                table.assigned.insert(host.to_string());
+            }
+            for alias in &source.aliases {
+                match HostPattern::new(alias) {
+                    Ok(HostPattern::Exact(host)) => {
+                        table.exact.insert(host, source.uri.clone());
+                    }
+                    Ok(HostPattern::Wildcard(regex)) => {
+                        table.wildcards.push((regex, source.uri.clone()))
+                    }
+                    // Aliases are validated on save; this only guards hand-edited
+                    // or legacy records.
+                    Err(err) => warn!("skipping invalid alias on source {}: {err}", source.uri),
+                }
+            }
+        }
+        *self.table.write().unwrap_or_else(|e| e.into_inner()) = table;
+        Ok(())
+    }
+
+    /// The source URI for a reporting hostname: its canonical source when it
+    /// matches an alias, otherwise its own website source.
+    pub fn source_for(&self, hostname: &str) -> String {
+        let table = self.table.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(uri) = table.exact.get(hostname) {
+            return uri.clone();
+        }
+        if !table.assigned.contains(hostname)
+            && let Some((_, uri)) = table
+                .wildcards
+                .iter()
+                .find(|(regex, _)| regex.is_match(hostname))
+        {
+            return uri.clone();
+        }
+        website_source(hostname)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use analytics_api::Source;
+    use chrono::Utc;
+
+    use super::*;
+
+    fn pattern(alias: &str) -> HostPattern {
+        HostPattern::new(alias).expect("valid alias")
+    }
+
+    #[test]
+    fn parses_exact_and_wildcard_aliases() {
+        assert!(
+            matches!(pattern("https://www.Example.co.uk/"), HostPattern::Exact(h) if h == "example.co.uk")
+        );
+        assert!(pattern("127.0.0.1").matches("127.0.0.1"));
+
+        let previews = pattern("app-preview-*.example.dev");
+        assert!(previews.matches("app-preview-123.example.dev"));
+        assert!(!previews.matches("app-preview-1.x.example.dev"));
+        assert!(!previews.matches("example.dev"));
+
+        assert!(HostPattern::new("").is_err());
+        assert!(HostPattern::new("exa mple.com").is_err());
+    }
+
+    #[test]
+    fn resolves_hostnames_to_their_canonical_source() {
+        let path =
+            std::env::temp_dir().join(format!("analytics-aliases-{}.redb", std::process::id()));
+        let store = Store::open(&path).unwrap();
+        let source = |uri: &str, project: Option<&str>, aliases: &[&str]| Source {
+            uri: uri.into(),
+            project_id: project.map(str::to_string),
+            kind: Default::default(),
+            display_name: None,
+            created_at: Utc::now(),
+            first_seen: None,
+            last_seen: None,
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        };
+        store
+            .put_source(&source(
+                "https://example.com",
+                Some("p1"),
+                &["example.co.uk", "*.example.dev", "docs.example.dev"],
+            ))
+            .unwrap();
+        store
+            .put_source(&source("https://api.example.dev", Some("p2"), &[]))
+            .unwrap();
+
+        let aliases = HostAliases::load(&store).unwrap();
+        assert_eq!(aliases.source_for("example.co.uk"), "https://example.com");
+        assert_eq!(
+            aliases.source_for("preview-7.example.dev"),
+            "https://example.com"
+        );
+        // An assigned source keeps its own traffic unless named exactly.
+        assert_eq!(
+            aliases.source_for("api.example.dev"),
+            "https://api.example.dev"
+        );
+        assert_eq!(aliases.source_for("other.org"), "https://other.org");
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,174 @@
+//! Breadcrumbs sent with exception reports: the trail of navigations, clicks,
+//! console messages and requests leading up to the failure.
+//!
+//! The trail comes straight from the page, so before it is stored it is
+//! scrubbed of the values most likely to be personal or secret (query and
+//! fragment parameters, email addresses, card numbers) and capped, both
+//! per breadcrumb and in total, keeping the newest steps.
+
+use std::sync::LazyLock;
+
+use analytics_api::{Breadcrumb, BreadcrumbKind};
+use regex::Regex;
+
+use super::truncate;
+
+/// The most breadcrumbs kept per occurrence (the newest).
+const MAX_BREADCRUMBS: usize = 50;
+/// The longest breadcrumb message kept; longer ones are truncated.
+const MAX_MESSAGE: usize = 300;
+/// The budget for all of an occurrence's breadcrumb messages together.
+const MAX_TOTAL: usize = 8_000;
+/// The oldest breadcrumb kept, in milliseconds before the report.
+const MAX_AGE_MS: u64 = 60 * 60 * 1000;
+
+/// `key=value` pairs of a query string or fragment (`?token=…`, `#access_token=…`).
+static URL_PARAMS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[?#&][\w.%\[\]-]+=[^\s&#]*").expect("valid parameter pattern"));
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\w.%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").expect("valid email pattern")
+});
+/// Runs of 13–19 digits, optionally grouped by spaces or dashes, starting with
+/// a card network's leading digit (2–6; millisecond timestamps start with 1).
+/// Only runs passing the [`luhn`] check are masked.
+static CARD_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[2-6](?:[ -]?\d){12,18}\b").expect("valid card pattern"));
+
+/// Scrub and cap a report's breadcrumbs and serialize them for storage,
+/// oldest first. `None` when none are left.
+pub(super) fn clean_breadcrumbs(mut crumbs: Vec<Breadcrumb>) -> Option<String> {
+    crumbs.retain(|crumb| crumb.kind != BreadcrumbKind::Other && crumb.age_ms <= MAX_AGE_MS);
+    // Newest first while the budget is spent, so the steps right before the
+    // failure are the ones kept.
+    crumbs.sort_by_key(|crumb| crumb.age_ms);
+    let mut budget = MAX_TOTAL;
+    let mut kept: Vec<Breadcrumb> = Vec::new();
+    for crumb in crumbs.into_iter().take(MAX_BREADCRUMBS) {
+        let message = truncate(&scrub(&crumb.message), MAX_MESSAGE);
+        if message.len() > budget {
+            break;
+        }
+        budget -= message.len();
+        kept.push(Breadcrumb {
+            message,
+            level: crumb
+                .level
+                .map(|level| truncate(level.trim(), 16))
+                .filter(|level| !level.is_empty()),
+            status: crumb.status.filter(|_| crumb.kind == BreadcrumbKind::Fetch),
+            ..crumb
+        });
+    }
+    if kept.is_empty() {
+        return None;
+    }
+    kept.reverse();
+    serde_json::to_string(&kept).ok()
+}
+
+/// Remove URL parameters and mask email addresses and card-like numbers.
+fn scrub(message: &str) -> String {
+    let message = URL_PARAMS.replace_all(message.trim(), "");
+    let message = EMAIL.replace_all(&message, "<email>");
+    CARD_NUMBER
+        .replace_all(&message, |caps: &regex::Captures| {
+            if luhn(&caps[0]) {
+                "<number>".to_string()
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// Whether the digits of `number` pass the Luhn checksum every card number
+/// carries (separators are skipped).
+fn luhn(number: &str) -> bool {
+    let sum: u32 = number
+        .bytes()
+        .rev()
+        .filter(u8::is_ascii_digit)
+        .map(|b| u32::from(b - b'0'))
+        .enumerate()
+        .map(|(i, d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crumb(age_ms: u64, kind: BreadcrumbKind, message: &str) -> Breadcrumb {
+        Breadcrumb {
+            age_ms,
+            kind,
+            message: message.into(),
+            level: None,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn breadcrumbs_are_scrubbed_and_capped() {
+        let crumbs = vec![
+            crumb(50, BreadcrumbKind::Console, "signed in as ada@example.com"),
+            Breadcrumb {
+                status: Some(500),
+                ..crumb(
+                    10,
+                    BreadcrumbKind::Fetch,
+                    "POST /api/pay?card=4111111111111111&t=x",
+                )
+            },
+            crumb(
+                900,
+                BreadcrumbKind::Navigation,
+                "https://example.com/cb#access_token=abc",
+            ),
+            crumb(5, BreadcrumbKind::Other, "from a newer tracker"),
+            crumb(
+                20,
+                BreadcrumbKind::Click,
+                "button \"Pay 4111 1111 1111 1111\"",
+            ),
+            crumb(
+                30,
+                BreadcrumbKind::Console,
+                "retry at 1760832000000 for order 4111111111111112",
+            ),
+        ];
+        let stored: Vec<Breadcrumb> =
+            serde_json::from_str(&clean_breadcrumbs(crumbs).unwrap()).unwrap();
+
+        // Oldest first, unknown kinds dropped, personal values gone.
+        let messages: Vec<&str> = stored.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "https://example.com/cb",
+                "signed in as <email>",
+                "retry at 1760832000000 for order 4111111111111112",
+                "button \"Pay <number>\"",
+                "POST /api/pay",
+            ]
+        );
+        assert_eq!(stored[4].status, Some(500));
+
+        // Over the budget, the newest steps are the ones kept.
+        let flood = (0..200)
+            .map(|i| crumb(i, BreadcrumbKind::Console, &"x".repeat(1_000)))
+            .collect();
+        let stored: Vec<Breadcrumb> =
+            serde_json::from_str(&clean_breadcrumbs(flood).unwrap()).unwrap();
+        assert!(stored.len() < MAX_BREADCRUMBS);
+        assert!(stored.iter().map(|c| c.message.len()).sum::<usize>() <= MAX_TOTAL);
+        assert_eq!(stored.last().unwrap().age_ms, 0);
+
+        assert_eq!(clean_breadcrumbs(Vec::new()), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Acquisition channels: a marketing-level summary of how a visit arrived
+//! (Organic Search, Paid Social, Email, ...), derived from the classified
+//! referrer and the UTM tags and stored on every hit.
+//!
+//! Channels are an ordered list of filt-rs rules; the first match names the
+//! channel and a visit matching none is [`DIRECT`]. `ingest.channels` replaces
+//! the [`default_channels`] wholesale. When the rules differ from the ones the
+//! stored channels were derived with, start-up re-derives them (see
+//! [`rederive_attribution_if_needed`](super::rederive_attribution_if_needed)).
+
+use std::borrow::Cow;
+
+use filt_rs::{Filter, FilterValue, Filterable};
+use sha2::{Digest, Sha256};
+
+use crate::config::ChannelRule;
+use crate::errors::{Result, ResultExt};
+
+/// The channel of a visit that matches no rule.
+pub const DIRECT: &str = "Direct";
+
+const PAID: &str = r#"["cpc", "ppc", "paid", "paidsearch", "paid_search", "paidsocial", "paid_social", "sem", "display", "retargeting"]"#;
+const SEARCH_SOURCES: &str =
+    r#"["google", "bing", "duckduckgo", "yahoo", "yandex", "baidu", "ecosia", "brave"]"#;
+const SOCIAL_SOURCES: &str = r#"["facebook", "fb", "instagram", "ig", "linkedin", "twitter", "x", "reddit", "tiktok", "pinterest", "youtube", "mastodon", "bluesky"]"#;
+
+/// The built-in rules, in evaluation order.
+pub fn default_channels() -> Vec<ChannelRule> {
+    let rule = |channel: &str, when: String| ChannelRule {
+        channel: channel.to_string(),
+        when,
+    };
+    vec![
+        rule(
+            "Affiliates",
+            r#"utm_medium in ["affiliate", "affiliates", "partner"]"#.into(),
+        ),
+        rule(
+            "Paid Search",
+            format!(
+                r#"(referrer_group == "Search" || utm_source in {SEARCH_SOURCES}) && utm_medium in {PAID}"#
+            ),
+        ),
+        rule(
+            "Paid Social",
+            format!(
+                r#"(referrer_group == "Social" || utm_source in {SOCIAL_SOURCES}) && utm_medium in {PAID}"#
+            ),
+        ),
+        rule(
+            "Email",
+            r#"referrer_group == "Email" || utm_medium in ["email", "e-mail", "newsletter"]"#
+                .into(),
+        ),
+        rule(
+            "Organic Search",
+            format!(
+                r#"referrer_group == "Search" || utm_medium == "organic" || utm_source in {SEARCH_SOURCES}"#
+            ),
+        ),
+        rule(
+            "Organic Social",
+            format!(
+                r#"referrer_group == "Social" || utm_medium in ["social", "social-network", "social-media", "sm"] || utm_source in {SOCIAL_SOURCES}"#
+            ),
+        ),
+        rule("Referral", r#"referrer != "" || utm_source != """#.into()),
+    ]
+}
+
+struct Rule {
+    channel: String,
+    filter: Filter,
+}
+
+/// The compiled channel rules.
+pub struct Channels {
+    rules: Vec<Rule>,
+    version: u32,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Self::new(&default_channels()).expect("default channel rules are valid")
+    }
+}
+
+impl Channels {
+    /// Compile `rules` (the defaults when empty), failing on the first invalid
+    /// expression.
+    pub fn new(rules: &[ChannelRule]) -> Result<Self> {
+        if rules.is_empty() {
+            return Ok(Self::default());
+        }
+        let mut digest = Sha256::new();
+        for rule in rules {
+            digest.update(rule.channel.as_bytes());
+            digest.update([0]);
+            digest.update(rule.when.as_bytes());
+            digest.update([0]);
+        }
+        let digest = digest.finalize();
+        let version = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let filter = Filter::new(rule.when.as_str()).wrap_user_err(
+                    format!(
+                        "The `ingest.channels` rule for `{}` is not a valid filter expression.",
+                        rule.channel
+                    ),
+                    &[
+                        "Rules may use referrer, referrer_group, utm_source, utm_medium and utm_campaign.",
+                        "String literals use double quotes; wrap each rule in single quotes in YAML.",
+                    ],
+                )?;
+                Ok(Rule {
+                    channel: rule.channel.clone(),
+                    filter,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules, version })
+    }
+
+    /// A digest of the rules, stamped on the stored data once its channels
+    /// were derived with them, so editing `ingest.channels` re-derives them.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The channel of a visit. A rule that fails to evaluate is skipped.
+    pub fn classify(&self, visit: &Visit<'_>) -> String {
+        self.rules
+            .iter()
+            .find(|rule| rule.filter.matches(visit).unwrap_or(false))
+            .map_or(DIRECT, |rule| rule.channel.as_str())
+            .to_string()
+    }
+}
+
+/// The attribution inputs of a hit. Absent values read as the empty string.
+#[derive(Default)]
+pub struct Visit<'a> {
+    pub referrer_host: Option<&'a str>,
+    pub referrer_group: Option<&'a str>,
+    pub utm_source: Option<&'a str>,
+    pub utm_medium: Option<&'a str>,
+    pub utm_campaign: Option<&'a str>,
+}
+
+impl Filterable for Visit<'_> {
+    fn get(&self, key: &str) -> FilterValue<'_> {
+        let value = match key {
+            "referrer" => self.referrer_host,
+            "referrer_group" => self.referrer_group,
+            "utm_source" => self.utm_source,
+            "utm_medium" => self.utm_medium,
+            "utm_campaign" => self.utm_campaign,
+            _ => return FilterValue::Null,
+        };
+        FilterValue::String(Cow::Borrowed(value.unwrap_or("")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(visit: Visit<'_>) -> String {
+        Channels::default().classify(&visit)
+    }
+
+    #[test]
+    fn attributes_the_default_channels() {
+        assert_eq!(channel(Visit::default()), DIRECT);
+        assert_eq!(
+            channel(Visit {
+                referrer_host: Some("google.com"),
+                referrer_group: Some("Search"),
+                ..Default::default()
+            }),
+            "Organic Search"
+        );
+        assert_eq!(
+            channel(Visit {
+                utm_source: Some("Google"),
+                utm_medium: Some("CPC"),
+                ..Default::default()
+            }),
+            "Paid Search"
+        );
+        assert_eq!(
+            channel(Visit {
+                referrer_group: Some("Social"),
+                utm_medium: Some("paid_social"),
+                ..Default::default()
+            }),
+            "Paid Social"
+        );
+        assert_eq!(
+            channel(Visit {
+                utm_medium: Some("newsletter"),
+                ..Default::default()
+            }),
+            "Email"
+        );
+        assert_eq!(
+            channel(Visit {
+                utm_medium: Some("affiliate"),
+                utm_source: Some("partner-site"),
+                ..Default::default()
+            }),
+            "Affiliates"
+        );
+        assert_eq!(
+            channel(Visit {
+                referrer_host: Some("blog.example.org"),
+                ..Default::default()
+            }),
+            "Referral"
+        );
+    }
+
+    #[test]
+    fn configured_rules_replace_the_defaults() {
+        let channels = Channels::new(&[ChannelRule {
+            channel: "Partners".into(),
+            when: r#"referrer contains "partner.example""#.into(),
+        }])
+        .unwrap();
+        let partner = Visit {
+            referrer_host: Some("shop.partner.example"),
+            ..Default::default()
+        };
+        assert_eq!(channels.classify(&partner), "Partners");
+        let search = Visit {
+            referrer_group: Some("Search"),
+            ..Default::default()
+        };
+        assert_eq!(channels.classify(&search), DIRECT);
+
+        assert_ne!(channels.version(), Channels::default().version());
+        assert!(
+            Channels::new(&[ChannelRule {
+                channel: "Broken".into(),
+                when: "&& ||".into(),
+            }])
+            .is_err()
+        );
+    }
+}
@@ -1,6 +1,7 @@
-//! Periodically seal the redb hot window into date-partitioned Parquet files and
-//! enforce retention. Reads-then-writes-then-deletes so a write failure never
-//! loses data.
+//! Periodically seal the redb hot window into date-partitioned Parquet files,
+//! indexing the archived exceptions for search, and enforce retention,
+//! rebuilding the exception indexes when it drops partitions.
+//! Reads-then-writes-then-deletes so a write failure never loses data.
 
 use std::collections::BTreeMap;
 use std::path::Path;
@@ -13,7 +14,7 @@ use tracing_batteries::prelude::*;
 
 use crate::config::StorageConfig;
 use crate::errors::Result;
-use crate::store::{Store, StoredEvent, write_partition};
+use crate::store::{EventKind, Store, StoredEvent, write_partition};
 
 pub(super) async fn run(store: Arc<Store>, storage: StorageConfig) {
     // Honour the configured interval; floor at 1s only to avoid a busy loop if it is
@@ -36,10 +37,15 @@ pub(super) async fn run(store: Arc<Store>, storage: StorageConfig) {
 }
 
 fn compact_once(store: &Store, storage: &StorageConfig) -> Result<usize> {
+    let archive = store.lock_archive();
     let now = Utc::now().timestamp_millis();
     let cutoff = now - storage.hot_window.as_millis() as i64;
     let written = compact_window(store, Path::new(&storage.parquet_dir), cutoff, now)?;
-    enforce_retention(storage);
+    // The indexes would otherwise keep finding groups whose every occurrence
+    // has expired.
+    if enforce_retention(storage) > 0 {
+        super::search::reindex_exceptions(store, storage, &archive)?;
+    }
     Ok(written)
 }
 
@@ -57,9 +63,13 @@ fn compact_window(store: &Store, parquet_dir: &Path, cutoff_ms: i64, stamp: i64)
     // Group by UTC date so each partition holds one day's events; remember the exact
     // keys to delete once they are safely archived.
     let mut keys: Vec<Vec<u8>> = Vec::with_capacity(pairs.len());
+    let mut exceptions: Vec<StoredEvent> = Vec::new();
     let mut by_date: BTreeMap<(i32, u32, u32), Vec<StoredEvent>> = BTreeMap::new();
     for (key, event) in pairs {
         keys.push(key);
+        if event.kind == EventKind::Exception {
+            exceptions.push(event.clone());
+        }
         let date = Utc
             .timestamp_millis_opt(event.received_ms)
             .single()
@@ -81,20 +91,24 @@ fn compact_window(store: &Store, parquet_dir: &Path, cutoff_ms: i64, stamp: i64)
         total += group.len();
     }
 
+    // Once archived, exceptions are only found by search through the index.
+    super::search::index_exceptions(store, &exceptions)?;
     store.delete_keys(&keys)?;
     Ok(total)
 }
 
 /// Best-effort deletion of day partitions older than the retention window.
-fn enforce_retention(storage: &StorageConfig) {
+/// Returns the number of days deleted.
+fn enforce_retention(storage: &StorageConfig) -> usize {
     let root = Path::new(&storage.parquet_dir);
     if !root.exists() {
-        return;
+        return 0;
     }
     let retention = chrono::Duration::from_std(storage.retention)
         .unwrap_or_else(|_| chrono::Duration::days(365));
     let cutoff = Utc::now() - retention;
 
+    let mut removed = 0;
     for year in dir_numbers(root) {
         let year_dir = root.join(format!("{year:04}"));
         for month in dir_numbers(&year_dir) {
@@ -104,12 +118,15 @@ fn enforce_retention(storage: &StorageConfig) {
                     continue;
                 };
                 let end_of_day = date.and_hms_opt(23, 59, 59).unwrap();
-                if Utc.from_utc_datetime(&end_of_day) < cutoff {
-                    let _ = std::fs::remove_dir_all(month_dir.join(format!("{day:02}")));
+                if Utc.from_utc_datetime(&end_of_day) < cutoff
+                    && std::fs::remove_dir_all(month_dir.join(format!("{day:02}"))).is_ok()
+                {
+                    removed += 1;
                 }
             }
         }
     }
+    removed
 }
 
 /// Numeric subdirectory names (year/month/day) under `dir`.
@@ -129,8 +146,6 @@ mod tests {
     use super::*;
     use std::sync::atomic::{AtomicU64, Ordering};
 
-    use crate::store::{EventKind, Store, StoredEvent};
-
     fn temp(suffix: &str) -> std::path::PathBuf {
         static COUNTER: AtomicU64 = AtomicU64::new(0);
         let n = COUNTER.fetch_add(1, Ordering::SeqCst);
@@ -158,14 +173,26 @@ mod tests {
         let redb = temp("redb");
         let parquet = temp("parquet");
         let store = Store::open(&redb).unwrap();
+        let exception = StoredEvent {
+            kind: EventKind::Exception,
+            exc_type: Some("TypeError".into()),
+            exc_message: Some("checkout failed".into()),
+            exc_group: Some("g1".into()),
+            ..event(2_000)
+        };
         store
-            .append_events(&[event(1_000), event(2_000), event(9_999_999_999_999)])
+            .append_events(&[event(1_000), exception, event(9_999_999_999_999)])
             .unwrap();
 
         // Cutoff excludes the far-future event.
         let written = compact_window(&store, &parquet, 5_000, 42).unwrap();
         assert_eq!(written, 2);
         assert_eq!(store.event_count().unwrap(), 1);
+        // The archived exception is indexed for search.
+        assert_eq!(
+            store.exception_groups_with_term("check").unwrap(),
+            [("https://example.com".to_string(), "g1".to_string())].into()
+        );
 
         let files: Vec<_> = walk(&parquet);
         assert_eq!(files.len(), 1, "one daily partition written");
@@ -179,6 +206,46 @@ mod tests {
         let _ = std::fs::remove_dir_all(&parquet);
     }
 
+    #[test]
+    fn expired_partitions_leave_the_exception_indexes() {
+        let redb = temp("redb");
+        let parquet = temp("parquet");
+        let store = Store::open(&redb).unwrap();
+        let storage = StorageConfig {
+            parquet_dir: parquet.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        store
+            .append_events(&[StoredEvent {
+                kind: EventKind::Exception,
+                exc_type: Some("TypeError".into()),
+                exc_message: Some("checkout failed".into()),
+                exc_group: Some("g1".into()),
+                ..event(2_000)
+            }])
+            .unwrap();
+
+        // Archived into a 1970 partition, which retention drops at once.
+        assert_eq!(compact_once(&store, &storage).unwrap(), 1);
+        assert!(walk(&parquet).is_empty());
+        assert!(
+            store
+                .exception_groups_with_term("check")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            store
+                .group_signatures("https://example.com")
+                .unwrap()
+                .is_empty()
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+        let _ = std::fs::remove_dir_all(&parquet);
+    }
+
     fn walk(dir: &Path) -> Vec<String> {
         let mut out = Vec::new();
         if let Ok(entries) = std::fs::read_dir(dir) {
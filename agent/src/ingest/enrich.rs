@@ -1,13 +1,16 @@
 //! Turn a beacon payload + request headers into an enriched, anonymized
-//! [`StoredEvent`], dropping bots. Raw IP/User-Agent/Accept-Language never appear
-//! in the result — only derived classes.
+//! [`StoredEvent`], dropping bots. Raw IP/User-Agent/Client Hints/Accept-Language
+//! never appear in the result — only derived classes.
 
 use std::collections::BTreeMap;
 
-use analytics_api::{BeaconKind, TrackEvent, website_source};
+use analytics_api::{BeaconKind, TrackEvent};
 use url::Url;
 
-use super::{geo, language, referrer, truncate, ua};
+use super::channel::Visit;
+use super::exception::clean_app_field;
+use super::{ClientHints, IngestRules, geo, language, truncate, ua};
+use crate::analytics::filter::FieldSet;
 use crate::store::{EventKind, StoredEvent};
 
 /// Caps on attacker-controlled hit-path text, mirroring the exception path. Bounds
@@ -18,12 +21,16 @@ const MAX_METADATA_ENTRIES: usize = 32;
 const MAX_METADATA_VALUE: usize = 1_024;
 
 /// Build an enriched event from a beacon payload. Returns `None` when the event
-/// should be dropped (bot, or an unparseable/host-less URL).
+/// should be dropped (bot, an unparseable/host-less URL, referrer spam, or
+/// matched by an `ingest.exclude` rule). The hostname is resolved through the source aliases
+/// and the pathname templated by the owning project's path rules.
 pub fn build_event(
     track: TrackEvent,
     user_agent: &str,
+    hints: &ClientHints,
     accept_language: Option<&str>,
     received_ms: i64,
+    rules: &IngestRules,
 ) -> Option<StoredEvent> {
     let url = Url::parse(&track.url).ok()?;
     let hostname = url.host_str()?.trim_start_matches("www.").to_lowercase();
@@ -33,12 +40,17 @@ pub fn build_event(
 
     // Bots (and UAs with nothing recognisable in them) are dropped; browsers
     // and application clients are kept.
-    let ua = ua::classify(user_agent);
+    let ua = ua::classify_with_hints(user_agent, hints);
     if ua.kind == ua::UaKind::Bot {
         return None;
     }
 
-    let referrer = referrer::classify(track.referrer.as_deref(), &hostname);
+    let referrer = rules
+        .referrers
+        .classify(track.referrer.as_deref(), &hostname);
+    if referrer.spam && rules.referrers.drops_spam() {
+        return None;
+    }
     let language = accept_language.and_then(language::primary_language);
     let country = track
         .timezone
@@ -46,21 +58,54 @@ pub fn build_event(
         .and_then(geo::country_from_timezone)
         .map(str::to_string);
     let (utm_source, utm_medium, utm_campaign) = extract_utm(&url);
+    let channel = rules.channels.classify(&Visit {
+        referrer_host: referrer.host.as_deref(),
+        referrer_group: referrer.group.as_deref(),
+        utm_source: utm_source.as_deref(),
+        utm_medium: utm_medium.as_deref(),
+        utm_campaign: utm_campaign.as_deref(),
+    });
 
     let kind = match track.kind {
         BeaconKind::Load => EventKind::PageLoad,
         BeaconKind::Unload => EventKind::PageUnload,
         BeaconKind::Custom => EventKind::Custom,
     };
+    // Only custom events carry a value; a currency without one means nothing.
+    let value = track
+        .value
+        .filter(|v| kind == EventKind::Custom && v.is_finite());
+    let currency = value.and(track.currency.as_deref().and_then(clean_currency));
+    // Engagement is client-measured: keep it within what the beacon itself
+    // claims (engaged time never exceeds time on page) and the page's bounds.
+    let engaged_ms = track.engaged_ms.map(|engaged| match track.duration_ms {
+        Some(duration) => engaged.clamp(0, duration.max(0)),
+        None => engaged.max(0),
+    });
+    let scroll_depth = track.scroll_depth.map(|depth| depth.clamp(0, 100));
+
+    let source = rules.aliases.source_for(&hostname);
+    let path = truncate(&normalize_path(url.path()), MAX_PATH);
+    let (pathname, original_pathname) = match rules.paths.for_source(&source) {
+        Some(paths) => match paths.rewrite(&path) {
+            Some(template) => (
+                truncate(&template, MAX_PATH),
+                paths.keep_original.then_some(path),
+            ),
+            None => (path, None),
+        },
+        None => (path, None),
+    };
 
-    Some(StoredEvent {
+    let event = StoredEvent {
         created_ms: received_ms,
         received_ms,
         bid: truncate(&track.beacon, MAX_FIELD),
         sid: clean_session(track.session.as_deref()),
         kind,
-        source: website_source(&hostname),
-        pathname: Some(truncate(&normalize_path(url.path()), MAX_PATH)),
+        source,
+        pathname: Some(pathname),
+        original_pathname,
         is_unique_user: track.unique_visit,
         is_unique_page: track.unique_page,
         referrer_host: referrer.host,
@@ -74,11 +119,18 @@ pub fn build_event(
         utm_source,
         utm_medium,
         utm_campaign,
+        channel: Some(channel),
         duration_ms: track.duration_ms,
+        engaged_ms,
+        scroll_depth,
         event_name: track.event_name.map(|n| truncate(&n, MAX_FIELD)),
         metadata_json: track.metadata.as_ref().and_then(serialize_metadata),
+        value,
+        currency,
+        app_version: clean_app_field(track.app_version.as_deref()),
         ..Default::default()
-    })
+    };
+    (!rules.exclusions.excludes(&event, FieldSet::Dashboard)).then_some(event)
 }
 
 fn extract_utm(url: &Url) -> (Option<String>, Option<String>, Option<String>) {
@@ -107,6 +159,13 @@ pub(super) fn clean_session(session: Option<&str>) -> Option<String> {
         .map(|s| truncate(s, MAX_FIELD))
 }
 
+/// Accept a three-letter (ISO 4217-shaped) currency code, upper-cased.
+fn clean_currency(currency: &str) -> Option<String> {
+    let currency = currency.trim();
+    (currency.len() == 3 && currency.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| currency.to_ascii_uppercase())
+}
+
 /// Normalize a path: keep case, drop a trailing slash, guarantee a leading one.
 fn normalize_path(path: &str) -> String {
     let trimmed = path.trim_end_matches('/');
@@ -134,13 +193,28 @@ fn serialize_metadata(meta: &BTreeMap<String, String>) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
+    use analytics_api::{PathPatternKind, PathRule};
+
     use super::*;
+    use crate::ingest::{Exclusions, PathRewrites};
 
     fn chrome() -> &'static str {
         "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
          (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"
     }
 
+    /// A hit with no ingest rules configured.
+    fn build(track: TrackEvent, user_agent: &str) -> Option<StoredEvent> {
+        build_event(
+            track,
+            user_agent,
+            &ClientHints::default(),
+            None,
+            1,
+            &IngestRules::default(),
+        )
+    }
+
     fn base(url: &str) -> TrackEvent {
         TrackEvent {
             beacon: "b1".into(),
@@ -152,8 +226,13 @@ mod tests {
             unique_page: true,
             timezone: Some("America/New_York".into()),
             duration_ms: None,
+            engaged_ms: None,
+            scroll_depth: None,
             event_name: None,
             metadata: None,
+            value: None,
+            currency: None,
+            app_version: None,
         }
     }
 
@@ -162,8 +241,10 @@ mod tests {
         let e = build_event(
             base("https://www.example.com/Blog/Post?utm_source=news&x=1"),
             chrome(),
+            &ClientHints::default(),
             Some("en-US,en;q=0.9"),
             1000,
+            &IngestRules::default(),
         )
         .expect("event");
         assert_eq!(e.source, "https://example.com");
@@ -171,6 +252,8 @@ mod tests {
         assert_eq!(e.country.as_deref(), Some("US"));
         assert_eq!(e.language.as_deref(), Some("en"));
         assert_eq!(e.utm_source.as_deref(), Some("news"));
+        // Tagged but from no known network: a referral.
+        assert_eq!(e.channel.as_deref(), Some("Referral"));
         assert_eq!(e.ua_browser.as_deref(), Some("Chrome"));
         assert_eq!(e.sid.as_deref(), Some("s1"));
         assert!(e.is_unique_user);
@@ -180,24 +263,91 @@ mod tests {
     fn blank_session_ids_are_dropped() {
         let mut track = base("https://example.com/");
         track.session = Some("   ".into());
-        let e = build_event(track, chrome(), None, 1).expect("event");
+        let e = build(track, chrome()).expect("event");
         assert_eq!(e.sid, None);
     }
 
+    #[test]
+    fn hits_carry_the_app_version() {
+        let mut track = base("https://example.com/");
+        track.app_version = Some(" 2.4.1 ".into());
+        let e = build(track, chrome()).expect("event");
+        assert_eq!(e.app_version.as_deref(), Some("2.4.1"));
+    }
+
     #[test]
     fn drops_bots_and_bad_urls() {
-        assert!(build_event(base("https://example.com/"), "Googlebot/2.1", None, 1).is_none());
-        assert!(build_event(base("https://example.com/"), "", None, 1).is_none());
-        assert!(build_event(base("not a url"), chrome(), None, 1).is_none());
+        assert!(build(base("https://example.com/"), "Googlebot/2.1").is_none());
+        assert!(build(base("https://example.com/"), "").is_none());
+        assert!(build(base("not a url"), chrome()).is_none());
+    }
+
+    #[test]
+    fn drops_referrer_spam_by_default() {
+        let spam = TrackEvent {
+            referrer: Some("https://semalt.com/".into()),
+            ..base("https://example.com/")
+        };
+        assert!(build(spam, chrome()).is_none());
+    }
+
+    #[test]
+    fn drops_events_matching_exclusion_rules() {
+        let rules = IngestRules {
+            exclusions: Exclusions::new(&[r#"path like "/admin/*""#.to_string()]).unwrap(),
+            ..Default::default()
+        };
+        let admin = base("https://example.com/admin/users");
+        assert!(build_event(admin, chrome(), &ClientHints::default(), None, 1, &rules).is_none());
+        let home = base("https://example.com/");
+        assert!(build_event(home, chrome(), &ClientHints::default(), None, 1, &rules).is_some());
+        assert_eq!(rules.exclusions.counts()[0].1, 1);
+    }
+
+    #[test]
+    fn templates_paths_with_project_rules() {
+        let rule = PathRule {
+            kind: PathPatternKind::Glob,
+            pattern: "/orders/*/items/*".into(),
+            template: "/orders/:id/items/:id".into(),
+        };
+        let order = || base("https://example.com/orders/8423/items/19");
+        let build_with = |paths: PathRewrites| {
+            let rules = IngestRules {
+                paths,
+                ..Default::default()
+            };
+            build_event(order(), chrome(), &ClientHints::default(), None, 1, &rules).expect("event")
+        };
+
+        let e = build_with(PathRewrites::with_rules(
+            "https://example.com",
+            &[rule.clone()],
+            false,
+        ));
+        assert_eq!(e.pathname.as_deref(), Some("/orders/:id/items/:id"));
+        assert_eq!(e.original_pathname, None);
+
+        let kept = build_with(PathRewrites::with_rules(
+            "https://example.com",
+            &[rule.clone()],
+            true,
+        ));
+        assert_eq!(
+            kept.original_pathname.as_deref(),
+            Some("/orders/8423/items/19")
+        );
+
+        // Rules only apply to the owning project's sources.
+        let other = build_with(PathRewrites::with_rules("https://other.com", &[rule], true));
+        assert_eq!(other.pathname.as_deref(), Some("/orders/8423/items/19"));
     }
 
     #[test]
     fn keeps_application_clients() {
-        let e = build_event(
+        let e = build(
             base("https://example.com/"),
             "MyApp/2.4.1 (Windows NT 10.0; Win64; x64)",
-            None,
-            1,
         )
         .expect("event");
         assert_eq!(e.ua_browser.as_deref(), Some("MyApp"));
@@ -217,7 +367,7 @@ mod tests {
         }
         track.metadata = Some(metadata);
 
-        let e = build_event(track, chrome(), None, 1).expect("event");
+        let e = build(track, chrome()).expect("event");
 
         // The ellipsis marker adds 3 bytes to a byte-boundary truncation.
         assert!(e.bid.len() <= MAX_FIELD + 3);
@@ -228,4 +378,47 @@ mod tests {
         assert_eq!(parsed.len(), MAX_METADATA_ENTRIES);
         assert!(parsed.values().all(|v| v.len() <= MAX_METADATA_VALUE + 3));
     }
+
+    #[test]
+    fn keeps_values_on_custom_events_only() {
+        let mut track = base("https://example.com/checkout");
+        track.kind = BeaconKind::Custom;
+        track.event_name = Some("purchase".into());
+        track.value = Some(42.5);
+        track.currency = Some(" eur ".into());
+        let e = build(track.clone(), chrome()).expect("event");
+        assert_eq!(e.value, Some(42.5));
+        assert_eq!(e.currency.as_deref(), Some("EUR"));
+
+        track.currency = Some("euros".into());
+        let e = build(track.clone(), chrome()).expect("event");
+        assert_eq!(e.currency, None);
+
+        track.value = Some(f64::NAN);
+        let e = build(track.clone(), chrome()).expect("event");
+        assert_eq!((e.value, e.currency), (None, None));
+
+        track.kind = BeaconKind::Load;
+        track.value = Some(1.0);
+        let e = build(track, chrome()).expect("event");
+        assert_eq!(e.value, None);
+    }
+
+    #[test]
+    fn bounds_engagement_to_the_beacon() {
+        let mut track = base("https://example.com/docs");
+        track.kind = BeaconKind::Unload;
+        track.duration_ms = Some(10_000);
+        track.engaged_ms = Some(60_000);
+        track.scroll_depth = Some(140);
+        let e = build(track.clone(), chrome()).expect("event");
+        assert_eq!(e.engaged_ms, Some(10_000));
+        assert_eq!(e.scroll_depth, Some(100));
+
+        track.engaged_ms = Some(-5);
+        track.scroll_depth = Some(-1);
+        let e = build(track, chrome()).expect("event");
+        assert_eq!(e.engaged_ms, Some(0));
+        assert_eq!(e.scroll_depth, Some(0));
+    }
 }
@@ -1,16 +1,20 @@
 //! Build anonymized `Exception` events from reports, with Sentry-style grouping.
 
-use analytics_api::{ExceptionReport, summary_line, website_source};
+use analytics_api::{ExceptionLevel, ExceptionReport, summary_line};
 use sha2::{Digest, Sha256};
 use url::Url;
 
-use super::{normalize, truncate, ua};
+use super::breadcrumbs::clean_breadcrumbs;
+use super::grouping::ProjectGrouping;
+use super::{ClientHints, IngestRules, normalize, stack, truncate, ua};
+use crate::analytics::filter::FieldSet;
 use crate::store::{EventKind, StoredEvent};
 
 const TOP_FRAMES: usize = 5;
 const MAX_MESSAGE: usize = 1_000;
 const MAX_STACK: usize = 16_000;
-const MAX_APP_FIELD: usize = 120;
+/// The longest app name or version kept; longer values are truncated.
+pub const MAX_APP_FIELD: usize = 120;
 
 /// The version of the exception grouping rules — the fingerprint logic in this
 /// module together with the `normalize` pipeline. Bump this whenever a change would
@@ -18,14 +22,21 @@ const MAX_APP_FIELD: usize = 120;
 /// the mismatch and re-groups its stored occurrences (see
 /// `ingest::regroup_if_needed`). A store predating this marker reports `0`, so the
 /// initial value of `1` re-groups it once under the current aggressive rules.
-pub const FINGERPRINT_VERSION: u32 = 1;
+///
+/// v2: library and runtime frames are skipped when a stack has in-app frames.
+pub const FINGERPRINT_VERSION: u32 = 2;
 
 /// Build an `Exception` event from a report. Returns `None` for bots or an
-/// unparseable URL (we attribute exceptions to a source by hostname).
+/// unparseable URL (we attribute exceptions to a source by hostname), when an
+/// `ingest.exclude` rule or one of the project's inbound filters matches (see
+/// [`ExceptionFilters`](super::ExceptionFilters)), or when storm protection only
+/// counts the occurrence (see [`ExceptionSampler`](super::ExceptionSampler)).
 pub fn build_exception(
     report: ExceptionReport,
     user_agent: &str,
+    hints: &ClientHints,
     received_ms: i64,
+    rules: &IngestRules,
 ) -> Option<StoredEvent> {
     let url = Url::parse(&report.url).ok()?;
     let hostname = url.host_str()?.trim_start_matches("www.").to_lowercase();
@@ -33,25 +44,19 @@ pub fn build_exception(
         return None;
     }
 
-    let ua = ua::classify(user_agent);
+    let ua = ua::classify_with_hints(user_agent, hints);
     if ua.kind == ua::UaKind::Bot {
         return None;
     }
 
-    let group = fingerprint(
-        &report.exc_type,
-        &report.message,
-        report.stack.as_deref(),
-        report.fingerprint.as_deref(),
-    );
-
-    Some(StoredEvent {
+    let message = truncate(&report.message, MAX_MESSAGE);
+    let mut event = StoredEvent {
         created_ms: received_ms,
         received_ms,
         bid: report.beacon.unwrap_or_default(),
         sid: super::enrich::clean_session(report.session.as_deref()),
         kind: EventKind::Exception,
-        source: website_source(&hostname),
+        source: rules.aliases.source_for(&hostname),
         is_unique_user: false,
         is_unique_page: false,
         ua_browser: ua.app,
@@ -63,34 +68,100 @@ pub fn build_exception(
             .as_ref()
             .filter(|m| !m.is_empty())
             .and_then(|m| serde_json::to_string(m).ok()),
+        breadcrumbs_json: clean_breadcrumbs(report.breadcrumbs),
         app_version: clean_app_field(report.app_version.as_deref()),
         exc_type: Some(truncate(&report.exc_type, MAX_MESSAGE)),
-        exc_message: Some(truncate(&report.message, MAX_MESSAGE)),
-        exc_stack: report.stack.map(|s| truncate(&s, MAX_STACK)),
-        exc_group: Some(group),
+        exc_message: Some(message.clone()),
+        exc_stack: report.stack.as_deref().map(|s| truncate(s, MAX_STACK)),
         exc_handled: Some(report.handled),
+        exc_level: Some(
+            report
+                .level
+                .as_deref()
+                .and_then(ExceptionLevel::parse)
+                .unwrap_or_default()
+                .as_str()
+                .to_string(),
+        ),
+        environment: clean_app_field(report.environment.as_deref()).map(|e| e.to_lowercase()),
         ..Default::default()
-    })
+    };
+    // Filter before symbolicating, so noise costs no source map work. The
+    // filters see the stack as the client sent it, which is what the
+    // extension and third-party presets look for.
+    if rules.exclusions.excludes(&event, FieldSet::Exceptions)
+        || rules.inbound.discards(&event, &rules.aliases)
+    {
+        return None;
+    }
+
+    // Symbolicate ahead of fingerprinting so minified bundles group on their
+    // original function names.
+    let stack = report.stack.map(|stack| {
+        rules
+            .sourcemaps
+            .symbolicate(&event.source, event.app_version.as_deref(), &stack)
+            .unwrap_or(stack)
+    });
+
+    // The override is stored as received (trimmed and capped) so re-grouping
+    // hashes exactly what ingest did.
+    let override_fp = report
+        .fingerprint
+        .as_deref()
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| truncate(f, MAX_MESSAGE));
+    let group = fingerprint(
+        &report.exc_type,
+        &report.message,
+        stack.as_deref(),
+        override_fp.as_deref(),
+        rules.grouping.for_source(&event.source).as_deref(),
+    );
+    // Manual merges and splits match what is stored, so re-grouping agrees.
+    let stack = stack.map(|s| truncate(&s, MAX_STACK));
+    let group = rules
+        .edits
+        .resolve(&event.source, group, &message, stack.as_deref());
+    event.exc_stack = stack;
+    event.exc_group = Some(group);
+    event.exc_fingerprint = override_fp;
+
+    // Unhandled occurrences mark their session crashed, which the sampling
+    // must not hide.
+    rules.sampling.admit(event, !report.handled)
 }
 
-/// Trim and cap a client-reported app name/version, dropping empty values.
-fn clean_app_field(value: Option<&str>) -> Option<String> {
+/// Trim and cap a client-reported app name, version or environment, dropping
+/// empty values.
+pub(super) fn clean_app_field(value: Option<&str>) -> Option<String> {
     value
         .map(str::trim)
         .filter(|v| !v.is_empty())
         .map(|v| truncate(v, MAX_APP_FIELD))
 }
 
-/// Compute a stable grouping fingerprint: a client override if given, otherwise a
-/// hash of the type + normalized top stack frames (falling back to the normalized
-/// message when there is no stack).
+/// Compute a stable grouping fingerprint: the group named by a matching project
+/// rule or a client override if given, otherwise a hash of the type + normalized
+/// top stack frames, preferring in-app frames and skipping those the project's
+/// rules ignore (falling back to the normalized message when there is no stack).
+///
+/// A rule's group hashes exactly like a client override of the same name, so
+/// moving an override from client code into a rule keeps the group.
 pub fn fingerprint(
     exc_type: &str,
     message: &str,
     stack: Option<&str>,
     override_fp: Option<&str>,
+    rules: Option<&ProjectGrouping>,
 ) -> String {
-    if let Some(fp) = override_fp.map(str::trim).filter(|f| !f.is_empty()) {
+    let ruled = rules.and_then(|rules| rules.group_for(exc_type.trim(), message));
+    if let Some(fp) = ruled
+        .or(override_fp)
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+    {
         return short_hash(fp.as_bytes());
     }
 
@@ -100,7 +171,10 @@ pub fn fingerprint(
 
     match stack.map(str::trim).filter(|s| !s.is_empty()) {
         Some(stack) => {
-            for frame in normalize::frames(stack).into_iter().take(TOP_FRAMES) {
+            for frame in normalize::frames(&grouping_lines(stack, rules))
+                .into_iter()
+                .take(TOP_FRAMES)
+            {
                 hasher.update(frame.as_bytes());
                 hasher.update(b"\n");
             }
@@ -114,8 +188,36 @@ pub fn fingerprint(
     short_hash(&hasher.finalize())
 }
 
+/// The stack lines a group keys off. Frames the project's rules ignore are
+/// dropped; then, when any remaining frame is the application's own, runtime and
+/// dependency frames are too, so the top frames are the code that failed rather
+/// than the framework that called it (and upgrading that framework doesn't
+/// split the group). Lines that aren't frames, such as a `caused by:` chain, are
+/// always kept.
+fn grouping_lines(stack: &str, rules: Option<&ProjectGrouping>) -> String {
+    let (ignored, frames): (Vec<_>, Vec<_>) = stack::parse(stack)
+        .into_iter()
+        .partition(|f| rules.is_some_and(|rules| rules.ignores_frame(&f.frame)));
+    let in_app = frames.iter().any(|f| f.frame.in_app);
+    let dropped: Vec<_> = ignored
+        .iter()
+        .chain(frames.iter().filter(|f| in_app && !f.frame.in_app))
+        .map(|f| f.lines.clone())
+        .collect();
+    if dropped.is_empty() {
+        return stack.to_string();
+    }
+    stack
+        .lines()
+        .enumerate()
+        .filter(|(i, _)| !dropped.iter().any(|lines| lines.contains(i)))
+        .map(|(_, line)| line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// First 8 bytes of (a hash of) the input, as 16 hex chars.
-fn short_hash(data: &[u8]) -> String {
+pub(super) fn short_hash(data: &[u8]) -> String {
     let digest = Sha256::digest(data);
     digest[..8].iter().map(|b| format!("{b:02x}")).collect()
 }
@@ -131,34 +233,83 @@ mod tests {
             "x is undefined",
             Some("at handler (app.js:42:10)\nat main (app.js:99:3)"),
             None,
+            None,
         );
         let b = fingerprint(
             "TypeError",
             "x is undefined",
             Some("at handler (app.js:43:18)\nat main (app.js:120:7)"),
             None,
+            None,
         );
         assert_eq!(a, b);
     }
 
     #[test]
     fn different_types_group_apart() {
-        let a = fingerprint("TypeError", "boom", None, None);
-        let b = fingerprint("RangeError", "boom", None, None);
+        let a = fingerprint("TypeError", "boom", None, None, None);
+        let b = fingerprint("RangeError", "boom", None, None, None);
         assert_ne!(a, b);
     }
 
     #[test]
     fn client_override_is_respected() {
-        let a = fingerprint("TypeError", "a", Some("frame1"), Some("custom"));
-        let b = fingerprint("RangeError", "b", Some("frame2"), Some("custom"));
+        let a = fingerprint("TypeError", "a", Some("frame1"), Some("custom"), None);
+        let b = fingerprint("RangeError", "b", Some("frame2"), Some("custom"), None);
         assert_eq!(a, b);
     }
 
+    #[test]
+    fn project_rules_override_grouping() {
+        use analytics_api::FingerprintRule;
+
+        let rules = ProjectGrouping::with_rules(&[
+            FingerprintRule::Group {
+                exc_type: Some("ChunkLoadError".into()),
+                message: None,
+                group: "chunk-load".into(),
+            },
+            FingerprintRule::IgnoreFrames {
+                module: "*/retry.js".into(),
+            },
+        ]);
+        // A group rule wins over both the stack and a client override, and
+        // hashes like an override of the same name.
+        let ruled = fingerprint(
+            "ChunkLoadError",
+            "Loading chunk 7 failed",
+            Some("at load (app.js:1:1)"),
+            Some("custom"),
+            Some(&rules),
+        );
+        assert_eq!(
+            ruled,
+            fingerprint("Error", "x", None, Some("chunk-load"), None)
+        );
+
+        let wrapped = fingerprint(
+            "TypeError",
+            "x is undefined",
+            Some(
+                "at attempt (https://example.com/retry.js:5:1)\nat total (https://example.com/cart.js:42:9)",
+            ),
+            None,
+            Some(&rules),
+        );
+        let direct = fingerprint(
+            "TypeError",
+            "x is undefined",
+            Some("at total (https://example.com/cart.js:42:9)"),
+            None,
+            Some(&rules),
+        );
+        assert_eq!(wrapped, direct);
+    }
+
     #[test]
     fn message_only_fallback_ignores_numbers() {
-        let a = fingerprint("Error", "HTTP 404 from /api", None, None);
-        let b = fingerprint("Error", "HTTP 500 from /api", None, None);
+        let a = fingerprint("Error", "HTTP 404 from /api", None, None, None);
+        let b = fingerprint("Error", "HTTP 500 from /api", None, None, None);
         assert_eq!(a, b);
     }
 
@@ -171,12 +322,14 @@ mod tests {
             "Failed to parse configuration file.\n\nCaused by:\n - filter at line 1, column 19",
             None,
             None,
+            None,
         );
         let b = fingerprint(
             "ConfigError",
             "Failed to parse configuration file.\n\nCaused by:\n - filter at line 8, column 42\n - make sure brackets are closed",
             None,
             None,
+            None,
         );
         assert_eq!(a, b);
     }
@@ -206,6 +359,7 @@ mod tests {
                 "https://git.raptor-perch.ts.net/api/v1/repos/sierrasoftworks/update-go/releases/tags/v1.1.0",
             )),
             None,
+            None,
         );
         let b = fingerprint(
             "human_errors::error::Error",
@@ -214,6 +368,7 @@ mod tests {
                 "https://git.other-host.ts.net/api/v1/repos/sierrasoftworks/backup/releases/tags/v9.9.9",
             )),
             None,
+            None,
         );
         assert_eq!(a, b);
     }
@@ -227,6 +382,7 @@ mod tests {
             "System failure",
             Some(&transport_stack("https://host/a")),
             None,
+            None,
         );
         let dns = fingerprint(
             "human_errors::error::Error",
@@ -239,10 +395,39 @@ mod tests {
                     .to_string(),
             ),
             None,
+            None,
         );
         assert_ne!(reset, dns);
     }
 
+    #[test]
+    fn library_frames_do_not_fragment_groups() {
+        // A dependency upgrade reshuffles the framework frames above the
+        // application's own; the group follows the in-app frames.
+        let a = fingerprint(
+            "TypeError",
+            "x is undefined",
+            Some(
+                "at dispatch (https://cdn.example.com/node_modules/react-dom/index.js:10:5)\n\
+                 at total (https://cdn.example.com/src/cart.js:42:9)",
+            ),
+            None,
+            None,
+        );
+        let b = fingerprint(
+            "TypeError",
+            "x is undefined",
+            Some(
+                "at runWithPriority (https://cdn.example.com/node_modules/scheduler/index.js:3:1)\n\
+                 at dispatch (https://cdn.example.com/node_modules/react-dom/index.js:12:5)\n\
+                 at total (https://cdn.example.com/src/cart.js:42:9)",
+            ),
+            None,
+            None,
+        );
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn hashed_bundle_urls_do_not_fragment_browser_frames() {
         // Cache-busted bundle filenames change every deploy; grouping should key
@@ -252,12 +437,14 @@ mod tests {
             "x is undefined",
             Some("at render (https://cdn.example.com/static/app.abc123.js:10:5)"),
             None,
+            None,
         );
         let b = fingerprint(
             "TypeError",
             "x is undefined",
             Some("at render (https://cdn.example.com/static/app.def456.js:88:3)"),
             None,
+            None,
         );
         assert_eq!(a, b);
     }
@@ -270,12 +457,14 @@ mod tests {
             "error sending request for url (https://a.example.com/x/v1.1.0)",
             None,
             None,
+            None,
         );
         let b = fingerprint(
             "Error",
             "error sending request for url (https://b.other.net/y/v2.0.0)",
             None,
             None,
+            None,
         );
         assert_eq!(a, b);
     }
@@ -289,12 +478,14 @@ mod tests {
             "Maximum call stack size exceeded",
             Some(&format!("{}at main (index.js:1:1)", "at walk (tree.js:9:3)\n".repeat(3))),
             None,
+            None,
         );
         let deep = fingerprint(
             "RangeError",
             "Maximum call stack size exceeded",
             Some(&format!("{}at main (index.js:1:1)", "at walk (tree.js:9:3)\n".repeat(400))),
             None,
+            None,
         );
         assert_eq!(shallow, deep);
     }
@@ -307,12 +498,14 @@ mod tests {
             "request 550e8400-e29b-41d4-a716-446655440000 failed",
             None,
             None,
+            None,
         );
         let b = fingerprint(
             "human_errors::error::Error",
             "request 7c9e6679-7425-40de-944b-e07fc1f90ae7 failed",
             None,
             None,
+            None,
         );
         assert_eq!(a, b);
     }
@@ -326,12 +519,14 @@ mod tests {
             "index out of bounds",
             Some("at bender::store::read (/home/alice/bender/src/store.rs:88:14)\nat bender::main (/home/alice/bender/src/main.rs:24:5)"),
             None,
+            None,
         );
         let ci = fingerprint(
             "panic",
             "index out of bounds",
             Some("at bender::store::read (/build/12345/bender/src/store.rs:88:14)\nat bender::main (/build/12345/bender/src/main.rs:24:5)"),
             None,
+            None,
         );
         assert_eq!(dev, ci);
     }
@@ -0,0 +1,481 @@
+//! Operator-configured exclusion rules (`ingest.exclude`): filt-rs expressions
+//! evaluated against each enriched event, dropping matches before they reach the
+//! store. Rules speak the same field vocabulary as the dashboard and exception
+//! queries ([`FieldSet`]), so a query that isolates unwanted traffic can be
+//! pasted into the config as-is. A rule applies to the events whose vocabulary
+//! has every field it reads: `path` rules to page events, `type` rules to
+//! exceptions, `browser` rules to both.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use filt_rs::{
+    BinaryOperator, CompiledRegex, Expr as FilterNode, ExprVisitor, Filter, FilterValue,
+    Filterable, Function, Glob, LogicalOperator, UnaryOperator,
+};
+
+use crate::analytics::filter::{FieldSet, source_matches};
+use crate::errors::{Result, ResultExt};
+use crate::store::{Store, StoredEvent};
+
+struct Rule {
+    expression: String,
+    filter: Filter,
+    /// The vocabularies that have every field the rule reads.
+    fields: Vec<FieldSet>,
+    sources: Vec<String>,
+    matches: AtomicU64,
+}
+
+/// The parsed `ingest.exclude` rules and how many events each has removed since
+/// startup, plus the project each source belongs to (so `project == "…"` rules
+/// match as they do in queries).
+#[derive(Default)]
+pub struct Exclusions {
+    rules: Vec<Rule>,
+    projects: RwLock<HashMap<String, String>>,
+}
+
+impl Exclusions {
+    /// Parse and check every rule up front, so a typo — in the syntax or in a
+    /// field name — fails at startup rather than silently letting traffic
+    /// through (or, negated, dropping everything).
+    pub fn new(expressions: &[String]) -> Result<Self> {
+        let rules = expressions
+            .iter()
+            .map(|expression| {
+                let filter = Filter::new(expression.as_str()).wrap_user_err(
+                    format!("The `ingest.exclude` rule `{expression}` is not a valid filter expression."),
+                    &[
+                        "Rules use the dashboard query syntax, e.g. path like \"/admin/*\".",
+                        "String literals use double quotes; wrap each rule in single quotes in YAML.",
+                    ],
+                )?;
+                let checks = [FieldSet::Dashboard, FieldSet::Exceptions]
+                    .map(|fields| (fields, check_fields(&filter, fields)));
+                if let [(_, Err(page)), (_, Err(exception))] = &checks {
+                    return Err(human_errors::user(
+                        format!(
+                            "The `ingest.exclude` rule `{expression}` can't match any event: for page events, {page}; for exceptions, {exception}."
+                        ),
+                        &[
+                            "Rules use the dashboard's fields for page events and the exception inbox's for exceptions.",
+                            "A rule may only mix fields both have in common (source, browser, version, os, device, project).",
+                        ],
+                    ));
+                }
+                Ok(Rule {
+                    expression: expression.clone(),
+                    sources: source_names(&filter),
+                    filter,
+                    fields: checks
+                        .into_iter()
+                        .filter(|(_, checked)| checked.is_ok())
+                        .map(|(fields, _)| fields)
+                        .collect(),
+                    matches: AtomicU64::new(0),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            projects: RwLock::default(),
+        })
+    }
+
+    /// Rebuild the `source → project name` map from the stored projects and
+    /// source assignments.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let names: HashMap<String, String> = store
+            .list_projects()?
+            .into_iter()
+            .map(|project| (project.id, project.name))
+            .collect();
+        let projects = store
+            .list_sources()?
+            .into_iter()
+            .filter_map(|source| {
+                let name = names.get(source.project_id.as_deref()?)?;
+                Some((source.uri, name.clone()))
+            })
+            .collect();
+        *self.projects.write().unwrap_or_else(|e| e.into_inner()) = projects;
+        Ok(())
+    }
+
+    /// Whether `event` should be dropped. Only rules over `fields` apply, and
+    /// only the first matching rule is counted, so the per-rule totals add up
+    /// to the number of events removed. A rule that fails to evaluate never
+    /// excludes anything.
+    pub fn excludes(&self, event: &StoredEvent, fields: FieldSet) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let projects = self.projects.read().unwrap_or_else(|e| e.into_inner());
+        let project = projects.get(&event.source).map(String::as_str);
+        let matched = self
+            .rules
+            .iter()
+            .filter(|rule| rule.fields.contains(&fields))
+            .find(|rule| {
+                let view = EventFields {
+                    event,
+                    fields,
+                    project,
+                    sources: &rule.sources,
+                };
+                rule.filter.matches(&view).unwrap_or(false)
+            });
+        match matched {
+            Some(rule) => {
+                rule.matches.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `(rule, events removed)` for every rule, in configuration order.
+    pub fn counts(&self) -> Vec<(String, u64)> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                (
+                    rule.expression.clone(),
+                    rule.matches.load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A [`StoredEvent`] seen through a query field set. Absent dimensions read as
+/// the empty string, matching the query compiler (`country == ""` selects events
+/// without one); unknown properties are `null`. Projects are never stored on the
+/// event, so the caller resolves the source's project name.
+///
+/// `sources` are the rule's scheme-less [source names](source_names): the
+/// source reads as whichever of them it [matches](source_matches), so
+/// `source == "example.com"` selects `https://example.com` as a query does.
+pub(super) struct EventFields<'a> {
+    pub(super) event: &'a StoredEvent,
+    pub(super) fields: FieldSet,
+    pub(super) project: Option<&'a str>,
+    pub(super) sources: &'a [String],
+}
+
+impl Filterable for EventFields<'_> {
+    fn get(&self, key: &str) -> FilterValue<'_> {
+        if key.eq_ignore_ascii_case("project") {
+            return FilterValue::String(Cow::Borrowed(self.project.unwrap_or("")));
+        }
+        let Some(field) = self.fields.column(key) else {
+            return FilterValue::Null;
+        };
+        if field.boolean {
+            return match field.column {
+                "exc_handled" => FilterValue::Bool(self.event.exc_handled.unwrap_or(false)),
+                _ => FilterValue::Null,
+            };
+        }
+        let e = self.event;
+        let value = match field.column {
+            "source" => Some(
+                self.sources
+                    .iter()
+                    .find(|name| source_matches(&e.source, name))
+                    .map_or(e.source.as_str(), String::as_str),
+            ),
+            "pathname" => e.pathname.as_deref(),
+            "referrer_host" => e.referrer_host.as_deref(),
+            "country" => e.country.as_deref(),
+            "language" => e.language.as_deref(),
+            "ua_browser" => e.ua_browser.as_deref(),
+            "ua_version" => e.ua_version.as_deref(),
+            "ua_os" => e.ua_os.as_deref(),
+            "ua_device" => e.ua_device.as_deref(),
+            "utm_source" => e.utm_source.as_deref(),
+            "utm_medium" => e.utm_medium.as_deref(),
+            "utm_campaign" => e.utm_campaign.as_deref(),
+            "channel" => e.channel.as_deref(),
+            "event_name" => e.event_name.as_deref(),
+            "app_version" => e.app_version.as_deref(),
+            "exc_type" => e.exc_type.as_deref(),
+            "exc_message" => e.exc_message.as_deref(),
+            "exc_level" => e.exc_level.as_deref(),
+            "environment" => e.environment.as_deref(),
+            _ => return FilterValue::Null,
+        };
+        FilterValue::String(Cow::Borrowed(value.unwrap_or("")))
+    }
+}
+
+/// Check that `filter` only reads properties [`EventFields`] resolves for
+/// `fields`: a misspelt field would read as `null` and the rule would silently
+/// never match. `Err` names the offending field or function.
+pub(super) fn check_fields(filter: &Filter, fields: FieldSet) -> std::result::Result<(), String> {
+    filter.visit(&mut FieldCheck(fields))
+}
+
+struct FieldCheck(FieldSet);
+
+type Checked = std::result::Result<(), String>;
+
+impl<'a> ExprVisitor<'a, Checked> for FieldCheck {
+    fn visit_literal(&mut self, _value: &'a FilterValue<'a>) -> Checked {
+        Ok(())
+    }
+
+    fn visit_property(&mut self, name: &'a str) -> Checked {
+        if name.eq_ignore_ascii_case("project") || self.0.column(name).is_some() {
+            Ok(())
+        } else {
+            Err(format!(
+                "unknown field `{name}` — expected one of: {}",
+                self.0.known()
+            ))
+        }
+    }
+
+    fn visit_function_call(
+        &mut self,
+        function: &'a dyn Function,
+        _args: &'a [FilterNode<'a>],
+    ) -> Checked {
+        Err(format!(
+            "functions are not supported in filter rules (`{}(…)`)",
+            function.name()
+        ))
+    }
+
+    fn visit_binary(
+        &mut self,
+        left: &'a FilterNode<'a>,
+        _operator: BinaryOperator,
+        right: &'a FilterNode<'a>,
+    ) -> Checked {
+        self.visit_expr(left)?;
+        self.visit_expr(right)
+    }
+
+    fn visit_logical(
+        &mut self,
+        left: &'a FilterNode<'a>,
+        _operator: LogicalOperator,
+        right: &'a FilterNode<'a>,
+    ) -> Checked {
+        self.visit_expr(left)?;
+        self.visit_expr(right)
+    }
+
+    fn visit_unary(&mut self, _operator: UnaryOperator, right: &'a FilterNode<'a>) -> Checked {
+        self.visit_expr(right)
+    }
+
+    fn visit_like(&mut self, left: &'a FilterNode<'a>, _glob: &'a Glob) -> Checked {
+        self.visit_expr(left)
+    }
+
+    fn visit_matches(&mut self, left: &'a FilterNode<'a>, _regex: &'a CompiledRegex) -> Checked {
+        self.visit_expr(left)
+    }
+}
+
+/// The scheme-less names `filter` compares `source` (or `app`) against with
+/// `==`, `!=` or `in`, for [`EventFields`] to read the source as.
+pub(super) fn source_names(filter: &Filter) -> Vec<String> {
+    let mut names = SourceNames(Vec::new());
+    filter.visit(&mut names);
+    names.0
+}
+
+struct SourceNames(Vec<String>);
+
+enum Operand {
+    Source,
+    Strings(Vec<String>),
+    Other,
+}
+
+impl<'a> ExprVisitor<'a, Operand> for SourceNames {
+    fn visit_literal(&mut self, value: &'a FilterValue<'a>) -> Operand {
+        match value {
+            FilterValue::String(s) => Operand::Strings(vec![s.to_string()]),
+            FilterValue::Tuple(values) => Operand::Strings(
+                values
+                    .iter()
+                    .filter_map(|value| match value {
+                        FilterValue::String(s) => Some(s.to_string()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => Operand::Other,
+        }
+    }
+
+    fn visit_property(&mut self, name: &'a str) -> Operand {
+        if name.eq_ignore_ascii_case("source") || name.eq_ignore_ascii_case("app") {
+            Operand::Source
+        } else {
+            Operand::Other
+        }
+    }
+
+    fn visit_function_call(
+        &mut self,
+        _function: &'a dyn Function,
+        _args: &'a [FilterNode<'a>],
+    ) -> Operand {
+        Operand::Other
+    }
+
+    fn visit_binary(
+        &mut self,
+        left: &'a FilterNode<'a>,
+        operator: BinaryOperator,
+        right: &'a FilterNode<'a>,
+    ) -> Operand {
+        use BinaryOperator::*;
+        let operands = (self.visit_expr(left), self.visit_expr(right));
+        if let (Operand::Source, Operand::Strings(names))
+        | (Operand::Strings(names), Operand::Source) = operands
+            && matches!(operator, Equals | NotEquals | In | InCs)
+        {
+            self.0
+                .extend(names.into_iter().filter(|name| !name.contains("://")));
+        }
+        Operand::Other
+    }
+
+    fn visit_logical(
+        &mut self,
+        left: &'a FilterNode<'a>,
+        _operator: LogicalOperator,
+        right: &'a FilterNode<'a>,
+    ) -> Operand {
+        self.visit_expr(left);
+        self.visit_expr(right);
+        Operand::Other
+    }
+
+    fn visit_unary(&mut self, _operator: UnaryOperator, right: &'a FilterNode<'a>) -> Operand {
+        self.visit_expr(right);
+        Operand::Other
+    }
+
+    fn visit_like(&mut self, left: &'a FilterNode<'a>, _glob: &'a Glob) -> Operand {
+        self.visit_expr(left);
+        Operand::Other
+    }
+
+    fn visit_matches(&mut self, left: &'a FilterNode<'a>, _regex: &'a CompiledRegex) -> Operand {
+        self.visit_expr(left);
+        Operand::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(expressions: &[&str]) -> Exclusions {
+        let expressions: Vec<String> = expressions.iter().map(|e| e.to_string()).collect();
+        Exclusions::new(&expressions).expect("valid rules")
+    }
+
+    fn page(path: &str) -> StoredEvent {
+        StoredEvent {
+            source: "https://example.com".into(),
+            pathname: Some(path.into()),
+            ua_browser: Some("Chrome".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_the_first_matching_rule() {
+        let exclusions = rules(&[
+            r#"path == "/health""#,
+            r#"browser == "chrome" && path like "/admin/*""#,
+            r#"path like "/admin/*""#,
+        ]);
+
+        assert!(exclusions.excludes(&page("/health"), FieldSet::Dashboard));
+        assert!(exclusions.excludes(&page("/admin/users"), FieldSet::Dashboard));
+        assert!(!exclusions.excludes(&page("/pricing"), FieldSet::Dashboard));
+
+        let counts: Vec<u64> = exclusions.counts().into_iter().map(|(_, n)| n).collect();
+        assert_eq!(counts, vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn uses_the_exception_vocabulary_for_exceptions() {
+        let exclusions = rules(&[r#"type == "ResizeObserverError" && handled == true"#]);
+        let exception = StoredEvent {
+            exc_type: Some("ResizeObserverError".into()),
+            exc_handled: Some(true),
+            ..Default::default()
+        };
+        assert!(exclusions.excludes(&exception, FieldSet::Exceptions));
+        // `type` is not a dashboard field, so the rule can't match a page event.
+        assert!(!exclusions.excludes(&exception, FieldSet::Dashboard));
+    }
+
+    #[test]
+    fn project_rules_match_the_source_project() {
+        let exclusions = rules(&[r#"project == "Internal Tools""#]);
+        exclusions.projects.write().unwrap().insert(
+            "https://example.com".to_string(),
+            "Internal Tools".to_string(),
+        );
+        assert!(exclusions.excludes(&page("/"), FieldSet::Dashboard));
+        let other = StoredEvent {
+            source: "https://other.example".into(),
+            ..page("/")
+        };
+        assert!(!exclusions.excludes(&other, FieldSet::Dashboard));
+    }
+
+    #[test]
+    fn absent_dimensions_compare_as_empty() {
+        let exclusions = rules(&[r#"country == """#]);
+        assert!(exclusions.excludes(&page("/"), FieldSet::Dashboard));
+    }
+
+    #[test]
+    fn source_rules_tolerate_missing_schemes() {
+        let exclusions = rules(&[r#"source == "Example.com""#]);
+        assert!(exclusions.excludes(&page("/"), FieldSet::Dashboard));
+        let negated = rules(&[r#"source != "example.com" && source != "https://other.example""#]);
+        assert!(!negated.excludes(&page("/"), FieldSet::Dashboard));
+        let app = rules(&[r#"app in ["staging.example.com", "example.com"]"#]);
+        let exception = StoredEvent {
+            exc_type: Some("TypeError".into()),
+            ..page("/")
+        };
+        assert!(app.excludes(&exception, FieldSet::Exceptions));
+    }
+
+    #[test]
+    fn rules_only_apply_to_events_with_their_fields() {
+        // `country` is a page event field: an exception has none, so the rule
+        // leaves exceptions alone rather than reading its country as null.
+        let exclusions = rules(&[r#"country != "US""#]);
+        let exception = StoredEvent {
+            exc_type: Some("TypeError".into()),
+            ..Default::default()
+        };
+        assert!(!exclusions.excludes(&exception, FieldSet::Exceptions));
+        assert!(exclusions.excludes(&page("/"), FieldSet::Dashboard));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(Exclusions::new(&["&& ||".to_string()]).is_err());
+        assert!(Exclusions::new(&[r#"contry == "US""#.to_string()]).is_err());
+        assert!(Exclusions::new(&[r#"path == "/" && type == "TypeError""#.to_string()]).is_err());
+    }
+}
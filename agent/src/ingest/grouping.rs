@@ -0,0 +1,252 @@
+//! Per-project fingerprint rules: server-side overrides of exception grouping
+//! ("type matches X and message like Y → group Z", "ignore frames from module
+//! M"), so a bad group can be fixed without shipping new client code.
+//!
+//! Rules live on the [`Project`](analytics_api::Project) and apply to the
+//! exceptions of its sources. Like path rules they are compiled once into a
+//! source-keyed cache that the project and source handlers
+//! [`reload`](GroupingRules::reload) after every change; the fingerprint itself
+//! consults them in `exception::fingerprint`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use analytics_api::{FingerprintRule, StackFrame};
+use regex::{Regex, RegexBuilder};
+use tracing_batteries::prelude::warn;
+
+use crate::errors::Result;
+use crate::store::Store;
+
+/// A validated, compiled [`FingerprintRule`].
+pub enum CompiledFingerprintRule {
+    Group {
+        exc_type: Option<Regex>,
+        message: Option<Regex>,
+        group: String,
+    },
+    IgnoreFrames {
+        module: Regex,
+    },
+}
+
+impl CompiledFingerprintRule {
+    /// Compile `rule`. `Err` carries a message suitable for a 400 response.
+    pub fn new(rule: &FingerprintRule) -> std::result::Result<Self, String> {
+        match rule {
+            FingerprintRule::Group {
+                exc_type,
+                message,
+                group,
+            } => {
+                let group = group.trim();
+                if group.is_empty() {
+                    return Err("A group rule needs a group name.".into());
+                }
+                let exc_type = optional_glob(exc_type.as_deref())?;
+                let message = optional_glob(message.as_deref())?;
+                if exc_type.is_none() && message.is_none() {
+                    return Err(format!(
+                        "The rule for group `{group}` needs a type or message pattern."
+                    ));
+                }
+                Ok(Self::Group {
+                    exc_type,
+                    message,
+                    group: group.to_string(),
+                })
+            }
+            FingerprintRule::IgnoreFrames { module } => match optional_glob(Some(module))? {
+                Some(module) => Ok(Self::IgnoreFrames { module }),
+                None => Err("An ignore-frames rule needs a module pattern.".into()),
+            },
+        }
+    }
+}
+
+/// One project's compiled rules.
+pub struct ProjectGrouping {
+    rules: Vec<CompiledFingerprintRule>,
+}
+
+impl ProjectGrouping {
+    /// The group named by the first group rule matching the exception, if any.
+    pub fn group_for(&self, exc_type: &str, message: &str) -> Option<&str> {
+        self.rules.iter().find_map(|rule| match rule {
+            CompiledFingerprintRule::Group {
+                exc_type: type_pattern,
+                message: message_pattern,
+                group,
+            } => {
+                let matches = type_pattern.as_ref().is_none_or(|p| p.is_match(exc_type))
+                    && message_pattern.as_ref().is_none_or(|p| p.is_match(message));
+                matches.then_some(group.as_str())
+            }
+            CompiledFingerprintRule::IgnoreFrames { .. } => None,
+        })
+    }
+
+    /// Whether `frame` is left out of the fingerprint.
+    pub fn ignores_frame(&self, frame: &StackFrame) -> bool {
+        let Some(module) = frame.module.as_deref().or(frame.file.as_deref()) else {
+            return false;
+        };
+        self.rules.iter().any(|rule| match rule {
+            CompiledFingerprintRule::IgnoreFrames { module: pattern } => pattern.is_match(module),
+            CompiledFingerprintRule::Group { .. } => false,
+        })
+    }
+}
+
+/// The compiled fingerprint rules of every project, keyed by member source URI.
+#[derive(Default)]
+pub struct GroupingRules {
+    by_source: RwLock<HashMap<String, Arc<ProjectGrouping>>>,
+}
+
+impl GroupingRules {
+    pub fn load(store: &Store) -> Result<Self> {
+        let rules = Self::default();
+        rules.reload(store)?;
+        Ok(rules)
+    }
+
+    /// Rebuild the cache from the stored projects and source assignments.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let mut by_project: HashMap<String, Arc<ProjectGrouping>> = HashMap::new();
+        for project in store.list_projects()? {
+            if project.fingerprint_rules.is_empty() {
+                continue;
+            }
+            let rules = project
+                .fingerprint_rules
+                .iter()
+                .filter_map(|rule| match CompiledFingerprintRule::new(rule) {
+                    Ok(rule) => Some(rule),
+                    // Rules are validated on save; this only guards hand-edited
+                    // or legacy records.
+                    Err(err) => {
+                        warn!(
+                            "skipping invalid fingerprint rule on project {}: {err}",
+                            project.id
+                        );
+                        None
+                    }
+                })
+                .collect();
+            by_project.insert(project.id.clone(), Arc::new(ProjectGrouping { rules }));
+        }
+
+        let by_source = store
+            .list_sources()?
+            .into_iter()
+            .filter_map(|source| {
+                let rules = by_project.get(source.project_id.as_deref()?)?;
+                Some((source.uri, rules.clone()))
+            })
+            .collect();
+        *self.by_source.write().unwrap_or_else(|e| e.into_inner()) = by_source;
+        Ok(())
+    }
+
+    /// The rules applying to exceptions from `source`, if its project has any.
+    pub fn for_source(&self, source: &str) -> Option<Arc<ProjectGrouping>> {
+        self.by_source
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(source)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+impl ProjectGrouping {
+    pub(crate) fn with_rules(rules: &[FingerprintRule]) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .map(|rule| CompiledFingerprintRule::new(rule).expect("valid rule"))
+                .collect(),
+        }
+    }
+}
+
+/// Compile a case-insensitive whole-value glob (`*` any run of characters,
+/// `?` one), treating a blank pattern as absent.
+pub(super) fn optional_glob(pattern: Option<&str>) -> std::result::Result<Option<Regex>, String> {
+    let Some(pattern) = pattern.map(str::trim).filter(|p| !p.is_empty()) else {
+        return Ok(None);
+    };
+    let mut regex = String::with_capacity(pattern.len() + 8);
+    regex.push('^');
+    for ch in pattern.chars() {
+        match ch {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    RegexBuilder::new(&regex)
+        .case_insensitive(true)
+        .dot_matches_new_line(true)
+        .build()
+        .map(Some)
+        .map_err(|err| format!("The pattern `{pattern}` is not valid: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_rules_match_every_given_pattern() {
+        let rules = ProjectGrouping::with_rules(&[FingerprintRule::Group {
+            exc_type: Some("TypeError".into()),
+            message: Some("*fetch*".into()),
+            group: "network".into(),
+        }]);
+        assert_eq!(
+            rules.group_for("typeerror", "Failed to fetch\nat /api"),
+            Some("network")
+        );
+        assert_eq!(rules.group_for("TypeError", "x is undefined"), None);
+        assert_eq!(rules.group_for("RangeError", "Failed to fetch"), None);
+    }
+
+    #[test]
+    fn ignores_frames_by_module_or_file() {
+        let rules = ProjectGrouping::with_rules(&[
+            FingerprintRule::IgnoreFrames {
+                module: "app.middleware.*".into(),
+            },
+            FingerprintRule::IgnoreFrames {
+                module: "*/vendor/*".into(),
+            },
+        ]);
+        let frame = |module: Option<&str>, file: &str| StackFrame {
+            module: module.map(str::to_string),
+            file: Some(file.into()),
+            ..Default::default()
+        };
+        assert!(rules.ignores_frame(&frame(Some("app.middleware.auth"), "auth.py")));
+        assert!(!rules.ignores_frame(&frame(Some("app.views"), "views.py")));
+        assert!(rules.ignores_frame(&frame(None, "https://example.com/vendor/lib.js")));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let invalid = |rule: FingerprintRule| CompiledFingerprintRule::new(&rule).is_err();
+        assert!(invalid(FingerprintRule::Group {
+            exc_type: None,
+            message: Some("  ".into()),
+            group: "g".into(),
+        }));
+        assert!(invalid(FingerprintRule::Group {
+            exc_type: Some("TypeError".into()),
+            message: None,
+            group: " ".into(),
+        }));
+        assert!(invalid(FingerprintRule::IgnoreFrames { module: "".into() }));
+    }
+}
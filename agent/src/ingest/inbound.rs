@@ -0,0 +1,420 @@
+//! Per-project inbound filters: exception reports that are known noise
+//! (browser extensions, `ResizeObserver` loops, opaque `Script error.`s,
+//! third-party scripts) dropped before they are stored, instead of muted by
+//! hand group after group.
+//!
+//! Filters live on the [`Project`](analytics_api::Project) as built-in presets
+//! plus filter expressions in the exception query vocabulary. Like the other
+//! project rules they are compiled into a source-keyed cache the project and
+//! source handlers [`reload`](ExceptionFilters::reload) after every change.
+//! Each filter counts what it discarded; the counts survive reloads as long as
+//! the filter does, and are [saved](ExceptionFilters::save) to the store so
+//! they survive restarts too.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use analytics_api::{InboundFilterPreset, InboundFilters, source_label};
+use filt_rs::Filter;
+use tokio::time::MissedTickBehavior;
+use tracing_batteries::prelude::{error, warn};
+use url::Url;
+
+use super::exclude::{EventFields, check_fields, source_names};
+use super::stack::parse_stack;
+use super::{HostAliases, IngestRules};
+use crate::analytics::filter::FieldSet;
+use crate::errors::Result;
+use crate::store::{EventKind, Store, StoredEvent};
+
+/// How often the discard counts are saved while running.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Markers of a script injected by a browser extension.
+const EXTENSION_SCHEMES: &[&str] = &[
+    "chrome-extension://",
+    "moz-extension://",
+    "safari-extension://",
+    "safari-web-extension://",
+];
+
+enum Check {
+    Preset(InboundFilterPreset),
+    /// A filter expression and the scheme-less source names it compares
+    /// against.
+    Rule(Filter, Vec<String>),
+}
+
+struct CompiledFilter {
+    check: Check,
+    label: String,
+    discarded: Arc<AtomicU64>,
+}
+
+/// One project's compiled filters.
+struct ProjectFilters {
+    project_id: String,
+    project_name: String,
+    filters: Vec<CompiledFilter>,
+}
+
+/// The inbound filters of every project, keyed by member source URI, and the
+/// discard counters behind them.
+#[derive(Default)]
+pub struct ExceptionFilters {
+    by_source: RwLock<HashMap<String, Arc<ProjectFilters>>>,
+    by_project: RwLock<Vec<Arc<ProjectFilters>>>,
+    /// Keyed by `(project id, filter label)`, so a reload keeps the counts.
+    counters: Mutex<HashMap<(String, String), Arc<AtomicU64>>>,
+}
+
+impl ExceptionFilters {
+    /// Load the filters, picking up the counts last [saved](Self::save).
+    pub fn load(store: &Store) -> Result<Self> {
+        let counters = store
+            .inbound_discards()?
+            .into_iter()
+            .map(|(project_id, label, discarded)| {
+                ((project_id, label), Arc::new(AtomicU64::new(discarded)))
+            })
+            .collect();
+        let filters = Self {
+            counters: Mutex::new(counters),
+            ..Self::default()
+        };
+        filters.reload(store)?;
+        Ok(filters)
+    }
+
+    /// Save the count of every current filter, replacing the saved counts (so
+    /// those of removed filters are dropped).
+    pub fn save(&self, store: &Store) -> Result<()> {
+        let counts: Vec<(String, String, u64)> = self
+            .counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|((project_id, label), discarded)| {
+                (
+                    project_id.clone(),
+                    label.clone(),
+                    discarded.load(Ordering::Relaxed),
+                )
+            })
+            .collect();
+        store.put_inbound_discards(&counts)
+    }
+
+    /// Rebuild the cache from the stored projects and source assignments.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let mut kept = HashMap::new();
+        let mut by_project: HashMap<String, Arc<ProjectFilters>> = HashMap::new();
+        for project in store.list_projects()? {
+            let mut filters = Vec::new();
+            let mut add = |check: Check, label: String| {
+                let key = (project.id.clone(), label.clone());
+                let discarded = counters.remove(&key).unwrap_or_default();
+                kept.insert(key, discarded.clone());
+                filters.push(CompiledFilter {
+                    check,
+                    label,
+                    discarded,
+                });
+            };
+            for preset in &project.inbound_filters.presets {
+                add(Check::Preset(*preset), preset.label().to_string());
+            }
+            for rule in &project.inbound_filters.rules {
+                match Filter::new(rule.as_str()) {
+                    Ok(filter) => {
+                        let sources = source_names(&filter);
+                        add(Check::Rule(filter, sources), rule.clone())
+                    }
+                    // Rules are validated on save; this only guards hand-edited
+                    // or legacy records.
+                    Err(err) => warn!(
+                        "skipping invalid inbound filter on project {}: {err}",
+                        project.id
+                    ),
+                }
+            }
+            if !filters.is_empty() {
+                by_project.insert(
+                    project.id.clone(),
+                    Arc::new(ProjectFilters {
+                        project_id: project.id,
+                        project_name: project.name,
+                        filters,
+                    }),
+                );
+            }
+        }
+        *counters = kept;
+
+        let by_source = store
+            .list_sources()?
+            .into_iter()
+            .filter_map(|source| {
+                let filters = by_project.get(source.project_id.as_deref()?)?;
+                Some((source.uri, filters.clone()))
+            })
+            .collect();
+        *self.by_source.write().unwrap_or_else(|e| e.into_inner()) = by_source;
+        *self.by_project.write().unwrap_or_else(|e| e.into_inner()) =
+            by_project.into_values().collect();
+        Ok(())
+    }
+
+    /// Whether the exception `event` matches one of its project's filters.
+    /// Only the first match is counted. `aliases` tells a first-party script
+    /// host from a third-party one.
+    pub fn discards(&self, event: &StoredEvent, aliases: &HostAliases) -> bool {
+        let Some(project) = self
+            .by_source
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&event.source)
+            .cloned()
+        else {
+            return false;
+        };
+        let matched = project.filters.iter().find(|filter| match &filter.check {
+            Check::Preset(preset) => preset_matches(*preset, event, aliases),
+            Check::Rule(rule, sources) => rule
+                .matches(&EventFields {
+                    event,
+                    fields: FieldSet::Exceptions,
+                    project: Some(&project.project_name),
+                    sources,
+                })
+                .unwrap_or(false),
+        });
+        match matched {
+            Some(filter) => {
+                filter.discarded.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `(project id, filter, reports discarded)` for every filter of every
+    /// project, in each project's filter order.
+    pub fn counts(&self) -> Vec<(String, String, u64)> {
+        self.by_project
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .flat_map(|project| {
+                project.filters.iter().map(|filter| {
+                    (
+                        project.project_id.clone(),
+                        filter.label.clone(),
+                        filter.discarded.load(Ordering::Relaxed),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Periodically [save](ExceptionFilters::save) the discard counts.
+pub fn spawn_save_discards(store: Arc<Store>, rules: Arc<IngestRules>) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(SAVE_INTERVAL);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tick.tick().await;
+            let (store, rules) = (store.clone(), rules.clone());
+            match tokio::task::spawn_blocking(move || rules.inbound.save(&store)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => error!("failed to save inbound filter counts: {err}"),
+                Err(err) => error!("inbound filter count save task panicked: {err}"),
+            }
+        }
+    });
+}
+
+/// Check `filters` before they are saved. Rules are parsed and evaluated as
+/// [`ExceptionFilters::discards`] does, so a rule that saves is one ingest can
+/// match. `Err` carries a message suitable for a 400 response.
+pub fn validate_inbound_filters(filters: &InboundFilters) -> std::result::Result<(), String> {
+    let blank = StoredEvent {
+        kind: EventKind::Exception,
+        ..Default::default()
+    };
+    for rule in &filters.rules {
+        if rule.trim().is_empty() {
+            return Err("An inbound filter needs an expression.".into());
+        }
+        let filter = Filter::new(rule.as_str())
+            .map_err(|err| format!("The inbound filter `{rule}` is invalid: {err}"))?;
+        check_fields(&filter, FieldSet::Exceptions)
+            .map_err(|err| format!("The inbound filter `{rule}` is invalid: {err}"))?;
+        let view = EventFields {
+            event: &blank,
+            fields: FieldSet::Exceptions,
+            project: Some(""),
+            sources: &source_names(&filter),
+        };
+        filter
+            .matches(&view)
+            .map_err(|err| format!("The inbound filter `{rule}` cannot be evaluated: {err}"))?;
+    }
+    Ok(())
+}
+
+fn preset_matches(preset: InboundFilterPreset, event: &StoredEvent, aliases: &HostAliases) -> bool {
+    let message = event.exc_message.as_deref().unwrap_or("");
+    let stack = event.exc_stack.as_deref().unwrap_or("");
+    match preset {
+        InboundFilterPreset::BrowserExtensions => EXTENSION_SCHEMES
+            .iter()
+            .any(|scheme| stack.contains(scheme) || message.contains(scheme)),
+        InboundFilterPreset::ResizeObserver => message.contains("ResizeObserver loop"),
+        InboundFilterPreset::ScriptError => {
+            matches!(message.trim(), "Script error." | "Script error")
+        }
+        InboundFilterPreset::ThirdPartyFrames => third_party_only(&event.source, stack, aliases),
+    }
+}
+
+/// Whether every frame of `stack` located by URL is in a script served from
+/// another site than `source` (neither its host, a subdomain of it, nor an
+/// alias of it), with at least one such frame. Frames in symbolicated or
+/// bundler paths (`webpack://…`) count as the application's.
+fn third_party_only(source: &str, stack: &str, aliases: &HostAliases) -> bool {
+    let own_host = source_label(source).trim_start_matches("www.");
+    let mut located = 0;
+    for frame in parse_stack(stack) {
+        let Some(file) = frame.file.as_deref().filter(|f| f.contains("://")) else {
+            continue;
+        };
+        located += 1;
+        let Ok(url) = Url::parse(file) else {
+            return false;
+        };
+        let first_party = match (url.scheme(), url.host_str()) {
+            ("http" | "https", Some(host)) => {
+                let host = host.trim_start_matches("www.").to_lowercase();
+                host == own_host
+                    || host.ends_with(&format!(".{own_host}"))
+                    || aliases.source_for(&host) == source
+            }
+            (scheme, _) => !EXTENSION_SCHEMES
+                .iter()
+                .any(|s| s.strip_suffix("://") == Some(scheme)),
+        };
+        if first_party {
+            return false;
+        }
+    }
+    located > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exception(message: &str, stack: &str) -> StoredEvent {
+        StoredEvent {
+            source: "https://example.com".into(),
+            exc_type: Some("Error".into()),
+            exc_message: Some(message.into()),
+            exc_stack: Some(stack.into()),
+            exc_handled: Some(false),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn presets_recognize_common_noise() {
+        let aliases = HostAliases::default();
+        let matches = |preset, event: &StoredEvent| preset_matches(preset, event, &aliases);
+        let own = "at render (https://cdn.example.com/app.js:1:10)";
+
+        assert!(matches(
+            InboundFilterPreset::ResizeObserver,
+            &exception("ResizeObserver loop limit exceeded", "")
+        ));
+        assert!(matches(
+            InboundFilterPreset::ScriptError,
+            &exception("Script error.", "")
+        ));
+        assert!(!matches(
+            InboundFilterPreset::ScriptError,
+            &exception("Script error in checkout", own)
+        ));
+        assert!(matches(
+            InboundFilterPreset::BrowserExtensions,
+            &exception("x", "at f (chrome-extension://abcdef/content.js:3:7)")
+        ));
+
+        let third_party =
+            "at track (https://cdn.ads.net/tag.js:1:99)\nat run (https://cdn.ads.net/tag.js:2:5)";
+        assert!(matches(
+            InboundFilterPreset::ThirdPartyFrames,
+            &exception("x", third_party)
+        ));
+        let mixed = format!("{third_party}\n{own}");
+        assert!(!matches(
+            InboundFilterPreset::ThirdPartyFrames,
+            &exception("x", &mixed)
+        ));
+        // Without a located frame there is nothing to call third-party.
+        assert!(!matches(
+            InboundFilterPreset::ThirdPartyFrames,
+            &exception("x", "")
+        ));
+    }
+
+    #[test]
+    fn source_rules_tolerate_missing_schemes() {
+        let rule = r#"source == "Example.com""#;
+        let filter = Filter::new(rule).unwrap();
+        let sources = source_names(&filter);
+        let project = Arc::new(ProjectFilters {
+            project_id: "shop".into(),
+            project_name: "Shop".into(),
+            filters: vec![CompiledFilter {
+                check: Check::Rule(filter, sources),
+                label: rule.into(),
+                discarded: Arc::default(),
+            }],
+        });
+        let filters = ExceptionFilters::default();
+        filters.by_source.write().unwrap().extend([
+            ("https://example.com".to_string(), project.clone()),
+            ("https://other.example".to_string(), project.clone()),
+        ]);
+
+        let aliases = HostAliases::default();
+        assert!(filters.discards(&exception("x", ""), &aliases));
+        let other = StoredEvent {
+            source: "https://other.example".into(),
+            ..exception("x", "")
+        };
+        assert!(!filters.discards(&other, &aliases));
+        assert_eq!(project.filters[0].discarded.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn rules_are_validated_against_the_ingest_fields() {
+        let validate = |rule: &str| {
+            validate_inbound_filters(&InboundFilters {
+                rules: vec![rule.into()],
+                ..Default::default()
+            })
+        };
+        assert!(validate(r#"message contains "chunk" and project == "Shop""#).is_ok());
+        assert!(validate("handled == false").is_ok());
+        assert!(validate("   ").is_err());
+        assert!(validate("message ==").is_err());
+        // `path` is a dashboard field; an exception never has one.
+        let err = validate(r#"path == "/admin""#).unwrap_err();
+        assert!(err.contains("unknown field `path`"), "{err}");
+    }
+}
@@ -0,0 +1,219 @@
+//! Manual merges and splits of exception groups (see `store::merge`), applied
+//! to new occurrences at ingest and to stored ones by re-grouping; queries
+//! follow a merge until it has been applied.
+//!
+//! Both are compiled into a source-keyed lookup that the merge and split
+//! handlers [`reload`](GroupEdits::reload) after every change.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use regex::Regex;
+use tracing_batteries::prelude::warn;
+
+use super::exception::short_hash;
+use super::grouping::optional_glob;
+use crate::errors::Result;
+use crate::store::Store;
+
+struct CompiledSplit {
+    message: Option<Regex>,
+    stack: Option<Regex>,
+    target: String,
+}
+
+impl CompiledSplit {
+    fn matches(&self, message: &str, stack: Option<&str>) -> bool {
+        self.message.as_ref().is_none_or(|p| p.is_match(message))
+            && self
+                .stack
+                .as_ref()
+                .is_none_or(|p| stack.is_some_and(|stack| p.is_match(stack)))
+    }
+}
+
+#[derive(Default)]
+struct EditTable {
+    redirects: HashMap<String, String>,
+    splits: HashMap<String, Vec<CompiledSplit>>,
+}
+
+impl EditTable {
+    fn redirect(&self, source: &str, group: String) -> String {
+        self.redirects
+            .get(&key(source, &group))
+            .cloned()
+            .unwrap_or(group)
+    }
+}
+
+/// The recorded merges and splits, keyed by source-scoped group.
+#[derive(Default)]
+pub struct GroupEdits {
+    table: RwLock<EditTable>,
+}
+
+impl GroupEdits {
+    pub fn load(store: &Store) -> Result<Self> {
+        let edits = Self::default();
+        edits.reload(store)?;
+        Ok(edits)
+    }
+
+    /// Rebuild the lookup from the stored merges and splits.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let mut table = EditTable::default();
+        for redirect in store.list_group_redirects()? {
+            table
+                .redirects
+                .insert(key(&redirect.source, &redirect.group_id), redirect.target);
+        }
+        for split in store.list_group_splits()? {
+            let compiled = optional_glob(split.message.as_deref()).and_then(|message| {
+                Ok(CompiledSplit {
+                    message,
+                    stack: optional_glob(split.stack.as_deref())?,
+                    target: split.target.clone(),
+                })
+            });
+            match compiled {
+                Ok(compiled) => table
+                    .splits
+                    .entry(key(&split.source, &split.group_id))
+                    .or_default()
+                    .push(compiled),
+                // Splits are validated when made; this only guards hand-edited
+                // or legacy records.
+                Err(err) => warn!(
+                    "skipping invalid split of exception group {}: {err}",
+                    split.group_id
+                ),
+            }
+        }
+        *self.table.write().unwrap_or_else(|e| e.into_inner()) = table;
+        Ok(())
+    }
+
+    /// The group a merge folded `group` into, if any.
+    pub fn redirect(&self, source: &str, group: &str) -> Option<String> {
+        self.table
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .redirects
+            .get(&key(source, group))
+            .cloned()
+    }
+
+    /// The group an occurrence fingerprinted to `group` belongs in: a merge is
+    /// followed, then the first matching split of the resulting group applied
+    /// (and followed in turn, should the split-off group have been merged).
+    pub fn resolve(
+        &self,
+        source: &str,
+        group: String,
+        message: &str,
+        stack: Option<&str>,
+    ) -> String {
+        let table = self.table.read().unwrap_or_else(|e| e.into_inner());
+        let group = table.redirect(source, group);
+        let split = table
+            .splits
+            .get(&key(source, &group))
+            .and_then(|splits| splits.iter().find(|split| split.matches(message, stack)));
+        match split {
+            Some(split) => table.redirect(source, split.target.clone()),
+            None => group,
+        }
+    }
+}
+
+/// Check a split's patterns: each must compile and at least one be given.
+/// `Err` carries a message suitable for a 400 response.
+pub fn check_split(message: Option<&str>, stack: Option<&str>) -> std::result::Result<(), String> {
+    let message = optional_glob(message)?;
+    let stack = optional_glob(stack)?;
+    if message.is_none() && stack.is_none() {
+        return Err("A split needs a message or stack pattern.".into());
+    }
+    Ok(())
+}
+
+/// The id of the group split off `group` by the given patterns: stable, so
+/// repeating a split converges on the same group.
+pub fn split_group_id(group: &str, message: Option<&str>, stack: Option<&str>) -> String {
+    let pattern = |p: Option<&str>| p.map(str::trim).unwrap_or_default().to_lowercase();
+    short_hash(
+        format!(
+            "split\u{1f}{group}\u{1f}{}\u{1f}{}",
+            pattern(message),
+            pattern(stack)
+        )
+        .as_bytes(),
+    )
+}
+
+fn key(source: &str, group: &str) -> String {
+    format!("{source}\u{1f}{group}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edits(redirects: &[(&str, &str)], splits: &[(&str, &str, &str)]) -> GroupEdits {
+        let mut table = EditTable::default();
+        for (group, target) in redirects {
+            table
+                .redirects
+                .insert(key("https://example.com", group), target.to_string());
+        }
+        for (group, message, target) in splits {
+            table
+                .splits
+                .entry(key("https://example.com", group))
+                .or_default()
+                .push(CompiledSplit {
+                    message: optional_glob(Some(message)).unwrap(),
+                    stack: None,
+                    target: target.to_string(),
+                });
+        }
+        GroupEdits {
+            table: RwLock::new(table),
+        }
+    }
+
+    #[test]
+    fn resolves_merges_then_splits() {
+        let edits = edits(
+            &[("a", "b"), ("child", "elsewhere")],
+            &[("b", "*timeout*", "child")],
+        );
+        let resolve = |group: &str, message: &str| {
+            edits.resolve("https://example.com", group.to_string(), message, None)
+        };
+        assert_eq!(resolve("a", "boom"), "b");
+        // Occurrences of a merged group are split out of its target too, and a
+        // split-off group that was since merged follows the merge.
+        assert_eq!(resolve("a", "Request timeout after 30s"), "elsewhere");
+        assert_eq!(resolve("other", "timeout"), "other");
+        assert_eq!(
+            edits.resolve("https://other.org", "a".into(), "boom", None),
+            "a"
+        );
+    }
+
+    #[test]
+    fn split_ids_are_stable_and_patterns_checked() {
+        assert_eq!(
+            split_group_id("g", Some("*timeout*"), None),
+            split_group_id("g", Some(" *Timeout* "), None)
+        );
+        assert_ne!(
+            split_group_id("g", Some("*timeout*"), None),
+            split_group_id("g", None, Some("*timeout*"))
+        );
+        assert!(check_split(None, Some("  ")).is_err());
+        assert!(check_split(Some("*timeout*"), None).is_ok());
+    }
+}
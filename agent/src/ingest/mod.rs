@@ -1,21 +1,110 @@
-//! Event ingest: enrichment (UA/language/geo/referrer/UTM), bot filtering, and the
-//! non-blocking batched writer + compactor pipeline.
+//! Event ingest: enrichment (UA/language/geo/referrer/UTM), bot filtering, the
+//! configured ingest rules, and the non-blocking batched writer + compactor
+//! pipeline.
 
+mod aliases;
+mod breadcrumbs;
+mod channel;
 mod compactor;
 mod enrich;
 mod exception;
+mod exclude;
 mod geo;
+mod grouping;
+mod inbound;
 mod language;
+mod merges;
 mod normalize;
+mod paths;
 mod pipeline;
 mod referrer;
 mod regroup;
+mod sampling;
+mod search;
+mod similarity;
+mod snooze;
+mod stack;
+mod symbolicate;
 mod ua;
 
+pub use aliases::{HostAliases, HostPattern, normalize_alias};
+pub use channel::Channels;
 pub use enrich::build_event;
-pub use exception::build_exception;
+pub use exception::{MAX_APP_FIELD, build_exception};
+pub use exclude::Exclusions;
+pub use grouping::{CompiledFingerprintRule, GroupingRules};
+pub use inbound::{ExceptionFilters, spawn_save_discards, validate_inbound_filters};
+pub use merges::{GroupEdits, check_split, split_group_id};
+pub use paths::{CompiledPathRule, PathRewrites, pattern_regex};
 pub use pipeline::{Ingest, spawn};
-pub use regroup::regroup_if_needed;
+pub use referrer::ReferrerRules;
+pub use regroup::{
+    apply_group_edits, rederive_attribution_if_needed, regroup_if_needed, regroup_sources,
+    spawn_group_edits, spawn_regroup,
+};
+pub use sampling::{ExceptionSampler, spawn_flush, validate_sampling};
+pub use search::{reindex_exceptions, reindex_if_needed, search_exception_groups};
+pub use similarity::similar_groups;
+pub use snooze::spawn_lift_snoozes;
+pub use stack::parse_stack;
+pub use symbolicate::SourceMaps;
+pub use ua::ClientHints;
+
+use crate::config::Config;
+use crate::errors::Result;
+use crate::store::Store;
+
+/// The configured and administrator-managed rules applied to every event as it
+/// is built: exclusions, referrer and channel rules from the config file,
+/// hostname aliases, path and fingerprint rules, inbound exception filters and
+/// sampling limits from the source and project records, manual exception group
+/// merges and splits, plus the uploaded source maps exceptions are symbolicated
+/// with.
+#[derive(Default)]
+pub struct IngestRules {
+    pub exclusions: Exclusions,
+    pub referrers: ReferrerRules,
+    pub channels: Channels,
+    pub aliases: HostAliases,
+    pub paths: PathRewrites,
+    pub grouping: GroupingRules,
+    pub inbound: ExceptionFilters,
+    pub edits: GroupEdits,
+    pub sampling: ExceptionSampler,
+    pub sourcemaps: SourceMaps,
+}
+
+impl IngestRules {
+    pub fn load(config: &Config, store: &Store) -> Result<Self> {
+        let exclusions = Exclusions::new(&config.ingest.exclude)?;
+        exclusions.reload(store)?;
+        Ok(Self {
+            exclusions,
+            referrers: ReferrerRules::load(&config.ingest.referrers)?,
+            channels: Channels::new(&config.ingest.channels)?,
+            aliases: HostAliases::load(store)?,
+            paths: PathRewrites::load(store)?,
+            grouping: GroupingRules::load(store)?,
+            inbound: ExceptionFilters::load(store)?,
+            edits: GroupEdits::load(store)?,
+            sampling: ExceptionSampler::load(store)?,
+            sourcemaps: SourceMaps::new(&config.storage.sourcemap_dir),
+        })
+    }
+
+    /// Pick up source/project edits (aliases, project membership, path and
+    /// fingerprint rules, inbound filters, sampling limits) and exception group
+    /// merges and splits.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        self.exclusions.reload(store)?;
+        self.aliases.reload(store)?;
+        self.paths.reload(store)?;
+        self.grouping.reload(store)?;
+        self.inbound.reload(store)?;
+        self.sampling.reload(store)?;
+        self.edits.reload(store)
+    }
+}
 
 /// Truncate `value` to at most `max` bytes on a char boundary, appending an ellipsis
 /// when shortened. Shared by the hit and exception ingest paths so every stored text
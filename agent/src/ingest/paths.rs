@@ -0,0 +1,258 @@
+//! Per-project path rewrite rules: collapse identifier-bearing pathnames
+//! (`/orders/8423/items/19`) into templates (`/orders/:id/items/:id`) before
+//! they are stored, so the pages breakdown aggregates by route rather than by
+//! record.
+//!
+//! Rules live on the [`Project`](analytics_api::Project) and apply to the page
+//! events of its sources. They are compiled once into a source-keyed cache that
+//! the project and source handlers [`reload`](PathRewrites::reload) after every
+//! change, keeping the hit path free of store reads and regex compilation.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use analytics_api::{PathPatternKind, PathRule};
+use regex::{NoExpand, Regex};
+use tracing_batteries::prelude::warn;
+
+use crate::errors::Result;
+use crate::store::Store;
+
+/// A validated, compiled [`PathRule`].
+pub struct CompiledPathRule {
+    regex: Regex,
+    template: String,
+    kind: PathPatternKind,
+}
+
+impl CompiledPathRule {
+    /// Compile `rule`. `Err` carries a message suitable for a 400 response.
+    pub fn new(rule: &PathRule) -> std::result::Result<Self, String> {
+        let pattern = rule.pattern.trim();
+        let template = rule.template.trim();
+        if pattern.is_empty() {
+            return Err("A path rule needs a pattern.".into());
+        }
+        if !template.starts_with('/') {
+            return Err(format!(
+                "The template `{template}` must be a path starting with `/`."
+            ));
+        }
+        let source = pattern_regex(rule.kind, pattern);
+        let regex = Regex::new(&source)
+            .map_err(|err| format!("The pattern `{pattern}` is not valid: {err}"))?;
+        Ok(Self {
+            regex,
+            template: template.to_string(),
+            kind: rule.kind,
+        })
+    }
+
+    /// The templated path, or `None` when the rule doesn't match `path`.
+    pub fn rewrite(&self, path: &str) -> Option<String> {
+        if !self.regex.is_match(path) {
+            return None;
+        }
+        Some(match self.kind {
+            PathPatternKind::Glob => self
+                .regex
+                .replace(path, NoExpand(&self.template))
+                .into_owned(),
+            PathPatternKind::Regex => self
+                .regex
+                .replace(path, self.template.as_str())
+                .into_owned(),
+        })
+    }
+}
+
+/// One project's compiled rules.
+pub struct ProjectPaths {
+    rules: Vec<CompiledPathRule>,
+    /// Whether the reported path is kept alongside a rewrite.
+    pub keep_original: bool,
+}
+
+impl ProjectPaths {
+    /// The first matching rule's template for `path`, if any.
+    pub fn rewrite(&self, path: &str) -> Option<String> {
+        self.rules.iter().find_map(|rule| rule.rewrite(path))
+    }
+}
+
+/// The compiled path rules of every project, keyed by member source URI.
+#[derive(Default)]
+pub struct PathRewrites {
+    by_source: RwLock<HashMap<String, Arc<ProjectPaths>>>,
+}
+
+impl PathRewrites {
+    pub fn load(store: &Store) -> Result<Self> {
+        let rewrites = Self::default();
+        rewrites.reload(store)?;
+        Ok(rewrites)
+    }
+
+    /// Rebuild the cache from the stored projects and source assignments.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let mut by_project: HashMap<String, Arc<ProjectPaths>> = HashMap::new();
+        for project in store.list_projects()? {
+            if project.path_rules.is_empty() {
+                continue;
+            }
+            let rules = project
+                .path_rules
+                .iter()
+                .filter_map(|rule| match CompiledPathRule::new(rule) {
+                    Ok(rule) => Some(rule),
+                    // Rules are validated on save; this only guards hand-edited
+                    // or legacy records.
+                    Err(err) => {
+                        warn!(
+                            "skipping invalid path rule on project {}: {err}",
+                            project.id
+                        );
+                        None
+                    }
+                })
+                .collect();
+            by_project.insert(
+                project.id.clone(),
+                Arc::new(ProjectPaths {
+                    rules,
+                    keep_original: project.keep_original_path,
+                }),
+            );
+        }
+
+        let by_source = store
+            .list_sources()?
+            .into_iter()
+            .filter_map(|source| {
+                let paths = by_project.get(source.project_id.as_deref()?)?;
+                Some((source.uri, paths.clone()))
+            })
+            .collect();
+        *self.by_source.write().unwrap_or_else(|e| e.into_inner()) = by_source;
+        Ok(())
+    }
+
+    /// The rules applying to events from `source`, if its project has any.
+    pub fn for_source(&self, source: &str) -> Option<Arc<ProjectPaths>> {
+        self.by_source
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(source)
+            .cloned()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_rules(source: &str, rules: &[PathRule], keep_original: bool) -> Self {
+        let paths = ProjectPaths {
+            rules: rules
+                .iter()
+                .map(|rule| CompiledPathRule::new(rule).expect("valid rule"))
+                .collect(),
+            keep_original,
+        };
+        let rewrites = Self::default();
+        rewrites
+            .by_source
+            .write()
+            .unwrap()
+            .insert(source.to_string(), Arc::new(paths));
+        rewrites
+    }
+}
+
+/// The anchored regex source a pattern of `kind` matches whole pathnames with
+/// (path rules and path goals read patterns alike).
+pub fn pattern_regex(kind: PathPatternKind, pattern: &str) -> String {
+    match kind {
+        PathPatternKind::Glob => glob_regex(pattern),
+        PathPatternKind::Regex => format!("^(?:{pattern})$"),
+    }
+}
+
+/// Translate a path glob into an anchored regex: `*` spans one segment, `**`
+/// anything across segments, `?` one character within a segment.
+fn glob_regex(pattern: &str) -> String {
+    let mut regex = String::with_capacity(pattern.len() + 8);
+    regex.push('^');
+    let mut chars = pattern.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]+"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: PathPatternKind, pattern: &str, template: &str) -> CompiledPathRule {
+        CompiledPathRule::new(&PathRule {
+            kind,
+            pattern: pattern.into(),
+            template: template.into(),
+        })
+        .expect("valid rule")
+    }
+
+    #[test]
+    fn globs_match_whole_segments() {
+        let orders = rule(
+            PathPatternKind::Glob,
+            "/orders/*/items/*",
+            "/orders/:id/items/:id",
+        );
+        assert_eq!(
+            orders.rewrite("/orders/8423/items/19").as_deref(),
+            Some("/orders/:id/items/:id")
+        );
+        assert_eq!(orders.rewrite("/orders/8423/items/19/edit"), None);
+        assert_eq!(orders.rewrite("/orders/8423/items"), None);
+
+        let docs = rule(PathPatternKind::Glob, "/docs/**", "/docs/:page");
+        assert_eq!(docs.rewrite("/docs/a/b/c").as_deref(), Some("/docs/:page"));
+    }
+
+    #[test]
+    fn regex_templates_expand_captures() {
+        let users = rule(
+            PathPatternKind::Regex,
+            r"/users/\d+/(?P<tab>[a-z]+)",
+            "/users/:id/${tab}",
+        );
+        assert_eq!(
+            users.rewrite("/users/42/settings").as_deref(),
+            Some("/users/:id/settings")
+        );
+        // Regex rules must match the whole path.
+        assert_eq!(users.rewrite("/team/users/42/settings"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let invalid = |kind, pattern: &str, template: &str| {
+            CompiledPathRule::new(&PathRule {
+                kind,
+                pattern: pattern.into(),
+                template: template.into(),
+            })
+            .is_err()
+        };
+        assert!(invalid(PathPatternKind::Regex, "/orders/(", "/orders/:id"));
+        assert!(invalid(PathPatternKind::Glob, "", "/x"));
+        assert!(invalid(PathPatternKind::Glob, "/orders/*", "orders"));
+    }
+}
@@ -1,21 +1,25 @@
 //! The ingest pipeline: a non-blocking submit handle backed by a background
 //! batched writer, plus the compaction task.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use tokio::sync::mpsc;
 use tokio::time::MissedTickBehavior;
 use tracing_batteries::prelude::*;
 
-use super::compactor;
+use super::{compactor, similarity};
 use crate::config::StorageConfig;
 use crate::store::{Store, StoredEvent};
 
 const QUEUE_CAPACITY: usize = 16_384;
 const BATCH_SIZE: usize = 512;
 const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// Ceiling on auto-registered releases per source. Versions are client-reported,
+/// so a misbehaving (or hostile) client could otherwise mint one per event.
+const MAX_AUTO_RELEASES: usize = 1_000;
 
 /// Cloneable handle for submitting events into the pipeline.
 #[derive(Clone)]
@@ -42,14 +46,35 @@ pub fn spawn(store: Arc<Store>, storage: StorageConfig) -> Ingest {
     Ingest { tx }
 }
 
+/// The sources and per-source release versions already registered, tracked in
+/// memory to avoid a store hit per event.
+#[derive(Default)]
+struct Known {
+    sources: HashSet<String>,
+    releases: HashMap<String, HashSet<String>>,
+}
+
 async fn writer_loop(store: Arc<Store>, mut rx: mpsc::Receiver<StoredEvent>, max_sources: usize) {
-    // Track already-registered sources in memory to avoid a store hit per event;
-    // seed it once from the persisted sources.
-    let mut known_sources: HashSet<String> = {
+    // Seed the registered sources and releases once from the store.
+    let mut known = {
         let store = store.clone();
-        match tokio::task::spawn_blocking(move || store.list_sources()).await {
-            Ok(Ok(sources)) => sources.into_iter().map(|s| s.uri).collect(),
-            _ => HashSet::new(),
+        let seed = tokio::task::spawn_blocking(move || -> crate::errors::Result<Known> {
+            let mut known = Known {
+                sources: store.list_sources()?.into_iter().map(|s| s.uri).collect(),
+                ..Known::default()
+            };
+            for release in store.list_releases()? {
+                known
+                    .releases
+                    .entry(release.source)
+                    .or_default()
+                    .insert(release.version);
+            }
+            Ok(known)
+        });
+        match seed.await {
+            Ok(Ok(known)) => known,
+            _ => Known::default(),
         }
     };
 
@@ -63,27 +88,28 @@ async fn writer_loop(store: Arc<Store>, mut rx: mpsc::Receiver<StoredEvent>, max
                 Some(event) => {
                     batch.push(event);
                     if batch.len() >= BATCH_SIZE {
-                        flush_batch(&store, &mut batch, &mut known_sources, max_sources).await;
+                        flush_batch(&store, &mut batch, &mut known, max_sources).await;
                     }
                 }
                 None => {
-                    flush_batch(&store, &mut batch, &mut known_sources, max_sources).await;
+                    flush_batch(&store, &mut batch, &mut known, max_sources).await;
                     break;
                 }
             },
             _ = flush.tick() => {
-                flush_batch(&store, &mut batch, &mut known_sources, max_sources).await
+                flush_batch(&store, &mut batch, &mut known, max_sources).await
             }
         }
     }
 }
 
 /// Persist the current batch off the async runtime (redb writes are synchronous),
-/// auto-registering any newly-seen sources as unassigned (up to `max_sources`).
+/// auto-registering any newly-seen sources as unassigned (up to `max_sources`)
+/// and any newly-seen versions of a registered source as releases.
 async fn flush_batch(
     store: &Arc<Store>,
     batch: &mut Vec<StoredEvent>,
-    known_sources: &mut HashSet<String>,
+    known: &mut Known,
     max_sources: usize,
 ) {
     if batch.is_empty() {
@@ -98,10 +124,10 @@ async fn flush_batch(
     let mut seen = HashSet::new();
     let mut capped = false;
     for event in &events {
-        if known_sources.contains(&event.source) || !seen.insert(event.source.clone()) {
+        if known.sources.contains(&event.source) || !seen.insert(event.source.clone()) {
             continue;
         }
-        if known_sources.len() + new_sources.len() >= max_sources {
+        if known.sources.len() + new_sources.len() >= max_sources {
             capped = true;
             continue;
         }
@@ -114,20 +140,66 @@ async fn flush_batch(
         );
     }
 
+    let new_releases = new_releases(&events, known, &new_sources);
+
     let store = store.clone();
     let to_register = new_sources.clone();
+    let releases = new_releases.clone();
     let result = tokio::task::spawn_blocking(move || -> crate::errors::Result<()> {
         store.append_events(&events)?;
+        similarity::index_signatures(&store, &events)?;
         for uri in &to_register {
             store.register_source_if_absent(uri)?;
         }
+        for (source, version, seen_at) in &releases {
+            store.register_release_if_absent(source, version, *seen_at)?;
+        }
         Ok(())
     })
     .await;
 
     match result {
-        Ok(Ok(())) => known_sources.extend(new_sources),
+        Ok(Ok(())) => {
+            known.sources.extend(new_sources);
+            for (source, version, _) in new_releases {
+                known.releases.entry(source).or_default().insert(version);
+            }
+        }
         Ok(Err(err)) => error!("failed to persist events: {err}"),
         Err(err) => error!("event writer task panicked: {err}"),
     }
 }
+
+/// The versions in `events` not yet registered as releases of their source,
+/// each with the time it was first seen. Only registered sources (or those
+/// about to be) get releases, and at most [`MAX_AUTO_RELEASES`] each.
+fn new_releases(
+    events: &[StoredEvent],
+    known: &Known,
+    new_sources: &[String],
+) -> Vec<(String, String, DateTime<Utc>)> {
+    let mut found: Vec<(String, String, DateTime<Utc>)> = Vec::new();
+    for event in events {
+        let Some(version) = event.app_version.as_deref() else {
+            continue;
+        };
+        if !known.sources.contains(&event.source) && !new_sources.contains(&event.source) {
+            continue;
+        }
+        let versions = known.releases.get(&event.source);
+        if versions.is_some_and(|v| v.contains(version))
+            || found
+                .iter()
+                .any(|(source, v, _)| *source == event.source && v == version)
+        {
+            continue;
+        }
+        let pending = found.iter().filter(|(s, ..)| *s == event.source).count();
+        if versions.map_or(0, HashSet::len) + pending >= MAX_AUTO_RELEASES {
+            continue;
+        }
+        let seen_at = DateTime::from_timestamp_millis(event.received_ms).unwrap_or_else(Utc::now);
+        found.push((event.source.clone(), version.to_string(), seen_at));
+    }
+    found
+}
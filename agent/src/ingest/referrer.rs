@@ -1,67 +1,151 @@
-//! Classify a referrer URL into a host and a coarse group (Search/Social), with
-//! self-referrals dropped.
+//! Classify a referrer URL into a host and a coarse group (Search, Social,
+//! Email, AI, ...), with self-referrals dropped and referrer spam recognised.
+//!
+//! The groups come from a versioned rules file: the bundled
+//! `assets/referrers.yaml`, or a replacement named by `ingest.referrers.rules`.
+//! When its `version` differs from the one applied to the stored data, start-up
+//! re-derives every stored event's group (see
+//! [`rederive_attribution_if_needed`](super::rederive_attribution_if_needed)).
 
+use serde::Deserialize;
 use url::Url;
 
+use crate::config::{ReferrerConfig, SpamAction};
+use crate::errors::{Result, ResultExt};
+
+/// The group recorded for flagged referrer spam.
+pub const SPAM_GROUP: &str = "Spam";
+
+const BUNDLED_RULES: &str = include_str!("../../assets/referrers.yaml");
+
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct Referrer {
     pub host: Option<String>,
     pub group: Option<String>,
+    /// The host is on the spam blocklist (and `group` is [`SPAM_GROUP`]).
+    pub spam: bool,
+}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    version: u32,
+    #[serde(default)]
+    groups: Vec<Group>,
+    #[serde(default)]
+    spam: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    name: String,
+    hosts: Vec<String>,
+}
+
+/// The referrer groups and spam blocklist in effect.
+pub struct ReferrerRules {
+    version: u32,
+    groups: Vec<Group>,
+    spam: Vec<String>,
+    spam_action: SpamAction,
+}
+
+impl Default for ReferrerRules {
+    fn default() -> Self {
+        Self::parse(BUNDLED_RULES, SpamAction::default()).expect("bundled referrer rules are valid")
+    }
 }
 
-const SEARCH: &[&str] = &[
-    "google.",
-    "bing.",
-    "duckduckgo.",
-    "yahoo.",
-    "yandex.",
-    "baidu.",
-    "ecosia.",
-    "brave.",
-    "startpage.",
-    "qwant.",
-];
-const SOCIAL: &[&str] = &[
-    "facebook.",
-    "twitter.",
-    "x.com",
-    "t.co",
-    "linkedin.",
-    "reddit.",
-    "instagram.",
-    "youtube.",
-    "mastodon",
-    "bsky.",
-    "pinterest.",
-    "tiktok.",
-    "news.ycombinator.com",
-    "lobste.rs",
-    "threads.",
-];
-
-/// Resolve a referrer URL to a host + group, treating same-host referrals as
-/// internal (no referrer).
-pub fn classify(referrer: Option<&str>, self_host: &str) -> Referrer {
-    let Some(raw) = referrer.map(str::trim).filter(|r| !r.is_empty()) else {
-        return Referrer::default();
-    };
-
-    let host = match Url::parse(raw)
-        .ok()
-        .and_then(|u| u.host_str().map(normalize))
-    {
-        Some(h) => h,
-        None => return Referrer::default(),
-    };
-
-    if host.eq_ignore_ascii_case(self_host) {
-        return Referrer::default();
-    }
-
-    let group = group_for(&host);
-    Referrer {
-        host: Some(host),
-        group,
+impl ReferrerRules {
+    /// Load the configured rules file, or the bundled rules when none is set.
+    pub fn load(config: &ReferrerConfig) -> Result<Self> {
+        let Some(path) = &config.rules else {
+            return Self::parse(BUNDLED_RULES, config.spam);
+        };
+        let raw = std::fs::read_to_string(path).wrap_user_err(
+            format!("Could not read the referrer rules file `{path}`."),
+            &["Check that `ingest.referrers.rules` points at a readable file."],
+        )?;
+        Self::parse(&raw, config.spam)
+    }
+
+    fn parse(raw: &str, spam_action: SpamAction) -> Result<Self> {
+        let file: RulesFile = serde_yaml::from_str(raw).wrap_user_err(
+            "The referrer rules file does not match the expected schema.",
+            &["Compare it against the bundled assets/referrers.yaml."],
+        )?;
+        let lower = |hosts: Vec<String>| -> Vec<String> {
+            hosts
+                .into_iter()
+                .map(|h| h.trim().to_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect()
+        };
+        Ok(Self {
+            version: file.version,
+            groups: file
+                .groups
+                .into_iter()
+                .map(|g| Group {
+                    name: g.name,
+                    hosts: lower(g.hosts),
+                })
+                .collect(),
+            spam: lower(file.spam),
+            spam_action,
+        })
+    }
+
+    /// The rules version, stamped on the stored data once it has been applied.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Whether events from a spam referrer are dropped rather than flagged.
+    pub fn drops_spam(&self) -> bool {
+        self.spam_action == SpamAction::Drop
+    }
+
+    /// Resolve a referrer URL to a host + group, treating same-host referrals as
+    /// internal (no referrer).
+    pub fn classify(&self, referrer: Option<&str>, self_host: &str) -> Referrer {
+        let Some(raw) = referrer.map(str::trim).filter(|r| !r.is_empty()) else {
+            return Referrer::default();
+        };
+
+        let host = match Url::parse(raw)
+            .ok()
+            .and_then(|u| u.host_str().map(normalize))
+        {
+            Some(h) => h,
+            None => return Referrer::default(),
+        };
+
+        if host.eq_ignore_ascii_case(self_host) {
+            return Referrer::default();
+        }
+
+        let spam = self.is_spam(&host);
+        let group = self.group_for(&host);
+        Referrer {
+            host: Some(host),
+            group,
+            spam,
+        }
+    }
+
+    /// The group for an (already normalized) referrer host.
+    pub fn group_for(&self, host: &str) -> Option<String> {
+        if self.is_spam(host) {
+            return Some(SPAM_GROUP.to_string());
+        }
+        self.groups
+            .iter()
+            .find(|g| g.hosts.iter().any(|entry| host_matches(entry, host)))
+            .map(|g| g.name.clone())
+    }
+
+    fn is_spam(&self, host: &str) -> bool {
+        self.spam.iter().any(|entry| host_matches(entry, host))
     }
 }
 
@@ -69,13 +153,16 @@ fn normalize(host: &str) -> String {
     host.trim_start_matches("www.").to_lowercase()
 }
 
-fn group_for(host: &str) -> Option<String> {
-    if SEARCH.iter().any(|p| host.contains(p)) {
-        Some("Search".to_string())
-    } else if SOCIAL.iter().any(|p| host.contains(p)) {
-        Some("Social".to_string())
+/// `google.` matches a `google` label anywhere in the host; `x.com` matches the
+/// domain itself and its subdomains (but not `max.com`).
+fn host_matches(entry: &str, host: &str) -> bool {
+    if entry.ends_with('.') {
+        host.starts_with(entry) || host.contains(&format!(".{entry}"))
     } else {
-        None
+        host == entry
+            || host
+                .strip_suffix(entry)
+                .is_some_and(|prefix| prefix.ends_with('.'))
     }
 }
 
@@ -83,13 +170,18 @@ fn group_for(host: &str) -> Option<String> {
 mod tests {
     use super::*;
 
+    fn classify(referrer: Option<&str>, self_host: &str) -> Referrer {
+        ReferrerRules::default().classify(referrer, self_host)
+    }
+
     #[test]
     fn classifies_search_and_social() {
         assert_eq!(
             classify(Some("https://www.google.com/search?q=x"), "example.com"),
             Referrer {
                 host: Some("google.com".into()),
-                group: Some("Search".into())
+                group: Some("Search".into()),
+                spam: false,
             }
         );
         assert_eq!(
@@ -100,6 +192,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn classifies_email_and_ai_assistants() {
+        let group = |url: &str| classify(Some(url), "example.com").group;
+        assert_eq!(
+            group("https://mail.google.com/mail/u/0").as_deref(),
+            Some("Email")
+        );
+        assert_eq!(group("https://chatgpt.com/").as_deref(), Some("AI"));
+        assert_eq!(
+            group("https://www.perplexity.ai/search").as_deref(),
+            Some("AI")
+        );
+        // Domain entries match whole labels only.
+        assert_eq!(group("https://bit.com/"), None);
+    }
+
+    #[test]
+    fn recognises_referrer_spam() {
+        let r = classify(Some("https://free.semalt.com/"), "example.com");
+        assert!(r.spam);
+        assert_eq!(r.group.as_deref(), Some(SPAM_GROUP));
+    }
+
     #[test]
     fn drops_self_and_empty_referrals() {
         assert_eq!(
@@ -116,4 +231,20 @@ mod tests {
         assert_eq!(r.host.as_deref(), Some("blog.somesite.io"));
         assert_eq!(r.group, None);
     }
+
+    #[test]
+    fn custom_rules_replace_the_bundled_ones() {
+        let rules = ReferrerRules::parse(
+            "version: 7\ngroups:\n  - name: Partners\n    hosts: [partner.example]\n",
+            SpamAction::Flag,
+        )
+        .unwrap();
+        assert_eq!(rules.version(), 7);
+        assert!(!rules.drops_spam());
+        assert_eq!(
+            rules.group_for("shop.partner.example").as_deref(),
+            Some("Partners")
+        );
+        assert_eq!(rules.group_for("google.com"), None);
+    }
 }
@@ -1,12 +1,24 @@
-//! Re-group stored exceptions when the fingerprinting rules change.
+//! Re-group stored exceptions when the fingerprinting rules change (the built-in
+//! ones, or a project's), a source moves between projects or a group is split,
+//! and re-derive referrer groups and channels when the referrer or channel rules
+//! do. Moving
+//! occurrences rebuilds the exception indexes (search terms and similarity
+//! signatures), which are keyed by their groups.
 
+use std::collections::HashSet;
+use std::sync::{Arc, MutexGuard};
+
+use chrono::Utc;
 use tracing_batteries::prelude::*;
 
 use crate::config::StorageConfig;
 use crate::errors::Result;
-use crate::store::Store;
+use crate::store::{Store, StoredException, StoredVisit};
 
+use super::channel::Visit;
 use super::exception::{FINGERPRINT_VERSION, fingerprint};
+use super::search::reindex_exceptions;
+use super::{Channels, GroupEdits, GroupingRules, IngestRules, ReferrerRules};
 
 /// Re-group every stored exception when the grouping rules have changed since the
 /// data was last processed, then stamp the current rules version. A no-op when the
@@ -14,11 +26,20 @@ use super::exception::{FINGERPRINT_VERSION, fingerprint};
 /// is cheap to call unconditionally at start-up.
 ///
 /// Recomputes each occurrence's `exc_group` from its stored `(type, message,
-/// stack)` using the current rules, over both the redb hot store and the archived
-/// Parquet partitions, so live and historical occurrences of the same failure land
-/// in one group. The work scales with the size of the archive, so it runs to
-/// completion before the server begins accepting traffic.
-pub fn regroup_if_needed(store: &Store, storage: &StorageConfig) -> Result<()> {
+/// stack)` and client fingerprint override using the current rules, its
+/// project's fingerprint rules and any manual merge or split, over both the redb
+/// hot store and the archived Parquet partitions, so live and historical
+/// occurrences of the same failure land in one group. The work scales with the
+/// size of the archive, so it runs to completion before the server begins
+/// accepting traffic (and before the compactor starts). Later changes to a
+/// project's rules, or to which project a source belongs, re-group just the
+/// sources affected through [`spawn_regroup`].
+pub fn regroup_if_needed(
+    store: &Store,
+    storage: &StorageConfig,
+    grouping: &GroupingRules,
+    edits: &GroupEdits,
+) -> Result<()> {
     let applied = store.fingerprint_version()?;
     if applied == FINGERPRINT_VERSION {
         return Ok(());
@@ -29,11 +50,11 @@ pub fn regroup_if_needed(store: &Store, storage: &StorageConfig) -> Result<()> {
          re-grouping stored exceptions"
     );
 
-    let remap = |exc_type: &str, message: Option<&str>, stack: Option<&str>| {
-        fingerprint(exc_type, message.unwrap_or_default(), stack, None)
-    };
-    let hot = store.regroup_hot_exceptions(&remap)?;
-    let cold = store.regroup_cold_exceptions(&storage.parquet_dir, &remap)?;
+    let started = Utc::now();
+    let remap = |exception: &StoredException<'_>| Some(regroup(exception, grouping, edits));
+    let archive = store.lock_archive();
+    let (hot, cold) = regroup_stored(store, storage, &remap, &archive)?;
+    store.mark_group_edits_applied(&|_| true, started)?;
     store.set_fingerprint_version(FINGERPRINT_VERSION)?;
 
     info!(
@@ -42,3 +63,298 @@ pub fn regroup_if_needed(store: &Store, storage: &StorageConfig) -> Result<()> {
     );
     Ok(())
 }
+
+/// Run [`regroup_sources`] in the background, logging the outcome. The pass
+/// rewrites the archive, so it runs on the blocking pool rather than in the
+/// request that changed the rules.
+pub fn spawn_regroup(
+    store: Arc<Store>,
+    storage: StorageConfig,
+    rules: Arc<IngestRules>,
+    sources: HashSet<String>,
+) {
+    if sources.is_empty() {
+        return;
+    }
+    tokio::task::spawn_blocking(move || {
+        let archive = store.lock_archive();
+        if let Err(err) = regroup_sources(
+            &store,
+            &storage,
+            &rules.grouping,
+            &rules.edits,
+            &sources,
+            &archive,
+        ) {
+            error!("re-grouping exceptions failed: {err}");
+        }
+    });
+}
+
+/// Re-group the stored exceptions of `sources` under the current rules, after
+/// their project's fingerprint rules changed, they moved to another project or
+/// other sources' exceptions were re-attributed to them; other sources' groups
+/// are left alone. Covers the hot store and the archive like
+/// [`regroup_if_needed`], under the caller's archive lock so compaction waits,
+/// and returns the number of occurrences that moved.
+pub fn regroup_sources(
+    store: &Store,
+    storage: &StorageConfig,
+    grouping: &GroupingRules,
+    edits: &GroupEdits,
+    sources: &HashSet<String>,
+    archive: &MutexGuard<'_, ()>,
+) -> Result<usize> {
+    let remap = |exception: &StoredException<'_>| {
+        sources
+            .contains(exception.source)
+            .then(|| regroup(exception, grouping, edits))
+    };
+    let started = Utc::now();
+    let (hot, cold) = regroup_stored(store, storage, &remap, archive)?;
+    let moved = hot + cold;
+    store.mark_group_edits_applied(&|source| sources.contains(source), started)?;
+    info!(
+        "re-grouped {moved} exception occurrences of {} sources under their project's \
+         fingerprint rules",
+        sources.len()
+    );
+    Ok(moved)
+}
+
+/// Run [`apply_group_edits`] in the background after a merge or split, logging
+/// the outcome; queries follow the edit until it has been applied.
+pub fn spawn_group_edits(
+    store: Arc<Store>,
+    storage: StorageConfig,
+    rules: Arc<IngestRules>,
+    source: String,
+) {
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = apply_group_edits(&store, &storage, &rules.edits, &source) {
+            error!("applying exception group merges and splits failed: {err}");
+        }
+    });
+}
+
+/// Apply the recorded merges and splits to the stored exceptions of `source`,
+/// after a group of it was merged or split. Unlike a re-grouping pass the
+/// stored groups aren't recomputed from scratch, so only the merged and split
+/// occurrences move. Holds the archive lock so compaction waits, and returns
+/// the number of occurrences that moved.
+pub fn apply_group_edits(
+    store: &Store,
+    storage: &StorageConfig,
+    edits: &GroupEdits,
+    source: &str,
+) -> Result<usize> {
+    let remap = |exception: &StoredException<'_>| {
+        let group = exception.group.filter(|_| exception.source == source)?;
+        let resolved = edits.resolve(
+            source,
+            group.to_string(),
+            exception.message.unwrap_or_default(),
+            exception.stack,
+        );
+        (resolved != group).then_some(resolved)
+    };
+    let archive = store.lock_archive();
+    let started = Utc::now();
+    let (hot, cold) = regroup_stored(store, storage, &remap, &archive)?;
+    let moved = hot + cold;
+    store.mark_group_edits_applied(&|merged| merged == source, started)?;
+    info!("moved {moved} exception occurrences of {source} into merged or split-off groups");
+    Ok(moved)
+}
+
+/// Re-group the live and archived exceptions through `remap`, rebuilding the
+/// exception indexes when any moved, under the caller's archive lock. Returns
+/// the number of live and archived occurrences that moved.
+fn regroup_stored(
+    store: &Store,
+    storage: &StorageConfig,
+    remap: &dyn Fn(&StoredException<'_>) -> Option<String>,
+    archive: &MutexGuard<'_, ()>,
+) -> Result<(usize, usize)> {
+    let hot = store.regroup_hot_exceptions(remap)?;
+    let cold = store.regroup_cold_exceptions(&storage.parquet_dir, remap)?;
+    if hot + cold > 0 {
+        reindex_exceptions(store, storage, archive)?;
+    }
+    Ok((hot, cold))
+}
+
+/// An occurrence's group under the current rules.
+fn regroup(
+    exception: &StoredException<'_>,
+    grouping: &GroupingRules,
+    edits: &GroupEdits,
+) -> String {
+    let message = exception.message.unwrap_or_default();
+    let rules = grouping.for_source(exception.source);
+    let group = fingerprint(
+        exception.exc_type,
+        message,
+        exception.stack,
+        exception.fingerprint,
+        rules.as_deref(),
+    );
+    edits.resolve(exception.source, group, message, exception.stack)
+}
+
+/// Re-derive every stored event's `referrer_group` and `channel` when the
+/// referrer rules version or the channel rules differ from the ones applied to
+/// the data, then stamp both. Like [`regroup_if_needed`] this is a no-op on the
+/// common path and covers both the hot store and the archive.
+///
+/// Historical events from a blocklisted host are flagged into the spam group
+/// rather than deleted, whatever `ingest.referrers.spam` says for new traffic.
+/// Only hits carry a channel, so events stored without one keep none.
+pub fn rederive_attribution_if_needed(
+    store: &Store,
+    storage: &StorageConfig,
+    referrers: &ReferrerRules,
+    channels: &Channels,
+) -> Result<()> {
+    let (applied, current) = (store.referrer_version()?, referrers.version());
+    let (applied_channels, current_channels) = (store.channel_version()?, channels.version());
+    if applied == current && applied_channels == current_channels {
+        return Ok(());
+    }
+
+    info!(
+        "referrer or channel rules changed (applied v{applied}, current v{current}); \
+         re-deriving stored referrer groups and channels"
+    );
+
+    let rederive = |visit: &StoredVisit<'_>| {
+        let group = match visit.referrer_host {
+            Some(host) => referrers.group_for(host),
+            None => visit.referrer_group.map(str::to_string),
+        };
+        let channel = visit.channel.map(|_| {
+            channels.classify(&Visit {
+                referrer_host: visit.referrer_host,
+                referrer_group: group.as_deref(),
+                utm_source: visit.utm_source,
+                utm_medium: visit.utm_medium,
+                utm_campaign: visit.utm_campaign,
+            })
+        });
+        (group, channel)
+    };
+    let hot = store.rederive_hot_attribution(&rederive)?;
+    let cold = store.rederive_cold_attribution(&storage.parquet_dir, &rederive)?;
+    store.set_referrer_version(current)?;
+    store.set_channel_version(current_channels)?;
+
+    info!(
+        "re-derived the referrer group or channel of {hot} live and {cold} archived events \
+         (referrer rules v{current})"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{EventKind, GroupRedirect, StoredEvent, read_partition, write_partition};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn temp(suffix: &str) -> std::path::PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+        std::env::temp_dir().join(format!(
+            "analytics-group-edits-{}-{}-{}",
+            std::process::id(),
+            n,
+            suffix
+        ))
+    }
+
+    fn exception(source: &str, received_ms: i64, group: &str) -> StoredEvent {
+        StoredEvent {
+            received_ms,
+            created_ms: received_ms,
+            kind: EventKind::Exception,
+            source: source.into(),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some("boom".into()),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn applying_a_merge_moves_its_occurrences_and_marks_it_applied() {
+        let redb = temp("redb");
+        let parquet = temp("parquet");
+        let store = Store::open(&redb).unwrap();
+        let (site, other) = ("https://example.com", "https://other.org");
+
+        let file = parquet
+            .join("2025")
+            .join("01")
+            .join("01")
+            .join("events-1.parquet");
+        write_partition(
+            &[exception(site, 1_000, "a"), exception(site, 2_000, "c")],
+            &file,
+        )
+        .unwrap();
+        store
+            .append_events(&[exception(site, 3_000, "a"), exception(other, 4_000, "a")])
+            .unwrap();
+        let merge = GroupRedirect {
+            source: site.into(),
+            group_id: "a".into(),
+            target: "b".into(),
+            merged_at: Utc::now(),
+            merged_by: None,
+            applied: false,
+        };
+        store.merge_exception_groups(None, &[merge], None).unwrap();
+
+        let edits = GroupEdits::load(&store).unwrap();
+        let storage = StorageConfig {
+            parquet_dir: parquet.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            apply_group_edits(&store, &storage, &edits, site).unwrap(),
+            2
+        );
+
+        // Only the merged source's occurrences move, hot and archived.
+        let hot: Vec<(String, Option<String>)> = store
+            .all_events()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.source, e.exc_group))
+            .collect();
+        assert_eq!(
+            hot,
+            vec![
+                (site.to_string(), Some("b".to_string())),
+                (other.to_string(), Some("a".to_string())),
+            ]
+        );
+        let df = read_partition(&file).unwrap();
+        let cold = df.column("exc_group").unwrap().str().unwrap();
+        assert_eq!(
+            (0..df.height()).map(|i| cold.get(i)).collect::<Vec<_>>(),
+            vec![Some("b"), Some("c")]
+        );
+        assert!(store.list_group_redirects().unwrap()[0].applied);
+
+        // Nothing is left to move.
+        assert_eq!(
+            apply_group_edits(&store, &storage, &edits, site).unwrap(),
+            0
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+        let _ = std::fs::remove_dir_all(&parquet);
+    }
+}
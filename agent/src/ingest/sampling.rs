@@ -0,0 +1,359 @@
+//! Per-group exception storm protection: a single tight-loop error on a busy
+//! page must not fill the store with millions of identical rows.
+//!
+//! Each exception group gets a window of its project's
+//! [`ExceptionSampling`] length. The first `limit` occurrences in a window are
+//! stored in full; past that, only every n-th occurrence is stored, with `n`
+//! doubling for every further `limit` occurrences (up to [`MAX_STRIDE`]). A
+//! stored sample stands for itself and the occurrences skipped before it, and
+//! records that as its `sample_rate`, so the counts derived from it stay
+//! accurate. Occurrences skipped since the last sample are carried into the
+//! group's next stored occurrence, whichever window it falls in; once a window
+//! has ended with none to carry them, the last skipped occurrence is stored in
+//! their stead by [`spawn_flush`] (and on shutdown), so no count is lost.
+//!
+//! A session's first unhandled occurrence is always stored, whatever the
+//! sampling, so a storm doesn't hide which sessions crashed; it stands for
+//! itself alone and doesn't count towards the group's window.
+//!
+//! Limits live on the [`Project`](analytics_api::Project) and are cached by
+//! source like the other project rules, [`reload`](ExceptionSampler::reload)ed
+//! after every change; sources outside a project get the default limits.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use analytics_api::ExceptionSampling;
+use chrono::Utc;
+use tokio::time::MissedTickBehavior;
+
+use super::{Ingest, IngestRules};
+use crate::errors::Result;
+use crate::store::{Store, StoredEvent};
+
+/// The sparsest sampling: one stored occurrence in this many.
+const MAX_STRIDE: u64 = 1_024;
+/// The longest sampling window, in seconds.
+const MAX_WINDOW_SECS: u32 = 24 * 60 * 60;
+/// Ceiling on the groups tracked at once, so a flood of distinct fingerprints
+/// can't grow the window table without bound.
+const MAX_TRACKED: usize = 10_000;
+/// Ceiling on the crashed sessions remembered at once.
+const MAX_CRASHED_SESSIONS: usize = 100_000;
+/// How long a crashed session is remembered after its last unhandled
+/// occurrence.
+const CRASH_MEMORY_MS: i64 = 24 * 60 * 60 * 1_000;
+/// How often ended windows are flushed.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Check `sampling` before it is saved. `Err` carries a message suitable for a
+/// 400 response.
+pub fn validate_sampling(sampling: &ExceptionSampling) -> std::result::Result<(), String> {
+    if sampling.window_secs == 0 || sampling.window_secs > MAX_WINDOW_SECS {
+        return Err(format!(
+            "The sampling window must be between 1 and {MAX_WINDOW_SECS} seconds."
+        ));
+    }
+    Ok(())
+}
+
+/// One group's current window.
+struct Window {
+    started_ms: i64,
+    /// The window's length, from its project's sampling limits.
+    window_ms: i64,
+    /// Occurrences in the window so far.
+    seen: u64,
+    /// Occurrences counted but not stored since the group's last stored one.
+    skipped: u64,
+    /// The latest of those, stored in their stead should the window end
+    /// without a further occurrence.
+    pending: Option<StoredEvent>,
+}
+
+/// The sampling limits of every project, keyed by member source URI, and the
+/// window of every recently seen exception group.
+#[derive(Default)]
+pub struct ExceptionSampler {
+    by_source: RwLock<HashMap<String, ExceptionSampling>>,
+    windows: Mutex<HashMap<(String, String), Window>>,
+    /// When each `(source, session)` last reported an unhandled occurrence,
+    /// for sessions that already have one stored.
+    crashed: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl ExceptionSampler {
+    pub fn load(store: &Store) -> Result<Self> {
+        let sampler = Self::default();
+        sampler.reload(store)?;
+        Ok(sampler)
+    }
+
+    /// Rebuild the limits from the stored projects and source assignments.
+    pub fn reload(&self, store: &Store) -> Result<()> {
+        let by_project: HashMap<String, ExceptionSampling> = store
+            .list_projects()?
+            .into_iter()
+            .filter(|project| project.exception_sampling != ExceptionSampling::default())
+            .map(|project| (project.id, project.exception_sampling))
+            .collect();
+        let by_source = store
+            .list_sources()?
+            .into_iter()
+            .filter_map(|source| {
+                let sampling = by_project.get(source.project_id.as_deref()?)?;
+                Some((source.uri, *sampling))
+            })
+            .collect();
+        *self.by_source.write().unwrap_or_else(|e| e.into_inner()) = by_source;
+        Ok(())
+    }
+
+    /// Count an exception occurrence and decide whether to store it: `Some`
+    /// with its `sample_rate` set when it stands for skipped occurrences too,
+    /// `None` when it is only counted. The first `unhandled` occurrence of each
+    /// session is stored before sampling is considered.
+    pub fn admit(&self, event: StoredEvent, unhandled: bool) -> Option<StoredEvent> {
+        let sampling = self
+            .by_source
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&event.source)
+            .copied()
+            .unwrap_or_default();
+        let Some(group) = event.exc_group.clone().filter(|_| sampling.limit > 0) else {
+            return Some(event);
+        };
+        let received_ms = event.received_ms;
+        let crashed = event.sid.as_deref().filter(|_| unhandled);
+        if crashed.is_some_and(|sid| self.first_crash(&event.source, sid, received_ms)) {
+            return Some(event);
+        }
+        let window_ms = i64::from(sampling.window_secs) * 1_000;
+
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (event.source.clone(), group);
+        if !windows.contains_key(&key) && windows.len() >= MAX_TRACKED {
+            // Finished windows with nothing carried can go (the flush takes
+            // the rest); if every tracked group is mid-storm, untracked groups
+            // are stored unsampled.
+            windows.retain(|_, w| w.pending.is_some() || received_ms - w.started_ms < w.window_ms);
+            if windows.len() >= MAX_TRACKED {
+                return Some(event);
+            }
+        }
+        let window = windows.entry(key).or_insert(Window {
+            started_ms: received_ms,
+            window_ms,
+            seen: 0,
+            skipped: 0,
+            pending: None,
+        });
+        if received_ms - window.started_ms >= window.window_ms {
+            window.started_ms = received_ms;
+            window.window_ms = window_ms;
+            window.seen = 0;
+        }
+        window.seen += 1;
+
+        let limit = u64::from(sampling.limit);
+        let stride = if window.seen <= limit {
+            1
+        } else {
+            let doublings = ((window.seen - limit - 1) / limit + 1).min(MAX_STRIDE.ilog2() as u64);
+            1 << doublings
+        };
+        if window.skipped + 1 < stride {
+            window.skipped += 1;
+            window.pending = Some(event);
+            return None;
+        }
+        let stands_for = window.skipped + 1;
+        window.skipped = 0;
+        window.pending = None;
+        Some(standing_for(event, stands_for))
+    }
+
+    /// Forget every window that ended by `now_ms`, returning the last skipped
+    /// occurrence of each that still carries skipped ones, standing for them
+    /// all. [`i64::MAX`] ends every window, for a shutdown.
+    pub fn flush(&self, now_ms: i64) -> Vec<StoredEvent> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let mut flushed = Vec::new();
+        windows.retain(|_, window| {
+            if now_ms.saturating_sub(window.started_ms) < window.window_ms {
+                return true;
+            }
+            if let Some(event) = window.pending.take() {
+                flushed.push(standing_for(event, window.skipped));
+            }
+            false
+        });
+        flushed
+    }
+
+    /// Note an unhandled occurrence of session `sid` on `source`, returning
+    /// whether it is the session's first. When the table is full of sessions
+    /// that crashed recently, new ones are left to the sampling.
+    fn first_crash(&self, source: &str, sid: &str, received_ms: i64) -> bool {
+        let mut crashed = self.crashed.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (source.to_string(), sid.to_string());
+        if let Some(last_ms) = crashed.get_mut(&key) {
+            *last_ms = received_ms;
+            return false;
+        }
+        if crashed.len() >= MAX_CRASHED_SESSIONS {
+            crashed.retain(|_, last_ms| received_ms - *last_ms < CRASH_MEMORY_MS);
+            if crashed.len() >= MAX_CRASHED_SESSIONS {
+                return false;
+            }
+        }
+        crashed.insert(key, received_ms);
+        true
+    }
+}
+
+/// `event` as the stored sample of `stands_for` occurrences.
+fn standing_for(event: StoredEvent, stands_for: u64) -> StoredEvent {
+    StoredEvent {
+        sample_rate: (stands_for > 1).then(|| 1.0 / stands_for as f64),
+        ..event
+    }
+}
+
+/// Periodically submit the occurrences that stand in for those skipped in
+/// windows that have since ended.
+pub fn spawn_flush(rules: Arc<IngestRules>, ingest: Ingest) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(FLUSH_INTERVAL);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tick.tick().await;
+            for event in rules.sampling.flush(Utc::now().timestamp_millis()) {
+                ingest.submit(event);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::EventKind;
+
+    fn occurrence(source: &str, group: &str, received_ms: i64, sid: Option<&str>) -> StoredEvent {
+        StoredEvent {
+            received_ms,
+            created_ms: received_ms,
+            kind: EventKind::Exception,
+            source: source.into(),
+            sid: sid.map(str::to_string),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+
+    /// The sample rate an occurrence is stored with, if it is.
+    fn admit(sampler: &ExceptionSampler, group: &str, received_ms: i64) -> Option<f64> {
+        sampler
+            .admit(occurrence("https://a.com", group, received_ms, None), false)
+            .map(|event| event.sample_rate.unwrap_or(1.0))
+    }
+
+    fn pending(sampler: &ExceptionSampler, group: &str) -> u64 {
+        sampler.windows.lock().unwrap()[&("https://a.com".into(), group.into())].skipped
+    }
+
+    #[test]
+    fn storms_are_sampled_without_losing_count() {
+        let sampler = ExceptionSampler::default();
+        let limit = u64::from(ExceptionSampling::default().limit);
+        let admitted: Vec<f64> = (0..10 * limit)
+            .filter_map(|i| admit(&sampler, "g1", i as i64))
+            .collect();
+
+        // The first `limit` are stored in full, then progressively fewer.
+        assert!(admitted[..limit as usize].iter().all(|&rate| rate == 1.0));
+        assert!(admitted.len() < 3 * limit as usize);
+        // Every stored sample stands for the occurrences skipped before it.
+        let counted: f64 = admitted.iter().map(|rate| (1.0 / rate).round()).sum();
+        let pending = pending(&sampler, "g1");
+        assert_eq!(counted as u64 + pending, 10 * limit);
+
+        // Other groups and later windows start afresh, carrying what was skipped.
+        assert_eq!(admit(&sampler, "g2", 0), Some(1.0));
+        let next = admit(&sampler, "g1", 120_000).unwrap();
+        assert_eq!((1.0 / next).round() as u64, pending + 1);
+        assert_eq!(admit(&sampler, "g1", 120_001), Some(1.0));
+    }
+
+    #[test]
+    fn ended_windows_flush_what_they_skipped() {
+        let sampler = ExceptionSampler::default();
+        let limit = i64::from(ExceptionSampling::default().limit);
+        let counted: f64 = (0..10 * limit)
+            .filter_map(|i| admit(&sampler, "g1", i))
+            .map(|rate| (1.0 / rate).round())
+            .sum();
+        let skipped = pending(&sampler, "g1");
+        assert!(skipped > 0);
+
+        // Nothing is flushed while the window runs.
+        assert!(sampler.flush(10 * limit).is_empty());
+        let flushed = sampler.flush(120_000);
+        assert_eq!(flushed.len(), 1);
+        let rate = flushed[0].sample_rate.unwrap_or(1.0);
+        assert_eq!(
+            counted as u64 + (1.0 / rate).round() as u64,
+            10 * limit as u64
+        );
+        // The flushed window is forgotten, so the table doesn't fill up.
+        assert!(sampler.windows.lock().unwrap().is_empty());
+
+        // A shutdown ends every window.
+        for i in 0..10 * limit {
+            admit(&sampler, "g1", 200_000 + i);
+        }
+        assert_eq!(sampler.flush(i64::MAX).len(), 1);
+    }
+
+    #[test]
+    fn every_crashed_session_keeps_an_occurrence() {
+        let sampler = ExceptionSampler::default();
+        let limit = i64::from(ExceptionSampling::default().limit);
+        // Fill the window so plain occurrences are only counted.
+        for i in 0..10 * limit {
+            admit(&sampler, "g1", i);
+        }
+        let sampled = (0..4)
+            .filter(|_| admit(&sampler, "g1", 10 * limit).is_some())
+            .count();
+        assert!(sampled < 4);
+
+        // A session's first unhandled occurrence is stored in full; its later
+        // ones are sampled like any other.
+        let crash = |source: &str, sid| {
+            sampler
+                .admit(occurrence(source, "g1", 10 * limit, Some(sid)), true)
+                .map(|event| event.sample_rate.unwrap_or(1.0))
+        };
+        assert_eq!(crash("https://a.com", "s1"), Some(1.0));
+        assert_eq!(crash("https://a.com", "s2"), Some(1.0));
+        assert!((0..4).any(|_| crash("https://a.com", "s1").is_none()));
+        assert_eq!(crash("https://b.com", "s1"), Some(1.0));
+    }
+
+    #[test]
+    fn rejects_out_of_range_windows() {
+        let window = |window_secs| {
+            validate_sampling(&ExceptionSampling {
+                limit: 10,
+                window_secs,
+            })
+        };
+        assert!(window(0).is_err());
+        assert!(window(60).is_ok());
+        assert!(window(MAX_WINDOW_SECS + 1).is_err());
+    }
+}
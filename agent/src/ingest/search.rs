@@ -0,0 +1,311 @@
+//! Full-text search over exception groups, by the words of their occurrences'
+//! types, messages and stack frames.
+//!
+//! Archived occurrences are found through the store's term index, which the
+//! compactor extends as it archives (and rebuilds once retention drops
+//! partitions) and the re-grouping passes rebuild; the hot occurrences
+//! received in the searched range are read directly. A search matches the
+//! groups with a word starting with each of its words, so `checkout undef`
+//! finds a group whose occurrences mention `CheckoutForm` and `undefined`,
+//! though not necessarily the same occurrence.
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::MutexGuard;
+
+use tracing_batteries::prelude::*;
+
+use crate::config::StorageConfig;
+use crate::errors::Result;
+use crate::store::{EventKind, Store, StoredEvent, StoredException};
+
+use super::GroupEdits;
+
+/// The version of the exception indexes: the term index and the similarity
+/// signatures. Bump it when [`terms`] or the signatures change, so both are
+/// rebuilt on next start.
+///
+/// v2: the similarity signatures are indexed.
+pub const SEARCH_VERSION: u32 = 2;
+/// The shortest word indexed or searched for.
+const MIN_WORD: usize = 2;
+/// The longest word indexed; longer ones (hashes, minified blobs) are skipped.
+const MAX_WORD: usize = 40;
+/// The most distinct words indexed per occurrence, so a huge stack can't
+/// flood the index.
+const MAX_TERMS: usize = 256;
+/// The most words a search is narrowed by.
+const MAX_QUERY_WORDS: usize = 8;
+
+/// The distinct words of an occurrence, as indexed: each word, plus the parts
+/// of a camel-case or snake-case identifier, so `checkout` finds
+/// `submitCheckout`.
+pub(crate) fn terms(exc_type: &str, message: Option<&str>, stack: Option<&str>) -> Vec<String> {
+    let mut terms = BTreeSet::new();
+    for text in [Some(exc_type), message, stack].into_iter().flatten() {
+        for word in words(text) {
+            for term in std::iter::once(word).chain(identifier_parts(word)) {
+                if terms.len() >= MAX_TERMS {
+                    return terms.into_iter().collect();
+                }
+                terms.insert(term.to_lowercase());
+            }
+        }
+    }
+    terms.into_iter().collect()
+}
+
+/// The lowercased words a search is for, in order and without repeats.
+pub fn query_words(search: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    words(search)
+        .map(str::to_lowercase)
+        .filter(|word| seen.insert(word.clone()))
+        .take(MAX_QUERY_WORDS)
+        .collect()
+}
+
+/// Runs of letters, digits and underscores, skipping bare numbers (line and
+/// column numbers, ids) and words too short or too long to search by.
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| searchable(word))
+}
+
+fn searchable(word: &str) -> bool {
+    (MIN_WORD..=MAX_WORD).contains(&word.chars().count())
+        && !word.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The searchable parts of an identifier split at underscores and lower-to-upper
+/// case changes; empty when it has no parts to split.
+fn identifier_parts(word: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<char> = None;
+    for (i, c) in word.char_indices() {
+        let boundary = c == '_' || (c.is_uppercase() && previous.is_some_and(char::is_lowercase));
+        if boundary {
+            parts.push(&word[start..i]);
+            start = if c == '_' { i + 1 } else { i };
+        }
+        previous = Some(c);
+    }
+    if parts.is_empty() {
+        return parts;
+    }
+    parts.push(&word[start..]);
+    parts.retain(|part| searchable(part));
+    parts
+}
+
+/// Index the terms of the exceptions among `events`, as they are archived.
+pub(super) fn index_exceptions(store: &Store, events: &[StoredEvent]) -> Result<()> {
+    let mut entries = BTreeSet::new();
+    for event in events.iter().filter(|e| e.kind == EventKind::Exception) {
+        let Some(group) = &event.exc_group else {
+            continue;
+        };
+        for term in event_terms(event) {
+            entries.insert((term, event.source.clone(), group.clone()));
+        }
+    }
+    store.add_exception_terms(&entries)
+}
+
+/// Rebuild the term index from the archive and the similarity signatures from
+/// every stored occurrence, after occurrences moved between groups or sources
+/// or retention dropped archived ones, and stamp the current
+/// [`SEARCH_VERSION`].
+///
+/// The rebuild reads the archive and the hot store, so it takes the caller's
+/// [`Store::lock_archive`] guard rather than locking itself: the caller holds
+/// it across the pass that made the rebuild necessary, and compaction waits
+/// for both.
+pub fn reindex_exceptions(
+    store: &Store,
+    storage: &StorageConfig,
+    _archive: &MutexGuard<'_, ()>,
+) -> Result<()> {
+    let exception_terms = |exception: &StoredException<'_>| {
+        terms(exception.exc_type, exception.message, exception.stack)
+    };
+    let indexed = store.rebuild_exception_terms(&storage.parquet_dir, &exception_terms)?;
+    let signed = super::similarity::rebuild_signatures(store, &storage.parquet_dir)?;
+    store.set_search_version(SEARCH_VERSION)?;
+    info!("rebuilt the exception indexes ({indexed} terms, {signed} group signatures)");
+    Ok(())
+}
+
+/// Rebuild the exception indexes when they were built by another version (or
+/// never, on data stored before they existed). Cheap to call at start-up.
+pub fn reindex_if_needed(store: &Store, storage: &StorageConfig) -> Result<()> {
+    if store.search_version()? == SEARCH_VERSION {
+        return Ok(());
+    }
+    reindex_exceptions(store, storage, &store.lock_archive())
+}
+
+/// The `(source, group)` of every exception group whose occurrences have a
+/// word starting with each word of `search`, following manual merges; `None`
+/// when `search` has no words to narrow by. Hot occurrences count only when
+/// received in `[from_ms, to_ms)`; the caller narrows archived ones to its
+/// range.
+pub fn search_exception_groups(
+    store: &Store,
+    edits: &GroupEdits,
+    search: &str,
+    from_ms: i64,
+    to_ms: i64,
+) -> Result<Option<HashSet<(String, String)>>> {
+    let words = query_words(search);
+    if words.is_empty() {
+        return Ok(None);
+    }
+
+    let mut matches: Vec<HashSet<(String, String)>> = words
+        .iter()
+        .map(|word| store.exception_groups_with_term(word))
+        .collect::<Result<_>>()?;
+    for event in store.events_between(from_ms, to_ms)? {
+        if event.kind != EventKind::Exception {
+            continue;
+        }
+        let Some(group) = &event.exc_group else {
+            continue;
+        };
+        let terms = event_terms(&event);
+        for (word, matched) in words.iter().zip(&mut matches) {
+            if terms.iter().any(|term| term.starts_with(word.as_str())) {
+                matched.insert((event.source.clone(), group.clone()));
+            }
+        }
+    }
+
+    let redirect = |(source, group): (String, String)| {
+        let group = edits.redirect(&source, &group).unwrap_or(group);
+        (source, group)
+    };
+    let mut matches = matches
+        .into_iter()
+        .map(|matched| matched.into_iter().map(redirect).collect::<HashSet<_>>());
+    let first = matches.next().unwrap_or_default();
+    Ok(Some(matches.fold(first, |all, matched| {
+        all.intersection(&matched).cloned().collect()
+    })))
+}
+
+fn event_terms(event: &StoredEvent) -> Vec<String> {
+    terms(
+        event.exc_type.as_deref().unwrap_or(""),
+        event.exc_message.as_deref(),
+        event.exc_stack.as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn exception(received_ms: i64, group: &str, message: &str, stack: &str) -> StoredEvent {
+        StoredEvent {
+            received_ms,
+            created_ms: received_ms,
+            kind: EventKind::Exception,
+            source: "https://example.com".into(),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some(message.into()),
+            exc_stack: Some(stack.into()),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn terms_skip_numbers_and_fold_case() {
+        assert_eq!(
+            terms(
+                "TypeError",
+                Some("Cannot read 'id' of undefined"),
+                Some("at CheckoutForm.render (app.js:10:5)")
+            ),
+            vec![
+                "app",
+                "at",
+                "cannot",
+                "checkout",
+                "checkoutform",
+                "error",
+                "form",
+                "id",
+                "js",
+                "of",
+                "read",
+                "render",
+                "type",
+                "typeerror",
+                "undefined"
+            ]
+        );
+        assert_eq!(
+            terms("x", Some("load_user_42"), None),
+            vec!["load", "load_user_42", "user"]
+        );
+        assert_eq!(query_words("Checkout checkout 42 x"), vec!["checkout"]);
+    }
+
+    #[test]
+    fn searches_match_every_word_across_hot_and_archived_occurrences() {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "analytics-search-ingest-{}-{}.redb",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let store = Store::open(&path).unwrap();
+        let edits = GroupEdits::default();
+
+        // An archived occurrence, indexed as the compactor would.
+        index_exceptions(
+            &store,
+            &[exception(
+                1_000,
+                "g1",
+                "Payment declined",
+                "at submitCheckout (pay.js:3:1)",
+            )],
+        )
+        .unwrap();
+        store
+            .append_events(&[
+                exception(5_000, "g1", "Payment timed out", "at retry (pay.js:9:9)"),
+                exception(6_000, "g2", "Checkout failed", "at render (cart.js:1:1)"),
+            ])
+            .unwrap();
+
+        let search = |query: &str, from_ms: i64| {
+            let mut groups: Vec<String> =
+                search_exception_groups(&store, &edits, query, from_ms, 10_000)
+                    .unwrap()
+                    .unwrap()
+                    .into_iter()
+                    .map(|(_, group)| group)
+                    .collect();
+            groups.sort();
+            groups
+        };
+        // Words may match different occurrences of a group.
+        assert_eq!(search("payment submitcheck", 0), vec!["g1"]);
+        assert_eq!(search("checkout", 0), vec!["g1", "g2"]);
+        assert_eq!(search("PAY timed", 0), vec!["g1"]);
+        // Hot occurrences outside the range don't match.
+        assert!(search("failed", 7_000).is_empty());
+        assert_eq!(
+            search_exception_groups(&store, &edits, " - ", 0, 10_000).unwrap(),
+            None
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
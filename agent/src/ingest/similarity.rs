@@ -0,0 +1,268 @@
+//! Similar-issue suggestions: exception groups that fingerprinting kept apart
+//! but that look like the same failure, such as a crash whose top frame moved
+//! between two builds.
+//!
+//! Fingerprints are exact hashes, so they can't say how *close* two groups are.
+//! Instead each group is reduced to a set of features: its normalized type,
+//! word pairs of its normalized message, and its top normalized frames (each
+//! alone and as adjacent pairs, so frame order counts). Groups are then
+//! compared by the Jaccard similarity of those sets, estimated from
+//! [`HASHES`]-value MinHash signatures.
+//!
+//! The store keeps each group's signature, taken from its latest occurrence as
+//! the ingest writer stores it and rebuilt with the search index when
+//! occurrences move between groups, so a group and its candidates are always
+//! compared on the same kind of occurrence and a lookup reads only the index.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use analytics_api::{SimilarGroup, summary_line};
+
+use super::{GroupEdits, normalize};
+use crate::errors::Result;
+use crate::store::{EventKind, GroupSignature, Store, StoredEvent, StoredException};
+
+/// The number of hash functions in a signature; the estimate's standard
+/// error is about `1 / sqrt(HASHES)`.
+const HASHES: usize = 64;
+/// The stack frames considered, from the top. More than the fingerprint's, so
+/// groups that differ only in their top frames still overlap.
+const TOP_FRAMES: usize = 12;
+/// The lowest score suggested.
+const MIN_SCORE: f64 = 0.5;
+/// The most groups returned by [`similar_groups`], for the caller to narrow
+/// to those active over the range it shows.
+const MAX_CANDIDATES: usize = 25;
+
+/// The MinHash signature of a group's features.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Signature([u64; HASHES]);
+
+impl Signature {
+    /// The signature of a group's latest occurrence; `None` when it has
+    /// nothing to compare on.
+    fn of(exc_type: &str, message: &str, stack: Option<&str>) -> Option<Self> {
+        let features = features(exc_type, message, stack);
+        if features.is_empty() {
+            return None;
+        }
+        let mut minimums = [u64::MAX; HASHES];
+        for feature in &features {
+            let base = fnv1a(feature.as_bytes());
+            for (seed, minimum) in minimums.iter_mut().enumerate() {
+                let hash = mix(base ^ (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+                *minimum = (*minimum).min(hash);
+            }
+        }
+        Some(Self(minimums))
+    }
+}
+
+/// The estimated Jaccard similarity of two stored signatures, in `[0, 1]`.
+/// Signatures of another length (from before [`HASHES`] changed) never match.
+fn similarity(a: &[u64], b: &[u64]) -> f64 {
+    if a.len() != HASHES || b.len() != HASHES {
+        return 0.0;
+    }
+    let equal = a.iter().zip(b).filter(|(a, b)| a == b).count();
+    equal as f64 / HASHES as f64
+}
+
+/// The index entry for an occurrence; `None` when it has nothing to compare
+/// on.
+fn index_entry(exc_type: &str, message: &str, stack: Option<&str>) -> Option<GroupSignature> {
+    let signature = Signature::of(exc_type, message, stack)?;
+    Some(GroupSignature {
+        exc_type: exc_type.to_string(),
+        message: summary_line(message).to_string(),
+        minhash: signature.0.to_vec(),
+    })
+}
+
+/// Record the signatures of the exception groups among `events` as they are
+/// stored, each from the group's latest occurrence.
+pub(super) fn index_signatures(store: &Store, events: &[StoredEvent]) -> Result<()> {
+    let mut signatures = BTreeMap::new();
+    for event in events.iter().filter(|e| e.kind == EventKind::Exception) {
+        let Some(group) = &event.exc_group else {
+            continue;
+        };
+        let entry = index_entry(
+            event.exc_type.as_deref().unwrap_or(""),
+            event.exc_message.as_deref().unwrap_or(""),
+            event.exc_stack.as_deref(),
+        );
+        if let Some(entry) = entry {
+            signatures.insert((event.source.clone(), group.clone()), entry);
+        }
+    }
+    store.put_group_signatures(&signatures)
+}
+
+/// Rebuild the signature index from the stored occurrences, after they moved
+/// between groups or sources. Returns the number of groups indexed.
+pub(super) fn rebuild_signatures(store: &Store, parquet_dir: &str) -> Result<usize> {
+    let sign = |exception: &StoredException<'_>| {
+        index_entry(
+            exception.exc_type,
+            exception.message.unwrap_or_default(),
+            exception.stack,
+        )
+    };
+    store.rebuild_group_signatures(parquet_dir, &sign)
+}
+
+/// The groups of `source` that look like `group_id`, best first, from the
+/// signature index. Neither the group itself nor a group merged into another
+/// is suggested. Only the score, type and message are filled in; how often
+/// and when each occurred is up to the caller.
+pub fn similar_groups(
+    store: &Store,
+    edits: &GroupEdits,
+    source: &str,
+    group_id: &str,
+) -> Result<Vec<SimilarGroup>> {
+    let Some(target) = store.group_signature(source, group_id)? else {
+        return Ok(Vec::new());
+    };
+    let mut similar: Vec<SimilarGroup> = store
+        .group_signatures(source)?
+        .into_iter()
+        .filter(|(group, _)| group != group_id && edits.redirect(source, group).is_none())
+        .filter_map(|(group, signature)| {
+            let score = similarity(&target.minhash, &signature.minhash);
+            (score >= MIN_SCORE).then(|| SimilarGroup {
+                group_id: group,
+                exc_type: signature.exc_type,
+                sample_message: signature.message,
+                count: 0,
+                last_seen_ms: 0,
+                score,
+            })
+        })
+        .collect();
+    similar.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.group_id.cmp(&b.group_id))
+    });
+    similar.truncate(MAX_CANDIDATES);
+    Ok(similar)
+}
+
+fn features(exc_type: &str, message: &str, stack: Option<&str>) -> BTreeSet<String> {
+    let mut features = BTreeSet::new();
+    let exc_type = exc_type.trim().to_lowercase();
+    if !exc_type.is_empty() {
+        features.insert(format!("t:{exc_type}"));
+    }
+
+    let message = normalize::message(summary_line(message));
+    let words: Vec<&str> = message.split_whitespace().collect();
+    match words.as_slice() {
+        [] => {}
+        [word] => {
+            features.insert(format!("m:{word}"));
+        }
+        words => features.extend(words.windows(2).map(|w| format!("m:{} {}", w[0], w[1]))),
+    }
+
+    let frames: Vec<String> = stack
+        .map(normalize::frames)
+        .unwrap_or_default()
+        .into_iter()
+        .take(TOP_FRAMES)
+        .collect();
+    features.extend(frames.iter().map(|frame| format!("f:{frame}")));
+    features.extend(frames.windows(2).map(|w| format!("p:{}\n{}", w[0], w[1])));
+    features
+}
+
+/// 64-bit FNV-1a: stable across builds, unlike the standard library's hasher.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The SplitMix64 finalizer, deriving each hash function from the base hash.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn exception(group: &str, message: &str, stack: &str) -> StoredEvent {
+        StoredEvent {
+            kind: EventKind::Exception,
+            source: "https://example.com".into(),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some(message.into()),
+            exc_stack: Some(stack.into()),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn near_identical_groups_rank_above_unrelated_ones() {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "analytics-similarity-{}-{}.redb",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let store = Store::open(&path).unwrap();
+
+        let frames = "at render (app.js:10:5)\nat mount (app.js:40:2)\nat commit (react.js:9:1)\nat flush (react.js:30:7)\nat run (scheduler.js:2:2)";
+        let message = "Cannot read properties of undefined (reading 'id')";
+        let moved = format!("at renderProfile (app.js:12:5)\n{frames}");
+        index_signatures(
+            &store,
+            &[
+                // The target's latest occurrence is the one it is compared on.
+                exception("self", "Failed to fetch", "at fetchJson (api.js:31:11)"),
+                exception("self", message, frames),
+                exception("moved", message, &moved),
+                exception(
+                    "other",
+                    "Failed to fetch",
+                    "at fetchJson (api.js:31:11)\nat load (dashboard.js:88:22)",
+                ),
+            ],
+        )
+        .unwrap();
+
+        let similar = similar_groups(
+            &store,
+            &GroupEdits::default(),
+            "https://example.com",
+            "self",
+        )
+        .unwrap();
+        // Only the group whose top frame moved is suggested, never the group itself.
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].group_id, "moved");
+        assert_eq!(similar[0].sample_message, message);
+        assert!(similar[0].score >= MIN_SCORE && similar[0].score < 1.0);
+        assert_eq!(Signature::of("", "", None), None);
+        assert!(
+            similar_groups(
+                &store,
+                &GroupEdits::default(),
+                "https://example.com",
+                "unknown"
+            )
+            .unwrap()
+            .is_empty()
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,207 @@
+//! Lift exception group snoozes once their condition is met. A snoozed group
+//! is muted until a date passes, or until it occurs so many more times or in
+//! so many more sessions; every snoozed group is checked at once, in the
+//! background, so listing or opening a group never writes to it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use analytics_api::ExceptionSnooze;
+use chrono::{DateTime, Utc};
+use tokio::time::MissedTickBehavior;
+use tracing_batteries::prelude::*;
+
+use crate::analytics;
+use crate::config::StorageConfig;
+use crate::errors::Result;
+use crate::store::{Store, unscoped_group};
+
+/// How often the snoozed groups are checked, and so how long a lapsed snooze
+/// can still mute its group.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Periodically [lift](lift_lapsed_snoozes) the snoozes that have lapsed.
+pub fn spawn_lift_snoozes(store: Arc<Store>, storage: StorageConfig) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(CHECK_INTERVAL);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tick.tick().await;
+            let store = store.clone();
+            let parquet_dir = storage.parquet_dir.clone();
+            let lifted = tokio::task::spawn_blocking(move || {
+                lift_lapsed_snoozes(&store, &parquet_dir, Utc::now())
+            })
+            .await;
+            match lifted {
+                Ok(Ok(0)) => {}
+                Ok(Ok(n)) => info!("lifted {n} lapsed exception group snoozes"),
+                Ok(Err(err)) => error!("checking exception group snoozes failed: {err}"),
+                Err(err) => error!("exception snooze check task panicked: {err}"),
+            }
+        }
+    });
+}
+
+/// Unmute, for good, every snoozed group whose snooze has lapsed as of `now`.
+/// Counting conditions are checked against each group's occurrences since it
+/// was snoozed, every group's in one query. Returns how many were lifted.
+fn lift_lapsed_snoozes(store: &Store, parquet_dir: &str, now: DateTime<Utc>) -> Result<usize> {
+    let snoozed = store.snoozed_triages()?;
+
+    // A group triaged under two projects (its source moved) counts from the
+    // later snooze.
+    let mut since: HashMap<(String, String), i64> = HashMap::new();
+    for (_, scoped, triage) in &snoozed {
+        let (Some(snoozed_at), Some(snooze)) = (triage.muted_at, triage.snooze) else {
+            continue;
+        };
+        if matches!(snooze, ExceptionSnooze::Date { .. }) {
+            continue;
+        }
+        if let Some((group, source)) = unscoped_group(scoped) {
+            let at = since
+                .entry((source.to_string(), group.to_string()))
+                .or_insert(i64::MIN);
+            *at = (*at).max(snoozed_at.timestamp_millis());
+        }
+    }
+    let activity = analytics::exceptions::exception_activity_since(
+        store,
+        parquet_dir,
+        &since,
+        now.timestamp_millis() + 1,
+    )?;
+
+    let mut lifted = 0;
+    for (project_id, scoped, triage) in snoozed {
+        let Some(snoozed_at) = triage.muted_at else {
+            continue;
+        };
+        let (occurrences, sessions) = unscoped_group(&scoped)
+            .and_then(|(group, source)| activity.get(&(source.to_string(), group.to_string())))
+            .copied()
+            .unwrap_or((0, 0));
+        if triage.snooze_lapsed(now, occurrences, sessions)
+            && lift_snooze(store, &project_id, &scoped, snoozed_at)?
+        {
+            lifted += 1;
+        }
+    }
+    Ok(lifted)
+}
+
+/// Unmute the group snoozed at `snoozed_at`, unless it was re-snoozed (or
+/// otherwise unmuted or muted) meanwhile. Returns whether it was lifted.
+fn lift_snooze(
+    store: &Store,
+    project_id: &str,
+    scoped: &str,
+    snoozed_at: DateTime<Utc>,
+) -> Result<bool> {
+    store.amend_triage(project_id, scoped, |triage| {
+        if triage.muted_at != Some(snoozed_at) || triage.snooze.is_none() {
+            return false;
+        }
+        triage.muted_at = None;
+        triage.snooze = None;
+        true
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use chrono::TimeZone;
+
+    use crate::store::{EventKind, ExceptionTriage, StoredEvent, scoped_group};
+
+    fn occurrence(group: &str, received_ms: i64, sid: &str) -> StoredEvent {
+        StoredEvent {
+            received_ms,
+            created_ms: received_ms,
+            kind: EventKind::Exception,
+            source: "https://a.com".into(),
+            sid: Some(sid.into()),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some("boom".into()),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lapsed_snoozes_are_lifted_in_one_pass() {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "analytics-snooze-{}-{}.redb",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let store = Store::open(&path).unwrap();
+        let snoozed_at = Utc.timestamp_millis_opt(1_000).unwrap();
+        let snooze = |group: &str, snooze: ExceptionSnooze| {
+            store
+                .update_triage("p1", &scoped_group(group, "https://a.com"), |triage| {
+                    triage.muted_at = Some(snoozed_at);
+                    triage.snooze = Some(snooze);
+                })
+                .unwrap();
+        };
+        snooze("dated", ExceptionSnooze::Date { at_ms: 5_000 });
+        snooze("recurring", ExceptionSnooze::Occurrences { count: 2 });
+        snooze("quiet", ExceptionSnooze::Sessions { count: 2 });
+        store
+            .append_events(&[
+                // Before the snooze, so not counted.
+                occurrence("recurring", 500, "s1"),
+                occurrence("recurring", 2_000, "s1"),
+                occurrence("recurring", 3_000, "s1"),
+                occurrence("quiet", 2_000, "s1"),
+                occurrence("quiet", 3_000, "s1"),
+            ])
+            .unwrap();
+        let muted = |group: &str| {
+            store
+                .get_triage("p1", &scoped_group(group, "https://a.com"))
+                .unwrap()
+                .is_some_and(|triage: ExceptionTriage| triage.is_muted())
+        };
+
+        let now = Utc.timestamp_millis_opt(4_000).unwrap();
+        assert_eq!(lift_lapsed_snoozes(&store, "/none", now).unwrap(), 1);
+        assert!(muted("dated"));
+        assert!(!muted("recurring"));
+        assert!(muted("quiet"));
+
+        let later = Utc.timestamp_millis_opt(6_000).unwrap();
+        assert_eq!(lift_lapsed_snoozes(&store, "/none", later).unwrap(), 1);
+        assert!(!muted("dated"));
+        // Two occurrences, but in one session.
+        assert!(muted("quiet"));
+
+        // A group re-snoozed after the pass read it isn't lifted, nor counted.
+        let resnoozed_at = Utc.timestamp_millis_opt(7_000).unwrap();
+        snooze("later", ExceptionSnooze::Date { at_ms: 8_000 });
+        store
+            .update_triage("p1", &scoped_group("later", "https://a.com"), |triage| {
+                triage.muted_at = Some(resnoozed_at);
+            })
+            .unwrap();
+        let scoped = scoped_group("later", "https://a.com");
+        assert!(!lift_snooze(&store, "p1", &scoped, snoozed_at).unwrap());
+        assert!(muted("later"));
+        assert!(lift_snooze(&store, "p1", &scoped, resnoozed_at).unwrap());
+        assert!(!muted("later"));
+        // Nor is one whose record is gone.
+        let gone = scoped_group("gone", "https://a.com");
+        assert!(!lift_snooze(&store, "p1", &gone, snoozed_at).unwrap());
+        assert!(store.get_triage("p1", &gone).unwrap().is_none());
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
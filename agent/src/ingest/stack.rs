@@ -0,0 +1,350 @@
+//! Parse the stack traces clients report into structured frames.
+//!
+//! Stacks are stored as reported; this recognises the frame syntax of the
+//! runtimes our clients run on — Chrome/V8 (and Node), Firefox and Safari,
+//! Rust panic backtraces, Python tracebacks and JVM stacks — and yields one
+//! [`StackFrame`] per frame, innermost first for every runtime except Python
+//! (whose tracebacks list the innermost call last). Lines that aren't frames
+//! (the message, `caused by:` chains, a traceback's source lines) are skipped.
+//!
+//! Each frame is flagged `in_app` unless it points into a runtime, the standard
+//! library or a third-party dependency, which lets grouping prefer the
+//! application's own frames and the UI collapse the rest.
+
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use analytics_api::StackFrame;
+use regex::Regex;
+
+/// JVM: `at com.example.Cart.total(Cart.java:42)`, optionally with a Java
+/// module prefix (`java.base/java.util.ArrayList.forEach(...)`).
+static JVM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^at (?:[\w$.@]+/)?([\w$]+(?:\.[\w$]+)*)\.([\w$<>-]+)\(([^)]*)\)$")
+        .expect("valid JVM frame")
+});
+
+/// V8: `at total (https://cdn/app.js:1:2)`, `at https://cdn/app.js:1:2`, or
+/// `at total (native)`.
+static V8: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^at (?:(.+?) \((.+?)\)|(.+?))$").expect("valid V8 frame"));
+
+/// Firefox and Safari: `total@https://cdn/app.js:1:2` (the name may be empty).
+static GECKO: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([^@]*)@(.+)$").expect("valid Gecko frame"));
+
+/// Rust backtraces: `12: app::cart::total`, with the location on a following
+/// `at ./src/cart.rs:42:9` line.
+static RUST: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+): (.+?)(?:::h[0-9a-f]{16})?$").expect("valid Rust frame"));
+
+/// Python: `File "app/cart.py", line 42, in total`.
+static PYTHON: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^File "([^"]+)", line (\d+)(?:, in (.+))?$"#).expect("valid Python frame")
+});
+
+/// `file:line[:column]`, anchored at the end of a location.
+static LOCATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?):(\d+)(?::(\d+))?$").expect("valid location"));
+
+/// Path fragments of files that belong to a runtime or a dependency.
+const LIBRARY_PATHS: &[&str] = &[
+    "node_modules/",
+    "chrome-extension://",
+    "moz-extension://",
+    "safari-extension://",
+    "site-packages/",
+    "dist-packages/",
+    "/lib/python",
+    "/.cargo/registry/",
+    "/rustc/",
+];
+
+/// How runtime-internal locations start (Node internals, frozen Python
+/// modules) and the placeholders engines report for native code.
+const RUNTIME_PREFIXES: &[&str] = &["node:", "internal/", "<frozen "];
+const RUNTIME_LOCATIONS: &[&str] = &["native", "<anonymous>", "[native code]"];
+
+/// Module prefixes of the Rust and JVM standard libraries and their runtimes.
+const LIBRARY_MODULES: &[&str] = &[
+    "std::",
+    "core::",
+    "alloc::",
+    "tokio::",
+    "futures::",
+    "backtrace::",
+    "java.",
+    "javax.",
+    "jdk.",
+    "sun.",
+    "com.sun.",
+    "kotlin.",
+    "kotlinx.",
+    "scala.",
+];
+
+/// A parsed frame and the stack lines it spans.
+pub(super) struct ParsedFrame {
+    pub frame: StackFrame,
+    pub lines: Range<usize>,
+}
+
+/// The structured frames of a stack trace.
+pub fn parse_stack(stack: &str) -> Vec<StackFrame> {
+    parse(stack).into_iter().map(|p| p.frame).collect()
+}
+
+pub(super) fn parse(stack: &str) -> Vec<ParsedFrame> {
+    let raw: Vec<&str> = stack.lines().collect();
+    let lines: Vec<&str> = raw.iter().map(|line| line.trim()).collect();
+    let mut frames: Vec<ParsedFrame> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let start = i;
+        i += 1;
+
+        let frame = if let Some(caps) = JVM.captures(line) {
+            let (file, line_no) = match caps[3].split_once(':') {
+                Some((file, n)) => (Some(file.to_string()), n.parse().ok()),
+                None => (None, None),
+            };
+            Some(StackFrame {
+                function: Some(caps[2].to_string()),
+                module: Some(caps[1].to_string()),
+                file,
+                line: line_no,
+                ..Default::default()
+            })
+        } else if let Some(caps) = RUST.captures(line) {
+            let path = &caps[2];
+            // The location, when there is one, is on the next line.
+            let location = lines
+                .get(i)
+                .and_then(|next| next.strip_prefix("at "))
+                .filter(|loc| !loc.contains('(') && LOCATION.is_match(loc));
+            if location.is_some() {
+                i += 1;
+            }
+            let (module, function) = match rust_path(path) {
+                Some((module, function)) => (Some(module.to_string()), function),
+                None => (None, path),
+            };
+            Some(with_location(
+                StackFrame {
+                    function: Some(function.to_string()),
+                    module,
+                    ..Default::default()
+                },
+                location,
+            ))
+        } else if let Some(caps) = PYTHON.captures(line) {
+            // The frame's source line follows it, indented further.
+            if raw
+                .get(i)
+                .is_some_and(|next| indent(next) > indent(raw[start]) && !lines[i].is_empty())
+            {
+                i += 1;
+            }
+            Some(StackFrame {
+                function: caps.get(3).map(|f| f.as_str().to_string()),
+                file: Some(caps[1].to_string()),
+                line: caps[2].parse().ok(),
+                ..Default::default()
+            })
+        } else if let Some(caps) = V8.captures(line) {
+            let (function, location) = match (caps.get(1), caps.get(2)) {
+                (Some(function), Some(location)) => (Some(function.as_str()), location.as_str()),
+                _ => (None, &caps[3]),
+            };
+            // `at bender::store::read (...)`: a Rust-style path in V8 syntax.
+            let (module, function) = match function.and_then(rust_path) {
+                Some((module, function)) => (Some(module.to_string()), Some(function)),
+                None => (None, function),
+            };
+            Some(with_location(
+                StackFrame {
+                    function: function.map(str::to_string),
+                    module,
+                    ..Default::default()
+                },
+                Some(location),
+            ))
+        } else if let Some(caps) = GECKO.captures(line)
+            && (LOCATION.is_match(&caps[2]) || &caps[2] == "[native code]")
+        {
+            Some(with_location(
+                StackFrame {
+                    function: Some(caps[1].to_string()).filter(|f| !f.is_empty()),
+                    ..Default::default()
+                },
+                Some(&caps[2]),
+            ))
+        } else {
+            None
+        };
+
+        if let Some(mut frame) = frame {
+            frame.in_app = is_in_app(&frame);
+            frames.push(ParsedFrame {
+                frame,
+                lines: start..i,
+            });
+        }
+    }
+    frames
+}
+
+/// Split `app::cart::total` into its module and function. Paths without a
+/// module, and qualified paths (`<T as Trait>::f`), stay whole.
+fn rust_path(path: &str) -> Option<(&str, &str)> {
+    if path.starts_with('<') {
+        return None;
+    }
+    let (module, function) = path.rsplit_once("::")?;
+    (!module.is_empty() && !module.contains(' ')).then_some((module, function))
+}
+
+fn with_location(mut frame: StackFrame, location: Option<&str>) -> StackFrame {
+    let Some(location) = location.map(str::trim).filter(|l| !l.is_empty()) else {
+        return frame;
+    };
+    match LOCATION.captures(location) {
+        Some(caps) => {
+            frame.file = Some(caps[1].to_string());
+            frame.line = caps[2].parse().ok();
+            frame.column = caps.get(3).and_then(|c| c.as_str().parse().ok());
+        }
+        None => frame.file = Some(location.to_string()),
+    }
+    frame
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_in_app(frame: &StackFrame) -> bool {
+    let in_library_path = frame.file.as_deref().is_some_and(|file| {
+        LIBRARY_PATHS.iter().any(|lib| file.contains(lib))
+            || RUNTIME_PREFIXES
+                .iter()
+                .any(|prefix| file.starts_with(prefix))
+            || RUNTIME_LOCATIONS.contains(&file)
+    });
+    let in_library_module = frame.module.as_deref().is_some_and(|module| {
+        let module = format!("{module}{}", if module.contains("::") { "::" } else { "." });
+        LIBRARY_MODULES.iter().any(|lib| module.starts_with(lib))
+    });
+    let runtime_function = frame
+        .function
+        .as_deref()
+        .is_some_and(|f| f.starts_with("__rust") || f == "rust_begin_unwind");
+    !(in_library_path || in_library_module || runtime_function)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(function: &str, module: Option<&str>, file: &str, line: u32) -> StackFrame {
+        StackFrame {
+            function: Some(function.into()),
+            module: module.map(str::to_string),
+            file: Some(file.into()),
+            line: Some(line),
+            column: None,
+            in_app: true,
+        }
+    }
+
+    #[test]
+    fn parses_browser_stacks() {
+        let chrome = parse_stack(
+            "TypeError: x is undefined\n    \
+             at total (https://cdn.example.com/app.js:10:5)\n    \
+             at https://cdn.example.com/app.js:20:7\n    \
+             at render (https://cdn.example.com/node_modules/react/index.js:1:2)",
+        );
+        assert_eq!(chrome.len(), 3);
+        assert_eq!(
+            chrome[0],
+            StackFrame {
+                column: Some(5),
+                ..frame("total", None, "https://cdn.example.com/app.js", 10)
+            }
+        );
+        assert_eq!(chrome[1].function, None);
+        assert_eq!(chrome[1].line, Some(20));
+        assert!(!chrome[2].in_app);
+
+        let firefox =
+            parse_stack("total@https://cdn.example.com/app.js:10:5\n@debugger eval code:1:1");
+        assert_eq!(firefox.len(), 2);
+        assert_eq!(firefox[0].function.as_deref(), Some("total"));
+        assert_eq!(firefox[0].column, Some(5));
+        assert_eq!(firefox[1].function, None);
+        assert_eq!(firefox[1].file.as_deref(), Some("debugger eval code"));
+    }
+
+    #[test]
+    fn parses_rust_backtraces() {
+        let frames = parse_stack(
+            "caused by: connection reset\n\n   \
+             0: std::panicking::begin_panic\n             \
+             at /rustc/abc/library/std/src/panicking.rs:616:12\n   \
+             1: shop::cart::total::h0123456789abcdef\n             \
+             at ./src/cart.rs:42:9\n   \
+             2: <shop::Api as shop::Handler>::on_error",
+        );
+        assert_eq!(frames.len(), 3);
+        assert!(!frames[0].in_app);
+        assert_eq!(
+            frames[1],
+            StackFrame {
+                column: Some(9),
+                ..frame("total", Some("shop::cart"), "./src/cart.rs", 42)
+            }
+        );
+        assert_eq!(
+            frames[2].function.as_deref(),
+            Some("<shop::Api as shop::Handler>::on_error")
+        );
+        assert!(frames[2].in_app);
+    }
+
+    #[test]
+    fn parses_python_tracebacks() {
+        let frames = parse_stack(
+            "Traceback (most recent call last):\n  \
+             File \"/srv/app/cart.py\", line 42, in total\n    \
+             return sum(items)\n  \
+             File \"/usr/lib/python3.12/site-packages/requests/api.py\", line 7, in get\n    \
+             raise ValueError()\n\
+             ValueError: boom",
+        );
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], frame("total", None, "/srv/app/cart.py", 42));
+        assert!(!frames[1].in_app);
+    }
+
+    #[test]
+    fn parses_jvm_stacks() {
+        let frames = parse_stack(
+            "java.lang.IllegalStateException: boom\n\t\
+             at com.example.shop.Cart.total(Cart.java:42)\n\t\
+             at java.base/java.util.ArrayList.forEach(Native Method)\n\t\
+             at java.util.ArrayList.forEach(ArrayList.java:1596)\n\t\
+             ... 12 more",
+        );
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames[0],
+            frame("total", Some("com.example.shop.Cart"), "Cart.java", 42)
+        );
+        assert_eq!(frames[1].module.as_deref(), Some("java.util.ArrayList"));
+        assert_eq!(frames[1].file, None);
+        assert!(!frames[1].in_app);
+        assert!(!frames[2].in_app);
+    }
+}
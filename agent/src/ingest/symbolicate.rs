@@ -0,0 +1,501 @@
+//! Source-map symbolication of JavaScript stack traces.
+//!
+//! Administrators upload the source maps of each release of a source, keyed by
+//! the reported `app_version` and the file name of the minified bundle. Frames
+//! pointing into an uploaded bundle (`https://cdn/main.3f9a.js:1:48213`) are
+//! rewritten to the original file, line and function. Exceptions are
+//! symbolicated before they are fingerprinted, so grouping keys off the original
+//! function names, and again when a group is served, so occurrences recorded
+//! before their maps were uploaded resolve too. Frames without a map, or whose
+//! position the map doesn't cover, are left exactly as reported. Uploading a
+//! map doesn't re-group those earlier occurrences, though: they stay in the
+//! groups their minified frames were fingerprinted into, so maps should be
+//! uploaded as part of a release's build, before it reports.
+//!
+//! Maps live on disk under `storage.sourcemap_dir`, next to the data, as
+//! `<source hash>/<app version>/<file>.map`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+
+use analytics_api::SourceMapInfo;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use sourcemap::DecodedMap;
+
+use crate::errors::{Result, ResultExt};
+
+const ADVICE: &[&str] = &[
+    "Ensure `storage.sourcemap_dir` exists and is writable by the analytics server.",
+    "Retry the operation, and if it persists report it with the server logs.",
+];
+
+/// Longest app version or file name a map may be stored under.
+const MAX_SEGMENT: usize = 128;
+
+/// How much source map, by size on disk, is kept parsed in memory. Maps for hot
+/// bundles are parsed once; the least recently used make way for others and
+/// are re-read on demand.
+const MAX_CACHED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// The last `file:line:column` location on a frame line.
+static LOCATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([^\s()@]+):(\d+):(\d+)").expect("valid location pattern"));
+
+/// The uploaded source maps, with a cache of the parsed ones.
+#[derive(Default)]
+pub struct SourceMaps {
+    root: Option<PathBuf>,
+    cache: Mutex<MapCache>,
+}
+
+impl SourceMaps {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(dir.into()),
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Check an app version or file name before it becomes part of a path.
+    /// `Err` carries a message suitable for a 400 response.
+    pub fn check_segment(kind: &str, value: &str) -> std::result::Result<(), String> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_SEGMENT
+            && !value.starts_with('.')
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+' | '~'));
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{value}` is not a valid {kind}: use letters, digits and `.-_+~` only."
+            ))
+        }
+    }
+
+    /// Store a (validated) source map, replacing any previous upload for the
+    /// same bundle.
+    pub fn put(
+        &self,
+        source: &str,
+        app_version: &str,
+        file: &str,
+        map: &[u8],
+    ) -> Result<SourceMapInfo> {
+        let path = self.path(source, app_version, file)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).or_system_err(ADVICE)?;
+        }
+        let tmp = path.with_extension("map.tmp");
+        std::fs::write(&tmp, map).or_system_err(ADVICE)?;
+        std::fs::rename(&tmp, &path).or_system_err(ADVICE)?;
+        self.forget(&path);
+        Ok(SourceMapInfo {
+            source: source.to_string(),
+            app_version: app_version.to_string(),
+            file: file.to_string(),
+            size_bytes: map.len() as u64,
+            uploaded_ms: Utc::now().timestamp_millis(),
+        })
+    }
+
+    /// Every map uploaded for a source, newest first.
+    pub fn list(&self, source: &str) -> Result<Vec<SourceMapInfo>> {
+        let Some(dir) = self.source_dir(source) else {
+            return Ok(Vec::new());
+        };
+        let mut out = Vec::new();
+        for version in read_dir(&dir)? {
+            for map in read_dir(&version)? {
+                let (Some(app_version), Some(file)) = (
+                    version.file_name().and_then(|n| n.to_str()),
+                    map.file_name()
+                        .and_then(|n| n.to_str())
+                        .and_then(|n| n.strip_suffix(".map")),
+                ) else {
+                    continue;
+                };
+                let meta = std::fs::metadata(&map).or_system_err(ADVICE)?;
+                out.push(SourceMapInfo {
+                    source: source.to_string(),
+                    app_version: app_version.to_string(),
+                    file: file.to_string(),
+                    size_bytes: meta.len(),
+                    uploaded_ms: meta
+                        .modified()
+                        .map(|t| DateTime::<Utc>::from(t).timestamp_millis())
+                        .unwrap_or_default(),
+                });
+            }
+        }
+        out.sort_by(|a, b| {
+            b.uploaded_ms
+                .cmp(&a.uploaded_ms)
+                .then_with(|| a.file.cmp(&b.file))
+        });
+        Ok(out)
+    }
+
+    /// Remove an uploaded map. Returns whether it existed.
+    pub fn delete(&self, source: &str, app_version: &str, file: &str) -> Result<bool> {
+        let path = self.path(source, app_version, file)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        std::fs::remove_file(&path).or_system_err(ADVICE)?;
+        if let Some(parent) = path.parent() {
+            // Tidy the version directory once its last map is gone.
+            let _ = std::fs::remove_dir(parent);
+        }
+        self.forget(&path);
+        Ok(true)
+    }
+
+    /// Rewrite every frame of `stack` that an uploaded map resolves. Returns
+    /// `None` when nothing resolved (no maps for this release, or none of the
+    /// frames point into a mapped bundle), so callers keep the stack as is.
+    pub fn symbolicate(
+        &self,
+        source: &str,
+        app_version: Option<&str>,
+        stack: &str,
+    ) -> Option<String> {
+        let app_version = app_version.filter(|v| Self::check_segment("app version", v).is_ok())?;
+        let dir = self.source_dir(source)?.join(app_version);
+        if !dir.is_dir() {
+            return None;
+        }
+
+        let lines: Vec<&str> = stack.lines().collect();
+        let frames: Vec<Option<Frame>> =
+            lines.iter().map(|line| self.resolve(&dir, line)).collect();
+        if frames.iter().all(Option::is_none) {
+            return None;
+        }
+
+        // A map names the identifier at each position, so the original name of
+        // a function is found at its caller's call site: the next frame down.
+        // The minified name stands in when that frame didn't resolve.
+        let mut out = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            let Some(frame) = &frames[i] else {
+                out.push(line.to_string());
+                continue;
+            };
+            let function = frames
+                .get(i + 1)
+                .and_then(Option::as_ref)
+                .and_then(|c| c.name_at_site.as_deref())
+                .or(frame.minified_name.as_deref());
+            out.push(match function {
+                Some(function) => format!(
+                    "{}at {function} ({}:{}:{})",
+                    frame.indent, frame.file, frame.line, frame.column
+                ),
+                None => format!(
+                    "{}at {}:{}:{}",
+                    frame.indent, frame.file, frame.line, frame.column
+                ),
+            });
+        }
+        Some(out.join("\n"))
+    }
+
+    /// Resolve one frame line against the maps in a release directory.
+    fn resolve(&self, dir: &Path, line: &str) -> Option<Frame> {
+        let location = LOCATION.captures_iter(line).last()?;
+        let whole = location.get(0)?;
+        let url = location.get(1)?.as_str();
+        let file = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .filter(|name| Self::check_segment("file", name).is_ok())?;
+        let row: u32 = location.get(2)?.as_str().parse().ok()?;
+        let column: u32 = location.get(3)?.as_str().parse().ok()?;
+
+        let map = self.load(&dir.join(format!("{file}.map")))?;
+        // Stack positions are 1-based; source maps are 0-based.
+        let token = map.lookup_token(row.checked_sub(1)?, column.saturating_sub(1))?;
+        let original = token.get_source()?;
+
+        let trimmed = line.trim_start();
+        let indent = line[..line.len() - trimmed.len()].to_string();
+        let prefix = trimmed[..trimmed.len() - line[whole.start()..].len()].trim();
+        Some(Frame {
+            indent,
+            minified_name: minified_name(prefix),
+            name_at_site: token.get_name().map(str::to_string),
+            file: display_source(original),
+            line: token.get_src_line() + 1,
+            column: token.get_src_col() + 1,
+        })
+    }
+
+    fn load(&self, path: &Path) -> Option<Arc<DecodedMap>> {
+        if let Some(map) = self.cache().get(path) {
+            return Some(map);
+        }
+        // Misses aren't remembered: the file name comes from the reported
+        // stack, so caching them would let clients flush the real maps out.
+        let bytes = std::fs::read(path).ok()?;
+        let map = Arc::new(sourcemap::decode_slice(&bytes).ok()?);
+        self.cache()
+            .insert(path.to_path_buf(), map.clone(), bytes.len() as u64);
+        Some(map)
+    }
+
+    fn forget(&self, path: &Path) {
+        self.cache().remove(path);
+    }
+
+    fn cache(&self) -> MutexGuard<'_, MapCache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A source's map directory. Source URIs are hashed so they never need
+    /// escaping as path segments.
+    fn source_dir(&self, source: &str) -> Option<PathBuf> {
+        let digest = Sha256::digest(source.as_bytes());
+        let name: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+        Some(self.root.as_ref()?.join(name))
+    }
+
+    fn path(&self, source: &str, app_version: &str, file: &str) -> Result<PathBuf> {
+        let dir = self.source_dir(source).ok_or_else(|| {
+            human_errors::system(
+                "Source map storage is not configured.",
+                &["This is a bug; please report it with the server version."],
+            )
+        })?;
+        Ok(dir.join(app_version).join(format!("{file}.map")))
+    }
+}
+
+/// Parsed maps by path, evicted least recently used first once their total
+/// size passes [`MAX_CACHED_BYTES`].
+#[derive(Default)]
+struct MapCache {
+    entries: HashMap<PathBuf, CachedMap>,
+    bytes: u64,
+    /// Bumped on every access; an entry's `used` is the tick it was last read.
+    clock: u64,
+}
+
+struct CachedMap {
+    map: Arc<DecodedMap>,
+    bytes: u64,
+    used: u64,
+}
+
+impl MapCache {
+    fn get(&mut self, path: &Path) -> Option<Arc<DecodedMap>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        entry.used = self.clock;
+        Some(entry.map.clone())
+    }
+
+    /// Cache a map read from `bytes` bytes on disk. A map larger than the
+    /// whole budget is never cached.
+    fn insert(&mut self, path: PathBuf, map: Arc<DecodedMap>, bytes: u64) {
+        if bytes > MAX_CACHED_BYTES {
+            return;
+        }
+        self.remove(&path);
+        while self.bytes + bytes > MAX_CACHED_BYTES {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.clock += 1;
+        self.bytes += bytes;
+        self.entries.insert(
+            path,
+            CachedMap {
+                map,
+                bytes,
+                used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.bytes -= entry.bytes;
+        }
+    }
+}
+
+/// A resolved frame.
+struct Frame {
+    indent: String,
+    minified_name: Option<String>,
+    /// The original name of the identifier at this frame's position.
+    name_at_site: Option<String>,
+    file: String,
+    line: u32,
+    column: u32,
+}
+
+/// The function name of a frame from the text ahead of its location: Chrome's
+/// `at name (` or Firefox/Safari's `name@`.
+fn minified_name(prefix: &str) -> Option<String> {
+    let name = prefix
+        .strip_prefix("at ")
+        .unwrap_or(prefix)
+        .trim_end_matches(['(', '@'])
+        .trim();
+    (!name.is_empty() && name != "at").then(|| name.to_string())
+}
+
+/// An original source as shown on a frame: bundler URL schemes and `./`
+/// segments are dropped (`webpack://app/./src/cart.ts` → `app/src/cart.ts`).
+fn display_source(source: &str) -> String {
+    let path = source.split_once("://").map_or(source, |(_, rest)| rest);
+    path.replace("/./", "/")
+        .trim_start_matches("./")
+        .to_string()
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir).or_system_err(ADVICE)? {
+        entries.push(entry.or_system_err(ADVICE)?.path());
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use sourcemap::SourceMapBuilder;
+
+    use super::*;
+
+    const SOURCE: &str = "https://example.com";
+
+    /// `main.3f9a.js`, line 1: a call to `b` (cart.ts `total`) at column 10,
+    /// and a read of `x` (cart.ts `items`) inside it at column 20.
+    fn bundle_map() -> Vec<u8> {
+        let source = Some("webpack://shop/./src/cart.ts");
+        let mut builder = SourceMapBuilder::new(Some("main.3f9a.js"));
+        builder.add(0, 9, 11, 4, source, Some("total"), false);
+        builder.add(0, 19, 41, 8, source, Some("items"), false);
+        let mut out = Vec::new();
+        builder.into_sourcemap().to_writer(&mut out).unwrap();
+        out
+    }
+
+    fn maps(name: &str) -> (SourceMaps, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("analytics-{name}-{}", std::process::id()));
+        let maps = SourceMaps::new(&dir);
+        maps.put(SOURCE, "1.4.0", "main.3f9a.js", &bundle_map())
+            .unwrap();
+        (maps, dir)
+    }
+
+    #[test]
+    fn resolves_frames_to_original_names_and_positions() {
+        let (maps, dir) = maps("symbolicate");
+        let stack = "TypeError: x is undefined\n    \
+                     at b (https://cdn.example.com/main.3f9a.js:1:21)\n    \
+                     at a (https://cdn.example.com/main.3f9a.js?v=2:1:10)\n    \
+                     at https://cdn.example.com/vendor.js:3:7";
+        assert_eq!(
+            maps.symbolicate(SOURCE, Some("1.4.0"), stack).unwrap(),
+            "TypeError: x is undefined\n    \
+             at total (shop/src/cart.ts:42:9)\n    \
+             at a (shop/src/cart.ts:12:5)\n    \
+             at https://cdn.example.com/vendor.js:3:7"
+        );
+
+        // Firefox-style frames resolve the same way.
+        assert_eq!(
+            maps.symbolicate(
+                SOURCE,
+                Some("1.4.0"),
+                "b@https://cdn.example.com/main.3f9a.js:1:21"
+            )
+            .unwrap(),
+            "at b (shop/src/cart.ts:42:9)"
+        );
+
+        // Other releases, sources and bundles fall back to the reported stack.
+        let frame = "at a (https://cdn.example.com/main.3f9a.js:1:10)";
+        assert_eq!(maps.symbolicate(SOURCE, Some("1.3.0"), frame), None);
+        assert_eq!(maps.symbolicate(SOURCE, None, frame), None);
+        assert_eq!(
+            maps.symbolicate("https://other.org", Some("1.4.0"), frame),
+            None
+        );
+        assert_eq!(
+            maps.symbolicate(
+                SOURCE,
+                Some("1.4.0"),
+                "at a (https://cdn.example.com/app.js:1:10)"
+            ),
+            None
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn lists_and_deletes_uploads() {
+        let (maps, dir) = maps("sourcemaps");
+        let listed = maps.list(SOURCE).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].app_version, "1.4.0");
+        assert_eq!(listed[0].file, "main.3f9a.js");
+        assert!(maps.list("https://other.org").unwrap().is_empty());
+
+        assert!(maps.delete(SOURCE, "1.4.0", "main.3f9a.js").unwrap());
+        assert!(!maps.delete(SOURCE, "1.4.0", "main.3f9a.js").unwrap());
+        let frame = "at a (https://cdn.example.com/main.3f9a.js:1:10)";
+        assert_eq!(maps.symbolicate(SOURCE, Some("1.4.0"), frame), None);
+
+        assert!(SourceMaps::check_segment("file", "../secrets").is_err());
+        assert!(SourceMaps::check_segment("app version", "1.4.0+build.7").is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn caches_found_maps_least_recently_used_first() {
+        let (maps, dir) = maps("sourcemap-cache");
+        let frame = "at a (https://cdn.example.com/main.3f9a.js:1:10)";
+        let unknown = "at a (https://cdn.example.com/other.js:1:10)";
+        maps.symbolicate(SOURCE, Some("1.4.0"), frame).unwrap();
+        assert_eq!(maps.symbolicate(SOURCE, Some("1.4.0"), unknown), None);
+        // Only the map that exists is cached, never the miss.
+        assert_eq!(maps.cache().entries.len(), 1);
+
+        let map = Arc::new(sourcemap::decode_slice(&bundle_map()).unwrap());
+        let half = MAX_CACHED_BYTES / 2;
+        let mut cache = MapCache::default();
+        cache.insert("a".into(), map.clone(), half);
+        cache.insert("b".into(), map.clone(), half);
+        assert!(cache.get(Path::new("a")).is_some());
+        cache.insert("c".into(), map.clone(), half);
+        assert!(cache.get(Path::new("b")).is_none());
+        assert!(cache.get(Path::new("a")).is_some());
+        assert_eq!(cache.bytes, MAX_CACHED_BYTES);
+        cache.insert("huge".into(), map, MAX_CACHED_BYTES + 1);
+        assert!(cache.get(Path::new("huge")).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
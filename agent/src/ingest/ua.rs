@@ -5,9 +5,17 @@
 //! cannot identify — pure application user agents like `curl/8.5.0` or
 //! `MyApp/2.4.1 (Windows NT 10.0)` — falls back to a product-token parser
 //! rather than being treated as a bot.
+//!
+//! Chromium freezes most of its UA string (the OS version, device model and
+//! minor browser version), so when the low-entropy User-Agent Client Hints
+//! (`Sec-CH-UA`, `Sec-CH-UA-Mobile`, `Sec-CH-UA-Platform`) accompany a request
+//! they take precedence for the brand, form factor and platform. Like the UA
+//! string, the hint values themselves are never stored.
 
 use woothee::parser::Parser;
 
+use super::truncate;
+
 /// What kind of client sent the request. Browsers split into desktop/mobile by
 /// form factor; anything that identifies itself as a program rather than a
 /// browser is an app; bots are dropped at ingest.
@@ -113,6 +121,96 @@ pub fn classify(user_agent: &str) -> UaClasses {
     }
 }
 
+/// The low-entropy User-Agent Client Hints sent with a request, as raw header
+/// values. Every Chromium browser sends them on secure origins.
+#[derive(Debug, Default)]
+pub struct ClientHints {
+    /// `Sec-CH-UA`: the brand list, e.g. `"Chromium";v="120", "Google Chrome";v="120"`.
+    pub brands: Option<String>,
+    /// `Sec-CH-UA-Mobile`: `?1` on mobile form factors, `?0` otherwise.
+    pub mobile: Option<String>,
+    /// `Sec-CH-UA-Platform`: a quoted platform name, e.g. `"Windows"`.
+    pub platform: Option<String>,
+}
+
+/// Longest brand or platform name taken from a hint; the headers are
+/// client-supplied.
+const MAX_HINT: usize = 64;
+
+/// Classify a User-Agent header value, refined by whatever Client Hints the
+/// browser sent. Hints only apply to browsers — they never turn an app or a
+/// bot into one.
+pub fn classify_with_hints(user_agent: &str, hints: &ClientHints) -> UaClasses {
+    let mut classes = classify(user_agent);
+    if !matches!(classes.kind, UaKind::Desktop | UaKind::Mobile) {
+        return classes;
+    }
+
+    if let Some((brand, major)) = hints.brands.as_deref().and_then(primary_brand) {
+        let same_app = classes.app.as_deref() == Some(brand.as_str());
+        let same_major = classes
+            .version
+            .as_deref()
+            .is_some_and(|v| v.split('.').next() == Some(major.as_str()));
+        if !same_app || !same_major {
+            classes.version = (!major.is_empty()).then_some(major);
+        }
+        classes.app = Some(brand);
+    }
+    match hints.mobile.as_deref().map(str::trim) {
+        Some("?1") => classes.kind = UaKind::Mobile,
+        Some("?0") => classes.kind = UaKind::Desktop,
+        _ => {}
+    }
+    if let Some(platform) = hints
+        .platform
+        .as_deref()
+        .map(|p| p.trim().trim_matches('"'))
+        .filter(|p| !p.is_empty() && !p.eq_ignore_ascii_case("unknown"))
+        .and_then(normalize_os)
+    {
+        classes.os = Some(truncate(&platform, MAX_HINT));
+    }
+    classes
+}
+
+/// The browser named by a `Sec-CH-UA` brand list, with its major version.
+/// GREASE entries ("Not_A Brand" and friends) are skipped, and the generic
+/// "Chromium" brand only ever qualifies a more specific one, so a list naming
+/// nothing else leaves the UA string's identification alone. Brand names are
+/// mapped onto the names woothee uses for the same browsers.
+fn primary_brand(header: &str) -> Option<(String, String)> {
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let brand = parts.next()?.trim().trim_matches('"').trim();
+            let version = parts
+                .find_map(|p| p.trim().strip_prefix("v="))
+                .map(|v| v.trim_matches('"').to_string())
+                .unwrap_or_default();
+            let lower = brand.to_lowercase();
+            if brand.is_empty()
+                || lower == "chromium"
+                || (lower.contains("not") && lower.contains("brand"))
+            {
+                return None;
+            }
+            let name = match brand {
+                "Google Chrome" => "Chrome",
+                "Microsoft Edge" => "Edge",
+                "Opera" | "Opera GX" => "Opera",
+                "Samsung Internet" => "SamsungBrowser",
+                "Yandex" | "YaBrowser" => "Yandex Browser",
+                other => other,
+            };
+            let major = version.split('.').next().unwrap_or_default();
+            let major = if looks_like_version(major) { major } else { "" };
+            Some((truncate(name, MAX_HINT), major.to_string()))
+        })
+        .next()
+}
+
 fn clean(value: &str) -> Option<String> {
     if value.is_empty() || value.eq_ignore_ascii_case("UNKNOWN") {
         None
@@ -144,7 +242,10 @@ fn normalize_os(os: &str) -> Option<String> {
         "macOS"
     } else if lower.starts_with("android") {
         "Android"
-    } else if lower.starts_with("chromeos") {
+    } else if lower.starts_with("chromeos")
+        || lower.starts_with("chrome os")
+        || lower.starts_with("chromium os")
+    {
         "ChromeOS"
     } else if lower.starts_with("linux") {
         "Linux"
@@ -438,6 +539,172 @@ mod tests {
         assert_eq!(c.kind, UaKind::App);
     }
 
+    /// One UA string per major browser, as currently shipped.
+    const BROWSERS: &[(&str, &str, &str, &str, UaKind)] = &[
+        (
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "Chrome",
+            "120.0.0.0",
+            "Windows",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            "Chrome",
+            "120.0.0.0",
+            "Android",
+            UaKind::Mobile,
+        ),
+        (
+            "Mozilla/5.0 (X11; CrOS x86_64 14541.0.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "Chrome",
+            "120.0.0.0",
+            "ChromeOS",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
+            "Safari",
+            "17.1",
+            "macOS",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1",
+            "Safari",
+            "17.1",
+            "iOS",
+            UaKind::Mobile,
+        ),
+        (
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:121.0) Gecko/20100101 Firefox/121.0",
+            "Firefox",
+            "121.0",
+            "Windows",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+            "Firefox",
+            "121.0",
+            "Linux",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (Android 14; Mobile; rv:121.0) Gecko/121.0 Firefox/121.0",
+            "Firefox",
+            "121.0",
+            "Android",
+            UaKind::Mobile,
+        ),
+        (
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.2210.91",
+            "Edge",
+            "120.0.2210.91",
+            "Windows",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 OPR/106.0.0.0",
+            "Opera",
+            "106.0.0.0",
+            "Windows",
+            UaKind::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 14; SAMSUNG SM-S918B) AppleWebKit/537.36 (KHTML, like Gecko) SamsungBrowser/23.0 Chrome/115.0.0.0 Mobile Safari/537.36",
+            "SamsungBrowser",
+            "23.0",
+            "Android",
+            UaKind::Mobile,
+        ),
+    ];
+
+    #[test]
+    fn classifies_every_major_browser() {
+        for &(ua, app, version, os, kind) in BROWSERS {
+            let c = classify(ua);
+            assert_eq!(c.app.as_deref(), Some(app), "{ua}");
+            assert_eq!(c.version.as_deref(), Some(version), "{ua}");
+            assert_eq!(c.os.as_deref(), Some(os), "{ua}");
+            assert_eq!(c.kind, kind, "{ua}");
+        }
+    }
+
+    fn hints(brands: &str, mobile: &str, platform: &str) -> ClientHints {
+        ClientHints {
+            brands: Some(brands.into()),
+            mobile: Some(mobile.into()),
+            platform: Some(platform.into()),
+        }
+    }
+
+    #[test]
+    fn client_hints_refine_chromium_browsers() {
+        let chrome = BROWSERS[0].0;
+
+        // Brave sends Chrome's UA string; only the brand list tells them apart.
+        let c = classify_with_hints(
+            chrome,
+            &hints(
+                r#""Brave";v="120", "Chromium";v="120", "Not_A Brand";v="8""#,
+                "?0",
+                r#""Windows""#,
+            ),
+        );
+        assert_eq!(c.app.as_deref(), Some("Brave"));
+        assert_eq!(c.version.as_deref(), Some("120"));
+        assert_eq!(c.os.as_deref(), Some("Windows"));
+        assert_eq!(c.kind, UaKind::Desktop);
+
+        // Matching brand and major: the UA's fuller version is kept.
+        let c = classify_with_hints(
+            chrome,
+            &hints(
+                r#""Not_A Brand";v="8", "Chromium";v="120", "Google Chrome";v="120""#,
+                "?0",
+                r#""Windows""#,
+            ),
+        );
+        assert_eq!(c.app.as_deref(), Some("Chrome"));
+        assert_eq!(c.version.as_deref(), Some("120.0.0.0"));
+
+        // The form-factor hint overrides the UA's: an Android UA carrying
+        // "Mobile" reads as desktop under `?0` (e.g. a tablet requesting the
+        // desktop site), and a desktop UA as mobile under `?1`, where the
+        // platform hint also replaces the UA's.
+        let c = classify_with_hints(
+            BROWSERS[1].0,
+            &hints(r#""Chromium";v="120""#, "?0", r#""Android""#),
+        );
+        assert_eq!(c.app.as_deref(), Some("Chrome"));
+        assert_eq!(c.kind, UaKind::Desktop);
+        let c = classify_with_hints(chrome, &hints("", "?1", r#""Chrome OS""#));
+        assert_eq!(c.kind, UaKind::Mobile);
+        assert_eq!(c.os.as_deref(), Some("ChromeOS"));
+
+        // Missing or "Unknown" hints change nothing.
+        let c = classify_with_hints(
+            chrome,
+            &ClientHints {
+                platform: Some(r#""Unknown""#.into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(c, classify(chrome));
+    }
+
+    #[test]
+    fn client_hints_do_not_apply_to_apps_or_bots() {
+        let brave = hints(r#""Brave";v="120""#, "?1", r#""Windows""#);
+        assert_eq!(
+            classify_with_hints("curl/8.5.0", &brave),
+            classify("curl/8.5.0")
+        );
+        let googlebot = "Googlebot/2.1 (+http://www.google.com/bot.html)";
+        assert_eq!(classify_with_hints(googlebot, &brave).kind, UaKind::Bot);
+    }
+
     #[test]
     fn find_version_requires_word_boundaries_and_a_dot() {
         assert_eq!(find_version("8.5.0"), Some("8.5.0".to_string()));
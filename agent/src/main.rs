@@ -89,14 +89,20 @@ async fn serve(config: Config, demo: bool) -> errors::Result<()> {
     let store = Arc::new(Store::open(&config.storage.redb_path)?);
 
     // Re-group archived exceptions if the fingerprinting rules changed since the
-    // data was last processed. Runs to completion before serving; a no-op when the
-    // stored rules version already matches this build.
+    // data was last processed, and build the exception search index if this
+    // build tokenizes differently. Runs to completion before serving; a no-op
+    // when the stored versions already match this build.
     {
         let store = store.clone();
         let storage = config.storage.clone();
-        tokio::task::spawn_blocking(move || ingest::regroup_if_needed(&store, &storage))
-            .await
-            .or_system_err(&["The exception re-grouping task panicked; check the logs."])??;
+        tokio::task::spawn_blocking(move || {
+            let grouping = ingest::GroupingRules::load(&store)?;
+            let edits = ingest::GroupEdits::load(&store)?;
+            ingest::regroup_if_needed(&store, &storage, &grouping, &edits)?;
+            ingest::reindex_if_needed(&store, &storage)
+        })
+        .await
+        .or_system_err(&["The exception re-grouping task panicked; check the logs."])??;
     }
 
     #[cfg(debug_assertions)]
@@ -107,11 +113,36 @@ async fn serve(config: Config, demo: bool) -> errors::Result<()> {
             "demo mode: injected {count} events into {}",
             config.storage.redb_path
         );
+        // The events bypassed the writer, which indexes what it stores.
+        ingest::reindex_exceptions(&store, &config.storage, &store.lock_archive())?;
     }
     #[cfg(not(debug_assertions))]
     let _ = demo;
 
+    let rules = Arc::new(ingest::IngestRules::load(&config, &store)?);
+    // Likewise re-derive referrer groups and channels if their rules changed,
+    // before the compactor can archive events still carrying the old ones.
+    {
+        let (store, rules) = (store.clone(), rules.clone());
+        let storage = config.storage.clone();
+        tokio::task::spawn_blocking(move || {
+            ingest::rederive_attribution_if_needed(
+                &store,
+                &storage,
+                &rules.referrers,
+                &rules.channels,
+            )
+        })
+        .await
+        .or_system_err(&[
+            "The referrer and channel re-derivation task panicked; check the logs.",
+        ])??;
+    }
+
     let ingest = ingest::spawn(store.clone(), config.storage.clone());
+    ingest::spawn_flush(rules.clone(), ingest.clone());
+    ingest::spawn_save_discards(store.clone(), rules.clone());
+    ingest::spawn_lift_snoozes(store.clone(), config.storage.clone());
 
     // Parse the ACL once at startup (config load already validated its syntax).
     let acl = Arc::new(config.web.admin.acl_filter()?);
@@ -123,6 +154,7 @@ async fn serve(config: Config, demo: bool) -> errors::Result<()> {
     let unauth_limiter = Arc::new(RateLimiter::from_rule(&config.ratelimit.unauthenticated));
     spawn_limiter_cleanup(tracking_limiter.clone(), unauth_limiter.clone());
 
+    let (shutdown_store, shutdown_rules) = (store.clone(), rules.clone());
     let state = AppState {
         store,
         ingest,
@@ -130,12 +162,35 @@ async fn serve(config: Config, demo: bool) -> errors::Result<()> {
         http,
         oidc_cache: Arc::new(web::helpers::oidc::OidcCache::default()),
         acl,
+        rules,
         tracking_limiter,
         unauth_limiter,
     };
 
     info!("Starting analytics server on {}", state.config.web.address);
-    web::run(state).await
+    let served = web::run(state).await;
+
+    // Store the stand-ins for exception occurrences the sampler skipped but
+    // hadn't yet carried into a stored one, so their counts survive a restart.
+    let pending = shutdown_rules.sampling.flush(i64::MAX);
+    if !pending.is_empty() {
+        let store = shutdown_store.clone();
+        let stored = tokio::task::spawn_blocking(move || store.append_events(&pending)).await;
+        match stored {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => error!("failed to store sampled exception counts: {err}"),
+            Err(err) => error!("sampled exception flush task panicked: {err}"),
+        }
+    }
+    // Likewise keep what the inbound filters discarded since the last save.
+    let saved =
+        tokio::task::spawn_blocking(move || shutdown_rules.inbound.save(&shutdown_store)).await;
+    match saved {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => error!("failed to save inbound filter counts: {err}"),
+        Err(err) => error!("inbound filter count save task panicked: {err}"),
+    }
+    served
 }
 
 /// Periodically reclaim memory from idle rate-limit buckets.
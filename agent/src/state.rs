@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::config::Config;
-use crate::ingest::Ingest;
+use crate::ingest::{Ingest, IngestRules};
 use crate::ratelimit::RateLimiter;
 use crate::store::Store;
 use crate::web::helpers::oidc::OidcCache;
@@ -18,6 +18,9 @@ pub struct AppState {
     pub oidc_cache: Arc<OidcCache>,
     /// The parsed admin ACL (filt-rs is not `Clone`, so it lives behind an `Arc`).
     pub acl: Arc<filt_rs::Filter>,
+    /// Exclusions, hostname aliases and path rules applied at ingest; the store
+    /// backed rules are reloaded whenever a source or project changes.
+    pub rules: Arc<IngestRules>,
     /// Per-IP limiter for the public tracking endpoints.
     pub tracking_limiter: Arc<RateLimiter>,
     /// Per-IP limiter for unauthenticated hits to protected endpoints.
@@ -1,11 +1,17 @@
-//! CRUD for the metadata entities: projects, sources, pixels, and exception triage.
+//! CRUD for the metadata entities: projects, sources, pixels, releases,
+//! exception triage, and the users who have signed in.
 
-use analytics_api::{Pixel, Project, Source, default_kind};
-use chrono::Utc;
-use redb::ReadableTable;
+use std::collections::HashMap;
+
+use analytics_api::{AdminUser, Pixel, Project, Release, Source, default_kind};
+use chrono::{DateTime, Utc};
+use redb::{ReadableDatabase, ReadableTable, Table};
 
 use super::Store;
-use super::tables::{EXCEPTION_TRIAGE, PIXELS, PROJECTS, SOURCES, STORAGE_ADVICE, triage_key};
+use super::tables::{
+    EXCEPTION_TRIAGE, META, META_INBOUND_DISCARDS, PIXELS, PROJECTS, RELEASES, SOURCES,
+    STORAGE_ADVICE, USERS, release_key, triage_key, user_key,
+};
 use super::triage::ExceptionTriage;
 use crate::errors::{Result, ResultExt};
 
@@ -118,6 +124,7 @@ impl Store {
             created_at: now,
             first_seen: Some(now),
             last_seen: Some(now),
+            aliases: Vec::new(),
         })
     }
 
@@ -135,6 +142,46 @@ impl Store {
         self.delete_key(PIXELS, id)
     }
 
+    // ------------------------------------------------------------- releases
+    pub fn put_release(&self, release: &Release) -> Result<()> {
+        self.put_json(
+            RELEASES,
+            &release_key(&release.source, &release.version),
+            release,
+        )
+    }
+    pub fn get_release(&self, source: &str, version: &str) -> Result<Option<Release>> {
+        self.get_json(RELEASES, &release_key(source, version))
+    }
+    pub fn list_releases(&self) -> Result<Vec<Release>> {
+        self.list_json(RELEASES)
+    }
+    pub fn delete_release(&self, source: &str, version: &str) -> Result<bool> {
+        self.delete_key(RELEASES, &release_key(source, version))
+    }
+
+    /// Register a newly-seen version of `source` as a release deployed when it
+    /// was first seen, if it does not already exist. The check and the insert
+    /// share a write transaction, so a release recorded through the API in
+    /// between is never overwritten.
+    pub fn register_release_if_absent(
+        &self,
+        source: &str,
+        version: &str,
+        seen_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let release = Release {
+            source: source.to_string(),
+            version: version.to_string(),
+            deployed_at: seen_at,
+            notes: None,
+            created_at: Utc::now(),
+            auto: true,
+        };
+        self.put_json_if_absent(RELEASES, &release_key(source, version), &release)?;
+        Ok(())
+    }
+
     // ------------------------------------------------------ exception triage
     pub fn put_triage(
         &self,
@@ -148,6 +195,28 @@ impl Store {
         self.get_json(EXCEPTION_TRIAGE, &triage_key(project_id, group_id))
     }
 
+    /// Every triage record of `project_id`, keyed by group, in one range scan.
+    pub fn project_triages(&self, project_id: &str) -> Result<HashMap<String, ExceptionTriage>> {
+        let prefix = triage_key(project_id, "");
+        let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
+        let table = txn
+            .open_table(EXCEPTION_TRIAGE)
+            .or_system_err(STORAGE_ADVICE)?;
+        let mut out = HashMap::new();
+        for item in table
+            .range(prefix.as_str()..)
+            .or_system_err(STORAGE_ADVICE)?
+        {
+            let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+            let Some(group_id) = key.value().strip_prefix(prefix.as_str()) else {
+                break;
+            };
+            let triage = serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+            out.insert(group_id.to_string(), triage);
+        }
+        Ok(out)
+    }
+
     /// Create-or-update a triage record in a single write transaction: `f` is
     /// applied to the existing record, or to a fresh empty one when none exists,
     /// then the result is persisted. Doing the read-modify-write under one
@@ -159,32 +228,146 @@ impl Store {
         group_id: &str,
         f: F,
     ) -> Result<ExceptionTriage> {
-        let key = triage_key(project_id, group_id);
         let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
         let updated = {
             let mut table = txn
                 .open_table(EXCEPTION_TRIAGE)
                 .or_system_err(STORAGE_ADVICE)?;
-            let mut triage = match table.get(key.as_str()).or_system_err(STORAGE_ADVICE)? {
-                Some(value) => {
-                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?
-                }
-                None => ExceptionTriage {
-                    resolved_at: None,
-                    muted_at: None,
-                    note: None,
-                    updated_at: Utc::now(),
-                    updated_by: None,
-                },
-            };
-            f(&mut triage);
-            let bytes = serde_json::to_vec(&triage).or_system_err(STORAGE_ADVICE)?;
-            table
-                .insert(key.as_str(), bytes.as_slice())
-                .or_system_err(STORAGE_ADVICE)?;
-            triage
+            update_triage_in(&mut table, &triage_key(project_id, group_id), f)?
         };
         txn.commit().or_system_err(STORAGE_ADVICE)?;
         Ok(updated)
     }
+
+    /// Amend an existing triage record in a single write transaction: `f` is
+    /// applied to it and reports whether it changed anything, and only then is
+    /// the record persisted. Unlike [`update_triage`](Self::update_triage) a
+    /// missing record stays missing. Returns whether the record was changed.
+    pub fn amend_triage<F: FnOnce(&mut ExceptionTriage) -> bool>(
+        &self,
+        project_id: &str,
+        group_id: &str,
+        f: F,
+    ) -> Result<bool> {
+        let key = triage_key(project_id, group_id);
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        let changed = {
+            let mut table = txn
+                .open_table(EXCEPTION_TRIAGE)
+                .or_system_err(STORAGE_ADVICE)?;
+            let triage: Option<ExceptionTriage> = table
+                .get(key.as_str())
+                .or_system_err(STORAGE_ADVICE)?
+                .map(|value| serde_json::from_slice(value.value()))
+                .transpose()
+                .or_system_err(STORAGE_ADVICE)?;
+            match triage {
+                Some(mut triage) if f(&mut triage) => {
+                    let bytes = serde_json::to_vec(&triage).or_system_err(STORAGE_ADVICE)?;
+                    table
+                        .insert(key.as_str(), bytes.as_slice())
+                        .or_system_err(STORAGE_ADVICE)?;
+                    true
+                }
+                _ => false,
+            }
+        };
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(changed)
+    }
+
+    /// `(project_id, group_id, triage)` for every snoozed group.
+    pub fn snoozed_triages(&self) -> Result<Vec<(String, String, ExceptionTriage)>> {
+        let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
+        let table = txn
+            .open_table(EXCEPTION_TRIAGE)
+            .or_system_err(STORAGE_ADVICE)?;
+        let mut out = Vec::new();
+        for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+            let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+            let triage: ExceptionTriage =
+                serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+            if triage.muted_at.is_none() || triage.snooze.is_none() {
+                continue;
+            }
+            if let Some((project_id, group_id)) = key.value().split_once('\u{1f}') {
+                out.push((project_id.to_string(), group_id.to_string(), triage));
+            }
+        }
+        Ok(out)
+    }
+
+    /// [`update_triage`](Self::update_triage) for many groups, each a
+    /// `(project_id, group_id)`, in a single write transaction: either every
+    /// record is updated or none is. Returns how many were.
+    pub fn update_triages<F: FnMut(&mut ExceptionTriage)>(
+        &self,
+        groups: &[(String, String)],
+        mut f: F,
+    ) -> Result<usize> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_TRIAGE)
+                .or_system_err(STORAGE_ADVICE)?;
+            for (project_id, group_id) in groups {
+                update_triage_in(&mut table, &triage_key(project_id, group_id), &mut f)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(groups.len())
+    }
+
+    // ---------------------------------------------------------------- users
+    /// Remember a signed-in user, so work can be assigned to them later.
+    pub fn record_user(&self, user: &AdminUser) -> Result<()> {
+        self.put_json(USERS, &user_key(user.key()), user)
+    }
+    /// The signed-in user known by `key` (see [`AdminUser::key`]), matched
+    /// case-insensitively.
+    pub fn get_user(&self, key: &str) -> Result<Option<AdminUser>> {
+        self.get_json(USERS, &user_key(key))
+    }
+    pub fn list_users(&self) -> Result<Vec<AdminUser>> {
+        self.list_json(USERS)
+    }
+
+    // ------------------------------------------------------ inbound discards
+    /// The last saved `(project id, filter label, reports discarded)` counts.
+    pub fn inbound_discards(&self) -> Result<Vec<(String, String, u64)>> {
+        Ok(self
+            .get_json(META, META_INBOUND_DISCARDS)?
+            .unwrap_or_default())
+    }
+    /// Replace the saved inbound filter counts with `counts`.
+    pub fn put_inbound_discards(&self, counts: &[(String, String, u64)]) -> Result<()> {
+        self.put_json(META, META_INBOUND_DISCARDS, &counts)
+    }
+}
+
+/// Apply `f` to the triage record at `key`, or to a fresh empty one, and store
+/// the result in `table`.
+fn update_triage_in(
+    table: &mut Table<'_, &'static str, &'static [u8]>,
+    key: &str,
+    f: impl FnOnce(&mut ExceptionTriage),
+) -> Result<ExceptionTriage> {
+    let mut triage = match table.get(key).or_system_err(STORAGE_ADVICE)? {
+        Some(value) => serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?,
+        None => ExceptionTriage {
+            resolved_at: None,
+            muted_at: None,
+            snooze: None,
+            assignee: None,
+            comments: Vec::new(),
+            updated_at: Utc::now(),
+            updated_by: None,
+        },
+    };
+    f(&mut triage);
+    let bytes = serde_json::to_vec(&triage).or_system_err(STORAGE_ADVICE)?;
+    table
+        .insert(key, bytes.as_slice())
+        .or_system_err(STORAGE_ADVICE)?;
+    Ok(triage)
 }
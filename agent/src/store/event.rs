@@ -32,7 +32,7 @@ impl EventKind {
 /// project is resolved at query time from the sources/pixels maps, never stored
 /// here. No raw IP, User-Agent, Accept-Language, or cookies are ever retained —
 /// only derived classes.
-#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct StoredEvent {
     /// Client-reported event time (epoch millis).
     pub created_ms: i64,
@@ -59,6 +59,10 @@ pub struct StoredEvent {
     pub source: String,
 
     pub pathname: Option<String>,
+    /// The pathname as reported, kept only when a project path rule rewrote it
+    /// and the project opted in (`keep_original_path`).
+    #[serde(default)]
+    pub original_pathname: Option<String>,
     pub is_unique_user: bool,
     pub is_unique_page: bool,
 
@@ -77,17 +81,36 @@ pub struct StoredEvent {
     pub utm_source: Option<String>,
     pub utm_medium: Option<String>,
     pub utm_campaign: Option<String>,
+    /// The acquisition channel (Organic Search, Email, ...) of a hit, derived
+    /// at ingest from the referrer and UTM tags. `serde(default)` keeps
+    /// pre-existing redb rows readable.
+    #[serde(default)]
+    pub channel: Option<String>,
     pub duration_ms: Option<i64>,
+    /// Time on page while the tab was visible (unload events). `serde(default)`
+    /// keeps pre-existing redb rows readable.
+    #[serde(default)]
+    pub engaged_ms: Option<i64>,
+    /// Maximum scroll depth reached, in percent of the page (unload events).
+    #[serde(default)]
+    pub scroll_depth: Option<i64>,
 
     /// Event name for `Custom`/`Pixel` events.
     pub event_name: Option<String>,
     /// Arbitrary key/value metadata (JSON object) for custom/pixel/exception events.
     pub metadata_json: Option<String>,
+    /// Optional numeric value of a custom event (order value, cart size, …).
+    /// `serde(default)` keeps pre-existing redb rows readable.
+    #[serde(default)]
+    pub value: Option<f64>,
+    /// Currency code qualifying `value` (upper-cased), when it is monetary.
+    #[serde(default)]
+    pub currency: Option<String>,
 
-    /// The reporting application's version, for attributing failures to a
-    /// specific release (exception events; the application itself is the
-    /// event's `source`). `serde(default)` keeps pre-attribution redb rows
-    /// readable.
+    /// The reporting application's version, for attributing failures and
+    /// sessions to a specific release (exception events, and hits from a
+    /// tracker given one; the application itself is the event's `source`).
+    /// `serde(default)` keeps pre-attribution redb rows readable.
     #[serde(default)]
     pub app_version: Option<String>,
 
@@ -96,5 +119,28 @@ pub struct StoredEvent {
     pub exc_message: Option<String>,
     pub exc_stack: Option<String>,
     pub exc_group: Option<String>,
+    /// The client's grouping override (`ExceptionReport::fingerprint`), kept so
+    /// re-grouping honours it. `serde(default)` keeps pre-override redb rows
+    /// readable.
+    #[serde(default)]
+    pub exc_fingerprint: Option<String>,
     pub exc_handled: Option<bool>,
+    /// The occurrence's severity (an [`ExceptionLevel`](analytics_api::ExceptionLevel)
+    /// name). `serde(default)` keeps pre-level redb rows readable.
+    #[serde(default)]
+    pub exc_level: Option<String>,
+    /// The deployment environment that reported the exception, lower-cased.
+    /// `serde(default)` keeps pre-environment redb rows readable.
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// The share of its group's occurrences this stored one was sampled from
+    /// during an exception storm: it stands for `1 / sample_rate` occurrences.
+    /// `None` for an occurrence stored in full. `serde(default)` keeps
+    /// pre-sampling redb rows readable.
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+    /// The report's scrubbed breadcrumbs (a JSON array, oldest first).
+    /// `serde(default)` keeps pre-breadcrumb redb rows readable.
+    #[serde(default)]
+    pub breadcrumbs_json: Option<String>,
 }
@@ -56,6 +56,26 @@ impl Store {
         Ok(out)
     }
 
+    /// Return the hot events received in `[from_ms, to_ms)` (oldest first),
+    /// reading only that range of keys.
+    pub fn events_between(&self, from_ms: i64, to_ms: i64) -> Result<Vec<StoredEvent>> {
+        let (from, to) = (event_key(from_ms, 0), event_key(to_ms, 0));
+        if to <= from {
+            return Ok(Vec::new());
+        }
+        let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
+        let table = txn.open_table(EVENTS).or_system_err(STORAGE_ADVICE)?;
+        let mut out = Vec::new();
+        for item in table
+            .range(from.as_slice()..to.as_slice())
+            .or_system_err(STORAGE_ADVICE)?
+        {
+            let (_key, value) = item.or_system_err(STORAGE_ADVICE)?;
+            out.push(serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?);
+        }
+        Ok(out)
+    }
+
     /// Number of events in the hot store.
     pub fn event_count(&self) -> Result<u64> {
         let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
@@ -109,4 +129,10 @@ impl Store {
     pub fn hot_dataframe(&self) -> Result<DataFrame> {
         build_dataframe(&self.all_events()?).or_system_err(STORAGE_ADVICE)
     }
+
+    /// Build a polars [`DataFrame`] from the hot events received in
+    /// `[from_ms, to_ms)`.
+    pub fn hot_dataframe_between(&self, from_ms: i64, to_ms: i64) -> Result<DataFrame> {
+        build_dataframe(&self.events_between(from_ms, to_ms)?).or_system_err(STORAGE_ADVICE)
+    }
 }
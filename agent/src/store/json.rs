@@ -26,6 +26,29 @@ impl Store {
         Ok(())
     }
 
+    /// Insert `value` under `key` unless the key exists, checking and writing in
+    /// one write transaction. Returns whether it was inserted.
+    pub(super) fn put_json_if_absent<T: Serialize>(
+        &self,
+        def: JsonTable,
+        key: &str,
+        value: &T,
+    ) -> Result<bool> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn.open_table(def).or_system_err(STORAGE_ADVICE)?;
+            if table.get(key).or_system_err(STORAGE_ADVICE)?.is_some() {
+                return Ok(false);
+            }
+            let bytes = serde_json::to_vec(value).or_system_err(STORAGE_ADVICE)?;
+            table
+                .insert(key, bytes.as_slice())
+                .or_system_err(STORAGE_ADVICE)?;
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(true)
+    }
+
     pub(super) fn get_json<T: DeserializeOwned>(
         &self,
         def: JsonTable,
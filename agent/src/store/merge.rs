@@ -0,0 +1,461 @@
+//! Manual merges and splits of exception groups.
+//!
+//! A merge is recorded as a redirect from each merged group to the surviving
+//! one, on the same source. New occurrences are redirected at ingest, and a
+//! re-grouping pass over the source stores the target on the recorded ones;
+//! until one has, queries map redirected groups onto their target as they read.
+//! Once it has, the redirect is marked applied and only ingest follows it.
+//! Redirects are kept flat — merging a group that others already redirect to
+//! re-points them too — so resolving one is a single lookup.
+//!
+//! A split moves the occurrences of a group whose message or stack matches a
+//! pattern into a group of their own. It is recorded so that ingest and
+//! re-grouping keep applying it; the stored occurrences are moved by a
+//! re-grouping pass in the background, which marks the split applied.
+
+use chrono::{DateTime, Utc};
+use redb::ReadableTable;
+use serde::{Deserialize, Serialize};
+
+use super::Store;
+use super::tables::{
+    EXCEPTION_REDIRECTS, EXCEPTION_SPLITS, EXCEPTION_TRIAGE, STORAGE_ADVICE, group_key, triage_key,
+};
+use super::triage::{ExceptionTriage, scoped_group};
+use crate::errors::{Result, ResultExt};
+
+/// One exception group merged into another on the same source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupRedirect {
+    pub source: String,
+    pub group_id: String,
+    pub target: String,
+    pub merged_at: DateTime<Utc>,
+    pub merged_by: Option<String>,
+    /// Set once a re-grouping pass has stored `target` on the occurrences
+    /// recorded before it, so queries no longer need to map the group.
+    #[serde(default)]
+    pub applied: bool,
+}
+
+/// The occurrences of `group_id` on `source` matching every given pattern,
+/// split off into `target`. Patterns are globs, as in fingerprint rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupSplit {
+    pub source: String,
+    pub group_id: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub stack: Option<String>,
+    pub target: String,
+    pub split_at: DateTime<Utc>,
+    pub split_by: Option<String>,
+    /// Set once a re-grouping pass has moved the occurrences recorded before
+    /// it into `target`.
+    #[serde(default)]
+    pub applied: bool,
+}
+
+impl Store {
+    pub fn list_group_redirects(&self) -> Result<Vec<GroupRedirect>> {
+        self.list_json(EXCEPTION_REDIRECTS)
+    }
+
+    pub fn list_group_splits(&self) -> Result<Vec<GroupSplit>> {
+        self.list_json(EXCEPTION_SPLITS)
+    }
+
+    /// Record `redirects` — every one onto the same target on the same source —
+    /// in one write transaction, re-pointing existing redirects onto a merged
+    /// group at the target. When `project_id` is given the members' triage
+    /// records are replaced by `triage` on the target.
+    pub fn merge_exception_groups(
+        &self,
+        project_id: Option<&str>,
+        redirects: &[GroupRedirect],
+        triage: Option<&ExceptionTriage>,
+    ) -> Result<()> {
+        let Some(first) = redirects.first() else {
+            return Ok(());
+        };
+        let (source, target) = (first.source.as_str(), first.target.as_str());
+        let merged = |group: &str| redirects.iter().any(|r| r.group_id == group);
+
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_REDIRECTS)
+                .or_system_err(STORAGE_ADVICE)?;
+            let mut repointed: Vec<GroupRedirect> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (_key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let existing: GroupRedirect =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                if existing.source == source && merged(&existing.target) {
+                    repointed.push(GroupRedirect {
+                        target: target.to_string(),
+                        ..existing
+                    });
+                }
+            }
+            for redirect in repointed.iter().chain(redirects) {
+                let bytes = serde_json::to_vec(redirect).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(
+                        group_key(&redirect.source, &redirect.group_id).as_str(),
+                        bytes.as_slice(),
+                    )
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+
+            if let Some(project_id) = project_id {
+                let mut table = txn
+                    .open_table(EXCEPTION_TRIAGE)
+                    .or_system_err(STORAGE_ADVICE)?;
+                for redirect in redirects {
+                    let key = triage_key(project_id, &scoped_group(&redirect.group_id, source));
+                    table.remove(key.as_str()).or_system_err(STORAGE_ADVICE)?;
+                }
+                let key = triage_key(project_id, &scoped_group(target, source));
+                match triage {
+                    Some(triage) => {
+                        let bytes = serde_json::to_vec(triage).or_system_err(STORAGE_ADVICE)?;
+                        table
+                            .insert(key.as_str(), bytes.as_slice())
+                            .or_system_err(STORAGE_ADVICE)?;
+                    }
+                    None => {
+                        table.remove(key.as_str()).or_system_err(STORAGE_ADVICE)?;
+                    }
+                }
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(())
+    }
+
+    /// Mark the unapplied merges and splits of the sources `covers` accepts,
+    /// recorded no later than `before`, as applied: a re-grouping pass that
+    /// started then has stored their targets. Returns the number marked.
+    pub fn mark_group_edits_applied(
+        &self,
+        covers: &dyn Fn(&str) -> bool,
+        before: DateTime<Utc>,
+    ) -> Result<usize> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        let mut marked = 0;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_REDIRECTS)
+                .or_system_err(STORAGE_ADVICE)?;
+            let mut applied: Vec<GroupRedirect> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (_key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let redirect: GroupRedirect =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                if !redirect.applied && redirect.merged_at <= before && covers(&redirect.source) {
+                    applied.push(GroupRedirect {
+                        applied: true,
+                        ..redirect
+                    });
+                }
+            }
+            for redirect in &applied {
+                let bytes = serde_json::to_vec(redirect).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(
+                        group_key(&redirect.source, &redirect.group_id).as_str(),
+                        bytes.as_slice(),
+                    )
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+            marked += applied.len();
+        }
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_SPLITS)
+                .or_system_err(STORAGE_ADVICE)?;
+            let mut applied: Vec<GroupSplit> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (_key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let split: GroupSplit =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                if !split.applied && split.split_at <= before && covers(&split.source) {
+                    applied.push(GroupSplit {
+                        applied: true,
+                        ..split
+                    });
+                }
+            }
+            for split in &applied {
+                let bytes = serde_json::to_vec(split).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(
+                        group_key(&split.source, &split.target).as_str(),
+                        bytes.as_slice(),
+                    )
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+            marked += applied.len();
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(marked)
+    }
+
+    /// Record `split`, and when `project_id` is given start the split-off group
+    /// with `triage` (its parent's state).
+    pub fn put_group_split(
+        &self,
+        project_id: Option<&str>,
+        split: &GroupSplit,
+        triage: Option<&ExceptionTriage>,
+    ) -> Result<()> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_SPLITS)
+                .or_system_err(STORAGE_ADVICE)?;
+            let bytes = serde_json::to_vec(split).or_system_err(STORAGE_ADVICE)?;
+            table
+                .insert(
+                    group_key(&split.source, &split.target).as_str(),
+                    bytes.as_slice(),
+                )
+                .or_system_err(STORAGE_ADVICE)?;
+
+            if let (Some(project_id), Some(triage)) = (project_id, triage) {
+                let mut table = txn
+                    .open_table(EXCEPTION_TRIAGE)
+                    .or_system_err(STORAGE_ADVICE)?;
+                let key = triage_key(project_id, &scoped_group(&split.target, &split.source));
+                let bytes = serde_json::to_vec(triage).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(key.as_str(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use analytics_api::{ExceptionSnooze, TriageComment};
+
+    fn redirect(group_id: &str, target: &str) -> GroupRedirect {
+        GroupRedirect {
+            source: "https://example.com".into(),
+            group_id: group_id.into(),
+            target: target.into(),
+            merged_at: Utc::now(),
+            merged_by: None,
+            applied: false,
+        }
+    }
+
+    fn triage(resolved_at: Option<DateTime<Utc>>, comment: &str) -> ExceptionTriage {
+        ExceptionTriage {
+            resolved_at,
+            muted_at: None,
+            snooze: None,
+            assignee: None,
+            comments: vec![TriageComment {
+                author: None,
+                body: comment.into(),
+                created_at: DateTime::UNIX_EPOCH,
+            }],
+            updated_at: Utc::now(),
+            updated_by: None,
+        }
+    }
+
+    fn bodies(triage: &ExceptionTriage) -> Vec<&str> {
+        triage.comments.iter().map(|c| c.body.as_str()).collect()
+    }
+
+    #[test]
+    fn merges_keep_redirects_flat_and_carry_triage() {
+        let path =
+            std::env::temp_dir().join(format!("analytics-merge-{}.redb", std::process::id()));
+        let store = Store::open(&path).unwrap();
+        let source = "https://example.com";
+
+        store
+            .put_triage("p1", &scoped_group("a", source), &triage(None, "flaky"))
+            .unwrap();
+        store
+            .merge_exception_groups(Some("p1"), &[redirect("a", "b")], None)
+            .unwrap();
+        assert!(
+            store
+                .get_triage("p1", &scoped_group("a", source))
+                .unwrap()
+                .is_none()
+        );
+
+        // Merging `b` into `c` brings `a`'s redirect along.
+        let merged = triage(None, "flaky");
+        store
+            .merge_exception_groups(Some("p1"), &[redirect("b", "c")], Some(&merged))
+            .unwrap();
+        let mut targets: Vec<(String, String)> = store
+            .list_group_redirects()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.group_id, r.target))
+            .collect();
+        targets.sort();
+        assert_eq!(
+            targets,
+            vec![("a".into(), "c".into()), ("b".into(), "c".into())]
+        );
+        assert_eq!(
+            store.get_triage("p1", &scoped_group("c", source)).unwrap(),
+            Some(merged)
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn marks_only_covered_edits_recorded_before_the_pass() {
+        let path = std::env::temp_dir().join(format!(
+            "analytics-merge-applied-{}.redb",
+            std::process::id()
+        ));
+        let store = Store::open(&path).unwrap();
+        let before = Utc::now();
+        let later = GroupRedirect {
+            merged_at: before + chrono::Duration::seconds(1),
+            ..redirect("b", "c")
+        };
+        let elsewhere = GroupRedirect {
+            source: "https://other.org".into(),
+            merged_at: before,
+            ..redirect("a", "c")
+        };
+        let first = GroupRedirect {
+            merged_at: before,
+            ..redirect("a", "c")
+        };
+        for redirect in [first, later, elsewhere] {
+            store
+                .merge_exception_groups(None, &[redirect], None)
+                .unwrap();
+        }
+        let split = GroupSplit {
+            source: "https://example.com".into(),
+            group_id: "c".into(),
+            message: Some("*timeout*".into()),
+            stack: None,
+            target: "d".into(),
+            split_at: before,
+            split_by: None,
+            applied: false,
+        };
+        store.put_group_split(None, &split, None).unwrap();
+
+        let covers = |source: &str| source == "https://example.com";
+        assert_eq!(store.mark_group_edits_applied(&covers, before).unwrap(), 2);
+        assert!(store.list_group_splits().unwrap()[0].applied);
+        let mut applied: Vec<(String, String, bool)> = store
+            .list_group_redirects()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.source, r.group_id, r.applied))
+            .collect();
+        applied.sort();
+        assert_eq!(
+            applied,
+            vec![
+                ("https://example.com".into(), "a".into(), true),
+                ("https://example.com".into(), "b".into(), false),
+                ("https://other.org".into(), "a".into(), false),
+            ]
+        );
+        // Already-applied edits aren't marked again.
+        assert_eq!(store.mark_group_edits_applied(&covers, before).unwrap(), 0);
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn merged_triage_is_resolved_only_when_every_member_is() {
+        let now = Utc::now();
+        let resolved_at = now - chrono::Duration::hours(1);
+        let before = resolved_at.timestamp_millis() - 1;
+        let after = resolved_at.timestamp_millis() + 1;
+
+        let merged = ExceptionTriage::merged(
+            &[
+                (Some(triage(Some(resolved_at), "fixed in 1.2")), before),
+                (Some(triage(Some(resolved_at), "fixed in 1.2")), before),
+            ],
+            now,
+            None,
+        )
+        .unwrap();
+        assert_eq!(merged.resolved_at, Some(now));
+        assert_eq!(bodies(&merged), vec!["fixed in 1.2"]);
+
+        // A member that regressed since its resolution, or was never triaged,
+        // keeps the merged group open.
+        let regressed = ExceptionTriage::merged(
+            &[
+                (Some(triage(Some(resolved_at), "a")), after),
+                (None, before),
+            ],
+            now,
+            None,
+        )
+        .unwrap();
+        assert_eq!(regressed.resolved_at, None);
+        assert_eq!(bodies(&regressed), vec!["a"]);
+
+        assert_eq!(ExceptionTriage::merged(&[(None, before)], now, None), None);
+    }
+
+    #[test]
+    fn merged_triage_stays_snoozed_only_when_every_member_is() {
+        let now = Utc::now();
+        let earlier = now - chrono::Duration::hours(1);
+        let snoozed = |muted_at, count| ExceptionTriage {
+            muted_at: Some(muted_at),
+            snooze: Some(ExceptionSnooze::Occurrences { count }),
+            ..triage(None, "")
+        };
+
+        // Every member snoozed: the most recently snoozed member's condition
+        // and anchor carry over.
+        let merged = ExceptionTriage::merged(
+            &[(Some(snoozed(earlier, 5)), 0), (Some(snoozed(now, 10)), 0)],
+            now,
+            None,
+        )
+        .unwrap();
+        assert_eq!(merged.muted_at, Some(now));
+        assert_eq!(
+            merged.snooze,
+            Some(ExceptionSnooze::Occurrences { count: 10 })
+        );
+
+        // A member muted for good keeps the merged group muted for good.
+        let forever = ExceptionTriage {
+            snooze: None,
+            ..snoozed(earlier, 0)
+        };
+        let merged = ExceptionTriage::merged(
+            &[(Some(snoozed(now, 10)), 0), (Some(forever), 0)],
+            now,
+            None,
+        )
+        .unwrap();
+        assert_eq!(merged.muted_at, Some(now));
+        assert_eq!(merged.snooze, None);
+    }
+}
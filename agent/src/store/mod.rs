@@ -6,25 +6,36 @@
 //! - [`schema`] — on-disk version + forward migrations
 //! - [`json`] — generic JSON CRUD helpers
 //! - [`events`] — append-only event log
-//! - [`entities`] — project/source/pixel/triage CRUD
+//! - [`entities`] — project/source/pixel/release/triage/user CRUD
 //! - [`parquet`] — columnar Parquet bridge
+//! - [`reattribute`] — moving stored events from alias sources to a canonical one
+//! - [`merge`] — manual merges and splits of exception groups
+//! - [`search`] — the full-text term index over archived exceptions
 
 mod entities;
 mod event;
 mod events;
 mod json;
+mod merge;
 mod parquet;
+mod reattribute;
 mod regroup;
 mod schema;
+mod search;
+mod signatures;
 mod tables;
 mod triage;
 
 pub use event::{EventKind, StoredEvent};
+pub use merge::{GroupRedirect, GroupSplit};
 pub use parquet::{build_dataframe, read_partition, write_partition};
-pub use triage::ExceptionTriage;
+pub use regroup::{StoredException, StoredVisit};
+pub use signatures::GroupSignature;
+pub use triage::{ExceptionTriage, scoped_group, unscoped_group};
 
 use std::path::Path;
 use std::sync::atomic::AtomicU64;
+use std::sync::{Mutex, MutexGuard};
 
 use redb::{Database, ReadableDatabase};
 
@@ -35,6 +46,9 @@ use crate::errors::{Result, ResultExt};
 pub struct Store {
     db: Database,
     next_seq: AtomicU64,
+    /// Held by every pass that moves events into the archive or rewrites them
+    /// there (compaction, retention, re-attribution, re-grouping).
+    archive: Mutex<()>,
 }
 
 impl Store {
@@ -48,8 +62,17 @@ impl Store {
         Ok(Self {
             db,
             next_seq: AtomicU64::new(next_seq),
+            archive: Mutex::new(()),
         })
     }
+
+    /// Serialise a pass over the stored events with compaction. A pass that
+    /// rewrites the hot store and the archive holds the guard across both, so
+    /// the compactor can't archive an event between the two halves (or delete
+    /// one rewritten after it was read).
+    pub fn lock_archive(&self) -> MutexGuard<'_, ()> {
+        self.archive.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 /// Touch every table so it exists for later read transactions.
@@ -65,6 +88,18 @@ fn ensure_tables(db: &Database) -> Result<()> {
         .or_system_err(tables::OPEN_ADVICE)?;
     txn.open_table(tables::EXCEPTION_TRIAGE)
         .or_system_err(tables::OPEN_ADVICE)?;
+    txn.open_table(tables::EXCEPTION_REDIRECTS)
+        .or_system_err(tables::OPEN_ADVICE)?;
+    txn.open_table(tables::EXCEPTION_SPLITS)
+        .or_system_err(tables::OPEN_ADVICE)?;
+    txn.open_table(tables::RELEASES)
+        .or_system_err(tables::OPEN_ADVICE)?;
+    txn.open_table(tables::EXCEPTION_TERMS)
+        .or_system_err(tables::OPEN_ADVICE)?;
+    txn.open_table(tables::EXCEPTION_SIGNATURES)
+        .or_system_err(tables::OPEN_ADVICE)?;
+    txn.open_table(tables::USERS)
+        .or_system_err(tables::OPEN_ADVICE)?;
     txn.open_table(tables::META)
         .or_system_err(tables::OPEN_ADVICE)?;
     txn.commit().or_system_err(tables::OPEN_ADVICE)?;
@@ -141,6 +176,12 @@ mod tests {
             name: "Example".to_string(),
             slug: "example".to_string(),
             created_at: Utc::now(),
+            path_rules: Vec::new(),
+            keep_original_path: false,
+            fingerprint_rules: Vec::new(),
+            exception_sampling: Default::default(),
+            inbound_filters: Default::default(),
+            goals: Vec::new(),
         };
         store.put_project(&project).unwrap();
         assert_eq!(store.get_project("p1").unwrap().as_ref(), Some(&project));
@@ -156,14 +197,22 @@ mod tests {
         let triage = ExceptionTriage {
             resolved_at: Some(resolved_at),
             muted_at: None,
-            note: Some("fixed in v2".to_string()),
+            snooze: None,
+            assignee: Some(analytics_api::AdminUser {
+                name: "Ada".to_string(),
+                email: Some("ada@example.com".to_string()),
+            }),
+            comments: vec![analytics_api::TriageComment {
+                author: Some("admin".to_string()),
+                body: "fixed in v2".to_string(),
+                created_at: resolved_at,
+            }],
             updated_at: resolved_at,
             updated_by: Some("admin".to_string()),
         };
         store.put_triage("p1", "g1", &triage).unwrap();
         let got = store.get_triage("p1", "g1").unwrap().unwrap();
-        assert_eq!(got.resolved_at, Some(resolved_at));
-        assert_eq!(got.note.as_deref(), Some("fixed in v2"));
+        assert_eq!(got, triage);
         // A different group, or different project, has no triage.
         assert!(store.get_triage("p1", "other").unwrap().is_none());
         assert!(store.get_triage("p2", "g1").unwrap().is_none());
@@ -176,16 +225,39 @@ mod tests {
         store
             .update_triage("p1", "g1", |t| {
                 t.resolved_at = Some(Utc::now());
-                t.note = Some("first".to_string());
+                t.assignee = Some(analytics_api::AdminUser {
+                    name: "Ada".to_string(),
+                    email: None,
+                });
             })
             .unwrap();
-        // Muting must not disturb the resolution axis or the note.
+        // Muting must not disturb the resolution axis or the assignee.
         let muted = store
             .update_triage("p1", "g1", |t| t.muted_at = Some(Utc::now()))
             .unwrap();
         assert!(muted.resolved_at.is_some(), "resolution preserved");
         assert!(muted.muted_at.is_some(), "now muted");
-        assert_eq!(muted.note.as_deref(), Some("first"), "note preserved");
+        assert!(muted.assignee.is_some(), "assignee preserved");
+    }
+
+    #[test]
+    fn update_triages_applies_to_every_group() {
+        let store = temp_store();
+        store
+            .update_triage("p1", "g1", |t| t.muted_at = Some(Utc::now()))
+            .unwrap();
+        let groups = [
+            ("p1".to_string(), "g1".to_string()),
+            ("p2".to_string(), "g2".to_string()),
+        ];
+        let updated = store
+            .update_triages(&groups, |t| t.resolved_at = Some(Utc::now()))
+            .unwrap();
+        assert_eq!(updated, 2);
+        let g1 = store.get_triage("p1", "g1").unwrap().unwrap();
+        assert!(g1.resolved_at.is_some() && g1.muted_at.is_some());
+        let g2 = store.get_triage("p2", "g2").unwrap().unwrap();
+        assert!(g2.resolved_at.is_some() && g2.muted_at.is_none());
     }
 
     #[test]
@@ -194,7 +266,9 @@ mod tests {
         let triage = ExceptionTriage {
             resolved_at: Some(resolved_at),
             muted_at: None,
-            note: None,
+            snooze: None,
+            assignee: None,
+            comments: Vec::new(),
             updated_at: resolved_at,
             updated_by: None,
         };
@@ -213,6 +287,46 @@ mod tests {
         };
         assert!(muted.is_muted());
         assert!(!muted.is_resolved(anchor + 1));
+        assert!(
+            !muted.snooze_lapsed(resolved_at, i64::MAX, i64::MAX),
+            "muted for good"
+        );
+    }
+
+    #[test]
+    fn snoozes_lapse_once_their_condition_is_met() {
+        use analytics_api::ExceptionSnooze;
+        let snoozed_at = Utc::now();
+        let snoozed = |snooze| ExceptionTriage {
+            resolved_at: None,
+            muted_at: Some(snoozed_at),
+            snooze: Some(snooze),
+            assignee: None,
+            comments: Vec::new(),
+            updated_at: snoozed_at,
+            updated_by: None,
+        };
+
+        let until = snoozed(ExceptionSnooze::Date {
+            at_ms: snoozed_at.timestamp_millis() + 1_000,
+        });
+        assert!(!until.snooze_lapsed(snoozed_at, 0, 0));
+        assert!(until.snooze_lapsed(snoozed_at + chrono::Duration::seconds(1), 0, 0));
+
+        let occurrences = snoozed(ExceptionSnooze::Occurrences { count: 10 });
+        assert!(!occurrences.snooze_lapsed(snoozed_at, 9, 100));
+        assert!(occurrences.snooze_lapsed(snoozed_at, 10, 1));
+
+        let sessions = snoozed(ExceptionSnooze::Sessions { count: 3 });
+        assert!(!sessions.snooze_lapsed(snoozed_at, 100, 2));
+        assert!(sessions.snooze_lapsed(snoozed_at, 3, 3));
+
+        // An unmuted group has no snooze to lapse.
+        let unmuted = ExceptionTriage {
+            muted_at: None,
+            ..occurrences
+        };
+        assert!(!unmuted.snooze_lapsed(snoozed_at, 10, 10));
     }
 
     #[test]
@@ -297,6 +411,44 @@ mod tests {
         assert!(store.get_source("https://missing").unwrap().is_none());
     }
 
+    #[test]
+    fn first_sighting_never_overrides_a_declared_release() {
+        let store = temp_store();
+        let deployed = Utc::now() - chrono::Duration::days(1);
+        store
+            .put_release(&analytics_api::Release {
+                source: "https://a.com".to_string(),
+                version: "1.2.0".to_string(),
+                deployed_at: deployed,
+                notes: Some("Checkout rewrite".to_string()),
+                created_at: deployed,
+                auto: false,
+            })
+            .unwrap();
+
+        store
+            .register_release_if_absent("https://a.com", "1.2.0", Utc::now())
+            .unwrap();
+        store
+            .register_release_if_absent("https://a.com", "1.3.0", Utc::now())
+            .unwrap();
+
+        let declared = store
+            .get_release("https://a.com", "1.2.0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(declared.deployed_at, deployed);
+        assert!(!declared.auto);
+        assert!(
+            store
+                .get_release("https://a.com", "1.3.0")
+                .unwrap()
+                .unwrap()
+                .auto
+        );
+        assert_eq!(store.list_releases().unwrap().len(), 2);
+    }
+
     #[test]
     fn delete_project_cascade_unassigns_sources_and_removes_pixels() {
         use analytics_api::{Pixel, Source, SourceKind};
@@ -308,6 +460,12 @@ mod tests {
                 name: "P".to_string(),
                 slug: "p".to_string(),
                 created_at: now,
+                path_rules: Vec::new(),
+                keep_original_path: false,
+                fingerprint_rules: Vec::new(),
+                exception_sampling: Default::default(),
+                inbound_filters: Default::default(),
+                goals: Vec::new(),
             })
             .unwrap();
         store
@@ -319,6 +477,7 @@ mod tests {
                 created_at: now,
                 first_seen: Some(now),
                 last_seen: Some(now),
+                aliases: Vec::new(),
             })
             .unwrap();
         store
@@ -5,6 +5,7 @@ use std::path::Path;
 use polars::prelude::*;
 
 use super::event::{EventKind, StoredEvent};
+use super::regroup::{StoredException, StoredVisit};
 use super::tables::STORAGE_ADVICE;
 use crate::errors::{Result, ResultExt};
 
@@ -35,6 +36,7 @@ pub fn build_dataframe(events: &[StoredEvent]) -> PolarsResult<DataFrame> {
         "kind" => kind,
         "source" => col!(source),
         "pathname" => col!(pathname),
+        "original_pathname" => col!(original_pathname),
         "is_unique_user" => col!(is_unique_user),
         "is_unique_page" => col!(is_unique_page),
         "referrer_host" => col!(referrer_host),
@@ -48,15 +50,25 @@ pub fn build_dataframe(events: &[StoredEvent]) -> PolarsResult<DataFrame> {
         "utm_source" => col!(utm_source),
         "utm_medium" => col!(utm_medium),
         "utm_campaign" => col!(utm_campaign),
+        "channel" => col!(channel),
         "duration_ms" => col!(duration_ms),
+        "engaged_ms" => col!(engaged_ms),
+        "scroll_depth" => col!(scroll_depth),
         "event_name" => col!(event_name),
         "metadata_json" => col!(metadata_json),
+        "value" => col!(value),
+        "currency" => col!(currency),
         "app_version" => col!(app_version),
         "exc_type" => col!(exc_type),
         "exc_message" => col!(exc_message),
         "exc_stack" => col!(exc_stack),
         "exc_group" => col!(exc_group),
+        "exc_fingerprint" => col!(exc_fingerprint),
         "exc_handled" => col!(exc_handled),
+        "exc_level" => col!(exc_level),
+        "environment" => col!(environment),
+        "sample_rate" => col!(sample_rate),
+        "breadcrumbs_json" => col!(breadcrumbs_json),
     ]
 }
 
@@ -100,12 +112,12 @@ pub fn read_partition(path: &Path) -> Result<DataFrame> {
 }
 
 /// Recompute `exc_group` for the exception rows of the partition at `path`, using
-/// `remap(exc_type, exc_message, exc_stack)`. The file is rewritten (atomically)
-/// only when at least one group actually changes; returns the number of changed
-/// occurrences.
+/// `remap`; rows it returns `None` for keep their group. The file is rewritten
+/// (atomically) only when at least one group actually changes; returns the
+/// number of changed occurrences.
 pub(super) fn regroup_partition(
     path: &Path,
-    remap: &dyn Fn(&str, Option<&str>, Option<&str>) -> String,
+    remap: &dyn Fn(&StoredException<'_>) -> Option<String>,
 ) -> Result<usize> {
     let mut df = read_partition(path)?;
     let height = df.height();
@@ -121,30 +133,46 @@ pub(super) fn regroup_partition(
             .or_system_err(STORAGE_ADVICE)
     };
     let kind = as_str(&df, "kind")?;
+    let source = as_str(&df, "source")?;
     let exc_type = as_str(&df, "exc_type")?;
     let exc_message = as_str(&df, "exc_message")?;
     let exc_stack = as_str(&df, "exc_stack")?;
     let exc_group = as_str(&df, "exc_group")?;
+    let exc_fingerprint = fingerprint_column(&df)?;
 
-    let (kind, exc_type, exc_message, exc_stack, exc_group) = (
+    let (kind, source, exc_type, exc_message, exc_stack, exc_group, exc_fingerprint) = (
         kind.str().or_system_err(STORAGE_ADVICE)?,
+        source.str().or_system_err(STORAGE_ADVICE)?,
         exc_type.str().or_system_err(STORAGE_ADVICE)?,
         exc_message.str().or_system_err(STORAGE_ADVICE)?,
         exc_stack.str().or_system_err(STORAGE_ADVICE)?,
         exc_group.str().or_system_err(STORAGE_ADVICE)?,
+        exc_fingerprint.str().or_system_err(STORAGE_ADVICE)?,
     );
 
     let mut new_groups: Vec<Option<String>> = Vec::with_capacity(height);
     let mut changed = 0usize;
     for i in 0..height {
-        if kind.get(i) == Some(EventKind::Exception.as_str()) {
-            let group = remap(exc_type.get(i).unwrap_or(""), exc_message.get(i), exc_stack.get(i));
-            if exc_group.get(i) != Some(group.as_str()) {
-                changed += 1;
+        let group = (kind.get(i) == Some(EventKind::Exception.as_str()))
+            .then(|| {
+                remap(&StoredException {
+                    source: source.get(i).unwrap_or(""),
+                    group: exc_group.get(i),
+                    exc_type: exc_type.get(i).unwrap_or(""),
+                    message: exc_message.get(i),
+                    stack: exc_stack.get(i),
+                    fingerprint: exc_fingerprint.get(i),
+                })
+            })
+            .flatten();
+        match group {
+            Some(group) => {
+                if exc_group.get(i) != Some(group.as_str()) {
+                    changed += 1;
+                }
+                new_groups.push(Some(group));
             }
-            new_groups.push(Some(group));
-        } else {
-            new_groups.push(exc_group.get(i).map(str::to_string));
+            None => new_groups.push(exc_group.get(i).map(str::to_string)),
         }
     }
 
@@ -157,3 +185,167 @@ pub(super) fn regroup_partition(
     write_dataframe(&mut df, path)?;
     Ok(changed)
 }
+
+/// Call `visit` with every exception row of the partition at `path`.
+pub(super) fn visit_partition_exceptions(
+    path: &Path,
+    visit: &mut dyn FnMut(&StoredException<'_>),
+) -> Result<()> {
+    let df = read_partition(path)?;
+    if df.height() == 0 {
+        return Ok(());
+    }
+    let as_str = |df: &DataFrame, name: &str| -> Result<Column> {
+        df.column(name)
+            .and_then(|c| c.cast(&DataType::String))
+            .or_system_err(STORAGE_ADVICE)
+    };
+    let kind = as_str(&df, "kind")?;
+    let source = as_str(&df, "source")?;
+    let exc_type = as_str(&df, "exc_type")?;
+    let exc_message = as_str(&df, "exc_message")?;
+    let exc_stack = as_str(&df, "exc_stack")?;
+    let exc_group = as_str(&df, "exc_group")?;
+    let exc_fingerprint = fingerprint_column(&df)?;
+
+    let (kind, source, exc_type, exc_message, exc_stack, exc_group, exc_fingerprint) = (
+        kind.str().or_system_err(STORAGE_ADVICE)?,
+        source.str().or_system_err(STORAGE_ADVICE)?,
+        exc_type.str().or_system_err(STORAGE_ADVICE)?,
+        exc_message.str().or_system_err(STORAGE_ADVICE)?,
+        exc_stack.str().or_system_err(STORAGE_ADVICE)?,
+        exc_group.str().or_system_err(STORAGE_ADVICE)?,
+        exc_fingerprint.str().or_system_err(STORAGE_ADVICE)?,
+    );
+    for i in 0..df.height() {
+        if kind.get(i) == Some(EventKind::Exception.as_str()) {
+            visit(&StoredException {
+                source: source.get(i).unwrap_or(""),
+                group: exc_group.get(i),
+                exc_type: exc_type.get(i).unwrap_or(""),
+                message: exc_message.get(i),
+                stack: exc_stack.get(i),
+                fingerprint: exc_fingerprint.get(i),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The partition's `exc_fingerprint` column as strings; all nulls in a
+/// partition archived before client overrides were stored.
+fn fingerprint_column(df: &DataFrame) -> Result<Column> {
+    match df.column("exc_fingerprint") {
+        Ok(column) => column.cast(&DataType::String).or_system_err(STORAGE_ADVICE),
+        Err(_) => Ok(
+            Series::full_null("exc_fingerprint".into(), df.height(), &DataType::String)
+                .into_column(),
+        ),
+    }
+}
+
+/// Recompute `referrer_group` and `channel` for the rows of the partition at
+/// `path` through `rederive`. The file is rewritten (atomically) only when at
+/// least one row changes; returns the number of changed events.
+pub(super) fn rederive_attribution_partition(
+    path: &Path,
+    rederive: &dyn Fn(&StoredVisit<'_>) -> (Option<String>, Option<String>),
+) -> Result<usize> {
+    let mut df = read_partition(path)?;
+    if df.height() == 0 {
+        return Ok(0);
+    }
+    // Partitions archived before channels were stored have no `channel`.
+    let as_str = |df: &DataFrame, name: &str| -> Result<Column> {
+        match df.column(name) {
+            Ok(column) => column.cast(&DataType::String).or_system_err(STORAGE_ADVICE),
+            Err(_) => {
+                Ok(Series::full_null(name.into(), df.height(), &DataType::String).into_column())
+            }
+        }
+    };
+    let host = as_str(&df, "referrer_host")?;
+    let group = as_str(&df, "referrer_group")?;
+    let source = as_str(&df, "utm_source")?;
+    let medium = as_str(&df, "utm_medium")?;
+    let campaign = as_str(&df, "utm_campaign")?;
+    let channel = as_str(&df, "channel")?;
+    let (host, group, source, medium, campaign, channel) = (
+        host.str().or_system_err(STORAGE_ADVICE)?,
+        group.str().or_system_err(STORAGE_ADVICE)?,
+        source.str().or_system_err(STORAGE_ADVICE)?,
+        medium.str().or_system_err(STORAGE_ADVICE)?,
+        campaign.str().or_system_err(STORAGE_ADVICE)?,
+        channel.str().or_system_err(STORAGE_ADVICE)?,
+    );
+
+    let mut changed = 0usize;
+    let mut groups: Vec<Option<String>> = Vec::with_capacity(df.height());
+    let mut channels: Vec<Option<String>> = Vec::with_capacity(df.height());
+    for i in 0..df.height() {
+        let visit = StoredVisit {
+            referrer_host: host.get(i),
+            referrer_group: group.get(i),
+            utm_source: source.get(i),
+            utm_medium: medium.get(i),
+            utm_campaign: campaign.get(i),
+            channel: channel.get(i),
+        };
+        let (rederived_group, rederived_channel) = rederive(&visit);
+        if rederived_group.as_deref() != visit.referrer_group
+            || rederived_channel.as_deref() != visit.channel
+        {
+            changed += 1;
+        }
+        groups.push(rederived_group);
+        channels.push(rederived_channel);
+    }
+    if changed == 0 {
+        return Ok(0);
+    }
+
+    df.with_column(Series::new("referrer_group".into(), groups).into_column())
+        .or_system_err(STORAGE_ADVICE)?;
+    df.with_column(Series::new("channel".into(), channels).into_column())
+        .or_system_err(STORAGE_ADVICE)?;
+    write_dataframe(&mut df, path)?;
+    Ok(changed)
+}
+
+/// Rewrite the `source` column of the partition at `path` through `remap`, which
+/// returns the new source for rows that move. The file is rewritten (atomically)
+/// only when at least one row changes; returns the number of moved events.
+pub(super) fn reattribute_partition(
+    path: &Path,
+    remap: &dyn Fn(&str) -> Option<String>,
+) -> Result<usize> {
+    let mut df = read_partition(path)?;
+    if df.height() == 0 {
+        return Ok(0);
+    }
+    let source = df
+        .column("source")
+        .and_then(|c| c.cast(&DataType::String))
+        .or_system_err(STORAGE_ADVICE)?;
+    let source = source.str().or_system_err(STORAGE_ADVICE)?;
+
+    let mut changed = 0usize;
+    let sources: Vec<Option<String>> = source
+        .into_iter()
+        .map(|value| match value.and_then(remap) {
+            Some(canonical) => {
+                changed += 1;
+                Some(canonical)
+            }
+            None => value.map(str::to_string),
+        })
+        .collect();
+    if changed == 0 {
+        return Ok(0);
+    }
+
+    df.with_column(Series::new("source".into(), sources).into_column())
+        .or_system_err(STORAGE_ADVICE)?;
+    write_dataframe(&mut df, path)?;
+    Ok(changed)
+}
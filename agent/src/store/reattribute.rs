@@ -0,0 +1,465 @@
+//! Move stored events from alias sources to their canonical source.
+//!
+//! Hostname aliases only affect new events; this one-off pass rewrites the
+//! `source` of historical events — across both the redb hot store and the
+//! archived Parquet partitions — so reports over past periods merge too. Like
+//! re-grouping it is idempotent (moved events no longer match an alias, and
+//! partitions are rewritten atomically), so an interrupted pass can simply be
+//! repeated. The aliases' exception triage, merges, splits and releases move
+//! along with their events. Callers hold [`Store::lock_archive`] throughout.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use analytics_api::Release;
+use redb::ReadableTable;
+
+use super::Store;
+use super::event::StoredEvent;
+use super::merge::{GroupRedirect, GroupSplit};
+use super::regroup::parquet_files;
+use super::tables::{
+    EVENTS, EXCEPTION_REDIRECTS, EXCEPTION_SPLITS, EXCEPTION_TRIAGE, RELEASES, STORAGE_ADVICE,
+    group_key, release_key, triage_key,
+};
+use super::triage::{ExceptionTriage, scoped_group, unscoped_group};
+use crate::errors::{Result, ResultExt};
+
+/// The canonical source for an event's current source, or `None` to leave it.
+type Remap = dyn Fn(&str) -> Option<String>;
+
+impl Store {
+    /// Re-attribute events in the redb hot store. Returns the number moved.
+    pub fn reattribute_hot(&self, remap: &Remap) -> Result<usize> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        let changed;
+        {
+            let mut table = txn.open_table(EVENTS).or_system_err(STORAGE_ADVICE)?;
+
+            let mut updates: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let mut event: StoredEvent =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                if let Some(source) = remap(&event.source) {
+                    event.source = source;
+                    let bytes = serde_json::to_vec(&event).or_system_err(STORAGE_ADVICE)?;
+                    updates.push((key.value().to_vec(), bytes));
+                }
+            }
+
+            changed = updates.len();
+            for (key, bytes) in &updates {
+                table
+                    .insert(key.as_slice(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(changed)
+    }
+
+    /// Re-attribute events in the archived Parquet partitions, rewriting only the
+    /// partitions that change. Returns the number moved.
+    pub fn reattribute_cold(&self, parquet_dir: &str, remap: &Remap) -> Result<usize> {
+        let root = Path::new(parquet_dir);
+        if !root.exists() {
+            return Ok(0);
+        }
+        let mut total = 0;
+        for file in parquet_files(root) {
+            total += super::parquet::reattribute_partition(&file, remap)?;
+        }
+        Ok(total)
+    }
+
+    /// Move the exception triage, merges, splits and releases recorded for the
+    /// sources `remap` moves onto their canonical source, in one write
+    /// transaction. Moved triage joins `project_id` — the canonical source's
+    /// project, when it has one — and is [absorbed](ExceptionTriage::absorb)
+    /// into the record the group already has there. Otherwise the canonical
+    /// source's own records win, except that a release recorded through the API
+    /// replaces one registered on first sight. Returns the number moved.
+    pub fn reattribute_records(&self, remap: &Remap, project_id: Option<&str>) -> Result<usize> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        let mut moved = 0;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_TRIAGE)
+                .or_system_err(STORAGE_ADVICE)?;
+            let mut updates: Vec<(String, String, ExceptionTriage)> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let Some((owner, scoped)) = key.value().split_once('\u{1f}') else {
+                    continue;
+                };
+                let Some((group_id, canonical)) =
+                    unscoped_group(scoped).and_then(|(g, source)| Some((g, remap(source)?)))
+                else {
+                    continue;
+                };
+                let triage: ExceptionTriage =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                updates.push((
+                    key.value().to_string(),
+                    triage_key(
+                        project_id.unwrap_or(owner),
+                        &scoped_group(group_id, &canonical),
+                    ),
+                    triage,
+                ));
+            }
+            for (from, to, triage) in updates {
+                table.remove(from.as_str()).or_system_err(STORAGE_ADVICE)?;
+                let existing: Option<ExceptionTriage> = table
+                    .get(to.as_str())
+                    .or_system_err(STORAGE_ADVICE)?
+                    .map(|value| serde_json::from_slice(value.value()))
+                    .transpose()
+                    .or_system_err(STORAGE_ADVICE)?;
+                let triage = match existing {
+                    Some(mut existing) => {
+                        existing.absorb(triage);
+                        existing
+                    }
+                    None => triage,
+                };
+                let bytes = serde_json::to_vec(&triage).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(to.as_str(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+                moved += 1;
+            }
+        }
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_REDIRECTS)
+                .or_system_err(STORAGE_ADVICE)?;
+            let mut redirects: HashMap<String, GroupRedirect> = HashMap::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let redirect: GroupRedirect =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                redirects.insert(key.value().to_string(), redirect);
+            }
+            let mut changed: HashSet<String> = HashSet::new();
+            let mut removed: Vec<String> = Vec::new();
+            let mut canonicals: HashSet<String> = HashSet::new();
+            let aliases: Vec<(String, String)> = redirects
+                .iter()
+                .filter_map(|(key, redirect)| Some((key.clone(), remap(&redirect.source)?)))
+                .collect();
+            for (from, canonical) in aliases {
+                let Some(redirect) = redirects.remove(&from) else {
+                    continue;
+                };
+                removed.push(from);
+                let to = group_key(&canonical, &redirect.group_id);
+                if !redirects.contains_key(&to) {
+                    redirects.insert(
+                        to.clone(),
+                        GroupRedirect {
+                            source: canonical.clone(),
+                            ..redirect
+                        },
+                    );
+                    changed.insert(to);
+                    moved += 1;
+                }
+                canonicals.insert(canonical);
+            }
+            // Keep the canonical sources' redirects flat: a group merged on one
+            // side may be the target of a merge on the other.
+            let targets: HashMap<(String, String), String> = redirects
+                .values()
+                .filter(|redirect| canonicals.contains(&redirect.source))
+                .map(|r| ((r.source.clone(), r.group_id.clone()), r.target.clone()))
+                .collect();
+            for (key, redirect) in redirects.iter_mut() {
+                if !canonicals.contains(&redirect.source) {
+                    continue;
+                }
+                let mut seen = HashSet::from([redirect.group_id.clone()]);
+                let mut target = redirect.target.clone();
+                while let Some(next) = targets.get(&(redirect.source.clone(), target.clone())) {
+                    if !seen.insert(target.clone()) {
+                        break;
+                    }
+                    target = next.clone();
+                }
+                if target != redirect.target {
+                    redirect.target = target;
+                    redirect.applied = false;
+                    changed.insert(key.clone());
+                }
+            }
+            for key in &removed {
+                table.remove(key.as_str()).or_system_err(STORAGE_ADVICE)?;
+            }
+            for key in changed {
+                let redirect = &redirects[&key];
+                if redirect.target == redirect.group_id {
+                    table.remove(key.as_str()).or_system_err(STORAGE_ADVICE)?;
+                    continue;
+                }
+                let bytes = serde_json::to_vec(redirect).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(key.as_str(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_SPLITS)
+                .or_system_err(STORAGE_ADVICE)?;
+            let mut updates: Vec<(String, GroupSplit)> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let split: GroupSplit =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                if let Some(source) = remap(&split.source) {
+                    updates.push((key.value().to_string(), GroupSplit { source, ..split }));
+                }
+            }
+            for (from, split) in updates {
+                table.remove(from.as_str()).or_system_err(STORAGE_ADVICE)?;
+                let to = group_key(&split.source, &split.target);
+                if table
+                    .get(to.as_str())
+                    .or_system_err(STORAGE_ADVICE)?
+                    .is_none()
+                {
+                    let bytes = serde_json::to_vec(&split).or_system_err(STORAGE_ADVICE)?;
+                    table
+                        .insert(to.as_str(), bytes.as_slice())
+                        .or_system_err(STORAGE_ADVICE)?;
+                    moved += 1;
+                }
+            }
+        }
+        {
+            let mut table = txn.open_table(RELEASES).or_system_err(STORAGE_ADVICE)?;
+            let mut updates: Vec<(String, Release)> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let release: Release =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                if let Some(source) = remap(&release.source) {
+                    updates.push((key.value().to_string(), Release { source, ..release }));
+                }
+            }
+            for (from, release) in updates {
+                table.remove(from.as_str()).or_system_err(STORAGE_ADVICE)?;
+                let to = release_key(&release.source, &release.version);
+                let existing: Option<Release> = table
+                    .get(to.as_str())
+                    .or_system_err(STORAGE_ADVICE)?
+                    .map(|value| serde_json::from_slice(value.value()))
+                    .transpose()
+                    .or_system_err(STORAGE_ADVICE)?;
+                if existing.is_none_or(|existing| existing.auto && !release.auto) {
+                    let bytes = serde_json::to_vec(&release).or_system_err(STORAGE_ADVICE)?;
+                    table
+                        .insert(to.as_str(), bytes.as_slice())
+                        .or_system_err(STORAGE_ADVICE)?;
+                    moved += 1;
+                }
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::EventKind;
+    use analytics_api::TriageComment;
+    use chrono::{DateTime, Utc};
+
+    fn page(received_ms: i64, source: &str) -> StoredEvent {
+        StoredEvent {
+            received_ms,
+            created_ms: received_ms,
+            kind: EventKind::PageLoad,
+            source: source.into(),
+            pathname: Some("/".into()),
+            ..Default::default()
+        }
+    }
+
+    fn to_canonical(source: &str) -> Option<String> {
+        (source == "https://example.co.uk").then(|| "https://example.com".to_string())
+    }
+
+    #[test]
+    fn moves_hot_and_cold_events_to_the_canonical_source() {
+        let base =
+            std::env::temp_dir().join(format!("analytics-reattribute-{}", std::process::id()));
+        let store = Store::open(base.with_extension("redb")).unwrap();
+        store
+            .append_events(&[
+                page(1_000, "https://example.co.uk"),
+                page(2_000, "https://example.com"),
+                page(3_000, "https://other.org"),
+            ])
+            .unwrap();
+        let file = base
+            .join("2025")
+            .join("01")
+            .join("01")
+            .join("events-1.parquet");
+        super::super::write_partition(
+            &[
+                page(500, "https://example.co.uk"),
+                page(600, "https://other.org"),
+            ],
+            &file,
+        )
+        .unwrap();
+
+        assert_eq!(store.reattribute_hot(&to_canonical).unwrap(), 1);
+        let parquet_dir = base.to_str().unwrap();
+        assert_eq!(
+            store.reattribute_cold(parquet_dir, &to_canonical).unwrap(),
+            1
+        );
+
+        let hot: Vec<String> = store
+            .all_events()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.source)
+            .collect();
+        assert_eq!(
+            hot,
+            vec![
+                "https://example.com",
+                "https://example.com",
+                "https://other.org"
+            ]
+        );
+        let df = super::super::read_partition(&file).unwrap();
+        let cold = df.column("source").unwrap().str().unwrap();
+        assert_eq!(cold.get(0), Some("https://example.com"));
+        assert_eq!(cold.get(1), Some("https://other.org"));
+
+        // Nothing left to move on a second pass.
+        assert_eq!(store.reattribute_hot(&to_canonical).unwrap(), 0);
+        assert_eq!(
+            store.reattribute_cold(parquet_dir, &to_canonical).unwrap(),
+            0
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(base.with_extension("redb"));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn moves_triaged_and_merged_groups_to_the_canonical_source() {
+        let path = std::env::temp_dir().join(format!(
+            "analytics-reattribute-records-{}.redb",
+            std::process::id()
+        ));
+        let store = Store::open(&path).unwrap();
+        let (alias, canonical) = ("https://example.co.uk", "https://example.com");
+        let at = |ms: i64| DateTime::from_timestamp_millis(ms).unwrap();
+        let triage = |resolved_ms: Option<i64>, comment: &str, written_ms: i64| ExceptionTriage {
+            resolved_at: resolved_ms.map(at),
+            muted_at: None,
+            snooze: None,
+            assignee: None,
+            comments: vec![TriageComment {
+                author: None,
+                body: comment.into(),
+                created_at: at(written_ms),
+            }],
+            updated_at: at(written_ms),
+            updated_by: None,
+        };
+        let redirect = |source: &str, group_id: &str, target: &str| GroupRedirect {
+            source: source.into(),
+            group_id: group_id.into(),
+            target: target.into(),
+            merged_at: Utc::now(),
+            merged_by: None,
+            applied: true,
+        };
+
+        // The alias resolved `a` and merged `b` into it; the canonical source
+        // left `a` open and merged it into `c`.
+        store
+            .put_triage(
+                "p2",
+                &scoped_group("a", alias),
+                &triage(Some(1_000), "alias", 1),
+            )
+            .unwrap();
+        store
+            .put_triage(
+                "p1",
+                &scoped_group("a", canonical),
+                &triage(None, "canonical", 2),
+            )
+            .unwrap();
+        store
+            .merge_exception_groups(None, &[redirect(alias, "b", "a")], None)
+            .unwrap();
+        store
+            .merge_exception_groups(None, &[redirect(canonical, "a", "c")], None)
+            .unwrap();
+        store
+            .register_release_if_absent(alias, "1.0", Utc::now())
+            .unwrap();
+
+        assert_eq!(
+            store
+                .reattribute_records(&to_canonical, Some("p1"))
+                .unwrap(),
+            3
+        );
+
+        assert!(
+            store
+                .get_triage("p2", &scoped_group("a", alias))
+                .unwrap()
+                .is_none()
+        );
+        let merged = store
+            .get_triage("p1", &scoped_group("a", canonical))
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.resolved_at, None);
+        let bodies: Vec<&str> = merged.comments.iter().map(|c| c.body.as_str()).collect();
+        assert_eq!(bodies, vec!["alias", "canonical"]);
+
+        let mut redirects: Vec<(String, String, String, bool)> = store
+            .list_group_redirects()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.source, r.group_id, r.target, r.applied))
+            .collect();
+        redirects.sort();
+        assert_eq!(
+            redirects,
+            vec![
+                (canonical.into(), "a".into(), "c".into(), true),
+                // Flattened onto the canonical source's merge, and left for the
+                // re-grouping pass to apply.
+                (canonical.into(), "b".into(), "c".into(), false),
+            ]
+        );
+        let releases: Vec<String> = store
+            .list_releases()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.source)
+            .collect();
+        assert_eq!(releases, vec![canonical]);
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,4 +1,5 @@
-//! Re-fingerprint stored exceptions when the grouping rules change.
+//! Re-fingerprint stored exceptions, and re-derive referrer groups, when the
+//! grouping rules change.
 //!
 //! The grouping-rules version that was last applied to the data is stamped in the
 //! `meta` table. When the running binary reports a different version, every stored
@@ -8,9 +9,16 @@
 //! group yields the same value, and partitions are rewritten atomically), so a
 //! crash part-way through simply repeats the work on the next start.
 //!
-//! Note: a client-supplied fingerprint override (`ExceptionReport::fingerprint`) is
-//! not persisted, so re-grouping recomputes purely from the stored
-//! `(type, message, stack)`. Overrides therefore apply only at ingest time.
+//! Groups are recomputed from the stored `(source, type, message, stack)` and
+//! the client's fingerprint override (`ExceptionReport::fingerprint`), which is
+//! stored with each occurrence (occurrences recorded before it was have none).
+//! Project fingerprint rules, looked up by source, apply to history too:
+//! changing a project's rules, or moving a source between projects, re-groups
+//! just the sources affected.
+//!
+//! Referrer groups and channels follow the same scheme, keyed by the referrer
+//! and channel rules versions and recomputed from the stored `referrer_host` and
+//! UTM tags.
 
 use std::path::{Path, PathBuf};
 
@@ -18,33 +26,89 @@ use redb::{ReadableDatabase, ReadableTable};
 
 use super::Store;
 use super::event::{EventKind, StoredEvent};
-use super::tables::{EVENTS, META, META_FINGERPRINT_VERSION, STORAGE_ADVICE, u32_from_be};
+use super::tables::{
+    EVENTS, META, META_CHANNEL_VERSION, META_FINGERPRINT_VERSION, META_REFERRER_VERSION,
+    STORAGE_ADVICE, u32_from_be,
+};
 use crate::errors::{Result, ResultExt};
 
-/// Recomputes a group id from an exception's stored `(type, message, stack)`.
-type Regroup = dyn Fn(&str, Option<&str>, Option<&str>) -> String;
+/// A stored exception occurrence, as a re-grouping pass sees it.
+pub struct StoredException<'a> {
+    pub source: &'a str,
+    /// The group the occurrence is currently stored under.
+    pub group: Option<&'a str>,
+    pub exc_type: &'a str,
+    pub message: Option<&'a str>,
+    pub stack: Option<&'a str>,
+    /// The client's grouping override, if it sent one.
+    pub fingerprint: Option<&'a str>,
+}
+
+/// Recomputes a stored exception's group id, or `None` to leave the occurrence
+/// in its current group.
+type Regroup = dyn Fn(&StoredException<'_>) -> Option<String>;
+
+/// A stored event's attribution, as a re-derivation pass sees it.
+pub struct StoredVisit<'a> {
+    pub referrer_host: Option<&'a str>,
+    /// The referrer group the event is currently stored with.
+    pub referrer_group: Option<&'a str>,
+    pub utm_source: Option<&'a str>,
+    pub utm_medium: Option<&'a str>,
+    pub utm_campaign: Option<&'a str>,
+    /// The channel the event is currently stored with; only hits have one.
+    pub channel: Option<&'a str>,
+}
+
+/// Recomputes a stored event's `(referrer_group, channel)`.
+type Attribution = dyn Fn(&StoredVisit<'_>) -> (Option<String>, Option<String>);
 
 impl Store {
     /// The grouping-rules version last applied to the stored data (`0` if never).
     pub fn fingerprint_version(&self) -> Result<u32> {
+        self.meta_version(META_FINGERPRINT_VERSION)
+    }
+
+    /// Record the grouping-rules version now applied to the stored data.
+    pub fn set_fingerprint_version(&self, version: u32) -> Result<()> {
+        self.set_meta_version(META_FINGERPRINT_VERSION, version)
+    }
+
+    /// The referrer rules version last applied to the stored data (`0` if never).
+    pub fn referrer_version(&self) -> Result<u32> {
+        self.meta_version(META_REFERRER_VERSION)
+    }
+
+    /// Record the referrer rules version now applied to the stored data.
+    pub fn set_referrer_version(&self, version: u32) -> Result<()> {
+        self.set_meta_version(META_REFERRER_VERSION, version)
+    }
+
+    /// The channel rules version last applied to the stored data (`0` if never).
+    pub fn channel_version(&self) -> Result<u32> {
+        self.meta_version(META_CHANNEL_VERSION)
+    }
+
+    /// Record the channel rules version now applied to the stored data.
+    pub fn set_channel_version(&self, version: u32) -> Result<()> {
+        self.set_meta_version(META_CHANNEL_VERSION, version)
+    }
+
+    pub(super) fn meta_version(&self, key: &str) -> Result<u32> {
         let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
         let table = txn.open_table(META).or_system_err(STORAGE_ADVICE)?;
-        match table
-            .get(META_FINGERPRINT_VERSION)
-            .or_system_err(STORAGE_ADVICE)?
-        {
+        match table.get(key).or_system_err(STORAGE_ADVICE)? {
             Some(value) => Ok(u32_from_be(value.value())),
             None => Ok(0),
         }
     }
 
-    /// Record the grouping-rules version now applied to the stored data.
-    pub fn set_fingerprint_version(&self, version: u32) -> Result<()> {
+    pub(super) fn set_meta_version(&self, key: &str, version: u32) -> Result<()> {
         let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
         {
             let mut table = txn.open_table(META).or_system_err(STORAGE_ADVICE)?;
             table
-                .insert(META_FINGERPRINT_VERSION, version.to_be_bytes().as_slice())
+                .insert(key, version.to_be_bytes().as_slice())
                 .or_system_err(STORAGE_ADVICE)?;
         }
         txn.commit().or_system_err(STORAGE_ADVICE)?;
@@ -70,11 +134,16 @@ impl Store {
                 if event.kind != EventKind::Exception {
                     continue;
                 }
-                let group = remap(
-                    event.exc_type.as_deref().unwrap_or(""),
-                    event.exc_message.as_deref(),
-                    event.exc_stack.as_deref(),
-                );
+                let Some(group) = remap(&StoredException {
+                    source: &event.source,
+                    group: event.exc_group.as_deref(),
+                    exc_type: event.exc_type.as_deref().unwrap_or(""),
+                    message: event.exc_message.as_deref(),
+                    stack: event.exc_stack.as_deref(),
+                    fingerprint: event.exc_fingerprint.as_deref(),
+                }) else {
+                    continue;
+                };
                 if event.exc_group.as_deref() != Some(group.as_str()) {
                     event.exc_group = Some(group);
                     let bytes = serde_json::to_vec(&event).or_system_err(STORAGE_ADVICE)?;
@@ -107,11 +176,71 @@ impl Store {
         }
         Ok(total)
     }
+
+    /// Recompute `referrer_group` and `channel` for every event in the redb hot
+    /// store, rewriting only the events whose attribution changes. Returns the
+    /// number of changed events.
+    pub fn rederive_hot_attribution(&self, rederive: &Attribution) -> Result<usize> {
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        let changed;
+        {
+            let mut table = txn.open_table(EVENTS).or_system_err(STORAGE_ADVICE)?;
+
+            let mut updates: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+            for item in table.iter().or_system_err(STORAGE_ADVICE)? {
+                let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+                let mut event: StoredEvent =
+                    serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+                let (group, channel) = rederive(&StoredVisit {
+                    referrer_host: event.referrer_host.as_deref(),
+                    referrer_group: event.referrer_group.as_deref(),
+                    utm_source: event.utm_source.as_deref(),
+                    utm_medium: event.utm_medium.as_deref(),
+                    utm_campaign: event.utm_campaign.as_deref(),
+                    channel: event.channel.as_deref(),
+                });
+                if event.referrer_group != group || event.channel != channel {
+                    event.referrer_group = group;
+                    event.channel = channel;
+                    let bytes = serde_json::to_vec(&event).or_system_err(STORAGE_ADVICE)?;
+                    updates.push((key.value().to_vec(), bytes));
+                }
+            }
+
+            changed = updates.len();
+            for (key, bytes) in &updates {
+                table
+                    .insert(key.as_slice(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(changed)
+    }
+
+    /// Recompute `referrer_group` and `channel` in the archived Parquet
+    /// partitions, rewriting only the partitions that actually change. Returns
+    /// the number of changed events.
+    pub fn rederive_cold_attribution(
+        &self,
+        parquet_dir: &str,
+        rederive: &Attribution,
+    ) -> Result<usize> {
+        let root = Path::new(parquet_dir);
+        if !root.exists() {
+            return Ok(0);
+        }
+        let mut total = 0;
+        for file in parquet_files(root) {
+            total += super::parquet::rederive_attribution_partition(&file, rederive)?;
+        }
+        Ok(total)
+    }
 }
 
 /// Every `*.parquet` partition under `root` (recursively); `.tmp` writes-in-progress
 /// are skipped by the extension filter.
-fn parquet_files(root: &Path) -> Vec<PathBuf> {
+pub(super) fn parquet_files(root: &Path) -> Vec<PathBuf> {
     let mut out = Vec::new();
     let mut stack = vec![root.to_path_buf()];
     while let Some(dir) = stack.pop() {
@@ -191,7 +320,7 @@ mod tests {
         // Remap everything to a constant group; only the two exceptions are touched,
         // and only the one whose group actually differs is counted as changed.
         let changed = store
-            .regroup_hot_exceptions(&|_, _, _| "fresh".to_string())
+            .regroup_hot_exceptions(&|_| Some("fresh".to_string()))
             .unwrap();
         assert_eq!(changed, 1);
 
@@ -221,7 +350,7 @@ mod tests {
             .unwrap();
 
         let changed = store
-            .regroup_cold_exceptions(parquet.to_str().unwrap(), &|_, _, _| "fresh".to_string())
+            .regroup_cold_exceptions(parquet.to_str().unwrap(), &|_| Some("fresh".to_string()))
             .unwrap();
         assert_eq!(changed, 2);
 
@@ -232,7 +361,7 @@ mod tests {
         // A second pass is a no-op now that every group already matches.
         assert_eq!(
             store
-                .regroup_cold_exceptions(parquet.to_str().unwrap(), &|_, _, _| "fresh".to_string())
+                .regroup_cold_exceptions(parquet.to_str().unwrap(), &|_| Some("fresh".to_string()))
                 .unwrap(),
             0
         );
@@ -241,4 +370,101 @@ mod tests {
         let _ = std::fs::remove_file(&redb);
         let _ = std::fs::remove_dir_all(&parquet);
     }
+
+    #[test]
+    fn rederiving_attribution_recomputes_hot_and_cold_groups_and_channels() {
+        let redb = temp_path("referrers.redb");
+        let parquet = temp_path("referrers-parquet");
+        let store = Store::open(&redb).unwrap();
+        assert_eq!(store.referrer_version().unwrap(), 0);
+        assert_eq!(store.channel_version().unwrap(), 0);
+
+        let referred = |received_ms: i64, host: Option<&str>| StoredEvent {
+            received_ms,
+            created_ms: received_ms,
+            kind: EventKind::PageLoad,
+            source: "https://example.com".into(),
+            referrer_host: host.map(str::to_string),
+            channel: Some("Referral".into()),
+            ..Default::default()
+        };
+        let unattributed = StoredEvent {
+            channel: None,
+            ..referred(3_000, None)
+        };
+        store
+            .append_events(&[
+                referred(1_000, Some("chatgpt.com")),
+                referred(2_000, None),
+                unattributed,
+            ])
+            .unwrap();
+        let file = parquet
+            .join("2025")
+            .join("01")
+            .join("01")
+            .join("events-1.parquet");
+        super::super::write_partition(
+            &[
+                referred(500, Some("chatgpt.com")),
+                referred(600, Some("blog.io")),
+            ],
+            &file,
+        )
+        .unwrap();
+
+        let rederive = |visit: &StoredVisit<'_>| {
+            let group = match visit.referrer_host {
+                Some(host) => (host == "chatgpt.com").then(|| "AI".to_string()),
+                None => visit.referrer_group.map(str::to_string),
+            };
+            let channel = visit
+                .channel
+                .map(|_| if group.is_some() { "AI" } else { "Direct" }.to_string());
+            (group, channel)
+        };
+        assert_eq!(store.rederive_hot_attribution(&rederive).unwrap(), 2);
+        assert_eq!(
+            store
+                .rederive_cold_attribution(parquet.to_str().unwrap(), &rederive)
+                .unwrap(),
+            2
+        );
+
+        let hot: Vec<(Option<String>, Option<String>)> = store
+            .all_events()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.referrer_group, e.channel))
+            .collect();
+        assert_eq!(
+            hot,
+            vec![
+                (Some("AI".to_string()), Some("AI".to_string())),
+                (None, Some("Direct".to_string())),
+                (None, None),
+            ]
+        );
+        let df = super::super::read_partition(&file).unwrap();
+        let column = |name: &str| {
+            df.column(name)
+                .unwrap()
+                .cast(&polars::prelude::DataType::String)
+                .unwrap()
+        };
+        let (groups, channels) = (column("referrer_group"), column("channel"));
+        assert_eq!(groups.str().unwrap().get(0), Some("AI"));
+        assert_eq!(groups.str().unwrap().get(1), None);
+        assert_eq!(channels.str().unwrap().get(0), Some("AI"));
+        assert_eq!(channels.str().unwrap().get(1), Some("Direct"));
+
+        store.set_referrer_version(2).unwrap();
+        store.set_channel_version(7).unwrap();
+        assert_eq!(store.referrer_version().unwrap(), 2);
+        assert_eq!(store.channel_version().unwrap(), 7);
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+        let _ = std::fs::remove_dir_all(&parquet);
+    }
 }
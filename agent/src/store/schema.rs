@@ -4,9 +4,10 @@
 //! any pending migration steps in order. A database newer than this build is
 //! rejected rather than silently misread.
 
+use analytics_api::TriageComment;
 use chrono::{DateTime, Utc};
 use redb::{Database, ReadableDatabase, ReadableTable};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use super::tables::{
     EXCEPTION_TRIAGE, META, META_SCHEMA_VERSION, OPEN_ADVICE, STORAGE_ADVICE, u32_from_be,
@@ -16,7 +17,7 @@ use crate::errors::{Result, ResultExt};
 
 /// The current on-disk schema version. Bump this and add an [`apply`] arm whenever
 /// the stored layout changes incompatibly.
-pub(super) const SCHEMA_VERSION: u32 = 2;
+pub(super) const SCHEMA_VERSION: u32 = 3;
 
 /// Ensure the database is at [`SCHEMA_VERSION`], applying migrations in order.
 pub(super) fn migrate(db: &Database) -> Result<()> {
@@ -59,6 +60,9 @@ fn apply(db: &Database, version: u32) -> Result<()> {
         // so a later occurrence reopens the group automatically) and a `muted_at`
         // suppression flag.
         2 => migrate_triage_to_axes(db),
+        // v3 replaces the exception-triage `note` with a comment thread (the
+        // note becomes its first comment) and adds an assignee and snoozes.
+        3 => migrate_triage_notes_to_comments(db),
         other => Err(human_errors::system(
             format!("No migration is defined for schema version {other}."),
             &["This is a bug; please report it with the server version."],
@@ -78,10 +82,70 @@ struct LegacyTriage {
     updated_by: Option<String>,
 }
 
+/// The v2 triage record: the two axes and a single free-text `note`.
+#[derive(Serialize, Deserialize)]
+struct TriageV2 {
+    #[serde(default)]
+    resolved_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    muted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    note: Option<String>,
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    updated_by: Option<String>,
+}
+
 /// Rewrite every triage record from the collapsed `status` to the resolution +
 /// suppression axes. A `resolved` record's `updated_at` was when it was resolved,
 /// so it becomes the `resolved_at` anchor; likewise `ignored` becomes `muted_at`.
 fn migrate_triage_to_axes(db: &Database) -> Result<()> {
+    rewrite_triage(db, |legacy: LegacyTriage| {
+        let (resolved_at, muted_at) = match legacy.status.as_str() {
+            "resolved" => (Some(legacy.updated_at), None),
+            "ignored" => (None, Some(legacy.updated_at)),
+            _ => (None, None),
+        };
+        TriageV2 {
+            resolved_at,
+            muted_at,
+            note: legacy.note,
+            updated_at: legacy.updated_at,
+            updated_by: legacy.updated_by,
+        }
+    })
+}
+
+/// Rewrite every triage record's `note` as the first comment of its thread,
+/// attributed to whoever last updated the record, when they did.
+fn migrate_triage_notes_to_comments(db: &Database) -> Result<()> {
+    rewrite_triage(db, |v2: TriageV2| ExceptionTriage {
+        resolved_at: v2.resolved_at,
+        muted_at: v2.muted_at,
+        snooze: None,
+        assignee: None,
+        comments: v2
+            .note
+            .filter(|note| !note.trim().is_empty())
+            .map(|body| TriageComment {
+                author: v2.updated_by.clone(),
+                body,
+                created_at: v2.updated_at,
+            })
+            .into_iter()
+            .collect(),
+        updated_at: v2.updated_at,
+        updated_by: v2.updated_by,
+    })
+}
+
+/// Rewrite every triage record from layout `F` to layout `T` in one
+/// transaction.
+fn rewrite_triage<F, T>(db: &Database, migrate: impl Fn(F) -> T) -> Result<()>
+where
+    F: DeserializeOwned,
+    T: Serialize,
+{
     let txn = db.begin_write().or_system_err(STORAGE_ADVICE)?;
     {
         let mut table = txn
@@ -90,21 +154,8 @@ fn migrate_triage_to_axes(db: &Database) -> Result<()> {
         let mut rewrites: Vec<(String, Vec<u8>)> = Vec::new();
         for item in table.iter().or_system_err(STORAGE_ADVICE)? {
             let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
-            let legacy: LegacyTriage =
-                serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
-            let (resolved_at, muted_at) = match legacy.status.as_str() {
-                "resolved" => (Some(legacy.updated_at), None),
-                "ignored" => (None, Some(legacy.updated_at)),
-                _ => (None, None),
-            };
-            let migrated = ExceptionTriage {
-                resolved_at,
-                muted_at,
-                note: legacy.note,
-                updated_at: legacy.updated_at,
-                updated_by: legacy.updated_by,
-            };
-            let bytes = serde_json::to_vec(&migrated).or_system_err(STORAGE_ADVICE)?;
+            let old: F = serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+            let bytes = serde_json::to_vec(&migrate(old)).or_system_err(STORAGE_ADVICE)?;
             rewrites.push((key.value().to_string(), bytes));
         }
         for (key, bytes) in rewrites {
@@ -229,7 +280,10 @@ mod tests {
         let res = read(triage_key("p", "res"));
         assert_eq!(res.resolved_at, Some(updated_at), "resolved anchors at updated_at");
         assert!(res.muted_at.is_none());
-        assert_eq!(res.note.as_deref(), Some("n"), "note preserved");
+        assert_eq!(res.comments.len(), 1, "note preserved as a comment");
+        assert_eq!(res.comments[0].body, "n");
+        assert_eq!(res.comments[0].author.as_deref(), Some("admin"));
+        assert_eq!(res.comments[0].created_at, updated_at);
 
         let ign = read(triage_key("p", "ign"));
         assert_eq!(ign.muted_at, Some(updated_at), "ignored becomes muted");
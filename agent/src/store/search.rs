@@ -0,0 +1,203 @@
+//! The full-text term index over archived exceptions.
+//!
+//! The compactor records the search terms of every exception it archives, one
+//! entry per `(term, source, group)`, so a search needn't read the archive:
+//! a prefix scan over the sorted keys finds every group mentioning a word that
+//! starts with the searched one. The hot window is small enough to be scanned
+//! directly. Which terms an occurrence has is up to the caller (see
+//! `ingest::search`); this module only stores them.
+//!
+//! The index goes stale when archived occurrences move to another group, so the
+//! re-grouping and re-attribution passes [`rebuild`](Store::rebuild_exception_terms)
+//! it from the archive.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+
+use redb::{ReadableDatabase, ReadableTable};
+
+use super::Store;
+use super::regroup::{StoredException, parquet_files};
+use super::tables::{EXCEPTION_TERMS, META_SEARCH_VERSION, STORAGE_ADVICE, term_key};
+use crate::errors::{Result, ResultExt};
+
+/// The search terms of a stored exception occurrence.
+type Terms = dyn Fn(&StoredException<'_>) -> Vec<String>;
+
+impl Store {
+    /// The version the exception indexes were built with (`0` if never).
+    pub fn search_version(&self) -> Result<u32> {
+        self.meta_version(META_SEARCH_VERSION)
+    }
+
+    /// Record the version the exception indexes are now built with.
+    pub fn set_search_version(&self, version: u32) -> Result<()> {
+        self.set_meta_version(META_SEARCH_VERSION, version)
+    }
+
+    /// Index `entries`, each a `(term, source, group)`, alongside those
+    /// already indexed.
+    pub fn add_exception_terms(&self, entries: &BTreeSet<(String, String, String)>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_TERMS)
+                .or_system_err(STORAGE_ADVICE)?;
+            for (term, source, group) in entries {
+                table
+                    .insert(term_key(term, source, group).as_str(), ())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(())
+    }
+
+    /// Replace the whole index with the terms of every exception in the
+    /// archived Parquet partitions. Returns the number of entries indexed.
+    /// Call with the archive lock held, so compaction can't archive (or
+    /// retention drop) partitions mid-rebuild.
+    pub fn rebuild_exception_terms(&self, parquet_dir: &str, terms: &Terms) -> Result<usize> {
+        let mut entries: BTreeSet<(String, String, String)> = BTreeSet::new();
+        let root = Path::new(parquet_dir);
+        if root.exists() {
+            for file in parquet_files(root) {
+                super::parquet::visit_partition_exceptions(&file, &mut |exception| {
+                    let Some(group) = exception.group else {
+                        return;
+                    };
+                    for term in terms(exception) {
+                        entries.insert((term, exception.source.to_string(), group.to_string()));
+                    }
+                })?;
+            }
+        }
+
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        txn.delete_table(EXCEPTION_TERMS)
+            .or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_TERMS)
+                .or_system_err(STORAGE_ADVICE)?;
+            for (term, source, group) in &entries {
+                table
+                    .insert(term_key(term, source, group).as_str(), ())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(entries.len())
+    }
+
+    /// The `(source, group)` of every indexed exception group with a term
+    /// starting with `prefix`.
+    pub fn exception_groups_with_term(&self, prefix: &str) -> Result<HashSet<(String, String)>> {
+        let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
+        let table = txn
+            .open_table(EXCEPTION_TERMS)
+            .or_system_err(STORAGE_ADVICE)?;
+        let mut out = HashSet::new();
+        for item in table.range(prefix..).or_system_err(STORAGE_ADVICE)? {
+            let (key, _) = item.or_system_err(STORAGE_ADVICE)?;
+            let key = key.value();
+            if !key.starts_with(prefix) {
+                break;
+            }
+            let mut parts = key.splitn(3, '\u{1f}').skip(1);
+            if let (Some(source), Some(group)) = (parts.next(), parts.next()) {
+                out.insert((source.to_string(), group.to_string()));
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use crate::store::{EventKind, StoredEvent, write_partition};
+
+    fn temp_path(suffix: &str) -> PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+        std::env::temp_dir().join(format!(
+            "analytics-search-{}-{}-{}",
+            std::process::id(),
+            n,
+            suffix
+        ))
+    }
+
+    fn entry(term: &str, source: &str, group: &str) -> (String, String, String) {
+        (term.into(), source.into(), group.into())
+    }
+
+    #[test]
+    fn prefix_scans_find_indexed_groups_until_rebuilt() {
+        let redb = temp_path("terms.redb");
+        let parquet = temp_path("terms-parquet");
+        let store = Store::open(&redb).unwrap();
+        store
+            .add_exception_terms(&BTreeSet::from([
+                entry("checkout", "https://a.com", "g1"),
+                entry("check", "https://a.com", "g2"),
+                entry("cart", "https://b.com", "g3"),
+            ]))
+            .unwrap();
+
+        let found = |prefix: &str| {
+            let mut groups: Vec<String> = store
+                .exception_groups_with_term(prefix)
+                .unwrap()
+                .into_iter()
+                .map(|(_, group)| group)
+                .collect();
+            groups.sort();
+            groups
+        };
+        assert_eq!(found("check"), vec!["g1", "g2"]);
+        assert_eq!(found("checko"), vec!["g1"]);
+        assert!(found("zebra").is_empty());
+
+        // A rebuild replaces the index with what the archive holds.
+        let file = parquet
+            .join("2025")
+            .join("01")
+            .join("01")
+            .join("events-1.parquet");
+        write_partition(
+            &[StoredEvent {
+                kind: EventKind::Exception,
+                source: "https://a.com".into(),
+                exc_type: Some("TypeError".into()),
+                exc_message: Some("zebra".into()),
+                exc_group: Some("g9".into()),
+                ..Default::default()
+            }],
+            &file,
+        )
+        .unwrap();
+        let terms = |exception: &StoredException<'_>| {
+            exception.message.into_iter().map(str::to_string).collect()
+        };
+        assert_eq!(
+            store
+                .rebuild_exception_terms(parquet.to_str().unwrap(), &terms)
+                .unwrap(),
+            1
+        );
+        assert_eq!(found("zeb"), vec!["g9"]);
+        assert!(found("check").is_empty());
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+        let _ = std::fs::remove_dir_all(&parquet);
+    }
+}
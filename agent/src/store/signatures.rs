@@ -0,0 +1,232 @@
+//! The similarity index over exception groups: one signature per
+//! `(source, group)`, so the groups like a given one are found without reading
+//! their occurrences.
+//!
+//! The ingest writer records the signature of each group's latest occurrence
+//! as it stores it. The index goes stale when occurrences move to another
+//! group, so the re-grouping and re-attribution passes
+//! [`rebuild`](Store::rebuild_group_signatures) it from the hot store and the
+//! archive. How an occurrence is reduced to a signature is up to the caller
+//! (see `ingest::similarity`); this module only stores them.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use redb::{ReadableDatabase, ReadableTable};
+use serde::{Deserialize, Serialize};
+
+use super::Store;
+use super::regroup::{StoredException, parquet_files};
+use super::tables::{EXCEPTION_SIGNATURES, STORAGE_ADVICE, group_key};
+use super::{EventKind, StoredEvent};
+use crate::errors::{Result, ResultExt};
+
+/// A group's signature and the type and message it was taken from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupSignature {
+    pub exc_type: String,
+    /// The summary line of the occurrence's message.
+    pub message: String,
+    pub minhash: Vec<u64>,
+}
+
+/// The signature of a stored exception occurrence, if it has one.
+type Sign = dyn Fn(&StoredException<'_>) -> Option<GroupSignature>;
+
+impl Store {
+    /// Record `signatures`, keyed by `(source, group)`, replacing those the
+    /// groups had.
+    pub fn put_group_signatures(
+        &self,
+        signatures: &BTreeMap<(String, String), GroupSignature>,
+    ) -> Result<()> {
+        if signatures.is_empty() {
+            return Ok(());
+        }
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_SIGNATURES)
+                .or_system_err(STORAGE_ADVICE)?;
+            for ((source, group), signature) in signatures {
+                let bytes = serde_json::to_vec(signature).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(group_key(source, group).as_str(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(())
+    }
+
+    /// The signature of one group of `source`.
+    pub fn group_signature(&self, source: &str, group: &str) -> Result<Option<GroupSignature>> {
+        self.get_json(EXCEPTION_SIGNATURES, &group_key(source, group))
+    }
+
+    /// `(group, signature)` for every indexed group of `source`.
+    pub fn group_signatures(&self, source: &str) -> Result<Vec<(String, GroupSignature)>> {
+        let prefix = group_key(source, "");
+        let txn = self.db.begin_read().or_system_err(STORAGE_ADVICE)?;
+        let table = txn
+            .open_table(EXCEPTION_SIGNATURES)
+            .or_system_err(STORAGE_ADVICE)?;
+        let mut out = Vec::new();
+        for item in table
+            .range(prefix.as_str()..)
+            .or_system_err(STORAGE_ADVICE)?
+        {
+            let (key, value) = item.or_system_err(STORAGE_ADVICE)?;
+            let Some(group) = key.value().strip_prefix(prefix.as_str()) else {
+                break;
+            };
+            let signature = serde_json::from_slice(value.value()).or_system_err(STORAGE_ADVICE)?;
+            out.push((group.to_string(), signature));
+        }
+        Ok(out)
+    }
+
+    /// Replace the whole index with the signature of each group's latest
+    /// occurrence, over the archived Parquet partitions and then the hot
+    /// store. Returns the number of groups indexed. Call with the archive lock
+    /// held, so compaction can't move occurrences mid-rebuild.
+    pub fn rebuild_group_signatures(&self, parquet_dir: &str, sign: &Sign) -> Result<usize> {
+        let mut signatures: BTreeMap<(String, String), GroupSignature> = BTreeMap::new();
+        let mut record = |exception: &StoredException<'_>| {
+            let Some(group) = exception.group else {
+                return;
+            };
+            if let Some(signature) = sign(exception) {
+                signatures.insert((exception.source.to_string(), group.to_string()), signature);
+            }
+        };
+
+        // Day directories and the stamped files within them sort by time, and
+        // each partition holds its events in the order they arrived, so the
+        // latest occurrence is the last one recorded.
+        let root = Path::new(parquet_dir);
+        if root.exists() {
+            let mut files = parquet_files(root);
+            files.sort();
+            for file in files {
+                super::parquet::visit_partition_exceptions(&file, &mut record)?;
+            }
+        }
+        let hot: Vec<StoredEvent> = self.all_events()?;
+        for event in hot.iter().filter(|e| e.kind == EventKind::Exception) {
+            record(&StoredException {
+                source: &event.source,
+                group: event.exc_group.as_deref(),
+                exc_type: event.exc_type.as_deref().unwrap_or(""),
+                message: event.exc_message.as_deref(),
+                stack: event.exc_stack.as_deref(),
+                fingerprint: event.exc_fingerprint.as_deref(),
+            });
+        }
+
+        let txn = self.db.begin_write().or_system_err(STORAGE_ADVICE)?;
+        txn.delete_table(EXCEPTION_SIGNATURES)
+            .or_system_err(STORAGE_ADVICE)?;
+        {
+            let mut table = txn
+                .open_table(EXCEPTION_SIGNATURES)
+                .or_system_err(STORAGE_ADVICE)?;
+            for ((source, group), signature) in &signatures {
+                let bytes = serde_json::to_vec(signature).or_system_err(STORAGE_ADVICE)?;
+                table
+                    .insert(group_key(source, group).as_str(), bytes.as_slice())
+                    .or_system_err(STORAGE_ADVICE)?;
+            }
+        }
+        txn.commit().or_system_err(STORAGE_ADVICE)?;
+        Ok(signatures.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use crate::store::write_partition;
+
+    fn temp_path(suffix: &str) -> PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+        std::env::temp_dir().join(format!(
+            "analytics-signatures-{}-{}-{}",
+            std::process::id(),
+            n,
+            suffix
+        ))
+    }
+
+    fn exception(source: &str, group: &str, message: &str) -> StoredEvent {
+        StoredEvent {
+            kind: EventKind::Exception,
+            source: source.into(),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some(message.into()),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rebuilds_keep_each_groups_latest_occurrence() {
+        let redb = temp_path("signatures.redb");
+        let parquet = temp_path("signatures-parquet");
+        let store = Store::open(&redb).unwrap();
+        let sign = |exception: &StoredException<'_>| {
+            Some(GroupSignature {
+                exc_type: exception.exc_type.to_string(),
+                message: exception.message.unwrap_or_default().to_string(),
+                minhash: vec![1],
+            })
+        };
+
+        let day = |d: &str| {
+            parquet
+                .join("2025")
+                .join("01")
+                .join(d)
+                .join("events-1.parquet")
+        };
+        write_partition(&[exception("https://a.com", "g1", "first")], &day("01")).unwrap();
+        write_partition(
+            &[
+                exception("https://a.com", "g1", "second"),
+                exception("https://b.com", "g2", "other source"),
+            ],
+            &day("02"),
+        )
+        .unwrap();
+        store
+            .append_events(&[exception("https://a.com", "g3", "live")])
+            .unwrap();
+
+        let indexed = store
+            .rebuild_group_signatures(parquet.to_str().unwrap(), &sign)
+            .unwrap();
+        assert_eq!(indexed, 3);
+        let mut groups = store.group_signatures("https://a.com").unwrap();
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+        let messages: Vec<(&str, &str)> = groups
+            .iter()
+            .map(|(group, signature)| (group.as_str(), signature.message.as_str()))
+            .collect();
+        assert_eq!(messages, vec![("g1", "second"), ("g3", "live")]);
+        assert_eq!(
+            store
+                .group_signature("https://b.com", "g2")
+                .unwrap()
+                .map(|s| s.message),
+            Some("other source".into())
+        );
+
+        drop(store);
+        let _ = std::fs::remove_file(&redb);
+        let _ = std::fs::remove_dir_all(&parquet);
+    }
+}
@@ -2,7 +2,8 @@
 
 use redb::TableDefinition;
 
-/// JSON-valued, string-keyed table (projects, sources, pixels, triage, meta).
+/// JSON-valued, string-keyed table (projects, sources, pixels, triage, releases,
+/// users, meta).
 pub(super) type JsonTable = TableDefinition<'static, &'static str, &'static [u8]>;
 
 /// Append-only event log, keyed by `(received_ms, monotonic_seq)` (16 bytes BE).
@@ -11,6 +12,20 @@ pub(super) const PROJECTS: JsonTable = TableDefinition::new("projects");
 pub(super) const SOURCES: JsonTable = TableDefinition::new("sources");
 pub(super) const PIXELS: JsonTable = TableDefinition::new("pixels");
 pub(super) const EXCEPTION_TRIAGE: JsonTable = TableDefinition::new("exception_triage");
+/// Manually merged exception groups, keyed by [`group_key`] of the merged group.
+pub(super) const EXCEPTION_REDIRECTS: JsonTable = TableDefinition::new("exception_redirects");
+/// Manual splits of exception groups, keyed by [`group_key`] of the split-off group.
+pub(super) const EXCEPTION_SPLITS: JsonTable = TableDefinition::new("exception_splits");
+/// Releases of each source's application, keyed by [`release_key`].
+pub(super) const RELEASES: JsonTable = TableDefinition::new("releases");
+/// The full-text index over archived exceptions: one empty entry per
+/// [`term_key`], so a prefix scan finds the groups mentioning a term.
+pub(super) const EXCEPTION_TERMS: TableDefinition<&str, ()> =
+    TableDefinition::new("exception_terms");
+/// The similarity signature of each exception group, keyed by [`group_key`].
+pub(super) const EXCEPTION_SIGNATURES: JsonTable = TableDefinition::new("exception_signatures");
+/// The users who have signed in, keyed by [`user_key`].
+pub(super) const USERS: JsonTable = TableDefinition::new("users");
 pub(super) const META: JsonTable = TableDefinition::new("meta");
 
 pub(super) const META_NEXT_SEQ: &str = "next_seq";
@@ -19,6 +34,19 @@ pub(super) const META_SCHEMA_VERSION: &str = "schema_version";
 /// the code's version (see `ingest::exception::FINGERPRINT_VERSION`) triggers a
 /// one-time re-grouping pass on next start.
 pub(super) const META_FINGERPRINT_VERSION: &str = "fingerprint_version";
+/// The referrer rules version last applied to the stored data's `referrer_group`
+/// (see `ingest::ReferrerRules::version`).
+pub(super) const META_REFERRER_VERSION: &str = "referrer_version";
+/// The version of the channel rules the stored data's `channel` was last
+/// derived with (see `ingest::Channels::version`).
+pub(super) const META_CHANNEL_VERSION: &str = "channel_version";
+/// The version the exception term index and similarity signatures were built
+/// with (see `ingest::search::SEARCH_VERSION`).
+pub(super) const META_SEARCH_VERSION: &str = "search_version";
+/// What each project's inbound exception filters have discarded, as a JSON
+/// list of `(project id, filter label, count)` (see
+/// `ingest::ExceptionFilters::save`).
+pub(super) const META_INBOUND_DISCARDS: &str = "inbound_discards";
 
 pub(super) const STORAGE_ADVICE: &[&str] = &[
     "This is an internal storage error.",
@@ -57,3 +85,26 @@ pub(super) fn u32_from_be(bytes: &[u8]) -> u32 {
 pub(super) fn triage_key(project_id: &str, group_id: &str) -> String {
     format!("{project_id}\u{1f}{group_id}")
 }
+
+/// Key for a source-scoped exception group in the merge and split tables.
+pub(super) fn group_key(source: &str, group_id: &str) -> String {
+    format!("{source}\u{1f}{group_id}")
+}
+
+/// Key for a source's release in the releases table.
+pub(super) fn release_key(source: &str, version: &str) -> String {
+    format!("{source}\u{1f}{version}")
+}
+
+/// Key for a term of an exception group in the term index. Terms never hold the
+/// separator, so every key of a term (and of each term it prefixes) sorts
+/// together.
+pub(super) fn term_key(term: &str, source: &str, group_id: &str) -> String {
+    format!("{term}\u{1f}{source}\u{1f}{group_id}")
+}
+
+/// Key for a user in the users table: their identity, folded to lower case so
+/// an email matches however the provider capitalises it.
+pub(super) fn user_key(key: &str) -> String {
+    key.trim().to_lowercase()
+}
@@ -1,6 +1,20 @@
+use analytics_api::{AdminUser, ExceptionSnooze, TriageComment};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// The triage-store group key for a source-scoped exception group. Triage is
+/// per `(project, fingerprint, source)` — the same fingerprint on two
+/// applications is two independent failures. The fingerprint is 16 hex chars,
+/// so the `@` join is unambiguous.
+pub fn scoped_group(group_id: &str, source: &str) -> String {
+    format!("{group_id}@{source}")
+}
+
+/// The `(group_id, source)` a [`scoped_group`] key was made from.
+pub fn unscoped_group(scoped: &str) -> Option<(&str, &str)> {
+    scoped.split_once('@')
+}
+
 /// Admin-set triage state for an exception group, keyed by `(project_id, group_id)`.
 /// This is the only mutable exception state; occurrences are append-only events.
 ///
@@ -10,14 +24,24 @@ use serde::{Deserialize, Serialize};
 ///   *after* `resolved_at` is a regression, so the group surfaces as unresolved
 ///   again automatically (see [`ExceptionTriage::is_resolved`]).
 /// - `muted_at` suppresses a group independently of whether it is resolved; a
-///   recurrence never lifts it (muting is a deliberate "stop showing me this").
+///   recurrence never lifts it (muting is a deliberate "stop showing me this"),
+///   unless the group was snoozed: then `snooze` names the condition that lifts
+///   it, counted from `muted_at` (see [`ExceptionTriage::snooze_lapsed`]).
+///
+/// Alongside the axes a group carries who it is assigned to and a comment
+/// thread, oldest first.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExceptionTriage {
     #[serde(default)]
     pub resolved_at: Option<DateTime<Utc>>,
     #[serde(default)]
     pub muted_at: Option<DateTime<Utc>>,
-    pub note: Option<String>,
+    #[serde(default)]
+    pub snooze: Option<ExceptionSnooze>,
+    #[serde(default)]
+    pub assignee: Option<AdminUser>,
+    #[serde(default)]
+    pub comments: Vec<TriageComment>,
     pub updated_at: DateTime<Utc>,
     pub updated_by: Option<String>,
 }
@@ -37,4 +61,109 @@ impl ExceptionTriage {
     pub fn is_muted(&self) -> bool {
         self.muted_at.is_some()
     }
+
+    /// Whether the group is snoozed and the condition lifting the snooze has
+    /// been met as of `now`. `occurrences` and `sessions` are the group's
+    /// occurrences, and the sessions they hit, since it was snoozed; they are
+    /// only consulted by the counting conditions.
+    pub fn snooze_lapsed(&self, now: DateTime<Utc>, occurrences: i64, sessions: i64) -> bool {
+        if self.muted_at.is_none() {
+            return false;
+        }
+        match self.snooze {
+            None => false,
+            Some(ExceptionSnooze::Date { at_ms }) => now.timestamp_millis() >= at_ms,
+            Some(ExceptionSnooze::Occurrences { count }) => occurrences.max(0) as u64 >= count,
+            Some(ExceptionSnooze::Sessions { count }) => sessions.max(0) as u64 >= count,
+        }
+    }
+
+    /// The triage of groups merged into one, from each member's record (if it
+    /// has one) and most recent occurrence. The merged group is resolved — as of
+    /// `now`, so any later occurrence reopens it — only if every member currently
+    /// is, and muted only if every member is: snoozed like the most recently
+    /// muted member when every member is snoozed, for good otherwise. It keeps
+    /// the first assignee in member order, and every member's comments.
+    /// `None` when no member was ever triaged.
+    pub fn merged(
+        members: &[(Option<ExceptionTriage>, i64)],
+        now: DateTime<Utc>,
+        updated_by: Option<String>,
+    ) -> Option<Self> {
+        if members.iter().all(|(triage, _)| triage.is_none()) {
+            return None;
+        }
+        let resolved = members
+            .iter()
+            .all(|(triage, last_seen)| triage.as_ref().is_some_and(|t| t.is_resolved(*last_seen)));
+        let muted = members
+            .iter()
+            .map(|(triage, _)| triage.as_ref().filter(|t| t.is_muted()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let latest_muted = muted.iter().max_by_key(|t| t.muted_at);
+        let snooze = latest_muted
+            .and_then(|t| t.snooze)
+            .filter(|_| muted.iter().all(|t| t.snooze.is_some()));
+        let assignee = members
+            .iter()
+            .find_map(|(triage, _)| triage.as_ref()?.assignee.clone());
+        let mut comments: Vec<TriageComment> = Vec::new();
+        for comment in members
+            .iter()
+            .filter_map(|(triage, _)| triage.as_ref())
+            .flat_map(|triage| &triage.comments)
+        {
+            if !comments.contains(comment) {
+                comments.push(comment.clone());
+            }
+        }
+        comments.sort_by_key(|comment| comment.created_at);
+        Some(Self {
+            resolved_at: resolved.then_some(now),
+            muted_at: latest_muted.and_then(|t| t.muted_at),
+            snooze,
+            assignee,
+            comments,
+            updated_at: now,
+            updated_by,
+        })
+    }
+
+    /// Fold in the triage `other` source kept for the same group, when its
+    /// exceptions are re-attributed to this one. Like [`merged`](Self::merged)
+    /// the group stays resolved only if both were — from the earlier
+    /// resolution, so an occurrence after either reopens it — and muted only
+    /// if both were, snoozed only if both were. It keeps this record's
+    /// assignee if it has one, and both comment threads.
+    pub fn absorb(&mut self, other: ExceptionTriage) {
+        self.resolved_at = self
+            .resolved_at
+            .zip(other.resolved_at)
+            .map(|(ours, theirs)| ours.min(theirs));
+        if self.muted_at.is_some() && other.muted_at.is_some() {
+            let snoozed = self.snooze.is_some() && other.snooze.is_some();
+            if other.muted_at > self.muted_at {
+                self.muted_at = other.muted_at;
+                self.snooze = other.snooze;
+            }
+            self.snooze = self.snooze.filter(|_| snoozed);
+        } else {
+            self.muted_at = None;
+            self.snooze = None;
+        }
+        if self.assignee.is_none() {
+            self.assignee = other.assignee;
+        }
+        for comment in other.comments {
+            if !self.comments.contains(&comment) {
+                self.comments.push(comment);
+            }
+        }
+        self.comments.sort_by_key(|comment| comment.created_at);
+        if other.updated_at > self.updated_at {
+            self.updated_at = other.updated_at;
+            self.updated_by = other.updated_by;
+        }
+    }
 }
@@ -17,8 +17,8 @@ use super::{CSRF_COOKIE, OAUTH_COOKIE, REFRESH_COOKIE, SESSION_COOKIE, json_erro
 use crate::state::AppState;
 use crate::web::extract::{base_url, is_https};
 use crate::web::helpers::oidc::{
-    authorize_url, discovery, exchange_code, generate_pkce, random_token, refresh_tokens,
-    validate_token,
+    admin_user_from_claims, authorize_url, discovery, exchange_code, generate_pkce, random_token,
+    refresh_tokens, validate_token,
 };
 
 const DEFAULT_SESSION_SECONDS: i64 = 8 * 60 * 60;
@@ -200,6 +200,7 @@ pub async fn auth_callback(
             return clear_oauth_and_redirect("/?auth_error=token");
         }
     };
+    remember_user(&state, &claims).await;
 
     let mut oauth_removal = Cookie::build(OAUTH_COOKIE, "")
         .path(OAUTH_COOKIE_PATH)
@@ -286,6 +287,7 @@ pub async fn auth_refresh(state: web::Data<AppState>, req: HttpRequest) -> HttpR
             return expired_session();
         }
     };
+    remember_user(&state, &claims).await;
 
     let mut response = HttpResponse::NoContent();
     response.cookie(session_cookie(tokens.id_token, &claims, secure));
@@ -295,6 +297,19 @@ pub async fn auth_refresh(state: web::Data<AppState>, req: HttpRequest) -> HttpR
     response.finish()
 }
 
+/// Remember who signed in (or renewed their session), so exception groups can
+/// be assigned to them. Failing only costs the assignee list an entry, so it
+/// never fails the sign-in.
+async fn remember_user(state: &AppState, claims: &serde_json::Map<String, serde_json::Value>) {
+    let user = admin_user_from_claims(claims);
+    let store = state.store.clone();
+    match web::block(move || store.record_user(&user)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => warn!("Failed to record the signed-in user: {err}"),
+        Err(err) => error!("user recording task failed: {err}"),
+    }
+}
+
 /// Build the session cookie for a freshly validated ID token, living as long as
 /// the token itself does.
 fn session_cookie(
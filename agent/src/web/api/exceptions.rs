@@ -1,51 +1,77 @@
-//! Exception groups (Sentry-style): the global filterable inbox, group detail,
-//! and triage.
+//! Exception groups (Sentry-style): the global filterable, searchable and
+//! paged inbox and the environments it can be scoped to, group detail,
+//! triage (with snoozes, assignees and comments), manual merges and splits,
+//! and what the inbound filters discarded.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use actix_web::http::StatusCode;
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
 use analytics_api::{
-    ExceptionGroupDetail, ExceptionStatus, GlobalException, TriageInput, pixel_source,
+    AssigneeInput, BulkTriageInput, BulkTriageResult, BulkTriageTarget, CommentInput,
+    DiscardedExceptions, ExceptionGroup, ExceptionGroupDetail, ExceptionPage, ExceptionSnooze,
+    ExceptionSort, ExceptionSplit, ExceptionStatus, ExceptionStatusCounts, GlobalException,
+    MergeInput, NEXT_CURSOR_HEADER, STATUS_COUNTS_HEADER, SnoozeInput, SplitInput, TriageComment,
+    TriageInput, pixel_source,
 };
-use chrono::Utc;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use tracing_batteries::prelude::*;
 
 use super::query::resolve_range;
 use super::{Authenticated, internal_error, json_error};
-use crate::analytics::{self, filter::FieldSet};
+use crate::analytics::{
+    self,
+    filter::{CompiledFilter, FieldSet},
+};
+use crate::ingest::{self, GroupEdits};
 use crate::state::AppState;
+use crate::store::{ExceptionTriage, GroupRedirect, GroupSplit, Store, scoped_group};
 
 const VARIANT_LIMIT: usize = 50;
-
-/// The triage-store group key for a source-scoped exception group. Triage is
-/// per `(project, fingerprint, source)` — the same fingerprint on two
-/// applications is two independent failures. The fingerprint is 16 hex chars,
-/// so the `@` join is unambiguous.
-fn scoped_group(group_id: &str, source: &str) -> String {
-    format!("{group_id}@{source}")
-}
+/// The inbox page size when none is asked for, and the largest allowed.
+const PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+/// The most similar issues suggested on a group's page.
+const SIMILAR_GROUPS: usize = 5;
+/// The longest triage comment accepted, in characters.
+const MAX_COMMENT_CHARS: usize = 10_000;
 
 /// Query parameters for the global exceptions inbox: a time range plus a
 /// filt-rs `q` expression over the dimensions exception events carry
 /// (project, source, browser, os, device, app, app_version, type, message,
-/// handled).
+/// handled, level, environment), full-text `search` words, and the order,
+/// status and page to list.
 #[derive(Deserialize)]
 pub struct ExceptionsQuery {
     pub from: Option<i64>,
     pub to: Option<i64>,
     pub q: Option<String>,
+    /// Words to find in the groups' types, messages and stack frames; each
+    /// matches words it starts.
+    pub search: Option<String>,
+    #[serde(default)]
+    pub sort: ExceptionSort,
+    /// Narrow the page to one status; the status counts cover every status.
+    pub status: Option<ExceptionStatus>,
+    /// The previous page's `X-Next-Cursor`.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
 }
 
 /// `GET /api/v1/exceptions` — exception groups across every project (and
 /// unassigned sources), each annotated with its project, for the global inbox.
+/// Paged by an opaque cursor in the requested order: the body is the page's
+/// groups, the following page's cursor is in `X-Next-Cursor` and the status
+/// counts are in `X-Status-Counts`.
 pub async fn list_all(
     state: web::Data<AppState>,
     query: web::Query<ExceptionsQuery>,
 ) -> HttpResponse {
     let query = query.into_inner();
-    let store = state.store.clone();
+    let (store, rules) = (state.store.clone(), state.rules.clone());
     let parquet_dir = state.config.storage.parquet_dir.clone();
 
     let filter = match query.q.as_deref() {
@@ -55,53 +81,87 @@ pub async fn list_all(
         },
         None => None,
     };
+    let cursor = match query
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, query.sort))
+    {
+        Some(None) => return json_error(StatusCode::BAD_REQUEST, "Invalid cursor."),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
 
-    let result = web::block(move || -> crate::errors::Result<Vec<GlobalException>> {
-        // `from=0` means "all time": anchor at the earliest stored event so the
-        // per-group trend buckets cover the data, not decades of empty space.
-        let from = match query.from {
-            Some(f) if f <= 0 => analytics::earliest_event_ms(&store, &parquet_dir)?,
-            other => other,
-        };
-        let (from, to, _) = resolve_range(from, query.to, None);
-        let per_source =
-            analytics::exception_groups_by_source(&store, &parquet_dir, from, to, filter.as_ref())?;
-
-        // Resolve a source URI to its owning project, and project ids to names.
-        let mut uri_project: HashMap<String, String> = HashMap::new();
-        for source in store.list_sources()? {
-            if let Some(project_id) = source.project_id {
-                uri_project.insert(source.uri, project_id);
-            }
+    let result = web::block(move || -> crate::errors::Result<ExceptionPage> {
+        let inbox = inbox(
+            &store,
+            &rules.edits,
+            &parquet_dir,
+            (query.from, query.to),
+            filter.as_ref(),
+            query.search.as_deref(),
+        )?;
+
+        let mut status_counts = ExceptionStatusCounts::default();
+        for listed in inbox.groups.values() {
+            let count = match listed.status {
+                ExceptionStatus::Unresolved => &mut status_counts.unresolved,
+                ExceptionStatus::Resolved => &mut status_counts.resolved,
+                ExceptionStatus::Ignored => &mut status_counts.ignored,
+            };
+            *count += 1;
         }
-        for pixel in store.list_pixels()? {
-            uri_project.insert(pixel_source(&pixel.id), pixel.project_id);
+        // Only a search or a status narrows the groups loaded to known pairs;
+        // otherwise the filter alone decides.
+        let keys: Option<Vec<(String, String)>> =
+            (query.search.is_some() || query.status.is_some()).then(|| {
+                inbox
+                    .groups
+                    .iter()
+                    .filter(|(_, listed)| query.status.is_none_or(|status| listed.status == status))
+                    .map(|(key, _)| key.clone())
+                    .collect()
+            });
+        if keys.as_ref().is_some_and(Vec::is_empty) {
+            return Ok(ExceptionPage {
+                groups: Vec::new(),
+                next_cursor: None,
+                status_counts,
+            });
         }
-        let project_names: HashMap<String, String> = store
-            .list_projects()?
-            .into_iter()
-            .map(|p| (p.id, p.name))
-            .collect();
 
-        // One row per (fingerprint, source): a group's identity is the failure
-        // *on that application* — the same fingerprint on two sources is two
-        // independent rows, each annotated with its owning project (when the
-        // source is assigned) for display and triage.
-        let mut out: Vec<GlobalException> = Vec::with_capacity(per_source.len());
-        for (mut group, source) in per_source {
-            let project_id = uri_project.get(&source).cloned();
-            let mut project_name = None;
-            if let Some(pid) = &project_id {
-                if let Some(triage) =
-                    store.get_triage(pid, &scoped_group(&group.group_id, &source))?
-                {
-                    group.resolved = triage.is_resolved(group.last_seen_ms);
-                    group.muted = triage.is_muted();
-                    group.status = ExceptionStatus::from(&group);
-                    group.note = triage.note;
+        // One more than the page, to tell whether another follows.
+        let rows = analytics::exceptions::exception_groups_by_source(
+            &store,
+            &parquet_dir,
+            inbox.from,
+            inbox.to,
+            filter.as_ref(),
+            keys.as_deref(),
+            Some(&analytics::exceptions::GroupPage {
+                sort: query.sort,
+                after: cursor
+                    .as_ref()
+                    .map(|c| (c.value, c.source.as_str(), c.group_id.as_str())),
+                limit: limit + 1,
+            }),
+        )?;
+        let mut reopened: HashMap<(String, String), i64> = HashMap::new();
+        let mut out: Vec<GlobalException> = Vec::with_capacity(rows.len());
+        for (mut group, source) in rows {
+            let key = (source.clone(), group.group_id.clone());
+            let (project_id, project_name) = match inbox.groups.get(&key) {
+                Some(listed) => {
+                    if let Some(triage) = &listed.triage {
+                        annotate(&mut group, triage);
+                        if let Some(resolved_at) = triage.resolved_at.filter(|_| !group.resolved) {
+                            reopened.insert(key, resolved_at.timestamp_millis());
+                        }
+                    }
+                    (listed.project_id.clone(), listed.project_name.clone())
                 }
-                project_name = project_names.get(pid).cloned();
-            }
+                None => (None, None),
+            };
             out.push(GlobalException {
                 group,
                 project_id,
@@ -109,13 +169,38 @@ pub async fn list_all(
                 source,
             });
         }
-        out.sort_by_key(|e| std::cmp::Reverse(e.group.last_seen_ms));
-        Ok(out)
+        let (mut groups, next_cursor) = page(out, query.sort, limit);
+
+        // Regressions are only worked out for the groups on the page, every
+        // source's in one query.
+        reopened.retain(|(source, group_id), _| {
+            groups
+                .iter()
+                .any(|e| &e.source == source && &e.group.group_id == group_id)
+        });
+        let mut regressions =
+            analytics::exceptions::exception_regressions(&store, &parquet_dir, &reopened)?;
+        for exception in &mut groups {
+            let key = (exception.source.clone(), exception.group.group_id.clone());
+            exception.group.regressed_in = regressions.remove(&key);
+        }
+        Ok(ExceptionPage {
+            groups,
+            next_cursor,
+            status_counts,
+        })
     })
     .await;
 
     match result {
-        Ok(Ok(groups)) => HttpResponse::Ok().json(groups),
+        Ok(Ok(page)) => {
+            let mut response = HttpResponse::Ok();
+            response.insert_header((STATUS_COUNTS_HEADER, page.status_counts.to_header()));
+            if let Some(cursor) = page.next_cursor {
+                response.insert_header((NEXT_CURSOR_HEADER, cursor));
+            }
+            response.json(page.groups)
+        }
         Ok(Err(err)) => internal_error(err),
         Err(err) => {
             error!("global exception listing task failed: {err}");
@@ -127,6 +212,269 @@ pub async fn list_all(
     }
 }
 
+/// The groups an inbox query matches, by `(source, group)`, and the range it
+/// resolved to. Only what is needed to count and narrow them by status is
+/// read; the page itself is loaded afterwards.
+struct Inbox {
+    from: i64,
+    to: i64,
+    groups: HashMap<(String, String), Listed>,
+}
+
+/// Where a matched group stands: its project (when its source is assigned
+/// one), its triage and the status that leaves it in.
+struct Listed {
+    project_id: Option<String>,
+    project_name: Option<String>,
+    triage: Option<ExceptionTriage>,
+    status: ExceptionStatus,
+}
+
+/// Every exception group seen between `from` and `to` (`from=0` meaning all
+/// time) that matches `filter` and the full-text `search`, one per
+/// `(fingerprint, source)`.
+fn inbox(
+    store: &Store,
+    edits: &GroupEdits,
+    parquet_dir: &str,
+    (from, to): (Option<i64>, Option<i64>),
+    filter: Option<&CompiledFilter>,
+    search: Option<&str>,
+) -> crate::errors::Result<Inbox> {
+    // `from=0` means "all time": anchor at the earliest stored event so the
+    // per-group trend buckets cover the data, not decades of empty space.
+    let from = match from {
+        Some(f) if f <= 0 => analytics::earliest_event_ms(store, parquet_dir)?,
+        other => other,
+    };
+    let (from, to, _) = resolve_range(from, to, None);
+    let matches: Option<Vec<(String, String)>> = match search {
+        Some(search) => ingest::search_exception_groups(store, edits, search, from, to)?
+            .map(|matches| matches.into_iter().collect()),
+        None => None,
+    };
+    let mut inbox = Inbox {
+        from,
+        to,
+        groups: HashMap::new(),
+    };
+    if matches.as_ref().is_some_and(Vec::is_empty) {
+        return Ok(inbox);
+    }
+    let keys = analytics::exceptions::exception_group_keys(
+        store,
+        parquet_dir,
+        from,
+        to,
+        filter,
+        matches.as_deref(),
+    )?;
+
+    // Resolve a source URI to its owning project, and project ids to names.
+    let uri_project = source_projects(store)?;
+    let project_names: HashMap<String, String> = store
+        .list_projects()?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+
+    // One row per (fingerprint, source): a group's identity is the failure
+    // *on that application* — the same fingerprint on two sources is two
+    // independent rows, each annotated with its owning project (when the
+    // source is assigned) for display and triage. Each project's triage is
+    // read once, the first time one of its groups comes up.
+    let mut triages: HashMap<String, HashMap<String, ExceptionTriage>> = HashMap::new();
+    for (source, group_id, last_seen_ms) in keys {
+        let project_id = uri_project.get(&source).cloned();
+        let triage = match &project_id {
+            Some(pid) => {
+                if !triages.contains_key(pid) {
+                    triages.insert(pid.clone(), store.project_triages(pid)?);
+                }
+                triages[pid].get(&scoped_group(&group_id, &source)).cloned()
+            }
+            None => None,
+        };
+        let status = triage
+            .as_ref()
+            .map_or(ExceptionStatus::Unresolved, |triage| {
+                triage_status(triage, last_seen_ms)
+            });
+        inbox.groups.insert(
+            (source, group_id),
+            Listed {
+                project_name: project_id
+                    .as_ref()
+                    .and_then(|pid| project_names.get(pid).cloned()),
+                project_id,
+                triage,
+                status,
+            },
+        );
+    }
+    Ok(inbox)
+}
+
+/// The project each assigned source URI (and pixel) belongs to.
+fn source_projects(store: &Store) -> crate::errors::Result<HashMap<String, String>> {
+    let mut uri_project: HashMap<String, String> = HashMap::new();
+    for source in store.list_sources()? {
+        if let Some(project_id) = source.project_id {
+            uri_project.insert(source.uri, project_id);
+        }
+    }
+    for pixel in store.list_pixels()? {
+        uri_project.insert(pixel_source(&pixel.id), pixel.project_id);
+    }
+    Ok(uri_project)
+}
+
+/// Whether `source` is assigned to `project_id`: a group is triaged under the
+/// project its source belongs to, and no other.
+fn owned_by(store: &Store, project_id: &str, source: &str) -> crate::errors::Result<bool> {
+    Ok(source_projects(store)?
+        .get(source)
+        .is_some_and(|owner| owner == project_id))
+}
+
+/// The status a group's triage leaves it in, given when it was last seen.
+fn triage_status(triage: &ExceptionTriage, last_seen_ms: i64) -> ExceptionStatus {
+    if triage.is_muted() {
+        ExceptionStatus::Ignored
+    } else if triage.is_resolved(last_seen_ms) {
+        ExceptionStatus::Resolved
+    } else {
+        ExceptionStatus::Unresolved
+    }
+}
+
+/// Where an inbox page ended: the last group's sort value and identity. Groups
+/// are listed by the value, descending, then by `(source, group)`, so the
+/// position stays put as groups are added or change around it.
+#[derive(Debug, PartialEq)]
+struct Cursor {
+    value: i64,
+    source: String,
+    group_id: String,
+}
+
+impl Cursor {
+    fn after(exception: &GlobalException, sort: ExceptionSort) -> Self {
+        Self {
+            value: sort_value(&exception.group, sort),
+            source: exception.source.clone(),
+            group_id: exception.group.group_id.clone(),
+        }
+    }
+
+    /// The opaque form handed to clients, which names the order it was made
+    /// for.
+    fn encode(&self, sort: ExceptionSort) -> String {
+        let raw = format!(
+            "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+            sort.as_str(),
+            self.value,
+            self.source,
+            self.group_id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// `None` when `cursor` is malformed or was made for another order.
+    fn decode(cursor: &str, sort: ExceptionSort) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let mut parts = raw.split('\u{1f}');
+        if ExceptionSort::parse(parts.next()?)? != sort {
+            return None;
+        }
+        let cursor = Self {
+            value: parts.next()?.parse().ok()?,
+            source: parts.next()?.to_string(),
+            group_id: parts.next()?.to_string(),
+        };
+        parts.next().is_none().then_some(cursor)
+    }
+}
+
+fn sort_value(group: &ExceptionGroup, sort: ExceptionSort) -> i64 {
+    match sort {
+        ExceptionSort::LastSeen => group.last_seen_ms,
+        ExceptionSort::FirstSeen => group.first_seen_ms,
+        ExceptionSort::Count => group.count,
+        ExceptionSort::Sessions => group.sessions,
+    }
+}
+
+/// Take the first `limit` of `exceptions`, already in `sort` order and read
+/// one past the page, with the cursor of the page following them (`None` when
+/// they are the last).
+fn page(
+    mut exceptions: Vec<GlobalException>,
+    sort: ExceptionSort,
+    limit: usize,
+) -> (Vec<GlobalException>, Option<String>) {
+    let next = (exceptions.len() > limit)
+        .then(|| Cursor::after(&exceptions[limit - 1], sort).encode(sort));
+    exceptions.truncate(limit);
+    (exceptions, next)
+}
+
+/// `GET /api/v1/exceptions/discarded` — how many reports each project's
+/// inbound filters dropped since each was added, one row per filter.
+pub async fn discarded(state: web::Data<AppState>) -> HttpResponse {
+    let project_names: HashMap<String, String> = match state.store.list_projects() {
+        Ok(projects) => projects.into_iter().map(|p| (p.id, p.name)).collect(),
+        Err(err) => return internal_error(err),
+    };
+    let counts: Vec<DiscardedExceptions> = state
+        .rules
+        .inbound
+        .counts()
+        .into_iter()
+        .map(|(project_id, filter, discarded)| DiscardedExceptions {
+            project_name: project_names.get(&project_id).cloned().unwrap_or_default(),
+            project_id,
+            filter,
+            discarded,
+        })
+        .collect();
+    HttpResponse::Ok().json(counts)
+}
+
+#[derive(Deserialize)]
+pub struct EnvironmentsQuery {
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// `GET /api/v1/exceptions/environments?from=…&to=…` — the environments
+/// exceptions reported over the range, most occurrences first, for the
+/// inbox's environment selector.
+pub async fn environments(
+    state: web::Data<AppState>,
+    query: web::Query<EnvironmentsQuery>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let (from, to, _) = resolve_range(query.from, query.to, None);
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+    let result = web::block(move || {
+        analytics::exceptions::exception_environments(&store, &parquet_dir, from, to)
+    })
+    .await;
+    match result {
+        Ok(Ok(rows)) => HttpResponse::Ok().json(rows),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("exception environments task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load environments.",
+            )
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct DetailQuery {
     project: String,
@@ -138,7 +486,9 @@ pub struct DetailQuery {
 }
 
 /// `GET /api/v1/exceptions/{group_id}?project=…&source=…` — a group with
-/// recent occurrences, scoped to the source it was seen on.
+/// recent occurrences, scoped to the source it was seen on, and the groups of
+/// that source it looks like. Stacks are symbolicated with the release's
+/// uploaded source maps and parsed into frames.
 ///
 /// Without an explicit range this looks across **all time**: a group linked
 /// from the inbox (which may cover a 12-month window) or from an old bookmark
@@ -160,13 +510,13 @@ pub async fn detail(
         .unwrap_or(now)
         .clamp(1, super::query::MAX_INSTANT_MS);
     let from = query.from.unwrap_or(0).clamp(0, to - 1);
-    let store = state.store.clone();
+    let (store, rules) = (state.store.clone(), state.rules.clone());
     let parquet_dir = state.config.storage.parquet_dir.clone();
 
     let result = web::block(
         move || -> crate::errors::Result<Option<ExceptionGroupDetail>> {
             let sources = [source.clone()];
-            let Some(mut detail) = analytics::exception_detail(
+            let Some(mut detail) = analytics::exceptions::exception_detail(
                 &store,
                 &parquet_dir,
                 &sources,
@@ -178,13 +528,48 @@ pub async fn detail(
             else {
                 return Ok(None);
             };
+            // Scored from the signature index, on each group's latest stored
+            // occurrence, before symbolication.
+            let similar = ingest::similar_groups(&store, &rules.edits, &source, &group_id)?;
+            detail.similar = analytics::exceptions::similar_group_activity(
+                &store,
+                &parquet_dir,
+                &source,
+                similar,
+                from,
+                to,
+                SIMILAR_GROUPS,
+            )?;
+            for variant in &mut detail.variants {
+                if let Some(stack) = variant.stack.as_deref()
+                    && let Some(resolved) =
+                        rules
+                            .sourcemaps
+                            .symbolicate(&source, variant.app_version.as_deref(), stack)
+                {
+                    variant.stack = Some(resolved);
+                }
+                variant.frames = variant
+                    .stack
+                    .as_deref()
+                    .map(ingest::parse_stack)
+                    .unwrap_or_default();
+            }
             if let Some(triage) =
                 store.get_triage(&project_id, &scoped_group(&group_id, &source))?
             {
-                detail.group.resolved = triage.is_resolved(detail.group.last_seen_ms);
-                detail.group.muted = triage.is_muted();
-                detail.group.status = ExceptionStatus::from(&detail.group);
-                detail.group.note = triage.note;
+                annotate(&mut detail.group, &triage);
+                detail.comments = triage.comments;
+                if let Some(resolved_at) = triage.resolved_at.filter(|_| !detail.group.resolved) {
+                    let key = (source.clone(), group_id.clone());
+                    let resolved = HashMap::from([(key.clone(), resolved_at.timestamp_millis())]);
+                    detail.group.regressed_in = analytics::exceptions::exception_regressions(
+                        &store,
+                        &parquet_dir,
+                        &resolved,
+                    )?
+                    .remove(&key);
+                }
             }
             Ok(Some(detail))
         },
@@ -211,6 +596,8 @@ pub async fn detail(
 /// is left unchanged, so the inbox's separate Resolve/Reopen and Mute/Unmute
 /// controls each touch only their own axis. Resolving anchors `resolved_at` at
 /// now, which is what lets a later occurrence reopen the group automatically.
+/// Muting here is for good and unmuting ends a snooze, so either drops the
+/// snooze condition. A legacy `note` is added to the group's comment thread.
 pub async fn triage(
     req: HttpRequest,
     state: web::Data<AppState>,
@@ -219,34 +606,283 @@ pub async fn triage(
 ) -> HttpResponse {
     let group_id = path.into_inner();
     let input = body.into_inner();
-    let updated_by = req
-        .extensions()
-        .get::<Authenticated>()
-        .and_then(|a| a.user.as_ref().map(|u| u.name.clone()));
+    let updated_by = acting_user(&req);
 
     let source = input.source.trim();
     if source.is_empty() {
         return json_error(StatusCode::BAD_REQUEST, "A source is required.");
     }
+    let note = match input.note.as_deref().filter(|note| !note.trim().is_empty()) {
+        Some(note) => match comment_body(note) {
+            Ok(body) => Some(body.to_string()),
+            Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+        },
+        None => None,
+    };
 
     match state.store.update_triage(
         &input.project_id,
         &scoped_group(&group_id, source),
         |triage| {
             let now = Utc::now();
-            if let Some(resolved) = input.resolved {
-                // Anchor resolution at now; reopening clears the anchor.
-                triage.resolved_at = resolved.then_some(now);
+            set_axes(triage, input.resolved, input.muted, now);
+            if let Some(body) = note {
+                triage.comments.push(TriageComment {
+                    author: updated_by.clone(),
+                    body,
+                    created_at: now,
+                });
+            }
+            triage.updated_at = now;
+            triage.updated_by = updated_by;
+        },
+    ) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// Set the triage axes a change names, leaving the others unchanged.
+fn set_axes(
+    triage: &mut ExceptionTriage,
+    resolved: Option<bool>,
+    muted: Option<bool>,
+    now: DateTime<Utc>,
+) {
+    if let Some(resolved) = resolved {
+        // Anchor resolution at now; reopening clears the anchor.
+        triage.resolved_at = resolved.then_some(now);
+    }
+    if let Some(muted) = muted {
+        triage.muted_at = muted.then_some(now);
+        triage.snooze = None;
+    }
+}
+
+/// `POST /api/v1/exceptions/triage` — resolve or reopen, mute or unmute, or
+/// comment on many groups at once: those listed, or every group an inbox
+/// query matches. Groups on sources not assigned to a project can't be
+/// triaged, so a query skips them; a listed group is rejected when it is
+/// blank or its source isn't the named project's. Resolving a query only
+/// reads, like listing it.
+///
+/// Every group is updated in one transaction, so a failure leaves them all as
+/// they were.
+pub async fn bulk_triage(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<BulkTriageInput>,
+) -> HttpResponse {
+    let input = body.into_inner();
+    let updated_by = acting_user(&req);
+    if input.resolved.is_none() && input.muted.is_none() && input.comment.is_none() {
+        return json_error(StatusCode::BAD_REQUEST, "Choose a change to apply.");
+    }
+    let comment = match input.comment.as_deref().map(comment_body) {
+        Some(Err(message)) => return json_error(StatusCode::BAD_REQUEST, message),
+        Some(Ok(body)) => Some(body.to_string()),
+        None => None,
+    };
+    let filter = match &input.target {
+        BulkTriageTarget::Query { q: Some(q), .. } => {
+            match analytics::filter::compile_query(q, FieldSet::Exceptions, &state.store) {
+                Ok(filter) => filter,
+                Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+            }
+        }
+        _ => None,
+    };
+
+    let (store, rules) = (state.store.clone(), state.rules.clone());
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+    let result = web::block(move || -> crate::errors::Result<BulkTriageResult> {
+        let (mut skipped, mut rejected) = (0, 0);
+        let targets: BTreeSet<(String, String)> = match input.target {
+            BulkTriageTarget::Groups { groups } => {
+                let uri_project = source_projects(&store)?;
+                let mut targets = BTreeSet::new();
+                for target in groups {
+                    let (source, group_id) = (target.source.trim(), target.group_id.trim());
+                    let owned = uri_project
+                        .get(source)
+                        .is_some_and(|project_id| *project_id == target.project_id);
+                    if group_id.is_empty() || !owned {
+                        rejected += 1;
+                        continue;
+                    }
+                    targets.insert((target.project_id, scoped_group(group_id, source)));
+                }
+                targets
             }
-            if let Some(muted) = input.muted {
-                triage.muted_at = muted.then_some(now);
+            BulkTriageTarget::Query {
+                from,
+                to,
+                search,
+                status,
+                ..
+            } => {
+                let Inbox { groups, .. } = inbox(
+                    &store,
+                    &rules.edits,
+                    &parquet_dir,
+                    (from, to),
+                    filter.as_ref(),
+                    search.as_deref(),
+                )?;
+                let mut targets = BTreeSet::new();
+                for ((source, group_id), listed) in groups {
+                    if status.is_some_and(|status| listed.status != status) {
+                        continue;
+                    }
+                    let Some(project_id) = listed.project_id else {
+                        skipped += 1;
+                        continue;
+                    };
+                    targets.insert((project_id, scoped_group(&group_id, &source)));
+                }
+                targets
             }
-            // A note is only touched when the caller sends one, so toggling an
-            // axis never wipes an existing note; an empty note clears it.
-            if let Some(note) = input.note {
-                triage.note = Some(note).filter(|n| !n.trim().is_empty());
+        };
+
+        let targets: Vec<(String, String)> = targets.into_iter().collect();
+        let now = Utc::now();
+        let updated = store.update_triages(&targets, |triage| {
+            set_axes(triage, input.resolved, input.muted, now);
+            if let Some(body) = &comment {
+                triage.comments.push(TriageComment {
+                    author: updated_by.clone(),
+                    body: body.clone(),
+                    created_at: now,
+                });
             }
             triage.updated_at = now;
+            triage.updated_by = updated_by.clone();
+        })?;
+        Ok(BulkTriageResult {
+            updated: updated as u64,
+            skipped,
+            rejected,
+        })
+    })
+    .await;
+
+    match result {
+        Ok(Ok(result)) => HttpResponse::Ok().json(result),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("bulk exception triage task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to triage the exception groups.",
+            )
+        }
+    }
+}
+
+/// The name of the signed-in user making a change, for audit fields.
+fn acting_user(req: &HttpRequest) -> Option<String> {
+    req.extensions()
+        .get::<Authenticated>()
+        .and_then(|a| a.user.as_ref().map(|u| u.name.clone()))
+}
+
+/// `PUT /api/v1/exceptions/{group_id}/snooze` — mute a group until a date
+/// passes, or until it occurs so many more times or in so many more sessions.
+/// The mute lifts by itself within a minute of that.
+pub async fn snooze(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<SnoozeInput>,
+) -> HttpResponse {
+    let group_id = path.into_inner();
+    let input = body.into_inner();
+    let updated_by = acting_user(&req);
+
+    let source = input.source.trim();
+    if source.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "A source is required.");
+    }
+    let now = Utc::now();
+    match input.snooze {
+        ExceptionSnooze::Date { at_ms } if at_ms <= now.timestamp_millis() => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                "A snooze must last until a future date.",
+            );
+        }
+        ExceptionSnooze::Occurrences { count: 0 } | ExceptionSnooze::Sessions { count: 0 } => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                "A snooze must last for at least one occurrence or session.",
+            );
+        }
+        _ => {}
+    }
+    match owned_by(&state.store, &input.project_id, source) {
+        Ok(true) => {}
+        Ok(false) => return json_error(StatusCode::NOT_FOUND, "Exception group not found."),
+        Err(err) => return internal_error(err),
+    }
+
+    match state.store.update_triage(
+        &input.project_id,
+        &scoped_group(&group_id, source),
+        |triage| {
+            // Counted from the new anchor, so re-snoozing starts afresh.
+            triage.muted_at = Some(now);
+            triage.snooze = Some(input.snooze);
+            triage.updated_at = now;
+            triage.updated_by = updated_by;
+        },
+    ) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// `PUT /api/v1/exceptions/{group_id}/assignee` — assign a group to one of
+/// the users who have signed in, or unassign it.
+pub async fn assign(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<AssigneeInput>,
+) -> HttpResponse {
+    let group_id = path.into_inner();
+    let input = body.into_inner();
+    let updated_by = acting_user(&req);
+
+    let source = input.source.trim();
+    if source.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "A source is required.");
+    }
+    let key = input.assignee.as_deref().map(str::trim);
+    let assignee = match key.filter(|key| !key.is_empty()) {
+        Some(key) => match state.store.get_user(key) {
+            Ok(Some(user)) => Some(user),
+            Ok(None) => {
+                return json_error(
+                    StatusCode::BAD_REQUEST,
+                    "Groups can only be assigned to users who have signed in.",
+                );
+            }
+            Err(err) => return internal_error(err),
+        },
+        None => None,
+    };
+    match owned_by(&state.store, &input.project_id, source) {
+        Ok(true) => {}
+        Ok(false) => return json_error(StatusCode::NOT_FOUND, "Exception group not found."),
+        Err(err) => return internal_error(err),
+    }
+
+    match state.store.update_triage(
+        &input.project_id,
+        &scoped_group(&group_id, source),
+        |triage| {
+            triage.assignee = assignee;
+            triage.updated_at = Utc::now();
             triage.updated_by = updated_by;
         },
     ) {
@@ -254,3 +890,763 @@ pub async fn triage(
         Err(err) => internal_error(err),
     }
 }
+
+/// `GET /api/v1/exceptions/assignees` — the users exception groups can be
+/// assigned to: everyone who has signed in, by name.
+pub async fn assignees(state: web::Data<AppState>) -> HttpResponse {
+    match state.store.list_users() {
+        Ok(mut users) => {
+            users.sort_by_cached_key(|user| user.name.to_lowercase());
+            HttpResponse::Ok().json(users)
+        }
+        Err(err) => internal_error(err),
+    }
+}
+
+/// `POST /api/v1/exceptions/{group_id}/comments` — add a comment to a group's
+/// triage thread, by the signed-in user. Responds with the stored comment.
+pub async fn comment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<CommentInput>,
+) -> HttpResponse {
+    let group_id = path.into_inner();
+    let input = body.into_inner();
+
+    let source = input.source.trim();
+    if source.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "A source is required.");
+    }
+    let body = match comment_body(&input.body) {
+        Ok(body) => body,
+        Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+    };
+    let comment = TriageComment {
+        author: acting_user(&req),
+        body: body.to_string(),
+        created_at: Utc::now(),
+    };
+    match owned_by(&state.store, &input.project_id, source) {
+        Ok(true) => {}
+        Ok(false) => return json_error(StatusCode::NOT_FOUND, "Exception group not found."),
+        Err(err) => return internal_error(err),
+    }
+
+    match state.store.update_triage(
+        &input.project_id,
+        &scoped_group(&group_id, source),
+        |triage| {
+            triage.comments.push(comment.clone());
+            triage.updated_at = comment.created_at;
+            triage.updated_by = comment.author.clone();
+        },
+    ) {
+        Ok(_) => HttpResponse::Created().json(comment),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// A comment's trimmed text, or why it can't be posted.
+fn comment_body(body: &str) -> Result<&str, String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("A comment can't be empty.".to_string());
+    }
+    if body.chars().count() > MAX_COMMENT_CHARS {
+        return Err(format!(
+            "A comment can be at most {MAX_COMMENT_CHARS} characters long."
+        ));
+    }
+    Ok(body)
+}
+
+/// Show a group's triage on its listing.
+fn annotate(group: &mut ExceptionGroup, triage: &ExceptionTriage) {
+    group.resolved = triage.is_resolved(group.last_seen_ms);
+    group.muted = triage.is_muted();
+    group.status = ExceptionStatus::from(&*group);
+    group.snooze = triage.snooze.filter(|_| group.muted);
+    group.assignee = triage.assignee.clone();
+    group.comments = triage.comments.len() as u32;
+}
+
+/// `POST /api/v1/exceptions/{group_id}/merge` — fold `groups` into the
+/// addressed group on one source.
+///
+/// The merge is recorded as redirects, so queries show the merged history at
+/// once, and the stored occurrences are rewritten in the background. The
+/// target's triage becomes the combination of every member's: resolved only if
+/// all of them are (and none recurred), muted only if all of them are, with
+/// their notes kept.
+pub async fn merge(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<MergeInput>,
+) -> HttpResponse {
+    let input = body.into_inner();
+    let merged_by = acting_user(&req);
+    let source = input.source.trim().to_string();
+    if source.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "A source is required.");
+    }
+
+    // Merging into a group that was itself merged away lands on its target.
+    let rules = state.rules.clone();
+    let group_id = path.into_inner();
+    let target = rules.edits.redirect(&source, &group_id).unwrap_or(group_id);
+    let mut groups: Vec<String> = Vec::new();
+    for group in input.groups {
+        let group = group.trim().to_string();
+        if !group.is_empty() && group != target && !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    if groups.is_empty() {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            "Name at least one other group to merge.",
+        );
+    }
+
+    let merged = source.clone();
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+    let project_id = Some(input.project_id).filter(|p| !p.trim().is_empty());
+    let result = web::block(move || -> crate::errors::Result<()> {
+        let now = Utc::now();
+        let triage = match &project_id {
+            Some(project_id) => {
+                let members: Vec<String> = groups.iter().chain([&target]).cloned().collect();
+                let last_seen = analytics::exceptions::exception_last_seen(
+                    &store,
+                    &parquet_dir,
+                    &source,
+                    &members,
+                )?;
+                let mut triage = Vec::with_capacity(members.len());
+                for group in &members {
+                    triage.push((
+                        store.get_triage(project_id, &scoped_group(group, &source))?,
+                        last_seen.get(group).copied().unwrap_or(0),
+                    ));
+                }
+                ExceptionTriage::merged(&triage, now, merged_by.clone())
+            }
+            None => None,
+        };
+        let redirects: Vec<GroupRedirect> = groups
+            .into_iter()
+            .map(|group_id| GroupRedirect {
+                source: source.clone(),
+                group_id,
+                target: target.clone(),
+                merged_at: now,
+                merged_by: merged_by.clone(),
+                applied: false,
+            })
+            .collect();
+        store.merge_exception_groups(project_id.as_deref(), &redirects, triage.as_ref())?;
+        rules.reload(&store)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {
+            ingest::spawn_group_edits(
+                state.store.clone(),
+                state.config.storage.clone(),
+                state.rules.clone(),
+                merged,
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("exception merge task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to merge the exception groups.",
+            )
+        }
+    }
+}
+
+/// `POST /api/v1/exceptions/{group_id}/split` — move the occurrences of the
+/// addressed group matching the given patterns into a group of their own,
+/// which starts with the group's triage state.
+///
+/// The split is recorded so new occurrences follow it, and the stored ones are
+/// moved by a pass in the background, which marks the split applied.
+pub async fn split(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<SplitInput>,
+) -> HttpResponse {
+    let input = body.into_inner();
+    let split_by = acting_user(&req);
+    let source = input.source.trim().to_string();
+    if source.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "A source is required.");
+    }
+    let (message, stack) = (input.message.as_deref(), input.stack.as_deref());
+    if let Err(message) = ingest::check_split(message, stack) {
+        return json_error(StatusCode::BAD_REQUEST, message);
+    }
+
+    let rules = state.rules.clone();
+    let group_id = path.into_inner();
+    let group_id = rules.edits.redirect(&source, &group_id).unwrap_or(group_id);
+    let target = ingest::split_group_id(&group_id, message, stack);
+    let now = Utc::now();
+    let split = GroupSplit {
+        source,
+        group_id,
+        message: input.message.filter(|p| !p.trim().is_empty()),
+        stack: input.stack.filter(|p| !p.trim().is_empty()),
+        target: target.clone(),
+        split_at: now,
+        split_by: split_by.clone(),
+        applied: false,
+    };
+
+    let store = state.store.clone();
+    let project_id = Some(input.project_id).filter(|p| !p.trim().is_empty());
+    let source = split.source.clone();
+    let result = web::block(move || -> crate::errors::Result<()> {
+        let triage = match &project_id {
+            Some(project_id) => store
+                .get_triage(project_id, &scoped_group(&split.group_id, &split.source))?
+                .map(|triage| ExceptionTriage {
+                    updated_at: now,
+                    updated_by: split_by,
+                    ..triage
+                }),
+            None => None,
+        };
+        store.put_group_split(project_id.as_deref(), &split, triage.as_ref())?;
+        rules.reload(&store)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {
+            ingest::spawn_group_edits(
+                state.store.clone(),
+                state.config.storage.clone(),
+                state.rules.clone(),
+                source,
+            );
+            HttpResponse::Accepted().json(ExceptionSplit { group_id: target })
+        }
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("exception split task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to split the exception group.",
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use actix_web::body::to_bytes;
+    use actix_web::test::TestRequest;
+    use analytics_api::{AdminUser, Source, SourceKind, TriageTarget};
+
+    use crate::config::{Config, StorageConfig};
+    use crate::ingest::IngestRules;
+    use crate::ratelimit::RateLimiter;
+    use crate::store::{EventKind, StoredEvent};
+    use crate::web::helpers::oidc::OidcCache;
+
+    const SOURCE: &str = "https://a.com";
+    /// A source of another project than `p1`.
+    const OTHER: &str = "https://b.com";
+
+    fn temp_path(suffix: &str) -> PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        std::env::temp_dir().join(format!(
+            "analytics-exceptions-api-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst),
+            suffix
+        ))
+    }
+
+    /// Handler state over a fresh store, with its redb file to remove.
+    fn state() -> (web::Data<AppState>, PathBuf) {
+        let redb = temp_path("redb");
+        let store = Arc::new(Store::open(&redb).unwrap());
+        let config = Config {
+            storage: StorageConfig {
+                parquet_dir: temp_path("parquet").to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let state = AppState {
+            store: store.clone(),
+            ingest: ingest::spawn(store, config.storage.clone()),
+            http: reqwest::Client::new(),
+            oidc_cache: Arc::new(OidcCache::default()),
+            acl: Arc::new(config.web.admin.acl_filter().unwrap()),
+            rules: Arc::new(IngestRules::default()),
+            tracking_limiter: Arc::new(RateLimiter::from_rule(&config.ratelimit.tracking)),
+            unauth_limiter: Arc::new(RateLimiter::from_rule(&config.ratelimit.unauthenticated)),
+            config: Arc::new(config),
+        };
+        (web::Data::new(state), redb)
+    }
+
+    /// A request made by the signed-in `name`.
+    fn signed_in(name: &str) -> HttpRequest {
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(Authenticated {
+            user: Some(AdminUser {
+                name: name.into(),
+                email: None,
+            }),
+        });
+        req
+    }
+
+    /// Record `uri` as a website source of `project_id`.
+    fn put_source(state: &AppState, uri: &str, project_id: Option<&str>) {
+        state
+            .store
+            .put_source(&Source {
+                uri: uri.into(),
+                project_id: project_id.map(str::to_string),
+                kind: SourceKind::Website,
+                display_name: None,
+                created_at: Utc::now(),
+                first_seen: None,
+                last_seen: None,
+                aliases: Vec::new(),
+            })
+            .unwrap();
+    }
+
+    /// Handler state with `SOURCE` in `p1` and `OTHER` in `p2`.
+    fn owned_state() -> (web::Data<AppState>, PathBuf) {
+        let (state, redb) = state();
+        put_source(&state, SOURCE, Some("p1"));
+        put_source(&state, OTHER, Some("p2"));
+        (state, redb)
+    }
+
+    fn stored(state: &AppState, group_id: &str) -> ExceptionTriage {
+        state
+            .store
+            .get_triage("p1", &scoped_group(group_id, SOURCE))
+            .unwrap()
+            .expect("triage stored")
+    }
+
+    fn exception(group_id: &str, count: i64) -> GlobalException {
+        GlobalException {
+            group: ExceptionGroup {
+                group_id: group_id.into(),
+                exc_type: "Error".into(),
+                sample_message: String::new(),
+                count,
+                sessions: 0,
+                page_views: 0,
+                first_seen_ms: 0,
+                last_seen_ms: 0,
+                status: ExceptionStatus::Unresolved,
+                resolved: false,
+                muted: false,
+                snooze: None,
+                assignee: None,
+                comments: 0,
+                first_release: None,
+                regressed_in: None,
+                trend: Vec::new(),
+            },
+            project_id: None,
+            project_name: None,
+            source: "https://a.com".into(),
+        }
+    }
+
+    fn ids(page: &[GlobalException]) -> Vec<&str> {
+        page.iter().map(|e| e.group.group_id.as_str()).collect()
+    }
+
+    #[test]
+    fn pages_hand_on_a_cursor_for_the_order_they_were_read_in() {
+        // Read one past the page, in the order the query sorted them.
+        let read = vec![exception("b", 9), exception("a", 5), exception("c", 5)];
+        let (first, next) = page(read, ExceptionSort::Count, 2);
+        assert_eq!(ids(&first), vec!["b", "a"]);
+
+        let cursor = Cursor::decode(&next.unwrap(), ExceptionSort::Count).unwrap();
+        assert_eq!(
+            cursor,
+            Cursor {
+                value: 5,
+                source: "https://a.com".into(),
+                group_id: "a".into(),
+            }
+        );
+        let (last, next) = page(vec![exception("c", 5)], ExceptionSort::Count, 2);
+        assert_eq!(ids(&last), vec!["c"]);
+        assert_eq!(next, None);
+
+        // A cursor only reads back for the order it was made for.
+        let encoded = cursor.encode(ExceptionSort::Count);
+        assert_eq!(Cursor::decode(&encoded, ExceptionSort::LastSeen), None);
+        assert_eq!(Cursor::decode("not a cursor", ExceptionSort::Count), None);
+    }
+
+    #[actix_web::test]
+    async fn the_inbox_is_an_array_with_its_cursor_and_counts_in_headers() {
+        let (state, redb) = state();
+        let query = web::Query(ExceptionsQuery {
+            from: Some(0),
+            to: None,
+            q: None,
+            search: None,
+            sort: ExceptionSort::LastSeen,
+            status: None,
+            cursor: None,
+            limit: None,
+        });
+
+        let response = super::list_all(state, query).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers.get(STATUS_COUNTS_HEADER).unwrap(),
+            "unresolved=0, resolved=0, ignored=0"
+        );
+        assert!(headers.get(NEXT_CURSOR_HEADER).is_none());
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let groups: Vec<GlobalException> = serde_json::from_slice(&body).unwrap();
+        assert!(groups.is_empty());
+
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[actix_web::test]
+    async fn snoozes_mute_until_their_condition_is_met() {
+        let (state, redb) = owned_state();
+        let snooze = |source: &str, snooze: ExceptionSnooze| {
+            web::Json(SnoozeInput {
+                project_id: "p1".into(),
+                source: source.into(),
+                snooze,
+            })
+        };
+        let path = || web::Path::from("g1".to_string());
+
+        let past = ExceptionSnooze::Date { at_ms: 1_000 };
+        let response = super::snooze(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            snooze(SOURCE, past),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let never = ExceptionSnooze::Occurrences { count: 0 };
+        let response = super::snooze(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            snooze(SOURCE, never),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let tens = ExceptionSnooze::Sessions { count: 10 };
+        let response =
+            super::snooze(signed_in("Ada"), state.clone(), path(), snooze(" ", tens)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // Another project's source isn't `p1`'s to snooze.
+        let response =
+            super::snooze(signed_in("Ada"), state.clone(), path(), snooze(OTHER, tens)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(
+            state
+                .store
+                .get_triage("p1", &scoped_group("g1", OTHER))
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            state
+                .store
+                .get_triage("p1", &scoped_group("g1", SOURCE))
+                .unwrap()
+                .is_none()
+        );
+
+        let response = super::snooze(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            snooze(SOURCE, tens),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let triage = stored(&state, "g1");
+        assert!(triage.is_muted());
+        assert_eq!(triage.snooze, Some(tens));
+        assert_eq!(triage.updated_by.as_deref(), Some("Ada"));
+
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[actix_web::test]
+    async fn groups_are_assigned_only_to_known_users() {
+        let (state, redb) = owned_state();
+        let assign_on = |source: &str, assignee: Option<&str>| {
+            web::Json(AssigneeInput {
+                project_id: "p1".into(),
+                source: source.into(),
+                assignee: assignee.map(str::to_string),
+            })
+        };
+        let assign = |assignee: Option<&str>| assign_on(SOURCE, assignee);
+        let path = || web::Path::from("g1".to_string());
+
+        let response = super::assign(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            assign(Some("Grace")),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let grace = AdminUser {
+            name: "Grace".into(),
+            email: None,
+        };
+        state.store.record_user(&grace).unwrap();
+        let response = super::assign(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            assign(Some("Grace")),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(stored(&state, "g1").assignee, Some(grace));
+
+        // Another project's source isn't `p1`'s to assign.
+        let response = super::assign(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            assign_on(OTHER, Some("Grace")),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(
+            state
+                .store
+                .get_triage("p1", &scoped_group("g1", OTHER))
+                .unwrap()
+                .is_none()
+        );
+
+        // A blank assignee unassigns the group.
+        let response =
+            super::assign(signed_in("Ada"), state.clone(), path(), assign(Some(" "))).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(stored(&state, "g1").assignee, None);
+
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[actix_web::test]
+    async fn comments_are_trimmed_and_attributed() {
+        let (state, redb) = owned_state();
+        let comment_on = |source: &str, body: &str| {
+            web::Json(CommentInput {
+                project_id: "p1".into(),
+                source: source.into(),
+                body: body.into(),
+            })
+        };
+        let comment = |body: &str| comment_on(SOURCE, body);
+        let path = || web::Path::from("g1".to_string());
+
+        let response = super::comment(signed_in("Ada"), state.clone(), path(), comment("  ")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = super::comment(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            comment(" Fixed in 1.2 "),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let posted: TriageComment = serde_json::from_slice(&body).unwrap();
+        assert_eq!(posted.author.as_deref(), Some("Ada"));
+        assert_eq!(posted.body, "Fixed in 1.2");
+        assert_eq!(stored(&state, "g1").comments, vec![posted]);
+
+        // Another project's source isn't `p1`'s to comment on.
+        let response = super::comment(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            comment_on(OTHER, "Also here"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(
+            state
+                .store
+                .get_triage("p1", &scoped_group("g1", OTHER))
+                .unwrap()
+                .is_none()
+        );
+
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[actix_web::test]
+    async fn legacy_triage_notes_are_added_as_comments() {
+        let (state, redb) = owned_state();
+        let triage = |note: Option<&str>| {
+            web::Json(TriageInput {
+                project_id: "p1".into(),
+                resolved: Some(true),
+                muted: None,
+                source: SOURCE.into(),
+                note: note.map(str::to_string),
+            })
+        };
+        let path = || web::Path::from("g1".to_string());
+
+        let response =
+            super::triage(signed_in("Ada"), state.clone(), path(), triage(Some(" "))).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(stored(&state, "g1").comments.is_empty());
+
+        let response = super::triage(
+            signed_in("Ada"),
+            state.clone(),
+            path(),
+            triage(Some(" Fixed in 1.2 ")),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let stored = stored(&state, "g1");
+        assert!(stored.resolved_at.is_some());
+        assert_eq!(stored.comments.len(), 1);
+        assert_eq!(stored.comments[0].body, "Fixed in 1.2");
+        assert_eq!(stored.comments[0].author.as_deref(), Some("Ada"));
+
+        let _ = std::fs::remove_file(&redb);
+    }
+
+    #[actix_web::test]
+    async fn bulk_triage_checks_listed_groups_and_skips_unassigned_matches() {
+        let (state, redb) = state();
+        let now = Utc::now();
+        for (uri, project_id) in [
+            (SOURCE, Some("p1")),
+            (OTHER, Some("p2")),
+            ("https://c.com", None),
+        ] {
+            put_source(&state, uri, project_id);
+        }
+        let seen_ms = now.timestamp_millis() - 60_000;
+        let occurrence = |source: &str, group: &str| StoredEvent {
+            received_ms: seen_ms,
+            created_ms: seen_ms,
+            kind: EventKind::Exception,
+            source: source.into(),
+            exc_type: Some("TypeError".into()),
+            exc_message: Some("boom".into()),
+            exc_group: Some(group.into()),
+            ..Default::default()
+        };
+        state
+            .store
+            .append_events(&[occurrence(SOURCE, "g3"), occurrence("https://c.com", "g4")])
+            .unwrap();
+        let triage = |target: BulkTriageTarget| {
+            web::Json(BulkTriageInput {
+                target,
+                resolved: Some(true),
+                muted: None,
+                comment: None,
+            })
+        };
+        let outcome = |response: HttpResponse| async move {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body()).await.unwrap();
+            serde_json::from_slice::<BulkTriageResult>(&body).unwrap()
+        };
+        let target = |project_id: &str, source: &str, group_id: &str| TriageTarget {
+            project_id: project_id.into(),
+            source: source.into(),
+            group_id: group_id.into(),
+        };
+
+        // Another project's source, and a blank group, are rejected.
+        let listed = BulkTriageTarget::Groups {
+            groups: vec![
+                target("p1", SOURCE, "g1"),
+                target("p1", "https://b.com", "g2"),
+                target("p1", SOURCE, " "),
+            ],
+        };
+        let response = bulk_triage(signed_in("Ada"), state.clone(), triage(listed)).await;
+        assert_eq!(
+            outcome(response).await,
+            BulkTriageResult {
+                updated: 1,
+                skipped: 0,
+                rejected: 2,
+            }
+        );
+        assert!(stored(&state, "g1").resolved_at.is_some());
+        assert!(
+            state
+                .store
+                .get_triage("p1", &scoped_group("g2", "https://b.com"))
+                .unwrap()
+                .is_none()
+        );
+
+        // A query triages what it matches on assigned sources only.
+        let query = BulkTriageTarget::Query {
+            from: Some(seen_ms - 60_000),
+            to: Some(now.timestamp_millis() + 1),
+            q: None,
+            search: None,
+            status: None,
+        };
+        let response = bulk_triage(signed_in("Ada"), state.clone(), triage(query)).await;
+        assert_eq!(
+            outcome(response).await,
+            BulkTriageResult {
+                updated: 1,
+                skipped: 1,
+                rejected: 0,
+            }
+        );
+        assert_eq!(stored(&state, "g3").updated_by.as_deref(), Some("Ada"));
+
+        let _ = std::fs::remove_file(&redb);
+    }
+}
@@ -0,0 +1,88 @@
+//! User flow exploration: the steps sessions took before and after a chosen
+//! page or event, as Sankey nodes and links.
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, web};
+use analytics_api::FlowQuery;
+use tracing_batteries::prelude::*;
+
+use super::query::resolve_range;
+use super::{internal_error, json_error};
+use crate::analytics::flow::{
+    DEFAULT_FLOW_DEPTH, DEFAULT_FLOW_LIMIT, FlowSpec, MAX_FLOW_DEPTH, MAX_FLOW_LIMIT,
+};
+use crate::analytics::{self, filter::FieldSet};
+use crate::state::AppState;
+
+/// `GET /api/v1/flows` — the top steps before and after the events matching
+/// `anchor`, to a depth. An anchor or filter that doesn't compile, or a depth
+/// or limit out of range, is the caller's error: 400.
+pub async fn flow(state: web::Data<AppState>, query: web::Query<FlowQuery>) -> HttpResponse {
+    let query = query.into_inner();
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+
+    let filter = match query.q.as_deref() {
+        Some(q) => match analytics::filter::compile_query(q, FieldSet::Dashboard, &store) {
+            Ok(filter) => filter,
+            Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+        },
+        None => None,
+    };
+
+    let anchor = match analytics::filter::compile_query(&query.anchor, FieldSet::Dashboard, &store)
+    {
+        Ok(Some(anchor)) => anchor,
+        Ok(None) => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                "Choose a page or event to explore from.",
+            );
+        }
+        Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+    };
+
+    let depth = query.depth.map_or(DEFAULT_FLOW_DEPTH, |d| d as usize);
+    if !(1..=MAX_FLOW_DEPTH).contains(&depth) {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            format!("A flow's depth must be between 1 and {MAX_FLOW_DEPTH} steps."),
+        );
+    }
+    let limit = query.limit.map_or(DEFAULT_FLOW_LIMIT, |l| l as usize);
+    if !(1..=MAX_FLOW_LIMIT).contains(&limit) {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            format!("A flow shows between 1 and {MAX_FLOW_LIMIT} steps at each position."),
+        );
+    }
+
+    let spec = FlowSpec {
+        anchor,
+        label: query.anchor.trim().to_string(),
+        depth,
+        limit,
+    };
+
+    let result = web::block(move || {
+        let from = match query.from {
+            Some(f) if f <= 0 => analytics::earliest_event_ms(&store, &parquet_dir)?,
+            other => other,
+        };
+        let (from, to, _) = resolve_range(from, query.to, None);
+        analytics::flow::flow(&store, &parquet_dir, filter.as_ref(), &spec, from, to)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(flow)) => HttpResponse::Ok().json(flow),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("flow computation task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to compute the flow.",
+            )
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Funnel analysis: how many sessions pass through an ordered series of steps,
+//! each a filter expression in the dashboard's `q` syntax.
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, web};
+use analytics_api::FunnelQuery;
+use tracing_batteries::prelude::*;
+
+use super::query::resolve_range;
+use super::{internal_error, json_error};
+use crate::analytics::funnel::{FunnelStepSpec, MAX_FUNNEL_STEPS};
+use crate::analytics::{self, filter::FieldSet};
+use crate::state::AppState;
+
+/// `POST /api/v1/funnels` — the sessions reaching each step in order, with
+/// conversion and drop-off, optionally broken down by a dashboard property
+/// (any but `project`). The query's `q` narrows the events every step is
+/// matched against. A step or filter that doesn't compile, or an unknown breakdown, is the
+/// caller's error: 400 naming the step.
+pub async fn funnel(state: web::Data<AppState>, body: web::Json<FunnelQuery>) -> HttpResponse {
+    let query = body.into_inner();
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+
+    if query.steps.len() < 2 || query.steps.len() > MAX_FUNNEL_STEPS {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            format!("A funnel needs between 2 and {MAX_FUNNEL_STEPS} steps."),
+        );
+    }
+
+    let filter = match query.q.as_deref() {
+        Some(q) => match analytics::filter::compile_query(q, FieldSet::Dashboard, &store) {
+            Ok(filter) => filter,
+            Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+        },
+        None => None,
+    };
+
+    let mut steps = Vec::with_capacity(query.steps.len());
+    for (i, step) in query.steps.iter().enumerate() {
+        let n = i + 1;
+        let filter = match analytics::filter::compile_query(&step.q, FieldSet::Dashboard, &store) {
+            Ok(Some(filter)) => filter,
+            Ok(None) => {
+                return json_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("Step {n} needs a condition."),
+                );
+            }
+            Err(message) => {
+                return json_error(StatusCode::BAD_REQUEST, format!("Step {n}: {message}"));
+            }
+        };
+        if step.within_ms.is_some_and(|ms| ms <= 0) {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                format!("Step {n}'s time limit must be positive."),
+            );
+        }
+        let label = step
+            .label
+            .as_deref()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .unwrap_or(step.q.trim())
+            .to_string();
+        steps.push(FunnelStepSpec {
+            label,
+            filter,
+            within_ms: step.within_ms,
+        });
+    }
+
+    let breakdown = match query.breakdown.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(property) => match FieldSet::Dashboard.column(property) {
+            Some(field) => Some(field.column),
+            None => {
+                return json_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("Funnels can't be broken down by `{property}`."),
+                );
+            }
+        },
+    };
+
+    let result = web::block(move || {
+        let from = match query.from {
+            Some(f) if f <= 0 => analytics::earliest_event_ms(&store, &parquet_dir)?,
+            other => other,
+        };
+        let (from, to, _) = resolve_range(from, query.to, None);
+        analytics::funnel::funnel(
+            &store,
+            &parquet_dir,
+            filter.as_ref(),
+            &steps,
+            breakdown,
+            from,
+            to,
+        )
+    })
+    .await;
+
+    match result {
+        Ok(Ok(funnel)) => HttpResponse::Ok().json(funnel),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("funnel computation task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to compute the funnel.",
+            )
+        }
+    }
+}
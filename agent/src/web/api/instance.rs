@@ -1,7 +1,7 @@
 //! Authenticated instance/runtime information for the Settings page.
 
 use actix_web::{HttpResponse, web};
-use analytics_api::Instance;
+use analytics_api::{ExclusionRule, Instance};
 
 use crate::state::AppState;
 
@@ -19,5 +19,12 @@ pub async fn instance(state: web::Data<AppState>) -> HttpResponse {
         tracking_per_minute: cfg.ratelimit.tracking.per_minute,
         unauthenticated_per_minute: cfg.ratelimit.unauthenticated.per_minute,
         max_auto_sources: cfg.storage.max_auto_sources as u64,
+        exclusions: state
+            .rules
+            .exclusions
+            .counts()
+            .into_iter()
+            .map(|(rule, matches)| ExclusionRule { rule, matches })
+            .collect(),
     })
 }
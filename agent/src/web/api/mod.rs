@@ -8,11 +8,15 @@
 mod auth;
 mod events;
 mod exceptions;
+mod flows;
+mod funnels;
 mod instance;
 mod me;
 mod pixels;
 mod projects;
 mod query;
+mod releases;
+mod sourcemaps;
 mod sources;
 mod stats;
 mod traces;
@@ -77,14 +81,32 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route("/me", web::get().to(me::me))
                     .route("/instance", web::get().to(instance::instance))
                     .route("/stats", web::get().to(stats::stats))
+                    .route("/funnels", web::post().to(funnels::funnel))
+                    .route("/flows", web::get().to(flows::flow))
                     .route("/projects", web::get().to(projects::list))
                     .route("/projects", web::post().to(projects::create))
                     .route("/projects/{id}", web::get().to(projects::get))
                     .route("/projects/{id}", web::put().to(projects::update))
                     .route("/projects/{id}", web::delete().to(projects::delete))
+                    .route(
+                        "/projects/{id}/path-rules/preview",
+                        web::post().to(projects::preview_path_rule),
+                    )
                     .route("/sources", web::get().to(sources::list))
                     .route("/sources", web::put().to(sources::update))
                     .route("/sources", web::delete().to(sources::delete))
+                    .route("/sources/reattribute", web::post().to(sources::reattribute))
+                    .service(
+                        web::resource("/sources/sourcemaps")
+                            .app_data(web::PayloadConfig::new(sourcemaps::MAX_SOURCE_MAP))
+                            .route(web::get().to(sourcemaps::list))
+                            .route(web::put().to(sourcemaps::upload))
+                            .route(web::delete().to(sourcemaps::delete)),
+                    )
+                    .route("/releases", web::get().to(releases::list))
+                    .route("/releases/crash-free", web::get().to(releases::crash_free))
+                    .route("/releases", web::put().to(releases::put))
+                    .route("/releases", web::delete().to(releases::delete))
                     .route("/pixels", web::get().to(pixels::list_all))
                     .route("/projects/{id}/pixels", web::get().to(pixels::list))
                     .route("/projects/{id}/pixels", web::post().to(pixels::create))
@@ -93,8 +115,44 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route("/pixels/{id}", web::delete().to(pixels::delete))
                     .route("/events", web::get().to(events::detail))
                     .route("/exceptions", web::get().to(exceptions::list_all))
+                    .route(
+                        "/exceptions/discarded",
+                        web::get().to(exceptions::discarded),
+                    )
+                    .route(
+                        "/exceptions/environments",
+                        web::get().to(exceptions::environments),
+                    )
+                    .route(
+                        "/exceptions/assignees",
+                        web::get().to(exceptions::assignees),
+                    )
+                    .route(
+                        "/exceptions/triage",
+                        web::post().to(exceptions::bulk_triage),
+                    )
                     .route("/exceptions/{group}", web::get().to(exceptions::detail))
                     .route("/exceptions/{group}", web::patch().to(exceptions::triage))
+                    .route(
+                        "/exceptions/{group}/merge",
+                        web::post().to(exceptions::merge),
+                    )
+                    .route(
+                        "/exceptions/{group}/split",
+                        web::post().to(exceptions::split),
+                    )
+                    .route(
+                        "/exceptions/{group}/snooze",
+                        web::put().to(exceptions::snooze),
+                    )
+                    .route(
+                        "/exceptions/{group}/assignee",
+                        web::put().to(exceptions::assign),
+                    )
+                    .route(
+                        "/exceptions/{group}/comments",
+                        web::post().to(exceptions::comment),
+                    )
                     .route("/traces/{session}", web::get().to(traces::detail)),
             ),
     );
@@ -1,15 +1,27 @@
-//! Project CRUD. Per-project statistics live on the unified `/stats` endpoint
-//! (as a `project=` filter) rather than a project-scoped route.
+//! Project CRUD (including the path and fingerprint rules, inbound exception
+//! filters, sampling limits and goals stored on a project) and path rule
+//! previews.
+//! Per-project statistics live on the unified `/stats` endpoint (as a
+//! `project=` filter) rather than a project-scoped route.
+
+use std::collections::HashSet;
 
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, web};
-use analytics_api::{Project, ProjectInput};
+use analytics_api::{PathRule, PathRulePreview, Project, ProjectInput};
 use chrono::Utc;
 use tracing_batteries::prelude::*;
 
 use super::{internal_error, json_error};
+use crate::analytics;
+use crate::ingest::{self, CompiledFingerprintRule, CompiledPathRule};
 use crate::state::AppState;
 
+/// How far back a path rule preview samples pathnames.
+const PREVIEW_WINDOW_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+/// How many distinct pathnames a preview returns.
+const PREVIEW_LIMIT: u32 = 200;
+
 pub async fn list(state: web::Data<AppState>) -> HttpResponse {
     match state.store.list_projects() {
         Ok(mut projects) => {
@@ -31,6 +43,9 @@ pub async fn create(state: web::Data<AppState>, body: web::Json<ProjectInput>) -
         Ok(true) => return name_conflict(&name),
         Err(err) => return internal_error(err),
     }
+    if let Err(message) = validate_rules(&input) {
+        return json_error(StatusCode::BAD_REQUEST, message);
+    }
     let project = Project {
         id: ulid::Ulid::generate().to_string(),
         slug: input
@@ -39,7 +54,14 @@ pub async fn create(state: web::Data<AppState>, body: web::Json<ProjectInput>) -
             .unwrap_or_else(|| slugify(&name)),
         name,
         created_at: Utc::now(),
+        path_rules: input.path_rules.unwrap_or_default(),
+        keep_original_path: input.keep_original_path.unwrap_or(false),
+        fingerprint_rules: input.fingerprint_rules.unwrap_or_default(),
+        inbound_filters: input.inbound_filters.unwrap_or_default(),
+        exception_sampling: input.exception_sampling.unwrap_or_default(),
+        goals: input.goals.unwrap_or_default(),
     };
+    // A new project has no sources yet, so the rule caches are unaffected.
     match state.store.put_project(&project) {
         Ok(()) => HttpResponse::Created().json(project),
         Err(err) => internal_error(err),
@@ -74,32 +96,99 @@ pub async fn update(
             Err(err) => return internal_error(err),
         }
     }
+    if let Err(message) = validate_rules(&input) {
+        return json_error(StatusCode::BAD_REQUEST, message);
+    }
+    let regroup = input
+        .fingerprint_rules
+        .as_ref()
+        .is_some_and(|rules| *rules != existing.fingerprint_rules);
     let updated = Project {
         slug: input
             .slug
             .filter(|s| !s.trim().is_empty())
             .unwrap_or(existing.slug),
         name: if name.is_empty() { existing.name } else { name },
+        path_rules: input.path_rules.unwrap_or(existing.path_rules),
+        keep_original_path: input
+            .keep_original_path
+            .unwrap_or(existing.keep_original_path),
+        fingerprint_rules: input
+            .fingerprint_rules
+            .unwrap_or(existing.fingerprint_rules),
+        inbound_filters: input.inbound_filters.unwrap_or(existing.inbound_filters),
+        exception_sampling: input
+            .exception_sampling
+            .unwrap_or(existing.exception_sampling),
+        goals: input.goals.unwrap_or(existing.goals),
         ..existing
     };
-    match state.store.put_project(&updated) {
-        Ok(()) => HttpResponse::Ok().json(updated),
-        Err(err) => internal_error(err),
+
+    let (store, rules) = (state.store.clone(), state.rules.clone());
+    let project = updated.clone();
+    let result = web::block(move || -> crate::errors::Result<HashSet<String>> {
+        store.put_project(&project)?;
+        rules.reload(&store)?;
+        if !regroup {
+            return Ok(HashSet::new());
+        }
+        Ok(analytics::project_source_uris(&store, &project.id)?
+            .into_iter()
+            .collect())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(sources)) => {
+            // New fingerprint rules apply to the project's history too, so a fixed
+            // group doesn't leave its past occurrences behind.
+            ingest::spawn_regroup(
+                state.store.clone(),
+                state.config.storage.clone(),
+                state.rules.clone(),
+                sources,
+            );
+            HttpResponse::Ok().json(updated)
+        }
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("project update task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to update the project.",
+            )
+        }
     }
 }
 
 pub async fn delete(state: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
     let id = path.into_inner();
-    let store = state.store.clone();
+    let (store, rules) = (state.store.clone(), state.rules.clone());
     // Atomic cascade: unassign the project's sources and delete its pixels in the
     // same write transaction that removes the project, so a partial failure can't
     // leave a half-deleted project. Historical events remain under their (now
     // unassigned) sources.
-    let result = web::block(move || store.delete_project_cascade(&id)).await;
+    let result = web::block(move || -> crate::errors::Result<Option<HashSet<String>>> {
+        let sources = analytics::project_source_uris(&store, &id)?;
+        let existed = store.delete_project_cascade(&id)?;
+        rules.reload(&store)?;
+        Ok(existed.then(|| sources.into_iter().collect()))
+    })
+    .await;
 
     match result {
-        Ok(Ok(true)) => HttpResponse::NoContent().finish(),
-        Ok(Ok(false)) => json_error(StatusCode::NOT_FOUND, "Project not found."),
+        Ok(Ok(Some(sources))) => {
+            // The unassigned sources no longer follow the project's fingerprint
+            // rules.
+            ingest::spawn_regroup(
+                state.store.clone(),
+                state.config.storage.clone(),
+                state.rules.clone(),
+                sources,
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Ok(None)) => json_error(StatusCode::NOT_FOUND, "Project not found."),
         Ok(Err(err)) => internal_error(err),
         Err(err) => {
             error!("project delete task failed: {err}");
@@ -111,6 +200,86 @@ pub async fn delete(state: web::Data<AppState>, path: web::Path<String>) -> Http
     }
 }
 
+/// `POST /api/v1/projects/{id}/path-rules/preview` — what a (not yet saved) path
+/// rule would do to the project's most-viewed pathnames of the past week.
+pub async fn preview_path_rule(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<PathRule>,
+) -> HttpResponse {
+    let rule = match CompiledPathRule::new(&body) {
+        Ok(rule) => rule,
+        Err(message) => return json_error(StatusCode::BAD_REQUEST, message),
+    };
+    let id = path.into_inner();
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+
+    let result = web::block(
+        move || -> crate::errors::Result<Option<Vec<PathRulePreview>>> {
+            if store.get_project(&id)?.is_none() {
+                return Ok(None);
+            }
+            let sources = analytics::project_source_uris(&store, &id)?;
+            let to = Utc::now().timestamp_millis();
+            let from = to - PREVIEW_WINDOW_MS;
+            let paths = analytics::recent_pathnames(
+                &store,
+                &parquet_dir,
+                &sources,
+                from,
+                to,
+                PREVIEW_LIMIT,
+            )?;
+            Ok(Some(
+                paths
+                    .into_iter()
+                    .map(|(pathname, count)| PathRulePreview {
+                        rewritten: rule.rewrite(&pathname),
+                        pathname,
+                        count,
+                    })
+                    .collect(),
+            ))
+        },
+    )
+    .await;
+
+    match result {
+        Ok(Ok(Some(preview))) => HttpResponse::Ok().json(preview),
+        Ok(Ok(None)) => json_error(StatusCode::NOT_FOUND, "Project not found."),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("path rule preview task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to preview the path rule.",
+            )
+        }
+    }
+}
+
+/// Compile every submitted path, fingerprint and inbound filter rule, so a bad
+/// pattern is rejected on save rather than silently skipped at ingest.
+fn validate_rules(input: &ProjectInput) -> Result<(), String> {
+    for rule in input.path_rules.as_deref().unwrap_or_default() {
+        CompiledPathRule::new(rule)?;
+    }
+    for rule in input.fingerprint_rules.as_deref().unwrap_or_default() {
+        CompiledFingerprintRule::new(rule)?;
+    }
+    if let Some(filters) = &input.inbound_filters {
+        ingest::validate_inbound_filters(filters)?;
+    }
+    if let Some(sampling) = &input.exception_sampling {
+        ingest::validate_sampling(sampling)?;
+    }
+    if let Some(goals) = &input.goals {
+        analytics::goals::validate_goals(goals)?;
+    }
+    Ok(())
+}
+
 /// Whether a project other than `except` already uses `name`. Names are
 /// compared case-insensitively because the filter language resolves
 /// `project == "<name>"` case-insensitively — a duplicate would make that
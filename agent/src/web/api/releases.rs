@@ -0,0 +1,151 @@
+//! Releases of a source's application: list them with their exception health
+//! and crash-free sessions, declare or update one (a deploy pipeline's hook),
+//! and delete one. Versions first seen in page loads or exception reports are
+//! registered automatically; declaring a release sets its real deploy time and
+//! notes.
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, web};
+use analytics_api::{Release, ReleaseInput};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing_batteries::prelude::*;
+
+use super::query::resolve_range;
+use super::{internal_error, json_error};
+use crate::analytics;
+use crate::ingest::MAX_APP_FIELD;
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct ReleasesQuery {
+    uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct CrashFreeQuery {
+    uri: String,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ReleaseRef {
+    uri: String,
+    version: String,
+}
+
+/// `GET /api/v1/releases?uri=…` — a source's releases, newest deploy first,
+/// with their exception counts and the groups each introduced.
+pub async fn list(state: web::Data<AppState>, query: web::Query<ReleasesQuery>) -> HttpResponse {
+    let uri = query.into_inner().uri;
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+    let result =
+        web::block(move || analytics::exceptions::release_summaries(&store, &parquet_dir, &uri))
+            .await;
+    match result {
+        Ok(Ok(releases)) => HttpResponse::Ok().json(releases),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("release listing task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load releases.",
+            )
+        }
+    }
+}
+
+/// `GET /api/v1/releases/crash-free?uri=…&from=…&to=…` — a source's
+/// crash-free session rate over the range (default the last 7 days), overall
+/// and per release, for gating a rollout on it.
+pub async fn crash_free(
+    state: web::Data<AppState>,
+    query: web::Query<CrashFreeQuery>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let (from, to, _) = resolve_range(query.from, query.to, None);
+    let store = state.store.clone();
+    let parquet_dir = state.config.storage.parquet_dir.clone();
+    let result = web::block(move || {
+        analytics::exceptions::crash_free_sessions(&store, &parquet_dir, &[query.uri], from, to)
+    })
+    .await;
+    match result {
+        Ok(Ok(rows)) => HttpResponse::Ok().json(rows),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("crash-free sessions task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load crash-free sessions.",
+            )
+        }
+    }
+}
+
+/// `PUT /api/v1/releases` — declare a release, or update the deploy time and
+/// notes of an existing one (including one registered on first sight).
+pub async fn put(state: web::Data<AppState>, body: web::Json<ReleaseInput>) -> HttpResponse {
+    let input = body.into_inner();
+    let version = input.version.trim();
+    if version.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "A version is required.");
+    }
+    if version.len() > MAX_APP_FIELD {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            format!("Versions are limited to {MAX_APP_FIELD} bytes."),
+        );
+    }
+    match state.store.get_source(&input.source) {
+        Ok(Some(_)) => {}
+        Ok(None) => return json_error(StatusCode::NOT_FOUND, "Source not found."),
+        Err(err) => return internal_error(err),
+    }
+
+    let existing = match state.store.get_release(&input.source, version) {
+        Ok(existing) => existing,
+        Err(err) => return internal_error(err),
+    };
+    let now = Utc::now();
+    let notes = input
+        .notes
+        .map(|n| Some(n).filter(|n| !n.trim().is_empty()));
+    let (status, release) = match existing {
+        Some(existing) => (
+            StatusCode::OK,
+            Release {
+                deployed_at: input.deployed_at.unwrap_or(existing.deployed_at),
+                notes: notes.unwrap_or(existing.notes),
+                auto: false,
+                ..existing
+            },
+        ),
+        None => (
+            StatusCode::CREATED,
+            Release {
+                source: input.source.clone(),
+                version: version.to_string(),
+                deployed_at: input.deployed_at.unwrap_or(now),
+                notes: notes.flatten(),
+                created_at: now,
+                auto: false,
+            },
+        ),
+    };
+    match state.store.put_release(&release) {
+        Ok(()) => HttpResponse::build(status).json(release),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// `DELETE /api/v1/releases?uri=…&version=…` — forget a release.
+pub async fn delete(state: web::Data<AppState>, query: web::Query<ReleaseRef>) -> HttpResponse {
+    match state.store.delete_release(&query.uri, &query.version) {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => json_error(StatusCode::NOT_FOUND, "Release not found."),
+        Err(err) => internal_error(err),
+    }
+}
@@ -0,0 +1,149 @@
+//! Source maps for symbolicating a source's JavaScript exceptions: list, upload
+//! and delete the maps of each release. Like the other source endpoints the
+//! source is identified by its URI in the query string; the map itself is the
+//! raw request body, so a build can upload it with
+//! `curl --data-binary @main.3f9a.js.map`.
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, web};
+use serde::Deserialize;
+use tracing_batteries::prelude::*;
+
+use super::{internal_error, json_error};
+use crate::ingest::SourceMaps;
+use crate::state::AppState;
+
+/// The largest source map accepted. Maps of large bundles run to tens of
+/// megabytes.
+pub const MAX_SOURCE_MAP: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct SourceMapsQuery {
+    uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct SourceMapRef {
+    uri: String,
+    /// The `app_version` the source's exceptions report for this release.
+    version: String,
+    /// The minified bundle's file name. Defaults to the map's own `file` on
+    /// upload.
+    file: Option<String>,
+}
+
+/// `GET /api/v1/sources/sourcemaps?uri=…` — the maps uploaded for a source.
+pub async fn list(state: web::Data<AppState>, query: web::Query<SourceMapsQuery>) -> HttpResponse {
+    let rules = state.rules.clone();
+    let result = web::block(move || rules.sourcemaps.list(&query.uri)).await;
+    match result {
+        Ok(Ok(maps)) => HttpResponse::Ok().json(maps),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("source map listing task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An internal error occurred.",
+            )
+        }
+    }
+}
+
+/// `PUT /api/v1/sources/sourcemaps?uri=…&version=…[&file=…]` — upload (or
+/// replace) the map of one bundle of a release. Exceptions are symbolicated
+/// with it from then on; occurrences already grouped on minified frames stay
+/// in their groups.
+pub async fn upload(
+    state: web::Data<AppState>,
+    query: web::Query<SourceMapRef>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let query = query.into_inner();
+    if let Err(message) = SourceMaps::check_segment("app version", &query.version) {
+        return json_error(StatusCode::BAD_REQUEST, message);
+    }
+    let map = match sourcemap::decode_slice(&body) {
+        Ok(map) => map,
+        Err(err) => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                format!("The body is not a valid source map: {err}"),
+            );
+        }
+    };
+    let embedded = match &map {
+        sourcemap::DecodedMap::Regular(sm) => sm.get_file(),
+        sourcemap::DecodedMap::Index(smi) => smi.get_file(),
+        sourcemap::DecodedMap::Hermes(smh) => smh.get_file(),
+    };
+    // The map's `file` may be a path or URL; frames are matched on the name.
+    let Some(file) = query
+        .file
+        .as_deref()
+        .or_else(|| embedded.and_then(|f| f.rsplit('/').next()))
+        .map(str::to_string)
+    else {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            "The source map doesn't name its bundle; pass the bundle's file name as `file`.",
+        );
+    };
+    if let Err(message) = SourceMaps::check_segment("file name", &file) {
+        return json_error(StatusCode::BAD_REQUEST, message);
+    }
+    match state.store.get_source(&query.uri) {
+        Ok(Some(_)) => {}
+        Ok(None) => return json_error(StatusCode::NOT_FOUND, "Source not found."),
+        Err(err) => return internal_error(err),
+    }
+
+    let rules = state.rules.clone();
+    let result = web::block(move || {
+        rules
+            .sourcemaps
+            .put(&query.uri, &query.version, &file, &body)
+    })
+    .await;
+    match result {
+        Ok(Ok(info)) => HttpResponse::Created().json(info),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("source map upload task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An internal error occurred.",
+            )
+        }
+    }
+}
+
+/// `DELETE /api/v1/sources/sourcemaps?uri=…&version=…&file=…`
+pub async fn delete(state: web::Data<AppState>, query: web::Query<SourceMapRef>) -> HttpResponse {
+    let query = query.into_inner();
+    let Some(file) = query.file else {
+        return json_error(StatusCode::BAD_REQUEST, "A file name is required.");
+    };
+    for (kind, value) in [
+        ("app version", query.version.as_str()),
+        ("file name", file.as_str()),
+    ] {
+        if let Err(message) = SourceMaps::check_segment(kind, value) {
+            return json_error(StatusCode::BAD_REQUEST, message);
+        }
+    }
+    let rules = state.rules.clone();
+    let result =
+        web::block(move || rules.sourcemaps.delete(&query.uri, &query.version, &file)).await;
+    match result {
+        Ok(Ok(true)) => HttpResponse::NoContent().finish(),
+        Ok(Ok(false)) => json_error(StatusCode::NOT_FOUND, "Source map not found."),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("source map delete task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An internal error occurred.",
+            )
+        }
+    }
+}
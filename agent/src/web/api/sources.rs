@@ -1,13 +1,18 @@
-//! Source management: list every source (incl. unassigned) and assign/update or
-//! delete one. Sources are identified by their URI via a query parameter to avoid
+//! Source management: list every source (incl. unassigned), assign/update or
+//! delete one, and re-attribute an alias's historical events to its canonical
+//! source. Sources are identified by their URI via a query parameter to avoid
 //! encoding a URI (with its `://`) in the path.
 
+use std::collections::HashSet;
+
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, web};
-use analytics_api::SourceInput;
+use analytics_api::{Reattribution, SourceInput};
 use serde::Deserialize;
+use tracing_batteries::prelude::*;
 
 use super::{internal_error, json_error};
+use crate::ingest::{self, HostPattern, normalize_alias, regroup_sources, reindex_exceptions};
 use crate::state::AppState;
 
 #[derive(Deserialize)]
@@ -30,12 +35,21 @@ pub async fn update(
     query: web::Query<SourceRef>,
     body: web::Json<SourceInput>,
 ) -> HttpResponse {
-    let input = body.into_inner();
+    let mut input = body.into_inner();
+    if let Some(aliases) = input.aliases.take() {
+        match validate_aliases(&state, &query.uri, &aliases) {
+            Ok(aliases) => input.aliases = Some(aliases),
+            Err(response) => return response,
+        }
+    }
     // Read-modify-write in a single transaction so concurrent edits can't clobber.
-    let result = state.store.mutate_source(&query.uri, move |source| {
+    let mut reassigned = false;
+    let result = state.store.mutate_source(&query.uri, |source| {
         if let Some(project_id) = input.project_id {
             // An empty string unassigns the source from any project.
-            source.project_id = Some(project_id).filter(|p| !p.trim().is_empty());
+            let project_id = Some(project_id).filter(|p| !p.trim().is_empty());
+            reassigned = project_id != source.project_id;
+            source.project_id = project_id;
         }
         if let Some(kind) = input.kind {
             source.kind = kind;
@@ -43,19 +57,164 @@ pub async fn update(
         if let Some(display_name) = input.display_name {
             source.display_name = Some(display_name).filter(|n| !n.trim().is_empty());
         }
+        if let Some(aliases) = input.aliases {
+            source.aliases = aliases;
+        }
+    });
+    // A (re)assignment changes which project's path rules the source follows, and
+    // alias edits change how hostnames resolve.
+    let result = result.and_then(|source| {
+        state.rules.reload(&state.store)?;
+        Ok(source)
     });
 
     match result {
-        Ok(Some(source)) => HttpResponse::Ok().json(source),
+        Ok(Some(source)) => {
+            // Its history moves to the new project's fingerprint rules too.
+            if reassigned {
+                ingest::spawn_regroup(
+                    state.store.clone(),
+                    state.config.storage.clone(),
+                    state.rules.clone(),
+                    HashSet::from([source.uri.clone()]),
+                );
+            }
+            HttpResponse::Ok().json(source)
+        }
         Ok(None) => json_error(StatusCode::NOT_FOUND, "Source not found."),
         Err(err) => internal_error(err),
     }
 }
 
 pub async fn delete(state: web::Data<AppState>, query: web::Query<SourceRef>) -> HttpResponse {
-    match state.store.delete_source(&query.uri) {
+    let result = state.store.delete_source(&query.uri).and_then(|existed| {
+        state.rules.reload(&state.store)?;
+        Ok(existed)
+    });
+    match result {
         Ok(true) => HttpResponse::NoContent().finish(),
         Ok(false) => json_error(StatusCode::NOT_FOUND, "Source not found."),
         Err(err) => internal_error(err),
     }
 }
+
+/// Move every stored event from the source's aliases to the source itself, then
+/// remove the alias sources that are now empty. New events already follow the
+/// aliases; this brings the history along, with the aliases' exception triage,
+/// merges, splits and releases, and re-groups the moved exceptions under the
+/// source's project's fingerprint rules.
+pub async fn reattribute(state: web::Data<AppState>, query: web::Query<SourceRef>) -> HttpResponse {
+    let canonical = query.into_inner().uri;
+    let (store, rules) = (state.store.clone(), state.rules.clone());
+    let storage = state.config.storage.clone();
+
+    let result = web::block(move || -> crate::errors::Result<Option<Reattribution>> {
+        let Some(source) = store.get_source(&canonical)? else {
+            return Ok(None);
+        };
+        let patterns: Vec<HostPattern> = source
+            .aliases
+            .iter()
+            .filter_map(|alias| HostPattern::new(alias).ok())
+            .collect();
+        let sources = store.list_sources()?;
+        // Mirrors ingest: wildcards never capture a source assigned to a project.
+        let assigned: HashSet<String> = sources
+            .iter()
+            .filter(|s| s.project_id.is_some())
+            .map(|s| s.uri.clone())
+            .collect();
+        let remap = |uri: &str| -> Option<String> {
+            let host = uri.strip_prefix("https://")?;
+            let moves = uri != canonical
+                && patterns.iter().any(|pattern| match pattern {
+                    HostPattern::Exact(_) => pattern.matches(host),
+                    HostPattern::Wildcard(_) => !assigned.contains(uri) && pattern.matches(host),
+                });
+            moves.then(|| canonical.clone())
+        };
+
+        // Compaction must not archive (or drop) events mid-pass.
+        let archive = store.lock_archive();
+        let events = store.reattribute_cold(&storage.parquet_dir, &remap)?
+            + store.reattribute_hot(&remap)?;
+        let records = store.reattribute_records(&remap, source.project_id.as_deref())?;
+        if events > 0 || records > 0 {
+            // Ingest and the re-grouping pass follow the moved merges and splits.
+            rules.reload(&store)?;
+            let regrouped = regroup_sources(
+                &store,
+                &storage,
+                &rules.grouping,
+                &rules.edits,
+                &HashSet::from([canonical.clone()]),
+                &archive,
+            )?;
+            if regrouped == 0 && events > 0 {
+                reindex_exceptions(&store, &storage, &archive)?;
+            }
+        }
+        let mut moved = Vec::new();
+        for alias in sources.iter().filter(|s| remap(&s.uri).is_some()) {
+            store.delete_source(&alias.uri)?;
+            moved.push(alias.uri.clone());
+        }
+        rules.reload(&store)?;
+        Ok(Some(Reattribution {
+            events: events as u64,
+            sources: moved,
+        }))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(Some(outcome))) => HttpResponse::Ok().json(outcome),
+        Ok(Ok(None)) => json_error(StatusCode::NOT_FOUND, "Source not found."),
+        Ok(Err(err)) => internal_error(err),
+        Err(err) => {
+            error!("source re-attribution task failed: {err}");
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An internal error occurred.",
+            )
+        }
+    }
+}
+
+/// Normalize and validate a source's new aliases: each must parse, must not be
+/// the source's own hostname, and an exact alias may only belong to one source.
+/// Returns the aliases as they should be stored, or the error response.
+fn validate_aliases(
+    state: &AppState,
+    uri: &str,
+    aliases: &[String],
+) -> Result<Vec<String>, HttpResponse> {
+    let own_host = uri.strip_prefix("https://");
+    let mut normalized: Vec<String> = Vec::new();
+    for alias in aliases.iter().filter(|a| !a.trim().is_empty()) {
+        HostPattern::new(alias).map_err(|message| json_error(StatusCode::BAD_REQUEST, message))?;
+        let alias = normalize_alias(alias);
+        if own_host == Some(alias.as_str()) {
+            return Err(json_error(
+                StatusCode::BAD_REQUEST,
+                format!("`{alias}` is this source's own hostname."),
+            ));
+        }
+        if !normalized.contains(&alias) {
+            normalized.push(alias);
+        }
+    }
+
+    let sources = state.store.list_sources().map_err(internal_error)?;
+    for other in sources.iter().filter(|s| s.uri != uri) {
+        if let Some(alias) = normalized.iter().find(|alias| {
+            !alias.contains('*') && other.aliases.iter().any(|a| normalize_alias(a) == **alias)
+        }) {
+            return Err(json_error(
+                StatusCode::CONFLICT,
+                format!("`{alias}` is already an alias of {}.", other.uri),
+            ));
+        }
+    }
+    Ok(normalized)
+}
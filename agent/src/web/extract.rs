@@ -3,6 +3,8 @@
 
 use actix_web::HttpRequest;
 
+use crate::ingest::ClientHints;
+
 /// The client IP, honouring `X-Forwarded-For`/`X-Real-IP` only when the operator
 /// has opted into trusting a reverse proxy. For rate limiting only.
 ///
@@ -42,6 +44,15 @@ pub fn header(req: &HttpRequest, name: &str) -> Option<String> {
         .map(str::to_string)
 }
 
+/// The low-entropy User-Agent Client Hints, for UA classification only.
+pub fn client_hints(req: &HttpRequest) -> ClientHints {
+    ClientHints {
+        brands: header(req, "sec-ch-ua"),
+        mobile: header(req, "sec-ch-ua-mobile"),
+        platform: header(req, "sec-ch-ua-platform"),
+    }
+}
+
 /// Whether the original request reached us over HTTPS. `X-Forwarded-Proto` is only
 /// consulted when the deployment is configured to trust its proxy.
 pub fn is_https(trust_proxy: bool, req: &HttpRequest) -> bool {
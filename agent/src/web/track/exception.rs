@@ -3,6 +3,7 @@
 use actix_web::{HttpRequest, HttpResponse, web};
 use analytics_api::ExceptionReport;
 use chrono::Utc;
+use tracing_batteries::prelude::*;
 
 use crate::ingest;
 use crate::state::AppState;
@@ -19,10 +20,26 @@ pub async fn exception(
     }
 
     let user_agent = extract::header(&req, "user-agent").unwrap_or_default();
+    let hints = extract::client_hints(&req);
     let received_ms = Utc::now().timestamp_millis();
 
-    if let Some(event) = ingest::build_exception(payload.into_inner(), &user_agent, received_ms) {
-        state.ingest.submit(event);
+    // Symbolication reads and decodes source maps, so the report is built off
+    // the async workers.
+    let rules = state.rules.clone();
+    let built = web::block(move || {
+        ingest::build_exception(
+            payload.into_inner(),
+            &user_agent,
+            &hints,
+            received_ms,
+            &rules,
+        )
+    })
+    .await;
+    match built {
+        Ok(Some(event)) => state.ingest.submit(event),
+        Ok(None) => {}
+        Err(err) => error!("exception report task failed: {err}"),
     }
 
     HttpResponse::NoContent().finish()
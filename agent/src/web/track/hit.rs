@@ -20,16 +20,19 @@ pub async fn hit(
     }
 
     let user_agent = extract::header(&req, "user-agent").unwrap_or_default();
+    let hints = extract::client_hints(&req);
     let accept_language = extract::header(&req, "accept-language");
     let received_ms = Utc::now().timestamp_millis();
 
-    // Bots / unparseable URLs yield `None`; respond 204 either way so the outcome is
+    // Bots / unparseable URLs / excluded events yield `None`; respond 204 either way so the outcome is
     // not observable to the caller.
     if let Some(event) = ingest::build_event(
         payload.into_inner(),
         &user_agent,
+        &hints,
         accept_language.as_deref(),
         received_ms,
+        &state.rules,
     ) {
         state.ingest.submit(event);
     }
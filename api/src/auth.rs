@@ -8,6 +8,14 @@ pub struct AdminUser {
     pub email: Option<String>,
 }
 
+impl AdminUser {
+    /// What the user is known by when assigning work: their email when the
+    /// identity provider shares one, otherwise their display name.
+    pub fn key(&self) -> &str {
+        self.email.as_deref().unwrap_or(&self.name)
+    }
+}
+
 /// A CSRF token issued to the frontend for the double-submit protection scheme.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CsrfToken {
@@ -17,6 +17,28 @@ pub struct EventBreakdowns {
     pub languages: Vec<crate::CountRow>,
 }
 
+/// How the values an event reported distribute across the same dimensions as
+/// [`EventBreakdowns`]. Only occurrences carrying a value contribute, so the
+/// lists are empty for events that never report one.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct EventValueBreakdowns {
+    pub sources: Vec<ValueRow>,
+    pub pages: Vec<ValueRow>,
+    pub browsers: Vec<ValueRow>,
+    pub operating_systems: Vec<ValueRow>,
+    pub devices: Vec<ValueRow>,
+    pub countries: Vec<ValueRow>,
+    pub languages: Vec<ValueRow>,
+}
+
+/// A `key → value aggregates` row (an empty `key` means the dimension was
+/// absent), ordered by descending total.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueRow {
+    pub key: String,
+    pub value: crate::ValueSummary,
+}
+
 /// A distinct example within an event's occurrences: those sharing the same
 /// reporter-supplied metadata, collapsed into one representative with a count.
 /// The detail page scrubs through these rather than paging a flat list.
@@ -50,7 +72,7 @@ pub struct EventVariant {
 /// `GET /api/v1/events?name=…`: the aggregate (with an occurrence trend on the
 /// same bucket grid as exception trends), dimension distributions, distinct
 /// metadata exemplars, and the sessions it occurred in.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EventDetail {
     pub name: String,
     pub count: i64,
@@ -62,6 +84,17 @@ pub struct EventDetail {
     pub trend: Vec<i64>,
     #[serde(default)]
     pub breakdowns: EventBreakdowns,
+    /// Aggregates of the values the occurrences reported; `None` when none
+    /// did. `per_visitor` is measured against the distinct sessions the event
+    /// occurred in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<crate::ValueSummary>,
+    /// Reported values summed on the same bucket grid as `trend` (empty when
+    /// the event carries no values).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub value_trend: Vec<f64>,
+    #[serde(default)]
+    pub value_breakdowns: EventValueBreakdowns,
     pub variants: Vec<EventVariant>,
     /// The most recent sessions the event occurred in (newest first).
     #[serde(default)]
@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::AdminUser;
+
 /// The collapsed, display-oriented triage status of an exception group, derived
 /// from the two independent triage axes (resolution and suppression). Muting takes
 /// precedence, so a muted group reads as `Ignored` regardless of whether it is also
@@ -40,6 +43,13 @@ pub struct ExceptionReport {
     /// Whether the exception was handled (vs an unhandled error/rejection).
     #[serde(rename = "h", default)]
     pub handled: bool,
+    /// The severity the reporter assigned, read leniently (see
+    /// [`ExceptionLevel::parse`]); reports without one are stored as `error`.
+    #[serde(rename = "lv", default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// The deployment environment that reported (`production`, `staging`, …).
+    #[serde(rename = "env", default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
     /// The reporting application's version (the tracker's `data-app-version`).
     /// The application itself is identified by the report's hostname — the
     /// same `source` attribution every other event uses.
@@ -50,6 +60,93 @@ pub struct ExceptionReport {
     pub fingerprint: Option<String>,
     #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<BTreeMap<String, String>>,
+    /// What happened in the page leading up to the exception, oldest first.
+    #[serde(rename = "bc", default, skip_serializing_if = "Vec::is_empty")]
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
+
+/// The severity of an exception occurrence, least severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExceptionLevel {
+    Debug,
+    Info,
+    Warning,
+    #[default]
+    Error,
+    Fatal,
+}
+
+impl ExceptionLevel {
+    pub const ALL: [ExceptionLevel; 5] = [
+        ExceptionLevel::Debug,
+        ExceptionLevel::Info,
+        ExceptionLevel::Warning,
+        ExceptionLevel::Error,
+        ExceptionLevel::Fatal,
+    ];
+
+    /// The stored (and queried) name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExceptionLevel::Debug => "debug",
+            ExceptionLevel::Info => "info",
+            ExceptionLevel::Warning => "warning",
+            ExceptionLevel::Error => "error",
+            ExceptionLevel::Fatal => "fatal",
+        }
+    }
+
+    /// Read a reported level case-insensitively, accepting the aliases
+    /// common logging libraries use (`warn`, `critical`, …).
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "debug" | "trace" => Some(ExceptionLevel::Debug),
+            "info" | "log" | "notice" => Some(ExceptionLevel::Info),
+            "warning" | "warn" => Some(ExceptionLevel::Warning),
+            "error" | "err" => Some(ExceptionLevel::Error),
+            "fatal" | "critical" | "panic" => Some(ExceptionLevel::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// What a [`Breadcrumb`] recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BreadcrumbKind {
+    Navigation,
+    Click,
+    Console,
+    Fetch,
+    /// A kind this agent doesn't know (from a newer tracker); dropped at
+    /// ingest rather than failing the report.
+    #[serde(other)]
+    Other,
+}
+
+/// One step of the trail the tracker keeps of recent page activity, sent with
+/// an exception report and stored with the occurrence. Short keys, like the
+/// report's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Breadcrumb {
+    /// How long before the report the step happened, in milliseconds. Relative
+    /// to the report so the client's clock doesn't matter.
+    #[serde(rename = "a")]
+    pub age_ms: u64,
+    #[serde(rename = "k")]
+    pub kind: BreadcrumbKind,
+    /// The URL navigated to, the element clicked, the console message, or the
+    /// request's method and URL.
+    #[serde(rename = "m")]
+    pub message: String,
+    /// The console level (`log`, `warn`, `error`, …) of a console breadcrumb.
+    #[serde(rename = "l", default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// The response status of a fetch breadcrumb; `0` when the request failed
+    /// without a response.
+    #[serde(rename = "st", default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
 }
 
 /// The number of buckets in an exception group's occurrence [`ExceptionGroup::trend`].
@@ -77,6 +174,13 @@ pub struct ExceptionGroup {
     pub exc_type: String,
     pub sample_message: String,
     pub count: i64,
+    /// Distinct sessions with an occurrence: how many visits the failure
+    /// actually hit, however often it fired within each.
+    #[serde(default)]
+    pub sessions: i64,
+    /// Distinct page views with an occurrence.
+    #[serde(default)]
+    pub page_views: i64,
     pub first_seen_ms: i64,
     pub last_seen_ms: i64,
     /// The collapsed display status ([`ExceptionStatus::derive`] of the two axes
@@ -89,8 +193,22 @@ pub struct ExceptionGroup {
     /// Whether the group is suppressed. Orthogonal to `resolved`.
     #[serde(default)]
     pub muted: bool,
+    /// What lifts the group's mute by itself, when it is snoozed rather than
+    /// muted for good.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub note: Option<String>,
+    pub snooze: Option<ExceptionSnooze>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<AdminUser>,
+    /// How many comments the group's triage thread has.
+    #[serde(default)]
+    pub comments: u32,
+    /// The release its earliest occurrence in the query range reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_release: Option<String>,
+    /// The release a resolved group recurred on, when that release is newer
+    /// than the last one it was seen on before being resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regressed_in: Option<String>,
     /// Occurrence counts over the query range, split into [`TREND_BUCKETS`]
     /// equal buckets (oldest first), for the frequency sparkline.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -143,6 +261,36 @@ pub struct ExceptionVariant {
     /// linking the exemplar to its session trace.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub session_id: Option<String>,
+    /// The breadcrumbs of the most recent occurrence of this variant that
+    /// carried any, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// The stack parsed into frames, innermost first (Python tracebacks keep
+    /// their outermost-first order). Empty when the stack isn't recognised.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<StackFrame>,
+}
+
+/// One frame of a parsed stack trace. Every part is optional: runtimes report
+/// different subsets (a JVM frame has no column, an anonymous browser frame no
+/// function).
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StackFrame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// The Rust module path or JVM class the function belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    /// Whether the frame is the application's own code, rather than a
+    /// runtime, standard library or dependency frame.
+    #[serde(default)]
+    pub in_app: bool,
 }
 
 /// How a group's occurrences distribute across key dimensions (empty keys mean
@@ -158,10 +306,18 @@ pub struct ExceptionBreakdowns {
     pub browsers: Vec<crate::CountRow>,
     pub operating_systems: Vec<crate::CountRow>,
     pub devices: Vec<crate::CountRow>,
+    /// Severity levels; occurrences stored before levels existed aggregate
+    /// under the empty sentinel.
+    #[serde(default)]
+    pub levels: Vec<crate::CountRow>,
+    /// Reporting environments; the empty sentinel collects occurrences that
+    /// named none.
+    #[serde(default)]
+    pub environments: Vec<crate::CountRow>,
 }
 
 /// An exception group with its dimension distributions and distinct examples.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExceptionGroupDetail {
     pub group: ExceptionGroup,
     #[serde(default)]
@@ -172,6 +328,54 @@ pub struct ExceptionGroupDetail {
     /// tolerates payloads from agents predating traces.
     #[serde(default)]
     pub traces: Vec<crate::TraceSummary>,
+    /// Other groups on the same source that look like the same failure, most
+    /// similar first: candidates for a merge.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub similar: Vec<SimilarGroup>,
+    /// The group's triage thread, oldest first.
+    #[serde(default)]
+    pub comments: Vec<TriageComment>,
+}
+
+/// An exception group suggested as similar to another, with how alike their
+/// normalized messages and stack frames are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarGroup {
+    pub group_id: String,
+    pub exc_type: String,
+    pub sample_message: String,
+    pub count: i64,
+    pub last_seen_ms: i64,
+    /// The estimated Jaccard similarity of the two groups' features, in `[0, 1]`.
+    pub score: f64,
+}
+
+/// How many of a source's sessions (page-load visits) ended without an
+/// unhandled exception, overall or for one release. A session belongs to the
+/// release its page loads report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrashFreeSessions {
+    pub source: String,
+    /// The release, or `None` for the source's row across every release.
+    /// Sessions whose page loads report no version count under `Some("")`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_version: Option<String>,
+    pub sessions: i64,
+    /// Sessions with at least one unhandled exception.
+    pub crashed_sessions: i64,
+    /// The share of `sessions` without one, in `[0, 1]`.
+    pub crash_free_rate: f64,
+}
+
+/// How many exception reports one of a project's inbound filters has
+/// discarded since the server started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscardedExceptions {
+    pub project_id: String,
+    pub project_name: String,
+    /// The preset's label or the rule's expression.
+    pub filter: String,
+    pub discarded: u64,
 }
 
 /// An exception group annotated with the project it belongs to, for the global
@@ -188,6 +392,109 @@ pub struct GlobalException {
     pub source: String,
 }
 
+/// The orders the exceptions inbox can be listed in, each descending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExceptionSort {
+    #[default]
+    LastSeen,
+    FirstSeen,
+    Count,
+    Sessions,
+}
+
+impl ExceptionSort {
+    pub const ALL: [ExceptionSort; 4] = [
+        ExceptionSort::LastSeen,
+        ExceptionSort::FirstSeen,
+        ExceptionSort::Count,
+        ExceptionSort::Sessions,
+    ];
+
+    /// The query-string (and cursor) name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExceptionSort::LastSeen => "last_seen",
+            ExceptionSort::FirstSeen => "first_seen",
+            ExceptionSort::Count => "count",
+            ExceptionSort::Sessions => "sessions",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExceptionSort::LastSeen => "Last seen",
+            ExceptionSort::FirstSeen => "First seen",
+            ExceptionSort::Count => "Events",
+            ExceptionSort::Sessions => "Sessions",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sort| sort.as_str() == value)
+    }
+}
+
+/// The `GET /api/v1/exceptions` response header carrying the cursor of the
+/// following page; absent on the last one.
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+/// The `GET /api/v1/exceptions` response header carrying the inbox's
+/// [`ExceptionStatusCounts`].
+pub const STATUS_COUNTS_HEADER: &str = "X-Status-Counts";
+
+/// How many of the groups matching an inbox query are in each status, whatever
+/// status the page itself was narrowed to, for the status tabs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ExceptionStatusCounts {
+    pub unresolved: u64,
+    pub resolved: u64,
+    pub ignored: u64,
+}
+
+impl ExceptionStatusCounts {
+    /// The [`STATUS_COUNTS_HEADER`] value, e.g.
+    /// `unresolved=3, resolved=1, ignored=0`.
+    pub fn to_header(&self) -> String {
+        format!(
+            "unresolved={}, resolved={}, ignored={}",
+            self.unresolved, self.resolved, self.ignored
+        )
+    }
+
+    /// Read a [`STATUS_COUNTS_HEADER`] value; unknown or malformed entries are
+    /// skipped, leaving their count at zero.
+    pub fn from_header(value: &str) -> Self {
+        let mut counts = Self::default();
+        for entry in value.split(',') {
+            let Some((status, count)) = entry.trim().split_once('=') else {
+                continue;
+            };
+            let Ok(count) = count.trim().parse() else {
+                continue;
+            };
+            match status.trim() {
+                "unresolved" => counts.unresolved = count,
+                "resolved" => counts.resolved = count,
+                "ignored" => counts.ignored = count,
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+/// One page of the global exceptions inbox, as a client reads it: the groups
+/// are the response body, a JSON array, and the cursor and status counts come
+/// in the [`NEXT_CURSOR_HEADER`] and [`STATUS_COUNTS_HEADER`] headers.
+/// `next_cursor` is passed back as `cursor` for the following page.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExceptionPage {
+    pub groups: Vec<GlobalException>,
+    pub next_cursor: Option<String>,
+    pub status_counts: ExceptionStatusCounts,
+}
+
 /// Payload for updating an exception group's triage state. Triage is scoped to
 /// the group's source — the same fingerprint on two applications is two
 /// independent failures.
@@ -205,15 +512,177 @@ pub struct TriageInput {
     /// `None` leaves it unchanged.
     #[serde(default)]
     pub muted: Option<bool>,
-    #[serde(default)]
-    pub note: Option<String>,
     /// The source URI the triaged group was seen on.
     pub source: String,
+    /// Triage used to carry a single free-text note; one still sent here is
+    /// added to the group's comment thread, as stored notes were. New clients
+    /// post to `POST /api/v1/exceptions/{group_id}/comments` instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Payload for triaging many exception groups at once. As with
+/// [`TriageInput`], an axis left `None` is left unchanged on every group; a
+/// `comment` is added to each group's thread.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkTriageInput {
+    pub target: BulkTriageTarget,
+    #[serde(default)]
+    pub resolved: Option<bool>,
+    #[serde(default)]
+    pub muted: Option<bool>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// The groups a bulk triage applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum BulkTriageTarget {
+    /// These groups.
+    Groups { groups: Vec<TriageTarget> },
+    /// Every group the inbox lists for this query, as `GET /exceptions`
+    /// takes it.
+    Query {
+        #[serde(default)]
+        from: Option<i64>,
+        #[serde(default)]
+        to: Option<i64>,
+        #[serde(default)]
+        q: Option<String>,
+        #[serde(default)]
+        search: Option<String>,
+        #[serde(default)]
+        status: Option<ExceptionStatus>,
+    },
+}
+
+/// One source-scoped exception group of a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriageTarget {
+    pub project_id: String,
+    pub source: String,
+    pub group_id: String,
+}
+
+/// The outcome of a bulk triage: how many groups were updated, how many
+/// matched but were skipped because their source isn't assigned to a project,
+/// and how many listed groups were rejected: blank, or on a source that isn't
+/// the named project's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BulkTriageResult {
+    pub updated: u64,
+    pub skipped: u64,
+    #[serde(default)]
+    pub rejected: u64,
+}
+
+/// What lifts a snooze: a snoozed group is muted until the condition is met,
+/// then unmutes by itself. Occurrences and sessions are counted from when the
+/// group was snoozed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "until", rename_all = "snake_case")]
+pub enum ExceptionSnooze {
+    /// Until a moment has passed.
+    Date { at_ms: i64 },
+    /// Until the group occurs this many more times.
+    Occurrences { count: u64 },
+    /// Until the group's occurrences hit this many more sessions.
+    Sessions { count: u64 },
+}
+
+/// Payload for snoozing an exception group until `snooze` is met. Snoozing
+/// mutes the group; unmuting (or muting for good) through [`TriageInput`]
+/// drops the condition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnoozeInput {
+    pub project_id: String,
+    pub source: String,
+    pub snooze: ExceptionSnooze,
+}
+
+/// Payload for assigning an exception group to a signed-in user, by their
+/// [`AdminUser::key`]; `None` unassigns it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssigneeInput {
+    pub project_id: String,
+    pub source: String,
+    #[serde(default)]
+    pub assignee: Option<String>,
+}
+
+/// A comment on an exception group's triage thread.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriageComment {
+    /// The signed-in user who wrote it; absent without sign-in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payload for adding a comment to an exception group's triage thread.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommentInput {
+    pub project_id: String,
+    pub source: String,
+    pub body: String,
+}
+
+/// Payload for merging exception groups into the addressed one. Merges are
+/// per source, like every group; the merged groups' occurrences are shown under
+/// the surviving group from then on, and their triage state is combined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeInput {
+    pub project_id: String,
+    pub source: String,
+    /// The groups to fold into the addressed group.
+    pub groups: Vec<String>,
+}
+
+/// Payload for splitting the occurrences matching every given pattern out of
+/// the addressed group into a new one, which starts with the group's triage
+/// state. Patterns are case-insensitive globs over the whole value (`*` any run
+/// of characters, `?` a single one), as in fingerprint rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitInput {
+    pub project_id: String,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+}
+
+/// The group a split created; its stored occurrences move in the background.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExceptionSplit {
+    pub group_id: String,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::summary_line;
+    use super::{ExceptionStatusCounts, summary_line};
+
+    #[test]
+    fn status_counts_round_trip_through_their_header() {
+        let counts = ExceptionStatusCounts {
+            unresolved: 3,
+            resolved: 1,
+            ignored: 12,
+        };
+        assert_eq!(
+            ExceptionStatusCounts::from_header(&counts.to_header()),
+            counts
+        );
+        assert_eq!(
+            ExceptionStatusCounts::from_header("ignored=2, snoozed=4, resolved=x"),
+            ExceptionStatusCounts {
+                ignored: 2,
+                ..Default::default()
+            }
+        );
+    }
 
     #[test]
     fn single_line_message_is_returned_trimmed() {
@@ -244,12 +713,18 @@ mod tests {
             exc_type: "T".into(),
             sample_message: "m".into(),
             count: 1,
+            sessions: 1,
+            page_views: 1,
             first_seen_ms: 0,
             last_seen_ms: 0,
             status: ExceptionStatus::Unresolved,
             resolved,
             muted,
-            note: None,
+            snooze: None,
+            assignee: None,
+            comments: 0,
+            first_release: None,
+            regressed_in: None,
             trend: Vec::new(),
         };
         assert_eq!(
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for `GET /api/v1/flows`: how sessions move to and from
+/// the events matching `anchor`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FlowQuery {
+    /// Range start (epoch millis, inclusive); defaults to 7 days before `to`.
+    /// `0` means "all time", as for `GET /api/v1/stats`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,
+    /// Range end (epoch millis, exclusive); defaults to now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,
+    /// A filter every considered event must match (e.g. `project == "Site"`);
+    /// absent or blank means unfiltered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    /// The page or event to explore from, as a filter expression
+    /// (`path == "/pricing"`, `event == "signup_started"`).
+    pub anchor: String,
+    /// How many steps to follow before and after the anchor; defaults to 3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+    /// How many distinct steps to keep at each position, the rest merging
+    /// into one "other" node; defaults to 5.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Where sessions came from before reaching the anchor and went after it, as
+/// the nodes and weighted links of a Sankey diagram.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Flow {
+    /// Sessions that reached the anchor.
+    pub sessions: i64,
+    /// Ordered by position, then by sessions (an "other" node last).
+    pub nodes: Vec<FlowNode>,
+    pub links: Vec<FlowLink>,
+}
+
+/// A step at one position relative to the anchor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowNode {
+    /// Steps from the anchor: negative before it, `0` the anchor itself.
+    pub position: i32,
+    pub kind: FlowNodeKind,
+    /// The pathname or event name; the anchor expression for the anchor, and
+    /// empty for an "other" node.
+    pub label: String,
+    /// Sessions passing through this step.
+    pub sessions: i64,
+}
+
+/// What a [`FlowNode`] stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowNodeKind {
+    Anchor,
+    Page,
+    Event,
+    /// The steps at a position beyond the top few.
+    Other,
+}
+
+/// Sessions moving from one node to the next, by index into [`Flow::nodes`].
+/// Links always run from a position to the one after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowLink {
+    pub source: usize,
+    pub target: usize,
+    pub sessions: i64,
+}
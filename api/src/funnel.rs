@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// The body of `POST /api/v1/funnels`: the ordered steps a session must pass
+/// through, each a filter expression over the dashboard's event dimensions
+/// (`path like "/pricing*"`, `event == "signup_completed"`), over a time range.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FunnelQuery {
+    /// Range start (epoch millis, inclusive); defaults to 7 days before `to`.
+    /// `0` means "all time", as for `GET /api/v1/stats`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,
+    /// Range end (epoch millis, exclusive); defaults to now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,
+    /// A filter every considered event must match (e.g. `project == "Site"`),
+    /// so each step only matches events that satisfy it too; absent or blank
+    /// means unfiltered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    pub steps: Vec<FunnelStepInput>,
+    /// A dashboard property (`country`, `referrer`, `utm_campaign`, …) to break
+    /// conversion down by; any `q` property but `project`. A session counts
+    /// under the first value it reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<String>,
+}
+
+/// One step of a [`FunnelQuery`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FunnelStepInput {
+    /// The filter expression an event must match to reach this step.
+    pub q: String,
+    /// A display name; defaults to the expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The longest a session may take from the previous step to this one, in
+    /// milliseconds; unlimited when absent. Ignored on the first step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub within_ms: Option<i64>,
+}
+
+/// A computed funnel: how many sessions reached each step in order.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Funnel {
+    pub steps: Vec<FunnelStep>,
+    /// Per-value step counts for the requested breakdown, the values entering
+    /// the most sessions first; empty without one.
+    #[serde(default)]
+    pub breakdown: Vec<FunnelBreakdownRow>,
+}
+
+/// One step of a [`Funnel`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunnelStep {
+    pub label: String,
+    /// Sessions that reached this step (having reached every earlier one).
+    pub sessions: i64,
+    /// Fraction (0..1) of the sessions entering the funnel that got this far.
+    pub conversion: f64,
+    /// Fraction (0..1) of the previous step's sessions that went on to this
+    /// one; `1` on the first step.
+    pub step_conversion: f64,
+    /// Sessions that reached the previous step but not this one.
+    pub dropped: i64,
+    /// Median time from the previous step to this one, in milliseconds;
+    /// `None` on the first step and when no session got here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub median_ms: Option<i64>,
+}
+
+/// A breakdown value's sessions at each step, index-aligned with
+/// [`Funnel::steps`]. Sessions that never reported the property count under
+/// the empty key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunnelBreakdownRow {
+    pub key: String,
+    pub sessions: Vec<i64>,
+}
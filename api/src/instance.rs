@@ -22,4 +22,16 @@ pub struct Instance {
     pub unauthenticated_per_minute: u32,
     /// Ceiling on auto-registered (unassigned) sources.
     pub max_auto_sources: u64,
+    /// The configured `ingest.exclude` rules and how many events each has dropped
+    /// since the server started.
+    #[serde(default)]
+    pub exclusions: Vec<ExclusionRule>,
+}
+
+/// One ingest exclusion rule and the number of events it has removed. An event
+/// is counted against the first rule it matches only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionRule {
+    pub rule: String,
+    pub matches: u64,
 }
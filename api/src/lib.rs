@@ -7,32 +7,46 @@
 mod auth;
 mod event;
 mod exception;
+mod flow;
+mod funnel;
 mod health;
 mod instance;
 mod pixel;
 mod project;
+mod release;
 mod source;
 mod stats;
 mod trace;
 mod track;
 
 pub use auth::{AdminUser, CsrfToken};
-pub use event::{EventBreakdowns, EventDetail, EventVariant};
+pub use event::{EventBreakdowns, EventDetail, EventValueBreakdowns, EventVariant, ValueRow};
 pub use exception::{
-    ExceptionBreakdowns, ExceptionGroup, ExceptionGroupDetail, ExceptionReport, ExceptionStatus,
-    ExceptionVariant, GlobalException, TREND_BUCKETS, TriageInput, summary_line,
+    AssigneeInput, Breadcrumb, BreadcrumbKind, BulkTriageInput, BulkTriageResult, BulkTriageTarget,
+    CommentInput, CrashFreeSessions, DiscardedExceptions, ExceptionBreakdowns, ExceptionGroup,
+    ExceptionGroupDetail, ExceptionLevel, ExceptionPage, ExceptionReport, ExceptionSnooze,
+    ExceptionSort, ExceptionSplit, ExceptionStatus, ExceptionStatusCounts, ExceptionVariant,
+    GlobalException, MergeInput, NEXT_CURSOR_HEADER, STATUS_COUNTS_HEADER, SimilarGroup,
+    SnoozeInput, SplitInput, StackFrame, TREND_BUCKETS, TriageComment, TriageInput, TriageTarget,
+    summary_line,
 };
+pub use flow::{Flow, FlowLink, FlowNode, FlowNodeKind, FlowQuery};
+pub use funnel::{Funnel, FunnelBreakdownRow, FunnelQuery, FunnelStep, FunnelStepInput};
 pub use health::Health;
-pub use instance::Instance;
+pub use instance::{ExclusionRule, Instance};
 pub use pixel::{Pixel, PixelInput};
-pub use project::{Project, ProjectInput};
+pub use project::{
+    ExceptionSampling, FingerprintRule, Goal, GoalTarget, InboundFilterPreset, InboundFilters,
+    PathPatternKind, PathRule, PathRulePreview, Project, ProjectInput,
+};
+pub use release::{Release, ReleaseInput, ReleaseIssue, ReleaseMarker, ReleaseSummary};
 pub use source::{
-    Source, SourceInput, SourceKind, SourceScheme, app_source, default_kind, pixel_id_of,
-    pixel_source, source_label, source_scheme, website_source,
+    Reattribution, Source, SourceInput, SourceKind, SourceMapInfo, SourceScheme, app_source,
+    default_kind, pixel_id_of, pixel_source, source_label, source_scheme, website_source,
 };
 pub use stats::{
-    BreakdownRow, Breakdowns, CountRow, Dashboard, DashboardQuery, MetricSummary, TimeSeriesPoint,
-    VersionRow,
+    BreakdownRow, Breakdowns, CountRow, Dashboard, DashboardQuery, GoalConversions, GoalSummary,
+    MetricSummary, SCROLL_MARKS, ScrollDepth, TimeSeriesPoint, ValueSummary, VersionRow,
 };
 pub use trace::{SessionTrace, TraceEvent, TraceEventKind, TraceSummary};
 pub use track::{BeaconKind, TrackEvent};
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -9,12 +11,209 @@ pub struct Project {
     pub name: String,
     pub slug: String,
     pub created_at: DateTime<Utc>,
+    /// Rewrites applied to the pathnames of this project's page events at ingest,
+    /// in order; the first matching rule wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_rules: Vec<PathRule>,
+    /// Keep the pre-rewrite pathname alongside the template. Off by default: the
+    /// raw path may carry identifiers the rules exist to collapse.
+    #[serde(default)]
+    pub keep_original_path: bool,
+    /// Server-side exception grouping rules for this project's sources, in
+    /// order; the first matching group rule wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fingerprint_rules: Vec<FingerprintRule>,
+    /// Storm protection for the exceptions of this project's sources.
+    #[serde(default)]
+    pub exception_sampling: ExceptionSampling,
+    /// Exception reports from this project's sources dropped before storage.
+    #[serde(default)]
+    pub inbound_filters: InboundFilters,
+    /// What counts as a conversion on this project's sources; the dashboard
+    /// reports each goal's conversions overall and per breakdown row.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<Goal>,
 }
 
 /// Payload for creating or updating a project.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct ProjectInput {
     pub name: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub slug: Option<String>,
+    /// Replaces the project's path rules when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_rules: Option<Vec<PathRule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_original_path: Option<bool>,
+    /// Replaces the project's fingerprint rules when present; the project's
+    /// stored exceptions are re-grouped if they change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint_rules: Option<Vec<FingerprintRule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exception_sampling: Option<ExceptionSampling>,
+    /// Replaces the project's inbound filters when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inbound_filters: Option<InboundFilters>,
+    /// Replaces the project's goals when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goals: Option<Vec<Goal>>,
+}
+
+/// How a [`PathRule`] pattern is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathPatternKind {
+    /// `*` matches a single path segment, `**` any number of them.
+    #[default]
+    Glob,
+    /// A regular expression that must match the whole pathname; the template may
+    /// refer to its capture groups (`$1`, `${name}`).
+    Regex,
+}
+
+/// Collapses pathnames matching `pattern` into `template`, e.g.
+/// `/orders/*/items/*` → `/orders/:id/items/:id`, so identifier-bearing URLs
+/// aggregate into one page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRule {
+    #[serde(default)]
+    pub kind: PathPatternKind,
+    pub pattern: String,
+    pub template: String,
+}
+
+/// One recently seen pathname and what a previewed [`PathRule`] would turn it
+/// into (`None` when the rule does not match it).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRulePreview {
+    pub pathname: String,
+    pub rewritten: Option<String>,
+    /// Page views of this pathname over the preview window.
+    pub count: i64,
+}
+
+/// A conversion: a session converts on a goal when it views a matching page
+/// or reports a matching event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Goal {
+    /// Unique within the project.
+    pub name: String,
+    #[serde(flatten)]
+    pub target: GoalTarget,
+}
+
+/// What a [`Goal`] matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "match", rename_all = "snake_case")]
+pub enum GoalTarget {
+    /// A page view whose pathname matches `pattern`, read as a [`PathRule`]
+    /// pattern of the same `kind`.
+    Path {
+        #[serde(default)]
+        kind: PathPatternKind,
+        pattern: String,
+    },
+    /// A custom event or pixel hit named `name` whose metadata carries every
+    /// given key with exactly the given value.
+    Event {
+        name: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        metadata: BTreeMap<String, String>,
+    },
+}
+
+/// A server-side exception grouping rule, so a bad group can be fixed without
+/// shipping new client code. Patterns are case-insensitive globs over the whole
+/// value: `*` matches any run of characters, `?` a single one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FingerprintRule {
+    /// Exceptions matching every given pattern share the group named `group`,
+    /// whatever their stack. Takes precedence over a client-sent fingerprint.
+    Group {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exc_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        group: String,
+    },
+    /// Stack frames whose module matches `module` are left out of the
+    /// fingerprint. Frames without a module (browser JavaScript) are matched on
+    /// their file instead, e.g. `*/vendor/*`.
+    IgnoreFrames { module: String },
+}
+
+/// Per-group exception storm protection. Once a group has had `limit`
+/// occurrences within a `window_secs` window, further occurrences in that
+/// window are still counted but only a sample of them is stored, thinning out
+/// the longer the storm lasts. Each stored sample records the rate it was kept
+/// at, so occurrence counts stay accurate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExceptionSampling {
+    /// Occurrences of a group stored in full per window; `0` stores every
+    /// occurrence.
+    pub limit: u32,
+    pub window_secs: u32,
+}
+
+impl Default for ExceptionSampling {
+    fn default() -> Self {
+        Self {
+            limit: 100,
+            window_secs: 60,
+        }
+    }
+}
+
+/// Inbound filters for a project's exceptions: reports matching any of them
+/// are dropped at ingest, before they are stored or grouped.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct InboundFilters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<InboundFilterPreset>,
+    /// Filter expressions over the exception query vocabulary, e.g.
+    /// `type == "ChunkLoadError" && handled == true`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+}
+
+impl InboundFilters {
+    pub fn is_empty(&self) -> bool {
+        self.presets.is_empty() && self.rules.is_empty()
+    }
+}
+
+/// A built-in inbound filter for well-known exception noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InboundFilterPreset {
+    /// Errors raised by browser extensions' injected scripts.
+    BrowserExtensions,
+    /// `ResizeObserver loop limit exceeded` and its "undelivered
+    /// notifications" variant, benign and unactionable.
+    ResizeObserver,
+    /// The opaque `Script error.` browsers report for cross-origin scripts.
+    ScriptError,
+    /// Errors whose every located frame is in a script served from another
+    /// site (ads, analytics, widgets).
+    ThirdPartyFrames,
+}
+
+impl InboundFilterPreset {
+    pub const ALL: [InboundFilterPreset; 4] = [
+        InboundFilterPreset::BrowserExtensions,
+        InboundFilterPreset::ResizeObserver,
+        InboundFilterPreset::ScriptError,
+        InboundFilterPreset::ThirdPartyFrames,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InboundFilterPreset::BrowserExtensions => "Browser extensions",
+            InboundFilterPreset::ResizeObserver => "ResizeObserver loop",
+            InboundFilterPreset::ScriptError => "Script error.",
+            InboundFilterPreset::ThirdPartyFrames => "Third-party scripts",
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A release of a source's application: one `app_version` its page loads and
+/// exceptions report, with when it was deployed. Releases are created through the API
+/// (typically by a deploy pipeline) or automatically when a version is first
+/// seen, in which case the deploy time is that first sighting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    pub source: String,
+    /// The `app_version` reported by the release (together with `source`, the
+    /// storage key).
+    pub version: String,
+    /// When the release went out. Releases of a source are ordered by it, so
+    /// it decides which of two versions is the newer.
+    pub deployed_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Whether the release was registered on first sight of its version
+    /// rather than through the API.
+    #[serde(default)]
+    pub auto: bool,
+}
+
+/// Payload for creating (or updating) a release. A field left `None` keeps its
+/// current value; a new release without a deploy time is deployed now.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ReleaseInput {
+    pub source: String,
+    pub version: String,
+    #[serde(default)]
+    pub deployed_at: Option<DateTime<Utc>>,
+    /// Send an empty string to clear the notes.
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// An exception group first seen in a release.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseIssue {
+    pub group_id: String,
+    pub exc_type: String,
+    pub sample_message: String,
+    /// Occurrences across every release.
+    pub count: i64,
+    pub first_seen_ms: i64,
+}
+
+/// A release with its exception health: how many occurrences and distinct
+/// groups it reported, its crash-free sessions, and which groups it
+/// introduced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseSummary {
+    #[serde(flatten)]
+    pub release: Release,
+    pub exceptions: i64,
+    pub groups: i64,
+    /// Sessions whose page loads reported this release.
+    #[serde(default)]
+    pub sessions: i64,
+    /// Of those, the sessions with an unhandled exception.
+    #[serde(default)]
+    pub crashed_sessions: i64,
+    /// The share of `sessions` without an unhandled exception; `None` until the
+    /// release has sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crash_free_rate: Option<f64>,
+    /// Groups whose earliest occurrence reported this release, most frequent
+    /// first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_issues: Vec<ReleaseIssue>,
+}
+
+/// A deploy inside a dashboard's window, drawn as a marker on its time series.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseMarker {
+    pub source: String,
+    pub version: String,
+    pub deployed_ms: i64,
+}
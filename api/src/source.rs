@@ -43,6 +43,11 @@ pub struct Source {
     pub first_seen: Option<DateTime<Utc>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_seen: Option<DateTime<Utc>>,
+    /// Hostnames reported under this source instead of their own, e.g.
+    /// `example.co.uk` or `app-preview-*.example.dev` (`*` matches within one
+    /// label). Matching hits and exceptions are attributed to `uri` at ingest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
 }
 
 /// Payload for assigning/updating a source's project, kind, and display name.
@@ -55,6 +60,32 @@ pub struct SourceInput {
     pub kind: Option<SourceKind>,
     #[serde(default)]
     pub display_name: Option<String>,
+    /// Replaces the source's hostname aliases when present.
+    #[serde(default)]
+    pub aliases: Option<Vec<String>>,
+}
+
+/// The outcome of re-attributing historical events from a source's aliases to
+/// the source itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reattribution {
+    /// How many stored events moved to the canonical source.
+    pub events: u64,
+    /// The alias source URIs whose events moved (and which were then removed).
+    pub sources: Vec<String>,
+}
+
+/// A source map uploaded to symbolicate a source's exceptions, for one bundle
+/// of one release.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMapInfo {
+    pub source: String,
+    /// The `app_version` reported with the exceptions it applies to.
+    pub app_version: String,
+    /// The minified bundle's file name, as it appears in stack frames.
+    pub file: String,
+    pub size_bytes: u64,
+    pub uploaded_ms: i64,
 }
 
 /// Canonical website source URI for a hostname.
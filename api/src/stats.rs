@@ -52,6 +52,53 @@ pub struct MetricSummary {
     /// Median time on page, in milliseconds; `None` if unmeasured.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub median_duration_ms: Option<i64>,
+    /// Median time on page while the tab was visible, in milliseconds; `None`
+    /// if unmeasured (e.g. only trackers predating engaged time reported).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub median_engaged_ms: Option<i64>,
+    /// How far readers scrolled; `None` if unmeasured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll_depth: Option<ScrollDepth>,
+    /// Aggregates of the numeric values custom events reported; `None` when
+    /// none did. `per_visitor` is measured against `visitors`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ValueSummary>,
+}
+
+/// The scroll-depth thresholds [`ScrollDepth::reached`] counts views against,
+/// in percent of the page's height.
+pub const SCROLL_MARKS: [i64; 4] = [25, 50, 75, 100];
+
+/// How far into their pages readers scrolled, from the maximum depth each
+/// page view reported on unload.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ScrollDepth {
+    /// Page views that reported a scroll depth.
+    pub samples: i64,
+    /// Median maximum scroll depth, in percent.
+    pub median: i64,
+    /// Page views that scrolled at least as far as each of [`SCROLL_MARKS`]
+    /// (index-aligned).
+    pub reached: Vec<i64>,
+}
+
+/// Aggregates of the optional numeric `value` custom events carry (order value,
+/// cart size, signup revenue, …) over some slice of events.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ValueSummary {
+    /// Sum of every reported value.
+    pub total: f64,
+    /// How many events reported a value (the average's denominator).
+    pub count: i64,
+    /// Mean of the reported values.
+    pub average: f64,
+    /// `total` spread over the slice's visitors; `None` when it has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_visitor: Option<f64>,
+    /// The currency every valued event agreed on; `None` when unreported or
+    /// mixed (the sums are then plain numbers).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
 }
 
 /// One point in a metrics time series (bucket start, in epoch millis).
@@ -85,7 +132,7 @@ pub struct CountRow {
 /// `visitors` is daily-unique visitors for visitor-stable dimensions and
 /// daily-unique *page* views for the pages breakdown; referrer/UTM rows carry
 /// landing-page attribution (the flag rides on the first load of the day).
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BreakdownRow {
     pub key: String,
     pub visitors: i64,
@@ -94,6 +141,55 @@ pub struct BreakdownRow {
     /// unassigned breakdowns; dimension columns are null on such events).
     #[serde(default)]
     pub events: i64,
+    /// Aggregates of the values custom events reported under this key;
+    /// `per_visitor` is measured against the row's `visitors` (against the
+    /// whole slice's visitors on event-name rows, which carry none).
+    /// `serde(default)` tolerates payloads from agents predating values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ValueSummary>,
+    /// Median maximum scroll depth (percent) of the views under this key;
+    /// reported on the pages breakdown only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll_depth: Option<i64>,
+    /// Each goal's conversions among the sessions under this key,
+    /// index-aligned with [`Dashboard::goals`]; reported on the page-view
+    /// breakdowns only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<GoalConversions>,
+}
+
+/// How many sessions converted on a goal, out of the sessions on the goal's
+/// project's sources.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct GoalConversions {
+    pub conversions: i64,
+    pub sessions: i64,
+    /// `conversions / sessions` (0..1); `0` without sessions.
+    pub rate: f64,
+}
+
+impl GoalConversions {
+    pub fn new(conversions: i64, sessions: i64) -> Self {
+        Self {
+            conversions,
+            sessions,
+            rate: if sessions > 0 {
+                conversions as f64 / sessions as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// A goal's conversions over the whole filtered slice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoalSummary {
+    pub project_id: String,
+    pub project_name: String,
+    pub goal: String,
+    #[serde(flatten)]
+    pub conversions: GoalConversions,
 }
 
 /// One row of the client-versions breakdown. A version number is only
@@ -114,7 +210,7 @@ pub struct VersionRow {
 
 /// Every dashboard breakdown, computed over the same filtered event set so the
 /// panels agree with the headline metrics and with each other.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Breakdowns {
     pub pages: Vec<BreakdownRow>,
     pub referrers: Vec<BreakdownRow>,
@@ -131,6 +227,10 @@ pub struct Breakdowns {
     pub utm_sources: Vec<BreakdownRow>,
     pub utm_mediums: Vec<BreakdownRow>,
     pub utm_campaigns: Vec<BreakdownRow>,
+    /// Acquisition channels. `serde(default)` tolerates payloads from agents
+    /// predating the column.
+    #[serde(default)]
+    pub channels: Vec<BreakdownRow>,
     /// Custom/pixel events keyed by event name (`events` carries the count;
     /// page-view columns are zero). `serde(default)` tolerates payloads from
     /// agents predating the column.
@@ -167,4 +267,11 @@ pub struct Dashboard {
     /// predating traces.
     #[serde(default)]
     pub traces: Vec<crate::TraceSummary>,
+    /// Deploys of the charted sources within the window, oldest first, for
+    /// markers on the time series.
+    #[serde(default)]
+    pub releases: Vec<crate::ReleaseMarker>,
+    /// The goals of the projects in view, with their conversions.
+    #[serde(default)]
+    pub goals: Vec<GoalSummary>,
 }
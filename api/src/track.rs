@@ -3,7 +3,7 @@ use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 
 /// What the tracking beacon reports. Short JSON keys keep the beacon payload small.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TrackEvent {
     /// Per-page-load beacon id, linking the events of a single page view.
     #[serde(rename = "b")]
@@ -33,12 +33,32 @@ pub struct TrackEvent {
     /// Time on page in milliseconds, sent on unload.
     #[serde(rename = "m", default, skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<i64>,
+    /// Time on page while the tab was visible, in milliseconds, sent on
+    /// unload alongside the wall-clock `duration_ms`.
+    #[serde(rename = "g", default, skip_serializing_if = "Option::is_none")]
+    pub engaged_ms: Option<i64>,
+    /// The deepest point of the page scrolled into view, as a percentage of
+    /// its height, sent on unload.
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub scroll_depth: Option<i64>,
     /// Custom event name (when `kind` is `custom`).
     #[serde(rename = "n", default, skip_serializing_if = "Option::is_none")]
     pub event_name: Option<String>,
     /// Custom event metadata.
     #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<BTreeMap<String, String>>,
+    /// Optional numeric value of a custom event (order value, cart size, …),
+    /// summed and averaged by the dashboard. `serde(default)` tolerates
+    /// beacons predating values.
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// ISO 4217 currency code qualifying `value`, when it is monetary.
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// The release of the page's application (the tracker's
+    /// `data-app-version`), so sessions can be attributed to a release.
+    #[serde(rename = "a", default, skip_serializing_if = "Option::is_none")]
+    pub app_version: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
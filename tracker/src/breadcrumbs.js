@@ -0,0 +1,109 @@
+// Breadcrumbs: a short trail of what happened in the page (navigations, clicks,
+// console messages, fetches) sent with each exception report, so a failure
+// arrives with the steps that led to it. The agent scrubs and caps the trail
+// again at ingest; this side only keeps it short, and never records the
+// tracker's own beacons.
+
+const MAX_MESSAGE = 300;
+const CONSOLE_LEVELS = ["debug", "log", "info", "warn", "error"];
+
+// A bounded trail, oldest entries dropped first. `now` is injectable for the
+// tests.
+export function createTrail(opts) {
+  opts = opts || {};
+  const max = opts.max || 30;
+  const now = opts.now || Date.now;
+  const entries = [];
+
+  function add(kind, message, extra) {
+    const crumb = { t: now(), k: kind, m: String(message).slice(0, MAX_MESSAGE) };
+    if (extra && extra.level) crumb.l = extra.level;
+    if (extra && extra.status != null) crumb.st = extra.status;
+    entries.push(crumb);
+    if (entries.length > max) entries.shift();
+  }
+
+  // The trail in the report's shape: oldest first, each entry aged relative to
+  // now (`a`, in ms) so the agent needn't trust the client's clock.
+  function snapshot() {
+    const at = now();
+    return entries.map(function (crumb) {
+      const out = { a: Math.max(0, at - crumb.t), k: crumb.k, m: crumb.m };
+      if (crumb.l) out.l = crumb.l;
+      if (crumb.st != null) out.st = crumb.st;
+      return out;
+    });
+  }
+
+  return { add: add, snapshot: snapshot };
+}
+
+// A short description of a clicked element: `tag#id.class "text"`.
+export function describeElement(el) {
+  if (!el || !el.tagName) return "unknown";
+  let out = el.tagName.toLowerCase();
+  if (el.id) out += "#" + el.id;
+  if (typeof el.className === "string" && el.className.trim()) {
+    out += "." + el.className.trim().split(/\s+/).slice(0, 3).join(".");
+  }
+  const text = (el.textContent || "").replace(/\s+/g, " ").trim();
+  if (text) out += ' "' + (text.length > 40 ? text.slice(0, 40) + "…" : text) + '"';
+  return out;
+}
+
+function stringifyArg(value) {
+  if (typeof value === "string") return value;
+  if (value instanceof Error) return value.name + ": " + value.message;
+  try {
+    return JSON.stringify(value) || String(value);
+  } catch (e) {
+    return String(value);
+  }
+}
+
+// Record clicks on `doc`, and console calls and fetches through `win`, into
+// `trail`. `ignore(url)` is true for the tracker's own requests.
+export function instrument(trail, win, doc, ignore) {
+  doc.addEventListener(
+    "click",
+    function (event) {
+      trail.add("click", describeElement(event.target));
+    },
+    { capture: true, passive: true },
+  );
+
+  const console = win.console;
+  if (console) {
+    CONSOLE_LEVELS.forEach(function (level) {
+      const original = console[level];
+      if (typeof original !== "function") return;
+      console[level] = function () {
+        trail.add("console", Array.prototype.map.call(arguments, stringifyArg).join(" "), {
+          level: level,
+        });
+        return original.apply(this, arguments);
+      };
+    });
+  }
+
+  const fetchImpl = win.fetch;
+  if (typeof fetchImpl === "function") {
+    win.fetch = function (input, init) {
+      const result = fetchImpl.apply(this, arguments);
+      const url = typeof input === "string" ? input : (input && input.url) || String(input);
+      if (ignore(url)) return result;
+      const method = ((init && init.method) || (input && input.method) || "GET").toUpperCase();
+      return result.then(
+        function (response) {
+          trail.add("fetch", method + " " + url, { status: response.status });
+          return response;
+        },
+        function (error) {
+          // No response at all: network failure, CORS, abort.
+          trail.add("fetch", method + " " + url, { status: 0 });
+          throw error;
+        },
+      );
+    };
+  }
+}
@@ -3,6 +3,9 @@
 // message and stack — short keys mirror the `/track/exception` contract.
 
 const MAX_STACK = 16000;
+// The agent's request body limit (`MAX_TRACK_BODY`, 16 KiB); a larger report is
+// rejected whole.
+const MAX_BODY = 16 * 1024;
 
 // Normalise an arbitrary thrown value (an Error, an error-like object, or a bare
 // primitive from a promise rejection) into { name, message, stack }.
@@ -36,7 +39,8 @@ function stringifyReason(value) {
 }
 
 // Build the `/track/exception` payload. `meta` is expected to already be a
-// string→string map (or undefined).
+// string→string map (or undefined), `breadcrumbs` a trail snapshot (see
+// breadcrumbs.js).
 export function buildExceptionPayload(desc, opts) {
   opts = opts || {};
   const payload = {
@@ -51,8 +55,35 @@ export function buildExceptionPayload(desc, opts) {
     payload.s =
       desc.stack.length > MAX_STACK ? desc.stack.slice(0, MAX_STACK) : desc.stack;
   }
+  if (opts.level) payload.lv = String(opts.level);
+  if (opts.environment) payload.env = opts.environment;
   if (opts.appVersion) payload.v = opts.appVersion;
   if (opts.meta) payload.d = opts.meta;
+  if (opts.breadcrumbs && opts.breadcrumbs.length) payload.bc = opts.breadcrumbs.slice();
+  return fitBody(payload);
+}
+
+function byteLength(text) {
+  return typeof TextEncoder === "function"
+    ? new TextEncoder().encode(text).length
+    : text.length * 3;
+}
+
+// Shrink `payload` until it fits the agent's body limit: the oldest breadcrumbs
+// go first, then the end of the stack, then of the message.
+function fitBody(payload) {
+  let excess = byteLength(JSON.stringify(payload)) - MAX_BODY;
+  while (excess > 0 && payload.bc) {
+    payload.bc.shift();
+    if (!payload.bc.length) delete payload.bc;
+    excess = byteLength(JSON.stringify(payload)) - MAX_BODY;
+  }
+  ["s", "m"].forEach(function (key) {
+    if (excess <= 0 || !payload[key]) return;
+    // Each character dropped saves at least one byte.
+    payload[key] = payload[key].slice(0, Math.max(0, payload[key].length - excess));
+    excess = byteLength(JSON.stringify(payload)) - MAX_BODY;
+  });
   return payload;
 }
 
@@ -61,18 +92,24 @@ export function buildExceptionPayload(desc, opts) {
 // navigations; `session` is fixed for the reporter's lifetime and links reports to
 // the visit's page views. `appVersion` attributes every report to a specific
 // release (the application itself is identified server-side by the page's
-// hostname). `send` receives the finished payload.
+// hostname), `environment` to a deployment (`production`, `staging`, …).
+// `breadcrumbs`, when given, returns the trail to attach to a report. `send`
+// receives the finished payload.
 export function createExceptionReporter(opts) {
   const send = opts.send;
   const getUrl = opts.url;
   const getBeacon = opts.beacon;
   const session = opts.session;
   const appVersion = opts.appVersion;
+  const environment = opts.environment;
+  const breadcrumbs = opts.breadcrumbs;
   const max = opts.max || 25;
   const seen = new Set();
   let count = 0;
 
-  function report(value, handled, meta, fallbackName) {
+  // `level` is the report's severity (`debug` … `fatal`); the agent stores
+  // reports without one as errors.
+  function report(value, handled, meta, fallbackName, level) {
     if (count >= max) return;
     const desc = describeError(value, fallbackName);
     // Dedup on type + message + the first stack frame.
@@ -87,8 +124,11 @@ export function createExceptionReporter(opts) {
         beacon: getBeacon(),
         session: session,
         handled: handled,
+        level: level,
+        environment: environment,
         appVersion: appVersion,
         meta: meta,
+        breadcrumbs: breadcrumbs ? breadcrumbs() : undefined,
       }),
     );
   }
@@ -12,14 +12,22 @@
 //                                                the script's own src was served from)
 //   data-auto-capture-exceptions="true"          hook window errors + rejections
 //   data-hash                                    treat #hash changes as navigations
-//   data-app-version="1.4.2"                     attribute exceptions to a release
+//   data-app-version="1.4.2"                     attribute exceptions and sessions to a release
 //                                                (the app itself is the hostname)
+//   data-environment="staging"                   attribute exceptions to a deployment
+//                                                environment
+//   data-breadcrumbs="true"                      with auto-captured exceptions, record
+//                                                the navigations, clicks, console
+//                                                messages and fetches leading up to
+//                                                each report (patches console and
+//                                                fetch)
 //
 // One build, no variants; behaviour is toggled by the attributes above at runtime.
 
 import { privacySignal } from "./privacy.js";
-import { createTransport, stringifyMeta } from "./transport.js";
+import { createTransport, joinUrl, stringifyMeta } from "./transport.js";
 import { createExceptionReporter } from "./exceptions.js";
+import { createTrail, instrument } from "./breadcrumbs.js";
 
 function attr(el, name) {
   return el && el.getAttribute ? el.getAttribute(name) : null;
@@ -97,6 +105,14 @@ export function init(overrides) {
     overrides.appVersion != null
       ? overrides.appVersion
       : attr(script, "data-app-version") || "";
+  const environment =
+    overrides.environment != null
+      ? overrides.environment
+      : attr(script, "data-environment") || "";
+  const recordBreadcrumbs =
+    overrides.breadcrumbs != null
+      ? overrides.breadcrumbs
+      : attr(script, "data-breadcrumbs") === "true";
 
   // Honour Do-Not-Track / Global Privacy Control: collect nothing, but still expose a
   // no-op API so sites that call `analytics.event(...)` don't throw.
@@ -119,6 +135,12 @@ export function init(overrides) {
   // already changed `location`.
   let viewUrl = loc.href;
   let unloaded = false;
+  // Engagement for the view: the deepest point of the page scrolled into view
+  // (percent), and time on page counted only while the tab is visible —
+  // background tabs inflate the wall-clock duration.
+  let maxScroll = 0;
+  let engagedMs = 0;
+  let visibleSince = doc.visibilityState === "hidden" ? null : now();
 
   let timezone = "";
   try {
@@ -133,6 +155,7 @@ export function init(overrides) {
     const payload = { b: beacon, i: session, e: kind, u: url || loc.href };
     if (timezone) payload.t = timezone;
     if (doc.referrer) payload.r = doc.referrer;
+    if (appVersion) payload.a = appVersion;
     if (extra) {
       for (const key in extra) {
         if (extra[key] !== undefined) payload[key] = extra[key];
@@ -160,7 +183,24 @@ export function init(overrides) {
       });
   }
 
+  function measureScroll() {
+    const root = doc.documentElement;
+    const height = Math.max(
+      root ? root.scrollHeight : 0,
+      doc.body ? doc.body.scrollHeight : 0,
+    );
+    if (!height) return;
+    const bottom = (win.scrollY || win.pageYOffset || 0) + (win.innerHeight || 0);
+    const depth = Math.min(100, Math.round((bottom / height) * 100));
+    if (depth > maxScroll) maxScroll = depth;
+  }
+
+  function engagedTime(at) {
+    return engagedMs + (visibleSince != null ? at - visibleSince : 0);
+  }
+
   function load() {
+    measureScroll();
     const host = encodeURIComponent(loc.hostname);
     const path = encodeURIComponent(loc.pathname);
     Promise.all([ping("h=" + host), ping("h=" + host + "&p=" + path)]).then(function (
@@ -174,7 +214,9 @@ export function init(overrides) {
   function unload() {
     if (unloaded) return;
     unloaded = true;
-    send("unload", { m: now() - startedAt }, true, viewUrl);
+    measureScroll();
+    const at = now();
+    send("unload", { m: at - startedAt, g: engagedTime(at), s: maxScroll }, true, viewUrl);
   }
 
   // Begin measuring a new page view (initial load and each SPA navigation).
@@ -183,6 +225,10 @@ export function init(overrides) {
     startedAt = now();
     viewUrl = loc.href;
     unloaded = false;
+    maxScroll = 0;
+    engagedMs = 0;
+    visibleSince = doc.visibilityState === "hidden" ? null : now();
+    if (trail) trail.add("navigation", loc.href);
     load();
   }
 
@@ -195,7 +241,8 @@ export function init(overrides) {
   }
 
   // Prefer pagehide (bfcache-friendly); fall back to beforeunload+unload on browsers
-  // without it. visibilitychange→hidden also flushes, to catch tab switches/closes.
+  // without it. visibilitychange→hidden also flushes, to catch tab switches/closes,
+  // and pauses the engaged-time clock until the tab is visible again.
   if ("onpagehide" in win) {
     win.addEventListener("pagehide", unload, { capture: true });
   } else {
@@ -205,10 +252,19 @@ export function init(overrides) {
   doc.addEventListener(
     "visibilitychange",
     function () {
-      if (doc.visibilityState === "hidden") unload();
+      if (doc.visibilityState === "hidden") {
+        unload();
+        if (visibleSince != null) {
+          engagedMs += now() - visibleSince;
+          visibleSince = null;
+        }
+      } else if (visibleSince == null) {
+        visibleSince = now();
+      }
     },
     { capture: true },
   );
+  win.addEventListener("scroll", measureScroll, { capture: true, passive: true });
 
   // --- SPA navigation -----------------------------------------------------------
 
@@ -252,6 +308,16 @@ export function init(overrides) {
 
   // --- Exceptions ---------------------------------------------------------------
 
+  // The trail patches the page's console and fetch, so it is recorded only when
+  // asked for and only for a site that has exceptions captured.
+  const trail = captureExceptions && recordBreadcrumbs ? createTrail() : null;
+  if (trail) {
+    trail.add("navigation", loc.href);
+    instrument(trail, win, doc, function (url) {
+      return url.indexOf(joinUrl(api, "/track/")) === 0;
+    });
+  }
+
   const reporter = createExceptionReporter({
     send: function (payload) {
       transport.post("/track/exception", payload, true);
@@ -264,6 +330,8 @@ export function init(overrides) {
     },
     session: session,
     appVersion: appVersion || undefined,
+    environment: environment || undefined,
+    breadcrumbs: trail ? trail.snapshot : undefined,
   });
 
   if (captureExceptions) {
@@ -281,13 +349,22 @@ export function init(overrides) {
   // --- Public API ---------------------------------------------------------------
 
   const analytics = {
-    // Record a custom event with an optional string→string metadata map.
-    event: function (name, data) {
-      send("custom", { n: name, d: stringifyMeta(data) });
+    // Record a custom event with an optional string→string metadata map and an
+    // optional numeric `value` (with `currency` when it is money), e.g.
+    // `event("purchase", { plan: "pro" }, { value: 49, currency: "USD" })`.
+    event: function (name, data, options) {
+      const value = options && options.value != null ? Number(options.value) : NaN;
+      send("custom", {
+        n: name,
+        d: stringifyMeta(data),
+        v: isFinite(value) ? value : undefined,
+        c: isFinite(value) && options.currency ? String(options.currency) : undefined,
+      });
     },
-    // Manually report a handled exception with optional metadata.
-    captureException: function (error, meta) {
-      reporter.report(error, true, stringifyMeta(meta));
+    // Manually report a handled exception with optional metadata and an
+    // optional severity, e.g. `captureException(err, {}, { level: "warning" })`.
+    captureException: function (error, meta, options) {
+      reporter.report(error, true, stringifyMeta(meta), undefined, options && options.level);
     },
   };
   win.analytics = analytics;
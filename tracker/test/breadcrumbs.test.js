@@ -0,0 +1,53 @@
+import { describe, it, expect, vi } from "vitest";
+import { createTrail, describeElement, instrument } from "../src/breadcrumbs.js";
+
+describe("createTrail", () => {
+  it("keeps the newest entries, aged relative to the snapshot", () => {
+    let clock = 1000;
+    const trail = createTrail({ max: 2, now: () => clock });
+    trail.add("navigation", "https://a/x");
+    clock = 1500;
+    trail.add("click", "button#pay");
+    clock = 1800;
+    trail.add("fetch", "POST /api/pay", { status: 500 });
+    clock = 2000;
+
+    expect(trail.snapshot()).toEqual([
+      { a: 500, k: "click", m: "button#pay" },
+      { a: 200, k: "fetch", m: "POST /api/pay", st: 500 },
+    ]);
+  });
+});
+
+describe("describeElement", () => {
+  it("names the tag, id, classes and text", () => {
+    const button = document.createElement("button");
+    button.id = "pay";
+    button.className = "btn  btn-primary";
+    button.textContent = "  Pay now ";
+    expect(describeElement(button)).toBe('button#pay.btn.btn-primary "Pay now"');
+  });
+});
+
+describe("instrument", () => {
+  it("records console calls and fetches, but not the tracker's own", async () => {
+    const trail = createTrail();
+    const fetch = vi.fn((url) =>
+      url.includes("/broken") ? Promise.reject(new Error("offline")) : Promise.resolve({ status: 404 }),
+    );
+    const fakeWindow = { console: { warn: vi.fn() }, fetch };
+    instrument(trail, fakeWindow, document, (url) => url.startsWith("/track/"));
+
+    fakeWindow.console.warn("low stock", { sku: 7 });
+    await fakeWindow.fetch("/api/cart", { method: "post" });
+    await fakeWindow.fetch("/broken").catch(() => {});
+    await fakeWindow.fetch("/track/hit", { method: "POST" });
+
+    expect(fetch).toHaveBeenCalledTimes(3);
+    expect(trail.snapshot().map(({ a, ...rest }) => rest)).toEqual([
+      { k: "console", m: 'low stock {"sku":7}', l: "warn" },
+      { k: "fetch", m: "POST /api/cart", st: 404 },
+      { k: "fetch", m: "GET /broken", st: 0 },
+    ]);
+  });
+});
@@ -53,6 +53,8 @@ describe("buildExceptionPayload", () => {
         beacon: "b1",
         session: "s1",
         handled: true,
+        level: "warning",
+        environment: "staging",
         appVersion: "1.4.2",
         meta: { k: "v" },
       },
@@ -65,6 +67,8 @@ describe("buildExceptionPayload", () => {
       b: "b1",
       i: "s1",
       s: "at x",
+      lv: "warning",
+      env: "staging",
       v: "1.4.2",
       d: { k: "v" },
     });
@@ -85,6 +89,31 @@ describe("buildExceptionPayload", () => {
     );
     expect(p.s.length).toBe(16000);
   });
+
+  it("fits the agent's body limit, dropping the oldest breadcrumbs first", () => {
+    const breadcrumbs = Array.from({ length: 30 }, (_, i) => ({
+      a: 30 - i,
+      k: "console",
+      m: String(i).padEnd(300, "x"),
+    }));
+    const p = buildExceptionPayload(
+      { name: "E", message: "m", stack: "x".repeat(15000) },
+      { breadcrumbs },
+    );
+    expect(new TextEncoder().encode(JSON.stringify(p)).length).toBeLessThanOrEqual(16 * 1024);
+    // The oldest steps went; the stack and the newest steps stayed.
+    expect(p.s.length).toBe(15000);
+    expect(p.bc.length).toBeLessThan(30);
+    expect(p.bc[p.bc.length - 1].m.startsWith("29")).toBe(true);
+
+    // Past the trail, the stack is cut.
+    const long = buildExceptionPayload(
+      { name: "E", message: "m", stack: "at x\n".repeat(4000) },
+      { breadcrumbs },
+    );
+    expect(long.bc).toBeUndefined();
+    expect(new TextEncoder().encode(JSON.stringify(long)).length).toBeLessThanOrEqual(16 * 1024);
+  });
 });
 
 describe("createExceptionReporter", () => {
@@ -128,6 +157,21 @@ describe("createExceptionReporter", () => {
     expect(sent[0].a).toBeUndefined();
   });
 
+  it("reports the environment and a per-report level", () => {
+    const sent = [];
+    const reporter = createExceptionReporter({
+      send: (p) => sent.push(p),
+      url: () => "https://a/x",
+      beacon: () => "b1",
+      environment: "production",
+    });
+    reporter.report(new Error("boom"), true, undefined, undefined, "warning");
+    reporter.report(new Error("bang"), false);
+    expect(sent[0]).toMatchObject({ env: "production", lv: "warning" });
+    expect(sent[1].env).toBe("production");
+    expect(sent[1].lv).toBeUndefined();
+  });
+
   it("deduplicates identical occurrences", () => {
     const { sent, reporter } = setup();
     const err = new Error("boom");
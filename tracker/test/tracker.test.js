@@ -1,9 +1,12 @@
-import { describe, it, expect, vi, beforeEach } from "vitest";
+import { describe, it, expect, vi, beforeEach, afterEach } from "vitest";
 import { init } from "../src/tracker.js";
 
-// The native history methods, captured before any init() patches them.
+// The native history, console and fetch, captured before any init() patches
+// them.
 const origPush = window.history.pushState;
 const origReplace = window.history.replaceState;
+const origConsole = { ...window.console };
+const origFetch = window.fetch;
 
 // Flush microtasks + the 0ms timer so the ping→json→hit promise chain settles.
 function tick() {
@@ -81,6 +84,12 @@ beforeEach(() => {
   navMock = { doNotTrack: null, sendBeacon: vi.fn(() => true) };
 });
 
+afterEach(() => {
+  // Un-patch what the breadcrumb trail wraps, so no test sees another's.
+  Object.assign(window.console, origConsole);
+  window.fetch = origFetch;
+});
+
 describe("init — privacy", () => {
   it("collects nothing under Do-Not-Track but still exposes a no-op API", async () => {
     const api = init({ fetch: fetchMock, navigator: { doNotTrack: "1" } });
@@ -120,6 +129,13 @@ describe("init — page load", () => {
 
     expect(postBodies(fetch, "/track/hit")[0]).toMatchObject({ q: false, p: false });
   });
+
+  it("attributes hits to the configured app version", async () => {
+    init({ fetch: fetchMock, navigator: navMock, appVersion: "1.4.2" });
+    await tick();
+
+    expect(postBodies(fetchMock, "/track/hit")[0].a).toBe("1.4.2");
+  });
 });
 
 describe("init — unload", () => {
@@ -133,6 +149,10 @@ describe("init — unload", () => {
     expect(bodies).toHaveLength(1);
     expect(bodies[0]).toMatchObject({ e: "unload" });
     expect(typeof bodies[0].m).toBe("number");
+    // Engaged time and scroll depth ride along with the wall-clock duration.
+    expect(typeof bodies[0].g).toBe("number");
+    expect(bodies[0].g).toBeLessThanOrEqual(bodies[0].m);
+    expect(typeof bodies[0].s).toBe("number");
   });
 
   it("sends the unload beacon at most once", async () => {
@@ -255,6 +275,33 @@ describe("init — exceptions", () => {
     expect(bodies[0]).toMatchObject({ ty: "Error", m: "rejected", h: false });
   });
 
+  it("attaches the breadcrumb trail to reports", async () => {
+    init({ fetch: fetchMock, navigator: navMock, captureExceptions: true, breadcrumbs: true });
+    await tick();
+
+    window.history.pushState({}, "", "/checkout");
+    document.body.click();
+    console.warn("retrying payment");
+    fireError(new Error("declined"), "declined");
+
+    const bodies = await beaconBodies(navMock.sendBeacon, "/track/exception");
+    const trail = bodies[0].bc.map((crumb) => crumb.k);
+    expect(trail).toEqual(["navigation", "navigation", "click", "console"]);
+    expect(bodies[0].bc[1].m).toContain("/checkout");
+    expect(bodies[0].bc[3]).toMatchObject({ m: "retrying payment", l: "warn" });
+  });
+
+  it("leaves console and fetch alone unless breadcrumbs are asked for", async () => {
+    const log = console.log;
+    const fetch = window.fetch;
+    init({ fetch: fetchMock, navigator: navMock, captureExceptions: true });
+    init({ fetch: fetchMock, navigator: navMock, breadcrumbs: true });
+    await tick();
+
+    expect(console.log).toBe(log);
+    expect(window.fetch).toBe(fetch);
+  });
+
   it("does not capture when the attribute is off", async () => {
     init({ fetch: fetchMock, navigator: navMock, captureExceptions: false });
     await tick();
@@ -282,6 +329,20 @@ describe("init — public API", () => {
     });
   });
 
+  it("attaches a numeric value and currency to custom events", async () => {
+    const api = init({ fetch: fetchMock, navigator: navMock });
+    await tick();
+    fetchMock.mockClear();
+
+    api.event("purchase", undefined, { value: "49.5", currency: "EUR" });
+    api.event("purchase", undefined, { value: "lots", currency: "EUR" });
+
+    const events = postBodies(fetchMock, "/track/hit");
+    expect(events[0]).toMatchObject({ n: "purchase", v: 49.5, c: "EUR" });
+    expect(events[1].v).toBeUndefined();
+    expect(events[1].c).toBeUndefined();
+  });
+
   it("captures manual exceptions as handled", async () => {
     const api = init({ fetch: fetchMock, navigator: navMock });
     await tick();
@@ -298,6 +359,16 @@ describe("init — public API", () => {
     });
   });
 
+  it("passes a manual exception's level and the configured environment", async () => {
+    const api = init({ fetch: fetchMock, navigator: navMock, environment: "staging" });
+    await tick();
+
+    api.captureException(new Error("slow"), undefined, { level: "warning" });
+
+    const bodies = await beaconBodies(navMock.sendBeacon, "/track/exception");
+    expect(bodies[0]).toMatchObject({ m: "slow", lv: "warning", env: "staging" });
+  });
+
   it("reads its configuration from the script element", async () => {
     const script = document.createElement("script");
     script.setAttribute("data-api", "https://collect.example");
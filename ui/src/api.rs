@@ -10,8 +10,12 @@
 use std::cell::RefCell;
 
 use analytics_api::{
-    AdminUser, CsrfToken, Dashboard, EventDetail, ExceptionGroupDetail, GlobalException, Instance,
-    Pixel, PixelInput, Project, ProjectInput, SessionTrace, Source, SourceInput, TriageInput,
+    AdminUser, AssigneeInput, BulkTriageInput, BulkTriageResult, CommentInput, CountRow, CsrfToken,
+    Dashboard, DiscardedExceptions, EventDetail, ExceptionGroupDetail, ExceptionPage,
+    ExceptionSort, ExceptionStatusCounts, Flow, Funnel, FunnelQuery, Instance, MergeInput,
+    NEXT_CURSOR_HEADER, PathRule, PathRulePreview, Pixel, PixelInput, Project, ProjectInput,
+    Reattribution, STATUS_COUNTS_HEADER, SessionTrace, SnoozeInput, Source, SourceInput,
+    TriageComment, TriageInput,
 };
 use gloo_net::http::Request;
 use serde::Serialize;
@@ -151,6 +155,18 @@ async fn put_json<B: Serialize, T: DeserializeOwned>(path: &str, body: &B) -> Re
     resp.json::<T>().await.map_err(net)
 }
 
+async fn post_empty<B: Serialize>(path: &str, body: &B) -> Result<(), ApiError> {
+    let url = format!("{API_BASE}{path}");
+    mutate(|token| Request::post(&url).header(CSRF_HEADER, token).json(body)).await?;
+    Ok(())
+}
+
+async fn put_empty<B: Serialize>(path: &str, body: &B) -> Result<(), ApiError> {
+    let url = format!("{API_BASE}{path}");
+    mutate(|token| Request::put(&url).header(CSRF_HEADER, token).json(body)).await?;
+    Ok(())
+}
+
 async fn patch_empty<B: Serialize>(path: &str, body: &B) -> Result<(), ApiError> {
     let url = format!("{API_BASE}{path}");
     mutate(|token| Request::patch(&url).header(CSRF_HEADER, token).json(body)).await?;
@@ -226,6 +242,13 @@ pub async fn update_project(id: &str, input: &ProjectInput) -> Result<Project, A
     put_json(&format!("/projects/{}", enc(id)), input).await
 }
 
+pub async fn preview_path_rule(
+    id: &str,
+    rule: &PathRule,
+) -> Result<Vec<PathRulePreview>, ApiError> {
+    post_json(&format!("/projects/{}/path-rules/preview", enc(id)), rule).await
+}
+
 pub async fn list_sources() -> Result<Vec<Source>, ApiError> {
     get_json("/sources").await
 }
@@ -234,6 +257,11 @@ pub async fn update_source(uri: &str, input: &SourceInput) -> Result<Source, Api
     put_json(&format!("/sources?uri={}", enc(uri)), input).await
 }
 
+/// Move the historical events of `uri`'s aliases onto `uri`.
+pub async fn reattribute_source(uri: &str) -> Result<Reattribution, ApiError> {
+    post_json(&format!("/sources/reattribute?uri={}", enc(uri)), &()).await
+}
+
 pub async fn create_pixel(project_id: &str, input: &PixelInput) -> Result<Pixel, ApiError> {
     post_json(&format!("/projects/{}/pixels", enc(project_id)), input).await
 }
@@ -246,10 +274,57 @@ pub async fn delete_pixel(id: &str) -> Result<(), ApiError> {
     delete(&format!("/pixels/{}", enc(id))).await
 }
 
-/// Exception groups across every project (and unassigned sources), filtered by
-/// a resolved query (see [`crate::filters::FilterSet::exceptions_query`]).
-pub async fn list_all_exceptions(query: &str) -> Result<Vec<GlobalException>, ApiError> {
-    get_json(&format!("/exceptions?{query}")).await
+/// A page of exception groups across every project (and unassigned sources),
+/// filtered by a resolved query (see
+/// [`crate::filters::FilterSet::exceptions_query`]) and `search` words, narrowed
+/// to `status` when given, in `sort` order. `cursor` is the previous page's
+/// `next_cursor`. The groups are the response body; the cursor and status
+/// counts come in its headers.
+pub async fn list_all_exceptions(
+    query: &str,
+    search: &str,
+    sort: ExceptionSort,
+    status: Option<&str>,
+    cursor: Option<&str>,
+) -> Result<ExceptionPage, ApiError> {
+    let mut url = format!("/exceptions?{query}&sort={}", sort.as_str());
+    if !search.is_empty() {
+        url.push_str(&format!("&search={}", enc(search)));
+    }
+    if let Some(status) = status {
+        url.push_str(&format!("&status={status}"));
+    }
+    if let Some(cursor) = cursor {
+        url.push_str(&format!("&cursor={}", enc(cursor)));
+    }
+    let url = format!("{API_BASE}{url}");
+    let resp = send_with_session(|| Request::get(&url).build()).await?;
+    if !resp.ok() {
+        return Err(error_from(resp).await);
+    }
+    let headers = resp.headers();
+    let next_cursor = headers.get(NEXT_CURSOR_HEADER);
+    let status_counts = headers
+        .get(STATUS_COUNTS_HEADER)
+        .map(|value| ExceptionStatusCounts::from_header(&value))
+        .unwrap_or_default();
+    Ok(ExceptionPage {
+        groups: resp.json().await.map_err(net)?,
+        next_cursor,
+        status_counts,
+    })
+}
+
+/// How many reports each project's inbound filters dropped since each was
+/// added.
+pub async fn discarded_exceptions() -> Result<Vec<DiscardedExceptions>, ApiError> {
+    get_json("/exceptions/discarded").await
+}
+
+/// The environments exceptions reported over a resolved `from=…&to=…` range
+/// (see [`crate::filters::FilterSet::range_query`]), most occurrences first.
+pub async fn exception_environments(range: &str) -> Result<Vec<CountRow>, ApiError> {
+    get_json(&format!("/exceptions/environments?{range}")).await
 }
 
 /// One custom/pixel event in detail. `query` is a pre-encoded dashboard query
@@ -291,6 +366,54 @@ pub async fn set_triage(group: &str, input: &TriageInput) -> Result<(), ApiError
     patch_empty(&format!("/exceptions/{}", enc(group)), input).await
 }
 
+/// Mute `group` until `input.snooze` is met.
+pub async fn snooze_exception(group: &str, input: &SnoozeInput) -> Result<(), ApiError> {
+    put_empty(&format!("/exceptions/{}/snooze", enc(group)), input).await
+}
+
+/// Assign `group` to a signed-in user, or unassign it.
+pub async fn assign_exception(group: &str, input: &AssigneeInput) -> Result<(), ApiError> {
+    put_empty(&format!("/exceptions/{}/assignee", enc(group)), input).await
+}
+
+/// The users exception groups can be assigned to.
+pub async fn exception_assignees() -> Result<Vec<AdminUser>, ApiError> {
+    get_json("/exceptions/assignees").await
+}
+
+pub async fn comment_on_exception(
+    group: &str,
+    input: &CommentInput,
+) -> Result<TriageComment, ApiError> {
+    post_json(&format!("/exceptions/{}/comments", enc(group)), input).await
+}
+
+/// Apply one triage change to many groups at once.
+pub async fn bulk_triage(input: &BulkTriageInput) -> Result<BulkTriageResult, ApiError> {
+    post_json("/exceptions/triage", input).await
+}
+
+/// Fold `input.groups` into `group` on `input.source`.
+pub async fn merge_exceptions(group: &str, input: &MergeInput) -> Result<(), ApiError> {
+    post_empty(&format!("/exceptions/{}/merge", enc(group)), input).await
+}
+
+/// Sessions through each of `query`'s steps.
+pub async fn funnel(query: &FunnelQuery) -> Result<Funnel, ApiError> {
+    post_json("/funnels", query).await
+}
+
+/// The steps sessions took before and after the events matching `anchor`.
+/// `query` is a pre-encoded dashboard query (range + `q`), as for
+/// [`event_detail`].
+pub async fn flow(query: &str, anchor: &str, depth: u32, limit: u32) -> Result<Flow, ApiError> {
+    get_json(&format!(
+        "/flows?{query}&anchor={}&depth={depth}&limit={limit}",
+        enc(anchor)
+    ))
+    .await
+}
+
 /// One session's full event timeline. No range is passed: a trace linked from
 /// the dashboard sample or an exception exemplar must always open whole.
 pub async fn session_trace(id: &str) -> Result<SessionTrace, ApiError> {
@@ -17,6 +17,14 @@ pub enum Route {
     Overview,
     #[at("/exceptions")]
     Exceptions,
+    /// Step-by-step conversion through sessions; the steps ride in the page's
+    /// own state, the range and filter in the URL as everywhere else.
+    #[at("/funnels")]
+    Funnels,
+    /// Where sessions go before and after a page or event; the anchor rides
+    /// in the page's own state, like the funnel steps.
+    #[at("/flows")]
+    Flows,
     /// Legacy per-project page — redirects to the dashboard with a project filter
     /// so old bookmarks keep working.
     #[at("/projects/:id")]
@@ -151,6 +159,8 @@ fn switch(route: Route) -> Html {
     match route {
         Route::Overview => html! { <pages::Dashboard /> },
         Route::Exceptions => html! { <pages::Exceptions /> },
+        Route::Funnels => html! { <pages::Funnels /> },
+        Route::Flows => html! { <pages::Flows /> },
         Route::Project { id } => html! { <ProjectRedirect {id} /> },
         Route::Exception { project, group } => {
             html! { <pages::ExceptionDetail {project} {group} /> }
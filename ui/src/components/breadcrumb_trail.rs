@@ -0,0 +1,70 @@
+//! The breadcrumb trail stored with an exception occurrence, as a timeline:
+//! the navigations, clicks, console messages and requests leading up to the
+//! failure, oldest first, each timed relative to the report.
+
+use analytics_api::{Breadcrumb, BreadcrumbKind};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct BreadcrumbTrailProps {
+    pub crumbs: Vec<Breadcrumb>,
+}
+
+#[function_component(BreadcrumbTrail)]
+pub fn breadcrumb_trail(props: &BreadcrumbTrailProps) -> Html {
+    if props.crumbs.is_empty() {
+        return html! {};
+    }
+    html! {
+        <div class="crumb-trail">
+            <h3 class="crumb-trail__title">{ "Breadcrumbs" }</h3>
+            <ol class="crumb-trail__list">
+                { for props.crumbs.iter().map(crumb_row) }
+            </ol>
+        </div>
+    }
+}
+
+fn crumb_row(crumb: &Breadcrumb) -> Html {
+    let (kind, failed) = match crumb.kind {
+        BreadcrumbKind::Navigation => ("navigation", false),
+        BreadcrumbKind::Click => ("click", false),
+        BreadcrumbKind::Console => (
+            "console",
+            matches!(crumb.level.as_deref(), Some("error" | "warn")),
+        ),
+        BreadcrumbKind::Fetch => ("fetch", crumb.status.is_some_and(|s| s == 0 || s >= 400)),
+        BreadcrumbKind::Other => ("other", false),
+    };
+    html! {
+        <li class={classes!("crumb-trail__row", failed.then_some("crumb-trail__row--error"))}>
+            <span class="crumb-trail__age" title="Before the exception was reported">
+                { format_age(crumb.age_ms) }
+            </span>
+            <span class="crumb-trail__kind">{ kind }</span>
+            <span class="crumb-trail__message">
+                if let Some(level) = &crumb.level {
+                    <span class="crumb-trail__level">{ level }</span>
+                }
+                { &crumb.message }
+                if let Some(status) = crumb.status {
+                    <span class="crumb-trail__status">
+                        { if status == 0 { "failed".to_string() } else { status.to_string() } }
+                    </span>
+                }
+            </span>
+        </li>
+    }
+}
+
+/// `-850ms`, `-12.4s`, `-3m 05s`.
+fn format_age(age_ms: u64) -> String {
+    if age_ms < 1_000 {
+        format!("-{age_ms}ms")
+    } else if age_ms < 60_000 {
+        format!("-{:.1}s", age_ms as f64 / 1_000.0)
+    } else {
+        let secs = age_ms / 1_000;
+        format!("-{}m {:02}s", secs / 60, secs % 60)
+    }
+}
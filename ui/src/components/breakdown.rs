@@ -24,6 +24,9 @@ pub struct PanelRow {
     pub events: i64,
     /// Hovering the label shows this (defaults to the label).
     pub title: Option<String>,
+    /// A secondary annotation shown before the share (e.g. a page's median
+    /// scroll depth).
+    pub detail: Option<String>,
     /// An additional filter term applied (and toggled) together with the
     /// row's main value — e.g. a version row also pins its application.
     pub extra: Option<(Dim, String)>,
@@ -246,6 +249,9 @@ pub fn breakdown_panel(props: &BreakdownPanelProps) -> Html {
                         </span>
                         <span class="brow__filter-hint">{ icons::filter() }</span>
                     </span>
+                    if let Some(detail) = &row.detail {
+                        <span class="brow__detail">{ detail.clone() }</span>
+                    }
                     <span class="brow__share">{ format!("{share:.0}%") }</span>
                     <span class="brow__count">{ compact(value) }</span>
                 </button>
@@ -1,9 +1,12 @@
 //! Hand-rolled interactive SVG charts. The main time-series chart renders in
 //! pixel coordinates (the viewBox tracks the measured container size) so text,
 //! strokes, and dashes stay undistorted; interactivity is plain Yew events —
-//! hover crosshair + tooltip, and click-drag to zoom the time range.
+//! hover crosshair + tooltip, and click-drag to zoom the time range. The flow
+//! chart (a Sankey diagram of session paths) sizes itself the same way.
 
-use analytics_api::TimeSeriesPoint;
+use analytics_api::{
+    Flow, FlowLink, FlowNode, FlowNodeKind, ReleaseMarker, TimeSeriesPoint, source_label,
+};
 use wasm_bindgen::JsCast;
 use wasm_bindgen::closure::Closure;
 use web_sys::HtmlElement;
@@ -58,52 +61,20 @@ pub struct TimeSeriesChartProps {
     /// Emitted with `(from_ms, to_ms)` when the user drag-selects a sub-range.
     #[prop_or_default]
     pub on_zoom: Option<Callback<(i64, i64)>>,
+    /// Deploys to mark on the time axis.
+    #[prop_or_default]
+    pub releases: Vec<ReleaseMarker>,
 }
 
 #[function_component(TimeSeriesChart)]
 pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
     let container = use_node_ref();
+    let w = use_container_width(container.clone());
     let svg_ref = use_node_ref();
-    let width = use_state(|| 0.0f64);
     let hover = use_state(|| None::<usize>);
     // `(anchor, current)` bucket indices of an in-progress drag selection.
     let drag = use_state(|| None::<(usize, usize)>);
 
-    // Track the container width: measure synchronously on mount (ResizeObserver
-    // deliveries are frame-timed and may be arbitrarily delayed in throttled
-    // tabs), then observe the container so later layout changes — window
-    // resizes, sidebar collapses — re-measure automatically.
-    {
-        let (container, width) = (container.clone(), width.clone());
-        use_effect_with((), move |_| {
-            let last = std::rc::Rc::new(std::cell::Cell::new(-1.0f64));
-            let measure = {
-                let (container, width, last) = (container.clone(), width.clone(), last.clone());
-                move || {
-                    if let Some(el) = container.cast::<HtmlElement>() {
-                        let measured = el.client_width() as f64;
-                        if (measured - last.get()).abs() >= 1.0 {
-                            last.set(measured);
-                            width.set(measured);
-                        }
-                    }
-                }
-            };
-            measure();
-            let callback = Closure::<dyn FnMut()>::new(measure);
-            let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref()).ok();
-            if let (Some(observer), Some(el)) = (&observer, container.cast::<web_sys::Element>()) {
-                observer.observe(&el);
-            }
-            move || {
-                if let Some(observer) = &observer {
-                    observer.disconnect();
-                }
-                drop(callback);
-            }
-        });
-    }
-
     // Interaction state is meaningless across a data change (a zoom or filter
     // swap can shrink the series); drop it whenever the series length changes.
     {
@@ -117,7 +88,6 @@ pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
 
     let points = &props.points;
     let n = points.len();
-    let w = *width;
 
     if n == 0 {
         return html! {
@@ -197,6 +167,39 @@ pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
         html! { <g>{ bars }</g> }
     });
 
+    // Deploys as dashed verticals at their instant, labelled with the version
+    // (qualified by the application when the chart spans several).
+    let x_at = |ms: i64| x_of(0) + (ms - points[0].timestamp_ms) as f64 / bucket_ms as f64 * step;
+    let qualify = props
+        .releases
+        .iter()
+        .any(|r| r.source != props.releases[0].source);
+    let release_label = move |release: &ReleaseMarker| {
+        if qualify {
+            format!("{} {}", source_label(&release.source), release.version)
+        } else {
+            release.version.clone()
+        }
+    };
+    let release_lines = props
+        .releases
+        .iter()
+        .enumerate()
+        .map(|(i, release)| {
+            let x = x_at(release.deployed_ms)
+                .min(w - MARGIN_RIGHT)
+                .max(MARGIN_LEFT);
+            html! {
+                <g key={i.to_string()} class="chart__release">
+                    <line class="chart__release-line" x1={format!("{x:.1}")} x2={format!("{x:.1}")}
+                        y1={MARGIN_TOP.to_string()} y2={(MARGIN_TOP + plot_h).to_string()} />
+                    <text class="chart__release-label" x={format!("{:.1}", x + 4.0)}
+                        y={(MARGIN_TOP + 10.0).to_string()}>{ release_label(release) }</text>
+                </g>
+            }
+        })
+        .collect::<Html>();
+
     // Horizontal gridlines + y labels at each tick.
     let gridlines = (0..=tick_count).map(|t| {
         let value = t * tick_step;
@@ -321,6 +324,15 @@ pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
             format!("left: {:.0}px; top: {:.0}px;", x + 12.0, MARGIN_TOP + 8.0)
         };
 
+        let deployed: Vec<String> = props
+            .releases
+            .iter()
+            .filter(|r| {
+                r.deployed_ms >= point.timestamp_ms && r.deployed_ms < point.timestamp_ms + bucket_ms
+            })
+            .map(release_label)
+            .collect();
+
         let secondary = match metric {
             Metric::Visitors => format!("{} page views", group_thousands(point.pageviews)),
             _ => format!("{} visitors", group_thousands(point.visitors)),
@@ -356,6 +368,13 @@ pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
                             <strong>{ group_thousands(point.exceptions) }</strong>
                         </div>
                     }
+                    { for deployed.into_iter().map(|release| html! {
+                        <div class="chart__tooltip-row chart__tooltip-row--release">
+                            <span class="chart__tooltip-dot chart__tooltip-dot--release" />
+                            <span>{ "Deployed" }</span>
+                            <strong>{ release }</strong>
+                        </div>
+                    }) }
                     <div class="chart__tooltip-sub">{ secondary }</div>
                 </div>
             },
@@ -390,6 +409,7 @@ pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
                     { for gridlines }
                     { for x_labels }
                     { exception_bars }
+                    { release_lines }
                     <path class="chart__area" d={area_path} fill="url(#chart-fill)" />
                     if let Some(previous_path) = previous_path {
                         <path class="chart__line chart__line--prev" d={previous_path} />
@@ -404,6 +424,48 @@ pub fn time_series_chart(props: &TimeSeriesChartProps) -> Html {
     }
 }
 
+/// The measured width of `container`, in pixels (zero until mounted).
+///
+/// Measured synchronously on mount (ResizeObserver deliveries are
+/// frame-timed and may be arbitrarily delayed in throttled tabs), then the
+/// container is observed so later layout changes — window resizes, sidebar
+/// collapses — re-measure automatically.
+#[hook]
+fn use_container_width(container: NodeRef) -> f64 {
+    let width = use_state(|| 0.0f64);
+    {
+        let width = width.clone();
+        use_effect_with((), move |_| {
+            let last = std::rc::Rc::new(std::cell::Cell::new(-1.0f64));
+            let measure = {
+                let (container, width, last) = (container.clone(), width.clone(), last.clone());
+                move || {
+                    if let Some(el) = container.cast::<HtmlElement>() {
+                        let measured = el.client_width() as f64;
+                        if (measured - last.get()).abs() >= 1.0 {
+                            last.set(measured);
+                            width.set(measured);
+                        }
+                    }
+                }
+            };
+            measure();
+            let callback = Closure::<dyn FnMut()>::new(measure);
+            let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref()).ok();
+            if let (Some(observer), Some(el)) = (&observer, container.cast::<web_sys::Element>()) {
+                observer.observe(&el);
+            }
+            move || {
+                if let Some(observer) = &observer {
+                    observer.disconnect();
+                }
+                drop(callback);
+            }
+        });
+    }
+    *width
+}
+
 /// The `M … L …` polyline through a series in plot coordinates.
 fn polyline(
     points: &[TimeSeriesPoint],
@@ -450,18 +512,35 @@ pub struct SparklineProps {
 /// stroke stays uniform via `vector-effect`.
 #[function_component(Sparkline)]
 pub fn sparkline(props: &SparklineProps) -> Html {
+    let points: Vec<f64> = props.points.iter().map(|v| *v as f64).collect();
+    sparkline_svg(&points, props.class.clone())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ValueSparklineProps {
+    pub points: Vec<f64>,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// A [`Sparkline`] over fractional values (e.g. summed event values).
+#[function_component(ValueSparkline)]
+pub fn value_sparkline(props: &ValueSparklineProps) -> Html {
+    sparkline_svg(&props.points, props.class.clone())
+}
+
+fn sparkline_svg(points: &[f64], class: Classes) -> Html {
     const W: f64 = 100.0;
     const H: f64 = 28.0;
     const PAD: f64 = 2.0;
 
-    let points = &props.points;
-    if points.is_empty() || points.iter().all(|v| *v == 0) {
-        return html! { <svg class={classes!("sparkline", props.class.clone())} viewBox={format!("0 0 {W} {H}")} preserveAspectRatio="none" aria-hidden="true">
+    if points.is_empty() || points.iter().all(|v| *v == 0.0) {
+        return html! { <svg class={classes!("sparkline", class)} viewBox={format!("0 0 {W} {H}")} preserveAspectRatio="none" aria-hidden="true">
             <line class="sparkline__flat" x1="0" y1={(H - PAD).to_string()} x2={W.to_string()} y2={(H - PAD).to_string()} />
         </svg> };
     }
 
-    let max = points.iter().copied().max().unwrap_or(1).max(1) as f64;
+    let max = points.iter().copied().fold(1.0, f64::max);
     let step = if points.len() > 1 {
         W / (points.len() - 1) as f64
     } else {
@@ -470,12 +549,7 @@ pub fn sparkline(props: &SparklineProps) -> Html {
     let coords: Vec<(f64, f64)> = points
         .iter()
         .enumerate()
-        .map(|(i, v)| {
-            (
-                i as f64 * step,
-                PAD + (1.0 - *v as f64 / max) * (H - 2.0 * PAD),
-            )
-        })
+        .map(|(i, v)| (i as f64 * step, PAD + (1.0 - v / max) * (H - 2.0 * PAD)))
         .collect();
     let line = coords
         .iter()
@@ -486,10 +560,217 @@ pub fn sparkline(props: &SparklineProps) -> Html {
     let area = format!("{line} L {W} {H} L 0 {H} Z");
 
     html! {
-        <svg class={classes!("sparkline", props.class.clone())} viewBox={format!("0 0 {W} {H}")}
+        <svg class={classes!("sparkline", class)} viewBox={format!("0 0 {W} {H}")}
             preserveAspectRatio="none" aria-hidden="true">
             <path class="sparkline__area" d={area} />
             <path class="sparkline__line" d={line} />
         </svg>
     }
 }
+
+const FLOW_NODE_W: f64 = 10.0;
+const FLOW_GAP: f64 = 14.0;
+const FLOW_MARGIN_Y: f64 = 10.0;
+/// Room on the right of the last column for its labels.
+const FLOW_LABEL_W: f64 = 150.0;
+
+#[derive(Properties, PartialEq)]
+pub struct FlowChartProps {
+    pub flow: Flow,
+    /// Emitted with a page or event node the operator clicked (to explore
+    /// from it next).
+    #[prop_or_default]
+    pub on_select: Option<Callback<FlowNode>>,
+}
+
+/// A Sankey diagram of a [`Flow`]: one column per step from the anchor, each
+/// node's height its sessions, and bands for the sessions moving from one
+/// step to the next. Hovering a band or node names it and its sessions.
+#[function_component(FlowChart)]
+pub fn flow_chart(props: &FlowChartProps) -> Html {
+    let container = use_node_ref();
+    let w = use_container_width(container.clone());
+    let flow = &props.flow;
+
+    if flow.nodes.is_empty() {
+        return html! {
+            <div class="chart" ref={container}>
+                <div class="chart__empty">{ "No sessions reached this step in this period." }</div>
+            </div>
+        };
+    }
+
+    // Columns, left to right, each stacking its nodes in the API's order.
+    let first = flow.nodes.iter().map(|n| n.position).min().unwrap_or(0);
+    let last = flow.nodes.iter().map(|n| n.position).max().unwrap_or(0);
+    let columns = (last - first + 1) as usize;
+    let column_of = |node: &FlowNode| (node.position - first) as usize;
+    let mut per_column = vec![Vec::new(); columns];
+    for (i, node) in flow.nodes.iter().enumerate() {
+        per_column[column_of(node)].push(i);
+    }
+    let tallest = per_column.iter().map(Vec::len).max().unwrap_or(1);
+    let height = (tallest as f64 * 44.0).max(240.0);
+
+    // One vertical scale for every column, so a band keeps its thickness end
+    // to end: the fullest column (counting its gaps) fills the height.
+    let usable = |count: usize| height - 2.0 * FLOW_MARGIN_Y - FLOW_GAP * (count as f64 - 1.0);
+    let scale = per_column
+        .iter()
+        .filter(|nodes| !nodes.is_empty())
+        .map(|nodes| {
+            let sessions: i64 = nodes.iter().map(|&i| flow.nodes[i].sessions).sum();
+            usable(nodes.len()) / sessions.max(1) as f64
+        })
+        .fold(f64::INFINITY, f64::min);
+
+    let span = (w - FLOW_NODE_W - FLOW_LABEL_W).max(1.0);
+    let x_of = |column: usize| {
+        if columns > 1 {
+            column as f64 * span / (columns - 1) as f64
+        } else {
+            span / 2.0
+        }
+    };
+    // Each node's top; columns are centred vertically.
+    let mut top = vec![0.0f64; flow.nodes.len()];
+    for nodes in &per_column {
+        let total: f64 = nodes
+            .iter()
+            .map(|&i| flow.nodes[i].sessions as f64 * scale)
+            .sum::<f64>()
+            + FLOW_GAP * (nodes.len().max(1) - 1) as f64;
+        let mut y = (height - total) / 2.0;
+        for &i in nodes {
+            top[i] = y;
+            y += flow.nodes[i].sessions as f64 * scale + FLOW_GAP;
+        }
+    }
+
+    // Bands leave a node top-down in the order of their targets, and enter
+    // one in the order of their sources, so they don't cross at the ends.
+    let mut outgoing = vec![0.0f64; flow.nodes.len()];
+    let mut incoming = vec![0.0f64; flow.nodes.len()];
+    let mut links: Vec<&FlowLink> = flow
+        .links
+        .iter()
+        .filter(|l| l.source < flow.nodes.len() && l.target < flow.nodes.len())
+        .collect();
+    links.sort_by(|a, b| {
+        top[a.source]
+            .total_cmp(&top[b.source])
+            .then(top[a.target].total_cmp(&top[b.target]))
+    });
+    let mut entry = vec![0.0f64; links.len()];
+    let mut by_target: Vec<usize> = (0..links.len()).collect();
+    by_target.sort_by(|&a, &b| {
+        top[links[a].target]
+            .total_cmp(&top[links[b].target])
+            .then(top[links[a].source].total_cmp(&top[links[b].source]))
+    });
+    for &l in &by_target {
+        entry[l] = top[links[l].target] + incoming[links[l].target];
+        incoming[links[l].target] += links[l].sessions as f64 * scale;
+    }
+
+    let label = |node: &FlowNode| match node.kind {
+        FlowNodeKind::Other => "Other".to_string(),
+        FlowNodeKind::Page if node.label.is_empty() => "(unknown page)".to_string(),
+        _ => node.label.clone(),
+    };
+
+    let bands = links
+        .iter()
+        .enumerate()
+        .map(|(l, link)| {
+            let thickness = link.sessions as f64 * scale;
+            let (source, target) = (&flow.nodes[link.source], &flow.nodes[link.target]);
+            let x0 = x_of(column_of(source)) + FLOW_NODE_W;
+            let x1 = x_of(column_of(target));
+            let y0 = top[link.source] + outgoing[link.source];
+            outgoing[link.source] += thickness;
+            let y1 = entry[l];
+            let xm = (x0 + x1) / 2.0;
+            let d = format!(
+                "M {x0:.1} {y0:.1} C {xm:.1} {y0:.1}, {xm:.1} {y1:.1}, {x1:.1} {y1:.1} \
+                 L {x1:.1} {:.1} C {xm:.1} {:.1}, {xm:.1} {:.1}, {x0:.1} {:.1} Z",
+                y1 + thickness,
+                y1 + thickness,
+                y0 + thickness,
+                y0 + thickness,
+            );
+            html! {
+                <path key={format!("{}-{}", link.source, link.target)} class="flow-chart__link" d={d}>
+                    <title>
+                        { format!("{} → {}: {} sessions", label(source), label(target), group_thousands(link.sessions)) }
+                    </title>
+                </path>
+            }
+        })
+        .collect::<Html>();
+
+    let nodes = flow
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let x = x_of(column_of(node));
+            let h = (node.sessions as f64 * scale).max(1.0);
+            let selectable = matches!(node.kind, FlowNodeKind::Page | FlowNodeKind::Event)
+                && props.on_select.is_some();
+            let onclick = props.on_select.clone().filter(|_| selectable).map(|cb| {
+                let node = node.clone();
+                Callback::from(move |_: MouseEvent| cb.emit(node.clone()))
+            });
+            let kind = match node.kind {
+                FlowNodeKind::Anchor => "flow-chart__node--anchor",
+                FlowNodeKind::Page => "flow-chart__node--page",
+                FlowNodeKind::Event => "flow-chart__node--event",
+                FlowNodeKind::Other => "flow-chart__node--other",
+            };
+            // The last column labels to its left; the rest to their right.
+            let (label_x, anchor) = if column_of(node) + 1 == columns && columns > 1 {
+                (x - 6.0, "end")
+            } else {
+                (x + FLOW_NODE_W + 6.0, "start")
+            };
+            let mut text = label(node);
+            if text.chars().count() > 28 {
+                text = format!("{}…", text.chars().take(27).collect::<String>());
+            }
+            html! {
+                <g key={i.to_string()}
+                    class={classes!("flow-chart__node", kind, selectable.then_some("flow-chart__node--selectable"))}
+                    {onclick}>
+                    <title>
+                        { format!("{}: {} sessions", label(node), group_thousands(node.sessions)) }
+                    </title>
+                    <rect x={format!("{x:.1}")} y={format!("{:.1}", top[i])}
+                        width={FLOW_NODE_W.to_string()} height={format!("{h:.1}")} rx="2" />
+                    <text class="flow-chart__label" x={format!("{label_x:.1}")}
+                        y={format!("{:.1}", top[i] + h / 2.0 + 4.0)} text-anchor={anchor}>
+                        { text }
+                        <tspan class="flow-chart__count">{ format!(" {}", compact(node.sessions)) }</tspan>
+                    </text>
+                </g>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <div class="chart flow-chart" ref={container}>
+            if w > 0.0 {
+                <svg
+                    viewBox={format!("0 0 {w:.0} {height:.0}")}
+                    width="100%"
+                    height={height.to_string()}
+                    role="img"
+                    aria-label="Session flow"
+                >
+                    { bands }
+                    { nodes }
+                </svg>
+            }
+        </div>
+    }
+}
@@ -1,10 +1,10 @@
 //! One distribution card: proportional bars over a dimension's values, shared
 //! by the exception and event detail pages.
 
-use analytics_api::CountRow;
+use analytics_api::{CountRow, ValueRow};
 use yew::prelude::*;
 
-use crate::format::compact;
+use crate::format::{compact, format_value};
 
 /// Render a distribution card. Cards whose only row is the absent sentinel
 /// carry no signal and are dropped.
@@ -42,3 +42,49 @@ pub fn distribution(title: &str, rows: &[CountRow], total: i64) -> Html {
         </section>
     }
 }
+
+/// Render a distribution card over reported values: bars and shares follow
+/// each key's total, annotated with its average. Dropped like
+/// [`distribution`] when it carries no signal.
+pub fn value_distribution(title: &str, rows: &[ValueRow], currency: Option<&str>) -> Html {
+    let informative = rows.iter().any(|r| !r.key.is_empty());
+    if rows.is_empty() || !informative {
+        return html! {};
+    }
+    let max = rows
+        .iter()
+        .map(|r| r.value.total)
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+    let total = rows
+        .iter()
+        .map(|r| r.value.total)
+        .sum::<f64>()
+        .max(f64::EPSILON);
+    let bars = rows.iter().map(|row| {
+        let share = row.value.total / total * 100.0;
+        let width = (row.value.total / max * 100.0).max(0.0);
+        let label = if row.key.is_empty() {
+            "Unknown".to_string()
+        } else {
+            row.key.clone()
+        };
+        html! {
+            <li class="dist__row" key={row.key.clone()}
+                title={format!("average {}", format_value(row.value.average, currency))}>
+                <span class="dist__bar" style={format!("width: {width:.1}%")} />
+                <span class={classes!("dist__label", row.key.is_empty().then_some("brow__text--absent"))}>
+                    { label }
+                </span>
+                <span class="dist__share">{ format!("{share:.0}%") }</span>
+                <span class="dist__count">{ format_value(row.value.total, None) }</span>
+            </li>
+        }
+    });
+    html! {
+        <section class="dist">
+            <h3 class="dist__title">{ title.to_string() }</h3>
+            <ul class="dist__rows">{ for bars }</ul>
+        </section>
+    }
+}
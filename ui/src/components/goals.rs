@@ -0,0 +1,240 @@
+//! The project drawer's goal editor: named conversions the dashboard reports
+//! for the project — a visit to a matching page, or a custom event (optionally
+//! carrying a given metadata value).
+
+use std::collections::BTreeMap;
+
+use analytics_api::{Goal, GoalTarget, PathPatternKind, Project, ProjectInput};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api;
+use crate::components::{Dropdown, DropdownItem};
+
+#[derive(Properties, PartialEq)]
+pub struct GoalsProps {
+    pub project: Project,
+    /// Fired with the saved project.
+    pub on_saved: Callback<Project>,
+}
+
+/// What a draft goal matches, as picked in the editor's dropdown.
+#[derive(Clone, Copy, PartialEq)]
+enum Match {
+    Glob,
+    Regex,
+    Event,
+}
+
+impl Match {
+    fn value(self) -> &'static str {
+        match self {
+            Match::Glob => "glob",
+            Match::Regex => "regex",
+            Match::Event => "event",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "regex" => Match::Regex,
+            "event" => Match::Event,
+            _ => Match::Glob,
+        }
+    }
+}
+
+/// A one-line description of what a goal matches.
+fn describe(target: &GoalTarget) -> String {
+    match target {
+        GoalTarget::Path { kind, pattern } => match kind {
+            PathPatternKind::Glob => format!("Visits {pattern}"),
+            PathPatternKind::Regex => format!("Visits /{pattern}/"),
+        },
+        GoalTarget::Event { name, metadata } if metadata.is_empty() => format!("Event {name}"),
+        GoalTarget::Event { name, metadata } => {
+            let pairs: Vec<String> = metadata.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            format!("Event {name} with {}", pairs.join(", "))
+        }
+    }
+}
+
+#[function_component(GoalsEditor)]
+pub fn goals_editor(props: &GoalsProps) -> Html {
+    let goals = use_state(|| props.project.goals.clone());
+    let name = use_state(String::new);
+    let matches = use_state(|| Match::Glob);
+    let target = use_state(String::new);
+    let meta_key = use_state(String::new);
+    let meta_value = use_state(String::new);
+    let error = use_state(|| None::<String>);
+    let busy = use_state(|| false);
+
+    // A different project (or a reload after saving) resets the editor.
+    {
+        let (goals, error) = (goals.clone(), error.clone());
+        use_effect_with(props.project.clone(), move |project| {
+            goals.set(project.goals.clone());
+            error.set(None);
+            || ()
+        });
+    }
+
+    let draft = {
+        let target = target.trim().to_string();
+        let (key, value) = (meta_key.trim(), meta_value.trim());
+        Goal {
+            name: name.trim().to_string(),
+            target: match *matches {
+                Match::Glob | Match::Regex => GoalTarget::Path {
+                    kind: if *matches == Match::Regex {
+                        PathPatternKind::Regex
+                    } else {
+                        PathPatternKind::Glob
+                    },
+                    pattern: target,
+                },
+                Match::Event => GoalTarget::Event {
+                    name: target,
+                    metadata: if key.is_empty() {
+                        BTreeMap::new()
+                    } else {
+                        BTreeMap::from([(key.to_string(), value.to_string())])
+                    },
+                },
+            },
+        }
+    };
+    let dirty = *goals != props.project.goals;
+
+    let input = |state: &UseStateHandle<String>| {
+        let state = state.clone();
+        Callback::from(move |e: InputEvent| {
+            state.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    let on_match = {
+        let matches = matches.clone();
+        Callback::from(move |value: String| matches.set(Match::parse(&value)))
+    };
+
+    let on_add = {
+        let (goals, draft, name, target, meta_key, meta_value) = (
+            goals.clone(),
+            draft.clone(),
+            name.clone(),
+            target.clone(),
+            meta_key.clone(),
+            meta_value.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let mut next = (*goals).clone();
+            next.push(draft.clone());
+            goals.set(next);
+            name.set(String::new());
+            target.set(String::new());
+            meta_key.set(String::new());
+            meta_value.set(String::new());
+        })
+    };
+
+    let on_save = {
+        let (id, goals, error, busy, on_saved) = (
+            props.project.id.clone(),
+            goals.clone(),
+            error.clone(),
+            busy.clone(),
+            props.on_saved.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let input = ProjectInput {
+                goals: Some((*goals).clone()),
+                ..Default::default()
+            };
+            let (id, error, busy, on_saved) =
+                (id.clone(), error.clone(), busy.clone(), on_saved.clone());
+            busy.set(true);
+            spawn_local(async move {
+                match api::update_project(&id, &input).await {
+                    Ok(project) => {
+                        error.set(None);
+                        on_saved.emit(project);
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+                busy.set(false);
+            });
+        })
+    };
+
+    let goal_rows = goals
+        .iter()
+        .enumerate()
+        .map(|(i, goal)| {
+            let on_remove = {
+                let goals = goals.clone();
+                Callback::from(move |_: MouseEvent| {
+                    let mut next = (*goals).clone();
+                    next.remove(i);
+                    goals.set(next);
+                })
+            };
+            let description = describe(&goal.target);
+            html! {
+                <div class="toggle-row" key={i}>
+                    <span class="toggle-row__label" title={description.clone()}>
+                        { &goal.name }
+                        <span class="goal-row__target">{ description }</span>
+                    </span>
+                    <button class="btn btn--small btn--ghost" onclick={on_remove}>{ "Remove" }</button>
+                </div>
+            }
+        })
+        .collect::<Html>();
+
+    let kinds = vec![
+        DropdownItem::new("glob", "Page (glob)"),
+        DropdownItem::new("regex", "Page (regex)"),
+        DropdownItem::new("event", "Custom event"),
+    ];
+    let event = *matches == Match::Event;
+    let placeholder = match *matches {
+        Match::Glob => "/thank-you",
+        Match::Regex => "/checkout/(done|complete)",
+        Match::Event => "signup_completed",
+    };
+    let incomplete = draft.name.is_empty() || target.trim().is_empty();
+
+    html! {
+        <div class="field">
+            <label class="field__label">{ "Goals" }</label>
+            <p class="drawer__hint">
+                { "A session converts on a goal when it visits a matching page or reports a matching custom event. The dashboard shows each goal's conversion rate overall and for every breakdown row." }
+            </p>
+            { goal_rows }
+            <div class="kv-editor">
+                <input class="input" placeholder="Goal name, e.g. Signed up" value={(*name).clone()} oninput={input(&name)} />
+                <Dropdown items={kinds} value={matches.value()} on_select={on_match} block={true} />
+                <input class="input" placeholder={placeholder} value={(*target).clone()} oninput={input(&target)} />
+                if event {
+                    <div class="kv-editor__row" style="grid-template-columns: 1fr 1fr;">
+                        <input class="input" placeholder="Metadata key (optional)" value={(*meta_key).clone()} oninput={input(&meta_key)} />
+                        <input class="input" placeholder="Value" value={(*meta_value).clone()} oninput={input(&meta_value)} />
+                    </div>
+                }
+                <div class="form-row" style="margin: 0;">
+                    <button class="btn btn--small" onclick={on_add} disabled={incomplete}>{ "Add goal" }</button>
+                </div>
+            </div>
+            if let Some(error) = &*error {
+                <p class="drawer__hint" style="color: var(--danger);">{ error.clone() }</p>
+            }
+            <div>
+                <button class="btn btn--primary btn--small" onclick={on_save} disabled={*busy || !dirty}>
+                    { "Save goals" }
+                </button>
+            </div>
+        </div>
+    }
+}
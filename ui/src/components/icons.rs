@@ -33,6 +33,23 @@ pub fn exceptions() -> Html {
     })
 }
 
+pub fn funnel() -> Html {
+    icon(html! {
+        <path d="M3 4h18l-7 8.5V19l-4 2v-8.5z" />
+    })
+}
+
+pub fn flow() -> Html {
+    icon(html! {
+        <>
+            <circle cx="5" cy="12" r="2" />
+            <circle cx="19" cy="6" r="2" />
+            <circle cx="19" cy="18" r="2" />
+            <path d="M7 12c5 0 6-6 10-6M7 12c5 0 6 6 10 6" />
+        </>
+    })
+}
+
 pub fn pixels() -> Html {
     icon(html! {
         <>
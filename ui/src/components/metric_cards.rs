@@ -1,7 +1,7 @@
 //! The headline metric cards: value, delta vs the previous window, and (for the
 //! countable metrics) selection of which metric the chart and panels display.
 
-use analytics_api::MetricSummary;
+use analytics_api::{MetricSummary, SCROLL_MARKS};
 use yew::prelude::*;
 
 use crate::components::charts::Metric;
@@ -24,10 +24,26 @@ pub fn metric_cards(props: &MetricCardsProps) -> Html {
         .bounce_rate
         .map(|b| format!("{:.0}%", b * 100.0))
         .unwrap_or_else(|| "—".to_string());
+    // Engaged (visible) time is the honest measure where the tracker reports
+    // it; wall-clock time on page is the fallback for older trackers.
+    let engaged = s.median_engaged_ms.is_some();
     let duration = s
-        .median_duration_ms
+        .median_engaged_ms
+        .or(s.median_duration_ms)
         .map(format_duration)
         .unwrap_or_else(|| "—".to_string());
+    let scroll = s.scroll_depth.as_ref().map(|scroll| {
+        let marks = SCROLL_MARKS
+            .iter()
+            .zip(&scroll.reached)
+            .map(|(mark, views)| {
+                let share = *views as f64 / scroll.samples.max(1) as f64 * 100.0;
+                format!("{share:.0}% reached {mark}%")
+            })
+            .collect::<Vec<_>>()
+            .join(" · ");
+        format!("Median scroll depth {}% · {marks}", scroll.median)
+    });
 
     let selectable = |metric: Metric, value: i64, previous: i64| {
         let active = props.metric == metric;
@@ -64,8 +80,8 @@ pub fn metric_cards(props: &MetricCardsProps) -> Html {
                 <span class="stat__value">{ bounce }</span>
                 { delta_badge(bounce_delta(s, p), true) }
             </div>
-            <div class="stat">
-                <span class="stat__label">{ "Median time" }</span>
+            <div class="stat" title={scroll}>
+                <span class="stat__label">{ if engaged { "Engaged time" } else { "Median time" } }</span>
                 <span class="stat__value">{ duration }</span>
                 { delta_badge(duration_delta(s, p), false) }
             </div>
@@ -81,7 +97,13 @@ fn bounce_delta(s: &MetricSummary, p: &MetricSummary) -> Option<f64> {
 }
 
 fn duration_delta(s: &MetricSummary, p: &MetricSummary) -> Option<f64> {
-    match (s.median_duration_ms, p.median_duration_ms) {
+    // Compare like with like: engaged time only against engaged time.
+    let pair = match (s.median_engaged_ms, p.median_engaged_ms) {
+        (Some(current), Some(previous)) => (Some(current), Some(previous)),
+        _ if s.median_engaged_ms.is_some() => (None, None),
+        _ => (s.median_duration_ms, p.median_duration_ms),
+    };
+    match pair {
         (Some(current), Some(previous)) => delta_percent(current as f64, previous as f64),
         _ => None,
     }
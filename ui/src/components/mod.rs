@@ -1,5 +1,6 @@
 mod alert;
 mod app_bar;
+mod breadcrumb_trail;
 mod breakdown;
 pub mod charts;
 mod distribution;
@@ -7,22 +8,26 @@ mod drawer;
 mod dropdown;
 mod error;
 mod filter_bar;
+mod goals;
 pub mod icons;
 mod layout;
 pub mod metadata;
 mod metric_cards;
 mod page_header;
+mod path_rules;
 mod project_drawer;
 mod shell;
 mod sidebar;
+mod stack_frames;
 pub mod status;
 mod trace_list;
 
 pub use alert::{Alert, AlertKind};
 pub use app_bar::AppBar;
+pub use breadcrumb_trail::BreadcrumbTrail;
 pub use breakdown::{ActionIcon, BreakdownPanel, PanelRow, PanelTab};
-pub use charts::{Sparkline, TimeSeriesChart};
-pub use distribution::distribution;
+pub use charts::{FlowChart, Sparkline, TimeSeriesChart, ValueSparkline};
+pub use distribution::{distribution, value_distribution};
 pub use drawer::Drawer;
 pub use dropdown::{Dropdown, DropdownItem};
 pub use error::ApiErrorAlert;
@@ -33,4 +38,5 @@ pub use page_header::{Crumb, PageHeader};
 pub use project_drawer::ProjectDrawer;
 pub use shell::{AppShell, ProjectsContext};
 pub use sidebar::Sidebar;
+pub use stack_frames::StackFrames;
 pub use trace_list::TraceList;
@@ -0,0 +1,257 @@
+//! The project drawer's path rule editor: ordered `pattern → template` rules
+//! that collapse identifier-bearing URLs into one page at ingest, with a
+//! preview of what a draft rule does to the project's recent pathnames before
+//! it is saved.
+
+use analytics_api::{PathPatternKind, PathRule, PathRulePreview, Project, ProjectInput};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api;
+use crate::components::{Dropdown, DropdownItem};
+use crate::format::group_thousands;
+
+#[derive(Properties, PartialEq)]
+pub struct PathRulesProps {
+    pub project: Project,
+    /// Fired with the saved project.
+    pub on_saved: Callback<Project>,
+}
+
+fn kind_value(kind: PathPatternKind) -> &'static str {
+    match kind {
+        PathPatternKind::Glob => "glob",
+        PathPatternKind::Regex => "regex",
+    }
+}
+
+#[function_component(PathRulesEditor)]
+pub fn path_rules_editor(props: &PathRulesProps) -> Html {
+    let rules = use_state(|| props.project.path_rules.clone());
+    let keep_original = use_state(|| props.project.keep_original_path);
+    let kind = use_state(PathPatternKind::default);
+    let pattern = use_state(String::new);
+    let template = use_state(String::new);
+    let preview = use_state(|| None::<Vec<PathRulePreview>>);
+    let error = use_state(|| None::<String>);
+    let busy = use_state(|| false);
+
+    // A different project (or a reload after saving) resets the editor.
+    {
+        let (rules, keep_original, preview, error) = (
+            rules.clone(),
+            keep_original.clone(),
+            preview.clone(),
+            error.clone(),
+        );
+        use_effect_with(props.project.clone(), move |project| {
+            rules.set(project.path_rules.clone());
+            keep_original.set(project.keep_original_path);
+            preview.set(None);
+            error.set(None);
+            || ()
+        });
+    }
+
+    let draft = PathRule {
+        kind: *kind,
+        pattern: pattern.trim().to_string(),
+        template: template.trim().to_string(),
+    };
+    let dirty =
+        *rules != props.project.path_rules || *keep_original != props.project.keep_original_path;
+
+    let input = |state: &UseStateHandle<String>| {
+        let state = state.clone();
+        Callback::from(move |e: InputEvent| {
+            state.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    let on_kind = {
+        let kind = kind.clone();
+        Callback::from(move |value: String| {
+            kind.set(if value == "regex" {
+                PathPatternKind::Regex
+            } else {
+                PathPatternKind::Glob
+            });
+        })
+    };
+
+    let on_preview = {
+        let (id, draft, preview, error) = (
+            props.project.id.clone(),
+            draft.clone(),
+            preview.clone(),
+            error.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let (id, draft, preview, error) =
+                (id.clone(), draft.clone(), preview.clone(), error.clone());
+            spawn_local(async move {
+                match api::preview_path_rule(&id, &draft).await {
+                    Ok(rows) => {
+                        error.set(None);
+                        preview.set(Some(rows));
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+
+    let on_add = {
+        let (rules, draft, pattern, template, preview) = (
+            rules.clone(),
+            draft.clone(),
+            pattern.clone(),
+            template.clone(),
+            preview.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let mut next = (*rules).clone();
+            next.push(draft.clone());
+            rules.set(next);
+            pattern.set(String::new());
+            template.set(String::new());
+            preview.set(None);
+        })
+    };
+
+    let on_keep = {
+        let keep_original = keep_original.clone();
+        Callback::from(move |_: MouseEvent| keep_original.set(!*keep_original))
+    };
+
+    let on_save = {
+        let (id, rules, keep_original, error, busy, on_saved) = (
+            props.project.id.clone(),
+            rules.clone(),
+            keep_original.clone(),
+            error.clone(),
+            busy.clone(),
+            props.on_saved.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let input = ProjectInput {
+                path_rules: Some((*rules).clone()),
+                keep_original_path: Some(*keep_original),
+                ..Default::default()
+            };
+            let (id, error, busy, on_saved) =
+                (id.clone(), error.clone(), busy.clone(), on_saved.clone());
+            busy.set(true);
+            spawn_local(async move {
+                match api::update_project(&id, &input).await {
+                    Ok(project) => on_saved.emit(project),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+                busy.set(false);
+            });
+        })
+    };
+
+    let rule_rows = rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let on_remove = {
+                let rules = rules.clone();
+                Callback::from(move |_: MouseEvent| {
+                    let mut next = (*rules).clone();
+                    next.remove(i);
+                    rules.set(next);
+                })
+            };
+            html! {
+                <div class="toggle-row" key={i}>
+                    <span class="toggle-row__label" title={rule.pattern.clone()}>
+                        { format!("{} → {}", rule.pattern, rule.template) }
+                    </span>
+                    if rule.kind == PathPatternKind::Regex {
+                        <span class="badge badge--muted">{ "Regex" }</span>
+                    }
+                    <button class="btn btn--small btn--ghost" onclick={on_remove}>{ "Remove" }</button>
+                </div>
+            }
+        })
+        .collect::<Html>();
+
+    let preview_table = preview.as_ref().map(|rows| {
+        let matched = rows.iter().filter(|r| r.rewritten.is_some()).count();
+        let body = rows
+            .iter()
+            .filter(|r| r.rewritten.is_some())
+            .take(20)
+            .map(|r| {
+                html! {
+                    <tr key={r.pathname.clone()}>
+                        <td><code>{ &r.pathname }</code></td>
+                        <td><code>{ r.rewritten.clone().unwrap_or_default() }</code></td>
+                        <td>{ group_thousands(r.count) }</td>
+                    </tr>
+                }
+            })
+            .collect::<Html>();
+        html! {
+            <>
+                <p class="drawer__hint">
+                    { format!("Matches {matched} of the {} most-viewed paths from the past week.", rows.len()) }
+                </p>
+                if matched > 0 {
+                    <div class="card-table">
+                        <table class="list">
+                            <thead><tr><th>{ "Path" }</th><th>{ "Becomes" }</th><th>{ "Views" }</th></tr></thead>
+                            <tbody>{ body }</tbody>
+                        </table>
+                    </div>
+                }
+            </>
+        }
+    });
+
+    let kinds = vec![
+        DropdownItem::new("glob", "Glob"),
+        DropdownItem::new("regex", "Regex"),
+    ];
+    let incomplete = draft.pattern.is_empty() || draft.template.is_empty();
+
+    html! {
+        <div class="field">
+            <label class="field__label">{ "Path rules" }</label>
+            <p class="drawer__hint">
+                { "Rewrite matching paths to a template as they are recorded, e.g. /orders/*/items/* → /orders/:id/items/:id. The first matching rule wins; existing data is unchanged." }
+            </p>
+            { rule_rows }
+            <div class="kv-editor">
+                <Dropdown items={kinds} value={kind_value(*kind)} on_select={on_kind} block={true} />
+                <div class="kv-editor__row" style="grid-template-columns: 1fr 1fr;">
+                    <input class="input" placeholder="/orders/*/items/*" value={(*pattern).clone()} oninput={input(&pattern)} />
+                    <input class="input" placeholder="/orders/:id/items/:id" value={(*template).clone()} oninput={input(&template)} />
+                </div>
+                <div class="form-row" style="margin: 0;">
+                    <button class="btn btn--small" onclick={on_preview} disabled={incomplete}>{ "Preview" }</button>
+                    <button class="btn btn--small" onclick={on_add} disabled={incomplete}>{ "Add rule" }</button>
+                </div>
+            </div>
+            { for preview_table }
+            <button
+                class={classes!("toggle", keep_original.then_some("toggle--on"))}
+                onclick={on_keep}
+                aria-pressed={keep_original.to_string()}
+            >
+                <span class="toggle__track"><span class="toggle__knob" /></span>
+                { "Keep the original path" }
+            </button>
+            if let Some(error) = &*error {
+                <p class="drawer__hint" style="color: var(--danger);">{ error.clone() }</p>
+            }
+            <div>
+                <button class="btn btn--primary btn--small" onclick={on_save} disabled={*busy || !dirty}>
+                    { "Save path rules" }
+                </button>
+            </div>
+        </div>
+    }
+}
@@ -1,6 +1,6 @@
-//! The project management drawer: rename, source membership, and deletion —
-//! management lives here so drilling into a project's data never leaves the
-//! dashboard.
+//! The project management drawer: rename, source membership, path rules,
+//! goals, and deletion — management lives here so drilling into a project's
+//! data never leaves the dashboard.
 
 use analytics_api::{Project, ProjectInput, Source, SourceInput, source_label};
 use wasm_bindgen_futures::spawn_local;
@@ -8,6 +8,8 @@ use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 use crate::api;
+use crate::components::goals::GoalsEditor;
+use crate::components::path_rules::PathRulesEditor;
 use crate::components::{Drawer, ProjectsContext};
 use crate::filters::{Dim, use_apply_filters, use_filters};
 
@@ -118,7 +120,7 @@ pub fn project_drawer(props: &ProjectDrawerProps) -> Html {
             spawn_local(async move {
                 let input = ProjectInput {
                     name: new_name,
-                    slug: None,
+                    ..Default::default()
                 };
                 match api::update_project(&id, &input).await {
                     Ok(_) => notify.emit(()),
@@ -213,6 +215,13 @@ pub fn project_drawer(props: &ProjectDrawerProps) -> Html {
         .as_ref()
         .map(|p| p.name.clone())
         .unwrap_or_else(|| "Project".to_string());
+    let on_rules_saved = {
+        let (project, notify) = (project.clone(), notify.clone());
+        Callback::from(move |saved: Project| {
+            project.set(Some(saved));
+            notify.emit(());
+        })
+    };
     let on_close = {
         let on_close = props.on_close.clone();
         Callback::from(move |_: ()| on_close.emit(()))
@@ -261,6 +270,10 @@ pub fn project_drawer(props: &ProjectDrawerProps) -> Html {
                     { for source_rows }
                 }
             </div>
+            if let Some(p) = &*project {
+                <PathRulesEditor project={p.clone()} on_saved={on_rules_saved.clone()} />
+                <GoalsEditor project={p.clone()} on_saved={on_rules_saved} />
+            }
             <div class="field">
                 <label class="field__label">{ "Danger zone" }</label>
                 <p class="drawer__hint">
@@ -177,7 +177,12 @@ pub fn app_shell(props: &AppShellProps) -> Html {
             let (dispatcher, navigator) = (dispatcher.clone(), navigator.clone());
             submitting.set(true);
             spawn_local(async move {
-                match api::create_project(&ProjectInput { name, slug: None }).await {
+                match api::create_project(&ProjectInput {
+                    name,
+                    ..Default::default()
+                })
+                .await
+                {
                     Ok(project) => {
                         for uri in &chosen {
                             let input = SourceInput {
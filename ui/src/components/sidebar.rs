@@ -1,9 +1,11 @@
-//! The left navigation sidebar: Dashboard, Exceptions, the project list (shown
-//! only when projects exist), Tracking pixels, and Settings.
+//! The left navigation sidebar: Dashboard, Exceptions, Funnels, Flows, the
+//! project list (shown only when projects exist), Tracking pixels, and
+//! Settings.
 //!
-//! Dashboard/Exceptions links carry the current filter state along so switching
-//! views never silently drops active filters. Project entries apply a project
-//! filter on the dashboard rather than navigating to a separate page.
+//! Dashboard/Exceptions/Funnels/Flows links carry the current filter state
+//! along so switching views never silently drops active filters. Project
+//! entries apply a project filter on the dashboard rather than navigating to a
+//! separate page.
 //!
 //! On small screens the sidebar renders as an overlay drawer toggled from the
 //! app bar's hamburger button; navigating closes it.
@@ -42,6 +44,8 @@ pub fn sidebar(props: &SidebarProps) -> Html {
 
     let is_dashboard = matches!(route, Some(Route::Overview | Route::Project { .. }));
     let is_exceptions = matches!(route, Some(Route::Exceptions | Route::Exception { .. }));
+    let is_funnels = matches!(route, Some(Route::Funnels));
+    let is_flows = matches!(route, Some(Route::Flows));
     let is_pixels = matches!(route, Some(Route::Pixels));
     let is_settings = matches!(route, Some(Route::Settings));
     let active_project = filters.get(Dim::Project).map(str::to_string);
@@ -102,6 +106,8 @@ pub fn sidebar(props: &SidebarProps) -> Html {
             <ul class="menu">
                 { menu_item(is_dashboard && active_project.is_none(), Route::Overview, icons::overview(), "Dashboard") }
                 { menu_item(is_exceptions, Route::Exceptions, icons::exceptions(), "Exceptions") }
+                { menu_item(is_funnels, Route::Funnels, icons::funnel(), "Funnels") }
+                { menu_item(is_flows, Route::Flows, icons::flow(), "Flows") }
 
                 if !projects.is_empty() {
                     <li class="menu__section">{ "Projects" }</li>
@@ -0,0 +1,106 @@
+//! A parsed stack trace as a frame list: the application's own frames in full,
+//! each run of library and runtime frames between them collapsed behind a
+//! disclosure, with a toggle back to the raw trace (which also keeps what the
+//! parser skips: the message, `caused by:` chains, a traceback's source lines).
+
+use analytics_api::StackFrame;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct StackFramesProps {
+    pub frames: Vec<StackFrame>,
+    /// The trace as reported; shown as-is when nothing in it parsed.
+    pub raw: AttrValue,
+}
+
+#[function_component(StackFrames)]
+pub fn stack_frames(props: &StackFramesProps) -> Html {
+    let show_raw = use_state(|| false);
+
+    if props.frames.is_empty() || *show_raw {
+        let toggle = (!props.frames.is_empty()).then(|| toggle_button(&show_raw, "Frames"));
+        return html! {
+            <div class="stack-frames">
+                { toggle }
+                <pre class="stack">{ props.raw.clone() }</pre>
+            </div>
+        };
+    }
+
+    // A trace with no in-app frames at all is shown open: hiding everything
+    // would leave nothing to read.
+    let any_in_app = props.frames.iter().any(|f| f.in_app);
+    let mut rows = Vec::new();
+    let mut library = Vec::new();
+    for frame in &props.frames {
+        if frame.in_app || !any_in_app {
+            rows.push(library_run(std::mem::take(&mut library)));
+            rows.push(frame_row(frame));
+        } else {
+            library.push(frame_row(frame));
+        }
+    }
+    rows.push(library_run(library));
+
+    html! {
+        <div class="stack-frames">
+            { toggle_button(&show_raw, "Raw") }
+            <ol class="stack stack-frames__list">{ for rows }</ol>
+        </div>
+    }
+}
+
+fn toggle_button(show_raw: &UseStateHandle<bool>, label: &'static str) -> Html {
+    let onclick = {
+        let show_raw = show_raw.clone();
+        Callback::from(move |_: MouseEvent| show_raw.set(!*show_raw))
+    };
+    html! {
+        <button class="btn btn--small stack-frames__toggle" onclick={onclick}>{ label }</button>
+    }
+}
+
+/// A collapsed run of consecutive library frames (nothing for an empty run).
+fn library_run(frames: Vec<Html>) -> Html {
+    if frames.is_empty() {
+        return html! {};
+    }
+    let label = format!(
+        "{} library frame{}",
+        frames.len(),
+        if frames.len() == 1 { "" } else { "s" }
+    );
+    html! {
+        <li class="stack-frames__library">
+            <details>
+                <summary>{ label }</summary>
+                <ol>{ for frames }</ol>
+            </details>
+        </li>
+    }
+}
+
+fn frame_row(frame: &StackFrame) -> Html {
+    // Module paths are joined with `.` or `::` depending on the runtime, so the
+    // module is shown alongside the function rather than spliced onto it.
+    let function = frame.function.as_deref().unwrap_or("<anonymous>");
+    let location = frame
+        .file
+        .as_ref()
+        .map(|file| match (frame.line, frame.column) {
+            (Some(line), Some(column)) => format!("{file}:{line}:{column}"),
+            (Some(line), None) => format!("{file}:{line}"),
+            _ => file.clone(),
+        });
+    html! {
+        <li class={classes!("stack-frames__frame", frame.in_app.then_some("stack-frames__frame--app"))}>
+            <span class="stack-frames__function">{ function }</span>
+            if let Some(module) = &frame.module {
+                <span class="stack-frames__module">{ module }</span>
+            }
+            if let Some(location) = location {
+                <span class="stack-frames__location">{ location }</span>
+            }
+        </li>
+    }
+}
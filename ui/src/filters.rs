@@ -46,14 +46,20 @@ pub enum Dim {
     UtmSource,
     UtmMedium,
     UtmCampaign,
+    /// The acquisition channel derived at ingest from referrer and UTM tags.
+    Channel,
     AppVersion,
     /// The name of a custom/pixel event (page views carry none, so an event
     /// filter scopes the view to those events).
     EventName,
+    /// The deployment environment an exception was reported from.
+    Environment,
+    /// An exception's severity (`debug` … `fatal`).
+    Level,
 }
 
 impl Dim {
-    pub const ALL: [Dim; 15] = [
+    pub const ALL: [Dim; 18] = [
         Dim::Project,
         Dim::Source,
         Dim::Path,
@@ -67,8 +73,11 @@ impl Dim {
         Dim::UtmSource,
         Dim::UtmMedium,
         Dim::UtmCampaign,
+        Dim::Channel,
         Dim::AppVersion,
         Dim::EventName,
+        Dim::Environment,
+        Dim::Level,
     ];
 
     /// The field name used in filter expressions.
@@ -87,8 +96,11 @@ impl Dim {
             Dim::UtmSource => "utm_source",
             Dim::UtmMedium => "utm_medium",
             Dim::UtmCampaign => "utm_campaign",
+            Dim::Channel => "channel",
             Dim::AppVersion => "app_version",
             Dim::EventName => "event",
+            Dim::Environment => "environment",
+            Dim::Level => "level",
         }
     }
 
@@ -115,8 +127,11 @@ impl Dim {
             Dim::UtmSource => "UTM source",
             Dim::UtmMedium => "UTM medium",
             Dim::UtmCampaign => "UTM campaign",
+            Dim::Channel => "Channel",
             Dim::AppVersion => "App version",
             Dim::EventName => "Event",
+            Dim::Environment => "Environment",
+            Dim::Level => "Level",
         }
     }
 
@@ -131,7 +146,7 @@ impl Dim {
     }
 
     /// Whether the exceptions endpoint can honour this dimension (exception
-    /// events carry source/UA/version columns only).
+    /// events carry source/UA/version/severity columns only).
     pub fn applies_to_exceptions(self) -> bool {
         matches!(
             self,
@@ -142,12 +157,14 @@ impl Dim {
                 | Dim::Os
                 | Dim::Device
                 | Dim::AppVersion
+                | Dim::Environment
+                | Dim::Level
         )
     }
 
     /// Whether the dashboard (page-event) endpoint can honour this dimension.
     pub fn applies_to_dashboard(self) -> bool {
-        !matches!(self, Dim::AppVersion)
+        !matches!(self, Dim::AppVersion | Dim::Environment | Dim::Level)
     }
 }
 
@@ -166,6 +183,8 @@ const EXCEPTION_FIELDS: &[&str] = &[
     "type",
     "message",
     "handled",
+    "level",
+    "environment",
 ];
 const DASHBOARD_FIELDS: &[&str] = &[
     "project",
@@ -181,6 +200,7 @@ const DASHBOARD_FIELDS: &[&str] = &[
     "utm_source",
     "utm_medium",
     "utm_campaign",
+    "channel",
     "event",
 ];
 
@@ -507,6 +527,17 @@ impl FilterSet {
             ("to".to_string(), to.to_string()),
             ("interval".to_string(), interval.to_string()),
         ];
+        let expression = self.dashboard_expression();
+        if !expression.is_empty() {
+            pairs.push(("q".to_string(), expression));
+        }
+        encode_pairs(&pairs)
+    }
+
+    /// The `q` expression of [`FilterSet::stats_query`] (also the funnel
+    /// page's filter): only the terms the dashboard can honour. Empty when
+    /// nothing applies.
+    pub fn dashboard_expression(&self) -> String {
         let query = Query {
             terms: self
                 .query
@@ -521,11 +552,7 @@ impl FilterSet {
                 .clone()
                 .filter(|_| fields_apply(&self.query.advanced_fields(), DASHBOARD_FIELDS)),
         };
-        let expression = query.to_expression();
-        if !expression.is_empty() {
-            pairs.push(("q".to_string(), expression));
-        }
-        encode_pairs(&pairs)
+        query.to_expression()
     }
 
     /// Just the resolved `from=…&to=…` pair (for the exception detail fetch,
@@ -557,6 +584,16 @@ impl FilterSet {
             ("from".to_string(), from.to_string()),
             ("to".to_string(), to.to_string()),
         ];
+        let expression = self.exceptions_expression();
+        if !expression.is_empty() {
+            pairs.push(("q".to_string(), expression));
+        }
+        encode_pairs(&pairs)
+    }
+
+    /// The `q` expression of [`FilterSet::exceptions_query`]: only the terms
+    /// exception events can honour. Empty when nothing applies.
+    pub fn exceptions_expression(&self) -> String {
         let query = Query {
             terms: self
                 .query
@@ -571,11 +608,7 @@ impl FilterSet {
                 .clone()
                 .filter(|_| fields_apply(&self.query.advanced_fields(), EXCEPTION_FIELDS)),
         };
-        let expression = query.to_expression();
-        if !expression.is_empty() {
-            pairs.push(("q".to_string(), expression));
-        }
-        encode_pairs(&pairs)
+        query.to_expression()
     }
 
     /// Whether the advanced expression applies on the exceptions page (an
@@ -910,6 +943,8 @@ pub fn use_apply_filters() -> Callback<FilterSet> {
         let Some(navigator) = &navigator else { return };
         let target = match &route {
             Some(Route::Exceptions) => Route::Exceptions,
+            Some(Route::Funnels) => Route::Funnels,
+            Some(Route::Flows) => Route::Flows,
             _ => Route::Overview,
         };
         let _ = navigator.push_with_query(&target, &filters.to_pairs());
@@ -28,6 +28,22 @@ pub fn compact(n: i64) -> String {
     }
 }
 
+/// A reported event value: whole amounts grouped (`1,234`), fractional ones to
+/// two places, large ones compacted (`12k`); a known currency follows.
+pub fn format_value(value: f64, currency: Option<&str>) -> String {
+    let amount = if value.abs() >= 10_000.0 {
+        compact(value.round() as i64)
+    } else if value.fract().abs() < 0.005 {
+        group_thousands(value.round() as i64)
+    } else {
+        format!("{value:.2}")
+    };
+    match currency {
+        Some(currency) => format!("{amount} {currency}"),
+        None => amount,
+    }
+}
+
 pub fn format_duration(ms: i64) -> String {
     if ms >= 60_000 {
         format!("{}m {}s", ms / 60_000, (ms % 60_000) / 1000)
@@ -5,7 +5,9 @@
 
 use std::rc::Rc;
 
-use analytics_api::{Dashboard as DashboardData, SourceInput, source_label};
+use analytics_api::{
+    BreakdownRow, Dashboard as DashboardData, GoalSummary, SourceInput, source_label,
+};
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
@@ -32,6 +34,9 @@ pub fn dashboard() -> Html {
     let metric = use_state(Metric::default);
     let compare = use_state(|| true);
     let manage_project = use_state(|| None::<String>);
+    // The goal whose conversion rate annotates the breakdown rows (an index
+    // into `Dashboard::goals`).
+    let focused_goal = use_state(|| None::<usize>);
     // Monotonic fetch sequence: only the *latest* request may publish its
     // response, so a slow query can't overwrite a faster later one and show
     // data that disagrees with the URL.
@@ -146,6 +151,10 @@ pub fn dashboard() -> Html {
             navigate.emit((Route::Event, pairs));
         })
     };
+    let on_focus_goal = {
+        let focused_goal = focused_goal.clone();
+        Callback::from(move |goal: Option<usize>| focused_goal.set(goal))
+    };
     let bump = {
         let reload = reload.clone();
         Callback::from(move |_: ()| reload.set(*reload + 1))
@@ -172,9 +181,16 @@ pub fn dashboard() -> Html {
         Some(Err(err)) => html! { <ApiErrorAlert error={err.clone()} /> },
         Some(Ok(dash)) => {
             let active = filters.query.terms.clone();
+            // A refetch can drop goals (a project filter, a quiet period), so a
+            // stale focus simply lapses.
+            let goal = (*focused_goal).filter(|i| *i < dash.goals.len());
+            let conversion = |r: &BreakdownRow| {
+                goal.and_then(|i| r.goals.get(i))
+                    .map(|c| format!("{:.1}% conv.", c.rate * 100.0))
+            };
 
             // ---------------------------------------------------- panel rows
-            let plain = |rows: &[analytics_api::BreakdownRow], absent: &str| -> Vec<PanelRow> {
+            let plain = |rows: &[BreakdownRow], absent: &str| -> Vec<PanelRow> {
                 rows.iter()
                     .map(|r| PanelRow {
                         value: r.key.clone(),
@@ -188,6 +204,10 @@ pub fn dashboard() -> Html {
                         pageviews: r.pageviews,
                         events: r.events,
                         title: (!r.key.is_empty()).then(|| r.key.clone()),
+                        // Only the pages breakdown carries scroll depth; a
+                        // focused goal's conversion rate takes its place.
+                        detail: conversion(r)
+                            .or_else(|| r.scroll_depth.map(|depth| format!("{depth}% read"))),
                         extra: None,
                     })
                     .collect()
@@ -204,6 +224,7 @@ pub fn dashboard() -> Html {
                     pageviews: r.pageviews,
                     events: r.events,
                     title: (!r.app.is_empty() || !r.version.is_empty()).then(|| version_label(r)),
+                    detail: None,
                     // The version alone would also match other applications'
                     // releases, so clicking pins the application with it.
                     extra: Some((Dim::Browser, r.app.clone())),
@@ -225,6 +246,7 @@ pub fn dashboard() -> Html {
                     pageviews: r.pageviews,
                     events: r.events,
                     title: None,
+                    detail: conversion(r),
                     extra: None,
                 })
                 .collect::<Vec<_>>();
@@ -244,6 +266,7 @@ pub fn dashboard() -> Html {
                     pageviews: r.pageviews,
                     events: r.events,
                     title: (!r.key.is_empty()).then(|| r.key.clone()),
+                    detail: conversion(r),
                     extra: None,
                 })
                 .collect::<Vec<_>>();
@@ -261,6 +284,7 @@ pub fn dashboard() -> Html {
                     pageviews: r.pageviews,
                     events: r.events,
                     title: None,
+                    detail: None,
                     extra: None,
                 })
                 .collect::<Vec<_>>();
@@ -276,6 +300,7 @@ pub fn dashboard() -> Html {
                     pageviews: r.pageviews,
                     events: r.events,
                     title: Some(r.key.clone()),
+                    detail: conversion(r),
                     extra: None,
                 })
                 .collect::<Vec<_>>();
@@ -299,6 +324,11 @@ pub fn dashboard() -> Html {
                 .with_action_icon(ActionIcon::Open),
             ];
             let acquisition_tabs = vec![
+                PanelTab::new(
+                    "Channels",
+                    Dim::Channel,
+                    plain(&dash.breakdowns.channels, Dim::Channel.absent_label()),
+                ),
                 PanelTab::new(
                     "Referrers",
                     Dim::Referrer,
@@ -380,8 +410,14 @@ pub fn dashboard() -> Html {
                             metric={*metric}
                             compare={*compare}
                             on_zoom={Some(on_zoom.clone())}
+                            releases={dash.releases.clone()}
                         />
                     </div>
+                    <GoalsCard
+                        goals={dash.goals.clone()}
+                        focused={goal}
+                        on_focus={on_focus_goal.clone()}
+                    />
                     <div class="panel-grid">
                         <BreakdownPanel tabs={source_tabs} metric={*metric} on_filter={on_filter.clone()} active={active.clone()} />
                         // Follows the metric: Events mode surfaces the event-name
@@ -525,6 +561,7 @@ fn build_suggestions(
         (Dim::Device, rows(&dash.breakdowns.devices, "Unknown")),
         (Dim::UtmSource, rows(&dash.breakdowns.utm_sources, "None")),
         (Dim::UtmMedium, rows(&dash.breakdowns.utm_mediums, "None")),
+        (Dim::Channel, rows(&dash.breakdowns.channels, "Unknown")),
         (
             Dim::UtmCampaign,
             rows(&dash.breakdowns.utm_campaigns, "None"),
@@ -649,3 +686,64 @@ fn unassigned_banner(props: &UnassignedBannerProps) -> Html {
         </div>
     }
 }
+
+#[derive(Properties, PartialEq)]
+struct GoalsCardProps {
+    goals: Vec<GoalSummary>,
+    focused: Option<usize>,
+    /// Fired with the clicked goal's index, or `None` when it was focused.
+    on_focus: Callback<Option<usize>>,
+}
+
+/// Each project goal's conversions over the period. Clicking a goal shows its
+/// conversion rate on every breakdown row that reports one; clicking it again
+/// restores the rows' usual annotations.
+#[function_component(GoalsCard)]
+fn goals_card(props: &GoalsCardProps) -> Html {
+    if props.goals.is_empty() {
+        return html! {};
+    }
+    // Only name the project when goals from several are on show.
+    let several = props
+        .goals
+        .iter()
+        .any(|g| g.project_id != props.goals[0].project_id);
+
+    let rows = props.goals.iter().enumerate().map(|(i, g)| {
+        let focused = props.focused == Some(i);
+        let onclick = {
+            let on_focus = props.on_focus.clone();
+            Callback::from(move |_: MouseEvent| on_focus.emit((!focused).then_some(i)))
+        };
+        let c = &g.conversions;
+        html! {
+            <button
+                class={classes!("goals__row", focused.then_some("goals__row--focused"))}
+                key={format!("{}/{}", g.project_id, g.goal)}
+                title="Show this goal's conversion rate in the breakdowns"
+                {onclick}
+            >
+                <span class="goals__name">
+                    { &g.goal }
+                    if several {
+                        <span class="goals__project">{ &g.project_name }</span>
+                    }
+                </span>
+                <span class="goals__count">
+                    { format!("{} of {} sessions", group_thousands(c.conversions), group_thousands(c.sessions)) }
+                </span>
+                <span class="goals__rate">{ format!("{:.1}%", c.rate * 100.0) }</span>
+            </button>
+        }
+    });
+
+    html! {
+        <div class="goals">
+            <div class="goals__head">
+                <strong>{ "Goals" }</strong>
+                <span class="muted">{ "Click a goal to compare its conversion rate across the breakdowns" }</span>
+            </div>
+            { for rows }
+        </div>
+    }
+}
@@ -2,14 +2,17 @@
 //! the occurrence trend, how the event distributes across key dimensions
 //! (source, page, application, …), a scrubber over its **distinct metadata
 //! variants** — one representative example per unique reporter payload — and
-//! the session traces it occurred in.
+//! the session traces it occurred in. Events reporting a numeric value also
+//! get their value totals, charted over the range and split by dimension.
 //!
 //! The event name rides in the query string (`?name=…`) alongside the filter
 //! state, because names are reporter-chosen free text that must not break the
 //! router; the filters scope the numbers to the same slice as the dashboard
 //! panel that linked here.
 
-use analytics_api::{CountRow, EventDetail as EventDetailData, EventVariant, source_label};
+use analytics_api::{
+    CountRow, EventDetail as EventDetailData, EventVariant, ValueRow, source_label,
+};
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_router::prelude::use_location;
@@ -18,11 +21,12 @@ use crate::api::{self, ApiError};
 use crate::app::Route;
 use crate::components::metadata::Metadata;
 use crate::components::{
-    ApiErrorAlert, Crumb, PageHeader, Sparkline, TraceList, distribution, icons,
+    ApiErrorAlert, Crumb, PageHeader, Sparkline, TraceList, ValueSparkline, distribution, icons,
+    value_distribution,
 };
 use crate::filters::{query_param, use_filters, use_navigate_with_filters};
 use crate::format::{
-    ago, country_flag, country_name, group_thousands, language_name, short_session_id,
+    ago, country_flag, country_name, format_value, group_thousands, language_name, short_session_id,
 };
 
 #[function_component(EventDetail)]
@@ -110,6 +114,8 @@ pub fn event_detail() -> Html {
                             { distribution("Languages", &languages_named(&detail.breakdowns.languages), detail.count) }
                         </div>
 
+                        { value_section(detail, &range_label) }
+
                         <VariantScrubber variants={detail.variants.clone()} key={detail.name.clone()} />
                         // Pick which of the event's sessions to inspect.
                         <TraceList
@@ -130,6 +136,71 @@ pub fn event_detail() -> Html {
     }
 }
 
+/// The value totals of an event that reports numeric values: headline
+/// aggregates, the summed value over the range, and how it splits across
+/// sources, pages and countries. Empty for events that never report one.
+fn value_section(detail: &EventDetailData, range_label: &str) -> Html {
+    let Some(value) = &detail.value else {
+        return html! {};
+    };
+    let currency = value.currency.as_deref();
+    let per_visitor = value
+        .per_visitor
+        .map(|v| format_value(v, currency))
+        .unwrap_or_else(|| "—".to_string());
+    let values = &detail.value_breakdowns;
+    html! {
+        <>
+            <h2 class="section__title">{ "Value" }</h2>
+            <div class="stats">
+                <div class="stat">
+                    <span class="stat__label">{ "Total" }</span>
+                    <span class="stat__value">{ format_value(value.total, currency) }</span>
+                </div>
+                <div class="stat">
+                    <span class="stat__label">{ "Average" }</span>
+                    <span class="stat__value">{ format_value(value.average, currency) }</span>
+                </div>
+                <div class="stat">
+                    <span class="stat__label" title="Total over the sessions that reported a value">
+                        { "Per visitor" }
+                    </span>
+                    <span class="stat__value">{ per_visitor }</span>
+                </div>
+                <div class="stat">
+                    <span class="stat__label">{ "Valued occurrences" }</span>
+                    <span class="stat__value">{ group_thousands(value.count) }</span>
+                </div>
+            </div>
+            if !detail.value_trend.is_empty() {
+                <div class="exc-head__trend">
+                    <span class="stat__label">{ format!("Value · {range_label}") }</span>
+                    <ValueSparkline points={detail.value_trend.clone()} class={classes!("exc-head__spark", "exc-head__spark--brand")} />
+                </div>
+            }
+            <div class="dist-grid dist-grid--brand">
+                { value_distribution("Value by source", &value_rows_named(&values.sources, |k| source_label(k).to_string()), currency) }
+                { value_distribution("Value by page", &values.pages, currency) }
+                { value_distribution("Value by country", &value_rows_named(&values.countries, country_name), currency) }
+            </div>
+        </>
+    }
+}
+
+/// Value rows re-keyed by a display label (the absent sentinel stays empty).
+fn value_rows_named(rows: &[ValueRow], name: impl Fn(&str) -> String) -> Vec<ValueRow> {
+    rows.iter()
+        .map(|r| ValueRow {
+            key: if r.key.is_empty() {
+                String::new()
+            } else {
+                name(&r.key)
+            },
+            value: r.value.clone(),
+        })
+        .collect()
+}
+
 /// Source rows re-keyed by their display label (URIs are read as bare names
 /// throughout the UI).
 fn sources_named(rows: &[CountRow]) -> Vec<CountRow> {
@@ -2,16 +2,22 @@
 //! trend, how failures distribute across key dimensions (app version, OS,
 //! application, …), and a scrubber over the group's **distinct variants** — one
 //! representative example per unique message/stack, with a count of the
-//! occurrences it stands for.
+//! occurrences it stands for and the breadcrumbs of its latest occurrence.
+//! Groups of the same source that look like the same failure are listed below,
+//! each one click from being merged into this one. Who the group is assigned
+//! to, what lifts its snooze and its comment thread sit under the header.
 //!
 //! A group's identity is source-scoped — fingerprint + the application it was
 //! seen on — carried as a `?source=` query parameter alongside the filter
 //! state.
 
 use analytics_api::{
-    ExceptionGroupDetail, ExceptionVariant, TriageInput, source_label,
+    AdminUser, AssigneeInput, CommentInput, ExceptionGroup, ExceptionGroupDetail, ExceptionSnooze,
+    ExceptionVariant, MergeInput, SimilarGroup, SnoozeInput, TriageComment, TriageInput,
+    source_label,
 };
 use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlTextAreaElement;
 use yew::prelude::*;
 use yew_router::prelude::use_location;
 
@@ -20,10 +26,13 @@ use crate::app::Route;
 use crate::components::metadata::Metadata;
 use crate::components::status::{status_class, status_label};
 use crate::components::{
-    ApiErrorAlert, Crumb, PageHeader, Sparkline, TraceList, distribution, icons,
+    ApiErrorAlert, BreadcrumbTrail, Crumb, Dropdown, DropdownItem, PageHeader, Sparkline,
+    StackFrames, TraceList, distribution, icons,
 };
-use crate::filters::{query_param, use_filters, use_navigate_with_filters};
-use crate::format::{ago, group_thousands, short_session_id};
+use crate::filters::{
+    query_param, use_filters, use_navigate_with_filters, use_navigate_with_query,
+};
+use crate::format::{ago, clock_time, group_thousands, short_date, short_session_id};
 
 #[derive(Properties, PartialEq)]
 pub struct ExceptionDetailProps {
@@ -85,8 +94,8 @@ pub fn exception_detail(props: &ExceptionDetailProps) -> Html {
                 project_id: project.clone(),
                 resolved,
                 muted,
-                note: None,
                 source: source.clone().unwrap_or_default(),
+                note: None,
             };
             let (group, reload) = (group.clone(), reload.clone());
             Callback::from(move |_: MouseEvent| {
@@ -183,11 +192,16 @@ pub fn exception_detail(props: &ExceptionDetailProps) -> Html {
         (Some(_), None) => html! { <div class="page-loading">{ "Loading…" }</div> },
         (Some(_), Some(Err(err))) => html! {  <ApiErrorAlert error={err.clone()} /> },
         (Some(source), Some(Ok(detail))) => {
+            let first_seen = match &detail.group.first_release {
+                Some(release) => format!("{} in {release}", ago(detail.group.first_seen_ms)),
+                None => ago(detail.group.first_seen_ms),
+            };
             let meta = format!(
-                "{} · {} occurrences · first seen {} · last seen {}",
+                "{} · {} occurrences · {} sessions · {} page views · first seen {first_seen} · last seen {}",
                 source_label(source),
                 group_thousands(detail.group.count),
-                ago(detail.group.first_seen_ms),
+                group_thousands(detail.group.sessions),
+                group_thousands(detail.group.page_views),
                 ago(detail.group.last_seen_ms),
             );
             html! {
@@ -200,6 +214,9 @@ pub fn exception_detail(props: &ExceptionDetailProps) -> Html {
                             </div>
                         }
                         <div class="exc-head__meta muted">{ meta }</div>
+                        if let Some(release) = &detail.group.regressed_in {
+                            <span class="badge badge--warn">{ format!("Regressed in {release}") }</span>
+                        }
 
                         /*
                         <div class="exc-head__top">
@@ -211,12 +228,25 @@ pub fn exception_detail(props: &ExceptionDetailProps) -> Html {
                          */
                     </div>
 
+                    <TriagePanel
+                        project={project.clone()}
+                        source={source.clone()}
+                        group={detail.group.clone()}
+                        comments={detail.comments.clone()}
+                        on_changed={{
+                            let reload = reload.clone();
+                            Callback::from(move |()| reload.set(*reload + 1))
+                        }}
+                    />
+
                     <h2 class="section__title">{ "Distribution" }</h2>
                     <div class="dist-grid">
                         { distribution("App versions", &detail.breakdowns.app_versions, detail.group.count) }
                         { distribution("Applications", &detail.breakdowns.browsers, detail.group.count) }
                         { distribution("Operating systems", &detail.breakdowns.operating_systems, detail.group.count) }
                         { distribution("Devices", &detail.breakdowns.devices, detail.group.count) }
+                        { distribution("Levels", &detail.breakdowns.levels, detail.group.count) }
+                        { distribution("Environments", &detail.breakdowns.environments, detail.group.count) }
                     </div>
 
                     <VariantScrubber
@@ -228,6 +258,16 @@ pub fn exception_detail(props: &ExceptionDetailProps) -> Html {
                         traces={detail.traces.clone()}
                         hint="Sessions this exception occurred in"
                     />
+                    <SimilarIssues
+                        project={project.clone()}
+                        source={source.clone()}
+                        group={detail.group.group_id.clone()}
+                        similar={detail.similar.clone()}
+                        on_merged={{
+                            let reload = reload.clone();
+                            Callback::from(move |()| reload.set(*reload + 1))
+                        }}
+                    />
                 </>
             }
         }
@@ -243,6 +283,246 @@ pub fn exception_detail(props: &ExceptionDetailProps) -> Html {
     }
 }
 
+/// The snoozes offered, by dropdown value.
+const SNOOZES: [(&str, &str); 6] = [
+    ("hour", "For an hour"),
+    ("day", "For a day"),
+    ("week", "For a week"),
+    ("occurrences:10", "Until 10 more occurrences"),
+    ("occurrences:100", "Until 100 more occurrences"),
+    ("sessions:10", "Until 10 more sessions"),
+];
+
+/// The snooze a [`SNOOZES`] value stands for, as of now.
+fn snooze_choice(value: &str) -> Option<ExceptionSnooze> {
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+    let from_now = |ms: i64| ExceptionSnooze::Date {
+        at_ms: js_sys::Date::now() as i64 + ms,
+    };
+    match value.split_once(':') {
+        Some(("occurrences", count)) => Some(ExceptionSnooze::Occurrences {
+            count: count.parse().ok()?,
+        }),
+        Some(("sessions", count)) => Some(ExceptionSnooze::Sessions {
+            count: count.parse().ok()?,
+        }),
+        _ => match value {
+            "hour" => Some(from_now(HOUR_MS)),
+            "day" => Some(from_now(24 * HOUR_MS)),
+            "week" => Some(from_now(7 * 24 * HOUR_MS)),
+            _ => None,
+        },
+    }
+}
+
+/// What lifts a snooze, e.g. "until 10 more sessions".
+fn snooze_label(snooze: ExceptionSnooze) -> String {
+    match snooze {
+        ExceptionSnooze::Date { at_ms } => {
+            format!("until {}, {}", short_date(at_ms), &clock_time(at_ms)[..5])
+        }
+        ExceptionSnooze::Occurrences { count } => format!("until {count} more occurrences"),
+        ExceptionSnooze::Sessions { count } => format!("until {count} more sessions"),
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TriagePanelProps {
+    project: String,
+    source: String,
+    group: ExceptionGroup,
+    comments: Vec<TriageComment>,
+    /// Fired after the group's triage changed.
+    on_changed: Callback<()>,
+}
+
+/// Who the group is assigned to, a snooze that lifts its mute by itself, and
+/// the comment thread.
+#[function_component(TriagePanel)]
+fn triage_panel(props: &TriagePanelProps) -> Html {
+    let assignees = use_state(Vec::<AdminUser>::new);
+    let draft = use_state(String::new);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<ApiError>);
+    {
+        let assignees = assignees.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                // Without the list the current assignee is still shown.
+                if let Ok(users) = api::exception_assignees().await {
+                    assignees.set(users);
+                }
+            });
+        });
+    }
+
+    let settle = {
+        let (busy, error, on_changed) = (busy.clone(), error.clone(), props.on_changed.clone());
+        move |result: Result<(), ApiError>| {
+            busy.set(false);
+            match result {
+                Ok(()) => {
+                    error.set(None);
+                    on_changed.emit(());
+                }
+                Err(err) => error.set(Some(err)),
+            }
+        }
+    };
+
+    let assignee_select = {
+        let current = props.group.assignee.clone();
+        let mut items = vec![DropdownItem::new("", "Unassigned")];
+        items.extend(
+            assignees
+                .iter()
+                .map(|user| DropdownItem::new(user.key().to_string(), user.name.clone())),
+        );
+        if let Some(user) = current
+            .as_ref()
+            .filter(|user| !assignees.iter().any(|u| u.key() == user.key()))
+        {
+            items.push(DropdownItem::new(user.key().to_string(), user.name.clone()));
+        }
+        let on_select = {
+            let (busy, settle) = (busy.clone(), settle.clone());
+            let (project, source, group) = (
+                props.project.clone(),
+                props.source.clone(),
+                props.group.group_id.clone(),
+            );
+            Callback::from(move |key: String| {
+                let input = AssigneeInput {
+                    project_id: project.clone(),
+                    source: source.clone(),
+                    assignee: Some(key).filter(|key| !key.is_empty()),
+                };
+                let (group, settle) = (group.clone(), settle.clone());
+                busy.set(true);
+                spawn_local(async move {
+                    settle(api::assign_exception(&group, &input).await);
+                });
+            })
+        };
+        html! {
+            <Dropdown items={items}
+                value={current.as_ref().map(|user| user.key().to_string()).unwrap_or_default()}
+                placeholder="Unassigned" on_select={on_select} disabled={*busy} />
+        }
+    };
+
+    let snooze_select = {
+        let items: Vec<DropdownItem> = SNOOZES
+            .iter()
+            .map(|(value, label)| DropdownItem::new(*value, *label))
+            .collect();
+        let placeholder = match props.group.snooze {
+            Some(snooze) => format!("Snoozed {}", snooze_label(snooze)),
+            None => "Snooze…".to_string(),
+        };
+        let on_select = {
+            let (busy, settle) = (busy.clone(), settle.clone());
+            let (project, source, group) = (
+                props.project.clone(),
+                props.source.clone(),
+                props.group.group_id.clone(),
+            );
+            Callback::from(move |value: String| {
+                let Some(snooze) = snooze_choice(&value) else {
+                    return;
+                };
+                let input = SnoozeInput {
+                    project_id: project.clone(),
+                    source: source.clone(),
+                    snooze,
+                };
+                let (group, settle) = (group.clone(), settle.clone());
+                busy.set(true);
+                spawn_local(async move {
+                    settle(api::snooze_exception(&group, &input).await);
+                });
+            })
+        };
+        html! {
+            <Dropdown items={items} placeholder={placeholder} on_select={on_select}
+                disabled={*busy} />
+        }
+    };
+
+    let on_draft = {
+        let draft = draft.clone();
+        Callback::from(move |e: InputEvent| {
+            draft.set(e.target_unchecked_into::<HtmlTextAreaElement>().value());
+        })
+    };
+    let on_comment = {
+        let (busy, draft, settle) = (busy.clone(), draft.clone(), settle.clone());
+        let (project, source, group) = (
+            props.project.clone(),
+            props.source.clone(),
+            props.group.group_id.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let input = CommentInput {
+                project_id: project.clone(),
+                source: source.clone(),
+                body: (*draft).clone(),
+            };
+            let (group, draft, settle) = (group.clone(), draft.clone(), settle.clone());
+            busy.set(true);
+            spawn_local(async move {
+                let result = api::comment_on_exception(&group, &input).await;
+                if result.is_ok() {
+                    draft.set(String::new());
+                }
+                settle(result.map(|_| ()));
+            });
+        })
+    };
+
+    let comments = props.comments.iter().map(|comment| {
+        html! {
+            <li class="exc-comment">
+                <div class="exc-comment__meta">
+                    <strong>{ comment.author.clone().unwrap_or_else(|| "Someone".into()) }</strong>
+                    <span class="muted">{ ago(comment.created_at.timestamp_millis()) }</span>
+                </div>
+                <p class="exc-comment__body">{ &comment.body }</p>
+            </li>
+        }
+    });
+
+    html! {
+        <>
+            <h2 class="section__title">{ "Triage" }</h2>
+            if let Some(err) = &*error {
+                <ApiErrorAlert error={err.clone()} />
+            }
+            <div class="exc-triage panel">
+                <div class="exc-triage__controls">
+                    <div class="field">
+                        <span class="field__label">{ "Assignee" }</span>
+                        { assignee_select }
+                    </div>
+                    <div class="field">
+                        <span class="field__label">{ "Snooze" }</span>
+                        { snooze_select }
+                    </div>
+                </div>
+                <ul class="exc-comments">{ for comments }</ul>
+                <div class="exc-triage__compose">
+                    <textarea class="input" rows="3" placeholder="Add a comment…"
+                        value={(*draft).clone()} oninput={on_draft} />
+                    <button class="btn btn--small" onclick={on_comment}
+                        disabled={*busy || draft.trim().is_empty()}>
+                        { "Comment" }
+                    </button>
+                </div>
+            </div>
+        </>
+    }
+}
+
 #[derive(Properties, PartialEq)]
 struct VariantScrubberProps {
     variants: Vec<ExceptionVariant>,
@@ -338,9 +618,127 @@ fn variant_scrubber(props: &VariantScrubberProps) -> Html {
                 }
                 { metadata.context_list() }
                 if let Some(stack) = &variant.stack {
-                    <pre class="stack">{ stack }</pre>
+                    <StackFrames frames={variant.frames.clone()} raw={stack.clone()} />
                 }
+                <BreadcrumbTrail crumbs={variant.breadcrumbs.clone()} />
             </div>
         </>
     }
 }
+
+#[derive(Properties, PartialEq)]
+struct SimilarIssuesProps {
+    project: String,
+    source: String,
+    group: String,
+    similar: Vec<SimilarGroup>,
+    /// Fired after a suggested group was merged into this one.
+    on_merged: Callback<()>,
+}
+
+/// Groups of the same source that look like this one, with how alike they
+/// are; each opens on click or can be merged into this group.
+#[function_component(SimilarIssues)]
+fn similar_issues(props: &SimilarIssuesProps) -> Html {
+    let filters = use_filters();
+    let navigate = use_navigate_with_query();
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<ApiError>);
+    if props.similar.is_empty() {
+        return html! {};
+    }
+
+    let rows = props.similar.iter().map(|similar| {
+        let open = {
+            let (navigate, filters) = (navigate.clone(), filters.clone());
+            let (project, group, source) = (
+                props.project.clone(),
+                similar.group_id.clone(),
+                props.source.clone(),
+            );
+            Callback::from(move |_: MouseEvent| {
+                let mut pairs = filters.to_pairs();
+                pairs.push(("source".into(), source.clone()));
+                navigate.emit((
+                    Route::Exception {
+                        project: project.clone(),
+                        group: group.clone(),
+                    },
+                    pairs,
+                ));
+            })
+        };
+        let merge = {
+            let (busy, error, on_merged) = (busy.clone(), error.clone(), props.on_merged.clone());
+            let target = props.group.clone();
+            let input = MergeInput {
+                project_id: props.project.clone(),
+                source: props.source.clone(),
+                groups: vec![similar.group_id.clone()],
+            };
+            Callback::from(move |e: MouseEvent| {
+                e.stop_propagation();
+                let confirmed = web_sys::window()
+                    .and_then(|w| {
+                        w.confirm_with_message(
+                            "Merge this group into the one you are viewing? Its occurrences and triage state move here.",
+                        )
+                        .ok()
+                    })
+                    .unwrap_or(false);
+                if !confirmed {
+                    return;
+                }
+                let (busy, error, on_merged) = (busy.clone(), error.clone(), on_merged.clone());
+                let (target, input) = (target.clone(), input.clone());
+                busy.set(true);
+                spawn_local(async move {
+                    match api::merge_exceptions(&target, &input).await {
+                        Ok(()) => {
+                            error.set(None);
+                            on_merged.emit(());
+                        }
+                        Err(err) => error.set(Some(err)),
+                    }
+                    busy.set(false);
+                });
+            })
+        };
+        html! {
+            <div class="exc-row" key={similar.group_id.clone()} role="button" tabindex="0"
+                onclick={open}>
+                <span class="exc-similar__score" title="Similarity of the normalized message and stack frames">
+                    { format!("{:.0}%", similar.score * 100.0) }
+                </span>
+                <div class="exc-row__main">
+                    <div class="exc-row__title">
+                        <span class="exc-row__type">{ &similar.exc_type }</span>
+                        <span class="exc-row__message" title={similar.sample_message.clone()}>
+                            { &similar.sample_message }
+                        </span>
+                    </div>
+                    <div class="exc-row__meta muted">
+                        { format!(
+                            "{} occurrences · last seen {}",
+                            group_thousands(similar.count),
+                            ago(similar.last_seen_ms),
+                        ) }
+                    </div>
+                </div>
+                <button class="btn btn--small" onclick={merge} disabled={*busy}>
+                    { "Merge into this group" }
+                </button>
+            </div>
+        }
+    });
+
+    html! {
+        <>
+            <h2 class="section__title">{ "Similar issues" }</h2>
+            if let Some(err) = &*error {
+                <ApiErrorAlert error={err.clone()} />
+            }
+            <div class="exc-list exc-similar">{ for rows }</div>
+        </>
+    }
+}
@@ -2,12 +2,28 @@
 //! (and unassigned sources), for triage and investigation. Shares the URL
 //! filter state with the dashboard — project/source/browser/OS/device chips
 //! apply here; other dimensions render inert since exception events don't
-//! carry them.
+//! carry them. The environment selector is the `environment` chip in another
+//! form, so it too lives in the URL. A line above the list reports what the
+//! projects' inbound filters discarded before storage.
+//!
+//! The agent searches, sorts and pages the groups: the status tabs, the search
+//! box and the sort selector each re-query the first page, and "Load more"
+//! follows the page's cursor.
+//!
+//! Rows of assigned sources can be ticked for bulk triage: the bar above the
+//! list resolves, reopens, mutes, unmutes or comments on the ticked groups in
+//! one request, or on every group the current view matches (pages not yet
+//! loaded included).
 
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 use std::rc::Rc;
 
-use analytics_api::{ExceptionStatus, GlobalException};
+use analytics_api::{
+    BulkTriageInput, BulkTriageResult, BulkTriageTarget, CountRow, DiscardedExceptions,
+    ExceptionLevel, ExceptionPage, ExceptionSort, ExceptionStatus, ExceptionStatusCounts,
+    GlobalException, TriageTarget,
+};
 use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
@@ -16,16 +32,17 @@ use crate::api::{self, ApiError};
 use crate::app::Route;
 use crate::components::status::{status_class, status_label};
 use crate::components::{
-    ApiErrorAlert, FilterBar, PageHeader, ProjectsContext, Sparkline, SuggestOption,
+    ApiErrorAlert, Dropdown, DropdownItem, FilterBar, PageHeader, ProjectsContext, Sparkline,
+    SuggestOption,
 };
-use crate::filters::{Dim, use_filters, use_navigate_with_query};
+use crate::filters::{Dim, use_apply_filters, use_filters, use_navigate_with_query};
 use crate::format::{ago, group_thousands};
 
 thread_local! {
-    /// The last-used status tab and text filter, so a detail round-trip (or any
-    /// navigation) restores the inbox the way the operator left it.
-    static INBOX_VIEW: RefCell<(StatusTab, String)> =
-        const { RefCell::new((StatusTab::Unresolved, String::new())) };
+    /// The last-used status tab, search and order, so a detail round-trip (or
+    /// any navigation) restores the inbox the way the operator left it.
+    static INBOX_VIEW: RefCell<(StatusTab, String, ExceptionSort)> =
+        const { RefCell::new((StatusTab::Unresolved, String::new(), ExceptionSort::LastSeen)) };
 }
 
 /// The status tabs across the top of the inbox.
@@ -55,12 +72,32 @@ impl StatusTab {
         }
     }
 
-    fn matches(self, status: ExceptionStatus) -> bool {
+    /// The `status` the listing is narrowed to; `None` lists every status.
+    fn status(self) -> Option<&'static str> {
+        match self {
+            StatusTab::Unresolved => Some("unresolved"),
+            StatusTab::Resolved => Some("resolved"),
+            StatusTab::Ignored => Some("ignored"),
+            StatusTab::All => None,
+        }
+    }
+
+    /// [`StatusTab::status`] as the bulk triage query takes it.
+    fn exception_status(self) -> Option<ExceptionStatus> {
+        match self {
+            StatusTab::Unresolved => Some(ExceptionStatus::Unresolved),
+            StatusTab::Resolved => Some(ExceptionStatus::Resolved),
+            StatusTab::Ignored => Some(ExceptionStatus::Ignored),
+            StatusTab::All => None,
+        }
+    }
+
+    fn count(self, counts: &ExceptionStatusCounts) -> u64 {
         match self {
-            StatusTab::Unresolved => status == ExceptionStatus::Unresolved,
-            StatusTab::Resolved => status == ExceptionStatus::Resolved,
-            StatusTab::Ignored => status == ExceptionStatus::Ignored,
-            StatusTab::All => true,
+            StatusTab::Unresolved => counts.unresolved,
+            StatusTab::Resolved => counts.resolved,
+            StatusTab::Ignored => counts.ignored,
+            StatusTab::All => counts.unresolved + counts.resolved + counts.ignored,
         }
     }
 }
@@ -71,50 +108,148 @@ pub fn exceptions() -> Html {
     let projects = use_context::<ProjectsContext>()
         .map(|c| c.projects.clone())
         .unwrap_or_default();
-    let data = use_state(|| None::<Result<Vec<GlobalException>, ApiError>>);
+    let data = use_state(|| None::<Result<ExceptionPage, ApiError>>);
     let tab = use_state(|| INBOX_VIEW.with(|v| v.borrow().0));
-    let needle = use_state(|| INBOX_VIEW.with(|v| v.borrow().1.clone()));
+    let search = use_state(|| INBOX_VIEW.with(|v| v.borrow().1.clone()));
+    let sort = use_state(|| INBOX_VIEW.with(|v| v.borrow().2));
+    // The "Load more" request in flight, and how the last one failed.
+    let loading_more = use_state(|| false);
+    let more_error = use_state(|| None::<ApiError>);
+    let discarded = use_state(Vec::<DiscardedExceptions>::new);
+    let environments = use_state(Vec::<CountRow>::new);
+    let apply = use_apply_filters();
+    // The ticked rows as (project, source, group), or every matching group;
+    // both reset whenever the listing is re-queried.
+    let selected = use_state(BTreeSet::<(String, String, String)>::new);
+    let all_matching = use_state(|| false);
+    let bulk_comment = use_state(String::new);
+    let applying = use_state(|| false);
+    let bulk_outcome = use_state(|| None::<Result<BulkTriageResult, ApiError>>);
+    let reload = use_state(|| 0u32);
     // Only the latest request may publish (out-of-order responses would show
     // data that disagrees with the active chips).
     let fetch_seq = use_mut_ref(|| 0u64);
 
     {
         let (data, fetch_seq) = (data.clone(), fetch_seq.clone());
+        let (loading_more, more_error) = (loading_more.clone(), more_error.clone());
+        let (selected, all_matching) = (selected.clone(), all_matching.clone());
         let filters = filters.clone();
-        use_effect_with(filters.canonical(), move |_| {
-            let seq = {
-                let mut current = fetch_seq.borrow_mut();
-                *current += 1;
-                *current
-            };
-            let query = filters.exceptions_query(js_sys::Date::now() as i64);
+        use_effect_with(
+            (filters.canonical(), *tab, (*search).clone(), *sort, *reload),
+            move |(_, tab, search, sort, _)| {
+                let seq = {
+                    let mut current = fetch_seq.borrow_mut();
+                    *current += 1;
+                    *current
+                };
+                let query = filters.exceptions_query(js_sys::Date::now() as i64);
+                let (tab, search, sort) = (*tab, search.clone(), *sort);
+                data.set(None);
+                loading_more.set(false);
+                more_error.set(None);
+                selected.set(BTreeSet::new());
+                all_matching.set(false);
+                spawn_local(async move {
+                    let result =
+                        api::list_all_exceptions(&query, &search, sort, tab.status(), None).await;
+                    if *fetch_seq.borrow() == seq {
+                        data.set(Some(result));
+                    }
+                });
+                || ()
+            },
+        );
+    }
+
+    {
+        // The counters run since each filter was added rather than over the
+        // filtered range, so they are fetched once; a failure just leaves the
+        // line out.
+        let discarded = discarded.clone();
+        use_effect_with((), move |_| {
             spawn_local(async move {
-                let result = api::list_all_exceptions(&query).await;
-                if *fetch_seq.borrow() == seq {
-                    data.set(Some(result));
+                if let Ok(counts) = api::discarded_exceptions().await {
+                    discarded.set(counts);
                 }
             });
             || ()
         });
     }
 
-    let on_needle = {
-        let needle = needle.clone();
-        Callback::from(move |e: InputEvent| {
+    {
+        // The selector offers what was reported over the window, whatever the
+        // other chips narrow the listing to.
+        let (environments, filters) = (environments.clone(), filters.clone());
+        use_effect_with(filters.range, move |_| {
+            let range = filters.range_query(js_sys::Date::now() as i64);
+            spawn_local(async move {
+                if let Ok(rows) = api::exception_environments(&range).await {
+                    environments.set(rows);
+                }
+            });
+            || ()
+        });
+    }
+
+    // The search runs when committed (Enter, or leaving the box), not on
+    // every keystroke: each one is a query over the whole range.
+    let on_search = {
+        let search = search.clone();
+        Callback::from(move |e: Event| {
             let value = e.target_unchecked_into::<HtmlInputElement>().value();
+            let value = value.trim().to_string();
             INBOX_VIEW.with(|v| v.borrow_mut().1 = value.clone());
-            needle.set(value);
+            search.set(value);
+        })
+    };
+
+    let load_more = {
+        let (data, fetch_seq, filters) = (data.clone(), fetch_seq.clone(), filters.clone());
+        let (loading_more, more_error) = (loading_more.clone(), more_error.clone());
+        let (tab, search, sort) = (*tab, (*search).clone(), *sort);
+        Callback::from(move |_: MouseEvent| {
+            let Some(Ok(current)) = (*data).clone() else {
+                return;
+            };
+            let Some(cursor) = current.next_cursor.clone() else {
+                return;
+            };
+            let seq = *fetch_seq.borrow();
+            let query = filters.exceptions_query(js_sys::Date::now() as i64);
+            let (data, fetch_seq, search) = (data.clone(), fetch_seq.clone(), search.clone());
+            let (loading_more, more_error) = (loading_more.clone(), more_error.clone());
+            loading_more.set(true);
+            more_error.set(None);
+            spawn_local(async move {
+                let result =
+                    api::list_all_exceptions(&query, &search, sort, tab.status(), Some(&cursor))
+                        .await;
+                // A change of filters, tab, search or order since starts over.
+                if *fetch_seq.borrow() != seq {
+                    return;
+                }
+                loading_more.set(false);
+                match result {
+                    Ok(next) => {
+                        let mut groups = current.groups;
+                        groups.extend(next.groups);
+                        data.set(Some(Ok(ExceptionPage { groups, ..next })));
+                    }
+                    Err(err) => more_error.set(Some(err)),
+                }
+            });
         })
     };
 
     // Suggestions for the (restricted) filter chips on this page: the full
-    // project list (filters address projects by name) and the sources seen in
-    // the current listing.
+    // project list (filters address projects by name), the sources seen in
+    // the current listing, the reported environments and every level.
     let suggestions: Vec<(Dim, Vec<SuggestOption>)> = {
         let sources: Vec<SuggestOption> = match &*data {
-            Some(Ok(list)) => {
+            Some(Ok(page)) => {
                 let mut seen: Vec<String> = Vec::new();
-                for e in list {
+                for e in &page.groups {
                     if !seen.contains(&e.source) {
                         seen.push(e.source.clone());
                     }
@@ -135,93 +270,400 @@ pub fn exceptions() -> Html {
                 label: p.name.clone(),
             })
             .collect();
-        vec![(Dim::Project, project_options), (Dim::Source, sources)]
+        let environment_options: Vec<SuggestOption> = environments
+            .iter()
+            .map(|row| SuggestOption {
+                value: row.key.clone(),
+                label: row.key.clone(),
+            })
+            .collect();
+        let level_options: Vec<SuggestOption> = ExceptionLevel::ALL
+            .into_iter()
+            .map(|level| SuggestOption {
+                value: level.as_str().to_string(),
+                label: level.as_str().to_string(),
+            })
+            .collect();
+        vec![
+            (Dim::Project, project_options),
+            (Dim::Source, sources),
+            (Dim::Environment, environment_options),
+            (Dim::Level, level_options),
+        ]
     };
 
-    let body = match &*data {
-        None => html! { <div class="page-loading">{ "Loading…" }</div> },
-        Some(Err(err)) => html! { <ApiErrorAlert error={err.clone()} /> },
-        Some(Ok(list)) => {
-            let text = needle.to_lowercase();
-            let mut counts = [0usize; 4];
-            for e in list {
-                for (i, t) in StatusTab::ALL.into_iter().enumerate() {
-                    if t.matches(e.group.status) {
-                        counts[i] += 1;
-                    }
+    // Every reported environment, plus the selected one if it reported
+    // nothing in this window, so the selection always shows.
+    let selected_environment = filters.get(Dim::Environment).unwrap_or("").to_string();
+    let environment_select = {
+        let mut items = vec![DropdownItem::new("", "All environments")];
+        items.extend(
+            environments
+                .iter()
+                .map(|row| DropdownItem::new(row.key.clone(), row.key.clone())),
+        );
+        if !selected_environment.is_empty()
+            && !environments
+                .iter()
+                .any(|row| row.key == selected_environment)
+        {
+            items.push(DropdownItem::new(
+                selected_environment.clone(),
+                selected_environment.clone(),
+            ));
+        }
+        let on_select = {
+            let (apply, filters) = (apply.clone(), filters.clone());
+            Callback::from(move |environment: String| {
+                apply.emit(if environment.is_empty() {
+                    filters.without(Dim::Environment)
+                } else {
+                    filters.with(Dim::Environment, environment)
+                });
+            })
+        };
+        html! {
+            <div class="exc-toolbar__environment">
+                <Dropdown items={items} value={selected_environment}
+                    placeholder="All environments" on_select={on_select} />
+            </div>
+        }
+    };
+
+    let sort_select = {
+        let items: Vec<DropdownItem> = ExceptionSort::ALL
+            .into_iter()
+            .map(|s| DropdownItem::new(s.as_str(), format!("Sort: {}", s.label())))
+            .collect();
+        let on_select = {
+            let sort = sort.clone();
+            Callback::from(move |value: String| {
+                if let Some(value) = ExceptionSort::parse(&value) {
+                    INBOX_VIEW.with(|v| v.borrow_mut().2 = value);
+                    sort.set(value);
                 }
-            }
+            })
+        };
+        html! {
+            <div class="exc-toolbar__sort">
+                <Dropdown items={items} value={sort.as_str()} on_select={on_select} />
+            </div>
+        }
+    };
 
-            let visible: Vec<&GlobalException> = list
-                .iter()
-                .filter(|e| tab.matches(e.group.status))
-                .filter(|e| {
-                    text.is_empty()
-                        || e.group.exc_type.to_lowercase().contains(&text)
-                        || e.group.sample_message.to_lowercase().contains(&text)
-                        || e.project_name
-                            .as_deref()
-                            .unwrap_or("")
-                            .to_lowercase()
-                            .contains(&text)
-                })
-                .collect();
+    let counts = match &*data {
+        Some(Ok(page)) => page.status_counts,
+        _ => ExceptionStatusCounts::default(),
+    };
+    let tabs = StatusTab::ALL.into_iter().enumerate().map(|(i, t)| {
+        let active = *tab == t;
+        let onclick = {
+            let tab = tab.clone();
+            Callback::from(move |_: MouseEvent| {
+                INBOX_VIEW.with(|v| v.borrow_mut().0 = t);
+                tab.set(t);
+            })
+        };
+        html! {
+            <button key={i.to_string()}
+                class={classes!("panel-tab", active.then_some("panel-tab--active"))}
+                onclick={onclick}>
+                { t.label() }
+                if data.is_some() {
+                    <span class="panel-tab__count">{ group_thousands(t.count(&counts) as i64) }</span>
+                }
+            </button>
+        }
+    });
+    let toolbar = html! {
+        <div class="exc-toolbar">
+            <div class="panel-card__tabs">{ for tabs }</div>
+            <div class="exc-toolbar__controls">
+                { environment_select }
+                { sort_select }
+                <input class="input exc-toolbar__search" type="search"
+                    placeholder="Search messages and stack frames…"
+                    value={(*search).clone()} onchange={on_search} />
+            </div>
+        </div>
+    };
+
+    let on_toggle = {
+        let (selected, all_matching, bulk_outcome) =
+            (selected.clone(), all_matching.clone(), bulk_outcome.clone());
+        Callback::from(move |key: (String, String, String)| {
+            let mut next = (*selected).clone();
+            if !next.remove(&key) {
+                next.insert(key);
+            }
+            all_matching.set(false);
+            bulk_outcome.set(None);
+            selected.set(next);
+        })
+    };
 
-            let tabs = StatusTab::ALL.into_iter().enumerate().map(|(i, t)| {
-                let active = *tab == t;
-                let onclick = {
-                    let tab = tab.clone();
-                    Callback::from(move |_: MouseEvent| {
-                        INBOX_VIEW.with(|v| v.borrow_mut().0 = t);
-                        tab.set(t);
+    // Build a click handler applying one change to the selection; the comment
+    // action sends the typed comment and does nothing while it is blank.
+    let bulk_action = |resolved: Option<bool>, muted: Option<bool>, with_comment: bool| {
+        let target = if *all_matching {
+            let (from, to, _) = filters.range.resolve(js_sys::Date::now() as i64);
+            let q = filters.exceptions_expression();
+            BulkTriageTarget::Query {
+                from: Some(from),
+                to: Some(to),
+                q: (!q.is_empty()).then_some(q),
+                search: (!search.is_empty()).then(|| (*search).clone()),
+                status: tab.exception_status(),
+            }
+        } else {
+            BulkTriageTarget::Groups {
+                groups: selected
+                    .iter()
+                    .map(|(project_id, source, group_id)| TriageTarget {
+                        project_id: project_id.clone(),
+                        source: source.clone(),
+                        group_id: group_id.clone(),
                     })
-                };
-                html! {
-                    <button key={i.to_string()}
-                        class={classes!("panel-tab", active.then_some("panel-tab--active"))}
-                        onclick={onclick}>
-                        { t.label() }
-                        <span class="panel-tab__count">{ counts[i] }</span>
-                    </button>
+                    .collect(),
+            }
+        };
+        let (bulk_comment, applying, bulk_outcome, reload) = (
+            bulk_comment.clone(),
+            applying.clone(),
+            bulk_outcome.clone(),
+            reload.clone(),
+        );
+        Callback::from(move |_: MouseEvent| {
+            let comment = if with_comment {
+                let body = bulk_comment.trim().to_string();
+                if body.is_empty() {
+                    return;
+                }
+                Some(body)
+            } else {
+                None
+            };
+            let input = BulkTriageInput {
+                target: target.clone(),
+                resolved,
+                muted,
+                comment,
+            };
+            let (bulk_comment, applying, bulk_outcome, reload) = (
+                bulk_comment.clone(),
+                applying.clone(),
+                bulk_outcome.clone(),
+                reload.clone(),
+            );
+            applying.set(true);
+            spawn_local(async move {
+                let result = api::bulk_triage(&input).await;
+                applying.set(false);
+                if result.is_ok() {
+                    if with_comment {
+                        bulk_comment.set(String::new());
+                    }
+                    reload.set(*reload + 1);
                 }
+                bulk_outcome.set(Some(result));
             });
+        })
+    };
 
-            html! {
-                <>
-                    <div class="exc-toolbar">
-                        <div class="panel-card__tabs">{ for tabs }</div>
-                        <input class="input exc-toolbar__search" type="search"
-                            placeholder="Filter by type, message, project…"
-                            value={(*needle).clone()} oninput={on_needle} />
-                    </div>
-                    if visible.is_empty() {
-                        <div class="empty">
-                            { if list.is_empty() { "No exceptions reported in this period." }
-                              else { "No exceptions match the current filters." } }
-                        </div>
-                    } else {
-                        <div class="exc-list">
-                            { for visible.iter().map(|e| exception_row(e)) }
-                        </div>
+    let bulk_bar = if selected.is_empty() && !*all_matching {
+        match &*bulk_outcome {
+            Some(Ok(outcome)) => bulk_outcome_line(outcome),
+            _ => Html::default(),
+        }
+    } else {
+        let matching = tab.count(&counts);
+        let summary = if *all_matching {
+            format!(
+                "All {} matching groups selected",
+                group_thousands(matching as i64)
+            )
+        } else {
+            format!("{} selected", group_thousands(selected.len() as i64))
+        };
+        let select_all = {
+            let all_matching = all_matching.clone();
+            Callback::from(move |_: MouseEvent| all_matching.set(true))
+        };
+        let clear = {
+            let (selected, all_matching) = (selected.clone(), all_matching.clone());
+            Callback::from(move |_: MouseEvent| {
+                selected.set(BTreeSet::new());
+                all_matching.set(false);
+            })
+        };
+        let on_comment_input = {
+            let bulk_comment = bulk_comment.clone();
+            Callback::from(move |e: InputEvent| {
+                bulk_comment.set(e.target_unchecked_into::<HtmlInputElement>().value());
+            })
+        };
+        html! {
+            <>
+                <div class="exc-bulk">
+                    <span class="exc-bulk__summary">{ summary }</span>
+                    if !*all_matching && matching > selected.len() as u64 {
+                        <button class="btn btn--small btn--ghost" onclick={select_all}>
+                            { format!("Select all {} matching", group_thousands(matching as i64)) }
+                        </button>
                     }
-                </>
-            }
+                    <button class="btn btn--small btn--ghost" onclick={clear}>{ "Clear" }</button>
+                    <div class="exc-bulk__actions">
+                        <button class="btn btn--small" disabled={*applying}
+                            onclick={bulk_action(Some(true), None, false)}>{ "Resolve" }</button>
+                        <button class="btn btn--small" disabled={*applying}
+                            onclick={bulk_action(Some(false), None, false)}>{ "Reopen" }</button>
+                        <button class="btn btn--small" disabled={*applying}
+                            onclick={bulk_action(None, Some(true), false)}>{ "Mute" }</button>
+                        <button class="btn btn--small" disabled={*applying}
+                            onclick={bulk_action(None, Some(false), false)}>{ "Unmute" }</button>
+                        <input class="input exc-bulk__comment" type="text"
+                            placeholder="Add a comment…"
+                            value={(*bulk_comment).clone()} oninput={on_comment_input} />
+                        <button class="btn btn--small"
+                            disabled={*applying || bulk_comment.trim().is_empty()}
+                            onclick={bulk_action(None, None, true)}>{ "Comment" }</button>
+                    </div>
+                </div>
+                if let Some(Err(err)) = &*bulk_outcome {
+                    <ApiErrorAlert error={err.clone()} />
+                }
+            </>
         }
     };
 
+    let body = match &*data {
+        None => html! { <div class="page-loading">{ "Loading…" }</div> },
+        Some(Err(err)) => html! { <ApiErrorAlert error={err.clone()} /> },
+        Some(Ok(page)) if page.groups.is_empty() => html! {
+            <div class="empty">
+                { if StatusTab::All.count(&page.status_counts) == 0 && search.is_empty() {
+                      "No exceptions reported in this period."
+                  } else {
+                      "No exceptions match the current filters."
+                  } }
+            </div>
+        },
+        Some(Ok(page)) => html! {
+            <>
+                <div class="exc-list">
+                    { for page.groups.iter().map(|e| {
+                        let ticked = e.project_id.as_ref().is_some_and(|project| {
+                            *all_matching
+                                || selected.contains(&(
+                                    project.clone(),
+                                    e.source.clone(),
+                                    e.group.group_id.clone(),
+                                ))
+                        });
+                        exception_row(e, ticked, &on_toggle)
+                    }) }
+                </div>
+                if let Some(err) = &*more_error {
+                    <ApiErrorAlert error={err.clone()} />
+                }
+                if page.next_cursor.is_some() {
+                    <div class="exc-more">
+                        <button class="btn btn--small" onclick={load_more}
+                            disabled={*loading_more}>
+                            { if *loading_more { "Loading…" } else { "Load more" } }
+                        </button>
+                    </div>
+                }
+            </>
+        },
+    };
+
     html! {
         <div class="page">
             <PageHeader title="Exceptions"
                 subtitle="Crashes and errors across every project, grouped by fingerprint." />
             <FilterBar suggestions={Rc::new(suggestions)} restricted={true} />
+            { discarded_line(&discarded) }
+            { toolbar }
+            { bulk_bar }
             { body }
         </div>
     }
 }
 
-fn exception_row(e: &GlobalException) -> Html {
+/// The inbound filters that dropped anything since they were added, with
+/// their counts; nothing when none did.
+fn discarded_line(counts: &[DiscardedExceptions]) -> Html {
+    let active: Vec<&DiscardedExceptions> = counts.iter().filter(|c| c.discarded > 0).collect();
+    if active.is_empty() {
+        return Html::default();
+    }
+    let total: u64 = active.iter().map(|c| c.discarded).sum();
+    html! {
+        <div class="exc-discarded" title="Reports dropped by inbound filters since they were added">
+            <span>
+                <strong>{ group_thousands(total as i64) }</strong>
+                { " discarded by inbound filters:" }
+            </span>
+            { for active.iter().map(|c| html! {
+                <span key={format!("{}/{}", c.project_id, c.filter)}>
+                    { format!("{} · {} ", c.project_name, c.filter) }
+                    <strong>{ group_thousands(c.discarded as i64) }</strong>
+                </span>
+            }) }
+        </div>
+    }
+}
+
+/// What the last bulk triage did, shown once the selection it applied to is
+/// gone.
+fn bulk_outcome_line(outcome: &BulkTriageResult) -> Html {
+    let plural = |n: u64| if n == 1 { "" } else { "s" };
+    html! {
+        <div class="exc-bulk exc-bulk--done">
+            { format!("Updated {} group{}", group_thousands(outcome.updated as i64), plural(outcome.updated)) }
+            if outcome.skipped > 0 {
+                <span class="muted">
+                    { format!(" · skipped {} on unassigned sources", group_thousands(outcome.skipped as i64)) }
+                </span>
+            }
+            if outcome.rejected > 0 {
+                <span class="muted">
+                    { format!(" · rejected {} not in their project", group_thousands(outcome.rejected as i64)) }
+                </span>
+            }
+        </div>
+    }
+}
+
+fn exception_row(
+    e: &GlobalException,
+    ticked: bool,
+    on_toggle: &Callback<(String, String, String)>,
+) -> Html {
+    // The tick box must not open the detail page the row links to.
+    let select = match &e.project_id {
+        Some(project) => {
+            let key = (project.clone(), e.source.clone(), e.group.group_id.clone());
+            let on_toggle = on_toggle.clone();
+            let onclick = Callback::from(move |ev: MouseEvent| {
+                ev.stop_propagation();
+                on_toggle.emit(key.clone());
+            });
+            let onkeydown = Callback::from(|ev: KeyboardEvent| ev.stop_propagation());
+            html! {
+                <input class="exc-row__select" type="checkbox" aria-label="Select for bulk triage"
+                    checked={ticked} onclick={onclick} onkeydown={onkeydown} />
+            }
+        }
+        None => html! {
+            <input class="exc-row__select" type="checkbox" disabled={true}
+                title="Assign this source to a project to triage it." />
+        },
+    };
     let row_body = html! {
         <>
+            { select }
             <div class="exc-row__main">
                 <div class="exc-row__title">
                     <span class="exc-row__type">{ &e.group.exc_type }</span>
@@ -231,6 +673,9 @@ fn exception_row(e: &GlobalException) -> Html {
                 </div>
                 <div class="exc-row__meta">
                     <span class={status_class(e.group.status)}>{ status_label(e.group.status) }</span>
+                    if let Some(release) = &e.group.regressed_in {
+                        <span class="badge badge--warn">{ format!("Regressed in {release}") }</span>
+                    }
                     {
                         // Groups are per source, so the row names the application
                         // it failed on alongside its owning project.
@@ -252,11 +697,22 @@ fn exception_row(e: &GlobalException) -> Html {
                         }
                     }
                     <span class="muted">{ format!("first seen {}", ago(e.group.first_seen_ms)) }</span>
+                    if let Some(assignee) = &e.group.assignee {
+                        <span class="exc-row__assignee" title={assignee.key().to_string()}>
+                            { assignee.name.clone() }
+                        </span>
+                    }
+                    if e.group.comments > 0 {
+                        <span class="muted">
+                            { format!("{} comment{}", e.group.comments, if e.group.comments == 1 { "" } else { "s" }) }
+                        </span>
+                    }
                 </div>
             </div>
             <Sparkline points={e.group.trend.clone()} class={classes!("exc-row__trend")} />
             <div class="exc-row__count">
                 <strong>{ group_thousands(e.group.count) }</strong>
+                <span class="muted">{ format!("{} sessions", group_thousands(e.group.sessions)) }</span>
                 <span class="muted">{ ago(e.group.last_seen_ms) }</span>
             </div>
         </>
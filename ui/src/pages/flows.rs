@@ -0,0 +1,240 @@
+//! Flows: how sessions move around a chosen page or event — the busiest steps
+//! before and after it, as a Sankey diagram. The anchor is a filter expression
+//! in the query bar's syntax; clicking a page or event in the diagram explores
+//! from it instead. The range and filter chips come from the URL like every
+//! other page; the anchor and depth are the page's own, kept across
+//! navigation.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use analytics_api::{Flow, FlowNode, FlowNodeKind};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, ApiError};
+use crate::components::{
+    ApiErrorAlert, Dropdown, DropdownItem, FilterBar, FlowChart, PageHeader, ProjectsContext,
+    SuggestOption,
+};
+use crate::filters::{Dim, Query, use_filters};
+use crate::format::group_thousands;
+
+/// How many steps either side of the anchor to follow: `(steps, label)`.
+const DEPTHS: [(u32, &str); 5] = [
+    (1, "1 step either side"),
+    (2, "2 steps either side"),
+    (3, "3 steps either side"),
+    (4, "4 steps either side"),
+    (5, "5 steps either side"),
+];
+
+/// How many distinct steps to show at each position: `(count, label)`.
+const LIMITS: [(u32, &str); 3] = [
+    (3, "Top 3 per step"),
+    (5, "Top 5 per step"),
+    (10, "Top 10 per step"),
+];
+
+/// The explorer's state, kept across navigation so a round-trip to the
+/// dashboard doesn't lose it.
+#[derive(Clone, PartialEq)]
+struct FlowView {
+    anchor: String,
+    depth: u32,
+    limit: u32,
+    /// Whether the flow has been run (so returning shows the result again).
+    ran: bool,
+}
+
+impl Default for FlowView {
+    fn default() -> Self {
+        FlowView {
+            anchor: r#"path == "/""#.to_string(),
+            depth: 3,
+            limit: 5,
+            ran: false,
+        }
+    }
+}
+
+thread_local! {
+    static FLOW_VIEW: RefCell<FlowView> = RefCell::new(FlowView::default());
+}
+
+#[function_component(Flows)]
+pub fn flows() -> Html {
+    let filters = use_filters();
+    let projects = use_context::<ProjectsContext>()
+        .map(|c| c.projects.clone())
+        .unwrap_or_default();
+    let view = use_state(|| FLOW_VIEW.with(|v| v.borrow().clone()));
+    let data = use_state(|| None::<Result<Flow, ApiError>>);
+    let running = use_state(|| false);
+    // Bumped by "Explore"; a change of range or filter re-runs a flow already
+    // run.
+    let run = use_state(|| 0u32);
+    let fetch_seq = use_mut_ref(|| 0u64);
+
+    let set_view = {
+        let view = view.clone();
+        Callback::from(move |next: FlowView| {
+            FLOW_VIEW.with(|v| *v.borrow_mut() = next.clone());
+            view.set(next);
+        })
+    };
+
+    {
+        let (data, running, fetch_seq) = (data.clone(), running.clone(), fetch_seq.clone());
+        let (filters, view) = (filters.clone(), view.clone());
+        use_effect_with((filters.canonical(), *run), move |_| {
+            if view.ran {
+                let seq = {
+                    let mut current = fetch_seq.borrow_mut();
+                    *current += 1;
+                    *current
+                };
+                let query = filters.stats_query(js_sys::Date::now() as i64);
+                let view = (*view).clone();
+                running.set(true);
+                spawn_local(async move {
+                    let result = api::flow(&query, &view.anchor, view.depth, view.limit).await;
+                    if *fetch_seq.borrow() == seq {
+                        data.set(Some(result));
+                        running.set(false);
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let on_run = {
+        let (view, set_view, run) = (view.clone(), set_view.clone(), run.clone());
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            set_view.emit(FlowView {
+                ran: true,
+                ..(*view).clone()
+            });
+            run.set(*run + 1);
+        })
+    };
+    let on_anchor = {
+        let (view, set_view) = (view.clone(), set_view.clone());
+        Callback::from(move |e: InputEvent| {
+            set_view.emit(FlowView {
+                anchor: e.target_unchecked_into::<HtmlInputElement>().value(),
+                ..(*view).clone()
+            });
+        })
+    };
+    let on_depth = {
+        let (view, set_view) = (view.clone(), set_view.clone());
+        Callback::from(move |value: String| {
+            set_view.emit(FlowView {
+                depth: value.parse().unwrap_or(3),
+                ..(*view).clone()
+            });
+        })
+    };
+    let on_limit = {
+        let (view, set_view) = (view.clone(), set_view.clone());
+        Callback::from(move |value: String| {
+            set_view.emit(FlowView {
+                limit: value.parse().unwrap_or(5),
+                ..(*view).clone()
+            });
+        })
+    };
+    // Clicking a page or event in the diagram explores from it.
+    let on_select = {
+        let (view, set_view, run) = (view.clone(), set_view.clone(), run.clone());
+        Callback::from(move |node: FlowNode| {
+            let dim = match node.kind {
+                FlowNodeKind::Page => Dim::Path,
+                FlowNodeKind::Event => Dim::EventName,
+                FlowNodeKind::Anchor | FlowNodeKind::Other => return,
+            };
+            let anchor = Query {
+                terms: vec![(dim, node.label)],
+                advanced: None,
+            }
+            .to_expression();
+            set_view.emit(FlowView {
+                anchor,
+                ran: true,
+                ..(*view).clone()
+            });
+            run.set(*run + 1);
+        })
+    };
+
+    let depth_items: Vec<DropdownItem> = DEPTHS
+        .iter()
+        .map(|(depth, label)| DropdownItem::new(depth.to_string(), *label))
+        .collect();
+    let limit_items: Vec<DropdownItem> = LIMITS
+        .iter()
+        .map(|(limit, label)| DropdownItem::new(limit.to_string(), *label))
+        .collect();
+
+    let editor = html! {
+        <form class="flow-editor" onsubmit={on_run}>
+            <input class="input flow-editor__anchor" type="text"
+                placeholder={r#"path == "/pricing" or event == "signup""#}
+                value={view.anchor.clone()} oninput={on_anchor} />
+            <div class="flow-editor__option">
+                <Dropdown items={depth_items} value={view.depth.to_string()} on_select={on_depth} />
+            </div>
+            <div class="flow-editor__option">
+                <Dropdown items={limit_items} value={view.limit.to_string()} on_select={on_limit} />
+            </div>
+            <button type="submit" class="btn btn--small btn--primary"
+                disabled={view.anchor.trim().is_empty() || *running}>
+                { if *running { "Exploring…" } else { "Explore" } }
+            </button>
+        </form>
+    };
+
+    let body = match &*data {
+        None if *running => html! { <div class="page-loading">{ "Loading…" }</div> },
+        None => Html::default(),
+        Some(Err(err)) => html! { <ApiErrorAlert error={err.clone()} /> },
+        Some(Ok(flow)) if flow.sessions == 0 => html! {
+            <div class="empty">{ "No sessions reached this page or event in this period." }</div>
+        },
+        Some(Ok(flow)) => html! {
+            <div class="panel panel--chart">
+                <div class="panel__head">
+                    <h2 class="panel__title">
+                        { format!("{} sessions", group_thousands(flow.sessions)) }
+                    </h2>
+                    <div class="panel__actions">
+                        <span class="panel__hint">{ "Click a page or event to explore from it" }</span>
+                    </div>
+                </div>
+                <FlowChart flow={flow.clone()} on_select={Some(on_select)} />
+            </div>
+        },
+    };
+
+    let project_options: Vec<SuggestOption> = projects
+        .iter()
+        .map(|p| SuggestOption {
+            value: p.name.clone(),
+            label: p.name.clone(),
+        })
+        .collect();
+
+    html! {
+        <div class="page">
+            <PageHeader title="Flows"
+                subtitle="Where sessions come from before a page or event, and where they go next." />
+            <FilterBar suggestions={Rc::new(vec![(Dim::Project, project_options)])} />
+            { editor }
+            { body }
+        </div>
+    }
+}
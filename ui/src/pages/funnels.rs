@@ -0,0 +1,378 @@
+//! Funnels: what share of sessions went from one step to the next. Each step
+//! is a filter expression in the query bar's syntax (`path == "/pricing"`,
+//! `event == "signup_completed"`), optionally bounded by how soon it must
+//! follow the previous one. The range and filter chips come from the URL like
+//! every other page; the steps and breakdown are the page's own, kept across
+//! navigation so a round-trip to the dashboard doesn't lose them.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use analytics_api::{Funnel, FunnelQuery, FunnelStep, FunnelStepInput};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, ApiError};
+use crate::components::{
+    ApiErrorAlert, Dropdown, DropdownItem, FilterBar, PageHeader, ProjectsContext, SuggestOption,
+};
+use crate::filters::{Dim, use_filters};
+use crate::format::{format_duration, group_thousands};
+
+/// The most steps the editor offers (the agent's limit).
+const MAX_STEPS: usize = 10;
+
+/// How soon a step must follow the previous one: `(milliseconds, label)`, the
+/// first meaning any time later in the session.
+const WITHIN: [(i64, &str); 5] = [
+    (0, "Any time later"),
+    (60_000, "Within 1 minute"),
+    (300_000, "Within 5 minutes"),
+    (3_600_000, "Within 1 hour"),
+    (86_400_000, "Within 1 day"),
+];
+
+/// The dashboard properties a funnel can be broken down by: `(property, label)`.
+const BREAKDOWNS: [(&str, &str); 12] = [
+    ("", "No breakdown"),
+    ("source", "Source"),
+    ("referrer", "Referrer"),
+    ("channel", "Channel"),
+    ("utm_source", "UTM source"),
+    ("utm_medium", "UTM medium"),
+    ("utm_campaign", "UTM campaign"),
+    ("country", "Country"),
+    ("language", "Language"),
+    ("browser", "Browser"),
+    ("os", "Operating system"),
+    ("device", "Device"),
+];
+
+/// One step as edited.
+#[derive(Clone, PartialEq, Default)]
+struct StepDraft {
+    q: String,
+    label: String,
+    /// Zero when unbounded (see [`WITHIN`]).
+    within_ms: i64,
+}
+
+/// The editor's state: the steps, the breakdown, and whether the funnel has
+/// been run (so returning to the page shows the result again).
+#[derive(Clone, PartialEq)]
+struct FunnelView {
+    steps: Vec<StepDraft>,
+    breakdown: String,
+    ran: bool,
+}
+
+impl Default for FunnelView {
+    fn default() -> Self {
+        FunnelView {
+            steps: vec![
+                StepDraft {
+                    q: r#"path == "/""#.to_string(),
+                    ..Default::default()
+                },
+                StepDraft::default(),
+            ],
+            breakdown: String::new(),
+            ran: false,
+        }
+    }
+}
+
+thread_local! {
+    static FUNNEL_VIEW: RefCell<FunnelView> = RefCell::new(FunnelView::default());
+}
+
+#[function_component(Funnels)]
+pub fn funnels() -> Html {
+    let filters = use_filters();
+    let projects = use_context::<ProjectsContext>()
+        .map(|c| c.projects.clone())
+        .unwrap_or_default();
+    let view = use_state(|| FUNNEL_VIEW.with(|v| v.borrow().clone()));
+    let data = use_state(|| None::<Result<Funnel, ApiError>>);
+    let running = use_state(|| false);
+    // Bumped by "Run"; a change of range or filter re-runs a funnel already run.
+    let run = use_state(|| 0u32);
+    let fetch_seq = use_mut_ref(|| 0u64);
+
+    let set_view = {
+        let view = view.clone();
+        Callback::from(move |next: FunnelView| {
+            FUNNEL_VIEW.with(|v| *v.borrow_mut() = next.clone());
+            view.set(next);
+        })
+    };
+
+    {
+        let (data, running, fetch_seq) = (data.clone(), running.clone(), fetch_seq.clone());
+        let (filters, view) = (filters.clone(), view.clone());
+        use_effect_with((filters.canonical(), *run), move |_| {
+            if view.ran {
+                let seq = {
+                    let mut current = fetch_seq.borrow_mut();
+                    *current += 1;
+                    *current
+                };
+                let (from, to, _) = filters.range.resolve(js_sys::Date::now() as i64);
+                let q = filters.dashboard_expression();
+                let query = FunnelQuery {
+                    from: Some(from),
+                    to: Some(to),
+                    q: (!q.is_empty()).then_some(q),
+                    steps: view
+                        .steps
+                        .iter()
+                        .map(|step| FunnelStepInput {
+                            q: step.q.clone(),
+                            label: Some(step.label.trim().to_string())
+                                .filter(|label| !label.is_empty()),
+                            within_ms: (step.within_ms > 0).then_some(step.within_ms),
+                        })
+                        .collect(),
+                    breakdown: Some(view.breakdown.clone()).filter(|b| !b.is_empty()),
+                };
+                running.set(true);
+                spawn_local(async move {
+                    let result = api::funnel(&query).await;
+                    if *fetch_seq.borrow() == seq {
+                        data.set(Some(result));
+                        running.set(false);
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let on_run = {
+        let (view, set_view, run) = (view.clone(), set_view.clone(), run.clone());
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            set_view.emit(FunnelView {
+                ran: true,
+                ..(*view).clone()
+            });
+            run.set(*run + 1);
+        })
+    };
+
+    // Build a handler that rewrites one step of the draft.
+    let edit_step = |index: usize, f: fn(&mut StepDraft, String)| {
+        let (view, set_view) = (view.clone(), set_view.clone());
+        Callback::from(move |value: String| {
+            let mut next = (*view).clone();
+            if let Some(step) = next.steps.get_mut(index) {
+                f(step, value);
+            }
+            set_view.emit(next);
+        })
+    };
+    let input_value = |cb: Callback<String>| {
+        Callback::from(move |e: InputEvent| {
+            cb.emit(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+
+    let step_rows = view.steps.iter().enumerate().map(|(i, step)| {
+        let on_q = input_value(edit_step(i, |s, v| s.q = v));
+        let on_label = input_value(edit_step(i, |s, v| s.label = v));
+        let on_within = edit_step(i, |s, v| s.within_ms = v.parse().unwrap_or(0));
+        let remove = {
+            let (view, set_view) = (view.clone(), set_view.clone());
+            Callback::from(move |_: MouseEvent| {
+                let mut next = (*view).clone();
+                next.steps.remove(i);
+                set_view.emit(next);
+            })
+        };
+        let within_items: Vec<DropdownItem> = WITHIN
+            .iter()
+            .map(|(ms, label)| DropdownItem::new(ms.to_string(), *label))
+            .collect();
+        html! {
+            <div class="funnel-step" key={i.to_string()}>
+                <span class="funnel-step__index">{ (i + 1).to_string() }</span>
+                <input class="input funnel-step__q" type="text"
+                    placeholder={r#"path == "/pricing" or event == "signup""#}
+                    value={step.q.clone()} oninput={on_q} />
+                <input class="input funnel-step__label" type="text"
+                    placeholder="Label (optional)"
+                    value={step.label.clone()} oninput={on_label} />
+                if i > 0 {
+                    <div class="funnel-step__within">
+                        <Dropdown items={within_items} value={step.within_ms.to_string()}
+                            on_select={on_within} />
+                    </div>
+                } else {
+                    <span class="funnel-step__within muted">{ "Enters the funnel" }</span>
+                }
+                <button type="button" class="btn btn--small btn--ghost"
+                    title="Remove this step" aria-label="Remove this step"
+                    disabled={view.steps.len() <= 2} onclick={remove}>
+                    { "Remove" }
+                </button>
+            </div>
+        }
+    });
+
+    let add_step = {
+        let (view, set_view) = (view.clone(), set_view.clone());
+        Callback::from(move |_: MouseEvent| {
+            let mut next = (*view).clone();
+            next.steps.push(StepDraft::default());
+            set_view.emit(next);
+        })
+    };
+    let on_breakdown = {
+        let (view, set_view) = (view.clone(), set_view.clone());
+        Callback::from(move |breakdown: String| {
+            set_view.emit(FunnelView {
+                breakdown,
+                ..(*view).clone()
+            });
+        })
+    };
+    let breakdown_items: Vec<DropdownItem> = BREAKDOWNS
+        .iter()
+        .map(|(value, label)| DropdownItem::new(*value, *label))
+        .collect();
+    let incomplete = view.steps.iter().any(|s| s.q.trim().is_empty());
+
+    let editor = html! {
+        <form class="funnel-editor" onsubmit={on_run}>
+            { for step_rows }
+            <div class="funnel-editor__actions">
+                <button type="button" class="btn btn--small" onclick={add_step}
+                    disabled={view.steps.len() >= MAX_STEPS}>
+                    { "Add step" }
+                </button>
+                <div class="funnel-editor__breakdown">
+                    <Dropdown items={breakdown_items} value={view.breakdown.clone()}
+                        on_select={on_breakdown} />
+                </div>
+                <button type="submit" class="btn btn--small btn--primary"
+                    disabled={incomplete || *running}>
+                    { if *running { "Running…" } else { "Run funnel" } }
+                </button>
+            </div>
+        </form>
+    };
+
+    let body = match &*data {
+        None if *running => html! { <div class="page-loading">{ "Loading…" }</div> },
+        None => Html::default(),
+        Some(Err(err)) => html! { <ApiErrorAlert error={err.clone()} /> },
+        Some(Ok(funnel)) if funnel.steps.first().is_none_or(|s| s.sessions == 0) => html! {
+            <div class="empty">{ "No sessions entered this funnel in this period." }</div>
+        },
+        Some(Ok(funnel)) => {
+            let breakdown_label = BREAKDOWNS
+                .iter()
+                .find(|(value, _)| *value == view.breakdown)
+                .map(|(_, label)| *label)
+                .unwrap_or("Value");
+            html! {
+                <>
+                    <div class="funnel">
+                        { for funnel.steps.iter().enumerate().map(|(i, step)| funnel_bar(i, step)) }
+                    </div>
+                    if !funnel.breakdown.is_empty() {
+                        { breakdown_table(breakdown_label, funnel) }
+                    }
+                </>
+            }
+        }
+    };
+
+    let project_options: Vec<SuggestOption> = projects
+        .iter()
+        .map(|p| SuggestOption {
+            value: p.name.clone(),
+            label: p.name.clone(),
+        })
+        .collect();
+
+    html! {
+        <div class="page">
+            <PageHeader title="Funnels"
+                subtitle="How many sessions go on from one step to the next." />
+            <FilterBar suggestions={Rc::new(vec![(Dim::Project, project_options)])} />
+            { editor }
+            { body }
+        </div>
+    }
+}
+
+/// One step's bar, sized by its share of the sessions entering the funnel,
+/// with what was lost on the way from the previous step.
+fn funnel_bar(index: usize, step: &FunnelStep) -> Html {
+    let width = format!("width: {:.1}%", step.conversion * 100.0);
+    html! {
+        <div class="funnel__step" key={index.to_string()}>
+            <div class="funnel__head">
+                <span class="funnel__label" title={step.label.clone()}>
+                    { format!("{}. {}", index + 1, step.label) }
+                </span>
+                <span class="funnel__count">
+                    <strong>{ group_thousands(step.sessions) }</strong>
+                    { format!(" sessions · {:.1}%", step.conversion * 100.0) }
+                </span>
+            </div>
+            <div class="funnel__track">
+                <div class="funnel__bar" style={width} />
+            </div>
+            if index > 0 {
+                <div class="funnel__drop muted">
+                    { format!(
+                        "{:.1}% from the previous step · {} dropped off",
+                        step.step_conversion * 100.0,
+                        group_thousands(step.dropped),
+                    ) }
+                    if let Some(median) = step.median_ms {
+                        { format!(" · median {}", format_duration(median)) }
+                    }
+                </div>
+            }
+        </div>
+    }
+}
+
+/// Sessions at each step per breakdown value, with the value's overall
+/// conversion.
+fn breakdown_table(label: &str, funnel: &Funnel) -> Html {
+    let rows = funnel.breakdown.iter().map(|row| {
+        let entered = row.sessions.first().copied().unwrap_or(0);
+        let converted = row.sessions.last().copied().unwrap_or(0);
+        let rate = if entered > 0 {
+            converted as f64 / entered as f64 * 100.0
+        } else {
+            0.0
+        };
+        html! {
+            <tr key={row.key.clone()}>
+                <td>{ if row.key.is_empty() { "(none)" } else { row.key.as_str() } }</td>
+                { for row.sessions.iter().map(|n| html! { <td class="funnel-breakdown__num">{ group_thousands(*n) }</td> }) }
+                <td class="funnel-breakdown__num">{ format!("{rate:.1}%") }</td>
+            </tr>
+        }
+    });
+    html! {
+        <div class="card-table funnel-breakdown">
+            <table class="list">
+                <thead>
+                    <tr>
+                        <th>{ label.to_string() }</th>
+                        { for (1..=funnel.steps.len()).map(|n| html! { <th class="funnel-breakdown__num">{ format!("Step {n}") }</th> }) }
+                        <th class="funnel-breakdown__num">{ "Converted" }</th>
+                    </tr>
+                </thead>
+                <tbody>{ for rows }</tbody>
+            </table>
+        </div>
+    }
+}
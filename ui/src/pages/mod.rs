@@ -2,6 +2,8 @@ mod dashboard;
 mod event_detail;
 mod exception_detail;
 mod exceptions;
+mod flows;
+mod funnels;
 mod misc;
 mod pixels;
 mod settings;
@@ -11,6 +13,8 @@ pub use dashboard::Dashboard;
 pub use event_detail::EventDetail;
 pub use exception_detail::ExceptionDetail;
 pub use exceptions::Exceptions;
+pub use flows::Flows;
+pub use funnels::Funnels;
 pub use misc::{Login, NotFound};
 pub use pixels::Pixels;
 pub use settings::Settings;
@@ -2,15 +2,20 @@
 //! it may reveal the version), reporting-source management, the tracker install
 //! snippet, and a danger zone.
 
-use analytics_api::{Instance, Source, SourceInput, SourceKind, source_label};
+use analytics_api::{
+    Instance, Source, SourceInput, SourceKind, SourceScheme, source_label, source_scheme,
+};
 use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 use crate::api::{self, ApiError};
 use crate::app::AuthHandle;
 use crate::components::{
-    ApiErrorAlert, Dropdown, DropdownItem, PageHeader, ProjectDrawer, ProjectsContext, icons,
+    ApiErrorAlert, Drawer, Dropdown, DropdownItem, PageHeader, ProjectDrawer, ProjectsContext,
+    icons,
 };
+use crate::format::group_thousands;
 
 #[function_component(Settings)]
 pub fn settings() -> Html {
@@ -143,6 +148,19 @@ fn sources_card() -> Html {
         })
     };
 
+    let editing = use_state(|| None::<Source>);
+    let close_editor = {
+        let editing = editing.clone();
+        Callback::from(move |_: ()| editing.set(None))
+    };
+    let on_aliases_saved = {
+        let (editing, reload) = (editing.clone(), reload.clone());
+        Callback::from(move |_: ()| {
+            editing.set(None);
+            reload.set(*reload + 1);
+        })
+    };
+
     let body = match &*sources {
         None => html! { <p class="muted">{ "Loading…" }</p> },
         Some(Err(err)) => html! { <ApiErrorAlert error={err.clone()} /> },
@@ -165,6 +183,20 @@ fn sources_card() -> Html {
                             assign.emit((uri.clone(), project_id))
                         })
                     };
+                    let aliases = if source_scheme(&s.uri) == SourceScheme::Website {
+                        let on_edit = {
+                            let (editing, source) = (editing.clone(), s.clone());
+                            Callback::from(move |_: MouseEvent| editing.set(Some(source.clone())))
+                        };
+                        html! {
+                            <>
+                                <span class="muted">{ s.aliases.join(", ") }</span>
+                                <button class="btn btn--small btn--ghost" onclick={on_edit}>{ "Edit" }</button>
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    };
                     html! {
                         <tr key={s.uri.clone()}>
                             <td><code title={s.uri.clone()}>{ source_label(&s.uri) }</code></td>
@@ -174,6 +206,7 @@ fn sources_card() -> Html {
                                     value={s.project_id.clone().unwrap_or_default()}
                                     placeholder="Unassigned" on_select={on_select} />
                             </td>
+                            <td>{ aliases }</td>
                         </tr>
                     }
                 })
@@ -181,7 +214,7 @@ fn sources_card() -> Html {
             html! {
                 <div class="card-table">
                     <table class="list">
-                        <thead><tr><th>{ "Source" }</th><th>{ "Kind" }</th><th>{ "Project" }</th></tr></thead>
+                        <thead><tr><th>{ "Source" }</th><th>{ "Kind" }</th><th>{ "Project" }</th><th>{ "Aliases" }</th></tr></thead>
                         <tbody>{ rows }</tbody>
                     </table>
                 </div>
@@ -192,12 +225,140 @@ fn sources_card() -> Html {
     html! {
         <div class="settings-card">
             <h2 class="settings-card__title">{ "Reporting sources" }</h2>
-            <p class="settings-card__desc">{ "Every hostname and application that has reported events, and the project each belongs to." }</p>
+            <p class="settings-card__desc">{ "Every hostname and application that has reported events, and the project each belongs to. Aliases attribute alternate hostnames to a site." }</p>
             { body }
+            <AliasDrawer source={(*editing).clone()} on_close={close_editor} on_saved={on_aliases_saved} />
         </div>
     }
 }
 
+#[derive(Properties, PartialEq)]
+struct AliasDrawerProps {
+    /// The source being edited; the drawer is closed when `None`.
+    source: Option<Source>,
+    on_close: Callback<()>,
+    on_saved: Callback<()>,
+}
+
+/// A website source's aliases, edited as a comma-separated list. Saving can
+/// also move the events already recorded under them onto the source.
+#[function_component(AliasDrawer)]
+fn alias_drawer(props: &AliasDrawerProps) -> Html {
+    let aliases = use_state(String::new);
+    let move_history = use_state(|| false);
+    let error = use_state(|| None::<String>);
+    let busy = use_state(|| false);
+
+    // Opening the drawer on another source resets the form.
+    {
+        let (aliases, move_history, error) = (aliases.clone(), move_history.clone(), error.clone());
+        use_effect_with(props.source.clone(), move |source| {
+            aliases.set(
+                source
+                    .as_ref()
+                    .map(|s| s.aliases.join(", "))
+                    .unwrap_or_default(),
+            );
+            move_history.set(false);
+            error.set(None);
+            || ()
+        });
+    }
+
+    let Some(source) = props.source.clone() else {
+        return html! {};
+    };
+
+    let on_input = {
+        let aliases = aliases.clone();
+        Callback::from(move |e: InputEvent| {
+            aliases.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    let on_move = {
+        let move_history = move_history.clone();
+        Callback::from(move |_: MouseEvent| move_history.set(!*move_history))
+    };
+    let on_save = {
+        let (aliases, move_history, error, busy) = (
+            aliases.clone(),
+            move_history.clone(),
+            error.clone(),
+            busy.clone(),
+        );
+        let (uri, on_saved) = (source.uri.clone(), props.on_saved.clone());
+        Callback::from(move |_: MouseEvent| {
+            let input = SourceInput {
+                aliases: Some(
+                    aliases
+                        .split(',')
+                        .map(|a| a.trim().to_string())
+                        .filter(|a| !a.is_empty())
+                        .collect(),
+                ),
+                ..Default::default()
+            };
+            let move_history = *move_history;
+            let (uri, error, busy, on_saved) =
+                (uri.clone(), error.clone(), busy.clone(), on_saved.clone());
+            busy.set(true);
+            spawn_local(async move {
+                let result = match api::update_source(&uri, &input).await {
+                    Ok(saved) if move_history && !saved.aliases.is_empty() => {
+                        api::reattribute_source(&saved.uri).await.map(|_| ())
+                    }
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(err),
+                };
+                busy.set(false);
+                match result {
+                    Ok(()) => on_saved.emit(()),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+    let on_cancel = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+
+    let footer = html! {
+        <>
+            <button class="btn btn--ghost" onclick={on_cancel}>{ "Cancel" }</button>
+            <button class="btn btn--primary" onclick={on_save} disabled={*busy}>
+                { if *busy { "Saving…" } else { "Save aliases" } }
+            </button>
+        </>
+    };
+
+    html! {
+        <Drawer open={true} title={format!("Aliases of {}", source_label(&source.uri))}
+            on_close={props.on_close.clone()} footer={footer}>
+            <div class="field">
+                <label class="field__label">{ "Hostnames" }</label>
+                <input class="input" placeholder="example.co.uk, app-preview-*.example.dev"
+                    value={(*aliases).clone()} oninput={on_input} />
+                <p class="drawer__hint">
+                    { "Comma-separated. Events from these hostnames are reported under this source from now on." }
+                </p>
+            </div>
+            <button
+                class={classes!("toggle", move_history.then_some("toggle--on"))}
+                onclick={on_move}
+                aria-pressed={move_history.to_string()}
+            >
+                <span class="toggle__track"><span class="toggle__knob" /></span>
+                { "Also move the events already recorded under these aliases" }
+            </button>
+            <p class="drawer__hint">{ "The alias sources are removed once their events have moved." }</p>
+            if let Some(error) = &*error {
+                <p class="drawer__hint" style="color: var(--danger);">{ error.clone() }</p>
+            }
+        </Drawer>
+    }
+}
+
 #[function_component(AccountCard)]
 fn account_card() -> Html {
     let auth = use_context::<AuthHandle>();
@@ -251,7 +412,32 @@ fn instance_card() -> Html {
             } else {
                 "Disabled".to_string()
             };
+            let exclusions = if i.exclusions.is_empty() {
+                html! {}
+            } else {
+                let rows = i
+                    .exclusions
+                    .iter()
+                    .map(|e| {
+                        html! {
+                            <tr key={e.rule.clone()}>
+                                <td><code>{ &e.rule }</code></td>
+                                <td>{ group_thousands(e.matches as i64) }</td>
+                            </tr>
+                        }
+                    })
+                    .collect::<Html>();
+                html! {
+                    <div class="card-table">
+                        <table class="list">
+                            <thead><tr><th>{ "Ingest exclusion rule" }</th><th>{ "Dropped" }</th></tr></thead>
+                            <tbody>{ rows }</tbody>
+                        </table>
+                    </div>
+                }
+            };
             html! {
+                <>
                 <div class="kv">
                     <span class="kv__key">{ "Version" }</span>
                     <span class="kv__val">{ &i.version }</span>
@@ -266,6 +452,8 @@ fn instance_card() -> Html {
                     <span class="kv__key">{ "Max auto-sources" }</span>
                     <span class="kv__val">{ i.max_auto_sources }</span>
                 </div>
+                { exclusions }
+                </>
             }
         }
     };